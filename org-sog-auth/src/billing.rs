@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use org_sog_core::plan::Plan;
+use ring::hmac;
+use serde::Deserialize;
+
+use crate::error::MyError::{self, UnauthorizedError};
+
+/// How old a signed webhook may be before it is treated as a replay.
+const SIGNATURE_TOLERANCE_SECS: i64 = 300;
+
+/// Subscription states that still pay for the plan. `past_due` keeps it while
+/// Stripe retries the charge.
+const PAID_STATUSES: [&str; 3] = ["active", "trialing", "past_due"];
+
+/// The part of a Stripe event the receiver reads.
+#[derive(Deserialize, Debug)]
+pub struct StripeEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub created: i64,
+    pub data: StripeEventData,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StripeEventData {
+    pub object: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Subscription {
+    pub customer: String,
+    pub status: String,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+    pub items: SubscriptionItems,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubscriptionItems {
+    pub data: Vec<SubscriptionItem>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubscriptionItem {
+    pub price: Price,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Price {
+    pub id: String,
+}
+
+/// Whose plan a subscription pays for, from its `org_id` or `user_id`
+/// metadata, falling back to the Stripe customer it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BillingAccount {
+    Org(String),
+    User(String),
+    Customer,
+}
+
+impl Subscription {
+    pub fn account(&self) -> BillingAccount {
+        if let Some(org_id) = self.metadata.get("org_id") {
+            BillingAccount::Org(org_id.to_owned())
+        } else if let Some(user_id) = self.metadata.get("user_id") {
+            BillingAccount::User(user_id.to_owned())
+        } else {
+            BillingAccount::Customer
+        }
+    }
+}
+
+/// Verifies Stripe webhooks and maps Stripe prices onto plans.
+///
+/// `STRIPE_WEBHOOK_SECRET` is the endpoint's signing secret; without it every
+/// webhook is rejected. `STRIPE_PRICE_PRO` and `STRIPE_PRICE_TEAM` name the
+/// price ids sold as each plan.
+pub struct StripeBilling {
+    webhook_key: Option<hmac::Key>,
+    prices: Vec<(String, Plan)>,
+}
+
+impl StripeBilling {
+    pub fn init() -> Self {
+        let webhook_key = std::env::var("STRIPE_WEBHOOK_SECRET")
+            .ok()
+            .map(|secret| hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()));
+        if webhook_key.is_none() {
+            println!("⚠️ STRIPE_WEBHOOK_SECRET not set, Stripe webhooks will be rejected");
+        }
+
+        let prices = [
+            ("STRIPE_PRICE_PRO", Plan::Pro),
+            ("STRIPE_PRICE_TEAM", Plan::Team),
+        ]
+        .into_iter()
+        .filter_map(|(var, plan)| Some((std::env::var(var).ok()?, plan)))
+        .collect();
+
+        Self {
+            webhook_key,
+            prices,
+        }
+    }
+
+    /// Checks a `Stripe-Signature` header (`t=<unix time>,v1=<hex hmac>,...`)
+    /// against the raw request body.
+    pub fn verify(&self, signature: Option<&str>, payload: &[u8]) -> Result<(), MyError> {
+        let key = self
+            .webhook_key
+            .as_ref()
+            .ok_or_else(|| UnauthorizedError("billing webhooks are not configured".to_string()))?;
+        let signature =
+            signature.ok_or_else(|| UnauthorizedError("missing Stripe signature".to_string()))?;
+
+        let mut timestamp = None;
+        let mut candidates = Vec::new();
+        for part in signature.split(',') {
+            match part.split_once('=') {
+                Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                Some(("v1", value)) => candidates.extend(hex::decode(value).ok()),
+                _ => {}
+            }
+        }
+
+        let timestamp =
+            timestamp.ok_or_else(|| UnauthorizedError("malformed Stripe signature".to_string()))?;
+        if (Utc::now().timestamp() - timestamp).abs() > SIGNATURE_TOLERANCE_SECS {
+            return Err(UnauthorizedError(
+                "Stripe signature has expired".to_string(),
+            ));
+        }
+
+        let mut signed = format!("{}.", timestamp).into_bytes();
+        signed.extend_from_slice(payload);
+        if candidates
+            .iter()
+            .any(|candidate| hmac::verify(key, &signed, candidate).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(UnauthorizedError("invalid Stripe signature".to_string()))
+        }
+    }
+
+    /// The plan a subscription in this state pays for, or `None` when it is
+    /// for a price that isn't sold as a plan.
+    pub fn plan_for(&self, subscription: &Subscription, deleted: bool) -> Option<Plan> {
+        if deleted || !PAID_STATUSES.contains(&subscription.status.as_str()) {
+            return Some(Plan::Free);
+        }
+
+        subscription.items.data.iter().find_map(|item| {
+            self.prices
+                .iter()
+                .find(|(price, _)| *price == item.price.id)
+                .map(|(_, plan)| *plan)
+        })
+    }
+}
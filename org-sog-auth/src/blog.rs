@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use org_sog_core::{
+    circuit_breaker::BreakerMetrics,
+    http::{HttpClient, HttpClientConfig, Method},
+};
+use serde::Deserialize;
+
+use crate::error::MyError::{self, UpstreamError};
+
+type Result<T> = std::result::Result<T, MyError>;
+
+const PAGE_SIZE: usize = 100;
+
+#[derive(Deserialize)]
+struct BlogPage {
+    blogs: Vec<serde_json::Value>,
+}
+
+/// Client for the parts of org-sog-blog that hold user data.
+#[derive(Clone)]
+pub struct BlogClient {
+    http: Arc<HttpClient>,
+    blog_url: String,
+}
+
+impl BlogClient {
+    pub fn init() -> Self {
+        let blog_url =
+            std::env::var("BLOG_URL").unwrap_or_else(|_| "http://localhost:8001".to_string());
+
+        Self {
+            http: Arc::new(HttpClient::new("blog", HttpClientConfig::default())),
+            blog_url: blog_url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// Every post attributed to `author`, drafts included. `token` has to
+    /// carry `blog:admin` for unlisted and protected posts to be listed.
+    /// The blog lists posts in `_id` order, so posts created while the
+    /// pages are walked land on the last page instead of shifting the rest.
+    pub async fn posts_by_author(
+        &self,
+        token: &str,
+        author: &str,
+    ) -> Result<Vec<serde_json::Value>> {
+        let mut posts = Vec::new();
+
+        for page in 1.. {
+            let url = format!("{}/api/blog", self.blog_url);
+            let batch: BlogPage = self
+                .http
+                .json(Method::GET, &url, |request| {
+                    request.bearer_auth(token).query(&[
+                        ("author", author.to_string()),
+                        ("limit", PAGE_SIZE.to_string()),
+                        ("page", page.to_string()),
+                    ])
+                })
+                .await
+                .map_err(|e| UpstreamError(e.to_string()))?;
+
+            let done = batch.blogs.len() < PAGE_SIZE;
+            posts.extend(batch.blogs);
+            if done {
+                break;
+            }
+        }
+
+        Ok(posts)
+    }
+
+    pub async fn publish_event(&self, token: &str, event: &serde_json::Value) -> Result<()> {
+        let url = format!("{}/api/events", self.blog_url);
+        self.http
+            .send(Method::POST, &url, |request| {
+                request.bearer_auth(token).json(event)
+            })
+            .await
+            .map_err(|e| UpstreamError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    pub fn breaker_metrics(&self) -> BreakerMetrics {
+        self.http.metrics()
+    }
+}
@@ -0,0 +1,78 @@
+use org_sog_core::names::NamePolicy;
+
+use crate::{model::ConsentDocument, response::ConsentVersion};
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub magic_link_url: String,
+    pub invite_url: String,
+    /// When set, accounts can only be created by accepting an invite.
+    pub invite_only: bool,
+    /// Current terms-of-service version, if one has been published.
+    pub tos_version: Option<String>,
+    /// Current privacy-policy version, if one has been published.
+    pub privacy_version: Option<String>,
+    /// When set, users must accept the current versions before using the API.
+    pub enforce_consent: bool,
+    /// Names users may not take.
+    pub names: NamePolicy,
+}
+
+impl Config {
+    pub fn init() -> Self {
+        let magic_link_url = std::env::var("MAGIC_LINK_URL")
+            .unwrap_or_else(|_| "http://localhost:8000/api/auth/magic".to_string());
+        let invite_url = std::env::var("INVITE_URL")
+            .unwrap_or_else(|_| "http://localhost:8000/api/invites".to_string());
+        let invite_only = match std::env::var("REGISTRATION_MODE").as_deref() {
+            Ok("invite") => true,
+            Ok("open") | Err(_) => false,
+            Ok(other) => panic!(
+                "REGISTRATION_MODE must be 'open' or 'invite', got '{}'",
+                other
+            ),
+        };
+
+        let tos_version = std::env::var("TOS_VERSION").ok();
+        let privacy_version = std::env::var("PRIVACY_VERSION").ok();
+        let enforce_consent = match std::env::var("CONSENT_MODE").as_deref() {
+            Ok("enforce") => true,
+            Ok("record") | Err(_) => false,
+            Ok(other) => panic!(
+                "CONSENT_MODE must be 'record' or 'enforce', got '{}'",
+                other
+            ),
+        };
+
+        Self {
+            magic_link_url,
+            invite_url,
+            invite_only,
+            tos_version,
+            privacy_version,
+            enforce_consent,
+            names: NamePolicy::init(),
+        }
+    }
+
+    pub fn current_version(&self, document: ConsentDocument) -> Option<&str> {
+        match document {
+            ConsentDocument::Tos => self.tos_version.as_deref(),
+            ConsentDocument::Privacy => self.privacy_version.as_deref(),
+        }
+    }
+
+    /// Every published document version a user is expected to have accepted.
+    pub fn current_consents(&self) -> Vec<ConsentVersion> {
+        [ConsentDocument::Tos, ConsentDocument::Privacy]
+            .into_iter()
+            .filter_map(|document| {
+                self.current_version(document)
+                    .map(|version| ConsentVersion {
+                        document,
+                        version: version.to_owned(),
+                    })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,65 @@
+use std::{str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{FromRequestParts, State},
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use mongodb::bson::oid::ObjectId;
+
+use crate::{
+    error::MyError, extract::AuthUser, model::UserModel, response::ConsentVersion, AppState,
+};
+
+/// Rejects authenticated users who haven't accepted the current legal documents.
+///
+/// Only active with `CONSENT_MODE=enforce`. Anonymous requests and callers
+/// that aren't users (service accounts) pass through untouched.
+pub async fn require_consent<B>(
+    State(app_state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)>
+where
+    B: Send,
+{
+    let current = app_state.config.current_consents();
+    if !app_state.config.enforce_consent || current.is_empty() {
+        return Ok(next.run(request).await);
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let Ok(auth) = AuthUser::from_request_parts(&mut parts, &app_state).await else {
+        return Ok(next.run(Request::from_parts(parts, body)).await);
+    };
+
+    if let Ok(user_id) = ObjectId::from_str(&auth.sub) {
+        match app_state.db.find_user_by_id(&user_id).await {
+            Ok(Some(user)) => {
+                let missing = missing_consents(&user, current);
+                if !missing.is_empty() {
+                    return Err(MyError::ConsentRequiredError(missing).into());
+                }
+            }
+            Ok(None) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    // Spare the handler a second authentication round-trip.
+    parts.extensions.insert(auth);
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+fn missing_consents(user: &UserModel, current: Vec<ConsentVersion>) -> Vec<ConsentVersion> {
+    current
+        .into_iter()
+        .filter(|required| {
+            !user.consents.iter().flatten().any(|consent| {
+                consent.document == required.document && consent.version == required.version
+            })
+        })
+        .collect()
+}
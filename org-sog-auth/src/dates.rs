@@ -0,0 +1,28 @@
+use axum::{
+    extract::Query,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use org_sog_core::dates::DateFormat;
+
+use crate::{error::MyError, schema::DateOptions};
+
+/// Serializes the response's timestamps as the `dates`/`tz` query asks.
+pub async fn negotiate<B>(
+    opts: Option<Query<DateOptions>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Query(opts) = opts.unwrap_or_default();
+
+    match DateFormat::parse(opts.dates.as_deref(), opts.tz.as_deref()) {
+        Ok(format) => format.scope(next.run(request)).await,
+        Err(e) => {
+            let error: (StatusCode, Json<serde_json::Value>) =
+                MyError::ValidationError(e.to_string()).into();
+            error.into_response()
+        }
+    }
+}
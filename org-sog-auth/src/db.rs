@@ -1,19 +1,113 @@
+use crate::billing::BillingAccount;
 use crate::error::MyError;
-use crate::response::{SingleUserResponse, UserData, UserListResponse, UserResponse};
+use crate::events;
+use crate::extract::ClientInfo;
+use crate::model::{
+    ApiKeyModel, AuditLogModel, ConsentModel, DeadLetterModel, EventModel, ExportModel,
+    ExportStatus, InviteModel, LoginHistoryModel, LoginOutcome, MagicLinkModel, MembershipModel,
+    OrgModel, PreferencesModel, ProfileModel, ServiceAccountModel, SessionModel,
+};
+use crate::response::{
+    AdminStatsResponse, ApiKeyCreatedResponse, ApiKeyData, ApiKeyListResponse, ApiKeyResponse,
+    AvailabilityResponse, ConsentResponse, DeadLetterData, DeadLetterListResponse,
+    DeadLetterResponse, ExportData, ExportResponse, IndexAdviceResponse, IndexBuildData,
+    IndexBuildListResponse, InviteCreatedData, InviteCreatedResponse, InviteData, InviteResponse,
+    LoginHistoryListResponse, LoginHistoryResponse, MemberListResponse, MemberResponse, OrgData,
+    OrgListResponse, OrgResponse, PreferencesResponse, ProfileLookupResponse,
+    PublicProfileResponse, QueueCountsResponse, ReplayResponse, ResolveUsersResponse,
+    ResolvedUserResponse, ServiceAccountCredentials, ServiceAccountCredentialsResponse,
+    ServiceAccountListResponse, ServiceAccountResponse, SessionListResponse, SessionResponse,
+    SingleDeadLetterResponse, SingleExportResponse, SingleIndexBuildResponse, SingleInviteResponse,
+    SingleOrgResponse, SingleUserResponse, SlowQueryListResponse, SlowQueryResponse, UserArchive,
+    UserCountsResponse, UserData, UserListResponse, UserResponse,
+};
+use crate::schema::{
+    AcceptInviteSchema, BuildIndexSchema, ConsentSchema, CreateOrgSchema, IndexAdviceOptions,
+    PreferencesSchema, SlowQueryOptions, UpdateOrgSchema,
+};
+use crate::scope;
 use crate::{
-    error::MyError::*, model::UserModel, schema::CreateUserSchema, schema::UpdateUserSchema,
+    error::MyError::*, model::UserModel, model::UserName, schema::CreateUserSchema,
+    schema::UpdateUserSchema,
 };
 use chrono::prelude::*;
+use chrono::Duration;
 use futures::StreamExt;
-use mongodb::bson::{doc, oid::ObjectId, Document};
-use mongodb::options::{FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::options::{
+    FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions, ReplaceOptions,
+    ReturnDocument,
+};
 use mongodb::{bson, options::ClientOptions, Client, Collection, IndexModel};
+use org_sog_core::conflict::duplicate_key;
+use org_sog_core::credentials;
+use org_sog_core::db_breaker::{DbBreaker, Guarded};
+use org_sog_core::db_metrics::DbMetrics;
+use org_sog_core::ids::IdStrategy;
+use org_sog_core::index_advisor::{IndexAdvisor, MAX_INDEX_KEYS};
+use org_sog_core::migrate;
+use org_sog_core::page::Pagination;
+use org_sog_core::patch::Patch;
+use org_sog_core::plan::Plan;
+use org_sog_core::read::{ReadFrom, ReadRouting};
+use org_sog_core::repo::Repository;
+use org_sog_core::slow_query::SlowQueryLog;
+use org_sog_core::tombstone::Tombstones;
+use rand::{distributions::Alphanumeric, Rng};
+use std::collections::HashSet;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+const MAX_SUGGESTIONS: usize = 3;
+pub const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+const MAGIC_LINK_TOKEN_LEN: usize = 43;
+const API_KEY_PREFIX: &str = "sog_";
+const API_KEY_LEN: usize = 40;
+const API_KEY_DISPLAY_LEN: usize = 8;
+const CLIENT_ID_PREFIX: &str = "sa_";
+const CLIENT_ID_LEN: usize = 20;
+const CLIENT_SECRET_LEN: usize = 48;
+const INVITE_TOKEN_LEN: usize = 43;
+const EXPORT_TTL_HOURS: i64 = 24;
+/// A pending export older than this is assumed to have died with its worker.
+const EXPORT_STALE_MINUTES: i64 = 10;
+/// Followed by the user's id, as names have a unique index.
+const DELETED_USER_NAME: &str = "Deleted user";
+const MAX_BIO_LEN: usize = 500;
+const MAX_LOCATION_LEN: usize = 100;
+const MAX_PROFILE_URL_LEN: usize = 2048;
+const MAX_LOCALE_LEN: usize = 35;
+const MAX_TIMEZONE_LEN: usize = 64;
+/// Users who signed up within this many days count as new.
+const NEW_USER_DAYS: i64 = 7;
+const DEFAULT_INDEX_ADVICE_DAYS: i64 = 7;
+/// The slow query log keeps two weeks.
+const MAX_INDEX_ADVICE_DAYS: i64 = 14;
 
 #[derive(Clone, Debug)]
 pub struct DB {
     pub user_collection: Collection<UserModel>,
+    pub users: Repository<UserModel>,
     pub collection: Collection<Document>,
+    pub login_history_collection: Collection<LoginHistoryModel>,
+    pub magic_link_collection: Collection<MagicLinkModel>,
+    pub session_collection: Collection<SessionModel>,
+    pub api_key_collection: Collection<ApiKeyModel>,
+    pub service_account_collection: Collection<ServiceAccountModel>,
+    pub invite_collection: Collection<InviteModel>,
+    pub org_collection: Collection<OrgModel>,
+    pub membership_collection: Collection<MembershipModel>,
+    pub export_collection: Collection<ExportModel>,
+    pub audit_log_collection: Collection<AuditLogModel>,
+    pub event_collection: Collection<EventModel>,
+    pub dead_letter_collection: Collection<DeadLetterModel>,
+    pub tombstones: Tombstones,
+    pub reads: ReadRouting,
+    pub metrics: Arc<DbMetrics>,
+    pub breaker: Arc<DbBreaker>,
+    pub slow_queries: Arc<SlowQueryLog>,
+    pub index_advisor: IndexAdvisor,
 }
 
 type Result<T> = std::result::Result<T, MyError>;
@@ -28,30 +122,266 @@ impl DB {
 
         let mut client_options = ClientOptions::parse(mongodb_uri).await?;
         client_options.app_name = Some(database_name.to_string());
+        let slow_queries = Arc::new(SlowQueryLog::init());
+        let metrics = Arc::new(DbMetrics::with_slow_query_log(slow_queries.clone()));
+        metrics.install(&mut client_options);
 
         let client = Client::with_options(client_options)?;
         let database = client.database(database_name.as_str());
+        let breaker = Arc::new(DbBreaker::new());
 
         let user_collection = database.collection(collection_name.as_str());
         let collection = database.collection::<Document>(collection_name.as_str());
+        let login_history_collection = database.collection("login_history");
+        let magic_link_collection = database.collection("magic_links");
+        let session_collection = database.collection("sessions");
+        let api_key_collection = database.collection("api_keys");
+        let service_account_collection = database.collection("service_accounts");
+        let invite_collection = database.collection("invites");
+        let org_collection = database.collection("orgs");
+        let membership_collection = database.collection("memberships");
+        let export_collection = database.collection("exports");
+        let audit_log_collection = database.collection("audit_log");
+        let event_collection = database.collection("events");
+        let dead_letter_collection = database.collection("dead_letters");
+        let reads = ReadRouting::init();
+        // Every other collection refers to users by ObjectId, so they keep those.
+        let users = Repository::new(user_collection.clone(), reads.clone(), IdStrategy::ObjectId);
+
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "createdAt": -1})
+            .build();
+        login_history_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let options = IndexOptions::builder().unique(true).sparse(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"email": 1})
+            .options(options)
+            .build();
+        user_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        users
+            .ensure_unique_keys()
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // Sparse until the plaintext tokens below have been hashed.
+        let options = IndexOptions::builder()
+            .name("tokenHash_1".to_string())
+            .unique(true)
+            .sparse(true)
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"tokenHash": 1})
+            .options(options)
+            .build();
+        migrate::ensure_index(&magic_link_collection, index, &["token_1"])
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let rehashed = rehash_magic_link_tokens(&magic_link_collection.clone_with_type()).await?;
+        if rehashed > 0 {
+            println!("🔑 Hashed {} plaintext magic link tokens", rehashed);
+        }
+
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"keyHash": 1})
+            .options(options)
+            .build();
+        api_key_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"clientId": 1})
+            .options(options)
+            .build();
+        service_account_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"tokenHash": 1})
+            .options(options)
+            .build();
+        invite_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"slug": 1})
+            .options(options)
+            .build();
+        org_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // Finds whose plan a Stripe subscription without metadata pays for.
+        for accounts in [collection.clone(), org_collection.clone_with_type()] {
+            let options = IndexOptions::builder().sparse(true).build();
+            let index = IndexModel::builder()
+                .keys(doc! {"stripeCustomerId": 1})
+                .options(options)
+                .build();
+            accounts
+                .create_index(index, None)
+                .guarded(&breaker)
+                .await
+                .map_err(MongoQueryError)?;
+        }
+
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"orgId": 1, "userId": 1})
+            .options(options)
+            .build();
+        membership_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "createdAt": 1})
+            .build();
+        membership_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "createdAt": -1})
+            .build();
+        export_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let index = IndexModel::builder()
+            .keys(doc! {"targetId": 1, "createdAt": -1})
+            .build();
+        audit_log_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let index = IndexModel::builder()
+            .keys(doc! {"deliveredAt": 1, "createdAt": 1})
+            .build();
+        event_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let index = IndexModel::builder().keys(doc! {"deadAt": -1}).build();
+        dead_letter_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // Expired links, sessions, invites and exports are purged by Mongo's TTL monitor.
+        let options = IndexOptions::builder()
+            .expire_after(StdDuration::from_secs(0))
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"expiresAt": 1})
+            .options(options)
+            .build();
+        magic_link_collection
+            .create_index(index.clone(), None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        session_collection
+            .create_index(index.clone(), None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        invite_collection
+            .create_index(index.clone(), None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        export_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let tombstones = Tombstones::init(&database).await.map_err(MongoQueryError)?;
+        slow_queries
+            .attach(&database)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
         println!("✅ Database connected successfully");
 
         Ok(Self {
             user_collection,
+            users,
             collection,
+            login_history_collection,
+            magic_link_collection,
+            session_collection,
+            api_key_collection,
+            service_account_collection,
+            invite_collection,
+            org_collection,
+            membership_collection,
+            export_collection,
+            audit_log_collection,
+            event_collection,
+            dead_letter_collection,
+            tombstones,
+            reads,
+            metrics,
+            breaker,
+            slow_queries,
+            index_advisor: IndexAdvisor::new(&client, &database),
         })
     }
 
-    pub async fn fetch_users(&self, limit: i64, page: i64) -> Result<UserListResponse> {
+    pub async fn fetch_users(
+        &self,
+        paging: Pagination,
+        read: ReadFrom,
+    ) -> Result<UserListResponse> {
         let find_options = FindOptions::builder()
-            .limit(limit)
-            .skip(u64::try_from((page - 1) * limit).unwrap())
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .selection_criteria(self.reads.criteria(read))
             .build();
 
         let mut cursor = self
             .user_collection
             .find(None, find_options)
+            .guarded(&self.breaker)
             .await
             .map_err(MongoQueryError)?;
 
@@ -62,51 +392,29 @@ impl DB {
 
         Ok(UserListResponse {
             status: "success",
+            page: paging.page,
+            limit: paging.limit,
             results: json_result.len(),
             users: json_result,
         })
     }
 
-    pub async fn create_user(&self, body: &CreateUserSchema) -> Result<SingleUserResponse> {
-        let document = self.create_user_document(body)?;
-
-        let options = IndexOptions::builder().unique(true).build();
-        let index = IndexModel::builder()
-            .keys(doc! {"name": 1})
-            .options(options)
-            .build();
+    pub async fn create_user(
+        &self,
+        body: &CreateUserSchema,
+        scopes: &[String],
+    ) -> Result<SingleUserResponse> {
+        let document = self.create_user_document(body, scopes)?;
 
-        match self.user_collection.create_index(index, None).await {
+        match self.collection.insert_one(&document, None).await {
             Ok(_) => {}
-            Err(e) => return Err(MongoQueryError(e)),
-        };
-
-        let insert_result = match self.collection.insert_one(&document, None).await {
-            Ok(result) => result,
             Err(e) => {
-                if e.to_string()
-                    .contains("E11000 duplicate key error collection")
-                {
-                    return Err(MongoDuplicateError(e));
-                }
-                return Err(MongoQueryError(e));
+                return Err(write_error(e));
             }
         };
 
-        let new_id = insert_result
-            .inserted_id
-            .as_object_id()
-            .expect("issue with new _id");
-
-        let user_doc = match self
-            .user_collection
-            .find_one(doc! {"_id": new_id}, None)
-            .await
-        {
-            Ok(Some(doc)) => doc,
-            Ok(None) => return Err(NotFoundError(new_id.to_string())),
-            Err(e) => return Err(MongoQueryError(e)),
-        };
+        // The document carries its own `_id`, so it already is what was stored.
+        let user_doc: UserModel = bson::from_document(document)?;
 
         Ok(SingleUserResponse {
             status: "success",
@@ -116,12 +424,13 @@ impl DB {
         })
     }
 
-    pub async fn get_user(&self, id: &str) -> Result<SingleUserResponse> {
+    pub async fn get_user(&self, id: &str, read: ReadFrom) -> Result<SingleUserResponse> {
         let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
 
         let user_doc = self
             .user_collection
-            .find_one(doc! {"_id":oid }, None)
+            .find_one(doc! {"_id":oid }, self.find_one_options(read))
+            .guarded(&self.breaker)
             .await
             .map_err(MongoQueryError)?;
 
@@ -139,6 +448,9 @@ impl DB {
 
     pub async fn edit_user(&self, id: &str, body: &UpdateUserSchema) -> Result<SingleUserResponse> {
         let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        if let Some(profile) = &body.profile {
+            check_profile(profile)?;
+        }
 
         let update = doc! {
             "$set": bson::to_document(body).map_err(MongoSerializeBsonError)?,
@@ -151,8 +463,9 @@ impl DB {
         if let Some(doc) = self
             .user_collection
             .find_one_and_update(doc! {"_id": oid}, update, options)
+            .guarded(&self.breaker)
             .await
-            .map_err(MongoQueryError)?
+            .map_err(write_error)?
         {
             let user = self.doc_to_user(&doc)?;
             let user_response = SingleUserResponse {
@@ -172,39 +485,2233 @@ impl DB {
         let result = self
             .collection
             .delete_one(filter, None)
+            .guarded(&self.breaker)
             .await
             .map_err(MongoQueryError)?;
 
-        match result.deleted_count {
-            0 => Err(NotFoundError(id.to_string())),
-            _ => Ok(()),
+        if result.deleted_count == 0 {
+            return Err(NotFoundError(id.to_string()));
         }
+
+        self.record_deletion("user", id).await
     }
 
-    fn doc_to_user(&self, user: &UserModel) -> Result<UserResponse> {
-        let user_response = UserResponse {
-            id: user.id.to_hex(),
-            name: user.name.to_owned(),
-            uid: user.uid.to_owned(),
-            createdAt: user.createdAt,
-            updatedAt: user.updatedAt,
+    /// Records that the user accepted a document version. Earlier acceptances
+    /// are kept, so the user record doubles as the consent history.
+    pub async fn accept_consent(
+        &self,
+        id: &str,
+        body: &ConsentSchema,
+    ) -> Result<SingleUserResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let now = Utc::now();
+
+        let consent = bson::to_bson(&ConsentModel {
+            document: body.document,
+            version: body.version.to_owned(),
+            acceptedAt: now,
+        })
+        .map_err(MongoSerializeBsonError)?;
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let user = self
+            .user_collection
+            .find_one_and_update(
+                doc! {"_id": oid},
+                doc! {"$push": {"consents": consent}, "$set": {"updatedAt": now}},
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))?;
+
+        Ok(SingleUserResponse {
+            status: "success",
+            data: UserData {
+                user: self.doc_to_user(&user)?,
+            },
+        })
+    }
+
+    /// Strips personal data from a user while keeping the record, so that
+    /// references to its id elsewhere stay valid.
+    pub async fn anonymize_user(&self, id: &str, actor: &str) -> Result<SingleUserResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let now = Utc::now();
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let user = self
+            .user_collection
+            .find_one_and_update(
+                doc! {"_id": oid},
+                doc! {
+                    "$set": {
+                        "name": format!("{} {}", DELETED_USER_NAME, oid.to_hex()),
+                        "uid": format!("deleted-{}", oid.to_hex()),
+                        "scopes": [],
+                        "anonymizedAt": now,
+                        "updatedAt": now,
+                    },
+                    "$unset": {"email": "", "profile": "", "privacy": "", "preferences": ""},
+                },
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))?;
+
+        self.session_collection
+            .update_many(
+                doc! {"userId": oid, "revokedAt": null},
+                doc! {"$set": {"revokedAt": now}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.session_collection
+            .update_many(
+                doc! {"userId": oid},
+                doc! {"$set": {"ip": null, "userAgent": null, "geo": null}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.login_history_collection
+            .update_many(
+                doc! {"userId": oid},
+                doc! {"$set": {"ip": null, "userAgent": null, "geo": null}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.api_key_collection
+            .update_many(
+                doc! {"userId": oid, "revokedAt": null},
+                doc! {"$set": {"revokedAt": now}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.magic_link_collection
+            .delete_many(doc! {"userId": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.export_collection
+            .delete_many(doc! {"userId": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.enqueue_event(events::USER_ANONYMIZED, doc! {"userId": oid.to_hex()})
+            .await?;
+        self.record_audit("user.anonymize", actor, id).await?;
+
+        Ok(SingleUserResponse {
+            status: "success",
+            data: UserData {
+                user: self.doc_to_user(&user)?,
+            },
+        })
+    }
+
+    pub async fn record_audit(&self, action: &str, actor: &str, target: &str) -> Result<()> {
+        let entry = AuditLogModel {
+            id: ObjectId::new(),
+            action: action.to_owned(),
+            actorId: actor.to_owned(),
+            targetId: target.to_owned(),
+            createdAt: Utc::now(),
         };
 
-        Ok(user_response)
+        self.audit_log_collection
+            .insert_one(entry, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
     }
 
-    fn create_user_document(&self, body: &CreateUserSchema) -> Result<bson::Document> {
-        let serialized_data = bson::to_bson(body).map_err(MongoSerializeBsonError)?;
-        let document = serialized_data.as_document().unwrap();
+    /// Undelivered events, oldest first.
+    pub async fn pending_events(&self, limit: i64) -> Result<Vec<EventModel>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"createdAt": 1})
+            .limit(limit)
+            .build();
 
-        let datetime = Utc::now();
+        let mut cursor = self
+            .event_collection
+            .find(doc! {"deliveredAt": null}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let mut doc_with_dates = doc! {
-            "createdAt": datetime,
-            "updatedAt": datetime
+        let mut pending = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            pending.push(doc.map_err(MongoQueryError)?);
+        }
+
+        Ok(pending)
+    }
+
+    pub async fn mark_event_delivered(&self, id: &ObjectId) -> Result<()> {
+        self.event_collection
+            .update_one(
+                doc! {"_id": id},
+                doc! {"$set": {"deliveredAt": Utc::now()}, "$inc": {"attempts": 1}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    pub async fn mark_event_failed(&self, id: &ObjectId, error: &str) -> Result<()> {
+        self.event_collection
+            .update_one(
+                doc! {"_id": id},
+                doc! {"$set": {"lastError": error}, "$inc": {"attempts": 1}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Moves an event that keeps failing out of the outbox into the
+    /// dead-letter queue. Safe to repeat if the move was interrupted.
+    pub async fn bury_event(&self, event: &EventModel, error: &str) -> Result<()> {
+        let dead = DeadLetterModel {
+            id: event.id,
+            kind: event.kind.to_owned(),
+            payload: event.payload.clone(),
+            attempts: event.attempts + 1,
+            lastError: Some(error.to_owned()),
+            createdAt: event.createdAt,
+            deadAt: Utc::now(),
         };
-        doc_with_dates.extend(document.clone());
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.dead_letter_collection
+            .replace_one(doc! {"_id": event.id}, dead, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        Ok(doc_with_dates)
+        self.event_collection
+            .delete_one(doc! {"_id": event.id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Dead letters, most recently failed first.
+    /// Headline numbers for the ops dashboard: users and how far the event
+    /// outbox is behind.
+    pub async fn admin_stats(&self) -> Result<AdminStatsResponse> {
+        let since = Utc::now() - Duration::days(NEW_USER_DAYS);
+
+        let total = self
+            .user_collection
+            .count_documents(None, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let new = self
+            .user_collection
+            .count_documents(doc! {"createdAt": {"$gte": since}}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let outbox = self
+            .event_collection
+            .count_documents(doc! {"deliveredAt": null}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let dead_letters = self
+            .dead_letter_collection
+            .count_documents(None, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(AdminStatsResponse {
+            status: "success",
+            users: UserCountsResponse { total, new },
+            queues: QueueCountsResponse {
+                outbox,
+                deadLetters: dead_letters,
+            },
+        })
+    }
+
+    /// The latest logged slow queries, newest first.
+    pub async fn fetch_slow_queries(
+        &self,
+        opts: &SlowQueryOptions,
+        paging: Pagination,
+    ) -> Result<SlowQueryListResponse> {
+        let found = self
+            .slow_queries
+            .recent(opts.collection.as_deref(), paging)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let queries: Vec<SlowQueryResponse> = found
+            .into_iter()
+            .map(|query| SlowQueryResponse {
+                id: query.id.to_hex(),
+                operation: query.operation,
+                collection: query.collection,
+                durationMs: query.durationMs,
+                shape: Bson::Document(query.shape).into_relaxed_extjson(),
+                sort: query
+                    .sort
+                    .map(|sort| Bson::Document(sort).into_relaxed_extjson()),
+                plan: query.plan,
+                traceId: query.traceId,
+                at: query.at,
+            })
+            .collect();
+
+        Ok(SlowQueryListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: queries.len(),
+            queries,
+        })
+    }
+
+    /// Indexes that would serve recent slow queries.
+    pub async fn index_advice(&self, opts: &IndexAdviceOptions) -> Result<IndexAdviceResponse> {
+        let days = opts.days.unwrap_or(DEFAULT_INDEX_ADVICE_DAYS);
+        if !(1..=MAX_INDEX_ADVICE_DAYS).contains(&days) {
+            return Err(ValidationError(format!(
+                "days must be between 1 and {}",
+                MAX_INDEX_ADVICE_DAYS
+            )));
+        }
+
+        let since = Utc::now() - chrono::Duration::days(days);
+        let suggestions = self
+            .index_advisor
+            .suggest(since, opts.collection.as_deref())
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(IndexAdviceResponse {
+            status: "success",
+            since,
+            results: suggestions.len(),
+            suggestions,
+        })
     }
+
+    /// Starts building the index in `body` in the background.
+    pub async fn start_index_build(
+        &self,
+        body: BuildIndexSchema,
+    ) -> Result<SingleIndexBuildResponse> {
+        if body.keys.is_empty() || body.keys.len() > MAX_INDEX_KEYS {
+            return Err(ValidationError(format!(
+                "an index needs between 1 and {} keys",
+                MAX_INDEX_KEYS
+            )));
+        }
+        let mut fields = HashSet::new();
+        for key in &body.keys {
+            if key.field.is_empty() || key.field.starts_with('$') {
+                return Err(ValidationError(format!(
+                    "invalid index field: '{}'",
+                    key.field
+                )));
+            }
+            if key.direction != 1 && key.direction != -1 {
+                return Err(ValidationError(
+                    "index key direction must be 1 or -1".to_string(),
+                ));
+            }
+            if !fields.insert(key.field.as_str()) {
+                return Err(ValidationError(format!(
+                    "index field '{}' is listed twice",
+                    key.field
+                )));
+            }
+        }
+
+        let exists = self
+            .index_advisor
+            .collection_exists(&body.collection)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if !exists {
+            return Err(NotFoundError(body.collection));
+        }
+
+        Ok(SingleIndexBuildResponse {
+            status: "success",
+            data: IndexBuildData {
+                build: self.index_advisor.build(&body.collection, body.keys),
+            },
+        })
+    }
+
+    pub async fn index_builds(&self) -> Result<IndexBuildListResponse> {
+        let builds = self.index_advisor.builds().await.map_err(MongoQueryError)?;
+
+        Ok(IndexBuildListResponse {
+            status: "success",
+            results: builds.len(),
+            builds,
+        })
+    }
+
+    pub async fn fetch_dead_letters(&self, paging: Pagination) -> Result<DeadLetterListResponse> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"deadAt": -1})
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .build();
+
+        let mut cursor = self
+            .dead_letter_collection
+            .find(None, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<DeadLetterResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_dead_letter(&doc.map_err(MongoQueryError)?, false));
+        }
+
+        Ok(DeadLetterListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: json_result.len(),
+            deadLetters: json_result,
+        })
+    }
+
+    pub async fn get_dead_letter(&self, id: &str) -> Result<SingleDeadLetterResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let dead = self
+            .dead_letter_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))?;
+
+        Ok(SingleDeadLetterResponse {
+            status: "success",
+            data: DeadLetterData {
+                deadLetter: self.doc_to_dead_letter(&dead, true),
+            },
+        })
+    }
+
+    /// Puts dead letters back in the outbox with a fresh set of attempts.
+    pub async fn replay_dead_letters(&self, ids: &[String]) -> Result<ReplayResponse> {
+        let oids = ids
+            .iter()
+            .map(|id| ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned())))
+            .collect::<Result<Vec<ObjectId>>>()?;
+
+        let mut replayed = Vec::new();
+        let mut missing = Vec::new();
+        for oid in oids {
+            let Some(dead) = self
+                .dead_letter_collection
+                .find_one(doc! {"_id": oid}, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?
+            else {
+                missing.push(oid.to_hex());
+                continue;
+            };
+
+            let event = EventModel {
+                id: dead.id,
+                kind: dead.kind,
+                payload: dead.payload,
+                attempts: 0,
+                lastError: None,
+                deliveredAt: None,
+                createdAt: dead.createdAt,
+            };
+            let options = ReplaceOptions::builder().upsert(true).build();
+            self.event_collection
+                .replace_one(doc! {"_id": oid}, event, options)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            self.dead_letter_collection
+                .delete_one(doc! {"_id": oid}, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+
+            replayed.push(oid.to_hex());
+        }
+
+        Ok(ReplayResponse {
+            status: "success",
+            replayed,
+            missing,
+        })
+    }
+
+    /// Leaves a tombstone for a deleted resource and tells other services.
+    async fn record_deletion(&self, resource_type: &str, resource_id: &str) -> Result<()> {
+        self.tombstones
+            .record(resource_type, resource_id)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.enqueue_event(
+            events::RESOURCE_DELETED,
+            doc! {"resourceType": resource_type, "resourceId": resource_id},
+        )
+        .await
+    }
+
+    async fn enqueue_event(&self, kind: &str, payload: Document) -> Result<()> {
+        let event = EventModel {
+            id: ObjectId::new(),
+            kind: kind.to_owned(),
+            payload,
+            attempts: 0,
+            lastError: None,
+            deliveredAt: None,
+            createdAt: Utc::now(),
+        };
+
+        self.event_collection
+            .insert_one(event, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    pub async fn find_user_by_email(&self, email: &str) -> Result<Option<UserModel>> {
+        self.user_collection
+            .find_one(doc! {"email": email}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)
+    }
+
+    pub async fn find_user_by_id(&self, id: &ObjectId) -> Result<Option<UserModel>> {
+        self.user_collection
+            .find_one(doc! {"_id": id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)
+    }
+
+    pub async fn create_api_key(
+        &self,
+        user_id: &str,
+        name: &str,
+        scopes: Vec<String>,
+    ) -> Result<ApiKeyCreatedResponse> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        let key = format!("{}{}", API_KEY_PREFIX, random_string(API_KEY_LEN));
+
+        let api_key = ApiKeyModel {
+            id: ObjectId::new(),
+            userId: user_oid,
+            name: name.to_owned(),
+            prefix: key[..API_KEY_PREFIX.len() + API_KEY_DISPLAY_LEN].to_owned(),
+            keyHash: credentials::hash(&key),
+            scopes,
+            lastUsedAt: None,
+            revokedAt: None,
+            createdAt: Utc::now(),
+        };
+
+        self.api_key_collection
+            .insert_one(&api_key, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(ApiKeyCreatedResponse {
+            status: "success",
+            data: ApiKeyData {
+                api_key: self.doc_to_api_key(&api_key),
+                key,
+            },
+        })
+    }
+
+    pub async fn fetch_api_keys(&self, user_id: &str) -> Result<ApiKeyListResponse> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        let find_options = FindOptions::builder().sort(doc! {"createdAt": -1}).build();
+        let mut cursor = self
+            .api_key_collection
+            .find(doc! {"userId": user_oid}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<ApiKeyResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_api_key(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(ApiKeyListResponse {
+            status: "success",
+            results: json_result.len(),
+            api_keys: json_result,
+        })
+    }
+
+    pub async fn revoke_api_key(&self, user_id: &str, id: &str) -> Result<()> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let result = self
+            .api_key_collection
+            .update_one(
+                doc! {"_id": oid, "userId": user_oid, "revokedAt": null},
+                doc! {"$set": {"revokedAt": Utc::now()}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        match result.matched_count {
+            0 => Err(NotFoundError(id.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// `user_id`'s live sessions, newest first, with `current` marked.
+    pub async fn fetch_sessions(
+        &self,
+        user_id: &str,
+        current: Option<&str>,
+    ) -> Result<SessionListResponse> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        let find_options = FindOptions::builder().sort(doc! {"createdAt": -1}).build();
+        let mut cursor = self
+            .session_collection
+            .find(
+                doc! {"userId": user_oid, "revokedAt": null, "expiresAt": {"$gt": Utc::now()}},
+                find_options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut sessions = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let mut session = self.doc_to_session(&doc.map_err(MongoQueryError)?);
+            session.current = Some(Some(session.id.as_str()) == current);
+            sessions.push(session);
+        }
+
+        Ok(SessionListResponse {
+            status: "success",
+            results: sessions.len(),
+            sessions,
+        })
+    }
+
+    /// Signs one of `user_id`'s sessions out.
+    pub async fn revoke_session(&self, user_id: &str, id: &str) -> Result<()> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let result = self
+            .session_collection
+            .update_one(
+                doc! {"_id": oid, "userId": user_oid, "revokedAt": null},
+                doc! {"$set": {"revokedAt": Utc::now()}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        match result.matched_count {
+            0 => Err(NotFoundError(id.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn get_preferences(
+        &self,
+        user_id: &str,
+        read: ReadFrom,
+    ) -> Result<PreferencesResponse> {
+        Ok(PreferencesResponse {
+            status: "success",
+            preferences: self.user_preferences(user_id, read).await?,
+        })
+    }
+
+    /// What the user chose for themselves, without anything inherited.
+    pub async fn user_preferences(
+        &self,
+        user_id: &str,
+        read: ReadFrom,
+    ) -> Result<PreferencesModel> {
+        let oid = ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        let user = self
+            .user_collection
+            .find_one(doc! {"_id": oid}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(user_id.to_string()))?;
+
+        Ok(user.preferences)
+    }
+
+    pub async fn update_preferences(
+        &self,
+        user_id: &str,
+        body: &PreferencesSchema,
+    ) -> Result<PreferencesResponse> {
+        let oid = ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+        let update = preferences_update(body)?;
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let user = self
+            .user_collection
+            .find_one_and_update(doc! {"_id": oid}, update, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(user_id.to_string()))?;
+
+        Ok(PreferencesResponse {
+            status: "success",
+            preferences: user.preferences,
+        })
+    }
+
+    pub async fn get_org_preferences(
+        &self,
+        org_id: &str,
+        read: ReadFrom,
+    ) -> Result<PreferencesResponse> {
+        Ok(PreferencesResponse {
+            status: "success",
+            preferences: self.org_preferences(org_id, read).await?,
+        })
+    }
+
+    /// The defaults an org sets for its members.
+    pub async fn org_preferences(&self, org_id: &str, read: ReadFrom) -> Result<PreferencesModel> {
+        let oid = ObjectId::from_str(org_id).map_err(|_| InvalidIDError(org_id.to_owned()))?;
+
+        let org = self
+            .org_collection
+            .find_one(doc! {"_id": oid}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(org_id.to_string()))?;
+
+        Ok(org.preferences)
+    }
+
+    pub async fn update_org_preferences(
+        &self,
+        org_id: &str,
+        body: &PreferencesSchema,
+    ) -> Result<PreferencesResponse> {
+        let oid = ObjectId::from_str(org_id).map_err(|_| InvalidIDError(org_id.to_owned()))?;
+        let update = preferences_update(body)?;
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let org = self
+            .org_collection
+            .find_one_and_update(doc! {"_id": oid}, update, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(org_id.to_string()))?;
+
+        Ok(PreferencesResponse {
+            status: "success",
+            preferences: org.preferences,
+        })
+    }
+
+    pub async fn find_active_api_key(&self, key: &str) -> Result<Option<ApiKeyModel>> {
+        self.api_key_collection
+            .find_one_and_update(
+                doc! {"keyHash": {"$in": credentials::lookup(key)}, "revokedAt": null},
+                // Keys hashed before the pepper was set move to the current hash on use.
+                doc! {"$set": {"lastUsedAt": Utc::now(), "keyHash": credentials::hash(key)}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)
+    }
+
+    pub async fn create_invite(
+        &self,
+        invited_by: &str,
+        role: &str,
+        email: Option<&str>,
+        expires_in: Duration,
+        invite_url: &str,
+    ) -> Result<InviteCreatedResponse> {
+        let invited_by =
+            ObjectId::from_str(invited_by).map_err(|_| InvalidIDError(invited_by.to_owned()))?;
+
+        let token = random_string(INVITE_TOKEN_LEN);
+        let now = Utc::now();
+        let invite = InviteModel {
+            id: ObjectId::new(),
+            tokenHash: credentials::hash(&token),
+            email: email.map(str::to_owned),
+            role: role.to_owned(),
+            invitedBy: invited_by,
+            acceptedBy: None,
+            acceptedAt: None,
+            expiresAt: now + expires_in,
+            createdAt: now,
+        };
+
+        self.invite_collection
+            .insert_one(&invite, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(InviteCreatedResponse {
+            status: "success",
+            data: InviteCreatedData {
+                invite: self.doc_to_invite(&invite),
+                url: format!("{}/{}", invite_url, token),
+                token,
+            },
+        })
+    }
+
+    pub async fn get_invite(&self, token: &str) -> Result<SingleInviteResponse> {
+        let invite = self
+            .invite_collection
+            .find_one(
+                doc! {"tokenHash": {"$in": credentials::lookup(token)}, "acceptedAt": null, "expiresAt": {"$gt": Utc::now()}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(InvalidInviteError)?;
+
+        Ok(SingleInviteResponse {
+            status: "success",
+            data: InviteData {
+                invite: self.doc_to_invite(&invite),
+            },
+        })
+    }
+
+    pub async fn accept_invite(&self, body: &AcceptInviteSchema) -> Result<SingleUserResponse> {
+        let now = Utc::now();
+
+        // Claim the invite first so two concurrent accepts can't both create an account.
+        let invite = self
+            .invite_collection
+            .find_one_and_update(
+                doc! {"tokenHash": {"$in": credentials::lookup(&body.token)}, "acceptedAt": null, "expiresAt": {"$gt": now}},
+                doc! {"$set": {"acceptedAt": now}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or(InvalidInviteError)?;
+
+        let scopes: Vec<String> = scope::for_role(&invite.role)
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let user = CreateUserSchema {
+            name: body.name.to_owned(),
+            uid: body.uid.to_owned(),
+            email: invite.email.to_owned().or_else(|| body.email.to_owned()),
+            website: String::new(),
+            formStamp: None,
+            captchaToken: None,
+        };
+
+        let created = match self.create_user(&user, &scopes).await {
+            Ok(created) => created,
+            Err(e) => {
+                // Release the claim so the invitee can retry with different details.
+                self.invite_collection
+                    .update_one(
+                        doc! {"_id": invite.id},
+                        doc! {"$set": {"acceptedAt": null}},
+                        None,
+                    )
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+                return Err(e);
+            }
+        };
+
+        let user_id = ObjectId::from_str(&created.data.user.id)
+            .map_err(|_| InvalidIDError(created.data.user.id.to_owned()))?;
+        self.invite_collection
+            .update_one(
+                doc! {"_id": invite.id},
+                doc! {"$set": {"acceptedBy": user_id}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(created)
+    }
+
+    pub async fn create_org(
+        &self,
+        creator: &str,
+        body: &CreateOrgSchema,
+    ) -> Result<SingleOrgResponse> {
+        let creator =
+            ObjectId::from_str(creator).map_err(|_| InvalidIDError(creator.to_owned()))?;
+
+        let now = Utc::now();
+        let org = OrgModel {
+            id: ObjectId::new(),
+            name: body.name.to_owned(),
+            slug: body.slug.to_owned(),
+            createdBy: creator,
+            plan: Plan::Free,
+            stripeCustomerId: None,
+            planChangedAt: None,
+            preferences: PreferencesModel::default(),
+            createdAt: now,
+            updatedAt: now,
+        };
+
+        if let Err(e) = self.org_collection.insert_one(&org, None).await {
+            return Err(write_error(e));
+        }
+
+        let membership = MembershipModel {
+            id: ObjectId::new(),
+            orgId: org.id,
+            userId: creator,
+            role: "owner".to_string(),
+            createdAt: now,
+        };
+        self.membership_collection
+            .insert_one(membership, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(SingleOrgResponse {
+            status: "success",
+            data: OrgData {
+                org: self.doc_to_org(&org),
+            },
+        })
+    }
+
+    pub async fn fetch_orgs_for_user(
+        &self,
+        user_id: &str,
+        read: ReadFrom,
+    ) -> Result<OrgListResponse> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        let find_options = FindOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .membership_collection
+            .find(doc! {"userId": user_oid}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut org_ids: Vec<ObjectId> = Vec::new();
+        while let Some(membership) = cursor.next().await {
+            org_ids.push(membership.map_err(MongoQueryError)?.orgId);
+        }
+
+        let find_options = FindOptions::builder()
+            .sort(doc! {"name": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .org_collection
+            .find(doc! {"_id": {"$in": org_ids}}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<OrgResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_org(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(OrgListResponse {
+            status: "success",
+            results: json_result.len(),
+            orgs: json_result,
+        })
+    }
+
+    pub async fn get_org(&self, id: &str, read: ReadFrom) -> Result<SingleOrgResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        match self
+            .org_collection
+            .find_one(doc! {"_id": oid}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            Some(org) => Ok(SingleOrgResponse {
+                status: "success",
+                data: OrgData {
+                    org: self.doc_to_org(&org),
+                },
+            }),
+            None => Err(NotFoundError(id.to_string())),
+        }
+    }
+
+    pub async fn edit_org(&self, id: &str, body: &UpdateOrgSchema) -> Result<SingleOrgResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let mut set = bson::to_document(body).map_err(MongoSerializeBsonError)?;
+        set.insert("updatedAt", Utc::now());
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        match self
+            .org_collection
+            .find_one_and_update(doc! {"_id": oid}, doc! {"$set": set}, options)
+            .await
+        {
+            Ok(Some(org)) => Ok(SingleOrgResponse {
+                status: "success",
+                data: OrgData {
+                    org: self.doc_to_org(&org),
+                },
+            }),
+            Ok(None) => Err(NotFoundError(id.to_string())),
+            Err(e) => Err(write_error(e)),
+        }
+    }
+
+    pub async fn delete_org(&self, id: &str) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let result = self
+            .org_collection
+            .delete_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if result.deleted_count == 0 {
+            return Err(NotFoundError(id.to_string()));
+        }
+
+        self.membership_collection
+            .delete_many(doc! {"orgId": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.record_deletion("org", id).await
+    }
+
+    pub async fn membership_role(&self, org_id: &str, user_id: &str) -> Result<Option<String>> {
+        let (Ok(org_oid), Ok(user_oid)) = (ObjectId::from_str(org_id), ObjectId::from_str(user_id))
+        else {
+            return Ok(None);
+        };
+
+        let membership = self
+            .membership_collection
+            .find_one(doc! {"orgId": org_oid, "userId": user_oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(membership.map(|membership| membership.role))
+    }
+
+    /// The membership used for tokens when the client doesn't pick an org: the oldest one.
+    pub async fn default_membership(&self, user_id: &ObjectId) -> Result<Option<MembershipModel>> {
+        let options = FindOneOptions::builder()
+            .sort(doc! {"createdAt": 1})
+            .build();
+
+        self.membership_collection
+            .find_one(doc! {"userId": user_id}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)
+    }
+
+    pub async fn org_plan(&self, org_id: &str) -> Result<Plan> {
+        let oid = ObjectId::from_str(org_id).map_err(|_| InvalidIDError(org_id.to_owned()))?;
+
+        match self
+            .org_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            Some(org) => Ok(org.plan),
+            None => Err(NotFoundError(org_id.to_string())),
+        }
+    }
+
+    /// Moves `account` onto `plan` as of a Stripe event created at `at`.
+    ///
+    /// Stripe doesn't deliver events in order, so an account whose plan was
+    /// already set by a later event is left alone.
+    pub async fn set_plan(
+        &self,
+        account: &BillingAccount,
+        customer: &str,
+        plan: Plan,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let update = doc! {
+            "$set": {
+                "plan": plan.as_str(),
+                "stripeCustomerId": customer,
+                "planChangedAt": at,
+                "updatedAt": Utc::now(),
+            },
+        };
+        let mut filter = doc! {
+            "$or": [{"planChangedAt": null}, {"planChangedAt": {"$lt": at}}],
+        };
+
+        let users = self.collection.clone();
+        let orgs = self.org_collection.clone_with_type::<Document>();
+        let collections = match account {
+            BillingAccount::Org(id) => {
+                let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+                filter.insert("_id", oid);
+                vec![orgs]
+            }
+            BillingAccount::User(id) => {
+                let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+                filter.insert("_id", oid);
+                vec![users]
+            }
+            BillingAccount::Customer => {
+                filter.insert("stripeCustomerId", customer);
+                vec![users, orgs]
+            }
+        };
+
+        for collection in collections {
+            let result = collection
+                .update_one(filter.clone(), update.clone(), None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            if result.matched_count > 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn fetch_members(&self, org_id: &str) -> Result<MemberListResponse> {
+        let org_oid = ObjectId::from_str(org_id).map_err(|_| InvalidIDError(org_id.to_owned()))?;
+
+        let find_options = FindOptions::builder().sort(doc! {"createdAt": 1}).build();
+        let mut cursor = self
+            .membership_collection
+            .find(doc! {"orgId": org_oid}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<MemberResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_member(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(MemberListResponse {
+            status: "success",
+            results: json_result.len(),
+            members: json_result,
+        })
+    }
+
+    /// Adds a member, or changes their role if they already belong to the org.
+    pub async fn add_member(
+        &self,
+        org_id: &str,
+        user_id: &str,
+        role: &str,
+    ) -> Result<MemberListResponse> {
+        let org_oid = ObjectId::from_str(org_id).map_err(|_| InvalidIDError(org_id.to_owned()))?;
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        if self.find_user_by_id(&user_oid).await?.is_none() {
+            return Err(NotFoundError(user_id.to_string()));
+        }
+
+        let existing = self.membership_role(org_id, user_id).await?;
+        if existing.as_deref() == Some("owner") && role != "owner" {
+            self.ensure_other_owner(&org_oid, &user_oid).await?;
+        }
+
+        let options = mongodb::options::UpdateOptions::builder()
+            .upsert(true)
+            .build();
+        self.membership_collection
+            .update_one(
+                doc! {"orgId": org_oid, "userId": user_oid},
+                doc! {
+                    "$set": {"role": role},
+                    "$setOnInsert": {"_id": ObjectId::new(), "createdAt": Utc::now()},
+                },
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.fetch_members(org_id).await
+    }
+
+    pub async fn remove_member(&self, org_id: &str, user_id: &str) -> Result<()> {
+        let org_oid = ObjectId::from_str(org_id).map_err(|_| InvalidIDError(org_id.to_owned()))?;
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        if self.membership_role(org_id, user_id).await?.as_deref() == Some("owner") {
+            self.ensure_other_owner(&org_oid, &user_oid).await?;
+        }
+
+        let result = self
+            .membership_collection
+            .delete_one(doc! {"orgId": org_oid, "userId": user_oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        match result.deleted_count {
+            0 => Err(NotFoundError(user_id.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    async fn ensure_other_owner(&self, org_id: &ObjectId, user_id: &ObjectId) -> Result<()> {
+        let owners = self
+            .membership_collection
+            .count_documents(
+                doc! {"orgId": org_id, "userId": {"$ne": user_id}, "role": "owner"},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if owners == 0 {
+            return Err(ValidationError(
+                "an organization must keep at least one owner".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_service_account(
+        &self,
+        name: &str,
+        scopes: Vec<String>,
+    ) -> Result<ServiceAccountCredentialsResponse> {
+        let now = Utc::now();
+        let client_secret = random_string(CLIENT_SECRET_LEN);
+        let account = ServiceAccountModel {
+            id: ObjectId::new(),
+            name: name.to_owned(),
+            clientId: format!("{}{}", CLIENT_ID_PREFIX, random_string(CLIENT_ID_LEN)),
+            secretHash: credentials::hash(&client_secret),
+            scopes,
+            secretRotatedAt: now,
+            createdAt: now,
+        };
+
+        self.service_account_collection
+            .insert_one(&account, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(ServiceAccountCredentialsResponse {
+            status: "success",
+            data: ServiceAccountCredentials {
+                service_account: self.doc_to_service_account(&account),
+                client_secret,
+            },
+        })
+    }
+
+    pub async fn fetch_service_accounts(&self) -> Result<ServiceAccountListResponse> {
+        let find_options = FindOptions::builder().sort(doc! {"name": 1}).build();
+        let mut cursor = self
+            .service_account_collection
+            .find(None, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<ServiceAccountResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_service_account(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(ServiceAccountListResponse {
+            status: "success",
+            results: json_result.len(),
+            service_accounts: json_result,
+        })
+    }
+
+    /// Replaces the client secret; the previous secret stops working immediately.
+    pub async fn rotate_service_account_secret(
+        &self,
+        id: &str,
+    ) -> Result<ServiceAccountCredentialsResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let client_secret = random_string(CLIENT_SECRET_LEN);
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        match self
+            .service_account_collection
+            .find_one_and_update(
+                doc! {"_id": oid},
+                doc! {"$set": {"secretHash": credentials::hash(&client_secret), "secretRotatedAt": Utc::now()}},
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            Some(account) => Ok(ServiceAccountCredentialsResponse {
+                status: "success",
+                data: ServiceAccountCredentials {
+                    service_account: self.doc_to_service_account(&account),
+                    client_secret,
+                },
+            }),
+            None => Err(NotFoundError(id.to_string())),
+        }
+    }
+
+    pub async fn authenticate_client(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<Option<ServiceAccountModel>> {
+        let account = self
+            .service_account_collection
+            .find_one(
+                doc! {"clientId": client_id, "secretHash": {"$in": credentials::lookup(client_secret)}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // Secrets hashed before the pepper was set move to the current hash on use.
+        if let Some(account) = &account {
+            let current = credentials::hash(client_secret);
+            if account.secretHash != current {
+                self.service_account_collection
+                    .update_one(
+                        doc! {"_id": account.id},
+                        doc! {"$set": {"secretHash": current}},
+                        None,
+                    )
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+            }
+        }
+        Ok(account)
+    }
+
+    pub async fn create_magic_link(&self, user_id: ObjectId) -> Result<String> {
+        let token = random_string(MAGIC_LINK_TOKEN_LEN);
+
+        let now = Utc::now();
+        let link = MagicLinkModel {
+            id: ObjectId::new(),
+            userId: user_id,
+            tokenHash: credentials::hash(&token),
+            expiresAt: now + Duration::minutes(MAGIC_LINK_TTL_MINUTES),
+            usedAt: None,
+            createdAt: now,
+        };
+
+        self.magic_link_collection
+            .insert_one(link, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(token)
+    }
+
+    pub async fn redeem_magic_link(&self, token: &str, client: &ClientInfo) -> Result<ObjectId> {
+        let now = Utc::now();
+
+        // Matching on `usedAt: null` inside the update makes redemption single-use even
+        // when the same link is opened concurrently.
+        let redeemed = self
+            .magic_link_collection
+            .find_one_and_update(
+                doc! {"tokenHash": {"$in": credentials::lookup(token)}, "usedAt": null, "expiresAt": {"$gt": now}},
+                doc! {"$set": {"usedAt": now}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if let Some(link) = redeemed {
+            self.record_login(Some(link.userId), client, LoginOutcome::Success, None)
+                .await?;
+            return Ok(link.userId);
+        }
+
+        let known = self
+            .magic_link_collection
+            .find_one(
+                doc! {"tokenHash": {"$in": credentials::lookup(token)}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let (user_id, reason) = match known {
+            Some(link) if link.usedAt.is_some() => (Some(link.userId), "magic link already used"),
+            Some(link) => (Some(link.userId), "magic link expired"),
+            None => (None, "unknown magic link"),
+        };
+        self.record_login(user_id, client, LoginOutcome::Failure, Some(reason))
+            .await?;
+
+        Err(UnauthorizedError(
+            "invalid or expired magic link".to_string(),
+        ))
+    }
+
+    pub async fn create_session(
+        &self,
+        id: ObjectId,
+        user_id: ObjectId,
+        client: &ClientInfo,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let session = SessionModel {
+            id,
+            userId: user_id,
+            ip: client.ip.to_owned(),
+            userAgent: client.user_agent.to_owned(),
+            geo: client.geo.to_owned(),
+            expiresAt: expires_at,
+            revokedAt: None,
+            createdAt: Utc::now(),
+        };
+
+        self.session_collection
+            .insert_one(session, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    pub async fn is_session_active(&self, id: &str) -> Result<bool> {
+        let Ok(oid) = ObjectId::from_str(id) else {
+            return Ok(false);
+        };
+
+        let session = self
+            .session_collection
+            .find_one(
+                doc! {"_id": oid, "revokedAt": null, "expiresAt": {"$gt": Utc::now()}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(session.is_some())
+    }
+
+    pub async fn record_login(
+        &self,
+        user_id: Option<ObjectId>,
+        client: &ClientInfo,
+        outcome: LoginOutcome,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        let entry = LoginHistoryModel {
+            id: ObjectId::new(),
+            userId: user_id,
+            ip: client.ip.to_owned(),
+            userAgent: client.user_agent.to_owned(),
+            geo: client.geo.to_owned(),
+            outcome,
+            reason: reason.map(str::to_owned),
+            createdAt: Utc::now(),
+        };
+
+        self.login_history_collection
+            .insert_one(entry, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_logins(
+        &self,
+        id: &str,
+        paging: Pagination,
+    ) -> Result<LoginHistoryListResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let find_options = FindOptions::builder()
+            .sort(doc! {"createdAt": -1})
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .build();
+
+        let mut cursor = self
+            .login_history_collection
+            .find(doc! {"userId": oid}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<LoginHistoryResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_login(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(LoginHistoryListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: json_result.len(),
+            logins: json_result,
+        })
+    }
+
+    /// Returns the user's current export, starting a new one if there is none.
+    ///
+    /// The flag is `true` when the export was just created and still needs a worker.
+    pub async fn start_export(&self, user_id: &str) -> Result<(ExportModel, bool)> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+
+        if self.find_user_by_id(&user_oid).await?.is_none() {
+            return Err(NotFoundError(user_id.to_string()));
+        }
+
+        let now = Utc::now();
+        let stale_before = now - Duration::minutes(EXPORT_STALE_MINUTES);
+        let options = FindOneOptions::builder()
+            .sort(doc! {"createdAt": -1})
+            .build();
+        let current = self
+            .export_collection
+            .find_one(
+                doc! {
+                    "userId": user_oid,
+                    "expiresAt": {"$gt": now},
+                    "$or": [
+                        {"status": "ready"},
+                        {"status": "pending", "createdAt": {"$gt": stale_before}},
+                    ],
+                },
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if let Some(export) = current {
+            return Ok((export, false));
+        }
+
+        let export = ExportModel {
+            id: ObjectId::new(),
+            userId: user_oid,
+            status: ExportStatus::Pending,
+            archive: None,
+            error: None,
+            expiresAt: now + Duration::hours(EXPORT_TTL_HOURS),
+            completedAt: None,
+            createdAt: now,
+        };
+        self.export_collection
+            .insert_one(&export, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok((export, true))
+    }
+
+    pub async fn get_export(&self, user_id: &str, id: &str) -> Result<SingleExportResponse> {
+        let export = self.find_export(user_id, id).await?;
+
+        Ok(self.export_response(&export))
+    }
+
+    /// Returns the finished archive as JSON.
+    pub async fn export_archive(&self, user_id: &str, id: &str) -> Result<String> {
+        match self.find_export(user_id, id).await? {
+            ExportModel {
+                status: ExportStatus::Ready,
+                archive: Some(archive),
+                ..
+            } => Ok(archive),
+            _ => Err(ExportNotReadyError(id.to_string())),
+        }
+    }
+
+    pub async fn complete_export(&self, id: &ObjectId, archive: String) -> Result<()> {
+        self.export_collection
+            .update_one(
+                doc! {"_id": id},
+                doc! {"$set": {"status": "ready", "archive": archive, "completedAt": Utc::now()}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    pub async fn fail_export(&self, id: &ObjectId, error: &str) -> Result<()> {
+        self.export_collection
+            .update_one(
+                doc! {"_id": id},
+                doc! {"$set": {"status": "failed", "error": error, "completedAt": Utc::now()}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    pub fn export_response(&self, export: &ExportModel) -> SingleExportResponse {
+        SingleExportResponse {
+            status: "success",
+            data: ExportData {
+                export: self.doc_to_export(export),
+            },
+        }
+    }
+
+    /// Gathers everything this service holds about a user. Posts live in the
+    /// blog service and are left for the caller to fill in.
+    pub async fn collect_user_data(&self, user_id: &ObjectId) -> Result<UserArchive> {
+        let user = match self.find_user_by_id(user_id).await? {
+            Some(user) => self.doc_to_user(&user)?,
+            None => return Err(NotFoundError(user_id.to_hex())),
+        };
+
+        let find_options = FindOptions::builder().sort(doc! {"createdAt": -1}).build();
+
+        let mut sessions = Vec::new();
+        let mut cursor = self
+            .session_collection
+            .find(doc! {"userId": user_id}, find_options.clone())
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            sessions.push(self.doc_to_session(&doc.map_err(MongoQueryError)?));
+        }
+
+        let mut logins = Vec::new();
+        let mut cursor = self
+            .login_history_collection
+            .find(doc! {"userId": user_id}, find_options.clone())
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            logins.push(self.doc_to_login(&doc.map_err(MongoQueryError)?));
+        }
+
+        let mut api_keys = Vec::new();
+        let mut cursor = self
+            .api_key_collection
+            .find(doc! {"userId": user_id}, find_options.clone())
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            api_keys.push(self.doc_to_api_key(&doc.map_err(MongoQueryError)?));
+        }
+
+        let mut memberships = Vec::new();
+        let mut cursor = self
+            .membership_collection
+            .find(doc! {"userId": user_id}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            memberships.push(self.doc_to_member(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(UserArchive {
+            generatedAt: Utc::now(),
+            user,
+            sessions,
+            logins,
+            apiKeys: api_keys,
+            memberships,
+            posts: Vec::new(),
+        })
+    }
+
+    async fn find_export(&self, user_id: &str, id: &str) -> Result<ExportModel> {
+        let user_oid =
+            ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        self.export_collection
+            .find_one(doc! {"_id": oid, "userId": user_oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))
+    }
+
+    pub async fn check_availability(
+        &self,
+        field: &'static str,
+        value: &str,
+    ) -> Result<AvailabilityResponse> {
+        let available = !self.is_taken(field, value).await?;
+
+        let suggestions = if available {
+            Vec::new()
+        } else {
+            self.suggest_alternatives(field, value).await?
+        };
+
+        Ok(AvailabilityResponse {
+            status: "success",
+            field,
+            value: value.to_owned(),
+            available,
+            suggestions,
+        })
+    }
+
+    /// Looks up active users by exact name, for callers that only know a
+    /// user by the name they go by, like comment mentions, or by id, for
+    /// callers that need a user's name. Ids that aren't valid are skipped.
+    pub async fn resolve_users(
+        &self,
+        names: &[String],
+        ids: &[String],
+    ) -> Result<ResolveUsersResponse> {
+        let find_options = FindOptions::builder()
+            .projection(doc! {"_id": 1, "name": 1})
+            .build();
+        let oids: Vec<ObjectId> = ids
+            .iter()
+            .filter_map(|id| ObjectId::from_str(id).ok())
+            .collect();
+
+        let mut cursor = self
+            .collection
+            .find(
+                doc! {
+                    "$or": [{"name": {"$in": names.to_vec()}}, {"_id": {"$in": oids}}],
+                    "anonymizedAt": null,
+                },
+                find_options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut users = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            users.push(ResolvedUserResponse {
+                id: doc.get_object_id("_id")?.to_hex(),
+                name: doc.get_str("name")?.to_owned(),
+            });
+        }
+
+        Ok(ResolveUsersResponse {
+            status: "success",
+            users,
+        })
+    }
+
+    /// The author page of the user going by `name`, as far as they show it.
+    pub async fn public_profile(
+        &self,
+        name: &str,
+        read: ReadFrom,
+    ) -> Result<ProfileLookupResponse> {
+        let user = self
+            .users
+            .find_by::<UserName>(name, read)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // Anonymized users keep a placeholder name, not their page.
+        let user = user.filter(|user| user.anonymizedAt.is_none());
+
+        let profile = user.filter(|user| user.privacy.listed).map(|user| {
+            let privacy = user.privacy;
+            let profile = user.profile;
+            PublicProfileResponse {
+                id: user.id.to_hex(),
+                name: user.name,
+                bio: profile.bio.filter(|_| privacy.bio),
+                website: profile.website.filter(|_| privacy.website),
+                location: profile.location.filter(|_| privacy.location),
+                avatarUrl: profile.avatarUrl.filter(|_| privacy.avatar),
+                joinedAt: privacy.joinedAt.then_some(user.createdAt),
+                recentPosts: privacy.recentPosts,
+                stats: privacy.stats,
+            }
+        });
+
+        Ok(ProfileLookupResponse {
+            status: "success",
+            profile,
+        })
+    }
+
+    async fn is_taken(&self, field: &str, value: &str) -> Result<bool> {
+        // Projecting only the looked-up field lets the unique index cover the query.
+        let options = FindOneOptions::builder()
+            .projection(doc! {"_id": 0, field: 1})
+            .build();
+
+        let existing = self
+            .collection
+            .find_one(doc! {field: value}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(existing.is_some())
+    }
+
+    async fn suggest_alternatives(&self, field: &str, value: &str) -> Result<Vec<String>> {
+        let year = Utc::now().year();
+        let mut candidates: Vec<String> = (1..=9).map(|n| format!("{}{}", value, n)).collect();
+        candidates.push(format!("{}_{}", value, year));
+        candidates.push(format!("the_{}", value));
+
+        let find_options = FindOptions::builder()
+            .projection(doc! {"_id": 0, field: 1})
+            .build();
+
+        let mut cursor = self
+            .collection
+            .find(doc! {field: {"$in": candidates.clone()}}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut taken = HashSet::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            taken.insert(doc.get_str(field)?.to_owned());
+        }
+
+        Ok(candidates
+            .into_iter()
+            .filter(|candidate| !taken.contains(candidate))
+            .take(MAX_SUGGESTIONS)
+            .collect())
+    }
+
+    fn find_one_options(&self, read: ReadFrom) -> FindOneOptions {
+        FindOneOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build()
+    }
+
+    fn doc_to_user(&self, user: &UserModel) -> Result<UserResponse> {
+        let user_response = UserResponse {
+            id: user.id.to_hex(),
+            name: user.name.to_owned(),
+            uid: user.uid.to_owned(),
+            email: user.email.to_owned(),
+            scopes: user.scopes.to_owned().unwrap_or_default(),
+            consents: user
+                .consents
+                .iter()
+                .flatten()
+                .map(|consent| ConsentResponse {
+                    document: consent.document,
+                    version: consent.version.to_owned(),
+                    acceptedAt: consent.acceptedAt,
+                })
+                .collect(),
+            plan: user.plan,
+            profile: user.profile.to_owned(),
+            privacy: user.privacy,
+            preferences: user.preferences.to_owned(),
+            anonymizedAt: user.anonymizedAt.map(|at| at.to_chrono()),
+            createdAt: user.createdAt,
+            updatedAt: user.updatedAt,
+        };
+
+        Ok(user_response)
+    }
+
+    fn doc_to_api_key(&self, api_key: &ApiKeyModel) -> ApiKeyResponse {
+        ApiKeyResponse {
+            id: api_key.id.to_hex(),
+            name: api_key.name.to_owned(),
+            prefix: api_key.prefix.to_owned(),
+            scopes: api_key.scopes.to_owned(),
+            lastUsedAt: api_key.lastUsedAt.map(|at| at.to_chrono()),
+            revokedAt: api_key.revokedAt.map(|at| at.to_chrono()),
+            createdAt: api_key.createdAt,
+        }
+    }
+
+    fn doc_to_org(&self, org: &OrgModel) -> OrgResponse {
+        OrgResponse {
+            id: org.id.to_hex(),
+            name: org.name.to_owned(),
+            slug: org.slug.to_owned(),
+            plan: org.plan,
+            createdAt: org.createdAt,
+            updatedAt: org.updatedAt,
+        }
+    }
+
+    fn doc_to_member(&self, membership: &MembershipModel) -> MemberResponse {
+        MemberResponse {
+            orgId: membership.orgId.to_hex(),
+            userId: membership.userId.to_hex(),
+            role: membership.role.to_owned(),
+            createdAt: membership.createdAt,
+        }
+    }
+
+    fn doc_to_invite(&self, invite: &InviteModel) -> InviteResponse {
+        InviteResponse {
+            id: invite.id.to_hex(),
+            email: invite.email.to_owned(),
+            role: invite.role.to_owned(),
+            expiresAt: invite.expiresAt,
+            createdAt: invite.createdAt,
+        }
+    }
+
+    fn doc_to_service_account(&self, account: &ServiceAccountModel) -> ServiceAccountResponse {
+        ServiceAccountResponse {
+            id: account.id.to_hex(),
+            name: account.name.to_owned(),
+            client_id: account.clientId.to_owned(),
+            scopes: account.scopes.to_owned(),
+            secretRotatedAt: account.secretRotatedAt,
+            createdAt: account.createdAt,
+        }
+    }
+
+    fn doc_to_dead_letter(&self, dead: &DeadLetterModel, with_payload: bool) -> DeadLetterResponse {
+        DeadLetterResponse {
+            id: dead.id.to_hex(),
+            kind: dead.kind.to_owned(),
+            payload: with_payload
+                .then(|| Bson::Document(dead.payload.clone()).into_relaxed_extjson()),
+            attempts: dead.attempts,
+            lastError: dead.lastError.to_owned(),
+            createdAt: dead.createdAt,
+            deadAt: dead.deadAt,
+        }
+    }
+
+    fn doc_to_session(&self, session: &SessionModel) -> SessionResponse {
+        SessionResponse {
+            id: session.id.to_hex(),
+            ip: session.ip.to_owned(),
+            userAgent: session.userAgent.to_owned(),
+            geo: session.geo.to_owned(),
+            expiresAt: session.expiresAt,
+            revokedAt: session.revokedAt.map(|at| at.to_chrono()),
+            createdAt: session.createdAt,
+            current: None,
+        }
+    }
+
+    fn doc_to_export(&self, export: &ExportModel) -> ExportResponse {
+        let download_url = match export.status {
+            ExportStatus::Ready => Some(format!(
+                "/api/users/{}/export/{}/download",
+                export.userId.to_hex(),
+                export.id.to_hex()
+            )),
+            _ => None,
+        };
+
+        ExportResponse {
+            id: export.id.to_hex(),
+            userId: export.userId.to_hex(),
+            status: export.status,
+            error: export.error.to_owned(),
+            downloadUrl: download_url,
+            expiresAt: export.expiresAt,
+            completedAt: export.completedAt.map(|at| at.to_chrono()),
+            createdAt: export.createdAt,
+        }
+    }
+
+    fn doc_to_login(&self, login: &LoginHistoryModel) -> LoginHistoryResponse {
+        LoginHistoryResponse {
+            id: login.id.to_hex(),
+            userId: login.userId.map(|id| id.to_hex()),
+            ip: login.ip.to_owned(),
+            userAgent: login.userAgent.to_owned(),
+            geo: login.geo.to_owned(),
+            outcome: login.outcome,
+            reason: login.reason.to_owned(),
+            createdAt: login.createdAt,
+        }
+    }
+
+    fn create_user_document(
+        &self,
+        body: &CreateUserSchema,
+        scopes: &[String],
+    ) -> Result<bson::Document> {
+        let serialized_data = bson::to_bson(body).map_err(MongoSerializeBsonError)?;
+        let document = serialized_data.as_document().unwrap();
+
+        let datetime = Utc::now();
+
+        let mut doc_with_dates = doc! {
+            "_id": ObjectId::new(),
+            "createdAt": datetime,
+            "updatedAt": datetime,
+            "scopes": scopes.to_vec()
+        };
+        doc_with_dates.extend(document.clone());
+
+        Ok(doc_with_dates)
+    }
+}
+
+/// Replaces the plaintext tokens of links issued before tokens were hashed.
+async fn rehash_magic_link_tokens(collection: &Collection<Document>) -> Result<u64> {
+    let mut cursor = collection
+        .find(doc! {"token": {"$type": "string"}}, None)
+        .await
+        .map_err(MongoQueryError)?;
+
+    let mut rehashed = 0;
+    while let Some(link) = cursor.next().await {
+        let link = link.map_err(MongoQueryError)?;
+        let (Ok(id), Ok(token)) = (link.get_object_id("_id"), link.get_str("token")) else {
+            continue;
+        };
+        collection
+            .update_one(
+                doc! {"_id": id},
+                doc! {"$set": {"tokenHash": credentials::hash(token)}, "$unset": {"token": ""}},
+                None,
+            )
+            .await
+            .map_err(MongoQueryError)?;
+        rehashed += 1;
+    }
+    Ok(rehashed)
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Duplicate-key failures become a conflict naming the clashing field; anything
+/// else is a plain query error.
+fn write_error(e: mongodb::error::Error) -> MyError {
+    match duplicate_key(&e) {
+        Some(conflict) => MongoDuplicateError(conflict),
+        None => MongoQueryError(e),
+    }
+}
+
+fn check_profile(profile: &ProfileModel) -> Result<()> {
+    let too_long = |value: &Option<String>, max: usize| {
+        value
+            .as_ref()
+            .is_some_and(|value| value.chars().count() > max)
+    };
+    if too_long(&profile.bio, MAX_BIO_LEN) {
+        return Err(ValidationError(format!(
+            "bio must be at most {} characters",
+            MAX_BIO_LEN
+        )));
+    }
+    if too_long(&profile.location, MAX_LOCATION_LEN) {
+        return Err(ValidationError(format!(
+            "location must be at most {} characters",
+            MAX_LOCATION_LEN
+        )));
+    }
+    for (field, url) in [
+        ("website", &profile.website),
+        ("avatarUrl", &profile.avatarUrl),
+    ] {
+        let Some(url) = url else {
+            continue;
+        };
+        if !(url.starts_with("https://") || url.starts_with("http://"))
+            || url.len() > MAX_PROFILE_URL_LEN
+        {
+            return Err(ValidationError(format!(
+                "{} must be an http(s) URL of at most {} characters",
+                field, MAX_PROFILE_URL_LEN
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The update applying `body` to a user's or an org's `preferences`;
+/// cleared fields are unset so they're inherited again.
+fn preferences_update(body: &PreferencesSchema) -> Result<Document> {
+    if let Some(Some(locale)) = &body.locale {
+        if !is_locale(locale) {
+            return Err(ValidationError(format!("{} is not a language tag", locale)));
+        }
+    }
+    if let Some(Some(timezone)) = &body.timezone {
+        if !is_timezone(timezone) {
+            return Err(ValidationError(format!("{} is not a time zone", timezone)));
+        }
+    }
+    let theme = match body.theme {
+        Some(Some(theme)) => Some(Some(
+            bson::to_bson(&theme).map_err(MongoSerializeBsonError)?,
+        )),
+        Some(None) => Some(None),
+        None => None,
+    };
+
+    let mut patch = Patch::new();
+    patch.set("updatedAt", Utc::now());
+    patch.nullable("preferences.locale", body.locale.clone());
+    patch.nullable("preferences.timezone", body.timezone.clone());
+    patch.nullable("preferences.theme", theme);
+    patch.nullable("preferences.emailNotifications", body.emailNotifications);
+    Ok(patch.into_update())
+}
+
+/// Roughly a BCP 47 tag: letter-led subtags of letters and digits.
+pub(crate) fn is_locale(tag: &str) -> bool {
+    tag.len() <= MAX_LOCALE_LEN
+        && tag.split('-').all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        && tag.starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+/// Shaped like an IANA zone name, such as `America/Argentina/Buenos_Aires`.
+pub(crate) fn is_timezone(zone: &str) -> bool {
+    !zone.is_empty()
+        && zone.len() <= MAX_TIMEZONE_LEN
+        && zone
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'))
 }
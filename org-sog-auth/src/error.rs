@@ -1,89 +1,418 @@
 use axum::{http::StatusCode, Json};
+use org_sog_core::{db_breaker, i18n};
 use serde::Serialize;
 
+use crate::response::ConsentVersion;
+
 #[derive(thiserror::Error, Debug)]
 pub enum MyError {
     #[error("MongoDB error")]
     MongoError(#[from] mongodb::error::Error),
     #[error("duplicate key error: {0}")]
-    MongoErrorKind(mongodb::error::ErrorKind),
+    MongoErrorKind(Box<mongodb::error::ErrorKind>),
     #[error("duplicate key error: {0}")]
-    MongoDuplicateError(mongodb::error::Error),
+    MongoDuplicateError(org_sog_core::conflict::DuplicateKey),
     #[error("error during mongodb query: {0}")]
     MongoQueryError(mongodb::error::Error),
     #[error("error serializing BSON")]
     MongoSerializeBsonError(#[from] mongodb::bson::ser::Error),
+    #[error("error deserializing BSON")]
+    MongoDeserializeBsonError(#[from] mongodb::bson::de::Error),
     #[error("validation error")]
     MongoDataError(#[from] mongodb::bson::document::ValueAccessError),
     #[error("invalid ID: {0}")]
     InvalidIDError(String),
+    #[error("missing query parameter: {0}")]
+    MissingParamError(String),
     #[error("User with ID: {0} not found")]
     NotFoundError(String),
+    #[error("unauthorized: {0}")]
+    UnauthorizedError(String),
+    #[error("missing required scope: {0}")]
+    ForbiddenError(String),
+    #[error("unknown scope: {0}")]
+    InvalidScopeError(String),
+    #[error("unsupported grant type: {0}")]
+    UnsupportedGrantError(String),
+    #[error("unknown role: {0}")]
+    InvalidRoleError(String),
+    #[error("name not allowed: {0}")]
+    NameNotAllowedError(#[from] org_sog_core::names::NameRejection),
+    #[error("{0}")]
+    InvalidPageError(#[from] org_sog_core::page::InvalidPage),
+    #[error("validation error: {0}")]
+    ValidationError(String),
+    #[error("invite is invalid, expired or already used")]
+    InvalidInviteError,
+    #[error("registration is by invitation only")]
+    RegistrationClosedError,
+    #[error("challenge verification failed")]
+    ChallengeFailedError,
+    #[error("too many requests, retry in {0}s")]
+    TooManyRequestsError(u64),
+    #[error("signing key {0} not found")]
+    SigningKeyNotFoundError(String),
+    #[error("signing key {0} is still in use")]
+    SigningKeyInUseError(String),
+    #[error("key store error: {0}")]
+    KeyStoreError(String),
+    #[error("error signing token: {0}")]
+    TokenError(jsonwebtoken::errors::Error),
+    #[error("error sending mail: {0}")]
+    MailError(#[from] org_sog_core::mail::MailError),
+    #[error("upstream service error: {0}")]
+    UpstreamError(String),
+    #[error("export {0} is not ready")]
+    ExportNotReadyError(String),
+    #[error("the current terms must be accepted")]
+    ConsentRequiredError(Vec<ConsentVersion>),
+    #[error("request timed out")]
+    RequestTimeoutError,
+    #[error("request body is too large")]
+    PayloadTooLargeError,
 }
 
 #[derive(Serialize)]
 struct ErrorResponse {
     status: &'static str,
+    /// Stable across languages, for clients that want their own wording.
+    code: &'static str,
     message: String,
 }
 
 impl Into<(axum::http::StatusCode, Json<serde_json::Value>)> for MyError {
     fn into(self) -> (axum::http::StatusCode, Json<serde_json::Value>) {
+        // Errors that carry more than a message.
+        match self {
+            MyError::MongoDuplicateError(conflict) => {
+                let field = conflict.field.unwrap_or_else(|| "value".to_string());
+                return (
+                    StatusCode::CONFLICT,
+                    Json(serde_json::json!({
+                        "status": "fail",
+                        "code": "duplicate_key",
+                        "message": i18n::message(
+                            "duplicate_key",
+                            "that {0} is already taken",
+                            &[&field],
+                        ),
+                        "conflict_field": field,
+                    })),
+                );
+            }
+            MyError::NameNotAllowedError(rejection) => {
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(serde_json::json!({
+                        "status": "fail",
+                        "code": rejection.code(),
+                        "message": i18n::message(
+                            rejection.code(),
+                            &rejection.to_string(),
+                            &[&rejection.name()],
+                        ),
+                    })),
+                )
+            }
+            _ => {}
+        }
+
         let (status, error_response) = match self {
             MyError::MongoErrorKind(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error kind: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
-            MyError::MongoDuplicateError(_) => (
-                StatusCode::CONFLICT,
+            MyError::InvalidIDError(id) => (
+                StatusCode::BAD_REQUEST,
                 ErrorResponse {
                     status: "fail",
-                    message: "User with that uid already exists".to_string(),
+                    code: "invalid_id",
+                    message: i18n::message("invalid_id", "invalid ID: {0}", &[&id]),
                 },
             ),
-            MyError::InvalidIDError(id) => (
+            MyError::MissingParamError(param) => (
                 StatusCode::BAD_REQUEST,
                 ErrorResponse {
                     status: "fail",
-                    message: format!("invalid ID: {}", id),
+                    code: "missing_param",
+                    message: i18n::message(
+                        "missing_param",
+                        "missing query parameter: {0}",
+                        &[&param],
+                    ),
                 },
             ),
             MyError::NotFoundError(id) => (
                 StatusCode::NOT_FOUND,
                 ErrorResponse {
                     status: "fail",
-                    message: format!("User with ID: {} not found", id),
+                    code: "user_not_found",
+                    message: i18n::message("user_not_found", "User with ID: {0} not found", &[&id]),
+                },
+            ),
+            MyError::UnauthorizedError(reason) => (
+                StatusCode::UNAUTHORIZED,
+                ErrorResponse {
+                    status: "fail",
+                    code: "unauthorized",
+                    message: i18n::message("unauthorized", "{0}", &[&reason]),
+                },
+            ),
+            MyError::ForbiddenError(scope) => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "forbidden",
+                    message: i18n::message("forbidden", "missing required scope: {0}", &[&scope]),
+                },
+            ),
+            MyError::InvalidScopeError(scope) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "invalid_scope",
+                    message: i18n::message("invalid_scope", "unknown scope: {0}", &[&scope]),
+                },
+            ),
+            MyError::UnsupportedGrantError(grant) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "unsupported_grant",
+                    message: i18n::message(
+                        "unsupported_grant",
+                        "unsupported grant type: {0}",
+                        &[&grant],
+                    ),
+                },
+            ),
+            MyError::InvalidRoleError(role) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "invalid_role",
+                    message: i18n::message("invalid_role", "unknown role: {0}", &[&role]),
+                },
+            ),
+            // Answered above.
+            MyError::MongoDuplicateError(_) | MyError::NameNotAllowedError(_) => unreachable!(),
+            MyError::InvalidPageError(e) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorResponse {
+                    status: "fail",
+                    code: "invalid_page",
+                    message: i18n::message("invalid_page", "{0}", &[&e]),
+                },
+            ),
+            MyError::ValidationError(reason) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "validation_failed",
+                    message: i18n::message("validation_failed", "{0}", &[&reason]),
+                },
+            ),
+            MyError::InvalidInviteError => (
+                StatusCode::GONE,
+                ErrorResponse {
+                    status: "fail",
+                    code: "invalid_invite",
+                    message: i18n::message(
+                        "invalid_invite",
+                        "invite is invalid, expired or already used",
+                        &[],
+                    ),
+                },
+            ),
+            MyError::RegistrationClosedError => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "registration_closed",
+                    message: i18n::message(
+                        "registration_closed",
+                        "registration is by invitation only",
+                        &[],
+                    ),
+                },
+            ),
+            MyError::ChallengeFailedError => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "challenge_failed",
+                    message: i18n::message(
+                        "challenge_failed",
+                        "challenge verification failed",
+                        &[],
+                    ),
+                },
+            ),
+            MyError::TooManyRequestsError(retry_after) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorResponse {
+                    status: "fail",
+                    code: "too_many_requests",
+                    message: i18n::message(
+                        "too_many_requests",
+                        "too many requests, retry in {0}s",
+                        &[&retry_after],
+                    ),
+                },
+            ),
+            MyError::SigningKeyNotFoundError(kid) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "signing_key_not_found",
+                    message: i18n::message(
+                        "signing_key_not_found",
+                        "signing key {0} not found",
+                        &[&kid],
+                    ),
+                },
+            ),
+            MyError::SigningKeyInUseError(kid) => (
+                StatusCode::CONFLICT,
+                ErrorResponse {
+                    status: "fail",
+                    code: "signing_key_in_use",
+                    message: i18n::message(
+                        "signing_key_in_use",
+                        "signing key {0} is still in use",
+                        &[&kid],
+                    ),
                 },
             ),
+            MyError::KeyStoreError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorResponse {
+                    status: "error",
+                    code: "key_store_error",
+                    message: i18n::message("key_store_error", "key store error: {0}", &[&e]),
+                },
+            ),
+            MyError::TokenError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorResponse {
+                    status: "error",
+                    code: "token_error",
+                    message: i18n::message("token_error", "token error: {0}", &[&e]),
+                },
+            ),
+            MyError::MailError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorResponse {
+                    status: "error",
+                    code: "mail_error",
+                    message: i18n::message("mail_error", "mail error: {0}", &[&e]),
+                },
+            ),
+            MyError::UpstreamError(e) => (
+                StatusCode::BAD_GATEWAY,
+                ErrorResponse {
+                    status: "error",
+                    code: "upstream_error",
+                    message: i18n::message("upstream_error", "upstream service error: {0}", &[&e]),
+                },
+            ),
+            MyError::ExportNotReadyError(id) => (
+                StatusCode::CONFLICT,
+                ErrorResponse {
+                    status: "fail",
+                    code: "export_not_ready",
+                    message: i18n::message("export_not_ready", "export {0} is not ready", &[&id]),
+                },
+            ),
+            MyError::ConsentRequiredError(required) => {
+                return (
+                    StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                    Json(serde_json::json!({
+                        "status": "fail",
+                        "code": "consent_required",
+                        "message": i18n::message(
+                            "consent_required",
+                            "the current terms must be accepted",
+                            &[],
+                        ),
+                        "required": required,
+                    })),
+                )
+            }
+            MyError::RequestTimeoutError => (
+                StatusCode::REQUEST_TIMEOUT,
+                ErrorResponse {
+                    status: "fail",
+                    code: "request_timeout",
+                    message: i18n::message("request_timeout", "request timed out", &[]),
+                },
+            ),
+            MyError::PayloadTooLargeError => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorResponse {
+                    status: "fail",
+                    code: "payload_too_large",
+                    message: i18n::message("payload_too_large", "request body is too large", &[]),
+                },
+            ),
+            MyError::MongoError(e) | MyError::MongoQueryError(e)
+                if db_breaker::unavailable(&e).is_some() =>
+            {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    ErrorResponse {
+                        status: "error",
+                        code: "database_unavailable",
+                        message: i18n::message(
+                            "database_unavailable",
+                            "database temporarily unavailable",
+                            &[],
+                        ),
+                    },
+                )
+            }
             MyError::MongoError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
             MyError::MongoQueryError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
             MyError::MongoSerializeBsonError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
+                },
+            ),
+            MyError::MongoDeserializeBsonError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorResponse {
+                    status: "error",
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
             MyError::MongoDataError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
         };
@@ -0,0 +1,79 @@
+use std::{sync::Arc, time::Duration};
+
+use mongodb::bson::{oid::ObjectId, Bson};
+
+use crate::{error::MyError, model::EventModel, scope, AppState};
+
+pub const USER_ANONYMIZED: &str = "user.anonymized";
+pub const RESOURCE_DELETED: &str = "resource.deleted";
+
+const BATCH_SIZE: i64 = 50;
+/// Failed deliveries before an event goes to the dead-letter queue; at one
+/// attempt a minute, about an hour and a half of retries.
+const MAX_ATTEMPTS: i32 = 90;
+const DISPATCH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Subject and client id used when this service calls others on its own behalf.
+pub const SERVICE_CLIENT_ID: &str = "org-sog-auth";
+
+/// Periodically retries outbox events that could not be delivered right away.
+pub fn spawn_dispatcher(app_state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DISPATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            dispatch(&app_state).await;
+        }
+    });
+}
+
+/// Makes one delivery attempt for each pending event.
+///
+/// Delivery is at-least-once, so receivers must tolerate seeing an event twice.
+pub async fn dispatch(app_state: &AppState) {
+    let pending = match app_state.db.pending_events(BATCH_SIZE).await {
+        Ok(pending) => pending,
+        Err(e) => {
+            println!("⚠️ Could not load pending events: {}", e);
+            return;
+        }
+    };
+
+    for event in pending {
+        let recorded = match deliver(app_state, &event).await {
+            Ok(()) => app_state.db.mark_event_delivered(&event.id).await,
+            Err(e) if event.attempts + 1 >= MAX_ATTEMPTS => {
+                println!(
+                    "⚠️ Giving up on event {} after {} attempts: {}",
+                    event.id, MAX_ATTEMPTS, e
+                );
+                app_state.db.bury_event(&event, &e.to_string()).await
+            }
+            Err(e) => {
+                app_state
+                    .db
+                    .mark_event_failed(&event.id, &e.to_string())
+                    .await
+            }
+        };
+
+        if let Err(e) = recorded {
+            println!("⚠️ Could not record delivery of event {}: {}", event.id, e);
+        }
+    }
+}
+
+async fn deliver(app_state: &AppState, event: &EventModel) -> Result<(), MyError> {
+    let (token, _claims) = app_state.tokens.issue_for_client(
+        SERVICE_CLIENT_ID,
+        SERVICE_CLIENT_ID,
+        &ObjectId::new().to_hex(),
+        &[scope::BLOG_ADMIN.to_string()],
+    )?;
+
+    let mut body = Bson::Document(event.payload.clone()).into_relaxed_extjson();
+    body["id"] = event.id.to_hex().into();
+    body["type"] = event.kind.clone().into();
+
+    app_state.blog.publish_event(&token, &body).await
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use mongodb::bson::oid::ObjectId;
+
+use crate::{error::MyError, events::SERVICE_CLIENT_ID, scope, AppState};
+
+/// Builds a user's data export and records the outcome on the export job.
+pub async fn run(app_state: Arc<AppState>, export_id: ObjectId, user_id: ObjectId) {
+    let recorded = match assemble(&app_state, &user_id).await {
+        Ok(archive) => app_state.db.complete_export(&export_id, archive).await,
+        Err(e) => app_state.db.fail_export(&export_id, &e.to_string()).await,
+    };
+
+    if let Err(e) = recorded {
+        println!("⚠️ Could not record result of export {}: {}", export_id, e);
+    }
+}
+
+async fn assemble(app_state: &AppState, user_id: &ObjectId) -> Result<String, MyError> {
+    let mut archive = app_state.db.collect_user_data(user_id).await?;
+    // As the blog admin, so unlisted and protected posts are exported too.
+    let (token, _claims) = app_state.tokens.issue_for_client(
+        SERVICE_CLIENT_ID,
+        SERVICE_CLIENT_ID,
+        &ObjectId::new().to_hex(),
+        &[scope::BLOG_ADMIN.to_string()],
+    )?;
+    archive.posts = app_state
+        .blog
+        .posts_by_author(&token, &user_id.to_hex())
+        .await?;
+
+    Ok(serde_json::to_string_pretty(&archive).expect("user archive serializes to JSON"))
+}
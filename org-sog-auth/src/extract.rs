@@ -0,0 +1,267 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, State},
+    http::{
+        header::{AsHeaderName, AUTHORIZATION, FORWARDED, USER_AGENT},
+        request::Parts,
+        HeaderMap, Request, StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use org_sog_core::{
+    client_ip::{ClientIp, ForwardingHeaders},
+    geo::GeoLocation,
+    http::TRACEPARENT_HEADER,
+    trace,
+};
+
+use crate::{
+    error::MyError::{self, ForbiddenError, UnauthorizedError},
+    scope, AppState,
+};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+const X_FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub geo: Option<GeoLocation>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Set by `resolve_client_ip`; requests that skipped it fall back to
+        // the peer address.
+        let ip = match parts.extensions.get::<ClientIp>() {
+            Some(ClientIp(ip)) => Some(ip.to_string()),
+            None => parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string()),
+        };
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        Ok(Self {
+            ip,
+            user_agent,
+            geo: parts.extensions.get::<GeoLocation>().cloned(),
+        })
+    }
+}
+
+/// Runs the request as part of the trace named in its `traceparent` header,
+/// or a new one, so the database calls and outbound requests it makes can be
+/// tied back to it.
+pub async fn propagate_trace<B>(request: Request<B>, next: Next<B>) -> Response {
+    let trace_id = request
+        .headers()
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(trace::parse_traceparent)
+        .map(str::to_owned)
+        .unwrap_or_else(trace::new_trace_id);
+
+    trace::scope(trace_id, next.run(request)).await
+}
+
+/// Works out the client address behind any trusted proxies, and where it is
+/// when geo-IP is enabled, and keeps both in the request's extensions, where
+/// [`ClientInfo`] picks them up.
+pub async fn resolve_client_ip<B>(
+    State(app_state): State<Arc<AppState>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(peer) = peer {
+        let headers = request.headers();
+        let forwarding = ForwardingHeaders {
+            client_ip: app_state
+                .proxies
+                .client_header()
+                .and_then(|name| headers.get(name))
+                .and_then(|value| value.to_str().ok()),
+            forwarded: header_values(headers, FORWARDED),
+            x_forwarded_for: header_values(headers, X_FORWARDED_FOR_HEADER),
+        };
+        let ip = app_state.proxies.resolve(peer, &forwarding);
+        request.extensions_mut().insert(ClientIp(ip));
+        if let Some(location) = app_state.geo.locate(ip) {
+            request.extensions_mut().insert(location);
+        }
+    }
+
+    next.run(request).await
+}
+
+fn header_values(headers: &HeaderMap, name: impl AsHeaderName) -> Vec<&str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect()
+}
+
+/// The caller behind a bearer token or `X-API-Key` header.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub sub: String,
+    pub scopes: Vec<String>,
+    /// The session a bearer token belongs to; API keys have none.
+    pub session_id: Option<String>,
+}
+
+impl AuthUser {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+
+    pub fn require_scope(&self, scope: &str) -> Result<(), MyError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(ForbiddenError(scope.to_owned()))
+        }
+    }
+
+    /// Lets users act on their own account while still letting admins act on anyone's.
+    pub fn require_self_or_scope(&self, user_id: &str, scope: &str) -> Result<(), MyError> {
+        if self.sub == user_id {
+            Ok(())
+        } else {
+            self.require_scope(scope)
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AuthUser {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(auth) = parts.extensions.get::<AuthUser>() {
+            return Ok(auth.clone());
+        }
+
+        authenticate(parts, state).await.map_err(|e| e.into())
+    }
+}
+
+async fn authenticate(parts: &Parts, state: &AppState) -> Result<AuthUser, MyError> {
+    if let Some(key) = header_str(parts, API_KEY_HEADER) {
+        let api_key = state
+            .db
+            .find_active_api_key(key)
+            .await?
+            .ok_or_else(|| UnauthorizedError("invalid API key".to_string()))?;
+
+        return Ok(AuthUser {
+            sub: api_key.userId.to_hex(),
+            scopes: api_key.scopes,
+            session_id: None,
+        });
+    }
+
+    let token = header_str(parts, AUTHORIZATION.as_str())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| UnauthorizedError("missing bearer token".to_string()))?;
+
+    let claims = state.tokens.verify(token)?;
+    if !state.db.is_session_active(&claims.jti).await? {
+        return Err(UnauthorizedError(
+            "session has expired or been revoked".to_string(),
+        ));
+    }
+
+    Ok(AuthUser {
+        scopes: scope::parse(claims.scope.as_deref()),
+        sub: claims.sub,
+        session_id: Some(claims.jti),
+    })
+}
+
+fn header_str<'a>(parts: &'a Parts, name: &str) -> Option<&'a str> {
+    parts
+        .headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// A service account calling with its client credentials in an HTTP Basic
+/// `Authorization` header, for endpoints other services call on their own
+/// behalf rather than a user's.
+#[derive(Debug, Clone)]
+pub struct ServiceClient {
+    pub scopes: Vec<String>,
+}
+
+impl ServiceClient {
+    pub fn require_scope(&self, scope: &str) -> Result<(), MyError> {
+        if self.scopes.iter().any(|granted| granted == scope) {
+            Ok(())
+        } else {
+            Err(ForbiddenError(scope.to_owned()))
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for ServiceClient {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        authenticate_client(parts, state)
+            .await
+            .map_err(|e| e.into())
+    }
+}
+
+async fn authenticate_client(parts: &Parts, state: &AppState) -> Result<ServiceClient, MyError> {
+    let (client_id, client_secret) = header_str(parts, AUTHORIZATION.as_str())
+        .and_then(basic_credentials)
+        .ok_or_else(|| UnauthorizedError("missing client credentials".to_string()))?;
+
+    let account = state
+        .db
+        .authenticate_client(&client_id, &client_secret)
+        .await?
+        .ok_or_else(|| UnauthorizedError("invalid client credentials".to_string()))?;
+
+    Ok(ServiceClient {
+        scopes: account.scopes,
+    })
+}
+
+fn basic_credentials(value: &str) -> Option<(String, String)> {
+    let encoded = value.strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(STANDARD.decode(encoded.trim()).ok()?).ok()?;
+    let (client_id, client_secret) = decoded.split_once(':')?;
+    Some((client_id.to_owned(), client_secret.to_owned()))
+}
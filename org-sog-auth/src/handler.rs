@@ -1,16 +1,39 @@
 use std::sync::Arc;
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    body::Bytes,
+    extract::{Form, Path, Query, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
     response::IntoResponse,
     Json,
 };
 
+use chrono::{Duration, TimeZone, Utc};
+use mongodb::bson::oid::ObjectId;
+use org_sog_core::{bot::Submission, db_metrics, read::ReadFrom};
+
 use crate::{
+    billing::{StripeEvent, Subscription},
+    db::MAGIC_LINK_TTL_MINUTES,
     error::MyError,
-    schema::{CreateUserSchema, FilterOptions, UpdateUserSchema},
-    AppState,
+    events, export,
+    extract::{AuthUser, ClientInfo, ServiceClient},
+    model::ExportStatus,
+    response::{ConsentPolicyResponse, GenericResponse, IntrospectionResponse, TokenResponse},
+    schema::{
+        AcceptInviteSchema, AddMemberSchema, BuildIndexSchema, CheckOptions, ConsentSchema,
+        CreateApiKeySchema, CreateInviteSchema, CreateOrgSchema, CreateServiceAccountSchema,
+        CreateUserSchema, FilterOptions, IndexAdviceOptions, IntrospectSchema, MagicLinkSchema,
+        OrgOptions, PreferencesSchema, ProfileOptions, ReplaySchema, ResolveUsersSchema,
+        RetireKeyOptions, SlowQueryOptions, TokenSchema, UpdateOrgSchema, UpdateUserSchema,
+    },
+    scope,
+    throttle::{self, Guarded},
+    token::OrgClaim,
+    AppState, FORM_SIGNUP,
 };
 
 pub async fn health_checker_handler() -> impl IntoResponse {
@@ -24,56 +47,542 @@ pub async fn health_checker_handler() -> impl IntoResponse {
     Json(json_response)
 }
 
+pub async fn dependencies_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "status": "success",
+        "dependencies": [app_state.db.breaker.metrics(), app_state.blog.breaker_metrics()],
+    }))
+}
+
+pub async fn metrics_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, db_metrics::CONTENT_TYPE)],
+        app_state.db.metrics.render(),
+    )
+}
+
+pub async fn jwks_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CACHE_CONTROL, "public, max-age=300")],
+        Json(app_state.tokens.jwks()),
+    )
+}
+
+pub async fn signing_key_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "keys": app_state.tokens.key_status(),
+    })))
+}
+
+pub async fn generate_signing_key_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.tokens.generate_key() {
+        Ok(kid) => Ok((
+            StatusCode::CREATED,
+            Json(serde_json::json!({
+                "status": "success",
+                "kid": kid,
+                "keys": app_state.tokens.key_status(),
+            })),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn activate_signing_key_handler(
+    auth: AuthUser,
+    Path(kid): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.tokens.activate_key(&kid) {
+        Ok(()) => Ok(Json(serde_json::json!({
+            "status": "success",
+            "keys": app_state.tokens.key_status(),
+        }))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn retire_signing_key_handler(
+    auth: AuthUser,
+    Path(kid): Path<String>,
+    opts: Option<Query<RetireKeyOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .tokens
+        .retire_key(&kid, opts.force.unwrap_or(false))
+    {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub async fn user_list_handler(
+    auth: AuthUser,
     opts: Option<Query<FilterOptions>>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
     let Query(opts) = opts.unwrap_or_default();
 
-    let limit = opts.limit.unwrap_or(10) as i64;
-    let page = opts.page.unwrap_or(1) as i64;
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
 
-    match app_state
-        .db
-        .fetch_users(limit, page)
-        .await
-        .map_err(MyError::from)
-    {
+    match app_state.db.fetch_users(paging, ReadFrom::Replica).await {
         Ok(res) => Ok(Json(res)),
         Err(e) => Err(e.into()),
     }
 }
 
 pub async fn create_user_handler(
+    client: ClientInfo,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<CreateUserSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state.db.create_user(&body).await.map_err(MyError::from) {
+    if app_state.config.invite_only {
+        return Err(MyError::RegistrationClosedError.into());
+    }
+    let submission = Submission {
+        honeypot: Some(&body.website),
+        stamp: body.formStamp.as_deref(),
+        captcha_token: body.captchaToken.as_deref(),
+        ip: client.ip.as_deref(),
+    };
+    if app_state
+        .bots
+        .check(FORM_SIGNUP, &submission)
+        .await
+        .is_err()
+    {
+        return Err(MyError::ChallengeFailedError.into());
+    }
+    if let Err(e) = app_state.config.names.check_name(&body.name) {
+        return Err(MyError::from(e).into());
+    }
+
+    let scopes: Vec<String> = scope::DEFAULT.iter().map(|s| s.to_string()).collect();
+    match app_state.db.create_user(&body, &scopes).await {
         Ok(res) => Ok((StatusCode::CREATED, Json(res))),
         Err(e) => Err(e.into()),
     }
 }
 
+pub async fn form_stamp_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CACHE_CONTROL, "no-store")],
+        Json(serde_json::json!({
+            "status": "success",
+            "stamp": app_state.bots.stamp(),
+        })),
+    )
+}
+
+pub async fn bot_metrics_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "blocked": app_state.bots.metrics(),
+    })))
+}
+
+pub async fn throttled_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "throttled": app_state.throttle.throttled().await,
+        "endpoints": app_state.throttle.metrics().await,
+    })))
+}
+
+pub async fn check_user_handler(
+    Query(opts): Query<CheckOptions>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let (field, value) = match (opts.name, opts.uid) {
+        (Some(name), _) if !name.trim().is_empty() => ("name", name),
+        (_, Some(uid)) if !uid.trim().is_empty() => ("uid", uid),
+        _ => return Err(MyError::MissingParamError("name or uid".to_string()).into()),
+    };
+    if field == "name" {
+        if let Err(e) = app_state.config.names.check_name(&value) {
+            return Err(MyError::from(e).into());
+        }
+    }
+
+    match app_state.db.check_availability(field, value.trim()).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Most names a single resolve request may look up.
+const MAX_RESOLVE_NAMES: usize = 20;
+
+pub async fn resolve_users_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<ResolveUsersSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if body.names.len() + body.ids.len() > MAX_RESOLVE_NAMES {
+        return Err(MyError::ValidationError(format!(
+            "at most {} users can be resolved at once",
+            MAX_RESOLVE_NAMES
+        ))
+        .into());
+    }
+
+    match app_state.db.resolve_users(&body.names, &body.ids).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// An author page as its user chose to show it, for the blog.
+pub async fn public_profile_handler(
+    Query(opts): Query<ProfileOptions>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .public_profile(&opts.name, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub async fn get_user_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.get_user(&id, ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn user_logins_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    opts: Option<Query<FilterOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state.db.fetch_logins(&id, paging).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn export_user_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    let (export, created) = match app_state.db.start_export(&id).await {
+        Ok(started) => started,
+        Err(e) => return Err(e.into()),
+    };
+
+    if created {
+        tokio::spawn(export::run(app_state.clone(), export.id, export.userId));
+    }
+
+    let status = match export.status {
+        ExportStatus::Ready => StatusCode::OK,
+        _ => StatusCode::ACCEPTED,
+    };
+    Ok((status, Json(app_state.db.export_response(&export))))
+}
+
+pub async fn export_status_handler(
+    auth: AuthUser,
+    Path((id, export_id)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.get_export(&id, &export_id).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn export_download_handler(
+    auth: AuthUser,
+    Path((id, export_id)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.export_archive(&id, &export_id).await {
+        Ok(archive) => Ok((
+            [
+                (CONTENT_TYPE, "application/json".to_string()),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"user-{}-export.json\"", id),
+                ),
+            ],
+            archive,
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn anonymize_user_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.anonymize_user(&id, &auth.sub).await {
+        Ok(res) => {
+            // Don't make the caller wait on the blog; the dispatcher retries anyway.
+            tokio::spawn(async move { events::dispatch(&app_state).await });
+            Ok(Json(res))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn consent_policy_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(ConsentPolicyResponse {
+        status: "success",
+        enforced: app_state.config.enforce_consent,
+        current: app_state.config.current_consents(),
+    })
+}
+
+pub async fn accept_consent_handler(
+    auth: AuthUser,
     Path(id): Path<String>,
     State(app_state): State<Arc<AppState>>,
+    Json(body): Json<ConsentSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state.db.get_user(&id).await.map_err(MyError::from) {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    // Only the version currently in force can be accepted.
+    match app_state.config.current_version(body.document) {
+        Some(version) if version == body.version => {}
+        Some(version) => {
+            return Err(MyError::ValidationError(format!(
+                "version {} is not current, the current version is {}",
+                body.version, version
+            ))
+            .into())
+        }
+        None => {
+            return Err(MyError::ValidationError(
+                "no version of that document is published".to_string(),
+            )
+            .into())
+        }
+    }
+
+    match app_state.db.accept_consent(&id, &body).await {
         Ok(res) => Ok(Json(res)),
         Err(e) => Err(e.into()),
     }
 }
 
 pub async fn edit_user_handler(
+    auth: AuthUser,
     Path(id): Path<String>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateUserSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_self_or_scope(&id, scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+    if let Err(e) = check_user_update(&auth, &app_state, &body) {
+        return Err(e.into());
+    }
+
+    match app_state.db.edit_user(&id, &body).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The checks on a user edit beyond who may make it.
+fn check_user_update(
+    auth: &AuthUser,
+    app_state: &AppState,
+    body: &UpdateUserSchema,
+) -> Result<(), MyError> {
+    if let Some(name) = &body.name {
+        app_state.config.names.check_name(name)?;
+    }
+
+    // Users may edit their own profile, but granting scopes is an admin action.
+    if let Some(scopes) = &body.scopes {
+        auth.require_scope(scope::USERS_ADMIN)?;
+        if let Some(unknown) = scopes.iter().find(|s| !scope::is_known(s)) {
+            return Err(MyError::InvalidScopeError(unknown.to_owned()));
+        }
+    }
+    Ok(())
+}
+
+/// The caller's own account, so clients needn't know their user id.
+pub async fn get_me_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.get_user(&auth.sub, ReadFrom::Primary).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn edit_me_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateUserSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = check_user_update(&auth, &app_state, &body) {
+        return Err(e.into());
+    }
+
+    match app_state.db.edit_user(&auth.sub, &body).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn my_sessions_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .fetch_sessions(&auth.sub, auth.session_id.as_deref())
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn revoke_my_session_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.revoke_session(&auth.sub, &id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_preferences_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     match app_state
         .db
-        .edit_user(&id, &body)
+        .get_preferences(&auth.sub, ReadFrom::Primary)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn update_preferences_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<PreferencesSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.update_preferences(&auth.sub, &body).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The settings the user ends up with once org and site defaults are filled
+/// in, and where each came from.
+pub async fn effective_settings_handler(
+    auth: AuthUser,
+    opts: Option<Query<OrgOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .settings
+        .resolve(&app_state.db, &auth.sub, opts.org.as_deref())
         .await
-        .map_err(MyError::from)
     {
         Ok(res) => Ok(Json(res)),
         Err(e) => Err(e.into()),
@@ -81,10 +590,882 @@ pub async fn edit_user_handler(
 }
 
 pub async fn delete_user_handler(
+    auth: AuthUser,
     Path(id): Path<String>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state.db.delete_user(&id).await.map_err(MyError::from) {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_user(&id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn magic_link_handler(
+    client: ClientInfo,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<MagicLinkSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let identities = [
+        throttle::ip_identity(client.ip.as_deref()),
+        throttle::account_identity(&body.email),
+    ];
+    if let Err(e) = app_state
+        .throttle
+        .check(Guarded::MagicLink, &identities)
+        .await
+    {
+        return Err(e.into());
+    }
+    // Every request may send mail, so each one counts.
+    app_state
+        .throttle
+        .failure(Guarded::MagicLink, &identities)
+        .await;
+
+    let user = match app_state.db.find_user_by_email(&body.email).await {
+        Ok(user) => user,
+        Err(e) => return Err(e.into()),
+    };
+
+    // Respond identically whether or not the address is known so the endpoint
+    // cannot be used to enumerate accounts.
+    if let Some(user) = user {
+        let token = match app_state.db.create_magic_link(user.id).await {
+            Ok(token) => token,
+            Err(e) => return Err(e.into()),
+        };
+
+        let link = format!("{}/{}", app_state.config.magic_link_url, token);
+        let text = format!(
+            "Hi {},\n\nUse the link below to sign in. It expires in {} minutes and can only be used once.\n\n{}\n",
+            user.name, MAGIC_LINK_TTL_MINUTES, link
+        );
+
+        if let Err(e) = app_state
+            .mailer
+            .send(&body.email, "Your sign-in link", text)
+            .await
+        {
+            return Err(MyError::from(e).into());
+        }
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(GenericResponse {
+            status: "success".to_string(),
+            message: "If an account exists for that address, a sign-in link has been sent"
+                .to_string(),
+        }),
+    ))
+}
+
+pub async fn magic_link_exchange_handler(
+    Path(token): Path<String>,
+    opts: Option<Query<OrgOptions>>,
+    client: ClientInfo,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    // Links aren't tied to anything but themselves, so guesses are only
+    // counted by address.
+    let identities = [throttle::ip_identity(client.ip.as_deref())];
+    if let Err(e) = app_state
+        .throttle
+        .check(Guarded::MagicExchange, &identities)
+        .await
+    {
+        return Err(e.into());
+    }
+
+    let user_id = match app_state.db.redeem_magic_link(&token, &client).await {
+        Ok(user_id) => user_id,
+        Err(e @ MyError::UnauthorizedError(_)) => {
+            app_state
+                .throttle
+                .failure(Guarded::MagicExchange, &identities)
+                .await;
+            return Err(e.into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let user = match app_state.db.find_user_by_id(&user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(MyError::NotFoundError(user_id.to_hex()).into()),
+        Err(e) => return Err(e.into()),
+    };
+    let scopes = user.scopes.unwrap_or_default();
+
+    // An explicit ?org= must be one of the user's orgs; otherwise fall back
+    // to their oldest membership, if any.
+    let org = match opts.org {
+        Some(org_id) => match app_state
+            .db
+            .membership_role(&org_id, &user_id.to_hex())
+            .await
+        {
+            Ok(Some(role)) => Some(OrgClaim { id: org_id, role }),
+            Ok(None) => return Err(MyError::ForbiddenError(format!("org:{}", org_id)).into()),
+            Err(e) => return Err(e.into()),
+        },
+        None => match app_state.db.default_membership(&user_id).await {
+            Ok(membership) => membership.map(|membership| OrgClaim {
+                id: membership.orgId.to_hex(),
+                role: membership.role,
+            }),
+            Err(e) => return Err(e.into()),
+        },
+    };
+
+    let plan = match &org {
+        Some(org) => match app_state.db.org_plan(&org.id).await {
+            Ok(plan) => plan,
+            Err(e) => return Err(e.into()),
+        },
+        None => user.plan,
+    };
+
+    let session_id = ObjectId::new();
+    let (access_token, _claims) =
+        match app_state
+            .tokens
+            .issue(&user_id.to_hex(), &session_id.to_hex(), &scopes, org, plan)
+        {
+            Ok(issued) => issued,
+            Err(e) => return Err(e.into()),
+        };
+
+    let expires_at = Utc::now() + app_state.tokens.ttl;
+    match app_state
+        .db
+        .create_session(session_id, user_id, &client, expires_at)
+        .await
+    {
+        Ok(_) => Ok(Json(TokenResponse {
+            status: "success",
+            access_token,
+            token_type: "Bearer",
+            expires_in: app_state.tokens.ttl.num_seconds(),
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn introspect_handler(
+    caller: ServiceClient,
+    State(app_state): State<Arc<AppState>>,
+    Form(body): Form<IntrospectSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = caller.require_scope(scope::AUTH_INTROSPECT) {
+        return Err(e.into());
+    }
+
+    // API keys carry their prefix, so any `token_type_hint` is not needed.
+    if body.token.starts_with("sog_") {
+        let api_key = match app_state.db.find_active_api_key(&body.token).await {
+            Ok(Some(api_key)) => api_key,
+            Ok(None) => return Ok(Json(IntrospectionResponse::default())),
+            Err(e) => return Err(e.into()),
+        };
+        return match app_state.db.find_user_by_id(&api_key.userId).await {
+            Ok(Some(user)) => Ok(Json(IntrospectionResponse {
+                active: true,
+                sub: Some(api_key.userId.to_hex()),
+                client_id: None,
+                org: None,
+                plan: Some(user.plan),
+                scope: Some(api_key.scopes.join(" ")),
+                jti: Some(api_key.id.to_hex()),
+                iat: Some(api_key.createdAt.timestamp()),
+                exp: None,
+                token_type: Some("api_key"),
+            })),
+            Ok(None) => Ok(Json(IntrospectionResponse::default())),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    // Per RFC 7662 an unusable token is not an error, it is simply inactive.
+    let claims = match app_state.tokens.verify(&body.token) {
+        Ok(claims) => claims,
+        Err(_) => return Ok(Json(IntrospectionResponse::default())),
+    };
+
+    match app_state.db.is_session_active(&claims.jti).await {
+        Ok(true) => Ok(Json(IntrospectionResponse {
+            active: true,
+            sub: Some(claims.sub),
+            client_id: claims.client_id,
+            org: claims.org,
+            plan: claims.plan,
+            scope: Some(claims.scope.unwrap_or_default()),
+            jti: Some(claims.jti),
+            iat: Some(claims.iat),
+            exp: Some(claims.exp),
+            token_type: Some("Bearer"),
+        })),
+        Ok(false) => Ok(Json(IntrospectionResponse::default())),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_api_key_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateApiKeySchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    // A key can never carry more authority than the account that minted it.
+    let scopes = body.scopes.unwrap_or_else(|| auth.scopes.to_owned());
+    for requested in &scopes {
+        if !scope::is_known(requested) {
+            return Err(MyError::InvalidScopeError(requested.to_owned()).into());
+        }
+        if let Err(e) = auth.require_scope(requested) {
+            return Err(e.into());
+        }
+    }
+
+    match app_state
+        .db
+        .create_api_key(&auth.sub, &body.name, scopes)
+        .await
+    {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn api_key_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.fetch_api_keys(&auth.sub).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn revoke_api_key_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.revoke_api_key(&auth.sub, &id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_service_account_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateServiceAccountSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+    if let Some(unknown) = body.scopes.iter().find(|s| !scope::is_known(s)) {
+        return Err(MyError::InvalidScopeError(unknown.to_owned()).into());
+    }
+
+    match app_state
+        .db
+        .create_service_account(&body.name, body.scopes)
+        .await
+    {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn service_account_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.fetch_service_accounts().await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn rotate_service_account_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.rotate_service_account_secret(&id).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn admin_stats_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.admin_stats().await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn slow_query_list_handler(
+    auth: AuthUser,
+    opts: Option<Query<SlowQueryOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state.db.fetch_slow_queries(&opts, paging).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn index_advice_handler(
+    auth: AuthUser,
+    opts: Option<Query<IndexAdviceOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state.db.index_advice(&opts).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn index_build_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.index_builds().await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn build_index_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<BuildIndexSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.start_index_build(body).await {
+        Ok(res) => Ok((StatusCode::ACCEPTED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn dead_letter_list_handler(
+    auth: AuthUser,
+    opts: Option<Query<FilterOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state.db.fetch_dead_letters(paging).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_dead_letter_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.get_dead_letter(&id).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn replay_dead_letters_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<ReplaySchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.replay_dead_letters(&body.ids).await {
+        Ok(res) => {
+            tokio::spawn(async move { events::dispatch(&app_state).await });
+            Ok(Json(res))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn token_handler(
+    client: ClientInfo,
+    State(app_state): State<Arc<AppState>>,
+    Form(body): Form<TokenSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if body.grant_type != "client_credentials" {
+        return Err(MyError::UnsupportedGrantError(body.grant_type).into());
+    }
+
+    let identities = [
+        throttle::ip_identity(client.ip.as_deref()),
+        throttle::account_identity(&body.client_id),
+    ];
+    if let Err(e) = app_state.throttle.check(Guarded::Token, &identities).await {
+        return Err(e.into());
+    }
+
+    let account = match app_state
+        .db
+        .authenticate_client(&body.client_id, &body.client_secret)
+        .await
+    {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            app_state
+                .throttle
+                .failure(Guarded::Token, &identities)
+                .await;
+            return Err(
+                MyError::UnauthorizedError("invalid client credentials".to_string()).into(),
+            );
+        }
+        Err(e) => return Err(e.into()),
+    };
+    app_state
+        .throttle
+        .success(Guarded::Token, &identities[1])
+        .await;
+
+    // Clients may ask for a narrower token than their account allows, never a wider one.
+    let scopes = match body.scope.as_deref() {
+        Some(requested) => {
+            let requested = scope::parse(Some(requested));
+            if let Some(extra) = requested.iter().find(|s| !account.scopes.contains(s)) {
+                return Err(MyError::ForbiddenError(extra.to_owned()).into());
+            }
+            requested
+        }
+        None => account.scopes.to_owned(),
+    };
+
+    let session_id = ObjectId::new();
+    let (access_token, _claims) = match app_state.tokens.issue_for_client(
+        &account.clientId,
+        &account.id.to_hex(),
+        &session_id.to_hex(),
+        &scopes,
+    ) {
+        Ok(issued) => issued,
+        Err(e) => return Err(e.into()),
+    };
+
+    let expires_at = Utc::now() + app_state.tokens.ttl;
+    match app_state
+        .db
+        .create_session(session_id, account.id, &client, expires_at)
+        .await
+    {
+        Ok(_) => Ok(Json(TokenResponse {
+            status: "success",
+            access_token,
+            token_type: "Bearer",
+            expires_in: app_state.tokens.ttl.num_seconds(),
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_invite_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateInviteSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::USERS_ADMIN) {
+        return Err(e.into());
+    }
+    if scope::for_role(&body.role).is_none() {
+        return Err(MyError::InvalidRoleError(body.role).into());
+    }
+
+    let expires_in = Duration::hours(body.expires_in_hours.unwrap_or(72).clamp(1, 720));
+
+    let res = match app_state
+        .db
+        .create_invite(
+            &auth.sub,
+            &body.role,
+            body.email.as_deref(),
+            expires_in,
+            &app_state.config.invite_url,
+        )
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => return Err(e.into()),
+    };
+
+    if let Some(email) = &body.email {
+        let text = format!(
+            "You have been invited to join as {}.\n\nAccept the invite here:\n\n{}\n",
+            body.role, res.data.url
+        );
+        if let Err(e) = app_state.mailer.send(email, "You're invited", text).await {
+            return Err(MyError::from(e).into());
+        }
+    }
+
+    Ok((StatusCode::CREATED, Json(res)))
+}
+
+pub async fn get_invite_handler(
+    Path(token): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.get_invite(&token).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn accept_invite_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<AcceptInviteSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = app_state.config.names.check_name(&body.name) {
+        return Err(MyError::from(e).into());
+    }
+
+    match app_state.db.accept_invite(&body).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Passes if the caller holds one of `roles` in the org, or is a users admin.
+async fn require_org_role(
+    app_state: &AppState,
+    auth: &AuthUser,
+    org_id: &str,
+    roles: &[&str],
+) -> Result<(), MyError> {
+    if auth.has_scope(scope::USERS_ADMIN) {
+        return Ok(());
+    }
+
+    match app_state.db.membership_role(org_id, &auth.sub).await? {
+        Some(role) if roles.contains(&role.as_str()) => Ok(()),
+        Some(_) => Err(MyError::ForbiddenError(format!("org:{}", roles.join("|")))),
+        None => Err(MyError::NotFoundError(org_id.to_string())),
+    }
+}
+
+fn validate_org_slug(slug: &str) -> Result<(), MyError> {
+    let valid = !slug.is_empty()
+        && slug.len() <= 64
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(MyError::ValidationError(
+            "slug must be 1-64 lowercase letters, digits or dashes".to_string(),
+        ))
+    }
+}
+
+pub async fn create_org_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateOrgSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = validate_org_slug(&body.slug) {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_org(&auth.sub, &body).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn org_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .fetch_orgs_for_user(&auth.sub, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_org_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = require_org_role(&app_state, &auth, &id, scope::ORG_ROLES).await {
+        return Err(e.into());
+    }
+
+    match app_state.db.get_org(&id, ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn edit_org_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateOrgSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = require_org_role(&app_state, &auth, &id, scope::ORG_MANAGERS).await {
+        return Err(e.into());
+    }
+    if let Some(slug) = &body.slug {
+        if let Err(e) = validate_org_slug(slug) {
+            return Err(e.into());
+        }
+    }
+
+    match app_state.db.edit_org(&id, &body).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_org_preferences_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = require_org_role(&app_state, &auth, &id, scope::ORG_ROLES).await {
+        return Err(e.into());
+    }
+
+    match app_state
+        .db
+        .get_org_preferences(&id, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn update_org_preferences_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<PreferencesSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = require_org_role(&app_state, &auth, &id, scope::ORG_MANAGERS).await {
+        return Err(e.into());
+    }
+
+    match app_state.db.update_org_preferences(&id, &body).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_org_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = require_org_role(&app_state, &auth, &id, &["owner"]).await {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_org(&id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn org_members_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = require_org_role(&app_state, &auth, &id, scope::ORG_ROLES).await {
+        return Err(e.into());
+    }
+
+    match app_state.db.fetch_members(&id).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn add_org_member_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<AddMemberSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if !scope::ORG_ROLES.contains(&body.role.as_str()) {
+        return Err(MyError::InvalidRoleError(body.role).into());
+    }
+
+    let current = match app_state.db.membership_role(&id, &body.user_id).await {
+        Ok(current) => current,
+        Err(e) => return Err(e.into()),
+    };
+
+    // Only owners can mint other owners, or change the role of one.
+    let required: &[&str] = if body.role == "owner" || current.as_deref() == Some("owner") {
+        &["owner"]
+    } else {
+        scope::ORG_MANAGERS
+    };
+    if let Err(e) = require_org_role(&app_state, &auth, &id, required).await {
+        return Err(e.into());
+    }
+
+    match app_state
+        .db
+        .add_member(&id, &body.user_id, &body.role)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn remove_org_member_handler(
+    auth: AuthUser,
+    Path((id, user_id)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    // Members can always leave; removing someone else takes a manager, and
+    // removing an owner takes another owner.
+    if auth.sub != user_id {
+        let required: &[&str] = match app_state.db.membership_role(&id, &user_id).await {
+            Ok(Some(role)) if role == "owner" => &["owner"],
+            Ok(_) => scope::ORG_MANAGERS,
+            Err(e) => return Err(e.into()),
+        };
+        if let Err(e) = require_org_role(&app_state, &auth, &id, required).await {
+            return Err(e.into());
+        }
+    }
+
+    match app_state.db.remove_member(&id, &user_id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Receives Stripe events and keeps account plans in line with their
+/// subscriptions. Events other than subscription changes are acknowledged
+/// and ignored.
+pub async fn stripe_webhook_handler(
+    headers: HeaderMap,
+    State(app_state): State<Arc<AppState>>,
+    body: Bytes,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let signature = headers
+        .get("stripe-signature")
+        .and_then(|value| value.to_str().ok());
+    if let Err(e) = app_state.billing.verify(signature, &body) {
+        return Err(e.into());
+    }
+
+    let event: StripeEvent = match serde_json::from_slice(&body) {
+        Ok(event) => event,
+        Err(e) => return Err(MyError::ValidationError(e.to_string()).into()),
+    };
+
+    let deleted = match event.kind.as_str() {
+        "customer.subscription.created" | "customer.subscription.updated" => false,
+        "customer.subscription.deleted" => true,
+        _ => return Ok(StatusCode::NO_CONTENT),
+    };
+
+    let subscription: Subscription = match serde_json::from_value(event.data.object) {
+        Ok(subscription) => subscription,
+        Err(e) => return Err(MyError::ValidationError(e.to_string()).into()),
+    };
+
+    let Some(plan) = app_state.billing.plan_for(&subscription, deleted) else {
+        println!(
+            "⚠️ Stripe event {} is for a price that isn't a plan, ignoring it",
+            event.id
+        );
+        return Ok(StatusCode::NO_CONTENT);
+    };
+
+    let at = match Utc.timestamp_opt(event.created, 0).single() {
+        Some(at) => at,
+        None => return Err(MyError::ValidationError("invalid event time".to_string()).into()),
+    };
+
+    match app_state
+        .db
+        .set_plan(&subscription.account(), &subscription.customer, plan, at)
+        .await
+    {
         Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => Err(e.into()),
     }
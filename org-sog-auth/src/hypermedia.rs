@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use axum::{
+    body::{boxed, Full},
+    extract::State,
+    http::{
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderValue, Request,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use org_sog_core::links::{Hypermedia, LinkBuilder, Resource};
+
+use crate::AppState;
+
+/// Links for users; their posts link into the blog service.
+pub fn links() -> LinkBuilder {
+    let public_url =
+        std::env::var("PUBLIC_URL").unwrap_or_else(|_| "http://localhost:8000".to_string());
+    let blog_url =
+        std::env::var("BLOG_URL").unwrap_or_else(|_| "http://localhost:8001".to_string());
+    let blog_url = blog_url.trim_end_matches('/');
+
+    LinkBuilder::new(&public_url).resource(
+        Resource::new("users", "user", "users", "/api/users/{id}")
+            .relation("posts", &format!("{}/api/blog?author={{id}}", blog_url))
+            .relation("logins", "/api/users/{id}/logins"),
+    )
+}
+
+/// Rewraps successful responses as HAL or JSON:API, with links, when
+/// `Accept` asks for one; everything else passes through as plain JSON.
+pub async fn wrap<B>(
+    State(app_state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let mode = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Hypermedia::from_accept);
+    let path_and_query = request.uri().path_and_query().map_or_else(
+        || request.uri().path().to_owned(),
+        |pq| pq.as_str().to_owned(),
+    );
+
+    let response = next.run(request).await;
+    let Some(mode) = mode else {
+        return response;
+    };
+    if !response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(_) => return parts.status.into_response(),
+    };
+    let wrapped = serde_json::from_slice(&bytes)
+        .ok()
+        .and_then(|body| app_state.links.wrap(mode, &path_and_query, body));
+    let Some(wrapped) = wrapped else {
+        return Response::from_parts(parts, boxed(Full::from(bytes)));
+    };
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_TYPE, HeaderValue::from_static(mode.content_type()));
+    let body = serde_json::to_vec(&wrapped).unwrap_or_default();
+    Response::from_parts(parts, boxed(Full::from(body)))
+}
@@ -0,0 +1,17 @@
+use axum::{
+    http::{header::ACCEPT_LANGUAGE, Request},
+    middleware::Next,
+    response::Response,
+};
+use org_sog_core::i18n;
+
+/// Words error messages in the language `Accept-Language` asks for.
+pub async fn localize<B>(request: Request<B>, next: Next<B>) -> Response {
+    let accept_language = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+
+    i18n::scope(accept_language.as_deref(), next.run(request)).await
+}
@@ -1,44 +1,146 @@
+mod billing;
+mod blog;
+mod config;
+mod consent;
+mod dates;
 mod db;
+mod encoding;
 mod error;
+mod events;
+mod export;
+mod extract;
 mod handler;
+mod hypermedia;
+mod limits;
+mod locale;
 mod model;
 mod response;
 mod route;
 mod schema;
+mod scope;
+mod settings;
+mod throttle;
+mod token;
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::http::{
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-    HeaderValue, Method,
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+        HeaderName, HeaderValue, Method,
+    },
+    middleware,
 };
+use billing::StripeBilling;
+use blog::BlogClient;
+use config::Config;
 use db::DB;
 use dotenv::dotenv;
 use error::MyError;
+use limits::RequestLimits;
+use org_sog_core::bot::{BotCheck, BotDefense};
+use org_sog_core::client_ip::TrustedProxies;
+use org_sog_core::credentials;
+use org_sog_core::geo::Geo;
+use org_sog_core::i18n;
+use org_sog_core::links::LinkBuilder;
+use org_sog_core::mail::Mailer;
+use org_sog_core::page::PageLimits;
 use route::create_router;
-use tower_http::cors::CorsLayer;
+use settings::SettingsResolver;
+use throttle::LoginThrottle;
+use token::TokenService;
+use tower_http::{cors::CorsLayer, timeout::TimeoutLayer};
 
 pub struct AppState {
     db: DB,
+    mailer: Mailer,
+    tokens: TokenService,
+    config: Config,
+    blog: BlogClient,
+    paging: PageLimits,
+    links: LinkBuilder,
+    billing: StripeBilling,
+    proxies: TrustedProxies,
+    geo: Geo,
+    bots: BotDefense,
+    throttle: LoginThrottle,
+    settings: SettingsResolver,
 }
 
+/// Forms run through bot checks, see [`BotDefense`].
+pub const FORM_SIGNUP: &str = "signup";
+
 #[tokio::main]
 async fn main() -> Result<(), MyError> {
     dotenv().ok();
+    i18n::init();
+    credentials::init();
+
+    // `org-sog-auth keys ...` manages signing keys instead of serving.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("keys") {
+        return token::run_cli(&args[1..]);
+    }
 
     let db = DB::init().await?;
+    let mailer = Mailer::init()?;
+    let tokens = TokenService::init();
+    let config = Config::init();
+    let blog = BlogClient::init();
+    let limits = RequestLimits::init();
 
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:8000".parse::<HeaderValue>().unwrap())
         .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
         .allow_credentials(true)
-        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
+        .allow_headers([
+            AUTHORIZATION,
+            ACCEPT,
+            CONTENT_TYPE,
+            HeaderName::from_static(extract::API_KEY_HEADER),
+        ]);
+
+    let app_state = Arc::new(AppState {
+        db: db.clone(),
+        mailer,
+        tokens,
+        config,
+        blog,
+        paging: PageLimits::init(),
+        links: hypermedia::links(),
+        billing: StripeBilling::init(),
+        proxies: TrustedProxies::init(),
+        geo: Geo::init(),
+        bots: BotDefense::init(&[(FORM_SIGNUP, &[BotCheck::Honeypot, BotCheck::Captcha])]),
+        throttle: LoginThrottle::init(),
+        settings: SettingsResolver::init(),
+    });
+    events::spawn_dispatcher(app_state.clone());
+    token::spawn_reloader(app_state.tokens.clone());
 
-    let app = create_router(Arc::new(AppState { db: db.clone() })).layer(cors);
+    let app = create_router(app_state.clone())
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            hypermedia::wrap,
+        ))
+        .layer(middleware::from_fn(encoding::negotiate))
+        .layer(middleware::from_fn(dates::negotiate))
+        .layer(middleware::from_fn_with_state(
+            app_state,
+            extract::resolve_client_ip,
+        ))
+        .layer(DefaultBodyLimit::max(limits.json_body))
+        .layer(TimeoutLayer::new(limits.timeout))
+        .layer(middleware::from_fn(limits::envelope_errors))
+        .layer(middleware::from_fn(locale::localize))
+        .layer(middleware::from_fn(extract::propagate_trace))
+        .layer(cors);
 
     println!("🚀 Auth API started successfully");
     axum::Server::bind(&"0.0.0.0:8000".parse().unwrap())
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 
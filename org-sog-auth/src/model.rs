@@ -1,5 +1,7 @@
 use chrono::prelude::*;
-use mongodb::bson::{self, oid::ObjectId};
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+use org_sog_core::repo::{Keyed, UniqueKey};
+use org_sog_core::{geo::GeoLocation, plan::Plan};
 use serde::{Deserialize, Serialize};
 
 #[allow(non_snake_case)]
@@ -9,8 +11,357 @@ pub struct UserModel {
     pub id: ObjectId,
     pub name: String,
     pub uid: String,
+    pub email: Option<String>,
+    pub scopes: Option<Vec<String>>,
+    pub consents: Option<Vec<ConsentModel>>,
+    pub anonymizedAt: Option<bson::DateTime>,
+    #[serde(default)]
+    pub plan: Plan,
+    pub stripeCustomerId: Option<String>,
+    pub planChangedAt: Option<bson::DateTime>,
+    #[serde(default)]
+    pub profile: ProfileModel,
+    #[serde(default)]
+    pub privacy: ProfilePrivacyModel,
+    #[serde(default)]
+    pub preferences: PreferencesModel,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub createdAt: DateTime<Utc>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub updatedAt: DateTime<Utc>,
 }
+
+impl Keyed for UserModel {
+    const UNIQUE_KEYS: &'static [&'static [&'static str]] = &[UserName::FIELDS, UserUid::FIELDS];
+}
+
+/// Looks a user up by the name they go by.
+#[derive(Debug)]
+pub enum UserName {}
+
+impl UniqueKey for UserName {
+    type Model = UserModel;
+    type Value<'a> = &'a str;
+
+    const FIELDS: &'static [&'static str] = &["name"];
+
+    fn filter(name: &str) -> Document {
+        doc! {"name": name}
+    }
+}
+
+/// Looks a user up by their `uid`.
+#[derive(Debug)]
+pub enum UserUid {}
+
+impl UniqueKey for UserUid {
+    type Model = UserModel;
+    type Value<'a> = &'a str;
+
+    const FIELDS: &'static [&'static str] = &["uid"];
+
+    fn filter(uid: &str) -> Document {
+        doc! {"uid": uid}
+    }
+}
+
+/// What a user tells readers about themselves on their author page.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ProfileModel {
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub location: Option<String>,
+    pub avatarUrl: Option<String>,
+}
+
+/// Which parts of their author page a user shows. What they wrote for it is
+/// shown unless hidden; where they are is hidden unless shown.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct ProfilePrivacyModel {
+    /// Whether they have an author page at all.
+    #[serde(default = "shown")]
+    pub listed: bool,
+    #[serde(default = "shown")]
+    pub bio: bool,
+    #[serde(default = "shown")]
+    pub website: bool,
+    #[serde(default)]
+    pub location: bool,
+    #[serde(default = "shown")]
+    pub avatar: bool,
+    #[serde(default = "shown")]
+    pub joinedAt: bool,
+    #[serde(default = "shown")]
+    pub recentPosts: bool,
+    #[serde(default = "shown")]
+    pub stats: bool,
+}
+
+impl Default for ProfilePrivacyModel {
+    fn default() -> Self {
+        Self {
+            listed: true,
+            bio: true,
+            website: true,
+            location: false,
+            avatar: true,
+            joinedAt: true,
+            recentPosts: true,
+            stats: true,
+        }
+    }
+}
+
+fn shown() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// How a user likes the apps to behave for them, kept with their account
+/// so it follows them across devices. Orgs keep the same set as defaults for
+/// their members; anything left unset is inherited, see
+/// [`SettingsResolver`](crate::settings::SettingsResolver).
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PreferencesModel {
+    /// A language tag such as `de` or `pt-BR`.
+    pub locale: Option<String>,
+    /// An IANA time zone such as `Europe/Berlin`.
+    pub timezone: Option<String>,
+    pub theme: Option<Theme>,
+    pub emailNotifications: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsentDocument {
+    Tos,
+    Privacy,
+}
+
+/// A user's acceptance of one version of a legal document.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConsentModel {
+    pub document: ConsentDocument,
+    pub version: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub acceptedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginOutcome {
+    Success,
+    Failure,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LoginHistoryModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: Option<ObjectId>,
+    pub ip: Option<String>,
+    pub userAgent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoLocation>,
+    pub outcome: LoginOutcome,
+    pub reason: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MagicLinkModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: ObjectId,
+    pub tokenHash: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiresAt: DateTime<Utc>,
+    pub usedAt: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: ObjectId,
+    pub ip: Option<String>,
+    pub userAgent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoLocation>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiresAt: DateTime<Utc>,
+    pub revokedAt: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiKeyModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: ObjectId,
+    pub name: String,
+    pub prefix: String,
+    pub keyHash: String,
+    pub scopes: Vec<String>,
+    pub lastUsedAt: Option<bson::DateTime>,
+    pub revokedAt: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServiceAccountModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+    pub clientId: String,
+    pub secretHash: String,
+    pub scopes: Vec<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub secretRotatedAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InviteModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub tokenHash: String,
+    pub email: Option<String>,
+    pub role: String,
+    pub invitedBy: ObjectId,
+    pub acceptedBy: Option<ObjectId>,
+    pub acceptedAt: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiresAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrgModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+    pub slug: String,
+    pub createdBy: ObjectId,
+    #[serde(default)]
+    pub plan: Plan,
+    pub stripeCustomerId: Option<String>,
+    pub planChangedAt: Option<bson::DateTime>,
+    /// Defaults for members who haven't chosen for themselves.
+    #[serde(default)]
+    pub preferences: PreferencesModel,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MembershipModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub orgId: ObjectId,
+    pub userId: ObjectId,
+    pub role: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Ready,
+    Failed,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: ObjectId,
+    pub status: ExportStatus,
+    /// The finished archive, serialized as JSON.
+    pub archive: Option<String>,
+    pub error: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expiresAt: DateTime<Utc>,
+    pub completedAt: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditLogModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub action: String,
+    pub actorId: String,
+    pub targetId: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// An event waiting in the outbox to be delivered to other services.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub payload: bson::Document,
+    pub attempts: i32,
+    pub lastError: Option<String>,
+    pub deliveredAt: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// An outbox event that failed too often to keep retrying, set aside until
+/// an admin replays it. Keeps the event's id.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetterModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub payload: bson::Document,
+    pub attempts: i32,
+    pub lastError: Option<String>,
+    /// When the event was first recorded.
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub deadAt: DateTime<Utc>,
+}
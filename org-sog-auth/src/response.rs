@@ -1,6 +1,21 @@
 use chrono::{DateTime, Utc};
+use org_sog_core::{
+    dates,
+    geo::GeoLocation,
+    index_advisor::{IndexBuild, IndexSuggestion},
+    plan::Plan,
+    slow_query::PlanSummary,
+};
 use serde::Serialize;
 
+use crate::{
+    model::{
+        ConsentDocument, ExportStatus, LoginOutcome, PreferencesModel, ProfileModel,
+        ProfilePrivacyModel, Theme,
+    },
+    token::OrgClaim,
+};
+
 #[derive(Serialize)]
 pub struct GenericResponse {
     pub status: String,
@@ -13,10 +28,45 @@ pub struct UserResponse {
     pub id: String,
     pub name: String,
     pub uid: String,
+    pub email: Option<String>,
+    pub scopes: Vec<String>,
+    pub consents: Vec<ConsentResponse>,
+    pub plan: Plan,
+    pub profile: ProfileModel,
+    pub privacy: ProfilePrivacyModel,
+    pub preferences: PreferencesModel,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "dates::serialize_option"
+    )]
+    pub anonymizedAt: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "dates::serialize")]
     pub createdAt: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize")]
     pub updatedAt: DateTime<Utc>,
 }
 
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ConsentResponse {
+    pub document: ConsentDocument,
+    pub version: String,
+    pub acceptedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ConsentVersion {
+    pub document: ConsentDocument,
+    pub version: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ConsentPolicyResponse {
+    pub status: &'static str,
+    pub enforced: bool,
+    pub current: Vec<ConsentVersion>,
+}
+
 #[derive(Serialize, Debug)]
 pub struct UserData {
     pub user: UserResponse,
@@ -31,6 +81,479 @@ pub struct SingleUserResponse {
 #[derive(Serialize, Debug)]
 pub struct UserListResponse {
     pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
     pub results: usize,
     pub users: Vec<UserResponse>,
 }
+
+#[derive(Serialize, Debug)]
+pub struct AvailabilityResponse {
+    pub status: &'static str,
+    pub field: &'static str,
+    pub value: String,
+    pub available: bool,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResolvedUserResponse {
+    pub id: String,
+    pub name: String,
+}
+
+/// The parts of a user's author page they chose to show. Hidden fields are
+/// left out; `recentPosts` and `stats` tell the blog what it may add.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct PublicProfileResponse {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatarUrl: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "dates::serialize_option"
+    )]
+    pub joinedAt: Option<DateTime<Utc>>,
+    pub recentPosts: bool,
+    pub stats: bool,
+}
+
+/// `profile` is null for unknown, deleted and unlisted users alike, so the
+/// blog's lookups don't count against the auth service as failures.
+#[derive(Serialize, Debug)]
+pub struct ProfileLookupResponse {
+    pub status: &'static str,
+    pub profile: Option<PublicProfileResponse>,
+}
+
+/// Users matching a batch of names; names with no active user are left out.
+#[derive(Serialize, Debug)]
+pub struct ResolveUsersResponse {
+    pub status: &'static str,
+    pub users: Vec<ResolvedUserResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct LoginHistoryResponse {
+    pub id: String,
+    pub userId: Option<String>,
+    pub ip: Option<String>,
+    pub userAgent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoLocation>,
+    pub outcome: LoginOutcome,
+    pub reason: Option<String>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LoginHistoryListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub logins: Vec<LoginHistoryResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TokenResponse {
+    pub status: &'static str,
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org: Option<OrgClaim>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<&'static str>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub lastUsedAt: Option<DateTime<Utc>>,
+    pub revokedAt: Option<DateTime<Utc>>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApiKeyData {
+    pub api_key: ApiKeyResponse,
+    /// The full key. It is only ever returned once, at creation time.
+    pub key: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApiKeyCreatedResponse {
+    pub status: &'static str,
+    pub data: ApiKeyData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ApiKeyListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ServiceAccountResponse {
+    pub id: String,
+    pub name: String,
+    pub client_id: String,
+    pub scopes: Vec<String>,
+    pub secretRotatedAt: DateTime<Utc>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ServiceAccountCredentials {
+    pub service_account: ServiceAccountResponse,
+    /// Only ever returned at creation and rotation time.
+    pub client_secret: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ServiceAccountCredentialsResponse {
+    pub status: &'static str,
+    pub data: ServiceAccountCredentials,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ServiceAccountListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub service_accounts: Vec<ServiceAccountResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct InviteResponse {
+    pub id: String,
+    pub email: Option<String>,
+    pub role: String,
+    pub expiresAt: DateTime<Utc>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InviteData {
+    pub invite: InviteResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleInviteResponse {
+    pub status: &'static str,
+    pub data: InviteData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InviteCreatedData {
+    pub invite: InviteResponse,
+    /// Only ever returned at creation time.
+    pub token: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InviteCreatedResponse {
+    pub status: &'static str,
+    pub data: InviteCreatedData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct OrgResponse {
+    pub id: String,
+    pub name: String,
+    pub slug: String,
+    pub plan: Plan,
+    pub createdAt: DateTime<Utc>,
+    pub updatedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OrgData {
+    pub org: OrgResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleOrgResponse {
+    pub status: &'static str,
+    pub data: OrgData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OrgListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub orgs: Vec<OrgResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct MemberResponse {
+    pub orgId: String,
+    pub userId: String,
+    pub role: String,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MemberListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub members: Vec<MemberResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct SessionResponse {
+    pub id: String,
+    pub ip: Option<String>,
+    pub userAgent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoLocation>,
+    pub expiresAt: DateTime<Utc>,
+    pub revokedAt: Option<DateTime<Utc>>,
+    pub createdAt: DateTime<Utc>,
+    /// Whether this is the session the request came in on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<bool>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SessionListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PreferencesResponse {
+    pub status: &'static str,
+    pub preferences: PreferencesModel,
+}
+
+/// Where an effective setting's value came from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingSource {
+    Global,
+    Org,
+    User,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EffectiveSetting<T> {
+    pub value: T,
+    pub source: SettingSource,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct EffectiveSettings {
+    pub locale: EffectiveSetting<String>,
+    pub timezone: EffectiveSetting<String>,
+    pub theme: EffectiveSetting<Theme>,
+    pub emailNotifications: EffectiveSetting<bool>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct EffectiveSettingsResponse {
+    pub status: &'static str,
+    /// The org whose defaults were applied, if the user belongs to one.
+    pub org: Option<String>,
+    pub settings: EffectiveSettings,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ExportResponse {
+    pub id: String,
+    pub userId: String,
+    pub status: ExportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub downloadUrl: Option<String>,
+    pub expiresAt: DateTime<Utc>,
+    pub completedAt: Option<DateTime<Utc>>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExportData {
+    pub export: ExportResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleExportResponse {
+    pub status: &'static str,
+    pub data: ExportData,
+}
+
+/// Everything held about a user, as handed over in a data export.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct UserArchive {
+    pub generatedAt: DateTime<Utc>,
+    pub user: UserResponse,
+    pub sessions: Vec<SessionResponse>,
+    pub logins: Vec<LoginHistoryResponse>,
+    pub apiKeys: Vec<ApiKeyResponse>,
+    pub memberships: Vec<MemberResponse>,
+    pub posts: Vec<serde_json::Value>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct DeadLetterResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Only when inspecting a single dead letter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
+    pub attempts: i32,
+    pub lastError: Option<String>,
+    pub createdAt: DateTime<Utc>,
+    pub deadAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct DeadLetterData {
+    pub deadLetter: DeadLetterResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleDeadLetterResponse {
+    pub status: &'static str,
+    pub data: DeadLetterData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct DeadLetterListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub deadLetters: Vec<DeadLetterResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UserCountsResponse {
+    pub total: u64,
+    /// Signed up within the last week.
+    pub new: u64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct QueueCountsResponse {
+    /// Outbox events not yet delivered.
+    pub outbox: u64,
+    pub deadLetters: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AdminStatsResponse {
+    pub status: &'static str,
+    pub users: UserCountsResponse,
+    pub queues: QueueCountsResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReplayResponse {
+    pub status: &'static str,
+    /// Back in the outbox, due for delivery.
+    pub replayed: Vec<String>,
+    /// Not in the dead-letter queue, e.g. already replayed.
+    pub missing: Vec<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct SlowQueryResponse {
+    pub id: String,
+    pub operation: String,
+    pub collection: String,
+    pub durationMs: i64,
+    pub shape: serde_json::Value,
+    pub sort: Option<serde_json::Value>,
+    pub plan: Option<PlanSummary>,
+    pub traceId: Option<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlowQueryListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub queries: Vec<SlowQueryResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexAdviceResponse {
+    pub status: &'static str,
+    /// Slow queries logged since then were looked at.
+    #[serde(serialize_with = "dates::serialize")]
+    pub since: DateTime<Utc>,
+    pub results: usize,
+    pub suggestions: Vec<IndexSuggestion>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexBuildData {
+    pub build: IndexBuild,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleIndexBuildResponse {
+    pub status: &'static str,
+    pub data: IndexBuildData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexBuildListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub builds: Vec<IndexBuild>,
+}
@@ -1,22 +1,84 @@
 use std::sync::Arc;
 
 use axum::{
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post},
     Router,
 };
 
 use crate::{
+    consent::require_consent,
     handler::{
-        create_user_handler, delete_user_handler, edit_user_handler, get_user_handler,
-        health_checker_handler, user_list_handler,
+        accept_consent_handler, accept_invite_handler, activate_signing_key_handler,
+        add_org_member_handler, admin_stats_handler, anonymize_user_handler, api_key_list_handler,
+        bot_metrics_handler, build_index_handler, check_user_handler, consent_policy_handler,
+        create_api_key_handler, create_invite_handler, create_org_handler,
+        create_service_account_handler, create_user_handler, dead_letter_list_handler,
+        delete_org_handler, delete_user_handler, dependencies_handler, edit_me_handler,
+        edit_org_handler, edit_user_handler, effective_settings_handler, export_download_handler,
+        export_status_handler, export_user_handler, form_stamp_handler,
+        generate_signing_key_handler, get_dead_letter_handler, get_invite_handler, get_me_handler,
+        get_org_handler, get_org_preferences_handler, get_preferences_handler, get_user_handler,
+        health_checker_handler, index_advice_handler, index_build_list_handler, introspect_handler,
+        jwks_handler, magic_link_exchange_handler, magic_link_handler, metrics_handler,
+        my_sessions_handler, org_list_handler, org_members_handler, public_profile_handler,
+        remove_org_member_handler, replay_dead_letters_handler, resolve_users_handler,
+        retire_signing_key_handler, revoke_api_key_handler, revoke_my_session_handler,
+        rotate_service_account_handler, service_account_list_handler, signing_key_list_handler,
+        slow_query_list_handler, stripe_webhook_handler, throttled_handler, token_handler,
+        update_org_preferences_handler, update_preferences_handler, user_list_handler,
+        user_logins_handler,
     },
     AppState,
 };
 
 pub fn create_router(app_state: Arc<AppState>) -> Router {
-    Router::new()
+    // Reachable without having accepted the current terms: sign-in, the
+    // consent flow itself, and the data-protection rights.
+    let open = Router::new()
         .route("/api/healthcheck", get(health_checker_handler))
+        .route("/api/healthcheck/dependencies", get(dependencies_handler))
+        .route("/api/metrics", get(metrics_handler))
+        .route("/.well-known/jwks.json", get(jwks_handler))
+        .route("/api/consent", get(consent_policy_handler))
+        .route("/api/forms/stamp", get(form_stamp_handler))
         .route("/api/users/new", post(create_user_handler))
+        .route("/api/users/check", get(check_user_handler))
+        .route("/api/users/resolve", post(resolve_users_handler))
+        .route("/api/users/profile", get(public_profile_handler))
+        .route("/api/users/accept-invite", post(accept_invite_handler))
+        .route("/api/users/:id/consent", post(accept_consent_handler))
+        .route("/api/users/:id/anonymize", post(anonymize_user_handler))
+        .route("/api/users/:id/export", get(export_user_handler))
+        .route(
+            "/api/users/:id/export/:export_id",
+            get(export_status_handler),
+        )
+        .route(
+            "/api/users/:id/export/:export_id/download",
+            get(export_download_handler),
+        )
+        .route("/api/auth/magic-link", post(magic_link_handler))
+        .route("/api/auth/magic/:token", get(magic_link_exchange_handler))
+        .route("/api/auth/introspect", post(introspect_handler))
+        .route("/api/auth/token", post(token_handler))
+        .route("/api/billing/stripe", post(stripe_webhook_handler))
+        .route("/api/invites/:token", get(get_invite_handler));
+
+    let gated = Router::new()
+        .route("/api/me", get(get_me_handler).patch(edit_me_handler))
+        .route("/api/me/sessions", get(my_sessions_handler))
+        .route("/api/me/sessions/:id", delete(revoke_my_session_handler))
+        .route(
+            "/api/me/apikeys",
+            get(api_key_list_handler).post(create_api_key_handler),
+        )
+        .route("/api/me/apikeys/:id", delete(revoke_api_key_handler))
+        .route(
+            "/api/me/preferences",
+            get(get_preferences_handler).patch(update_preferences_handler),
+        )
+        .route("/api/me/settings", get(effective_settings_handler))
         .route("/api/users", get(user_list_handler))
         .route(
             "/api/users/:id",
@@ -24,5 +86,71 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
                 .patch(edit_user_handler)
                 .delete(delete_user_handler),
         )
-        .with_state(app_state)
+        .route("/api/users/:id/logins", get(user_logins_handler))
+        .route(
+            "/api/apikeys",
+            get(api_key_list_handler).post(create_api_key_handler),
+        )
+        .route("/api/apikeys/:id", delete(revoke_api_key_handler))
+        .route("/api/invites", post(create_invite_handler))
+        .route("/api/orgs", get(org_list_handler).post(create_org_handler))
+        .route(
+            "/api/orgs/:id",
+            get(get_org_handler)
+                .patch(edit_org_handler)
+                .delete(delete_org_handler),
+        )
+        .route(
+            "/api/orgs/:id/preferences",
+            get(get_org_preferences_handler).patch(update_org_preferences_handler),
+        )
+        .route(
+            "/api/orgs/:id/members",
+            get(org_members_handler).post(add_org_member_handler),
+        )
+        .route(
+            "/api/orgs/:id/members/:user_id",
+            delete(remove_org_member_handler),
+        )
+        .route(
+            "/api/service-accounts",
+            get(service_account_list_handler).post(create_service_account_handler),
+        )
+        .route(
+            "/api/service-accounts/:id/rotate",
+            post(rotate_service_account_handler),
+        )
+        .route("/api/admin/bot-metrics", get(bot_metrics_handler))
+        .route(
+            "/api/admin/signing-keys",
+            get(signing_key_list_handler).post(generate_signing_key_handler),
+        )
+        .route(
+            "/api/admin/signing-keys/:kid",
+            delete(retire_signing_key_handler),
+        )
+        .route(
+            "/api/admin/signing-keys/:kid/activate",
+            post(activate_signing_key_handler),
+        )
+        .route("/api/admin/throttled", get(throttled_handler))
+        .route("/api/admin/stats", get(admin_stats_handler))
+        .route("/api/admin/slow-queries", get(slow_query_list_handler))
+        .route("/api/admin/index-advice", get(index_advice_handler))
+        .route(
+            "/api/admin/index-builds",
+            get(index_build_list_handler).post(build_index_handler),
+        )
+        .route("/api/admin/dead-letters", get(dead_letter_list_handler))
+        .route(
+            "/api/admin/dead-letters/replay",
+            post(replay_dead_letters_handler),
+        )
+        .route("/api/admin/dead-letters/:id", get(get_dead_letter_handler))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_consent,
+        ));
+
+    open.merge(gated).with_state(app_state)
 }
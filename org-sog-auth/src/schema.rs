@@ -1,9 +1,32 @@
-use serde::{Deserialize, Serialize};
+use org_sog_core::index_advisor::IndexKey;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::model::{ConsentDocument, ProfileModel, ProfilePrivacyModel, Theme};
 
 #[derive(Deserialize, Debug, Default)]
 pub struct FilterOptions {
-    pub page: Option<usize>,
-    pub limit: Option<usize>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CheckOptions {
+    pub name: Option<String>,
+    pub uid: Option<String>,
+}
+
+/// Users to look up: by name, as written after an `@` in a mention, or by id.
+#[derive(Deserialize, Debug)]
+pub struct ResolveUsersSchema {
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub ids: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProfileOptions {
+    pub name: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -11,10 +34,21 @@ pub struct ParamOptions {
     pub id: String,
 }
 
+#[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CreateUserSchema {
     pub name: String,
     pub uid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Honeypot; hidden from people, so anything in it came from a bot.
+    #[serde(default, skip_serializing)]
+    pub website: String,
+    /// Handed out by `/api/forms/stamp` when the form was opened.
+    #[serde(skip_serializing)]
+    pub formStamp: Option<String>,
+    #[serde(skip_serializing)]
+    pub captchaToken: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -23,4 +57,155 @@ pub struct UpdateUserSchema {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+    /// Replaces the whole profile; fields left out are cleared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<ProfileModel>,
+    /// Replaces all privacy settings; fields left out take their defaults.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub privacy: Option<ProfilePrivacyModel>,
+}
+
+/// Changes to a user's or an org's preferences; fields left out stay as
+/// they are, `null` clears one so it's inherited again.
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct PreferencesSchema {
+    #[serde(default, deserialize_with = "nullable")]
+    pub locale: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub timezone: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub theme: Option<Option<Theme>>,
+    #[serde(default, deserialize_with = "nullable")]
+    pub emailNotifications: Option<Option<bool>>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`).
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MagicLinkSchema {
+    pub email: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RetireKeyOptions {
+    /// Retire even while tokens the key signed may still be live.
+    pub force: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct IntrospectSchema {
+    pub token: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateApiKeySchema {
+    pub name: String,
+    pub scopes: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateServiceAccountSchema {
+    pub name: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TokenSchema {
+    pub grant_type: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateInviteSchema {
+    pub role: String,
+    pub email: Option<String>,
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AcceptInviteSchema {
+    pub token: String,
+    pub name: String,
+    pub uid: String,
+    pub email: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateOrgSchema {
+    pub name: String,
+    pub slug: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateOrgSchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AddMemberSchema {
+    pub user_id: String,
+    pub role: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct OrgOptions {
+    pub org: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ConsentSchema {
+    pub document: ConsentDocument,
+    pub version: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReplaySchema {
+    pub ids: Vec<String>,
+}
+
+/// Response date format, applied to every route.
+#[derive(Deserialize, Debug, Default)]
+pub struct DateOptions {
+    /// `rfc3339` (the default) or `epoch` for Unix milliseconds.
+    pub dates: Option<String>,
+    /// IANA zone for rfc3339 dates, e.g. `Europe/Berlin`.
+    pub tz: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SlowQueryOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    /// Only queries on this collection.
+    pub collection: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct IndexAdviceOptions {
+    /// Days of the slow query log to look at.
+    pub days: Option<i64>,
+    pub collection: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BuildIndexSchema {
+    pub collection: String,
+    pub keys: Vec<IndexKey>,
 }
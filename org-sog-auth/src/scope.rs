@@ -0,0 +1,45 @@
+pub const BLOG_READ: &str = "blog:read";
+pub const BLOG_WRITE: &str = "blog:write";
+pub const BLOG_ADMIN: &str = "blog:admin";
+pub const USERS_ADMIN: &str = "users:admin";
+/// Lets a service account ask whether other credentials are valid.
+pub const AUTH_INTROSPECT: &str = "auth:introspect";
+
+pub const ALL: &[&str] = &[
+    BLOG_READ,
+    BLOG_WRITE,
+    BLOG_ADMIN,
+    USERS_ADMIN,
+    AUTH_INTROSPECT,
+];
+
+/// Scopes granted to accounts created through open registration.
+pub const DEFAULT: &[&str] = &[BLOG_READ];
+
+/// Scopes granted by each role an invite can carry.
+pub fn for_role(role: &str) -> Option<&'static [&'static str]> {
+    match role {
+        "member" => Some(&[BLOG_READ]),
+        "editor" => Some(&[BLOG_READ, BLOG_WRITE]),
+        "admin" => Some(ALL),
+        _ => None,
+    }
+}
+
+pub fn is_known(scope: &str) -> bool {
+    ALL.contains(&scope)
+}
+
+pub fn parse(scope: Option<&str>) -> Vec<String> {
+    scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Roles a user can hold within an organization, from most to least privileged.
+pub const ORG_ROLES: &[&str] = &["owner", "admin", "member"];
+
+/// Org roles allowed to manage the organization and its members.
+pub const ORG_MANAGERS: &[&str] = &["owner", "admin"];
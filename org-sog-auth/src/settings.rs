@@ -0,0 +1,131 @@
+use std::str::FromStr;
+
+use mongodb::bson::oid::ObjectId;
+use org_sog_core::read::ReadFrom;
+
+use crate::{
+    db::{is_locale, is_timezone, DB},
+    error::MyError::{self, ForbiddenError, InvalidIDError},
+    model::{PreferencesModel, Theme},
+    response::{EffectiveSetting, EffectiveSettings, EffectiveSettingsResponse, SettingSource},
+};
+
+/// Works out the settings a user ends up with: what they chose themselves,
+/// else what their org set for its members, else the site-wide defaults.
+///
+/// The defaults come from `DEFAULT_LOCALE` (default `en`), `DEFAULT_TIMEZONE`
+/// (default `UTC`), `DEFAULT_THEME` (default `system`) and
+/// `DEFAULT_EMAIL_NOTIFICATIONS` (default `true`).
+#[derive(Debug, Clone)]
+pub struct SettingsResolver {
+    locale: String,
+    timezone: String,
+    theme: Theme,
+    email_notifications: bool,
+}
+
+impl SettingsResolver {
+    pub fn init() -> Self {
+        let locale = std::env::var("DEFAULT_LOCALE").unwrap_or_else(|_| "en".to_string());
+        if !is_locale(&locale) {
+            panic!("DEFAULT_LOCALE must be a language tag, got '{}'", locale);
+        }
+        let timezone = std::env::var("DEFAULT_TIMEZONE").unwrap_or_else(|_| "UTC".to_string());
+        if !is_timezone(&timezone) {
+            panic!("DEFAULT_TIMEZONE must be a time zone, got '{}'", timezone);
+        }
+        let theme = match std::env::var("DEFAULT_THEME").as_deref() {
+            Ok("system") | Err(_) => Theme::System,
+            Ok("light") => Theme::Light,
+            Ok("dark") => Theme::Dark,
+            Ok(other) => panic!(
+                "DEFAULT_THEME must be 'system', 'light' or 'dark', got '{}'",
+                other
+            ),
+        };
+        let email_notifications = match std::env::var("DEFAULT_EMAIL_NOTIFICATIONS") {
+            Ok(value) => value.parse::<bool>().unwrap_or_else(|_| {
+                panic!(
+                    "DEFAULT_EMAIL_NOTIFICATIONS must be true or false, got '{}'",
+                    value
+                )
+            }),
+            Err(_) => true,
+        };
+
+        Self {
+            locale,
+            timezone,
+            theme,
+            email_notifications,
+        }
+    }
+
+    /// The settings `user_id` has when acting for `org_id`, or for their
+    /// oldest org when none is named. A named org must be one of theirs.
+    pub async fn resolve(
+        &self,
+        db: &DB,
+        user_id: &str,
+        org_id: Option<&str>,
+    ) -> Result<EffectiveSettingsResponse, MyError> {
+        let user = db.user_preferences(user_id, ReadFrom::Primary).await?;
+
+        let org = match org_id {
+            Some(org_id) => match db.membership_role(org_id, user_id).await? {
+                Some(_) => Some(org_id.to_owned()),
+                None => return Err(ForbiddenError(format!("org:{}", org_id))),
+            },
+            None => {
+                let oid =
+                    ObjectId::from_str(user_id).map_err(|_| InvalidIDError(user_id.to_owned()))?;
+                db.default_membership(&oid)
+                    .await?
+                    .map(|membership| membership.orgId.to_hex())
+            }
+        };
+        let org_preferences = match &org {
+            Some(org) => Some(db.org_preferences(org, ReadFrom::Replica).await?),
+            None => None,
+        };
+
+        Ok(EffectiveSettingsResponse {
+            status: "success",
+            org,
+            settings: self.merge(org_preferences.as_ref(), &user),
+        })
+    }
+
+    /// `user`'s choices over `org`'s over the site defaults.
+    pub fn merge(
+        &self,
+        org: Option<&PreferencesModel>,
+        user: &PreferencesModel,
+    ) -> EffectiveSettings {
+        let unset = PreferencesModel::default();
+        let org = org.unwrap_or(&unset);
+
+        EffectiveSettings {
+            locale: pick(&user.locale, &org.locale, &self.locale),
+            timezone: pick(&user.timezone, &org.timezone, &self.timezone),
+            theme: pick(&user.theme, &org.theme, &self.theme),
+            emailNotifications: pick(
+                &user.emailNotifications,
+                &org.emailNotifications,
+                &self.email_notifications,
+            ),
+        }
+    }
+}
+
+fn pick<T: Clone>(user: &Option<T>, org: &Option<T>, global: &T) -> EffectiveSetting<T> {
+    let (value, source) = match (user, org) {
+        (Some(value), _) => (value, SettingSource::User),
+        (None, Some(value)) => (value, SettingSource::Org),
+        (None, None) => (global, SettingSource::Global),
+    };
+    EffectiveSetting {
+        value: value.clone(),
+        source,
+    }
+}
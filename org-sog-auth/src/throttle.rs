@@ -0,0 +1,182 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::error::MyError;
+
+/// Failures older than this are forgotten, once any lockout has run out.
+const FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// The sign-in endpoints guarded against guessing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Guarded {
+    Token,
+    MagicLink,
+    MagicExchange,
+}
+
+struct Record {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// An identity that is currently locked out of an endpoint.
+#[allow(non_snake_case)]
+#[derive(Debug, Serialize)]
+pub struct ThrottledIdentity {
+    pub endpoint: Guarded,
+    /// `ip:<address>` or `account:<client id or email>`.
+    pub identity: String,
+    pub failures: u32,
+    pub retryAfter: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ThrottleMetrics {
+    pub endpoint: Guarded,
+    pub failures: u64,
+    pub blocked: u64,
+}
+
+/// Brute-force protection for the sign-in endpoints, keyed by both client
+/// address and target account.
+///
+/// The first `LOGIN_FREE_ATTEMPTS` (default 5) failures within fifteen
+/// minutes cost nothing; each one after that locks the identity out for
+/// twice as long as the last, from one second up to `LOGIN_MAX_DELAY_SECS`
+/// (default 900). Counts are kept per instance.
+pub struct LoginThrottle {
+    free_attempts: u32,
+    max_delay: Duration,
+    records: Mutex<HashMap<(Guarded, String), Record>>,
+    counts: Mutex<BTreeMap<Guarded, (u64, u64)>>,
+}
+
+impl LoginThrottle {
+    pub fn init() -> Self {
+        let env_or = |name: &str, default: u64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("{} must be a number.", name)),
+            Err(_) => default,
+        };
+
+        Self {
+            free_attempts: env_or("LOGIN_FREE_ATTEMPTS", 5) as u32,
+            max_delay: Duration::from_secs(env_or("LOGIN_MAX_DELAY_SECS", 900)),
+            records: Mutex::new(HashMap::new()),
+            counts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Fails while any of `identities` is locked out of `endpoint`.
+    pub async fn check(&self, endpoint: Guarded, identities: &[String]) -> Result<(), MyError> {
+        let now = Instant::now();
+        let records = self.records.lock().await;
+        let retry_after = identities
+            .iter()
+            .filter_map(|identity| records.get(&(endpoint, identity.to_owned())))
+            .filter_map(|record| record.locked_until)
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+            .max();
+        drop(records);
+
+        match retry_after {
+            Some(retry_after) => {
+                self.counts.lock().await.entry(endpoint).or_default().1 += 1;
+                Err(MyError::TooManyRequestsError(
+                    retry_after.as_secs_f64().ceil() as u64,
+                ))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Counts a failed attempt against each of `identities`.
+    pub async fn failure(&self, endpoint: Guarded, identities: &[String]) {
+        let now = Instant::now();
+        let mut records = self.records.lock().await;
+        records.retain(|_, record| {
+            now.duration_since(record.last_failure) < FAILURE_WINDOW
+                || record.locked_until.is_some_and(|until| until > now)
+        });
+
+        for identity in identities {
+            let record = records
+                .entry((endpoint, identity.to_owned()))
+                .or_insert(Record {
+                    failures: 0,
+                    last_failure: now,
+                    locked_until: None,
+                });
+            record.failures += 1;
+            record.last_failure = now;
+            if record.failures > self.free_attempts {
+                let doublings = (record.failures - self.free_attempts - 1).min(31);
+                let delay = Duration::from_secs(1 << doublings).min(self.max_delay);
+                record.locked_until = Some(now + delay);
+            }
+        }
+        drop(records);
+
+        self.counts.lock().await.entry(endpoint).or_default().0 += 1;
+    }
+
+    /// Forgets the failures against `identity` once it got in.
+    pub async fn success(&self, endpoint: Guarded, identity: &str) {
+        self.records
+            .lock()
+            .await
+            .remove(&(endpoint, identity.to_owned()));
+    }
+
+    pub async fn throttled(&self) -> Vec<ThrottledIdentity> {
+        let now = Instant::now();
+        let mut throttled: Vec<ThrottledIdentity> = self
+            .records
+            .lock()
+            .await
+            .iter()
+            .filter_map(|((endpoint, identity), record)| {
+                let until = record.locked_until.filter(|until| *until > now)?;
+                Some(ThrottledIdentity {
+                    endpoint: *endpoint,
+                    identity: identity.to_owned(),
+                    failures: record.failures,
+                    retryAfter: (until - now).as_secs_f64().ceil() as u64,
+                })
+            })
+            .collect();
+        throttled.sort_by_key(|identity| Reverse(identity.retryAfter));
+        throttled
+    }
+
+    pub async fn metrics(&self) -> Vec<ThrottleMetrics> {
+        self.counts
+            .lock()
+            .await
+            .iter()
+            .map(|(endpoint, (failures, blocked))| ThrottleMetrics {
+                endpoint: *endpoint,
+                failures: *failures,
+                blocked: *blocked,
+            })
+            .collect()
+    }
+}
+
+pub fn ip_identity(ip: Option<&str>) -> String {
+    format!("ip:{}", ip.unwrap_or("unknown"))
+}
+
+pub fn account_identity(account: &str) -> String {
+    format!("account:{}", account.trim().to_lowercase())
+}
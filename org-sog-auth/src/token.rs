@@ -0,0 +1,537 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use org_sog_core::{dates, plan::Plan};
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::MyError::{
+    self, KeyStoreError, SigningKeyInUseError, SigningKeyNotFoundError, UnauthorizedError,
+};
+
+/// Records which key signs; see [`TokenService`].
+const ACTIVE_MARKER: &str = "ACTIVE";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Claims {
+    pub sub: String,
+    pub jti: String,
+    pub iat: i64,
+    pub exp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Set when the token was obtained through the client-credentials grant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// The organization the session is acting on behalf of, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org: Option<OrgClaim>,
+    /// The plan limits apply from: the org's when acting for one, otherwise
+    /// the user's. Client-credentials tokens carry none and aren't metered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrgClaim {
+    pub id: String,
+    pub role: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Jwk {
+    pub kty: &'static str,
+    pub crv: &'static str,
+    #[serde(rename = "use")]
+    pub use_: &'static str,
+    pub alg: &'static str,
+    pub kid: String,
+    pub x: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+#[derive(Clone)]
+struct SigningKey {
+    kid: String,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    public_key: String,
+}
+
+impl SigningKey {
+    fn from_pkcs8(kid: String, der: &[u8]) -> Result<Self, String> {
+        let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der)
+            .map_err(|e| format!("signing key {} is not a valid Ed25519 key: {}", kid, e))?;
+
+        let public_key = URL_SAFE_NO_PAD.encode(pair.public_key().as_ref());
+        let decoding_key = DecodingKey::from_ed_components(&public_key)
+            .map_err(|e| format!("signing key {} has an invalid public key: {}", kid, e))?;
+
+        Ok(Self {
+            encoding_key: EncodingKey::from_ed_der(der),
+            decoding_key,
+            public_key,
+            kid,
+        })
+    }
+
+    fn to_jwk(&self) -> Jwk {
+        Jwk {
+            kty: "OKP",
+            crv: "Ed25519",
+            use_: "sig",
+            alg: "EdDSA",
+            kid: self.kid.to_owned(),
+            x: self.public_key.to_owned(),
+        }
+    }
+}
+
+/// Which key signs, and since when; written to `ACTIVE` in the key directory
+/// by rotations so every instance and restart agrees.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ActiveMarker {
+    kid: String,
+    activated_at: DateTime<Utc>,
+}
+
+struct KeyRing {
+    keys: Vec<SigningKey>,
+    active: usize,
+    activated_at: Option<DateTime<Utc>>,
+}
+
+/// Where a signing key is in its rotation.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStage {
+    /// Signs new tokens.
+    Active,
+    /// Published but not signing; tokens it signed earlier may still be live.
+    Grace,
+    /// Published but not signing, and every token it could have signed has expired.
+    Retirable,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct SigningKeyStatus {
+    pub kid: String,
+    pub stage: KeyStage,
+    /// When the last token this key may have signed expires.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "dates::serialize_option"
+    )]
+    pub graceEndsAt: Option<DateTime<Utc>>,
+}
+
+/// Issues EdDSA-signed access tokens.
+///
+/// Keys are loaded from `JWT_KEYS_DIR`, one PKCS#8 PEM file per key named `<kid>.pem`.
+/// Every key in the directory is published in the JWKS so tokens signed by a key that
+/// is being rotated out keep verifying. The key named in the directory's `ACTIVE`
+/// marker signs new tokens, falling back to `JWT_ACTIVE_KID` and then the last kid
+/// in sort order.
+///
+/// Rotation goes generate, activate, then retire once the old key's grace window (one
+/// token lifetime after the new key took over) has passed. Other instances pick up
+/// changes to the directory every `JWT_KEYS_RELOAD_SECS` (default 60).
+#[derive(Clone)]
+pub struct TokenService {
+    ring: Arc<RwLock<KeyRing>>,
+    dir: Option<PathBuf>,
+    pub ttl: Duration,
+    pub reload_every: std::time::Duration,
+}
+
+impl TokenService {
+    pub fn init() -> Self {
+        let ttl_secs = std::env::var("JWT_TTL_SECS")
+            .ok()
+            .and_then(|value| value.parse::<i64>().ok())
+            .unwrap_or(3600);
+        let reload_secs = std::env::var("JWT_KEYS_RELOAD_SECS")
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or(60);
+
+        let dir = std::env::var("JWT_KEYS_DIR").ok().map(PathBuf::from);
+        let ring = match &dir {
+            Some(dir) => load_ring(dir).unwrap_or_else(|e| panic!("{}", e)),
+            None => {
+                println!("⚠️ JWT_KEYS_DIR not set, signing with an ephemeral key");
+                KeyRing {
+                    keys: vec![ephemeral_key()],
+                    active: 0,
+                    activated_at: None,
+                }
+            }
+        };
+
+        Self {
+            ring: Arc::new(RwLock::new(ring)),
+            dir,
+            ttl: Duration::seconds(ttl_secs),
+            reload_every: std::time::Duration::from_secs(reload_secs.max(1)),
+        }
+    }
+
+    pub fn issue(
+        &self,
+        sub: &str,
+        jti: &str,
+        scopes: &[String],
+        org: Option<OrgClaim>,
+        plan: Plan,
+    ) -> Result<(String, Claims), MyError> {
+        let mut claims = self.claims(sub, jti, scopes, None);
+        claims.org = org;
+        claims.plan = Some(plan);
+        self.sign(claims)
+    }
+
+    pub fn issue_for_client(
+        &self,
+        client_id: &str,
+        sub: &str,
+        jti: &str,
+        scopes: &[String],
+    ) -> Result<(String, Claims), MyError> {
+        self.sign(self.claims(sub, jti, scopes, Some(client_id)))
+    }
+
+    fn claims(&self, sub: &str, jti: &str, scopes: &[String], client_id: Option<&str>) -> Claims {
+        let now = Utc::now();
+        Claims {
+            sub: sub.to_owned(),
+            jti: jti.to_owned(),
+            iat: now.timestamp(),
+            exp: (now + self.ttl).timestamp(),
+            scope: Some(scopes.join(" ")),
+            client_id: client_id.map(str::to_owned),
+            org: None,
+            plan: None,
+        }
+    }
+
+    fn sign(&self, claims: Claims) -> Result<(String, Claims), MyError> {
+        let ring = self.ring.read().unwrap();
+        let key = &ring.keys[ring.active];
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(key.kid.to_owned());
+
+        let token = encode(&header, &claims, &key.encoding_key).map_err(MyError::TokenError)?;
+
+        Ok((token, claims))
+    }
+
+    /// Checks the signature and expiry of `token` against every published key.
+    pub fn verify(&self, token: &str) -> Result<Claims, MyError> {
+        let header =
+            decode_header(token).map_err(|_| UnauthorizedError("malformed token".to_string()))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| UnauthorizedError("token has no key id".to_string()))?;
+        let ring = self.ring.read().unwrap();
+        let key = ring
+            .keys
+            .iter()
+            .find(|key| key.kid == kid)
+            .ok_or_else(|| UnauthorizedError(format!("unknown signing key: {}", kid)))?;
+
+        decode::<Claims>(token, &key.decoding_key, &Validation::new(Algorithm::EdDSA))
+            .map(|data| data.claims)
+            .map_err(|e| UnauthorizedError(format!("invalid token: {}", e)))
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self
+                .ring
+                .read()
+                .unwrap()
+                .keys
+                .iter()
+                .map(SigningKey::to_jwk)
+                .collect(),
+        }
+    }
+
+    pub fn key_status(&self) -> Vec<SigningKeyStatus> {
+        let ring = self.ring.read().unwrap();
+        let grace_ends_at = ring.activated_at.map(|at| at + self.ttl);
+        let in_grace = grace_ends_at.is_some_and(|ends| ends > Utc::now());
+
+        ring.keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let stage = match (i == ring.active, in_grace) {
+                    (true, _) => KeyStage::Active,
+                    (false, true) => KeyStage::Grace,
+                    (false, false) => KeyStage::Retirable,
+                };
+                SigningKeyStatus {
+                    kid: key.kid.to_owned(),
+                    stage,
+                    graceEndsAt: if stage == KeyStage::Active {
+                        None
+                    } else {
+                        grace_ends_at
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// Adds a fresh key to the directory. It is published straight away, so
+    /// verifiers can fetch it before it starts signing.
+    pub fn generate_key(&self) -> Result<String, MyError> {
+        let dir = self.key_dir()?;
+        let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| KeyStoreError("failed to generate an Ed25519 key".to_string()))?;
+        let kid = Utc::now().format("%Y%m%d%H%M%S").to_string();
+        let key =
+            SigningKey::from_pkcs8(kid.to_owned(), document.as_ref()).map_err(KeyStoreError)?;
+
+        let pem = pem::encode(&pem::Pem {
+            tag: "PRIVATE KEY".to_string(),
+            contents: document.as_ref().to_vec(),
+        });
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(dir.join(format!("{}.pem", kid)))
+            .and_then(|mut file| file.write_all(pem.as_bytes()))
+            .map_err(|e| KeyStoreError(format!("failed to write signing key {}: {}", kid, e)))?;
+
+        let mut ring = self.ring.write().unwrap();
+        let active_kid = ring.keys[ring.active].kid.to_owned();
+        ring.keys.push(key);
+        ring.keys.sort_by(|a, b| a.kid.cmp(&b.kid));
+        ring.active = ring
+            .keys
+            .iter()
+            .position(|key| key.kid == active_kid)
+            .unwrap();
+        Ok(kid)
+    }
+
+    /// Makes `kid` the signing key; the previous one keeps verifying.
+    pub fn activate_key(&self, kid: &str) -> Result<(), MyError> {
+        let dir = self.key_dir()?;
+        let mut ring = self.ring.write().unwrap();
+        let active = ring
+            .keys
+            .iter()
+            .position(|key| key.kid == kid)
+            .ok_or_else(|| SigningKeyNotFoundError(kid.to_owned()))?;
+        if active == ring.active {
+            return Ok(());
+        }
+
+        let marker = ActiveMarker {
+            kid: kid.to_owned(),
+            activated_at: Utc::now(),
+        };
+        let contents = serde_json::to_vec_pretty(&marker).unwrap();
+        std::fs::write(dir.join(ACTIVE_MARKER), contents)
+            .map_err(|e| KeyStoreError(format!("failed to record the active key: {}", e)))?;
+
+        ring.active = active;
+        ring.activated_at = Some(marker.activated_at);
+        Ok(())
+    }
+
+    /// Stops publishing `kid`. Its file is kept as `<kid>.pem.retired`.
+    ///
+    /// Refused for the signing key, and while tokens it signed may still be
+    /// live unless `force` is set, which logs out everyone holding one.
+    pub fn retire_key(&self, kid: &str, force: bool) -> Result<(), MyError> {
+        let dir = self.key_dir()?;
+        let stage = self
+            .key_status()
+            .into_iter()
+            .find(|status| status.kid == kid)
+            .ok_or_else(|| SigningKeyNotFoundError(kid.to_owned()))?
+            .stage;
+        match stage {
+            KeyStage::Active => return Err(SigningKeyInUseError(kid.to_owned())),
+            KeyStage::Grace if !force => return Err(SigningKeyInUseError(kid.to_owned())),
+            _ => {}
+        }
+
+        let path = dir.join(format!("{}.pem", kid));
+        std::fs::rename(&path, path.with_extension("pem.retired"))
+            .map_err(|e| KeyStoreError(format!("failed to retire signing key {}: {}", kid, e)))?;
+
+        let mut ring = self.ring.write().unwrap();
+        let active_kid = ring.keys[ring.active].kid.to_owned();
+        ring.keys.retain(|key| key.kid != kid);
+        ring.active = ring
+            .keys
+            .iter()
+            .position(|key| key.kid == active_kid)
+            .unwrap();
+        Ok(())
+    }
+
+    /// Picks up keys and rotations made through another instance.
+    pub fn reload(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        match load_ring(dir) {
+            Ok(ring) => *self.ring.write().unwrap() = ring,
+            Err(e) => println!(
+                "⚠️ Could not reload signing keys, keeping the current ones: {}",
+                e
+            ),
+        }
+    }
+
+    fn key_dir(&self) -> Result<&Path, MyError> {
+        self.dir.as_deref().ok_or_else(|| {
+            KeyStoreError("JWT_KEYS_DIR must be set to manage signing keys".to_string())
+        })
+    }
+}
+
+pub fn spawn_reloader(tokens: TokenService) {
+    if tokens.dir.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokens.reload_every);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let tokens = tokens.clone();
+            // Reading the directory blocks, so keep it off the runtime's workers.
+            let _ = tokio::task::spawn_blocking(move || tokens.reload()).await;
+        }
+    });
+}
+
+/// Manages the keys in `JWT_KEYS_DIR` from the command line:
+/// `keys list`, `keys generate`, `keys activate <kid>` and
+/// `keys retire <kid> [--force]`.
+pub fn run_cli(args: &[String]) -> Result<(), MyError> {
+    let tokens = TokenService::init();
+    match args {
+        [command] if command == "list" => {}
+        [command] if command == "generate" => {
+            let kid = tokens.generate_key()?;
+            println!("🔑 Generated signing key {}", kid);
+        }
+        [command, kid] if command == "activate" => {
+            tokens.activate_key(kid)?;
+            println!("🔑 {} now signs new tokens", kid);
+        }
+        [command, kid, rest @ ..] if command == "retire" => {
+            let force = rest.iter().any(|arg| arg == "--force");
+            tokens.retire_key(kid, force)?;
+            println!("🔑 Retired signing key {}", kid);
+        }
+        _ => {
+            println!("usage: keys list | generate | activate <kid> | retire <kid> [--force]");
+            return Ok(());
+        }
+    }
+
+    for status in tokens.key_status() {
+        match status.graceEndsAt {
+            Some(ends) if status.stage == KeyStage::Grace => {
+                println!("{}\t{:?}\tuntil {}", status.kid, status.stage, ends)
+            }
+            _ => println!("{}\t{:?}", status.kid, status.stage),
+        }
+    }
+    Ok(())
+}
+
+fn load_ring(dir: &Path) -> Result<KeyRing, String> {
+    let keys = load_keys(dir)?;
+
+    let marker = match std::fs::read(dir.join(ACTIVE_MARKER)) {
+        Ok(contents) => Some(
+            serde_json::from_slice::<ActiveMarker>(&contents)
+                .map_err(|e| format!("{} is invalid: {}", ACTIVE_MARKER, e))?,
+        ),
+        Err(_) => None,
+    };
+    let active_kid = marker
+        .as_ref()
+        .map(|marker| marker.kid.to_owned())
+        .or_else(|| std::env::var("JWT_ACTIVE_KID").ok());
+
+    let active = match &active_kid {
+        Some(kid) => keys
+            .iter()
+            .position(|key| &key.kid == kid)
+            .ok_or_else(|| format!("active signing key {} is not in JWT_KEYS_DIR", kid))?,
+        None => keys.len() - 1,
+    };
+
+    Ok(KeyRing {
+        keys,
+        active,
+        activated_at: marker.map(|marker| marker.activated_at),
+    })
+}
+
+fn load_keys(dir: &Path) -> Result<Vec<SigningKey>, String> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|e| format!("JWT_KEYS_DIR must be a readable directory: {}", e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pem"))
+        .collect();
+    paths.sort();
+
+    let keys = paths
+        .iter()
+        .map(|path| {
+            let kid = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or("key file names must be valid UTF-8")?
+                .to_owned();
+            let contents = std::fs::read(path)
+                .map_err(|e| format!("failed to read signing key {}: {}", kid, e))?;
+            let pem = pem::parse(contents)
+                .map_err(|e| format!("signing key {} is not valid PEM: {}", kid, e))?;
+            SigningKey::from_pkcs8(kid, &pem.contents)
+        })
+        .collect::<Result<Vec<SigningKey>, String>>()?;
+
+    if keys.is_empty() {
+        return Err("JWT_KEYS_DIR must contain at least one <kid>.pem key".to_string());
+    }
+    Ok(keys)
+}
+
+fn ephemeral_key() -> SigningKey {
+    let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .expect("failed to generate an Ed25519 key");
+    let kid = format!("ephemeral-{}", Utc::now().timestamp());
+    SigningKey::from_pkcs8(kid, document.as_ref()).unwrap()
+}
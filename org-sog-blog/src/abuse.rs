@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::State,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use org_sog_core::client_ip::{Cidr, ClientIp};
+use tokio::sync::Mutex;
+
+use crate::{error::MyError, AppState};
+
+/// Rate limited requests from one address within this window count towards
+/// an automatic ban.
+const STRIKE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// What the admin-managed rules say about one address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Allowlisted: never denied, banned or counted towards a ban.
+    Allowed,
+    Denied,
+    Unlisted,
+}
+
+/// The current allow and deny rules, including temporary bans.
+#[derive(Debug, Default)]
+pub struct IpRuleTable {
+    pub allow: Vec<Cidr>,
+    pub deny: Vec<(Cidr, Option<DateTime<Utc>>)>,
+}
+
+impl IpRuleTable {
+    pub fn verdict(&self, ip: &IpAddr) -> Verdict {
+        if self.allow.iter().any(|cidr| cidr.contains(ip)) {
+            return Verdict::Allowed;
+        }
+        // Expired bans linger until the TTL monitor gets to them.
+        let now = Utc::now();
+        let denied = self.deny.iter().any(|(cidr, expires_at)| {
+            cidr.contains(ip) && expires_at.is_none_or(|expires_at| expires_at > now)
+        });
+        if denied {
+            Verdict::Denied
+        } else {
+            Verdict::Unlisted
+        }
+    }
+}
+
+/// Automatic bans. An address that gets rate limited `AUTO_BAN_STRIKES` times
+/// in ten minutes is banned for `AUTO_BAN_MINUTES`; setting the strikes to 0
+/// turns automatic bans off. Strikes are counted per instance, bans are shared
+/// through the database.
+pub struct AbuseGuard {
+    ban_after_strikes: usize,
+    ban_for: chrono::Duration,
+    strikes: Mutex<HashMap<IpAddr, Vec<Instant>>>,
+}
+
+impl AbuseGuard {
+    pub fn init() -> Self {
+        let env_or = |name: &str, default: i64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<i64>()
+                .ok()
+                .filter(|value| *value >= 0)
+                .unwrap_or_else(|| panic!("{} must be a non-negative number.", name)),
+            Err(_) => default,
+        };
+
+        Self {
+            ban_after_strikes: env_or("AUTO_BAN_STRIKES", 10) as usize,
+            ban_for: chrono::Duration::minutes(env_or("AUTO_BAN_MINUTES", 60)),
+            strikes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a rate limited request from `ip`, returning how long to ban it
+    /// for once it has struck out.
+    pub async fn strike(&self, ip: IpAddr) -> Option<chrono::Duration> {
+        if self.ban_after_strikes == 0 {
+            return None;
+        }
+
+        let mut strikes = self.strikes.lock().await;
+        strikes.retain(|_, struck| {
+            struck.retain(|at| at.elapsed() < STRIKE_WINDOW);
+            !struck.is_empty()
+        });
+
+        let struck = strikes.entry(ip).or_default();
+        struck.push(Instant::now());
+        if struck.len() < self.ban_after_strikes {
+            return None;
+        }
+        strikes.remove(&ip);
+        Some(self.ban_for)
+    }
+}
+
+/// Turns away denylisted and banned addresses, and bans addresses that keep
+/// running into the rate limiter. Rules are looked up from a short-lived
+/// cache, and a failed lookup lets the request through.
+pub async fn guard<B>(
+    State(app_state): State<Arc<AppState>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(&ClientIp(ip)) = request.extensions().get::<ClientIp>() else {
+        return next.run(request).await;
+    };
+
+    let verdict = match app_state.db.ip_verdict(&ip).await {
+        Ok(verdict) => verdict,
+        Err(e) => {
+            println!("⚠️ Could not load IP rules: {}", e);
+            Verdict::Unlisted
+        }
+    };
+    if verdict == Verdict::Denied {
+        let error: (StatusCode, Json<serde_json::Value>) = MyError::IpBlockedError.into();
+        return error.into_response();
+    }
+
+    let response = next.run(request).await;
+
+    if response.status() == StatusCode::TOO_MANY_REQUESTS && verdict != Verdict::Allowed {
+        if let Some(ban_for) = app_state.abuse.strike(ip).await {
+            if let Err(e) = app_state.db.ban_ip(&ip, ban_for).await {
+                println!("⚠️ Could not ban {}: {}", ip, e);
+            }
+        }
+    }
+    response
+}
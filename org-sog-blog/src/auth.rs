@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use org_sog_core::{
+    circuit_breaker::BreakerMetrics,
+    http::{HttpClient, HttpClientConfig, Method},
+    plan::Plan,
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::{
+    error::MyError::{self, AuthServiceError, UnauthorizedError},
+    extract::{Credential, API_KEY_HEADER},
+};
+
+type Result<T> = std::result::Result<T, MyError>;
+
+/// Minimum time between JWKS fetches, so a flood of tokens with a bogus `kid`
+/// can't be turned into a flood of requests against the auth service.
+const JWKS_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Claims {
+    pub sub: String,
+    #[serde(default)]
+    pub scope: Option<String>,
+    /// Absent on service tokens, which aren't held to a plan.
+    #[serde(default)]
+    pub plan: Option<Plan>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kid: String,
+    x: String,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Introspection {
+    active: bool,
+    sub: Option<String>,
+    scope: Option<String>,
+    plan: Option<Plan>,
+    jti: Option<String>,
+}
+
+/// Who an API key acts for, as reported by introspection.
+#[derive(Debug, Clone)]
+pub struct ApiKeyGrant {
+    pub sub: String,
+    pub scopes: Vec<String>,
+    pub plan: Option<Plan>,
+    pub key_id: Option<String>,
+}
+
+/// A user looked up by name.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ResolvedUser {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct Resolution {
+    users: Vec<ResolvedUser>,
+}
+
+/// An author page as its user chose to show it. Hidden fields are absent.
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuthorProfile {
+    pub id: String,
+    pub name: String,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub location: Option<String>,
+    pub avatarUrl: Option<String>,
+    pub joinedAt: Option<String>,
+    /// Whether the author shows their recent posts.
+    pub recentPosts: bool,
+    /// Whether the author shows their numbers.
+    pub stats: bool,
+}
+
+#[derive(Deserialize)]
+struct ProfileLookup {
+    profile: Option<AuthorProfile>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserCounts {
+    pub total: u64,
+    pub new: u64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuthQueueCounts {
+    pub outbox: u64,
+    pub deadLetters: u64,
+}
+
+/// The auth service's side of the admin overview.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AdminStats {
+    pub users: UserCounts,
+    pub queues: AuthQueueCounts,
+}
+
+#[derive(Default)]
+struct KeyCache {
+    keys: HashMap<String, DecodingKey>,
+    fetched_at: Option<Instant>,
+}
+
+/// Verifies credentials issued by org-sog-auth.
+///
+/// Access tokens are checked locally against the auth service's published JWKS;
+/// API keys are opaque, so they are resolved through its introspection endpoint,
+/// which the blog calls with its own client credentials.
+/// It also resolves user names, which only the auth service knows about.
+pub struct AuthVerifier {
+    http: HttpClient,
+    auth_url: String,
+    client: Option<ClientCredentials>,
+    cache: RwLock<KeyCache>,
+}
+
+struct ClientCredentials {
+    id: String,
+    secret: String,
+}
+
+impl AuthVerifier {
+    pub fn init() -> Self {
+        let auth_url =
+            std::env::var("AUTH_URL").unwrap_or_else(|_| "http://localhost:8000".to_string());
+        let client = match (
+            std::env::var("AUTH_CLIENT_ID"),
+            std::env::var("AUTH_CLIENT_SECRET"),
+        ) {
+            (Ok(id), Ok(secret)) => Some(ClientCredentials { id, secret }),
+            _ => {
+                println!("⚠️ AUTH_CLIENT_ID/AUTH_CLIENT_SECRET not set, API keys will be rejected");
+                None
+            }
+        };
+
+        Self {
+            http: HttpClient::new("auth", HttpClientConfig::default()),
+            auth_url: auth_url.trim_end_matches('/').to_owned(),
+            client,
+            cache: RwLock::new(KeyCache::default()),
+        }
+    }
+
+    pub async fn verify_token(&self, token: &str) -> Result<Claims> {
+        let header =
+            decode_header(token).map_err(|_| UnauthorizedError("malformed token".to_string()))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| UnauthorizedError("token has no key id".to_string()))?;
+
+        let key = match self.cached_key(&kid).await {
+            Some(key) => key,
+            None => {
+                self.refresh_keys().await?;
+                self.cached_key(&kid)
+                    .await
+                    .ok_or_else(|| UnauthorizedError(format!("unknown signing key: {}", kid)))?
+            }
+        };
+
+        decode::<Claims>(token, &key, &Validation::new(Algorithm::EdDSA))
+            .map(|data| data.claims)
+            .map_err(|e| UnauthorizedError(format!("invalid token: {}", e)))
+    }
+
+    /// Resolves an active API key to its owner, scopes and plan.
+    pub async fn introspect_api_key(&self, key: &str) -> Result<ApiKeyGrant> {
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| UnauthorizedError("API keys are not accepted".to_string()))?;
+        let url = format!("{}/api/auth/introspect", self.auth_url);
+        let introspection: Introspection = self
+            .http
+            .json(Method::POST, &url, |request| {
+                request
+                    .basic_auth(&client.id, Some(&client.secret))
+                    .form(&[("token", key), ("token_type_hint", "api_key")])
+            })
+            .await
+            .map_err(|e| AuthServiceError(e.to_string()))?;
+
+        match introspection {
+            Introspection {
+                active: true,
+                sub: Some(sub),
+                scope,
+                plan,
+                jti,
+            } => Ok(ApiKeyGrant {
+                sub,
+                scopes: crate::scope::parse(scope.as_deref()),
+                plan,
+                key_id: jti,
+            }),
+            _ => Err(UnauthorizedError("invalid API key".to_string())),
+        }
+    }
+
+    /// The active users going by any of `names`; unknown names are left out.
+    pub async fn resolve_users(&self, names: &[String]) -> Result<Vec<ResolvedUser>> {
+        self.resolve(serde_json::json!({ "names": names })).await
+    }
+
+    /// The active users with any of `ids`, for their names.
+    pub async fn resolve_ids(&self, ids: &[String]) -> Result<Vec<ResolvedUser>> {
+        self.resolve(serde_json::json!({ "ids": ids })).await
+    }
+
+    /// The author page of the user going by `name`; `None` when there is no
+    /// such user or they keep no author page.
+    pub async fn author_profile(&self, name: &str) -> Result<Option<AuthorProfile>> {
+        let url = format!("{}/api/users/profile", self.auth_url);
+        let lookup: ProfileLookup = self
+            .http
+            .json(Method::GET, &url, |request| {
+                request.query(&[("name", name)])
+            })
+            .await
+            .map_err(|e| AuthServiceError(e.to_string()))?;
+
+        Ok(lookup.profile)
+    }
+
+    /// User and outbox numbers for the admin overview, asked for with the
+    /// caller's own `credential`, so auth decides whether they may see them.
+    pub async fn admin_stats(&self, credential: &Credential) -> Result<AdminStats> {
+        let url = format!("{}/api/admin/stats", self.auth_url);
+        self.http
+            .json(Method::GET, &url, |request| match credential {
+                Credential::ApiKey(key) => request.header(API_KEY_HEADER, key),
+                Credential::Bearer(token) => request.bearer_auth(token),
+            })
+            .await
+            .map_err(|e| AuthServiceError(e.to_string()))
+    }
+
+    async fn resolve(&self, body: serde_json::Value) -> Result<Vec<ResolvedUser>> {
+        let url = format!("{}/api/users/resolve", self.auth_url);
+        let resolution: Resolution = self
+            .http
+            .json(Method::POST, &url, |request| request.json(&body))
+            .await
+            .map_err(|e| AuthServiceError(e.to_string()))?;
+
+        Ok(resolution.users)
+    }
+
+    pub fn breaker_metrics(&self) -> BreakerMetrics {
+        self.http.metrics()
+    }
+
+    async fn cached_key(&self, kid: &str) -> Option<DecodingKey> {
+        self.cache.read().await.keys.get(kid).cloned()
+    }
+
+    async fn refresh_keys(&self) -> Result<()> {
+        let mut cache = self.cache.write().await;
+        if let Some(fetched_at) = cache.fetched_at {
+            if fetched_at.elapsed() < JWKS_REFRESH_INTERVAL {
+                return Ok(());
+            }
+        }
+
+        let url = format!("{}/.well-known/jwks.json", self.auth_url);
+        let jwks: JwkSet = self
+            .http
+            .json(Method::GET, &url, |request| request)
+            .await
+            .map_err(|e| AuthServiceError(e.to_string()))?;
+
+        cache.keys = jwks
+            .keys
+            .into_iter()
+            .filter_map(|jwk| {
+                DecodingKey::from_ed_components(&jwk.x)
+                    .ok()
+                    .map(|key| (jwk.kid, key))
+            })
+            .collect();
+        cache.fetched_at = Some(Instant::now());
+
+        Ok(())
+    }
+}
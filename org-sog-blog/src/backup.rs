@@ -0,0 +1,163 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use org_sog_core::store::{ObjectStore, StoreError};
+
+use crate::{
+    error::MyError::{self, BackupNotFoundError, NotPermittedError, StorageError, ValidationError},
+    response::{BackupListResponse, BackupResponse, RestoreResponse},
+    AppState,
+};
+
+type Result<T> = std::result::Result<T, MyError>;
+
+const KEY_PREFIX: &str = "blog-backup-";
+
+/// Where backups go and how many are kept, from `BACKUP_DIR`,
+/// `BACKUP_RETENTION` and `BACKUP_INTERVAL_HOURS`.
+pub struct BackupConfig {
+    store: ObjectStore,
+    retention: usize,
+    interval: Option<Duration>,
+}
+
+impl BackupConfig {
+    pub fn init() -> Self {
+        let dir = std::env::var("BACKUP_DIR").unwrap_or_else(|_| "./backups".to_string());
+        let retention = std::env::var("BACKUP_RETENTION")
+            .ok()
+            .map(|value| {
+                value
+                    .parse::<usize>()
+                    .expect("BACKUP_RETENTION must be a number of backups.")
+            })
+            .unwrap_or(7)
+            .max(1);
+        let interval = std::env::var("BACKUP_INTERVAL_HOURS").ok().map(|value| {
+            let hours = value
+                .parse::<u64>()
+                .expect("BACKUP_INTERVAL_HOURS must be a number of hours.");
+            Duration::from_secs(hours.max(1) * 60 * 60)
+        });
+
+        Self {
+            store: ObjectStore::new(dir),
+            retention,
+            interval,
+        }
+    }
+}
+
+/// Takes a backup every `BACKUP_INTERVAL_HOURS`, if set.
+pub fn spawn_scheduler(app_state: Arc<AppState>) {
+    let Some(period) = app_state.backups.interval else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick fires at once; skip it so restarts don't each take a backup.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(e) = run(&app_state).await {
+                println!("⚠️ Scheduled backup failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Dumps the database into a new archive, then prunes archives past the retention count.
+pub async fn run(app_state: &AppState) -> Result<BackupResponse> {
+    let config = &app_state.backups;
+    let (archive, documents) = app_state.db.dump().await?;
+
+    let created_at = Utc::now();
+    let key = format!(
+        "{}{}.ndjson",
+        KEY_PREFIX,
+        created_at.format("%Y%m%dT%H%M%S%.3fZ")
+    );
+    config
+        .store
+        .put(&key, &archive)
+        .await
+        .map_err(store_error)?;
+
+    let backups = config.store.list(KEY_PREFIX).await.map_err(store_error)?;
+    let expired = backups.len().saturating_sub(config.retention);
+    for backup in &backups[..expired] {
+        if let Err(e) = config.store.delete(&backup.key).await {
+            println!("⚠️ Could not prune backup {}: {}", backup.key, e);
+        }
+    }
+
+    Ok(BackupResponse {
+        key,
+        size: archive.len() as u64,
+        documents: Some(documents),
+        createdAt: created_at,
+    })
+}
+
+/// Newest first.
+pub async fn list(app_state: &AppState) -> Result<BackupListResponse> {
+    let mut backups: Vec<BackupResponse> = app_state
+        .backups
+        .store
+        .list(KEY_PREFIX)
+        .await
+        .map_err(store_error)?
+        .into_iter()
+        .map(|object| BackupResponse {
+            key: object.key,
+            size: object.size,
+            documents: None,
+            createdAt: DateTime::<Utc>::from(object.modified),
+        })
+        .collect();
+    backups.reverse();
+
+    Ok(BackupListResponse {
+        status: "success",
+        results: backups.len(),
+        backups,
+    })
+}
+
+/// Loads `key` into `database`, replacing the contents of every collection in
+/// the archive. Restoring over the live database has to be asked for explicitly.
+pub async fn restore(
+    app_state: &AppState,
+    key: &str,
+    database: &str,
+    overwrite_live: bool,
+) -> Result<RestoreResponse> {
+    if database == app_state.db.database_name() && !overwrite_live {
+        return Err(NotPermittedError(
+            "restoring over the live database needs overwrite set".to_string(),
+        ));
+    }
+
+    let archive = app_state
+        .backups
+        .store
+        .get(key)
+        .await
+        .map_err(store_error)?;
+    let collections = app_state.db.restore(database, &archive).await?;
+
+    Ok(RestoreResponse {
+        status: "success",
+        database: database.to_owned(),
+        collections,
+    })
+}
+
+fn store_error(e: StoreError) -> MyError {
+    match e {
+        StoreError::NotFound(key) => BackupNotFoundError(key),
+        StoreError::InvalidKey(key) => ValidationError(format!("invalid backup key: {}", key)),
+        e => StorageError(e.to_string()),
+    }
+}
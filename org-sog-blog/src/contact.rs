@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use org_sog_core::mail::Mailer;
+use tokio::sync::Mutex;
+
+use crate::{
+    error::MyError::{self, TooManyRequestsError},
+    model::ContactMessageModel,
+};
+
+type Result<T> = std::result::Result<T, MyError>;
+
+const RATE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Everything around a contact form submission besides storing and bot
+/// checks: a per-IP rate limit and mailing the message on to `CONTACT_EMAIL`.
+///
+/// Limits are kept in memory, so each instance counts its own submissions.
+pub struct ContactService {
+    mailer: Mailer,
+    owner_email: Option<String>,
+    max_per_window: usize,
+    submissions: Mutex<HashMap<String, Vec<Instant>>>,
+}
+
+impl ContactService {
+    pub fn init(mailer: Mailer) -> Self {
+        let max_per_window = std::env::var("CONTACT_MAX_PER_HOUR")
+            .ok()
+            .map(|value| {
+                value
+                    .parse::<usize>()
+                    .expect("CONTACT_MAX_PER_HOUR must be a number.")
+            })
+            .unwrap_or(5);
+
+        let owner_email = std::env::var("CONTACT_EMAIL").ok();
+        if owner_email.is_none() {
+            println!("⚠️ CONTACT_EMAIL not set, contact messages will only be stored");
+        }
+
+        Self {
+            mailer,
+            owner_email,
+            max_per_window,
+            submissions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a submission from `ip`, failing once it has sent too many this hour.
+    pub async fn check_rate(&self, ip: &str) -> Result<()> {
+        let mut submissions = self.submissions.lock().await;
+        submissions.retain(|_, sent| {
+            sent.retain(|at| at.elapsed() < RATE_WINDOW);
+            !sent.is_empty()
+        });
+
+        let sent = submissions.entry(ip.to_owned()).or_default();
+        if sent.len() >= self.max_per_window {
+            let retry_after = RATE_WINDOW.saturating_sub(sent[0].elapsed());
+            return Err(TooManyRequestsError(retry_after.as_secs().max(1)));
+        }
+        sent.push(Instant::now());
+        Ok(())
+    }
+
+    /// Forwards a stored message to the site owner. The message is already
+    /// saved, so a mail failure is only logged.
+    pub async fn notify_owner(&self, contact: &ContactMessageModel) {
+        let Some(owner) = &self.owner_email else {
+            return;
+        };
+
+        let subject = format!(
+            "Contact form: {}",
+            contact.subject.as_deref().unwrap_or("new message")
+        );
+        let body = format!(
+            "From: {} <{}>\n\n{}\n",
+            contact.name, contact.email, contact.message
+        );
+        if let Err(e) = self
+            .mailer
+            .send_with_reply_to(owner, Some(&contact.email), &subject, body)
+            .await
+        {
+            println!("⚠️ failed to forward contact message {}: {}", contact.id, e);
+        }
+    }
+}
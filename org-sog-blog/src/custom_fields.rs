@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use mongodb::bson::{doc, Bson, Document};
+use serde_json::Value;
+
+use crate::model::{FieldDefinitionModel, FieldType};
+
+/// Query parameters starting with this filter on a custom field, e.g.
+/// `cf.location=Berlin`.
+pub const FILTER_PREFIX: &str = "cf.";
+const MAX_KEY_LEN: usize = 32;
+/// Longest a text field may be when its definition doesn't say.
+const DEFAULT_MAX_TEXT_LEN: i64 = 1000;
+
+/// Keys are stored as field names, so they're kept to lowercase letters,
+/// digits and underscores, starting with a letter.
+pub fn check_key(key: &str) -> Result<(), String> {
+    let valid = key.len() <= MAX_KEY_LEN
+        && key.starts_with(|c: char| c.is_ascii_lowercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    match valid {
+        true => Ok(()),
+        false => Err(format!(
+            "field keys are up to {} lowercase letters, digits or underscores, starting with a letter",
+            MAX_KEY_LEN
+        )),
+    }
+}
+
+/// Checks `values` against `definitions` and converts them for storage.
+/// Unknown keys are refused, as are missing required fields; `null` counts
+/// as missing.
+pub fn validate(
+    definitions: &[FieldDefinitionModel],
+    values: &BTreeMap<String, Value>,
+) -> Result<Document, String> {
+    for key in values.keys() {
+        if !definitions.iter().any(|definition| &definition.key == key) {
+            return Err(format!("unknown custom field {}", key));
+        }
+    }
+
+    let mut fields = Document::new();
+    for definition in definitions {
+        match values.get(&definition.key) {
+            None | Some(Value::Null) if definition.required => {
+                return Err(format!("custom field {} is required", definition.key));
+            }
+            None | Some(Value::Null) => {}
+            Some(value) => {
+                fields.insert(&definition.key, convert(definition, value)?);
+            }
+        }
+    }
+    Ok(fields)
+}
+
+/// The query for posts whose field `definition` matches `raw`, as given in
+/// a query string. Dates match the whole day.
+pub fn filter(definition: &FieldDefinitionModel, raw: &str) -> Result<Document, String> {
+    let path = format!("customFields.{}", definition.key);
+    let value = match definition.kind {
+        FieldType::Date => {
+            let day = parse_date(raw)
+                .ok_or_else(|| format!("cf.{} must be a date", definition.key))?
+                .date_naive();
+            let start = Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).unwrap_or_default());
+            let end = start + chrono::Duration::days(1);
+            let mut filter = Document::new();
+            filter.insert(path, doc! {"$gte": start, "$lt": end});
+            return Ok(filter);
+        }
+        FieldType::Number => raw
+            .parse::<f64>()
+            .map(Bson::Double)
+            .map_err(|_| format!("cf.{} must be a number", definition.key))?,
+        FieldType::Boolean => raw
+            .parse::<bool>()
+            .map(Bson::Boolean)
+            .map_err(|_| format!("cf.{} must be true or false", definition.key))?,
+        FieldType::Text | FieldType::Select => Bson::String(raw.to_owned()),
+    };
+    let mut filter = Document::new();
+    filter.insert(path, value);
+    Ok(filter)
+}
+
+/// A stored value as the API shows it; dates as RFC 3339.
+pub fn to_json(value: &Bson) -> Value {
+    match value {
+        Bson::DateTime(at) => Value::String(at.to_chrono().to_rfc3339()),
+        other => other.clone().into_relaxed_extjson(),
+    }
+}
+
+fn convert(definition: &FieldDefinitionModel, value: &Value) -> Result<Bson, String> {
+    let key = &definition.key;
+    match definition.kind {
+        FieldType::Text => {
+            let text = value
+                .as_str()
+                .ok_or_else(|| format!("custom field {} must be text", key))?;
+            let max_len = definition.maxLength.unwrap_or(DEFAULT_MAX_TEXT_LEN);
+            if text.chars().count() as i64 > max_len {
+                return Err(format!(
+                    "custom field {} must be at most {} characters",
+                    key, max_len
+                ));
+            }
+            Ok(Bson::String(text.to_owned()))
+        }
+        FieldType::Number => {
+            let number = value
+                .as_f64()
+                .ok_or_else(|| format!("custom field {} must be a number", key))?;
+            if definition.min.is_some_and(|min| number < min)
+                || definition.max.is_some_and(|max| number > max)
+            {
+                return Err(format!(
+                    "custom field {} must be between {} and {}",
+                    key,
+                    definition.min.unwrap_or(f64::MIN),
+                    definition.max.unwrap_or(f64::MAX)
+                ));
+            }
+            Ok(Bson::Double(number))
+        }
+        FieldType::Boolean => value
+            .as_bool()
+            .map(Bson::Boolean)
+            .ok_or_else(|| format!("custom field {} must be true or false", key)),
+        FieldType::Date => value
+            .as_str()
+            .and_then(parse_date)
+            .map(|at| Bson::DateTime(at.into()))
+            .ok_or_else(|| format!("custom field {} must be a date", key)),
+        FieldType::Select => {
+            let choice = value.as_str().unwrap_or_default();
+            let options = definition.options.as_deref().unwrap_or_default();
+            if !options.iter().any(|option| option == choice) {
+                return Err(format!(
+                    "custom field {} must be one of: {}",
+                    key,
+                    options.join(", ")
+                ));
+            }
+            Ok(Bson::String(choice.to_owned()))
+        }
+    }
+}
+
+/// An RFC 3339 timestamp, or a plain `YYYY-MM-DD` taken as midnight UTC.
+fn parse_date(raw: &str) -> Option<DateTime<Utc>> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(raw) {
+        return Some(at.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|at| Utc.from_utc_datetime(&at))
+}
@@ -1,23 +1,326 @@
+use crate::abuse::{IpRuleTable, Verdict};
+use crate::access;
+use crate::custom_fields;
+use crate::diff;
+use crate::environment::{FieldSnapshot, Snapshot, SNAPSHOT_VERSION};
 use crate::error::MyError;
-use crate::response::{BlogData, BlogListResponse, BlogResponse, SingleBlogResponse};
+use crate::feed;
+use crate::fingerprint::{self, DuplicatePolicy};
+use crate::linkcheck::{self, LinkStatus};
+use crate::metering::{MeterCounts, MeterKey};
+use crate::migrations;
+use crate::model::{
+    AccessTokenModel, ActivityKind, ActivityModel, AnalyticsEventModel, AnalyticsKind, BlockModel,
+    BookmarkModel, CategoryModel, CommentDefaults, CommentEditModel, CommentModel,
+    ContactMessageModel, ContactStatus, ContributorModel, DraftModel, EmbeddingModel,
+    FieldDefinitionModel, FieldType, FollowKind, FollowModel, IpRuleKind, IpRuleModel,
+    LinkCheckModel, MentionModel, MeteringModel, PageField, PageModel, PageSlug, PageStatus,
+    PostField, PostStatus, ReactionModel, ReadingProgressModel, RedirectModel, RevisionModel,
+    SavedSearchModel, SettingsModel, StatKind, SyndicationModel, TagStatModel, TemplateModel,
+    Visibility,
+};
+use crate::preview::PreviewGrant;
+use crate::quality;
+use crate::quota::Quotas;
+use crate::response::{
+    AccessTokenListResponse, AccessTokenResponse, ActivityListResponse, ActivityResponse,
+    AnalyticsAcceptedResponse, AuthorStatsResponse, BlockData, BlockListResponse, BlockResponse,
+    BlogData, BlogListResponse, BlogResponse, BlogSummaryResponse, BookmarkData,
+    BookmarkListResponse, BookmarkResponse, BrokenLinkListResponse, CategoryData,
+    CategoryListResponse, CategoryResponse, ChangesResponse, CommentCountsResponse, CommentData,
+    CommentEditResponse, CommentHistoryData, CommentHistoryResponse, CommentListResponse,
+    CommentResponse, CommentStatusResponse, ConsistencyIssue, ConsistencyReportResponse,
+    ContactMessageData, ContactMessageListResponse, ContactMessageResponse, ContributorResponse,
+    CountryStatsResponse, DailyStatsResponse, DraftData, DraftResponse, ErrorRateResponse,
+    FieldDefinitionData, FieldDefinitionListResponse, FieldDefinitionResponse, FollowData,
+    FollowListResponse, FollowResponse, FollowerCountResponse, HistoryEntryResponse,
+    HistoryListResponse, IndexAdviceResponse, IndexBuildData, IndexBuildListResponse, IpRuleData,
+    IpRuleListResponse, IpRuleResponse, KeyMeteringResponse, LinkCheckResponse, LinkHealthResponse,
+    MentionResponse, MeteringResponse, MeteringRollupResponse, MigrationJobData,
+    MigrationJobListResponse, MigrationJobResponse, NavItemResponse, NavigationResponse,
+    NewAccessTokenResponse, PageData, PageListResponse, PageResponse, PostCountsResponse,
+    PostStatsData, PostStatsResponse, PostView, QualityResponse, QuotaUsage, RedirectData,
+    RedirectListResponse, RedirectResponse, RestoredCollection, RevisionDiff, RevisionDiffData,
+    RevisionDiffResponse, RevisionListResponse, RevisionResponse, RouteMeteringResponse,
+    SavedSearchData, SavedSearchListResponse, SavedSearchResponse, SearchHitResponse,
+    SemanticSearchResponse, SettingsData, SettingsResponse, SingleBlockResponse,
+    SingleBlogResponse, SingleBookmarkResponse, SingleCategoryResponse, SingleCommentResponse,
+    SingleContactMessageResponse, SingleDraftResponse, SingleFieldDefinitionResponse,
+    SingleFollowResponse, SingleIndexBuildResponse, SingleIpRuleResponse,
+    SingleMigrationJobResponse, SinglePageResponse, SinglePostStatsResponse,
+    SingleRedirectResponse, SingleSavedSearchResponse, SingleSettingsResponse,
+    SingleTemplateResponse, SingleTitleTestResponse, SlowQueryListResponse, SlowQueryResponse,
+    TagStatListResponse, TagStatResponse, TemplateData, TemplateListResponse, TemplateResponse,
+    TitleCheckResponse, TitleTestData, TitleTestResponse, TitleVariantStats, UsageResponse,
+    UserMeteringResponse,
+};
+use crate::sandbox;
+use crate::schema::{
+    ActivityOptions, AnalyticsBatchSchema, AnalyticsEventSchema, BlockSchema, BookmarkListOptions,
+    BookmarkSchema, BuildIndexSchema, ContactListOptions, ContactSchema, CreateCategorySchema,
+    CreateCommentSchema, CreateIpRuleSchema, CreatePageSchema, CreateRedirectSchema,
+    CreateTemplateSchema, DraftSchema, EditCommentSchema, FieldDefinitionSchema, FilterOptions,
+    FollowSchema, Granularity, HistoryOptions, IndexAdviceOptions, MeteringOptions,
+    MigrationAction, OverdueOptions, ProgressSchema, ReactionSchema, SavedSearchSchema,
+    SettingsSchema, SlowQueryOptions, UpdatePageSchema,
+};
+use crate::semantic;
+use crate::toc;
 use crate::{
     error::MyError::*, model::BlogModel, schema::CreateBlogSchema, schema::UpdateBlogSchema,
 };
 use chrono::prelude::*;
+use chrono::DurationRound;
 use futures::StreamExt;
-use mongodb::bson::{doc, oid::ObjectId, Document};
-use mongodb::options::{FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument};
-use mongodb::{bson, options::ClientOptions, Client, Collection, IndexModel};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::options::{
+    Collation, CollationStrength, CountOptions, CreateCollectionOptions, CursorType,
+    FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions, ReplaceOptions,
+    ReturnDocument, TimeseriesGranularity, TimeseriesOptions, UpdateOptions,
+};
+use mongodb::{
+    bson, options::ClientOptions, Client, ClientSession, Collection, Cursor, Database, IndexModel,
+};
+use org_sog_core::client_ip::Cidr;
+use org_sog_core::conflict::duplicate_key;
+use org_sog_core::credentials;
+use org_sog_core::db_breaker::{DbBreaker, Guarded};
+use org_sog_core::db_metrics::DbMetrics;
+use org_sog_core::filter::Filter;
+use org_sog_core::geo::GeoLocation;
+use org_sog_core::ids::IdStrategy;
+use org_sog_core::index_advisor::{IndexAdvisor, MAX_INDEX_KEYS};
+use org_sog_core::migrate;
+use org_sog_core::migration_jobs::{MigrationJob, MigrationRunner, Transition};
+use org_sog_core::names::NamePolicy;
+use org_sog_core::page::Pagination;
+use org_sog_core::patch::Patch;
+use org_sog_core::read::{ReadFrom, ReadRouting};
+use org_sog_core::repo::Repository;
+use org_sog_core::slow_query::SlowQueryLog;
+use org_sog_core::tombstone::Tombstones;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::IpAddr;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+use tokio::sync::RwLock;
 
+/// Post writes update `tag_stats` in the same transaction, so the deployment
+/// has to be a replica set (a single-node one is enough).
 #[derive(Clone, Debug)]
 pub struct DB {
+    pub client: Client,
     pub blog_collection: Collection<BlogModel>,
     pub collection: Collection<Document>,
+    pub tag_stats_collection: Collection<TagStatModel>,
+    pub category_collection: Collection<CategoryModel>,
+    pub draft_collection: Collection<DraftModel>,
+    pub revision_collection: Collection<RevisionModel>,
+    database: Database,
+    pub analytics_collection: Collection<AnalyticsEventModel>,
+    pub settings_collection: Collection<SettingsModel>,
+    pub pages: Repository<PageModel>,
+    pub contact_collection: Collection<ContactMessageModel>,
+    pub redirect_collection: Collection<RedirectModel>,
+    pub metering_collection: Collection<MeteringModel>,
+    pub comment_collection: Collection<CommentModel>,
+    pub reaction_collection: Collection<ReactionModel>,
+    pub block_collection: Collection<BlockModel>,
+    pub follow_collection: Collection<FollowModel>,
+    pub bookmark_collection: Collection<BookmarkModel>,
+    pub progress_collection: Collection<ReadingProgressModel>,
+    pub ip_rule_collection: Collection<IpRuleModel>,
+    pub link_check_collection: Collection<LinkCheckModel>,
+    pub embedding_collection: Collection<EmbeddingModel>,
+    pub activity_collection: Collection<ActivityModel>,
+    pub saved_search_collection: Collection<SavedSearchModel>,
+    pub field_collection: Collection<FieldDefinitionModel>,
+    pub template_collection: Collection<TemplateModel>,
+    pub tombstones: Tombstones,
+    pub reads: ReadRouting,
+    stats_cache: Arc<RwLock<StatsCache>>,
+    settings_cache: Arc<RwLock<Option<(Instant, SettingsResponse)>>>,
+    redirect_cache: Cached<Arc<RedirectTable>>,
+    ip_rule_cache: Cached<Arc<IpRuleTable>>,
+    duplicate_policy: DuplicatePolicy,
+    names: NamePolicy,
+    quotas: Quotas,
+    comment_edit_window: chrono::Duration,
+    pub metrics: Arc<DbMetrics>,
+    pub breaker: Arc<DbBreaker>,
+    pub slow_queries: Arc<SlowQueryLog>,
+    pub index_advisor: IndexAdvisor,
+    pub migrations: Arc<MigrationRunner>,
 }
 
 type Result<T> = std::result::Result<T, MyError>;
 
+/// Post stats by post and period, with when they were computed.
+type StatsCache = HashMap<(ObjectId, i64), (Instant, PostStatsResponse)>;
+
+/// A table loaded from the database, with when it was loaded.
+type Cached<T> = Arc<RwLock<Option<(Instant, T)>>>;
+
+const ANALYTICS_COLLECTION: &str = "analytics";
+const ANALYTICS_RETENTION_DAYS: u64 = 400;
+const MAX_ANALYTICS_BATCH: usize = 100;
+const MAX_VISITOR_ID_LEN: usize = 128;
+const MAX_REFERRER_LEN: usize = 2048;
+/// How far ahead of the server clock a client timestamp may be.
+const ANALYTICS_CLOCK_SKEW_MINUTES: i64 = 5;
+const MAX_STATS_DAYS: i64 = 90;
+/// Countries listed in post stats; the rest go uncounted there.
+const MAX_STATS_COUNTRIES: i64 = 20;
+const STATS_CACHE_TTL: StdDuration = StdDuration::from_secs(60);
+/// Alternatives allowed next to the main title.
+const MAX_TITLE_VARIANTS: usize = 4;
+/// Impressions each variant needs before a title test names a leader.
+const MIN_TEST_IMPRESSIONS: i64 = 100;
+
+/// `_id` of the single settings document.
+const SETTINGS_ID: &str = "site";
+const TITLE_INDEX: &str = "title_ci";
+/// Writes refresh this instance's copy at once; other instances pick them up
+/// when their copy expires.
+const SETTINGS_CACHE_TTL: StdDuration = StdDuration::from_secs(300);
+const MAX_POSTS_PER_PAGE: i64 = 100;
+const MAX_SOCIAL_LINKS: usize = 20;
+const MAX_SLUG_LEN: usize = 64;
+/// Where the front-end serves pages, for redirects created on slug changes.
+const PAGE_PATH_PREFIX: &str = "/pages/";
+const REDIRECT_CACHE_TTL: StdDuration = StdDuration::from_secs(60);
+/// How long a ban or rule change can take to reach other instances.
+const IP_RULE_CACHE_TTL: StdDuration = StdDuration::from_secs(30);
+const MAX_IP_RULE_REASON_LEN: usize = 200;
+/// Temporary rules last at most a year.
+const MAX_IP_RULE_MINUTES: i64 = 365 * 24 * 60;
+/// Documents per `insert_many` when restoring a backup.
+const RESTORE_BATCH_SIZE: usize = 1000;
+const MAX_CONTACT_NAME_LEN: usize = 200;
+const MAX_CONTACT_SUBJECT_LEN: usize = 200;
+const MAX_CONTACT_MESSAGE_LEN: usize = 10_000;
+/// Visibilities left out of listings and the changes feed.
+const HIDDEN_VISIBILITIES: &[&str] = &["unlisted", "protected"];
+const SEARCH_INDEX: &str = "title_summary_content_text";
+/// Posts each side of a search ranks before the two are blended.
+const SEARCH_CANDIDATES: usize = 200;
+const MAX_SEARCH_QUERY_LEN: usize = 200;
+const MAX_ACCESS_TOKENS: usize = 50;
+const MAX_ACCESS_TOKEN_LABEL_LEN: usize = 100;
+const MAX_COMMENT_LEN: usize = 5000;
+const MAX_COMMENT_NAME_LEN: usize = 100;
+/// How deep replies may nest; replying deeper than this is refused.
+const MAX_COMMENT_DEPTH: usize = 8;
+const DEFAULT_INLINE_REPLIES: i64 = 3;
+const MAX_INLINE_REPLIES: i64 = 20;
+/// Longest a post can be set to keep taking comments, about ten years.
+const MAX_COMMENT_LOCK_DAYS: i64 = 3650;
+const MAX_REACTION_KINDS: usize = 20;
+const MAX_SYNDICATION_URL_LEN: usize = 2048;
+/// Blocks are applied as one `$nin` on every comment read, so keep them bounded.
+const MAX_BLOCKS: u64 = 1000;
+const MAX_FOLLOWS: u64 = 1000;
+const AUTHOR_TOP_TAGS: i64 = 5;
+const MAX_BOOKMARKS: u64 = 5000;
+const MAX_BOOKMARK_LABELS: usize = 10;
+const MAX_BOOKMARK_FOLDER_LEN: usize = 64;
+const HISTORY_RETENTION_DAYS: u64 = 180;
+/// Older entries past this many are dropped as new ones come in.
+const MAX_HISTORY_ENTRIES: u64 = 500;
+/// Scrolled this far, a post counts as read to the end.
+const FINISHED_POSITION: f64 = 0.95;
+const DEFAULT_INDEX_ADVICE_DAYS: i64 = 7;
+/// The slow query log keeps two weeks.
+const MAX_INDEX_ADVICE_DAYS: i64 = 14;
+/// Long enough for emoji joined from several code points, like family emoji.
+const MAX_REACTION_LEN: usize = 16;
+const ACTIVITY_COLLECTION: &str = "activity";
+/// The activity stream is capped at this size; older entries make way for new ones.
+const ACTIVITY_LOG_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_ACTIVITY_ENTRIES: u64 = 50_000;
+const DEFAULT_ACTIVITY_LIMIT: i64 = 50;
+const MAX_ACTIVITY_LIMIT: i64 = 200;
+const MAX_CUSTOM_FIELDS: u64 = 50;
+const MAX_FIELD_LABEL_LEN: usize = 100;
+const MAX_FIELD_OPTIONS: usize = 100;
+const MAX_TEMPLATES: u64 = 100;
+const MAX_TEMPLATE_NAME_LEN: usize = 100;
+const MAX_TITLE_PATTERN_LEN: usize = 200;
+const MAX_SAVED_SEARCHES: u64 = 100;
+const MAX_SAVED_SEARCH_NAME_LEN: usize = 100;
+/// Longest a poll for new activity is held open; under the default request timeout.
+const MAX_ACTIVITY_WAIT_SECS: u64 = 20;
+
+/// Autosaves closer together than this belong to the same draft version.
+const DRAFT_VERSION_DEBOUNCE_SECS: i64 = 30;
+
+/// Redirect targets by normalized source path: status and `Location`, if any.
+pub type RedirectTable = HashMap<String, (u16, Option<String>)>;
+
+/// Author recorded on content whose author has been anonymized.
+pub const DELETED_AUTHOR: &str = "deleted-user";
+
+/// Who is writing to a post, for the ownership checks on edit and delete.
+pub struct Actor<'a> {
+    pub id: &'a str,
+    /// Holders of `blog:admin` may edit or delete any post.
+    pub admin: bool,
+}
+
+/// What to narrow a list of posts to; unset fields match every post. Post
+/// listings and saved searches both filter through [`post_filter`].
+pub struct PostCriteria<'a> {
+    pub status: Option<PostStatus>,
+    pub tag: Option<&'a str>,
+    /// Matches posts the user owns or contributes to.
+    pub author: Option<&'a str>,
+    pub category: Option<&'a str>,
+    /// With `category`, also match posts filed under any of its subcategories.
+    pub include_descendants: bool,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Custom field keys and the values they must have, as given in the query.
+    pub custom_fields: &'a [(String, String)],
+}
+
+impl<'a> PostCriteria<'a> {
+    pub fn from_options(opts: &'a FilterOptions) -> Self {
+        Self {
+            status: opts.status,
+            tag: opts.tag.as_deref(),
+            author: opts.author.as_deref(),
+            category: opts.category.as_deref(),
+            include_descendants: opts.include_descendants.unwrap_or(false),
+            from: opts.from,
+            to: opts.to,
+            custom_fields: &opts.custom_fields,
+        }
+    }
+
+    pub fn from_saved(search: &'a SavedSearchModel) -> Self {
+        Self {
+            status: search.status,
+            tag: search.tag.as_deref(),
+            author: search.author.as_deref(),
+            category: search.category.as_deref(),
+            include_descendants: search.includeDescendants,
+            from: search.from.map(|at| at.to_chrono()),
+            to: search.to.map(|at| at.to_chrono()),
+            custom_fields: &[],
+        }
+    }
+}
+
+/// Who is reading a post, for the checks on protected ones.
+pub struct Viewer<'a> {
+    /// Owners, contributors and admins can always read a post.
+    pub actor: Option<Actor<'a>>,
+    /// A password or access token offered for the post.
+    pub access: Option<&'a str>,
+}
+
 impl DB {
     pub async fn init() -> Result<Self> {
         let mongodb_uri = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set.");
@@ -28,182 +331,6616 @@ impl DB {
 
         let mut client_options = ClientOptions::parse(mongodb_uri).await?;
         client_options.app_name = Some(database_name.to_string());
+        let slow_queries = Arc::new(SlowQueryLog::init());
+        let metrics = Arc::new(DbMetrics::with_slow_query_log(slow_queries.clone()));
+        metrics.install(&mut client_options);
 
         let client = Client::with_options(client_options)?;
         let database = client.database(database_name.as_str());
+        let breaker = Arc::new(DbBreaker::new());
 
         let blog_collection = database.collection(collection_name.as_str());
         let collection = database.collection::<Document>(collection_name.as_str());
+        let tag_stats_collection = database.collection("tag_stats");
+        let category_collection = database.collection("categories");
+        let draft_collection = database.collection("drafts");
+        let revision_collection = database.collection("revisions");
+        let analytics_collection = database.collection(ANALYTICS_COLLECTION);
+        let settings_collection = database.collection("settings");
+        let reads = ReadRouting::init();
+        // Nothing refers to pages by id, so they alone follow `ID_STRATEGY`.
+        let ids = IdStrategy::init();
+        let pages = Repository::new(database.collection("pages"), reads.clone(), ids);
+        let contact_collection = database.collection("contact_messages");
+        let redirect_collection = database.collection("redirects");
+        let metering_collection = database.collection("metering");
+        let comment_collection = database.collection("comments");
+        let reaction_collection = database.collection("comment_reactions");
+        let block_collection = database.collection("blocks");
+        let follow_collection = database.collection("follows");
+        let bookmark_collection = database.collection("bookmarks");
+        let progress_collection = database.collection("reading_progress");
+        let ip_rule_collection = database.collection("ip_rules");
+        let link_check_collection = database.collection("link_checks");
+        let embedding_collection = database.collection("embeddings");
+        let activity_collection = database.collection(ACTIVITY_COLLECTION);
+        let saved_search_collection = database.collection("saved_searches");
+        let field_collection = database.collection("custom_fields");
+        let template_collection = database.collection("templates");
 
-        println!("✅ Database connected successfully");
+        let existing = database
+            .list_collection_names(doc! {"name": ANALYTICS_COLLECTION})
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if existing.is_empty() {
+            let timeseries = TimeseriesOptions::builder()
+                .time_field("occurredAt".to_string())
+                .meta_field(Some("postId".to_string()))
+                .granularity(Some(TimeseriesGranularity::Minutes))
+                .build();
+            let options = CreateCollectionOptions::builder()
+                .timeseries(timeseries)
+                .expire_after_seconds(StdDuration::from_secs(
+                    ANALYTICS_RETENTION_DAYS * 24 * 60 * 60,
+                ))
+                .build();
+            database
+                .create_collection(ANALYTICS_COLLECTION, options)
+                .guarded(&breaker)
+                .await
+                .map_err(MongoQueryError)?;
+        }
 
-        Ok(Self {
-            blog_collection,
-            collection,
-        })
-    }
+        // Tailing the activity stream needs a capped collection.
+        let existing = database
+            .list_collection_names(doc! {"name": ACTIVITY_COLLECTION})
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if existing.is_empty() {
+            let options = CreateCollectionOptions::builder()
+                .capped(true)
+                .size(ACTIVITY_LOG_BYTES)
+                .max(MAX_ACTIVITY_ENTRIES)
+                .build();
+            database
+                .create_collection(ACTIVITY_COLLECTION, options)
+                .guarded(&breaker)
+                .await
+                .map_err(MongoQueryError)?;
+        }
 
-    pub async fn fetch_blogs(&self, limit: i64, page: i64) -> Result<BlogListResponse> {
-        let find_options = FindOptions::builder()
-            .limit(limit)
-            .skip(u64::try_from((page - 1) * limit).unwrap())
+        // Titles used to be unique only as typed; "Hello" and "hello" now clash.
+        let options = IndexOptions::builder()
+            .name(TITLE_INDEX.to_string())
+            .unique(true)
+            .collation(title_collation())
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"title": 1})
+            .options(options)
             .build();
+        if let Err(e) = migrate::ensure_index(&blog_collection, index, &["title_1"]).await {
+            println!(
+                "⚠️ Could not make titles case-insensitively unique, keeping the old index: {}",
+                e
+            );
+        }
 
-        let mut cursor = self
-            .blog_collection
-            .find(None, find_options)
+        let index = IndexModel::builder()
+            .keys(doc! {"fingerprintBands": 1})
+            .build();
+        blog_collection
+            .create_index(index, None)
+            .guarded(&breaker)
             .await
             .map_err(MongoQueryError)?;
 
-        let mut json_result: Vec<BlogResponse> = Vec::new();
-        while let Some(doc) = cursor.next().await {
-            json_result.push(self.doc_to_blog(&doc.unwrap())?);
-        }
+        // Delta sync reads posts in the order they changed.
+        let index = IndexModel::builder()
+            .keys(doc! {"updatedAt": 1, "_id": 1})
+            .build();
+        blog_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        Ok(BlogListResponse {
-            status: "success",
-            results: json_result.len(),
-            blogs: json_result,
-        })
-    }
+        // Custom fields are defined at runtime, so one wildcard index covers them all.
+        let index = IndexModel::builder()
+            .keys(doc! {"customFields.$**": 1})
+            .build();
+        blog_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-    pub async fn create_blog(&self, body: &CreateBlogSchema) -> Result<SingleBlogResponse> {
-        let published = body.published.to_owned().unwrap_or(false);
-        let category = body.category.to_owned().unwrap_or_default();
+        // Only drafts with a deadline are ever looked up by it.
+        let options = IndexOptions::builder()
+            .partial_filter_expression(doc! {"dueAt": {"$exists": true}})
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"published": 1, "dueAt": 1})
+            .options(options)
+            .build();
+        blog_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "hour": 1})
+            .build();
+        metering_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let index = IndexModel::builder().keys(doc! {"hour": 1}).build();
+        metering_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let document = self.create_blog_document(body, published, category)?;
+        // Counts an author's posts this month against their plan.
+        let index = IndexModel::builder()
+            .keys(doc! {"author": 1, "createdAt": -1})
+            .build();
+        blog_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
         let options = IndexOptions::builder().unique(true).build();
         let index = IndexModel::builder()
-            .keys(doc! {"title": 1})
+            .keys(doc! {"kind": 1, "name": 1})
             .options(options)
             .build();
+        tag_stats_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        match self.blog_collection.create_index(index, None).await {
-            Ok(_) => {}
-            Err(e) => return Err(MongoQueryError(e)),
-        };
-
-        let insert_result = match self.collection.insert_one(&document, None).await {
-            Ok(result) => result,
-            Err(e) => {
-                if e.to_string()
-                    .contains("E11000 duplicate key error collection")
-                {
-                    return Err(MongoDuplicateError(e));
-                }
-                return Err(MongoQueryError(e));
-            }
-        };
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"name": 1})
+            .options(options)
+            .build();
+        category_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let new_id = insert_result
-            .inserted_id
-            .as_object_id()
-            .expect("issue with new _id");
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"postId": 1, "userId": 1})
+            .options(options)
+            .build();
+        draft_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let blog_doc = match self
-            .blog_collection
-            .find_one(doc! {"_id": new_id}, None)
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"postId": 1, "number": -1})
+            .options(options)
+            .build();
+        revision_collection
+            .create_index(index, None)
+            .guarded(&breaker)
             .await
-        {
-            Ok(Some(doc)) => doc,
-            Ok(None) => return Err(NotFoundError(new_id.to_string())),
-            Err(e) => return Err(MongoQueryError(e)),
-        };
+            .map_err(MongoQueryError)?;
 
-        Ok(SingleBlogResponse {
-            status: "success",
-            data: BlogData {
-                blog: self.doc_to_blog(&blog_doc)?,
-            },
-        })
-    }
+        pages.ensure_unique_keys().await.map_err(MongoQueryError)?;
 
-    pub async fn get_blog(&self, id: &str) -> Result<SingleBlogResponse> {
-        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"from": 1})
+            .options(options)
+            .build();
+        redirect_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let blog_doc = self
-            .blog_collection
-            .find_one(doc! {"_id":oid }, None)
+        // Top-level comments of a post, then the replies under each comment.
+        let options = IndexOptions::builder()
+            .name("postId_1_parentId_1_createdAt_1".to_string())
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"postId": 1, "parentId": 1, "createdAt": 1})
+            .options(options)
+            .build();
+        migrate::ensure_index(&comment_collection, index, &["postId_1_createdAt_1"])
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let index = IndexModel::builder()
+            .keys(doc! {"parentId": 1, "createdAt": 1})
+            .build();
+        comment_collection
+            .create_index(index, None)
+            .guarded(&breaker)
             .await
             .map_err(MongoQueryError)?;
 
-        match blog_doc {
-            Some(doc) => {
-                let blog = self.doc_to_blog(&doc)?;
-                Ok(SingleBlogResponse {
-                    status: "success",
-                    data: BlogData { blog },
-                })
-            }
-            None => Err(NotFoundError(id.to_string())),
-        }
-    }
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"commentId": 1, "userId": 1, "emoji": 1})
+            .options(options)
+            .build();
+        reaction_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-    pub async fn edit_blog(&self, id: &str, body: &UpdateBlogSchema) -> Result<SingleBlogResponse> {
-        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        // Covers both listing a user's blocks and checking a single one.
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"blocker": 1, "blocked": 1})
+            .options(options)
+            .build();
+        block_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let update = doc! {
-            "$set": bson::to_document(body).map_err(MongoSerializeBsonError)?,
-        };
+        // One follow per user and target; the second index finds followers.
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "kind": 1, "target": 1})
+            .options(options)
+            .build();
+        follow_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let index = IndexModel::builder()
+            .keys(doc! {"kind": 1, "target": 1, "userId": 1})
+            .build();
+        follow_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let options = FindOneAndUpdateOptions::builder()
-            .return_document(ReturnDocument::After)
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"name": 1})
+            .options(options)
             .build();
+        template_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        if let Some(doc) = self
-            .blog_collection
-            .find_one_and_update(doc! {"_id": oid}, update, options)
+        // Saved search names are per editor.
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"owner": 1, "name": 1})
+            .options(options)
+            .build();
+        saved_search_collection
+            .create_index(index, None)
+            .guarded(&breaker)
             .await
-            .map_err(MongoQueryError)?
-        {
-            let blog = self.doc_to_blog(&doc)?;
-            let blog_response = SingleBlogResponse {
-                status: "success",
-                data: BlogData { blog },
-            };
-            Ok(blog_response)
-        } else {
-            Err(NotFoundError(id.to_string()))
-        }
-    }
+            .map_err(MongoQueryError)?;
 
-    pub async fn delete_blog(&self, id: &str) -> Result<()> {
-        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
-        let filter = doc! {"_id": oid };
+        // One bookmark per user and post; the second index pages through folders.
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "postId": 1})
+            .options(options)
+            .build();
+        bookmark_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "folder": 1, "createdAt": -1})
+            .build();
+        bookmark_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-        let result = self
-            .collection
-            .delete_one(filter, None)
+        // One entry per user and post, listed most recent first; entries
+        // nobody came back to for a while expire.
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "postId": 1})
+            .options(options)
+            .build();
+        progress_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let index = IndexModel::builder()
+            .keys(doc! {"userId": 1, "updatedAt": -1})
+            .build();
+        progress_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let options = IndexOptions::builder()
+            .expire_after(StdDuration::from_secs(
+                HISTORY_RETENTION_DAYS * 24 * 60 * 60,
+            ))
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"updatedAt": 1})
+            .options(options)
+            .build();
+        progress_collection
+            .create_index(index, None)
+            .guarded(&breaker)
             .await
             .map_err(MongoQueryError)?;
 
-        match result.deleted_count {
-            0 => Err(NotFoundError(id.to_string())),
-            _ => Ok(()),
-        }
-    }
+        // Lets expired bans clean themselves up.
+        let options = IndexOptions::builder()
+            .expire_after(StdDuration::from_secs(0))
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"expiresAt": 1})
+            .options(options)
+            .build();
+        ip_rule_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
 
-    fn doc_to_blog(&self, blog: &BlogModel) -> Result<BlogResponse> {
-        let blog_response = BlogResponse {
-            id: blog.id.to_hex(),
-            title: blog.title.to_owned(),
-            summary: blog.summary.to_owned(),
-            content: blog.content.to_owned(),
-            category: blog.category.to_owned().unwrap(),
-            published: blog.published.unwrap(),
-            createdAt: blog.createdAt,
-            updatedAt: blog.updatedAt,
-        };
+        // One record per link per post; the report lists broken ones oldest first.
+        let options = IndexOptions::builder().unique(true).build();
+        let index = IndexModel::builder()
+            .keys(doc! {"postId": 1, "url": 1})
+            .options(options)
+            .build();
+        link_check_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let index = IndexModel::builder()
+            .keys(doc! {"broken": 1, "brokenSince": 1})
+            .build();
+        link_check_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // Keyword search, weighted towards the title.
+        let options = IndexOptions::builder()
+            .name(SEARCH_INDEX.to_string())
+            .weights(doc! {"title": 10, "summary": 5, "content": 1})
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"title": "text", "summary": "text", "content": "text"})
+            .options(options)
+            .build();
+        blog_collection
+            .create_index(index, None)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let tombstones = Tombstones::init(&database).await.map_err(MongoQueryError)?;
+        slow_queries
+            .attach(&database)
+            .guarded(&breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let index_advisor = IndexAdvisor::new(&client, &database);
+        let migrations = Arc::new(MigrationRunner::init(
+            &database,
+            migrations::all(&collection_name),
+        ));
+
+        println!("✅ Database connected successfully");
+
+        Ok(Self {
+            client,
+            blog_collection,
+            collection,
+            tag_stats_collection,
+            category_collection,
+            draft_collection,
+            revision_collection,
+            database,
+            analytics_collection,
+            settings_collection,
+            pages,
+            contact_collection,
+            redirect_collection,
+            metering_collection,
+            comment_collection,
+            reaction_collection,
+            block_collection,
+            follow_collection,
+            bookmark_collection,
+            progress_collection,
+            ip_rule_collection,
+            link_check_collection,
+            embedding_collection,
+            activity_collection,
+            saved_search_collection,
+            field_collection,
+            template_collection,
+            tombstones,
+            reads,
+            stats_cache: Arc::new(RwLock::new(HashMap::new())),
+            settings_cache: Arc::new(RwLock::new(None)),
+            redirect_cache: Arc::new(RwLock::new(None)),
+            ip_rule_cache: Arc::new(RwLock::new(None)),
+            duplicate_policy: DuplicatePolicy::init(),
+            names: NamePolicy::init(),
+            quotas: Quotas::init(),
+            comment_edit_window: comment_edit_window(),
+            metrics,
+            breaker,
+            slow_queries,
+            index_advisor,
+            migrations,
+        })
+    }
+
+    /// Lists public posts, and with `include_hidden` unlisted and protected
+    /// ones too.
+    pub async fn fetch_blogs(
+        &self,
+        paging: Pagination,
+        opts: &FilterOptions,
+        include_hidden: bool,
+        read: ReadFrom,
+    ) -> Result<BlogListResponse> {
+        self.list_posts(
+            paging,
+            &PostCriteria::from_options(opts),
+            include_hidden,
+            opts.visitor.as_deref(),
+            read,
+        )
+        .await
+    }
+
+    async fn list_posts(
+        &self,
+        paging: Pagination,
+        criteria: &PostCriteria<'_>,
+        include_hidden: bool,
+        visitor: Option<&str>,
+        read: ReadFrom,
+    ) -> Result<BlogListResponse> {
+        // Sorted on `_id` so pages stay stable while posts are written, for
+        // clients such as the GDPR export that walk every page.
+        let find_options = FindOptions::builder()
+            .sort(doc! {"_id": 1})
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .build();
+        let mut filter = post_filter(criteria, include_hidden)?;
+        if !criteria.custom_fields.is_empty() {
+            filter.extend(
+                self.custom_field_filter(criteria.custom_fields, read)
+                    .await?,
+            );
+        }
+
+        let blogs = self
+            .find_summaries(filter, find_options, visitor, read)
+            .await?;
+
+        Ok(BlogListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: blogs.len(),
+            blogs,
+        })
+    }
+
+    /// Posts matching `filter` in the order found, without their content,
+    /// which isn't even read from the database. Titles are the ones
+    /// `visitor` is bucketed into, when given.
+    async fn find_summaries(
+        &self,
+        filter: Document,
+        mut options: FindOptions,
+        visitor: Option<&str>,
+        read: ReadFrom,
+    ) -> Result<Vec<BlogSummaryResponse>> {
+        options.projection = Some(doc! {"content": 0});
+        options.selection_criteria = Some(self.reads.criteria(read));
+        let mut cursor = self
+            .blog_collection
+            .find(filter, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let comments = self.settings(read).await?.comments;
+        let mut blogs = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let mut blog = self.doc_to_blog(&doc.map_err(MongoQueryError)?, &comments)?;
+            serve_title_variant(&mut blog, visitor);
+            blogs.push(summarize(blog));
+        }
+        Ok(blogs)
+    }
+
+    pub async fn create_blog(
+        &self,
+        body: &CreateBlogSchema,
+        author: &str,
+    ) -> Result<SingleBlogResponse> {
+        if body.title.trim().is_empty() {
+            return Err(ValidationError("title must not be empty".to_string()));
+        }
+        self.names.check_text(&body.title)?;
+        if let Some(settings) = &body.commentSettings {
+            check_lock_after_days(settings.lockAfterDays)?;
+        }
+        if let Some(syndication) = &body.syndication {
+            check_syndication(syndication)?;
+        }
+        self.check_content_quota(author, body.content.len(), 0)
+            .await?;
+        let published = body.published.to_owned().unwrap_or(false);
+        let category = match &body.category {
+            Some(category) => category.to_owned(),
+            None => self
+                .settings(ReadFrom::Primary)
+                .await?
+                .defaultCategory
+                .unwrap_or_default(),
+        };
+
+        let category_path = self.category_path(&category).await?;
+        let custom_fields = self
+            .check_custom_fields(body.customFields.as_ref().unwrap_or(&BTreeMap::new()))
+            .await?;
+        let mut document =
+            self.create_blog_document(body, published, category, category_path, author)?;
+        document.insert("customFields", custom_fields);
+
+        let fingerprint = fingerprint::simhash(&body.content);
+        let duplicates = match fingerprint {
+            Some(hash) if self.duplicate_policy != DuplicatePolicy::Off => {
+                self.find_duplicates(hash).await?
+            }
+            _ => Vec::new(),
+        };
+        if !duplicates.is_empty() && self.duplicate_policy == DuplicatePolicy::Reject {
+            return Err(DuplicateContentError(duplicates));
+        }
+        document.extend(fingerprint_fields(fingerprint));
+
+        let mut session = self.start_transaction().await?;
+
+        match self
+            .collection
+            .insert_one_with_session(&document, None, &mut session)
+            .await
+        {
+            Ok(_) => {}
+            Err(e) => {
+                return Err(write_error(e));
+            }
+        };
+
+        // The document carries its own `_id`, so it already is what was stored.
+        let blog_doc: BlogModel = bson::from_document(document)?;
+
+        self.adjust_tag_stats(&mut session, None, Some(&blog_doc))
+            .await?;
+        self.record_revision(&mut session, &blog_doc, Some(author))
+            .await?;
+        self.finish_transaction(session).await?;
+
+        let comments = self.settings(ReadFrom::Primary).await?.comments;
+        Ok(SingleBlogResponse {
+            status: "success",
+            data: BlogData {
+                blog: self.doc_to_blog(&blog_doc, &comments)?,
+            },
+            possibleDuplicates: duplicates,
+        })
+    }
+
+    /// Whether `title` is free, comparing case-insensitively as the unique index does.
+    pub async fn check_title(
+        &self,
+        title: &str,
+        exclude: Option<&str>,
+        read: ReadFrom,
+    ) -> Result<TitleCheckResponse> {
+        let mut filter = doc! {"title": title.trim()};
+        if let Some(id) = exclude {
+            let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+            filter.insert("_id", doc! {"$ne": oid});
+        }
+
+        let options = FindOneOptions::builder()
+            .projection(doc! {"_id": 1})
+            .collation(title_collation())
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let taken = self
+            .collection
+            .find_one(filter, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let taken_by = match taken {
+            Some(doc) => Some(doc.get_object_id("_id")?.to_hex()),
+            None => None,
+        };
+        Ok(TitleCheckResponse {
+            status: "success",
+            title: title.trim().to_owned(),
+            available: taken_by.is_none(),
+            takenBy: taken_by,
+        })
+    }
+
+    pub async fn get_blog(
+        &self,
+        id: &str,
+        visitor: Option<&str>,
+        viewer: &Viewer<'_>,
+        read: ReadFrom,
+    ) -> Result<SingleBlogResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let blog_doc = self
+            .blog_collection
+            .find_one(doc! {"_id":oid }, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        match blog_doc {
+            Some(doc) => {
+                check_can_read(&doc, viewer)?;
+                let comments = self.settings(read).await?.comments;
+                let mut blog = self.doc_to_blog(&doc, &comments)?;
+                serve_title_variant(&mut blog, visitor);
+                Ok(SingleBlogResponse {
+                    status: "success",
+                    data: BlogData { blog },
+                    possibleDuplicates: Vec::new(),
+                })
+            }
+            None => Err(NotFoundError(id.to_string())),
+        }
+    }
+
+    pub async fn edit_blog(
+        &self,
+        id: &str,
+        body: &UpdateBlogSchema,
+        actor: &Actor<'_>,
+    ) -> Result<SingleBlogResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        if let Some(title) = &body.title {
+            self.names.check_text(title)?;
+        }
+        if let Some(settings) = &body.commentSettings {
+            check_lock_after_days(settings.lockAfterDays)?;
+        }
+        if let Some(syndication) = &body.syndication {
+            check_syndication(syndication)?;
+        }
+
+        let mut patch = Patch::from_set(bson::to_document(body).map_err(MongoSerializeBsonError)?);
+        if let Some(tags) = &body.tags {
+            patch.set("tags", normalize_tags(tags));
+        }
+        if let Some(category) = &body.category {
+            patch.set("categoryPath", self.category_path(category).await?);
+        }
+        if let Some(variants) = &body.titleVariants {
+            patch.set("titleVariants", normalize_title_variants(variants)?);
+        }
+        if let Some(content) = &body.content {
+            patch.extend(fingerprint_fields(fingerprint::simhash(content)));
+            patch.set("toc", bson::to_bson(&toc::extract(content))?);
+            patch.set("quality", bson::to_bson(&quality::measure(content))?);
+        }
+        if let Some(values) = &body.customFields {
+            patch.set("customFields", self.check_custom_fields(values).await?);
+        }
+        patch.nullable("dueAt", body.dueAt);
+        patch.set("updatedAt", Utc::now());
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let mut session = self.start_transaction().await?;
+
+        let Some(before) = self
+            .blog_collection
+            .find_one_with_session(doc! {"_id": oid}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        else {
+            return Err(NotFoundError(id.to_string()));
+        };
+
+        if body.contributors.is_some() && !is_owner(&before, actor) {
+            return Err(NotPermittedError(
+                "only the owner can change contributors".to_string(),
+            ));
+        }
+        if (body.visibility.is_some() || body.password.is_some()) && !is_owner(&before, actor) {
+            return Err(NotPermittedError(
+                "only the owner can change who may read a post".to_string(),
+            ));
+        }
+        check_can_edit(&before, actor)?;
+        // Content counts against the owner, whoever edits it.
+        if let (Some(content), Some(owner)) = (&body.content, &before.author) {
+            self.check_content_quota(owner, content.len(), before.content.len())
+                .await?;
+        }
+
+        if let Some(contributors) = &body.contributors {
+            patch.set(
+                "contributors",
+                bson::to_bson(&contributors_without(
+                    contributors,
+                    before.author.as_deref(),
+                ))?,
+            );
+        }
+        match body.password.as_deref() {
+            Some("") => {
+                patch.unset("accessHash");
+            }
+            Some(password) => {
+                patch.set("accessHash", access::hash_password(password));
+            }
+            None => {}
+        }
+        let update = patch.into_update();
+
+        if let Some(doc) = self
+            .blog_collection
+            .find_one_and_update_with_session(doc! {"_id": oid}, update, options, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(write_error)?
+        {
+            self.adjust_tag_stats(&mut session, Some(&before), Some(&doc))
+                .await?;
+            if !self.has_revisions(&mut session, &before.id).await? {
+                self.record_revision(&mut session, &before, before.author.as_deref())
+                    .await?;
+            }
+            self.record_revision(&mut session, &doc, Some(actor.id))
+                .await?;
+            self.finish_transaction(session).await?;
+
+            let comments = self.settings(ReadFrom::Primary).await?.comments;
+            let blog = self.doc_to_blog(&doc, &comments)?;
+            let blog_response = SingleBlogResponse {
+                status: "success",
+                data: BlogData { blog },
+                possibleDuplicates: Vec::new(),
+            };
+            Ok(blog_response)
+        } else {
+            Err(NotFoundError(id.to_string()))
+        }
+    }
+
+    pub async fn delete_blog(&self, id: &str, actor: &Actor<'_>) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let filter = doc! {"_id": oid };
+
+        let mut session = self.start_transaction().await?;
+
+        let Some(blog) = self
+            .blog_collection
+            .find_one_with_session(filter.clone(), None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        else {
+            return Err(NotFoundError(id.to_string()));
+        };
+
+        if !is_owner(&blog, actor) {
+            return Err(NotPermittedError(
+                "only the owner can delete a post".to_string(),
+            ));
+        }
+
+        self.blog_collection
+            .delete_one_with_session(filter, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.draft_collection
+            .delete_many_with_session(doc! {"postId": oid}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.revision_collection
+            .delete_many_with_session(doc! {"postId": oid}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.comment_collection
+            .delete_many_with_session(doc! {"postId": oid}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.reaction_collection
+            .delete_many_with_session(doc! {"postId": oid}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.bookmark_collection
+            .delete_many_with_session(doc! {"postId": oid}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.progress_collection
+            .delete_many_with_session(doc! {"postId": oid}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.adjust_tag_stats(&mut session, Some(&blog), None)
+            .await?;
+
+        self.tombstones
+            .record_with_session("post", id, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.finish_transaction(session).await?;
+
+        Ok(())
+    }
+
+    /// Posts created, updated or deleted since `since`, oldest change first.
+    ///
+    /// Posts are paged on `(updatedAt, _id)`, so a bulk update stamping many
+    /// posts with one time can't hold the checkpoint in place. Deletions
+    /// stamped exactly at the returned checkpoint may be sent again on the
+    /// next call, so clients should apply them idempotently. Without
+    /// `include_hidden`, drafts and posts that are unlisted or protected are
+    /// reported as deleted, so mirrors drop them when they stop being public.
+    pub async fn fetch_changes(
+        &self,
+        since: &str,
+        limit: i64,
+        include_hidden: bool,
+    ) -> Result<ChangesResponse> {
+        let (since, after) = parse_checkpoint(since)?;
+        // Taken before reading, so writes landing during the reads are still
+        // after the checkpoint handed out.
+        let now = Utc::now();
+
+        let filter = match after {
+            Some(after) => doc! {"$or": [
+                {"updatedAt": {"$gt": since}},
+                {"updatedAt": since, "_id": {"$gt": after}},
+            ]},
+            None => doc! {"updatedAt": {"$gte": since}},
+        };
+        // Always the primary: a lagging secondary would hand out a checkpoint
+        // past writes it hasn't seen yet.
+        let options = FindOptions::builder()
+            .sort(doc! {"updatedAt": 1, "_id": 1})
+            .limit(limit + 1)
+            .build();
+        let mut cursor = self
+            .blog_collection
+            .find(filter, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut posts = Vec::new();
+        while let Some(post) = cursor.next().await {
+            posts.push(post.map_err(MongoQueryError)?);
+        }
+
+        let tombstones = self
+            .tombstones
+            .since("post", since, limit + 1)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // When either list was cut short, stop both where the first one ran
+        // out, so neither skips past the other. Posts stop after the last one
+        // sent, deletions before the first one that didn't fit.
+        let mut last_post = None;
+        if posts.len() as i64 > limit {
+            let post = &posts[limit as usize - 1];
+            last_post = Some((post.updatedAt, post.id));
+        }
+        let mut first_tombstone = None;
+        if tombstones.len() as i64 > limit {
+            first_tombstone = Some(tombstones[limit as usize].deletedAt);
+        }
+        let (checkpoint, after) = match (last_post, first_tombstone) {
+            (Some((at, id)), Some(deleted_at)) if at < deleted_at => (at, Some(id)),
+            (Some((at, id)), None) => (at, Some(id)),
+            (_, Some(deleted_at)) => (deleted_at, None),
+            (None, None) => (now, None),
+        };
+        let sent = |post: &BlogModel| match after {
+            Some(after) => (post.updatedAt, post.id) <= (checkpoint, after),
+            None => post.updatedAt < checkpoint,
+        };
+
+        let comments = self.settings(ReadFrom::Primary).await?.comments;
+        let mut created = Vec::new();
+        let mut updated = Vec::new();
+        let mut deleted = Vec::new();
+        for post in posts.iter().filter(|post| sent(post)) {
+            let public = post.published.unwrap_or(false) && post.visibility == Visibility::Public;
+            if !include_hidden && !public {
+                deleted.push(post.id.to_hex());
+                continue;
+            }
+            let blog = self.doc_to_blog(post, &comments)?;
+            if post.createdAt >= since {
+                created.push(blog);
+            } else {
+                updated.push(blog);
+            }
+        }
+        deleted.extend(
+            tombstones
+                .into_iter()
+                .filter(|tombstone| tombstone.deletedAt < checkpoint)
+                .map(|tombstone| tombstone.resourceId),
+        );
+
+        let millis = checkpoint.timestamp_millis();
+        Ok(ChangesResponse {
+            status: "success",
+            checkpoint: match after {
+                Some(after) => format!("{}_{}", millis, after.to_hex()),
+                None => millis.to_string(),
+            },
+            hasMore: last_post.is_some() || first_tombstone.is_some(),
+            created,
+            updated,
+            deleted,
+        })
+    }
+
+    pub async fn fetch_revisions(
+        &self,
+        id: &str,
+        viewer: &Viewer<'_>,
+        read: ReadFrom,
+    ) -> Result<RevisionListResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        self.check_readable(&oid, viewer, read).await?;
+
+        let find_options = FindOptions::builder()
+            .sort(doc! {"number": -1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+
+        let mut cursor = self
+            .revision_collection
+            .find(doc! {"postId": oid}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<RevisionResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_revision(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(RevisionListResponse {
+            status: "success",
+            results: json_result.len(),
+            revisions: json_result,
+        })
+    }
+
+    pub async fn diff_revisions(
+        &self,
+        id: &str,
+        from: i64,
+        to: i64,
+        granularity: Granularity,
+        viewer: &Viewer<'_>,
+        read: ReadFrom,
+    ) -> Result<RevisionDiffResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        self.check_readable(&oid, viewer, read).await?;
+
+        let find_options = FindOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .revision_collection
+            .find(
+                doc! {"postId": oid, "number": {"$in": [from, to]}},
+                find_options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut revisions: Vec<RevisionModel> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            revisions.push(doc.map_err(MongoQueryError)?);
+        }
+
+        let find = |number: i64| {
+            revisions
+                .iter()
+                .find(|revision| revision.number == number)
+                .ok_or(RevisionNotFoundError(number))
+        };
+        let old = find(from)?;
+        let new = find(to)?;
+
+        Ok(RevisionDiffResponse {
+            status: "success",
+            data: RevisionDiffData {
+                diff: RevisionDiff {
+                    from,
+                    to,
+                    granularity,
+                    fields: diff::changed_fields(old, new),
+                    content: diff::diff_text(&old.content, &new.content, granularity),
+                },
+            },
+        })
+    }
+
+    /// Validates a batch of reader events and stores it in one insert. A bad
+    /// event rejects the whole batch so clients notice instead of losing data.
+    pub async fn ingest_analytics(
+        &self,
+        body: &AnalyticsBatchSchema,
+        geo: Option<&GeoLocation>,
+    ) -> Result<AnalyticsAcceptedResponse> {
+        if body.events.is_empty() || body.events.len() > MAX_ANALYTICS_BATCH {
+            return Err(ValidationError(format!(
+                "a batch must hold between 1 and {} events",
+                MAX_ANALYTICS_BATCH
+            )));
+        }
+
+        let now = Utc::now();
+        let events = body
+            .events
+            .iter()
+            .enumerate()
+            .map(|(index, event)| {
+                analytics_event(event, now, geo)
+                    .map_err(|reason| ValidationError(format!("event {}: {}", index, reason)))
+            })
+            .collect::<Result<Vec<AnalyticsEventModel>>>()?;
+
+        let mut post_ids: Vec<ObjectId> = events.iter().map(|event| event.postId).collect();
+        post_ids.sort();
+        post_ids.dedup();
+
+        // Number of titles each referenced post can be shown with.
+        let options = FindOptions::builder()
+            .projection(doc! {"titleVariants": 1})
+            .build();
+        let mut cursor = self
+            .collection
+            .find(doc! {"_id": {"$in": post_ids.clone()}}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut titles: HashMap<ObjectId, usize> = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            if let Ok(id) = doc.get_object_id("_id") {
+                let variants = doc.get_array("titleVariants").map_or(0, |v| v.len());
+                titles.insert(id, variants + 1);
+            }
+        }
+        if titles.len() != post_ids.len() {
+            return Err(ValidationError(
+                "batch references a post that does not exist".to_string(),
+            ));
+        }
+        for (index, event) in events.iter().enumerate() {
+            if let Some(variant) = event.variant {
+                if variant as usize >= titles[&event.postId] {
+                    return Err(ValidationError(format!(
+                        "event {}: post has no title variant {}",
+                        index, variant
+                    )));
+                }
+            }
+        }
+
+        self.analytics_collection
+            .insert_many(&events, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(AnalyticsAcceptedResponse {
+            status: "success",
+            accepted: events.len(),
+        })
+    }
+
+    /// Views and unique visitors per UTC day over the last `days` days.
+    pub async fn post_stats(
+        &self,
+        id: &str,
+        days: i64,
+        read: ReadFrom,
+    ) -> Result<SinglePostStatsResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let days = days.clamp(1, MAX_STATS_DAYS);
+
+        if let Some((cached_at, stats)) = self.stats_cache.read().await.get(&(oid, days)) {
+            if cached_at.elapsed() < STATS_CACHE_TTL {
+                return Ok(stats_response(stats.clone()));
+            }
+        }
+
+        if self
+            .collection
+            .count_documents(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            == 0
+        {
+            return Err(NotFoundError(id.to_string()));
+        }
+
+        let midnight = Utc::now().date_naive().and_hms_opt(0, 0, 0).unwrap();
+        let since = DateTime::<Utc>::from_utc(midnight, Utc) - chrono::Duration::days(days - 1);
+
+        let pipeline = vec![
+            doc! {"$match": {
+                "postId": oid,
+                "type": AnalyticsKind::View.as_str(),
+                "occurredAt": {"$gte": bson::DateTime::from_chrono(since)},
+            }},
+            doc! {"$facet": {
+                "daily": [
+                    {"$group": {
+                        "_id": {"$dateToString": {"format": "%Y-%m-%d", "date": "$occurredAt"}},
+                        "views": {"$sum": 1},
+                        "visitors": {"$addToSet": "$visitorId"},
+                    }},
+                    {"$project": {"views": 1, "uniques": {"$size": "$visitors"}}},
+                    {"$sort": {"_id": 1}},
+                ],
+                "totals": [
+                    {"$group": {
+                        "_id": null,
+                        "views": {"$sum": 1},
+                        "visitors": {"$addToSet": "$visitorId"},
+                    }},
+                    {"$project": {"views": 1, "uniques": {"$size": "$visitors"}}},
+                ],
+                "countries": [
+                    {"$match": {"geo.country": {"$type": "string"}}},
+                    {"$group": {
+                        "_id": "$geo.country",
+                        "views": {"$sum": 1},
+                        "visitors": {"$addToSet": "$visitorId"},
+                    }},
+                    {"$project": {"views": 1, "uniques": {"$size": "$visitors"}}},
+                    {"$sort": {"views": -1, "_id": 1}},
+                    {"$limit": MAX_STATS_COUNTRIES},
+                ],
+            }},
+        ];
+
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .analytics_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let facets: StatsFacets = match cursor.next().await {
+            Some(doc) => bson::from_document(doc.map_err(MongoQueryError)?)?,
+            None => StatsFacets::default(),
+        };
+        let totals = facets.totals.first();
+
+        let stats = PostStatsResponse {
+            postId: oid.to_hex(),
+            days,
+            views: totals.map_or(0, |totals| totals.views),
+            uniques: totals.map_or(0, |totals| totals.uniques),
+            daily: facets
+                .daily
+                .into_iter()
+                .map(|row| DailyStatsResponse {
+                    date: row.day.unwrap_or_default(),
+                    views: row.views,
+                    uniques: row.uniques,
+                })
+                .collect(),
+            countries: facets
+                .countries
+                .into_iter()
+                .map(|row| CountryStatsResponse {
+                    country: row.country,
+                    views: row.views,
+                    uniques: row.uniques,
+                })
+                .collect(),
+        };
+
+        let mut cache = self.stats_cache.write().await;
+        cache.retain(|_, (cached_at, _)| cached_at.elapsed() < STATS_CACHE_TTL);
+        cache.insert((oid, days), (Instant::now(), stats.clone()));
+
+        Ok(stats_response(stats))
+    }
+
+    /// Impressions, clicks and click-through rate for each of a post's titles.
+    pub async fn title_test(&self, id: &str, read: ReadFrom) -> Result<SingleTitleTestResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let Some(blog) = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        else {
+            return Err(NotFoundError(id.to_string()));
+        };
+
+        let pipeline = vec![
+            doc! {"$match": {
+                "postId": oid,
+                "type": {"$in": [AnalyticsKind::Impression.as_str(), AnalyticsKind::Click.as_str()]},
+            }},
+            doc! {"$group": {
+                "_id": "$variant",
+                "impressions": {"$sum": {
+                    "$cond": [{"$eq": ["$type", AnalyticsKind::Impression.as_str()]}, 1, 0]
+                }},
+                "clicks": {"$sum": {
+                    "$cond": [{"$eq": ["$type", AnalyticsKind::Click.as_str()]}, 1, 0]
+                }},
+            }},
+        ];
+
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .analytics_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut counts: HashMap<usize, VariantRow> = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let row: VariantRow = bson::from_document(doc.map_err(MongoQueryError)?)?;
+            if let Some(variant) = row.variant {
+                counts.insert(variant as usize, row);
+            }
+        }
+
+        let titles = std::iter::once(&blog.title).chain(blog.titleVariants.iter().flatten());
+        let variants: Vec<TitleVariantStats> = titles
+            .enumerate()
+            .map(|(variant, title)| {
+                let (impressions, clicks) = counts
+                    .get(&variant)
+                    .map_or((0, 0), |row| (row.impressions, row.clicks));
+                TitleVariantStats {
+                    variant,
+                    title: title.to_owned(),
+                    impressions,
+                    clicks,
+                    ctr: if impressions > 0 {
+                        clicks as f64 / impressions as f64
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+
+        let decided = variants.len() > 1
+            && variants
+                .iter()
+                .all(|stats| stats.impressions >= MIN_TEST_IMPRESSIONS);
+        let leader = if decided {
+            variants
+                .iter()
+                .max_by(|a, b| a.ctr.total_cmp(&b.ctr))
+                .map(|stats| stats.variant)
+        } else {
+            None
+        };
+
+        Ok(SingleTitleTestResponse {
+            status: "success",
+            data: TitleTestData {
+                test: TitleTestResponse {
+                    postId: oid.to_hex(),
+                    variants,
+                    leader,
+                },
+            },
+        })
+    }
+
+    pub async fn get_settings(&self, read: ReadFrom) -> Result<SingleSettingsResponse> {
+        Ok(SingleSettingsResponse {
+            status: "success",
+            data: SettingsData {
+                settings: self.settings(read).await?,
+            },
+        })
+    }
+
+    pub async fn update_settings(
+        &self,
+        body: &SettingsSchema,
+        user_id: &str,
+    ) -> Result<SingleSettingsResponse> {
+        let site_title = body.siteTitle.trim();
+        if site_title.is_empty() {
+            return Err(ValidationError("siteTitle must not be empty".to_string()));
+        }
+        if !(1..=MAX_POSTS_PER_PAGE).contains(&body.postsPerPage) {
+            return Err(ValidationError(format!(
+                "postsPerPage must be between 1 and {}",
+                MAX_POSTS_PER_PAGE
+            )));
+        }
+        if body.socialLinks.len() > MAX_SOCIAL_LINKS {
+            return Err(ValidationError(format!(
+                "at most {} social links are allowed",
+                MAX_SOCIAL_LINKS
+            )));
+        }
+        for link in &body.socialLinks {
+            if link.name.trim().is_empty()
+                || !(link.url.starts_with("https://") || link.url.starts_with("http://"))
+            {
+                return Err(ValidationError(format!(
+                    "social link {:?} needs a name and an http(s) url",
+                    link.name
+                )));
+            }
+        }
+        check_lock_after_days(body.comments.lockAfterDays)?;
+        check_reactions(&body.comments.reactions)?;
+
+        let settings = SettingsModel {
+            id: SETTINGS_ID.to_string(),
+            siteTitle: site_title.to_owned(),
+            description: body.description.trim().to_owned(),
+            postsPerPage: body.postsPerPage,
+            defaultCategory: body
+                .defaultCategory
+                .as_deref()
+                .map(str::trim)
+                .filter(|category| !category.is_empty())
+                .map(str::to_owned),
+            socialLinks: body.socialLinks.to_owned(),
+            theme: body.theme.to_owned(),
+            comments: body.comments.to_owned(),
+            suggestions: body.suggestions,
+            updatedBy: Some(user_id.to_owned()),
+            updatedAt: Utc::now(),
+        };
+
+        let options = mongodb::options::ReplaceOptions::builder()
+            .upsert(true)
+            .build();
+        self.settings_collection
+            .replace_one(doc! {"_id": SETTINGS_ID}, &settings, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let response = self.doc_to_settings(&settings);
+        *self.settings_cache.write().await = Some((Instant::now(), response.clone()));
+
+        Ok(SingleSettingsResponse {
+            status: "success",
+            data: SettingsData { settings: response },
+        })
+    }
+
+    /// The current settings, falling back to defaults until an admin saves some.
+    pub async fn settings(&self, read: ReadFrom) -> Result<SettingsResponse> {
+        if let Some((cached_at, settings)) = self.settings_cache.read().await.as_ref() {
+            if cached_at.elapsed() < SETTINGS_CACHE_TTL {
+                return Ok(settings.clone());
+            }
+        }
+
+        let settings = match self
+            .settings_collection
+            .find_one(doc! {"_id": SETTINGS_ID}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            Some(settings) => self.doc_to_settings(&settings),
+            None => SettingsResponse {
+                siteTitle: "Blog".to_string(),
+                description: String::new(),
+                postsPerPage: 10,
+                defaultCategory: None,
+                socialLinks: Vec::new(),
+                theme: Default::default(),
+                comments: Default::default(),
+                suggestions: false,
+                updatedAt: None,
+            },
+        };
+
+        *self.settings_cache.write().await = Some((Instant::now(), settings.clone()));
+        Ok(settings)
+    }
+
+    pub async fn fetch_pages(
+        &self,
+        include_drafts: bool,
+        read: ReadFrom,
+    ) -> Result<PageListResponse> {
+        let filter = if include_drafts {
+            Filter::All
+        } else {
+            Filter::eq(PageField::Status, PageStatus::Published.as_str())
+        };
+
+        let pages = self
+            .pages
+            .find(filter.to_document(), Some(doc! {"slug": 1}), read)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(PageListResponse {
+            status: "success",
+            results: pages.len(),
+            pages: pages.iter().map(|page| self.doc_to_page(page)).collect(),
+        })
+    }
+
+    /// Drafts are only returned when `include_drafts` is set.
+    pub async fn get_page(
+        &self,
+        slug: &str,
+        include_drafts: bool,
+        read: ReadFrom,
+    ) -> Result<SinglePageResponse> {
+        match self
+            .pages
+            .find_by::<PageSlug>(slug, read)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            Some(page) if include_drafts || page.status == PageStatus::Published => {
+                Ok(self.page_response(&page))
+            }
+            _ => Err(PageNotFoundError(slug.to_owned())),
+        }
+    }
+
+    pub async fn create_page(
+        &self,
+        body: &CreatePageSchema,
+        author: &str,
+    ) -> Result<SinglePageResponse> {
+        check_slug(&body.slug)?;
+        self.names.check_name(&body.slug)?;
+
+        let now = Utc::now();
+        let page = PageModel {
+            id: self.pages.new_id(),
+            slug: body.slug.to_owned(),
+            title: body.title.to_owned(),
+            content: body.content.to_owned(),
+            status: body.status.unwrap_or_default(),
+            navOrder: body.navOrder,
+            navLabel: body.navLabel.to_owned(),
+            author: Some(author.to_owned()),
+            createdAt: now,
+            updatedAt: now,
+        };
+
+        match self.pages.insert(&page).await {
+            Ok(_) => Ok(self.page_response(&page)),
+            Err(e) if is_duplicate_key(&e) => Err(SlugTakenError(page.slug)),
+            Err(e) => Err(MongoQueryError(e)),
+        }
+    }
+
+    pub async fn edit_page(
+        &self,
+        slug: &str,
+        body: &UpdatePageSchema,
+    ) -> Result<SinglePageResponse> {
+        if let Some(new_slug) = &body.slug {
+            check_slug(new_slug)?;
+            self.names.check_name(new_slug)?;
+        }
+
+        let mut changes = bson::to_document(body).map_err(MongoSerializeBsonError)?;
+        changes.insert("updatedAt", Utc::now());
+
+        match self
+            .pages
+            .update_by::<PageSlug>(slug, doc! {"$set": changes})
+            .await
+        {
+            Ok(Some(page)) => {
+                if page.slug != slug {
+                    self.move_path(
+                        &format!("{}{}", PAGE_PATH_PREFIX, slug),
+                        &format!("{}{}", PAGE_PATH_PREFIX, page.slug),
+                    )
+                    .await?;
+                }
+                Ok(self.page_response(&page))
+            }
+            Ok(None) => Err(PageNotFoundError(slug.to_owned())),
+            Err(e) if is_duplicate_key(&e) => {
+                Err(SlugTakenError(body.slug.to_owned().unwrap_or_default()))
+            }
+            Err(e) => Err(MongoQueryError(e)),
+        }
+    }
+
+    pub async fn delete_page(&self, slug: &str) -> Result<()> {
+        if self
+            .pages
+            .delete_by::<PageSlug>(slug)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            // Pages are addressed by slug, so that is what the tombstone names.
+            self.tombstones
+                .record("page", slug)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            Ok(())
+        } else {
+            Err(PageNotFoundError(slug.to_owned()))
+        }
+    }
+
+    /// Published pages that have a menu position, in menu order.
+    pub async fn navigation(&self, read: ReadFrom) -> Result<NavigationResponse> {
+        let pages = self
+            .pages
+            .find(
+                Filter::eq(PageField::Status, PageStatus::Published.as_str())
+                    .and(Filter::present(PageField::NavOrder))
+                    .to_document(),
+                Some(doc! {"navOrder": 1, "slug": 1}),
+                read,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let items: Vec<NavItemResponse> = pages
+            .into_iter()
+            .map(|page| NavItemResponse {
+                label: page.navLabel.unwrap_or(page.title),
+                order: page.navOrder.unwrap_or_default(),
+                slug: page.slug,
+            })
+            .collect();
+
+        Ok(NavigationResponse {
+            status: "success",
+            results: items.len(),
+            items,
+        })
+    }
+
+    pub async fn fetch_redirects(&self, read: ReadFrom) -> Result<RedirectListResponse> {
+        let options = FindOptions::builder()
+            .sort(doc! {"from": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .redirect_collection
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut redirects: Vec<RedirectResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            redirects.push(self.doc_to_redirect(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(RedirectListResponse {
+            status: "success",
+            results: redirects.len(),
+            redirects,
+        })
+    }
+
+    pub async fn create_redirect(
+        &self,
+        body: &CreateRedirectSchema,
+    ) -> Result<SingleRedirectResponse> {
+        let status = body.status.unwrap_or(301);
+        let from = normalize_path(&body.from)
+            .ok_or_else(|| ValidationError("from must be a path starting with /".to_string()))?;
+
+        let to = match (status, body.to.as_deref()) {
+            (301 | 302, Some(to)) if to.starts_with("https://") || to.starts_with("http://") => {
+                to.to_owned()
+            }
+            (301 | 302, Some(to)) => normalize_path(to).ok_or_else(|| {
+                ValidationError("to must be a path or an http(s) url".to_string())
+            })?,
+            (301 | 302, None) => {
+                return Err(ValidationError(format!(
+                    "a {} redirect needs a target",
+                    status
+                )))
+            }
+            (410, None) => String::new(),
+            (410, Some(_)) => {
+                return Err(ValidationError("a 410 has no target".to_string()));
+            }
+            _ => {
+                return Err(ValidationError(
+                    "status must be 301, 302 or 410".to_string(),
+                ))
+            }
+        };
+        if from == to {
+            return Err(ValidationError(
+                "a path cannot redirect to itself".to_string(),
+            ));
+        }
+
+        let redirect = RedirectModel {
+            id: ObjectId::new(),
+            from,
+            to: Some(to).filter(|to| !to.is_empty()),
+            status,
+            createdAt: Utc::now(),
+        };
+
+        match self.redirect_collection.insert_one(&redirect, None).await {
+            Ok(_) => {}
+            Err(e) if is_duplicate_key(&e) => return Err(RedirectExistsError(redirect.from)),
+            Err(e) => return Err(MongoQueryError(e)),
+        }
+        self.invalidate_redirects().await;
+
+        Ok(SingleRedirectResponse {
+            status: "success",
+            data: RedirectData {
+                redirect: self.doc_to_redirect(&redirect),
+            },
+        })
+    }
+
+    pub async fn delete_redirect(&self, id: &str) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let result = self
+            .redirect_collection
+            .delete_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.deleted_count == 0 {
+            return Err(NotFoundError(id.to_string()));
+        }
+        self.tombstones
+            .record("redirect", id)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.invalidate_redirects().await;
+
+        Ok(())
+    }
+
+    /// The redirect for `path`, if any, served from a table reloaded at most
+    /// once per [`REDIRECT_CACHE_TTL`].
+    pub async fn resolve_redirect(&self, path: &str) -> Result<Option<(u16, Option<String>)>> {
+        let Some(path) = normalize_path(path) else {
+            return Ok(None);
+        };
+
+        let cached = self
+            .redirect_cache
+            .read()
+            .await
+            .as_ref()
+            .filter(|(loaded_at, _)| loaded_at.elapsed() < REDIRECT_CACHE_TTL)
+            .map(|(_, table)| table.clone());
+        let table = match cached {
+            Some(table) => table,
+            None => {
+                let mut cursor = self
+                    .redirect_collection
+                    .find(None, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+                let mut table = RedirectTable::new();
+                while let Some(doc) = cursor.next().await {
+                    let redirect = doc.map_err(MongoQueryError)?;
+                    table.insert(redirect.from, (redirect.status, redirect.to));
+                }
+
+                let table = Arc::new(table);
+                *self.redirect_cache.write().await = Some((Instant::now(), table.clone()));
+                table
+            }
+        };
+
+        Ok(table.get(&path).cloned())
+    }
+
+    /// Points `from` at `to` after content moved, rewriting older redirects so
+    /// they lead straight to the new location instead of through a chain.
+    async fn move_path(&self, from: &str, to: &str) -> Result<()> {
+        self.redirect_collection
+            .update_many(doc! {"to": from}, doc! {"$set": {"to": to}}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        // Content lives at `to` again, so nothing should send it away.
+        self.redirect_collection
+            .delete_one(doc! {"from": to}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.redirect_collection
+            .update_one(
+                doc! {"from": from},
+                doc! {
+                    "$set": {"to": to, "status": 301},
+                    "$setOnInsert": {"_id": ObjectId::new(), "createdAt": Utc::now()},
+                },
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.invalidate_redirects().await;
+        Ok(())
+    }
+
+    async fn invalidate_redirects(&self) {
+        *self.redirect_cache.write().await = None;
+    }
+
+    /// Every IP rule, including automatic bans, newest first.
+    pub async fn fetch_ip_rules(&self, read: ReadFrom) -> Result<IpRuleListResponse> {
+        let options = FindOptions::builder()
+            .sort(doc! {"createdAt": -1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .ip_rule_collection
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut rules: Vec<IpRuleResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            rules.push(doc_to_ip_rule(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(IpRuleListResponse {
+            status: "success",
+            results: rules.len(),
+            rules,
+        })
+    }
+
+    pub async fn create_ip_rule(
+        &self,
+        body: &CreateIpRuleSchema,
+        user_id: &str,
+    ) -> Result<SingleIpRuleResponse> {
+        let cidr = Cidr::parse(&body.cidr).ok_or_else(|| {
+            ValidationError(format!(
+                "{:?} is not an IP address or CIDR range",
+                body.cidr
+            ))
+        })?;
+        let reason = body
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty());
+        if reason.is_some_and(|reason| reason.chars().count() > MAX_IP_RULE_REASON_LEN) {
+            return Err(ValidationError(format!(
+                "reason must be at most {} characters",
+                MAX_IP_RULE_REASON_LEN
+            )));
+        }
+        let expires_at = match body.expiresInMinutes {
+            Some(minutes) if !(1..=MAX_IP_RULE_MINUTES).contains(&minutes) => {
+                return Err(ValidationError(format!(
+                    "expiresInMinutes must be between 1 and {}",
+                    MAX_IP_RULE_MINUTES
+                )))
+            }
+            Some(minutes) => Some(Utc::now() + chrono::Duration::minutes(minutes)),
+            None => None,
+        };
+
+        let rule = IpRuleModel {
+            id: ObjectId::new(),
+            kind: body.kind,
+            cidr: cidr.to_string(),
+            reason: reason.map(str::to_owned),
+            automatic: false,
+            createdBy: Some(user_id.to_owned()),
+            expiresAt: expires_at.map(bson::DateTime::from_chrono),
+            createdAt: Utc::now(),
+        };
+        self.ip_rule_collection
+            .insert_one(&rule, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.invalidate_ip_rules().await;
+
+        Ok(SingleIpRuleResponse {
+            status: "success",
+            data: IpRuleData {
+                rule: doc_to_ip_rule(&rule),
+            },
+        })
+    }
+
+    pub async fn delete_ip_rule(&self, id: &str) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let result = self
+            .ip_rule_collection
+            .delete_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.deleted_count == 0 {
+            return Err(IpRuleNotFoundError(id.to_string()));
+        }
+        self.invalidate_ip_rules().await;
+
+        Ok(())
+    }
+
+    /// Bans `ip` for `duration` after it kept getting rate limited.
+    pub async fn ban_ip(&self, ip: &IpAddr, duration: chrono::Duration) -> Result<()> {
+        let Some(cidr) = Cidr::parse(&ip.to_string()) else {
+            return Ok(());
+        };
+        let now = Utc::now();
+        let rule = IpRuleModel {
+            id: ObjectId::new(),
+            kind: IpRuleKind::Deny,
+            cidr: cidr.to_string(),
+            reason: Some("rate limited repeatedly".to_string()),
+            automatic: true,
+            createdBy: None,
+            expiresAt: Some(bson::DateTime::from_chrono(now + duration)),
+            createdAt: now,
+        };
+        self.ip_rule_collection
+            .insert_one(&rule, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.invalidate_ip_rules().await;
+
+        Ok(())
+    }
+
+    /// What the IP rules say about `ip`, from a table reloaded at most once
+    /// per [`IP_RULE_CACHE_TTL`].
+    pub async fn ip_verdict(&self, ip: &IpAddr) -> Result<Verdict> {
+        let cached = self
+            .ip_rule_cache
+            .read()
+            .await
+            .as_ref()
+            .filter(|(loaded_at, _)| loaded_at.elapsed() < IP_RULE_CACHE_TTL)
+            .map(|(_, table)| table.clone());
+        let table = match cached {
+            Some(table) => table,
+            None => {
+                let mut cursor = self
+                    .ip_rule_collection
+                    .find(None, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+                let mut table = IpRuleTable::default();
+                while let Some(doc) = cursor.next().await {
+                    let rule = doc.map_err(MongoQueryError)?;
+                    // Rules are validated on the way in; skip any edited by hand.
+                    let Some(cidr) = Cidr::parse(&rule.cidr) else {
+                        continue;
+                    };
+                    match rule.kind {
+                        IpRuleKind::Allow => table.allow.push(cidr),
+                        IpRuleKind::Deny => table
+                            .deny
+                            .push((cidr, rule.expiresAt.map(|at| at.to_chrono()))),
+                    }
+                }
+
+                let table = Arc::new(table);
+                *self.ip_rule_cache.write().await = Some((Instant::now(), table.clone()));
+                table
+            }
+        };
+
+        Ok(table.verdict(ip))
+    }
+
+    async fn invalidate_ip_rules(&self) {
+        *self.ip_rule_cache.write().await = None;
+    }
+
+    pub fn database_name(&self) -> &str {
+        self.database.name()
+    }
+
+    /// Every collection as NDJSON: one `{"collection", "document"}` object per
+    /// line, documents in canonical extended JSON so their types survive a
+    /// restore. Returns the archive and the number of documents in it.
+    ///
+    /// The archive is built in memory, which is fine at blog scale.
+    pub async fn dump(&self) -> Result<(Vec<u8>, usize)> {
+        let mut names = self
+            .database
+            .list_collection_names(None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        names.retain(|name| !name.starts_with("system."));
+        names.sort();
+
+        let mut archive: Vec<u8> = Vec::new();
+        let mut documents = 0;
+        for name in names {
+            let mut cursor = self
+                .database
+                .collection::<Document>(&name)
+                .find(None, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            while let Some(doc) = cursor.next().await {
+                let line = serde_json::json!({
+                    "collection": name,
+                    "document": Bson::Document(doc.map_err(MongoQueryError)?).into_canonical_extjson(),
+                });
+                archive.extend_from_slice(line.to_string().as_bytes());
+                archive.push(b'\n');
+                documents += 1;
+            }
+        }
+
+        Ok((archive, documents))
+    }
+
+    /// Every post, drafts and hidden ones included, oldest first. They are
+    /// read as the cursor is drained, so exports needn't hold them all.
+    pub async fn export_posts(&self, read: ReadFrom) -> Result<Cursor<BlogModel>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"createdAt": 1, "_id": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        self.blog_collection
+            .find(None, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)
+    }
+
+    /// Replaces the contents of every collection named in `archive` within
+    /// `database`. The whole archive is parsed before anything is written, so
+    /// a corrupt file leaves the target untouched.
+    pub async fn restore(&self, database: &str, archive: &[u8]) -> Result<Vec<RestoredCollection>> {
+        let mut collections: Vec<(String, Vec<Document>)> = Vec::new();
+        for (index, line) in archive.split(|byte| *byte == b'\n').enumerate() {
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let (name, document) = parse_archive_line(line)
+                .map_err(|reason| ValidationError(format!("line {}: {}", index + 1, reason)))?;
+
+            match collections.last_mut() {
+                Some((last, documents)) if *last == name => documents.push(document),
+                _ => collections.push((name, vec![document])),
+            }
+        }
+
+        let target = self.client.database(database);
+        let mut restored: Vec<RestoredCollection> = Vec::new();
+        for (name, documents) in collections {
+            let collection = target.collection::<Document>(&name);
+            // Emptying rather than dropping keeps indexes and collection options.
+            collection
+                .delete_many(doc! {}, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            for batch in documents.chunks(RESTORE_BATCH_SIZE) {
+                collection
+                    .insert_many(batch, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+            }
+            restored.push(RestoredCollection {
+                name,
+                documents: documents.len(),
+            });
+        }
+
+        Ok(restored)
+    }
+
+    /// Validates and stores a contact form submission.
+    pub async fn create_contact_message(
+        &self,
+        body: &ContactSchema,
+        ip: Option<&str>,
+    ) -> Result<ContactMessageModel> {
+        let name = body.name.trim();
+        let email = body.email.trim();
+        let subject = body
+            .subject
+            .as_deref()
+            .map(str::trim)
+            .filter(|subject| !subject.is_empty());
+        let message = body.message.trim();
+
+        if name.is_empty() || name.len() > MAX_CONTACT_NAME_LEN {
+            return Err(ValidationError(format!(
+                "name must be 1 to {} characters",
+                MAX_CONTACT_NAME_LEN
+            )));
+        }
+        if !looks_like_email(email) {
+            return Err(ValidationError("email is not a valid address".to_string()));
+        }
+        if subject.is_some_and(|subject| subject.len() > MAX_CONTACT_SUBJECT_LEN) {
+            return Err(ValidationError(format!(
+                "subject must be at most {} characters",
+                MAX_CONTACT_SUBJECT_LEN
+            )));
+        }
+        if message.is_empty() || message.len() > MAX_CONTACT_MESSAGE_LEN {
+            return Err(ValidationError(format!(
+                "message must be 1 to {} characters",
+                MAX_CONTACT_MESSAGE_LEN
+            )));
+        }
+
+        let contact = ContactMessageModel {
+            id: ObjectId::new(),
+            name: name.to_owned(),
+            email: email.to_owned(),
+            subject: subject.map(str::to_owned),
+            message: message.to_owned(),
+            status: ContactStatus::New,
+            ip: ip.map(str::to_owned),
+            createdAt: Utc::now(),
+        };
+
+        self.contact_collection
+            .insert_one(&contact, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(contact)
+    }
+
+    /// Newest first.
+    pub async fn fetch_contact_messages(
+        &self,
+        paging: Pagination,
+        opts: &ContactListOptions,
+        read: ReadFrom,
+    ) -> Result<ContactMessageListResponse> {
+        let find_options = FindOptions::builder()
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .sort(doc! {"createdAt": -1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+
+        let filter = match opts.status {
+            Some(status) => doc! {"status": status.as_str()},
+            None => doc! {},
+        };
+
+        let mut cursor = self
+            .contact_collection
+            .find(filter, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut messages: Vec<ContactMessageResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            messages.push(self.doc_to_contact(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(ContactMessageListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: messages.len(),
+            messages,
+        })
+    }
+
+    /// The links of each published post, up to `max_per_post`.
+    pub async fn published_post_links(
+        &self,
+        max_per_post: usize,
+    ) -> Result<Vec<(ObjectId, Vec<String>)>> {
+        let find_options = FindOptions::builder()
+            .projection(doc! {"_id": 1, "content": 1})
+            .build();
+        let mut cursor = self
+            .collection
+            .find(doc! {"published": true}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut posts = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            let mut links = linkcheck::extract_links(doc.get_str("content").unwrap_or_default());
+            links.truncate(max_per_post);
+            posts.push((doc.get_object_id("_id")?, links));
+        }
+        Ok(posts)
+    }
+
+    /// Public posts published since `since`, newest first, as the feed ranks them.
+    pub async fn feed_candidates(
+        &self,
+        since: DateTime<Utc>,
+        limit: i64,
+        read: ReadFrom,
+    ) -> Result<Vec<feed::Candidate>> {
+        let find_options = FindOptions::builder()
+            .projection(feed_projection())
+            .sort(doc! {"createdAt": -1})
+            .limit(limit)
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let filter = doc! {
+            "published": true,
+            "visibility": {"$nin": HIDDEN_VISIBILITIES},
+            "createdAt": {"$gte": bson::DateTime::from_chrono(since)},
+        };
+        let mut cursor = self
+            .collection
+            .find(filter, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut candidates = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            candidates.push(doc_to_candidate(&doc.map_err(MongoQueryError)?)?);
+        }
+        Ok(candidates)
+    }
+
+    /// The posts `visitor` viewed or read, most recent first, at most `limit`.
+    pub async fn read_history(
+        &self,
+        visitor: &str,
+        limit: i64,
+        read: ReadFrom,
+    ) -> Result<Vec<feed::Candidate>> {
+        let pipeline = vec![
+            doc! {"$match": {
+                "visitorId": visitor,
+                "type": {"$in": [AnalyticsKind::View.as_str(), AnalyticsKind::Read.as_str()]},
+            }},
+            doc! {"$group": {"_id": "$postId", "lastAt": {"$max": "$occurredAt"}}},
+            doc! {"$sort": {"lastAt": -1}},
+            doc! {"$limit": limit},
+        ];
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .analytics_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut ids: Vec<ObjectId> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            ids.push(doc.map_err(MongoQueryError)?.get_object_id("_id")?);
+        }
+
+        let find_options = FindOptions::builder()
+            .projection(feed_projection())
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .collection
+            .find(doc! {"_id": {"$in": &ids}}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut posts: HashMap<ObjectId, feed::Candidate> = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let candidate = doc_to_candidate(&doc.map_err(MongoQueryError)?)?;
+            posts.insert(candidate.id, candidate);
+        }
+        Ok(ids.iter().filter_map(|id| posts.remove(id)).collect())
+    }
+
+    /// Public published posts among `ids`, in the order given.
+    pub async fn blogs_in_order(
+        &self,
+        ids: &[ObjectId],
+        read: ReadFrom,
+    ) -> Result<Vec<BlogSummaryResponse>> {
+        let filter = doc! {
+            "_id": {"$in": ids},
+            "published": true,
+            "visibility": {"$nin": HIDDEN_VISIBILITIES},
+        };
+        let mut posts: HashMap<String, BlogSummaryResponse> = self
+            .find_summaries(filter, FindOptions::default(), None, read)
+            .await?
+            .into_iter()
+            .map(|blog| (blog.id.to_owned(), blog))
+            .collect();
+        Ok(ids
+            .iter()
+            .filter_map(|id| posts.remove(&id.to_hex()))
+            .collect())
+    }
+
+    /// Published posts with no vector from `model`, or one older than their
+    /// last edit, as (id, updatedAt, text to embed); at most `limit`. Vectors
+    /// of posts no longer published, or from another model, are dropped.
+    pub async fn posts_to_embed(
+        &self,
+        model: &str,
+        limit: usize,
+    ) -> Result<Vec<(ObjectId, DateTime<Utc>, String)>> {
+        self.embedding_collection
+            .delete_many(doc! {"model": {"$ne": model}}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut embedded: HashMap<ObjectId, DateTime<Utc>> = HashMap::new();
+        let find_options = FindOptions::builder()
+            .projection(doc! {"_id": 1, "sourceUpdatedAt": 1})
+            .build();
+        let mut cursor = self
+            .embedding_collection
+            .clone_with_type::<Document>()
+            .find(None, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            embedded.insert(
+                doc.get_object_id("_id")?,
+                doc.get_datetime("sourceUpdatedAt")?.to_chrono(),
+            );
+        }
+
+        let find_options = FindOptions::builder()
+            .projection(doc! {"_id": 1, "title": 1, "summary": 1, "content": 1, "updatedAt": 1})
+            .build();
+        let mut cursor = self
+            .collection
+            .find(doc! {"published": true}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut published: Vec<ObjectId> = Vec::new();
+        let mut stale = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            let id = doc.get_object_id("_id")?;
+            let updated_at = doc.get_datetime("updatedAt")?.to_chrono();
+            published.push(id);
+            let fresh = embedded.get(&id).is_some_and(|at| *at >= updated_at);
+            if !fresh && stale.len() < limit {
+                let text = format!(
+                    "{}\n\n{}\n\n{}",
+                    doc.get_str("title").unwrap_or_default(),
+                    doc.get_str("summary").unwrap_or_default(),
+                    doc.get_str("content").unwrap_or_default()
+                );
+                stale.push((id, updated_at, text));
+            }
+        }
+
+        self.embedding_collection
+            .delete_many(doc! {"_id": {"$nin": published}}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(stale)
+    }
+
+    pub async fn store_embedding(
+        &self,
+        post_id: ObjectId,
+        model: &str,
+        vector: Vec<f32>,
+        source_updated_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let embedding = EmbeddingModel {
+            postId: post_id,
+            model: model.to_owned(),
+            vector,
+            sourceUpdatedAt: source_updated_at,
+        };
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.embedding_collection
+            .replace_one(doc! {"_id": post_id}, &embedding, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(())
+    }
+
+    /// Public posts matching `query`, ranked by a blend of how near their
+    /// vectors are to `vector` (from `model`) and their keyword score;
+    /// `weight` is the share of the former. Without a vector, by keywords
+    /// alone.
+    pub async fn semantic_search(
+        &self,
+        query: &str,
+        vector: Option<(&str, &[f32])>,
+        weight: f64,
+        paging: Pagination,
+        read: ReadFrom,
+    ) -> Result<SemanticSearchResponse> {
+        let query = query.trim();
+        if query.is_empty() || query.chars().count() > MAX_SEARCH_QUERY_LEN {
+            return Err(ValidationError(format!(
+                "q must be 1 to {} characters",
+                MAX_SEARCH_QUERY_LEN
+            )));
+        }
+        let visible = doc! {"published": true, "visibility": {"$nin": HIDDEN_VISIBILITIES}};
+
+        let mut keyword: HashMap<ObjectId, f64> = HashMap::new();
+        let find_options = FindOptions::builder()
+            .projection(doc! {"_id": 1, "score": {"$meta": "textScore"}})
+            .sort(doc! {"score": {"$meta": "textScore"}})
+            .limit(SEARCH_CANDIDATES as i64)
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut filter = visible.clone();
+        filter.insert("$text", doc! {"$search": query});
+        let mut cursor = self
+            .collection
+            .find(filter, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            keyword.insert(doc.get_object_id("_id")?, doc.get_f64("score")?);
+        }
+        let top_keyword = keyword.values().cloned().fold(0.0, f64::max);
+
+        let mut nearest: Vec<(ObjectId, f64)> = Vec::new();
+        if let Some((model, vector)) = vector {
+            let find_options = FindOptions::builder()
+                .selection_criteria(self.reads.criteria(read))
+                .build();
+            let mut cursor = self
+                .embedding_collection
+                .find(doc! {"model": model}, find_options)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            while let Some(embedding) = cursor.next().await {
+                let embedding = embedding.map_err(MongoQueryError)?;
+                nearest.push((
+                    embedding.postId,
+                    semantic::cosine(vector, &embedding.vector),
+                ));
+            }
+            nearest.sort_by(|a, b| b.1.total_cmp(&a.1));
+            nearest.truncate(SEARCH_CANDIDATES);
+        }
+        let weight = if vector.is_some() { weight } else { 0.0 };
+
+        let mut scores: HashMap<ObjectId, (f64, f64)> = HashMap::new();
+        for (id, similarity) in nearest {
+            scores.entry(id).or_default().0 = similarity.max(0.0);
+        }
+        for (id, score) in keyword {
+            scores.entry(id).or_default().1 = if top_keyword > 0.0 {
+                score / top_keyword
+            } else {
+                0.0
+            };
+        }
+        let mut ranked: Vec<(ObjectId, f64, f64, f64)> = scores
+            .into_iter()
+            .map(|(id, (similarity, keyword))| {
+                let score = weight * similarity + (1.0 - weight) * keyword;
+                (id, score, similarity, keyword)
+            })
+            .filter(|(_, score, ..)| *score > 0.0)
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.0.cmp(&a.0)));
+
+        let page: Vec<_> = ranked
+            .into_iter()
+            .skip(paging.skip() as usize)
+            .take(paging.limit as usize)
+            .collect();
+        let ids: Vec<ObjectId> = page.iter().map(|(id, ..)| *id).collect();
+        let mut filter = visible;
+        filter.insert("_id", doc! {"$in": ids});
+        let mut posts: HashMap<String, BlogSummaryResponse> = self
+            .find_summaries(filter, FindOptions::default(), None, read)
+            .await?
+            .into_iter()
+            .map(|blog| (blog.id.to_owned(), blog))
+            .collect();
+
+        let mut hits = Vec::new();
+        for (id, score, similarity, keyword) in page {
+            // Vectors can outlive a post being hidden until the next indexing run.
+            let Some(blog) = posts.remove(&id.to_hex()) else {
+                continue;
+            };
+            hits.push(SearchHitResponse {
+                score,
+                semanticScore: similarity,
+                keywordScore: keyword,
+                blog,
+            });
+        }
+
+        Ok(SemanticSearchResponse {
+            status: "success",
+            query: query.to_owned(),
+            semantic: vector.is_some(),
+            page: paging.page,
+            limit: paging.limit,
+            results: hits.len(),
+            hits,
+        })
+    }
+
+    /// Stores the outcome of a link check run. Records for links that are
+    /// gone, and for posts no longer published, are dropped.
+    pub async fn record_link_checks(
+        &self,
+        posts: &[(ObjectId, Vec<String>)],
+        results: &HashMap<String, LinkStatus>,
+    ) -> Result<()> {
+        let post_ids: Vec<ObjectId> = posts.iter().map(|(id, _)| *id).collect();
+        self.link_check_collection
+            .delete_many(doc! {"postId": {"$nin": post_ids}}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let now = Utc::now();
+        for (post_id, links) in posts {
+            self.link_check_collection
+                .delete_many(
+                    doc! {"postId": *post_id, "url": {"$nin": links.clone()}},
+                    None,
+                )
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+
+            let mut previous: HashMap<String, LinkCheckModel> = HashMap::new();
+            let mut cursor = self
+                .link_check_collection
+                .find(doc! {"postId": *post_id}, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            while let Some(check) = cursor.next().await {
+                let check = check.map_err(MongoQueryError)?;
+                previous.insert(check.url.to_owned(), check);
+            }
+
+            for url in links {
+                let Some(status) = results.get(url) else {
+                    continue;
+                };
+                let before = previous.get(url);
+                let broken = status.is_broken();
+                let (failures, broken_since) = match (broken, before) {
+                    (false, _) => (0, None),
+                    (true, Some(before)) if before.broken => {
+                        (before.failures + 1, before.brokenSince)
+                    }
+                    (true, _) => (1, Some(bson::DateTime::from_chrono(now))),
+                };
+                let check = LinkCheckModel {
+                    id: before.map(|before| before.id).unwrap_or_default(),
+                    postId: *post_id,
+                    url: url.to_owned(),
+                    broken,
+                    statusCode: status.status.map(i32::from),
+                    error: status.error.to_owned(),
+                    failures,
+                    brokenSince: broken_since,
+                    checkedAt: now,
+                };
+                let options = ReplaceOptions::builder().upsert(true).build();
+                self.link_check_collection
+                    .replace_one(
+                        doc! {"postId": *post_id, "url": url.as_str()},
+                        &check,
+                        options,
+                    )
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// How the links in one post fared when last checked.
+    pub async fn link_health(&self, id: &str, read: ReadFrom) -> Result<LinkHealthResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        if self
+            .collection
+            .count_documents(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            == 0
+        {
+            return Err(NotFoundError(id.to_string()));
+        }
+
+        let find_options = FindOptions::builder()
+            .sort(doc! {"broken": -1, "url": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .link_check_collection
+            .find(doc! {"postId": oid}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut links: Vec<LinkCheckResponse> = Vec::new();
+        while let Some(check) = cursor.next().await {
+            links.push(self.doc_to_link_check(&check.map_err(MongoQueryError)?));
+        }
+
+        Ok(LinkHealthResponse {
+            status: "success",
+            postId: id.to_owned(),
+            checked: links.len(),
+            broken: links.iter().filter(|link| link.broken).count(),
+            links,
+        })
+    }
+
+    /// Readability of one post, with warnings about its metadata if `lint`.
+    pub async fn quality(&self, id: &str, lint: bool, read: ReadFrom) -> Result<QualityResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let Some(blog) = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        else {
+            return Err(NotFoundError(id.to_string()));
+        };
+
+        Ok(QualityResponse {
+            status: "success",
+            postId: id.to_owned(),
+            // Posts saved before quality was measured get measured now.
+            quality: blog
+                .quality
+                .unwrap_or_else(|| quality::measure(&blog.content)),
+            warnings: lint.then(|| {
+                quality::lint(
+                    &blog.title,
+                    &blog.summary,
+                    blog.tags.as_deref().unwrap_or_default(),
+                )
+            }),
+        })
+    }
+
+    /// Broken links across the site, longest broken first.
+    pub async fn broken_links(
+        &self,
+        paging: Pagination,
+        read: ReadFrom,
+    ) -> Result<BrokenLinkListResponse> {
+        let find_options = FindOptions::builder()
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .sort(doc! {"brokenSince": 1, "_id": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .link_check_collection
+            .find(doc! {"broken": true}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut links: Vec<LinkCheckResponse> = Vec::new();
+        while let Some(check) = cursor.next().await {
+            links.push(self.doc_to_link_check(&check.map_err(MongoQueryError)?));
+        }
+
+        Ok(BrokenLinkListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: links.len(),
+            links,
+        })
+    }
+
+    /// Marks a message read or archived, or back to new.
+    pub async fn set_contact_status(
+        &self,
+        id: &str,
+        status: ContactStatus,
+    ) -> Result<SingleContactMessageResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        match self
+            .contact_collection
+            .find_one_and_update(
+                doc! {"_id": oid},
+                doc! {"$set": {"status": status.as_str()}},
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            Some(contact) => Ok(SingleContactMessageResponse {
+                status: "success",
+                data: ContactMessageData {
+                    message: self.doc_to_contact(&contact),
+                },
+            }),
+            None => Err(NotFoundError(id.to_string())),
+        }
+    }
+
+    /// Autosaves `actor`'s in-progress edits to a post without touching the post.
+    pub async fn save_draft(
+        &self,
+        id: &str,
+        body: &DraftSchema,
+        actor: &Actor<'_>,
+    ) -> Result<SingleDraftResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let changes = bson::to_document(body).map_err(MongoSerializeBsonError)?;
+        if changes.is_empty() {
+            return Err(ValidationError(
+                "a draft save needs a title, summary or content".to_string(),
+            ));
+        }
+
+        let blog = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))?;
+        check_can_edit(&blog, actor)?;
+
+        let existing = self
+            .draft_collection
+            .find_one(doc! {"postId": oid, "userId": actor.id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if existing.is_none() {
+            let drafts = self
+                .draft_collection
+                .count_documents(doc! {"userId": actor.id}, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            if drafts >= self.quotas.max_drafts {
+                return Err(QuotaExceededError("drafts", self.quotas.max_drafts));
+            }
+        }
+        if let Some(content) = &body.content {
+            let replacing = existing
+                .as_ref()
+                .and_then(|draft| draft.content.as_ref())
+                .map_or(0, String::len);
+            self.check_content_quota(actor.id, content.len(), replacing)
+                .await?;
+        }
+
+        let now = Utc::now();
+        let cutoff = now - chrono::Duration::seconds(DRAFT_VERSION_DEBOUNCE_SECS);
+
+        // A pipeline update so the version check and the write happen atomically;
+        // on the first save `updatedAt` is missing, which sorts before any date.
+        let mut set = doc! {
+            "version": {
+                "$cond": [
+                    {"$lt": ["$updatedAt", cutoff]},
+                    {"$add": [{"$ifNull": ["$version", 0]}, 1]},
+                    "$version",
+                ]
+            },
+            "createdAt": {"$ifNull": ["$createdAt", now]},
+            "updatedAt": now,
+        };
+        for (field, value) in changes {
+            // `$literal` keeps content that starts with `$` from reading as a field path.
+            set.insert(field, doc! {"$literal": value});
+        }
+
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+
+        let draft = self
+            .draft_collection
+            .find_one_and_update(
+                doc! {"postId": oid, "userId": actor.id},
+                vec![doc! {"$set": set}],
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| DraftNotFoundError(id.to_string()))?;
+
+        Ok(SingleDraftResponse {
+            status: "success",
+            data: DraftData {
+                draft: self.doc_to_draft(&draft),
+            },
+        })
+    }
+
+    pub async fn get_draft(&self, id: &str, user_id: &str) -> Result<SingleDraftResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        // Autosaves land on the primary; reading one back must see the latest.
+        match self
+            .draft_collection
+            .find_one(doc! {"postId": oid, "userId": user_id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        {
+            Some(draft) => Ok(SingleDraftResponse {
+                status: "success",
+                data: DraftData {
+                    draft: self.doc_to_draft(&draft),
+                },
+            }),
+            None => Err(DraftNotFoundError(id.to_string())),
+        }
+    }
+
+    /// Fails unless `actor` may share previews of post `id`, which takes the
+    /// same rights as editing it.
+    pub async fn check_can_preview(&self, id: &str, actor: &Actor<'_>) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let blog = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))?;
+        check_can_edit(&blog, actor)
+    }
+
+    /// The post as `grant`'s user would publish it: their draft's title,
+    /// summary and content over the stored post.
+    pub async fn preview_blog(&self, grant: &PreviewGrant) -> Result<SingleBlogResponse> {
+        let oid = ObjectId::from_str(&grant.post_id)
+            .map_err(|_| InvalidIDError(grant.post_id.to_owned()))?;
+
+        // A preview is usually opened right after sharing; read the primary.
+        let blog = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(grant.post_id.to_string()))?;
+        let draft = self
+            .draft_collection
+            .find_one(doc! {"postId": oid, "userId": &grant.user_id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let comments = self.settings(ReadFrom::Primary).await?.comments;
+        let mut blog = self.doc_to_blog(&blog, &comments)?;
+        if let Some(draft) = draft {
+            if let Some(title) = draft.title {
+                blog.title = title;
+            }
+            if let Some(summary) = draft.summary {
+                blog.summary = summary;
+            }
+            if let Some(content) = draft.content {
+                blog.toc = toc::extract(&content);
+                blog.content = content;
+            }
+        }
+
+        Ok(SingleBlogResponse {
+            status: "success",
+            data: BlogData { blog },
+            possibleDuplicates: Vec::new(),
+        })
+    }
+
+    /// The title and content `actor` is working on for post `id`: their draft
+    /// over the stored post. Takes the same rights as editing it.
+    pub async fn draft_text(&self, id: &str, actor: &Actor<'_>) -> Result<(String, String)> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let blog = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))?;
+        check_can_edit(&blog, actor)?;
+        let draft = self
+            .draft_collection
+            .find_one(doc! {"postId": oid, "userId": actor.id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let (title, content) = match draft {
+            Some(draft) => (draft.title, draft.content),
+            None => (None, None),
+        };
+        Ok((title.unwrap_or(blog.title), content.unwrap_or(blog.content)))
+    }
+
+    /// The names of the `limit` tags on the most published posts.
+    pub async fn popular_tags(&self, limit: i64, read: ReadFrom) -> Result<Vec<String>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"count": -1, "name": 1})
+            .limit(limit)
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .tag_stats_collection
+            .find(
+                doc! {"kind": StatKind::Tag.as_str(), "count": {"$gt": 0}},
+                find_options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut tags = Vec::new();
+        while let Some(stat) = cursor.next().await {
+            tags.push(stat.map_err(MongoQueryError)?.name);
+        }
+        Ok(tags)
+    }
+
+    /// Adds a comment to post `id`, if the post is taking comments from this
+    /// reader. Readers who aren't signed in have to give a name.
+    pub async fn create_comment(
+        &self,
+        id: &str,
+        body: &CreateCommentSchema,
+        mentions: Vec<MentionModel>,
+        viewer: &Viewer<'_>,
+    ) -> Result<SingleCommentResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let blog = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(id.to_string()))?;
+        check_can_read(&blog, viewer)?;
+
+        let defaults = self.settings(ReadFrom::Primary).await?.comments;
+        let status = comment_status(&blog, &defaults);
+        if !status.open {
+            return Err(CommentsClosedError(id.to_string()));
+        }
+        let author = viewer.actor.as_ref().map(|actor| actor.id);
+        if status.membersOnly && author.is_none() {
+            return Err(UnauthorizedError(
+                "sign in to comment on this post".to_string(),
+            ));
+        }
+        if let (Some(author), Some(owner)) = (author, blog.author.as_deref()) {
+            if self.is_blocked(owner, author).await? {
+                return Err(NotPermittedError(
+                    "you can't comment on this post".to_string(),
+                ));
+            }
+        }
+
+        let text = check_comment_body(&body.body)?;
+        let name = match (
+            body.name
+                .as_deref()
+                .map(str::trim)
+                .filter(|name| !name.is_empty()),
+            author,
+        ) {
+            (Some(name), _) => name,
+            (None, Some(author)) => author,
+            (None, None) => {
+                return Err(ValidationError(
+                    "name is required to comment without signing in".to_string(),
+                ))
+            }
+        };
+        if name.chars().count() > MAX_COMMENT_NAME_LEN {
+            return Err(ValidationError(format!(
+                "name must be at most {} characters",
+                MAX_COMMENT_NAME_LEN
+            )));
+        }
+
+        let (parent_id, path) = match &body.parentId {
+            Some(parent_id) => {
+                let parent_oid = ObjectId::from_str(parent_id)
+                    .map_err(|_| InvalidIDError(parent_id.to_owned()))?;
+                let parent = self
+                    .comment_collection
+                    .find_one(doc! {"_id": parent_oid, "postId": oid}, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?
+                    .ok_or_else(|| CommentNotFoundError(parent_id.to_owned()))?;
+                let path = format!("{}{}/", parent.path, parent.id.to_hex());
+                if path.matches('/').count() > MAX_COMMENT_DEPTH {
+                    return Err(ValidationError(format!(
+                        "replies can nest at most {} deep",
+                        MAX_COMMENT_DEPTH
+                    )));
+                }
+                (Some(parent_oid), path)
+            }
+            None => (None, String::new()),
+        };
+
+        let comment = CommentModel {
+            id: ObjectId::new(),
+            postId: oid,
+            parentId: parent_id,
+            path,
+            author: author.map(str::to_owned),
+            name: name.to_owned(),
+            body: text.to_owned(),
+            createdAt: Utc::now(),
+            editedAt: None,
+            history: Vec::new(),
+            mentions,
+            reactions: BTreeMap::new(),
+        };
+        self.comment_collection
+            .insert_one(&comment, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(SingleCommentResponse {
+            status: "success",
+            data: CommentData {
+                comment: doc_to_comment(&comment),
+            },
+        })
+    }
+
+    /// Top-level comments on post `id`, oldest first, each with its first
+    /// `replies` replies inlined.
+    pub async fn fetch_comments(
+        &self,
+        id: &str,
+        paging: Pagination,
+        replies: Option<i64>,
+        viewer: &Viewer<'_>,
+        read: ReadFrom,
+    ) -> Result<CommentListResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        self.check_readable(&oid, viewer, read).await?;
+        let hidden = self.blocked_by(viewer).await?;
+
+        let comments = self
+            .comment_thread(
+                doc! {"postId": oid, "parentId": null},
+                paging,
+                inline_replies(replies)?,
+                &hidden,
+                read,
+            )
+            .await?;
+
+        Ok(CommentListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: comments.len(),
+            comments,
+        })
+    }
+
+    /// Direct replies to comment `id`, for following a thread past what was
+    /// inlined.
+    pub async fn fetch_replies(
+        &self,
+        id: &str,
+        paging: Pagination,
+        replies: Option<i64>,
+        viewer: &Viewer<'_>,
+        read: ReadFrom,
+    ) -> Result<CommentListResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let comment = self
+            .comment_collection
+            .find_one(doc! {"_id": oid}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| CommentNotFoundError(id.to_owned()))?;
+        self.check_readable(&comment.postId, viewer, read).await?;
+        let hidden = self.blocked_by(viewer).await?;
+
+        let comments = self
+            .comment_thread(
+                doc! {"parentId": oid},
+                paging,
+                inline_replies(replies)?,
+                &hidden,
+                read,
+            )
+            .await?;
+
+        Ok(CommentListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: comments.len(),
+            comments,
+        })
+    }
+
+    /// Replaces the text of comment `id`. Only its author may, and only within
+    /// `COMMENT_EDIT_WINDOW_SECS` of posting it; the old text is kept.
+    pub async fn edit_comment(
+        &self,
+        id: &str,
+        body: &EditCommentSchema,
+        user_id: &str,
+    ) -> Result<SingleCommentResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let text = check_comment_body(&body.body)?;
+
+        let comment = self
+            .comment_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| CommentNotFoundError(id.to_owned()))?;
+        if comment.author.as_deref() != Some(user_id) {
+            return Err(NotPermittedError(
+                "only its author can edit a comment".to_string(),
+            ));
+        }
+        let now = Utc::now();
+        if now - comment.createdAt > self.comment_edit_window {
+            return Err(NotPermittedError(format!(
+                "comments can only be edited for {} minutes after posting",
+                self.comment_edit_window.num_minutes()
+            )));
+        }
+        if text == comment.body {
+            return Ok(SingleCommentResponse {
+                status: "success",
+                data: CommentData {
+                    comment: doc_to_comment(&comment),
+                },
+            });
+        }
+
+        let edit = CommentEditModel {
+            body: comment.body.to_owned(),
+            replacedAt: now,
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        // Matching the text read above keeps a concurrent edit from being
+        // dropped from the history.
+        let updated = self
+            .comment_collection
+            .find_one_and_update(
+                doc! {"_id": oid, "body": &comment.body},
+                doc! {
+                    "$set": {"body": text, "editedAt": now},
+                    "$push": {"history": bson::to_bson(&edit)?},
+                },
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| {
+                ValidationError("the comment changed while editing, try again".to_string())
+            })?;
+
+        Ok(SingleCommentResponse {
+            status: "success",
+            data: CommentData {
+                comment: doc_to_comment(&updated),
+            },
+        })
+    }
+
+    /// Adds the viewer's `emoji` reaction to comment `id`. Reacting twice
+    /// with the same emoji changes nothing.
+    pub async fn add_reaction(
+        &self,
+        id: &str,
+        body: &ReactionSchema,
+        viewer: &Viewer<'_>,
+    ) -> Result<SingleCommentResponse> {
+        let (comment, user_id) = self.reactable_comment(id, viewer).await?;
+        let allowed = self.settings(ReadFrom::Primary).await?.comments.reactions;
+        if !allowed.contains(&body.emoji) {
+            return Err(ValidationError(format!(
+                "{:?} is not an allowed reaction",
+                body.emoji
+            )));
+        }
+
+        let reaction = ReactionModel {
+            id: ObjectId::new(),
+            commentId: comment.id,
+            postId: comment.postId,
+            userId: user_id.to_owned(),
+            emoji: body.emoji.to_owned(),
+            createdAt: Utc::now(),
+        };
+        match self.reaction_collection.insert_one(&reaction, None).await {
+            Ok(_) => {}
+            Err(e) if is_duplicate_key(&e) => {
+                return Ok(SingleCommentResponse {
+                    status: "success",
+                    data: CommentData {
+                        comment: doc_to_comment(&comment),
+                    },
+                })
+            }
+            Err(e) => return Err(MongoQueryError(e)),
+        }
+
+        self.count_reaction(&comment.id, &body.emoji, 1).await
+    }
+
+    /// Takes back the viewer's `emoji` reaction to comment `id`, if any.
+    pub async fn remove_reaction(
+        &self,
+        id: &str,
+        emoji: &str,
+        viewer: &Viewer<'_>,
+    ) -> Result<SingleCommentResponse> {
+        let (comment, user_id) = self.reactable_comment(id, viewer).await?;
+
+        let result = self
+            .reaction_collection
+            .delete_one(
+                doc! {"commentId": comment.id, "userId": user_id, "emoji": emoji},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.deleted_count == 0 {
+            return Ok(SingleCommentResponse {
+                status: "success",
+                data: CommentData {
+                    comment: doc_to_comment(&comment),
+                },
+            });
+        }
+
+        self.count_reaction(&comment.id, emoji, -1).await
+    }
+
+    /// Comment `id` and the signed-in viewer reacting to it, as long as they
+    /// can read the post it's on.
+    async fn reactable_comment<'v>(
+        &self,
+        id: &str,
+        viewer: &Viewer<'v>,
+    ) -> Result<(CommentModel, &'v str)> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let user_id = viewer
+            .actor
+            .as_ref()
+            .map(|actor| actor.id)
+            .ok_or_else(|| UnauthorizedError("sign in to react to comments".to_string()))?;
+
+        let comment = self
+            .comment_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| CommentNotFoundError(id.to_owned()))?;
+        self.check_readable(&comment.postId, viewer, ReadFrom::Primary)
+            .await?;
+
+        Ok((comment, user_id))
+    }
+
+    async fn count_reaction(
+        &self,
+        oid: &ObjectId,
+        emoji: &str,
+        by: i64,
+    ) -> Result<SingleCommentResponse> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let updated = self
+            .comment_collection
+            .find_one_and_update(
+                doc! {"_id": oid},
+                doc! {"$inc": {format!("reactions.{}", emoji): by}},
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| CommentNotFoundError(oid.to_hex()))?;
+
+        Ok(SingleCommentResponse {
+            status: "success",
+            data: CommentData {
+                comment: doc_to_comment(&updated),
+            },
+        })
+    }
+
+    /// Blocks `body.userId` for `user_id`. Blocking someone twice changes nothing.
+    pub async fn block_user(
+        &self,
+        body: &BlockSchema,
+        user_id: &str,
+    ) -> Result<SingleBlockResponse> {
+        let blocked = body.userId.trim();
+        if blocked.is_empty() {
+            return Err(ValidationError("userId must not be empty".to_string()));
+        }
+        if blocked == user_id {
+            return Err(ValidationError("you can't block yourself".to_string()));
+        }
+
+        let existing = self
+            .block_collection
+            .find_one(doc! {"blocker": user_id, "blocked": blocked}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let block = match existing {
+            Some(block) => block,
+            None => {
+                let count = self
+                    .block_collection
+                    .count_documents(doc! {"blocker": user_id}, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+                if count >= MAX_BLOCKS {
+                    return Err(QuotaExceededError("blocked users", MAX_BLOCKS));
+                }
+
+                let block = BlockModel {
+                    id: ObjectId::new(),
+                    blocker: user_id.to_owned(),
+                    blocked: blocked.to_owned(),
+                    createdAt: Utc::now(),
+                };
+                match self.block_collection.insert_one(&block, None).await {
+                    Ok(_) => {}
+                    // Lost a race with the same block; it exists either way.
+                    Err(e) if is_duplicate_key(&e) => {}
+                    Err(e) => return Err(MongoQueryError(e)),
+                }
+                block
+            }
+        };
+
+        Ok(SingleBlockResponse {
+            status: "success",
+            data: BlockData {
+                block: doc_to_block(&block),
+            },
+        })
+    }
+
+    pub async fn unblock_user(&self, blocked: &str, user_id: &str) -> Result<()> {
+        self.block_collection
+            .delete_one(doc! {"blocker": user_id, "blocked": blocked}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(())
+    }
+
+    /// Everyone `user_id` has blocked, most recent first.
+    pub async fn fetch_blocks(&self, user_id: &str) -> Result<BlockListResponse> {
+        let find_options = FindOptions::builder().sort(doc! {"createdAt": -1}).build();
+        let mut cursor = self
+            .block_collection
+            .find(doc! {"blocker": user_id}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut blocks = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            blocks.push(doc_to_block(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(BlockListResponse {
+            status: "success",
+            results: blocks.len(),
+            blocks,
+        })
+    }
+
+    /// Bookmarks post `id` for the viewer, or refiles an existing bookmark
+    /// under `body.folder` and `body.labels`.
+    pub async fn bookmark(
+        &self,
+        id: &str,
+        body: &BookmarkSchema,
+        viewer: &Viewer<'_>,
+    ) -> Result<SingleBookmarkResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let Some(actor) = &viewer.actor else {
+            return Err(NotPermittedError("sign in to bookmark posts".to_string()));
+        };
+
+        let folder = body
+            .folder
+            .as_deref()
+            .map(str::trim)
+            .filter(|folder| !folder.is_empty());
+        if folder.is_some_and(|folder| folder.chars().count() > MAX_BOOKMARK_FOLDER_LEN) {
+            return Err(ValidationError(format!(
+                "folder must be at most {} characters",
+                MAX_BOOKMARK_FOLDER_LEN
+            )));
+        }
+        let labels = normalize_tags(body.labels.as_deref().unwrap_or_default());
+        if labels.len() > MAX_BOOKMARK_LABELS {
+            return Err(ValidationError(format!(
+                "a bookmark takes at most {} labels",
+                MAX_BOOKMARK_LABELS
+            )));
+        }
+
+        let Some(blog) = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        else {
+            return Err(NotFoundError(id.to_string()));
+        };
+        check_can_read(&blog, viewer)?;
+
+        let filter = doc! {"userId": actor.id, "postId": oid};
+        let existing = self
+            .bookmark_collection
+            .count_documents(filter.clone(), None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if existing == 0 {
+            let count = self
+                .bookmark_collection
+                .count_documents(doc! {"userId": actor.id}, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            if count >= MAX_BOOKMARKS {
+                return Err(QuotaExceededError("bookmarks", MAX_BOOKMARKS));
+            }
+        }
+
+        let update = doc! {
+            "$set": {"folder": folder, "labels": &labels},
+            "$setOnInsert": {"_id": ObjectId::new(), "createdAt": Utc::now()},
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+        let bookmark = match self
+            .bookmark_collection
+            .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            .await
+        {
+            Ok(bookmark) => bookmark,
+            // Lost a race creating the same bookmark; update the winner's.
+            Err(e) if is_duplicate_key(&e) => self
+                .bookmark_collection
+                .find_one_and_update(filter, update, options)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?,
+            Err(e) => return Err(MongoQueryError(e)),
+        };
+        let Some(bookmark) = bookmark else {
+            return Err(NotFoundError(id.to_string()));
+        };
+
+        let comments = self.settings(ReadFrom::Primary).await?.comments;
+        let mut blog = summarize(self.doc_to_blog(&blog, &comments)?);
+        blog.bookmarked = Some(true);
+        Ok(SingleBookmarkResponse {
+            status: "success",
+            data: BookmarkData {
+                bookmark: doc_to_bookmark(&bookmark, Some(blog)),
+            },
+        })
+    }
+
+    pub async fn remove_bookmark(&self, id: &str, user_id: &str) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        self.bookmark_collection
+            .delete_one(doc! {"userId": user_id, "postId": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(())
+    }
+
+    /// `user_id`'s bookmarks, newest first, optionally only one folder's or
+    /// those with a label. Posts no longer public come without their post.
+    pub async fn fetch_bookmarks(
+        &self,
+        user_id: &str,
+        opts: &BookmarkListOptions,
+        paging: Pagination,
+        read: ReadFrom,
+    ) -> Result<BookmarkListResponse> {
+        let mut filter = doc! {"userId": user_id};
+        if let Some(folder) = opts.folder.as_deref().map(str::trim) {
+            filter.insert("folder", folder);
+        }
+        if let Some(label) = &opts.label {
+            filter.insert("labels", label.trim().to_lowercase());
+        }
+        let find_options = FindOptions::builder()
+            .sort(doc! {"createdAt": -1})
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .bookmark_collection
+            .find(filter, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut bookmarks = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            bookmarks.push(doc.map_err(MongoQueryError)?);
+        }
+
+        let ids: Vec<ObjectId> = bookmarks.iter().map(|bookmark| bookmark.postId).collect();
+        let mut blogs: HashMap<String, BlogSummaryResponse> = self
+            .blogs_in_order(&ids, read)
+            .await?
+            .into_iter()
+            .map(|mut blog| {
+                blog.bookmarked = Some(true);
+                (blog.id.to_owned(), blog)
+            })
+            .collect();
+        let bookmarks: Vec<BookmarkResponse> = bookmarks
+            .iter()
+            .map(|bookmark| doc_to_bookmark(bookmark, blogs.remove(&bookmark.postId.to_hex())))
+            .collect();
+
+        Ok(BookmarkListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: bookmarks.len(),
+            bookmarks,
+        })
+    }
+
+    /// `author`'s latest public posts, newest first.
+    pub async fn recent_posts_by(
+        &self,
+        author: &str,
+        limit: i64,
+        read: ReadFrom,
+    ) -> Result<Vec<BlogSummaryResponse>> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"createdAt": -1})
+            .limit(limit)
+            .build();
+        let filter = doc! {
+            "author": author,
+            "published": true,
+            "visibility": {"$nin": HIDDEN_VISIBILITIES},
+        };
+        self.find_summaries(filter, find_options, None, read).await
+    }
+
+    /// How much `author` has published, and who follows them.
+    pub async fn author_stats(&self, author: &str, read: ReadFrom) -> Result<AuthorStatsResponse> {
+        let filter = doc! {
+            "author": author,
+            "published": true,
+            "visibility": {"$nin": HIDDEN_VISIBILITIES},
+        };
+        let options = CountOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let posts = self
+            .blog_collection
+            .count_documents(filter.clone(), options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let followers = self
+            .count_followers(FollowKind::Author, author, read)
+            .await?
+            .followers;
+
+        let pipeline = vec![
+            doc! {"$match": filter},
+            doc! {"$unwind": "$tags"},
+            doc! {"$group": {"_id": "$tags", "count": {"$sum": 1}}},
+            doc! {"$sort": {"count": -1, "_id": 1}},
+            doc! {"$limit": AUTHOR_TOP_TAGS},
+        ];
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut top_tags = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            top_tags.push(doc.map_err(MongoQueryError)?.get_str("_id")?.to_owned());
+        }
+
+        Ok(AuthorStatsResponse {
+            posts,
+            followers,
+            topTags: top_tags,
+        })
+    }
+
+    /// Marks each of `blogs` with whether `user_id` bookmarked it.
+    pub async fn mark_bookmarked<T: PostView>(&self, user_id: &str, blogs: &mut [T]) -> Result<()> {
+        let ids: Vec<ObjectId> = blogs
+            .iter()
+            .filter_map(|blog| ObjectId::from_str(blog.post_id()).ok())
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let bookmarked = self
+            .bookmark_collection
+            .distinct(
+                "postId",
+                doc! {"userId": user_id, "postId": {"$in": ids}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let bookmarked: HashSet<String> = bookmarked
+            .iter()
+            .filter_map(|id| id.as_object_id())
+            .map(|id| id.to_hex())
+            .collect();
+        for blog in blogs {
+            let marked = bookmarked.contains(blog.post_id());
+            blog.set_bookmarked(marked);
+        }
+        Ok(())
+    }
+
+    /// Records how far `user_id` has read post `id`. Called as the reader
+    /// scrolls, so it only checks the post exists.
+    pub async fn record_progress(
+        &self,
+        id: &str,
+        body: &ProgressSchema,
+        user_id: &str,
+    ) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        if !(0.0..=1.0).contains(&body.position) {
+            return Err(ValidationError("position must be from 0 to 1".to_string()));
+        }
+
+        let exists = self
+            .blog_collection
+            .count_documents(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if exists == 0 {
+            return Err(NotFoundError(id.to_string()));
+        }
+
+        let now = Utc::now();
+        let filter = doc! {"userId": user_id, "postId": oid};
+        let update = doc! {
+            "$set": {"position": body.position, "updatedAt": now},
+            "$max": {"furthest": body.position},
+            "$setOnInsert": {"_id": ObjectId::new(), "createdAt": now},
+        };
+        let options = UpdateOptions::builder().upsert(true).build();
+        let result = match self
+            .progress_collection
+            .update_one(filter.clone(), update.clone(), options.clone())
+            .await
+        {
+            Ok(result) => result,
+            // Lost a race creating the same entry; update the winner's.
+            Err(e) if is_duplicate_key(&e) => self
+                .progress_collection
+                .update_one(filter, update, options)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?,
+            Err(e) => return Err(MongoQueryError(e)),
+        };
+
+        if result.upserted_id.is_some() {
+            self.trim_history(user_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Drops `user_id`'s oldest entries past `MAX_HISTORY_ENTRIES`.
+    async fn trim_history(&self, user_id: &str) -> Result<()> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"updatedAt": -1})
+            .skip(MAX_HISTORY_ENTRIES)
+            .projection(doc! {"_id": 1})
+            .build();
+        let mut cursor = self
+            .progress_collection
+            .clone_with_type::<Document>()
+            .find(doc! {"userId": user_id}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut expired = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            expired.push(doc.map_err(MongoQueryError)?.get_object_id("_id")?);
+        }
+        if !expired.is_empty() {
+            self.progress_collection
+                .delete_many(doc! {"_id": {"$in": expired}}, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+        }
+        Ok(())
+    }
+
+    /// What `user_id` has been reading, most recent first.
+    pub async fn fetch_history(
+        &self,
+        user_id: &str,
+        opts: &HistoryOptions,
+        paging: Pagination,
+        read: ReadFrom,
+    ) -> Result<HistoryListResponse> {
+        let mut filter = doc! {"userId": user_id};
+        if opts.unfinished == Some(true) {
+            filter.insert("furthest", doc! {"$lt": FINISHED_POSITION});
+        }
+        let find_options = FindOptions::builder()
+            .sort(doc! {"updatedAt": -1})
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .progress_collection
+            .find(filter, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut entries = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            entries.push(doc.map_err(MongoQueryError)?);
+        }
+
+        let ids: Vec<ObjectId> = entries.iter().map(|entry| entry.postId).collect();
+        let mut blogs: HashMap<String, BlogSummaryResponse> = self
+            .blogs_in_order(&ids, read)
+            .await?
+            .into_iter()
+            .map(|blog| (blog.id.to_owned(), blog))
+            .collect();
+        let history: Vec<HistoryEntryResponse> = entries
+            .iter()
+            .map(|entry| HistoryEntryResponse {
+                postId: entry.postId.to_hex(),
+                position: entry.position,
+                furthest: entry.furthest,
+                finished: entry.furthest >= FINISHED_POSITION,
+                updatedAt: entry.updatedAt,
+                blog: blogs.remove(&entry.postId.to_hex()),
+            })
+            .collect();
+
+        Ok(HistoryListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: history.len(),
+            history,
+        })
+    }
+
+    pub async fn clear_history(&self, user_id: &str) -> Result<()> {
+        self.progress_collection
+            .delete_many(doc! {"userId": user_id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(())
+    }
+
+    pub async fn follow(&self, body: &FollowSchema, user_id: &str) -> Result<SingleFollowResponse> {
+        let target = match body.kind {
+            FollowKind::Author => body.target.trim().to_owned(),
+            FollowKind::Tag => body.target.trim().to_lowercase(),
+        };
+        if target.is_empty() {
+            return Err(ValidationError("target must not be empty".to_string()));
+        }
+        if body.kind == FollowKind::Author && target == user_id {
+            return Err(ValidationError("you can't follow yourself".to_string()));
+        }
+
+        let filter = doc! {"userId": user_id, "kind": body.kind.as_str(), "target": &target};
+        let existing = self
+            .follow_collection
+            .find_one(filter, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let follow = match existing {
+            Some(follow) => follow,
+            None => {
+                let count = self
+                    .follow_collection
+                    .count_documents(doc! {"userId": user_id}, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+                if count >= MAX_FOLLOWS {
+                    return Err(QuotaExceededError("follows", MAX_FOLLOWS));
+                }
+
+                let follow = FollowModel {
+                    id: ObjectId::new(),
+                    userId: user_id.to_owned(),
+                    kind: body.kind,
+                    target,
+                    createdAt: Utc::now(),
+                };
+                match self.follow_collection.insert_one(&follow, None).await {
+                    Ok(_) => {}
+                    // Lost a race with the same follow; it exists either way.
+                    Err(e) if is_duplicate_key(&e) => {}
+                    Err(e) => return Err(MongoQueryError(e)),
+                }
+                follow
+            }
+        };
+
+        Ok(SingleFollowResponse {
+            status: "success",
+            data: FollowData {
+                follow: doc_to_follow(&follow),
+            },
+        })
+    }
+
+    pub async fn unfollow(&self, kind: FollowKind, target: &str, user_id: &str) -> Result<()> {
+        let target = match kind {
+            FollowKind::Author => target.trim().to_owned(),
+            FollowKind::Tag => target.trim().to_lowercase(),
+        };
+        self.follow_collection
+            .delete_one(
+                doc! {"userId": user_id, "kind": kind.as_str(), "target": target},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(())
+    }
+
+    /// Everything `user_id` follows, most recent first.
+    pub async fn fetch_follows(&self, user_id: &str, read: ReadFrom) -> Result<FollowListResponse> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"createdAt": -1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .follow_collection
+            .find(doc! {"userId": user_id}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut follows = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            follows.push(doc_to_follow(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(FollowListResponse {
+            status: "success",
+            results: follows.len(),
+            follows,
+        })
+    }
+
+    pub async fn count_followers(
+        &self,
+        kind: FollowKind,
+        target: &str,
+        read: ReadFrom,
+    ) -> Result<FollowerCountResponse> {
+        let target = match kind {
+            FollowKind::Author => target.trim().to_owned(),
+            FollowKind::Tag => target.trim().to_lowercase(),
+        };
+        let options = CountOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let followers = self
+            .follow_collection
+            .count_documents(doc! {"kind": kind.as_str(), "target": &target}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(FollowerCountResponse {
+            status: "success",
+            kind,
+            target,
+            followers,
+        })
+    }
+
+    /// The authors and tags `user_id` follows.
+    pub async fn follows_of(
+        &self,
+        user_id: &str,
+        read: ReadFrom,
+    ) -> Result<(HashSet<String>, HashSet<String>)> {
+        let find_options = FindOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .follow_collection
+            .find(doc! {"userId": user_id}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let (mut authors, mut tags) = (HashSet::new(), HashSet::new());
+        while let Some(doc) = cursor.next().await {
+            let follow = doc.map_err(MongoQueryError)?;
+            match follow.kind {
+                FollowKind::Author => authors.insert(follow.target),
+                FollowKind::Tag => tags.insert(follow.target),
+            };
+        }
+        Ok((authors, tags))
+    }
+
+    /// Who follows `author` or any of `tags`, each once; `author` left out.
+    pub async fn followers_of(&self, author: &str, tags: &[String]) -> Result<Vec<String>> {
+        let filter = doc! {"$or": [
+            {"kind": FollowKind::Author.as_str(), "target": author},
+            {"kind": FollowKind::Tag.as_str(), "target": {"$in": tags}},
+        ]};
+        let followers = self
+            .follow_collection
+            .distinct("userId", filter, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(followers
+            .into_iter()
+            .filter_map(|user_id| user_id.as_str().map(str::to_owned))
+            .filter(|user_id| user_id != author)
+            .collect())
+    }
+
+    /// Drafts past their deadline, the longest overdue first. Non-admins only
+    /// see drafts they own, contribute to or are assigned.
+    pub async fn fetch_overdue(
+        &self,
+        paging: Pagination,
+        opts: &OverdueOptions,
+        actor: &Actor<'_>,
+        read: ReadFrom,
+    ) -> Result<BlogListResponse> {
+        let mut filter = doc! {
+            "published": false,
+            "dueAt": {"$lt": Utc::now()},
+        };
+        if !actor.admin {
+            filter.insert(
+                "$or",
+                vec![
+                    doc! {"author": actor.id},
+                    doc! {"contributors.userId": actor.id},
+                    doc! {"assignee": actor.id},
+                ],
+            );
+        }
+        if let Some(assignee) = &opts.assignee {
+            filter.insert("assignee", assignee.as_str());
+        }
+
+        let find_options = FindOptions::builder()
+            .sort(doc! {"dueAt": 1, "_id": 1})
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .build();
+        let blogs = self
+            .find_summaries(filter, find_options, None, read)
+            .await?;
+
+        Ok(BlogListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: blogs.len(),
+            blogs,
+        })
+    }
+
+    /// Drafts due by `horizon` that haven't been reminded of their current deadline.
+    pub async fn due_reminders(&self, horizon: DateTime<Utc>) -> Result<Vec<BlogModel>> {
+        let filter = doc! {
+            "published": false,
+            "dueAt": {"$lte": horizon},
+            "$expr": {"$ne": ["$remindedFor", "$dueAt"]},
+        };
+        let mut cursor = self
+            .blog_collection
+            .find(filter, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut due = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            due.push(doc.map_err(MongoQueryError)?);
+        }
+        Ok(due)
+    }
+
+    /// Marks post `id` as reminded of deadline `due_at`. True only for the
+    /// first claim, so instances don't remind twice.
+    pub async fn claim_deadline_reminder(
+        &self,
+        id: ObjectId,
+        due_at: bson::DateTime,
+    ) -> Result<bool> {
+        let result = self
+            .collection
+            .update_one(
+                doc! {"_id": id, "dueAt": due_at, "remindedFor": {"$ne": due_at}},
+                doc! {"$set": {"remindedFor": due_at}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(result.modified_count == 1)
+    }
+
+    /// Marks post `id` as announced to followers. True the first time a
+    /// published post is marked, so each post is only announced once.
+    pub async fn claim_publish_notice(&self, id: &str) -> Result<bool> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let result = self
+            .collection
+            .update_one(
+                doc! {"_id": oid, "published": true, "followersNotified": {"$ne": true}},
+                doc! {"$set": {"followersNotified": true}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(result.modified_count == 1)
+    }
+
+    /// The users whose comments the viewer has asked not to see.
+    async fn blocked_by(&self, viewer: &Viewer<'_>) -> Result<Vec<String>> {
+        let Some(actor) = &viewer.actor else {
+            return Ok(Vec::new());
+        };
+
+        // Projecting only `blocked` lets the blocker index cover the query.
+        let find_options = FindOptions::builder()
+            .projection(doc! {"_id": 0, "blocked": 1})
+            .build();
+        let mut cursor = self
+            .block_collection
+            .clone_with_type::<Document>()
+            .find(doc! {"blocker": actor.id}, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut blocked = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            blocked.push(doc.map_err(MongoQueryError)?.get_str("blocked")?.to_owned());
+        }
+        Ok(blocked)
+    }
+
+    async fn is_blocked(&self, blocker: &str, blocked: &str) -> Result<bool> {
+        let block = self
+            .block_collection
+            .find_one(doc! {"blocker": blocker, "blocked": blocked}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(block.is_some())
+    }
+
+    /// Comment `id` with every earlier version of its text, for moderators.
+    pub async fn comment_history(&self, id: &str) -> Result<CommentHistoryResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let comment = self
+            .comment_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| CommentNotFoundError(id.to_owned()))?;
+
+        Ok(CommentHistoryResponse {
+            status: "success",
+            data: CommentHistoryData {
+                comment: doc_to_comment(&comment),
+                original: comment
+                    .history
+                    .first()
+                    .map_or_else(|| comment.body.to_owned(), |edit| edit.body.to_owned()),
+                edits: comment
+                    .history
+                    .iter()
+                    .map(|edit| CommentEditResponse {
+                        body: edit.body.to_owned(),
+                        replacedAt: edit.replacedAt,
+                    })
+                    .collect(),
+            },
+        })
+    }
+
+    /// A page of the comments matching `filter`, oldest first, each with its
+    /// reply count and first `inline` replies.
+    async fn comment_thread(
+        &self,
+        mut filter: Document,
+        paging: Pagination,
+        inline: i64,
+        hidden: &[String],
+        read: ReadFrom,
+    ) -> Result<Vec<CommentResponse>> {
+        hide_authors(&mut filter, hidden);
+        let find_options = FindOptions::builder()
+            .limit(paging.limit)
+            .skip(paging.skip())
+            .sort(doc! {"createdAt": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .comment_collection
+            .find(filter, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut comments = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            comments.push(doc.map_err(MongoQueryError)?);
+        }
+        if comments.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<ObjectId> = comments.iter().map(|comment| comment.id).collect();
+        let mut replies = doc! {"parentId": {"$in": ids}};
+        hide_authors(&mut replies, hidden);
+        let pipeline = vec![
+            doc! {"$match": replies},
+            doc! {"$sort": {"createdAt": 1}},
+            doc! {"$group": {
+                "_id": "$parentId",
+                "count": {"$sum": 1},
+                "replies": {"$push": "$$ROOT"},
+            }},
+            doc! {"$project": {
+                "count": 1,
+                "replies": {"$slice": ["$replies", inline.max(1)]},
+            }},
+        ];
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .comment_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut groups = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let mut group: ReplyGroup = bson::from_document(doc.map_err(MongoQueryError)?)?;
+            group.replies.truncate(inline as usize);
+            groups.insert(group.parent, group);
+        }
+
+        // Inlined replies only say how many replies they have in turn.
+        let inlined: Vec<ObjectId> = groups
+            .values()
+            .flat_map(|group| group.replies.iter().map(|reply| reply.id))
+            .collect();
+        let nested_counts = self.reply_counts(&inlined, hidden, read).await?;
+
+        Ok(comments
+            .iter()
+            .map(|comment| {
+                let mut response = doc_to_comment(comment);
+                if let Some(group) = groups.get(&comment.id) {
+                    response.replyCount = group.count;
+                    response.replies = group
+                        .replies
+                        .iter()
+                        .map(|reply| {
+                            let mut reply_response = doc_to_comment(reply);
+                            reply_response.replyCount =
+                                nested_counts.get(&reply.id).copied().unwrap_or(0);
+                            reply_response
+                        })
+                        .collect();
+                }
+                response
+            })
+            .collect())
+    }
+
+    async fn reply_counts(
+        &self,
+        ids: &[ObjectId],
+        hidden: &[String],
+        read: ReadFrom,
+    ) -> Result<HashMap<ObjectId, u64>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut filter = doc! {"parentId": {"$in": ids.to_vec()}};
+        hide_authors(&mut filter, hidden);
+        let pipeline = vec![
+            doc! {"$match": filter},
+            doc! {"$group": {"_id": "$parentId", "count": {"$sum": 1}}},
+        ];
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .comment_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut counts = HashMap::new();
+        while let Some(doc) = cursor.next().await {
+            let row: ReplyCountRow = bson::from_document(doc.map_err(MongoQueryError)?)?;
+            counts.insert(row.parent, row.count);
+        }
+        Ok(counts)
+    }
+
+    /// Issues a token that opens protected post `id`. Only the owner may.
+    pub async fn create_access_token(
+        &self,
+        id: &str,
+        label: Option<&str>,
+        actor: &Actor<'_>,
+    ) -> Result<NewAccessTokenResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        self.owned_blog(&oid, actor).await?;
+
+        let label = label.map(str::trim).filter(|label| !label.is_empty());
+        if label.is_some_and(|label| label.chars().count() > MAX_ACCESS_TOKEN_LABEL_LEN) {
+            return Err(ValidationError(format!(
+                "label must be at most {} characters",
+                MAX_ACCESS_TOKEN_LABEL_LEN
+            )));
+        }
+
+        let (token, hash) = access::new_token();
+        let model = AccessTokenModel {
+            id: ObjectId::new(),
+            hash,
+            label: label.map(str::to_owned),
+            createdAt: Utc::now(),
+        };
+        // Checked in the filter so concurrent requests can't push past the limit.
+        let mut filter = doc! {"_id": oid};
+        filter.insert(
+            format!("accessTokens.{}", MAX_ACCESS_TOKENS - 1),
+            doc! {"$exists": false},
+        );
+        let result = self
+            .blog_collection
+            .update_one(
+                filter,
+                doc! {"$push": {"accessTokens": bson::to_bson(&model)?}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.matched_count == 0 {
+            return Err(ValidationError(format!(
+                "a post can have at most {} access tokens",
+                MAX_ACCESS_TOKENS
+            )));
+        }
+
+        Ok(NewAccessTokenResponse {
+            status: "success",
+            token,
+            data: doc_to_access_token(&model),
+        })
+    }
+
+    pub async fn fetch_access_tokens(
+        &self,
+        id: &str,
+        actor: &Actor<'_>,
+    ) -> Result<AccessTokenListResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let blog = self.owned_blog(&oid, actor).await?;
+
+        let tokens: Vec<AccessTokenResponse> = blog
+            .accessTokens
+            .iter()
+            .flatten()
+            .map(doc_to_access_token)
+            .collect();
+        Ok(AccessTokenListResponse {
+            status: "success",
+            results: tokens.len(),
+            tokens,
+        })
+    }
+
+    pub async fn revoke_access_token(
+        &self,
+        id: &str,
+        token_id: &str,
+        actor: &Actor<'_>,
+    ) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let token_oid =
+            ObjectId::from_str(token_id).map_err(|_| InvalidIDError(token_id.to_owned()))?;
+        self.owned_blog(&oid, actor).await?;
+
+        let result = self
+            .blog_collection
+            .update_one(
+                doc! {"_id": oid},
+                doc! {"$pull": {"accessTokens": {"id": token_oid}}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.modified_count == 0 {
+            return Err(AccessTokenNotFoundError(token_id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Post `oid`, read from the primary, if `actor` owns it.
+    async fn owned_blog(&self, oid: &ObjectId, actor: &Actor<'_>) -> Result<BlogModel> {
+        let blog = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(oid.to_hex()))?;
+        if !is_owner(&blog, actor) {
+            return Err(NotPermittedError(
+                "only the owner can manage access tokens".to_string(),
+            ));
+        }
+        Ok(blog)
+    }
+
+    /// What `user_id` has stored against their quotas.
+    pub async fn usage(&self, user_id: &str) -> Result<UsageResponse> {
+        let drafts = self
+            .draft_collection
+            .count_documents(doc! {"userId": user_id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(UsageResponse {
+            status: "success",
+            userId: user_id.to_owned(),
+            drafts: QuotaUsage {
+                used: drafts,
+                limit: self.quotas.max_drafts,
+            },
+            contentBytes: QuotaUsage {
+                used: self.content_bytes(user_id).await?,
+                limit: self.quotas.max_content_bytes,
+            },
+        })
+    }
+
+    pub async fn record_metering(&self, batch: HashMap<MeterKey, MeterCounts>) -> Result<()> {
+        let documents = batch.into_iter().map(|(key, counts)| MeteringModel {
+            id: ObjectId::new(),
+            userId: key.user_id,
+            keyId: key.key_id,
+            method: key.method,
+            route: key.route,
+            status: key.status as i32,
+            calls: counts.calls as i64,
+            bytes: counts.bytes as i64,
+            hour: key.hour,
+        });
+
+        self.metering_collection
+            .insert_many(documents, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(())
+    }
+
+    /// `user_id`'s API calls in the window, by key and by route.
+    pub async fn metering(
+        &self,
+        user_id: &str,
+        opts: &MeteringOptions,
+        read: ReadFrom,
+    ) -> Result<MeteringResponse> {
+        let (from, to) = metering_window(opts)?;
+
+        let pipeline = vec![
+            doc! {"$match": {"userId": user_id, "hour": {"$gte": from, "$lt": to}}},
+            doc! {"$facet": {
+                "totals": [
+                    {"$group": {"_id": null, "calls": {"$sum": "$calls"}, "bytes": {"$sum": "$bytes"}}},
+                ],
+                "keys": [
+                    {"$group": {"_id": "$keyId", "calls": {"$sum": "$calls"}, "bytes": {"$sum": "$bytes"}}},
+                    {"$sort": {"calls": -1}},
+                ],
+                "routes": [
+                    {"$group": {
+                        "_id": {"method": "$method", "route": "$route", "status": "$status"},
+                        "calls": {"$sum": "$calls"},
+                        "bytes": {"$sum": "$bytes"},
+                    }},
+                    {"$sort": {"calls": -1}},
+                ],
+            }},
+        ];
+
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .metering_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let facets: MeteringFacets = match cursor.next().await {
+            Some(doc) => bson::from_document(doc.map_err(MongoQueryError)?)?,
+            None => MeteringFacets::default(),
+        };
+        let totals = facets.totals.first();
+
+        Ok(MeteringResponse {
+            status: "success",
+            userId: user_id.to_owned(),
+            from,
+            to,
+            calls: totals.map_or(0, |totals| totals.calls),
+            bytes: totals.map_or(0, |totals| totals.bytes),
+            keys: facets
+                .keys
+                .into_iter()
+                .map(|row| KeyMeteringResponse {
+                    keyId: row.key_id,
+                    calls: row.calls,
+                    bytes: row.bytes,
+                })
+                .collect(),
+            routes: facets
+                .routes
+                .into_iter()
+                .map(|row| RouteMeteringResponse {
+                    method: row.route.method,
+                    route: row.route.route,
+                    status: row.route.status,
+                    calls: row.calls,
+                    bytes: row.bytes,
+                })
+                .collect(),
+        })
+    }
+
+    /// The latest logged slow queries, newest first.
+    pub async fn fetch_slow_queries(
+        &self,
+        opts: &SlowQueryOptions,
+        paging: Pagination,
+    ) -> Result<SlowQueryListResponse> {
+        let found = self
+            .slow_queries
+            .recent(opts.collection.as_deref(), paging)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let queries: Vec<SlowQueryResponse> = found
+            .into_iter()
+            .map(|query| SlowQueryResponse {
+                id: query.id.to_hex(),
+                operation: query.operation,
+                collection: query.collection,
+                durationMs: query.durationMs,
+                shape: Bson::Document(query.shape).into_relaxed_extjson(),
+                sort: query
+                    .sort
+                    .map(|sort| Bson::Document(sort).into_relaxed_extjson()),
+                plan: query.plan,
+                traceId: query.traceId,
+                at: query.at,
+            })
+            .collect();
+
+        Ok(SlowQueryListResponse {
+            status: "success",
+            page: paging.page,
+            limit: paging.limit,
+            results: queries.len(),
+            queries,
+        })
+    }
+
+    /// Indexes that would serve recent slow queries.
+    pub async fn index_advice(&self, opts: &IndexAdviceOptions) -> Result<IndexAdviceResponse> {
+        let days = opts.days.unwrap_or(DEFAULT_INDEX_ADVICE_DAYS);
+        if !(1..=MAX_INDEX_ADVICE_DAYS).contains(&days) {
+            return Err(ValidationError(format!(
+                "days must be between 1 and {}",
+                MAX_INDEX_ADVICE_DAYS
+            )));
+        }
+
+        let since = Utc::now() - chrono::Duration::days(days);
+        let suggestions = self
+            .index_advisor
+            .suggest(since, opts.collection.as_deref())
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(IndexAdviceResponse {
+            status: "success",
+            since,
+            results: suggestions.len(),
+            suggestions,
+        })
+    }
+
+    /// Starts building the index in `body` in the background.
+    pub async fn start_index_build(
+        &self,
+        body: BuildIndexSchema,
+    ) -> Result<SingleIndexBuildResponse> {
+        if body.keys.is_empty() || body.keys.len() > MAX_INDEX_KEYS {
+            return Err(ValidationError(format!(
+                "an index needs between 1 and {} keys",
+                MAX_INDEX_KEYS
+            )));
+        }
+        let mut fields = HashSet::new();
+        for key in &body.keys {
+            if key.field.is_empty() || key.field.starts_with('$') {
+                return Err(ValidationError(format!(
+                    "invalid index field: '{}'",
+                    key.field
+                )));
+            }
+            if key.direction != 1 && key.direction != -1 {
+                return Err(ValidationError(
+                    "index key direction must be 1 or -1".to_string(),
+                ));
+            }
+            if !fields.insert(key.field.as_str()) {
+                return Err(ValidationError(format!(
+                    "index field '{}' is listed twice",
+                    key.field
+                )));
+            }
+        }
+
+        let exists = self
+            .index_advisor
+            .collection_exists(&body.collection)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if !exists {
+            return Err(NotFoundError(body.collection));
+        }
+
+        Ok(SingleIndexBuildResponse {
+            status: "success",
+            data: IndexBuildData {
+                build: self.index_advisor.build(&body.collection, body.keys),
+            },
+        })
+    }
+
+    pub async fn index_builds(&self) -> Result<IndexBuildListResponse> {
+        let builds = self.index_advisor.builds().await.map_err(MongoQueryError)?;
+
+        Ok(IndexBuildListResponse {
+            status: "success",
+            results: builds.len(),
+            builds,
+        })
+    }
+
+    pub async fn migration_jobs(&self) -> Result<MigrationJobListResponse> {
+        let jobs = self.migrations.jobs().await.map_err(MongoQueryError)?;
+
+        Ok(MigrationJobListResponse {
+            status: "success",
+            results: jobs.len(),
+            jobs: jobs.iter().map(doc_to_migration_job).collect(),
+        })
+    }
+
+    /// Pauses, resumes or aborts migration `name`, as `action` says.
+    pub async fn change_migration(
+        &self,
+        name: &str,
+        action: MigrationAction,
+    ) -> Result<SingleMigrationJobResponse> {
+        let transition = match action {
+            MigrationAction::Pause => self.migrations.pause(name).await,
+            MigrationAction::Resume => self.migrations.resume(name).await,
+            MigrationAction::Abort => self.migrations.abort(name).await,
+        }
+        .map_err(MongoQueryError)?;
+
+        match transition {
+            Transition::Changed(job) => Ok(SingleMigrationJobResponse {
+                status: "success",
+                data: MigrationJobData {
+                    job: doc_to_migration_job(&job),
+                },
+            }),
+            Transition::NotFound => Err(MigrationNotFoundError(name.to_owned())),
+            Transition::Refused(state) => Err(MigrationStateError(name.to_owned(), state)),
+        }
+    }
+
+    async fn field_definitions(&self, read: ReadFrom) -> Result<Vec<FieldDefinitionModel>> {
+        let options = FindOptions::builder()
+            .sort(doc! {"_id": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .field_collection
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut definitions = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            definitions.push(doc.map_err(MongoQueryError)?);
+        }
+        Ok(definitions)
+    }
+
+    /// `values` as stored, once they pass the site's field definitions.
+    async fn check_custom_fields(
+        &self,
+        values: &BTreeMap<String, serde_json::Value>,
+    ) -> Result<Document> {
+        let definitions = self.field_definitions(ReadFrom::Primary).await?;
+        custom_fields::validate(&definitions, values).map_err(ValidationError)
+    }
+
+    async fn custom_field_filter(
+        &self,
+        filters: &[(String, String)],
+        read: ReadFrom,
+    ) -> Result<Document> {
+        let definitions = self.field_definitions(read).await?;
+        let mut filter = doc! {};
+        for (key, raw) in filters {
+            let Some(definition) = definitions.iter().find(|definition| &definition.key == key)
+            else {
+                return Err(ValidationError(format!("unknown custom field {}", key)));
+            };
+            filter.extend(custom_fields::filter(definition, raw).map_err(ValidationError)?);
+        }
+        Ok(filter)
+    }
+
+    pub async fn fetch_field_definitions(
+        &self,
+        read: ReadFrom,
+    ) -> Result<FieldDefinitionListResponse> {
+        let fields: Vec<FieldDefinitionResponse> = self
+            .field_definitions(read)
+            .await?
+            .iter()
+            .map(doc_to_field_definition)
+            .collect();
+
+        Ok(FieldDefinitionListResponse {
+            status: "success",
+            results: fields.len(),
+            fields,
+        })
+    }
+
+    /// Creates custom field `key` or replaces its definition.
+    pub async fn put_field_definition(
+        &self,
+        key: &str,
+        body: &FieldDefinitionSchema,
+        user_id: &str,
+    ) -> Result<SingleFieldDefinitionResponse> {
+        custom_fields::check_key(key).map_err(ValidationError)?;
+        let label = body.label.trim();
+        if label.is_empty() || label.chars().count() > MAX_FIELD_LABEL_LEN {
+            return Err(ValidationError(format!(
+                "label must be 1 to {} characters",
+                MAX_FIELD_LABEL_LEN
+            )));
+        }
+        let options = match body.kind {
+            FieldType::Select => {
+                let options = normalize_field_options(body.options.as_deref().unwrap_or_default());
+                if options.is_empty() || options.len() > MAX_FIELD_OPTIONS {
+                    return Err(ValidationError(format!(
+                        "select fields need 1 to {} options",
+                        MAX_FIELD_OPTIONS
+                    )));
+                }
+                Some(options)
+            }
+            _ => None,
+        };
+        if let (Some(min), Some(max)) = (body.min, body.max) {
+            if min > max {
+                return Err(ValidationError("min must not be above max".to_string()));
+            }
+        }
+        if body.maxLength.is_some_and(|max_len| max_len < 1) {
+            return Err(ValidationError("maxLength must be at least 1".to_string()));
+        }
+
+        let exists = self
+            .field_collection
+            .count_documents(doc! {"_id": key}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            > 0;
+        if !exists {
+            let count = self
+                .field_collection
+                .count_documents(None, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            if count >= MAX_CUSTOM_FIELDS {
+                return Err(QuotaExceededError("custom fields", MAX_CUSTOM_FIELDS));
+            }
+        }
+
+        let numeric = body.kind == FieldType::Number;
+        let definition = FieldDefinitionModel {
+            key: key.to_owned(),
+            label: label.to_owned(),
+            kind: body.kind,
+            required: body.required,
+            options,
+            min: body.min.filter(|_| numeric),
+            max: body.max.filter(|_| numeric),
+            maxLength: body.maxLength.filter(|_| body.kind == FieldType::Text),
+            updatedBy: Some(user_id.to_owned()),
+            updatedAt: Utc::now(),
+        };
+        let options = ReplaceOptions::builder().upsert(true).build();
+        self.field_collection
+            .replace_one(doc! {"_id": key}, &definition, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(SingleFieldDefinitionResponse {
+            status: "success",
+            data: FieldDefinitionData {
+                field: doc_to_field_definition(&definition),
+            },
+        })
+    }
+
+    /// Removes custom field `key`. Posts keep their values, but they're no
+    /// longer accepted on write.
+    pub async fn delete_field_definition(&self, key: &str) -> Result<()> {
+        let result = self
+            .field_collection
+            .delete_one(doc! {"_id": key}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.deleted_count == 0 {
+            return Err(FieldNotFoundError(key.to_string()));
+        }
+        Ok(())
+    }
+
+    /// This deployment's settings, categories, templates and custom fields,
+    /// without ids, timestamps or who last changed them.
+    pub async fn snapshot_environment(&self) -> Result<Snapshot> {
+        let settings = self.settings(ReadFrom::Primary).await?;
+
+        let options = FindOptions::builder().sort(doc! {"path": 1}).build();
+        let mut cursor = self
+            .category_collection
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut categories = Vec::new();
+        while let Some(category) = cursor.next().await {
+            let category = category.map_err(MongoQueryError)?;
+            categories.push(CreateCategorySchema {
+                name: category.name,
+                parent: category.parent,
+            });
+        }
+
+        let options = FindOptions::builder().sort(doc! {"name": 1}).build();
+        let mut cursor = self
+            .template_collection
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let mut templates = Vec::new();
+        while let Some(template) = cursor.next().await {
+            let template = template.map_err(MongoQueryError)?;
+            templates.push(CreateTemplateSchema {
+                name: template.name,
+                titlePattern: template.titlePattern,
+                summary: template.summary,
+                content: template.content,
+                tags: template.tags,
+                category: template.category,
+            });
+        }
+
+        let fields = self
+            .field_definitions(ReadFrom::Primary)
+            .await?
+            .into_iter()
+            .map(|definition| FieldSnapshot {
+                key: definition.key,
+                definition: FieldDefinitionSchema {
+                    label: definition.label,
+                    kind: definition.kind,
+                    required: definition.required,
+                    options: definition.options,
+                    min: definition.min,
+                    max: definition.max,
+                    maxLength: definition.maxLength,
+                },
+            })
+            .collect();
+
+        Ok(Snapshot {
+            version: SNAPSHOT_VERSION,
+            exportedAt: Utc::now(),
+            settings: SettingsSchema {
+                siteTitle: settings.siteTitle,
+                description: settings.description,
+                postsPerPage: settings.postsPerPage,
+                defaultCategory: settings.defaultCategory,
+                socialLinks: settings.socialLinks,
+                theme: settings.theme,
+                comments: settings.comments,
+                suggestions: settings.suggestions,
+            },
+            categories,
+            templates,
+            fields,
+        })
+    }
+
+    pub async fn fetch_templates(&self, read: ReadFrom) -> Result<TemplateListResponse> {
+        let options = FindOptions::builder()
+            .sort(doc! {"name": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .template_collection
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut templates = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            templates.push(doc_to_template(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(TemplateListResponse {
+            status: "success",
+            results: templates.len(),
+            templates,
+        })
+    }
+
+    pub async fn create_template(
+        &self,
+        body: &CreateTemplateSchema,
+        user_id: &str,
+    ) -> Result<SingleTemplateResponse> {
+        let (name, title_pattern) = self.check_template(body)?;
+        let count = self
+            .template_collection
+            .count_documents(None, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if count >= MAX_TEMPLATES {
+            return Err(QuotaExceededError("templates", MAX_TEMPLATES));
+        }
+
+        let template = TemplateModel {
+            id: ObjectId::new(),
+            name: name.to_owned(),
+            titlePattern: title_pattern.to_owned(),
+            summary: body.summary.to_owned(),
+            content: body.content.to_owned(),
+            tags: normalize_tags(&body.tags),
+            category: body.category.to_owned(),
+            createdBy: Some(user_id.to_owned()),
+            createdAt: Utc::now(),
+        };
+        self.template_collection
+            .insert_one(&template, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(write_error)?;
+
+        Ok(SingleTemplateResponse {
+            status: "success",
+            data: TemplateData {
+                template: doc_to_template(&template),
+            },
+        })
+    }
+
+    /// Creates template `body.name`, or overwrites the one by that name in
+    /// place so its id stays the same.
+    pub async fn put_template(&self, body: &CreateTemplateSchema, user_id: &str) -> Result<()> {
+        let (name, title_pattern) = self.check_template(body)?;
+        let exists = self
+            .template_collection
+            .count_documents(doc! {"name": name}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            > 0;
+        if !exists {
+            let count = self
+                .template_collection
+                .count_documents(None, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+            if count >= MAX_TEMPLATES {
+                return Err(QuotaExceededError("templates", MAX_TEMPLATES));
+            }
+        }
+
+        let changes = doc! {
+            "titlePattern": title_pattern,
+            "summary": &body.summary,
+            "content": &body.content,
+            "tags": normalize_tags(&body.tags),
+            "category": body.category.as_deref(),
+        };
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.template_collection
+            .update_one(
+                doc! {"name": name},
+                doc! {
+                    "$set": changes,
+                    "$setOnInsert": {"createdBy": user_id, "createdAt": Utc::now()},
+                },
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(write_error)?;
+        Ok(())
+    }
+
+    /// The trimmed name and title pattern of `body`, once they check out.
+    fn check_template<'a>(&self, body: &'a CreateTemplateSchema) -> Result<(&'a str, &'a str)> {
+        let name = body.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_TEMPLATE_NAME_LEN {
+            return Err(ValidationError(format!(
+                "name must be 1 to {} characters",
+                MAX_TEMPLATE_NAME_LEN
+            )));
+        }
+        let title_pattern = body.titlePattern.trim();
+        if title_pattern.is_empty() || title_pattern.chars().count() > MAX_TITLE_PATTERN_LEN {
+            return Err(ValidationError(format!(
+                "titlePattern must be 1 to {} characters",
+                MAX_TITLE_PATTERN_LEN
+            )));
+        }
+        self.names.check_text(title_pattern)?;
+        Ok((name, title_pattern))
+    }
+
+    pub async fn delete_template(&self, id: &str) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let result = self
+            .template_collection
+            .delete_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.deleted_count == 0 {
+            return Err(TemplateNotFoundError(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Fills whatever `body` leaves empty from template `id`: title,
+    /// summary, content, tags and category.
+    pub async fn apply_template(&self, id: &str, body: &mut CreateBlogSchema) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let Some(template) = self
+            .template_collection
+            .find_one(doc! {"_id": oid}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        else {
+            return Err(TemplateNotFoundError(id.to_string()));
+        };
+
+        if body.title.trim().is_empty() {
+            body.title = fill_title_pattern(&template.titlePattern, Utc::now());
+        }
+        if body.summary.is_empty() {
+            body.summary = template.summary;
+        }
+        if body.content.is_empty() {
+            body.content = template.content;
+        }
+        if body.tags.is_none() {
+            body.tags = Some(template.tags);
+        }
+        if body.category.is_none() {
+            body.category = template.category;
+        }
+        Ok(())
+    }
+
+    pub async fn create_saved_search(
+        &self,
+        owner: &str,
+        body: &SavedSearchSchema,
+    ) -> Result<SingleSavedSearchResponse> {
+        let name = body.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_SAVED_SEARCH_NAME_LEN {
+            return Err(ValidationError(format!(
+                "name must be 1 to {} characters",
+                MAX_SAVED_SEARCH_NAME_LEN
+            )));
+        }
+        let count = self
+            .saved_search_collection
+            .count_documents(doc! {"owner": owner}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if count >= MAX_SAVED_SEARCHES {
+            return Err(QuotaExceededError("saved searches", MAX_SAVED_SEARCHES));
+        }
+
+        let search = SavedSearchModel {
+            id: ObjectId::new(),
+            owner: owner.to_owned(),
+            name: name.to_owned(),
+            status: body.status,
+            tag: body.tag.as_deref().map(normalize_tag),
+            author: body.author.to_owned(),
+            category: body.category.to_owned(),
+            includeDescendants: body.includeDescendants.unwrap_or(false),
+            from: body.from.map(bson::DateTime::from_chrono),
+            to: body.to.map(bson::DateTime::from_chrono),
+            createdAt: Utc::now(),
+        };
+        // Rejects a date range that's back to front before it's kept.
+        post_filter(&PostCriteria::from_saved(&search), false)?;
+
+        self.saved_search_collection
+            .insert_one(&search, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(write_error)?;
+
+        Ok(SingleSavedSearchResponse {
+            status: "success",
+            data: SavedSearchData {
+                search: doc_to_saved_search(&search),
+            },
+        })
+    }
+
+    pub async fn fetch_saved_searches(
+        &self,
+        owner: &str,
+        read: ReadFrom,
+    ) -> Result<SavedSearchListResponse> {
+        let options = FindOptions::builder()
+            .sort(doc! {"name": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .saved_search_collection
+            .find(doc! {"owner": owner}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut searches = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            searches.push(doc_to_saved_search(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(SavedSearchListResponse {
+            status: "success",
+            results: searches.len(),
+            searches,
+        })
+    }
+
+    pub async fn delete_saved_search(&self, id: &str, owner: &str) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let result = self
+            .saved_search_collection
+            .delete_one(doc! {"_id": oid, "owner": owner}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if result.deleted_count == 0 {
+            return Err(SavedSearchNotFoundError(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Runs saved search `id` as the post list would run the same filters.
+    /// Hidden posts are included for admins, and for searches of the
+    /// owner's own posts.
+    pub async fn saved_search_results(
+        &self,
+        id: &str,
+        actor: &Actor<'_>,
+        paging: Pagination,
+        visitor: Option<&str>,
+        read: ReadFrom,
+    ) -> Result<BlogListResponse> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+        let options = FindOneOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let Some(search) = self
+            .saved_search_collection
+            .find_one(doc! {"_id": oid, "owner": actor.id}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+        else {
+            return Err(SavedSearchNotFoundError(id.to_string()));
+        };
+
+        let include_hidden = actor.admin || search.author.as_deref() == Some(actor.id);
+        self.list_posts(
+            paging,
+            &PostCriteria::from_saved(&search),
+            include_hidden,
+            visitor,
+            read,
+        )
+        .await
+    }
+
+    /// Adds a line to the activity stream.
+    pub async fn record_activity(
+        &self,
+        kind: ActivityKind,
+        actor: Option<&str>,
+        subject: &str,
+        summary: String,
+    ) -> Result<()> {
+        let entry = ActivityModel {
+            id: ObjectId::new(),
+            kind,
+            actor: actor.map(str::to_owned),
+            subject: subject.to_owned(),
+            summary,
+            createdAt: Utc::now(),
+        };
+        self.activity_collection
+            .insert_one(&entry, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(())
+    }
+
+    /// The activity stream after `opts.after`, or without it the latest
+    /// entries, oldest first.
+    ///
+    /// When nothing has happened since `after`, waits up to `opts.wait`
+    /// seconds for something to. Ids come from the clock of the instance that
+    /// wrote them, so with several instances an entry written within a second
+    /// of `after` can be missed.
+    pub async fn fetch_activity(&self, opts: &ActivityOptions) -> Result<ActivityListResponse> {
+        let limit = opts.limit.unwrap_or(DEFAULT_ACTIVITY_LIMIT);
+        if !(1..=MAX_ACTIVITY_LIMIT).contains(&limit) {
+            return Err(ValidationError(format!(
+                "limit must be between 1 and {}",
+                MAX_ACTIVITY_LIMIT
+            )));
+        }
+        let wait = opts.wait.unwrap_or(0);
+        if wait > MAX_ACTIVITY_WAIT_SECS {
+            return Err(ValidationError(format!(
+                "wait must be at most {} seconds",
+                MAX_ACTIVITY_WAIT_SECS
+            )));
+        }
+
+        let entries = match &opts.after {
+            Some(after) => {
+                let oid =
+                    ObjectId::from_str(after).map_err(|_| InvalidIDError(after.to_owned()))?;
+                let mut entries = self.activity_after(oid, limit).await?;
+                if entries.is_empty() && wait > 0 && self.await_activity(oid, wait).await? {
+                    entries = self.activity_after(oid, limit).await?;
+                }
+                entries
+            }
+            None => {
+                let options = FindOptions::builder()
+                    .sort(doc! {"$natural": -1})
+                    .limit(limit)
+                    .build();
+                let mut entries = self.find_activity(doc! {}, options).await?;
+                entries.reverse();
+                entries
+            }
+        };
+
+        let cursor = entries
+            .last()
+            .map(|entry| entry.id.to_hex())
+            .or_else(|| opts.after.clone());
+        let activity: Vec<ActivityResponse> = entries
+            .into_iter()
+            .map(|entry| ActivityResponse {
+                id: entry.id.to_hex(),
+                kind: entry.kind,
+                actor: entry.actor,
+                subject: entry.subject,
+                summary: entry.summary,
+                createdAt: entry.createdAt,
+            })
+            .collect();
+
+        Ok(ActivityListResponse {
+            status: "success",
+            results: activity.len(),
+            cursor,
+            activity,
+        })
+    }
+
+    async fn activity_after(&self, after: ObjectId, limit: i64) -> Result<Vec<ActivityModel>> {
+        let options = FindOptions::builder().limit(limit).build();
+        self.find_activity(doc! {"_id": {"$gt": after}}, options)
+            .await
+    }
+
+    async fn find_activity(
+        &self,
+        filter: Document,
+        options: FindOptions,
+    ) -> Result<Vec<ActivityModel>> {
+        let mut cursor = self
+            .activity_collection
+            .find(filter, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut entries = Vec::new();
+        while cursor.advance().await.map_err(MongoQueryError)? {
+            entries.push(cursor.deserialize_current().map_err(MongoQueryError)?);
+        }
+        Ok(entries)
+    }
+
+    /// Whether an entry after `after` turns up within `wait_secs`, watched for
+    /// on a tailable cursor rather than by polling.
+    async fn await_activity(&self, after: ObjectId, wait_secs: u64) -> Result<bool> {
+        let wait = StdDuration::from_secs(wait_secs);
+        let options = FindOptions::builder()
+            .cursor_type(CursorType::TailableAwait)
+            .max_await_time(wait)
+            .batch_size(1)
+            .build();
+        let mut tail = self
+            .activity_collection
+            .find(doc! {"_id": {"$gt": after}}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        // The cursor only ends on its own when the collection is still empty.
+        match tokio::time::timeout(wait, tail.advance()).await {
+            Ok(arrived) => arrived.map_err(MongoQueryError),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Every user's API calls in the window, one line each, for billing.
+    pub async fn metering_rollup(
+        &self,
+        paging: Pagination,
+        opts: &MeteringOptions,
+        read: ReadFrom,
+    ) -> Result<MeteringRollupResponse> {
+        let (from, to) = metering_window(opts)?;
+
+        let pipeline = vec![
+            doc! {"$match": {"hour": {"$gte": from, "$lt": to}}},
+            doc! {"$group": {
+                "_id": "$userId",
+                "calls": {"$sum": "$calls"},
+                "failedCalls": {"$sum": {"$cond": [{"$gte": ["$status", 400]}, "$calls", 0]}},
+                "bytes": {"$sum": "$bytes"},
+            }},
+            doc! {"$sort": {"_id": 1}},
+            doc! {"$skip": paging.skip() as i64},
+            doc! {"$limit": paging.limit},
+        ];
+
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self
+            .metering_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut users = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let row: UserMeteringRow = bson::from_document(doc.map_err(MongoQueryError)?)?;
+            users.push(UserMeteringResponse {
+                userId: row.user_id,
+                calls: row.calls,
+                failedCalls: row.failed_calls,
+                bytes: row.bytes,
+            });
+        }
+
+        Ok(MeteringRollupResponse {
+            status: "success",
+            from,
+            to,
+            page: paging.page,
+            limit: paging.limit,
+            results: users.len(),
+            users,
+        })
+    }
+
+    /// Posts by whether they are published and who may see them.
+    pub async fn post_counts(&self, read: ReadFrom) -> Result<PostCountsResponse> {
+        let pipeline = vec![doc! {"$group": {
+            "_id": null,
+            "total": {"$sum": 1},
+            "published": {"$sum": {"$cond": [{"$eq": ["$published", true]}, 1, 0]}},
+            "unlisted": {"$sum": {"$cond": [{"$eq": ["$visibility", "unlisted"]}, 1, 0]}},
+            "protected": {"$sum": {"$cond": [{"$eq": ["$visibility", "protected"]}, 1, 0]}},
+        }}];
+
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let row = match self
+            .collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .next()
+            .await
+        {
+            Some(doc) => bson::from_document(doc.map_err(MongoQueryError)?)?,
+            None => PostCountRow::default(),
+        };
+
+        Ok(PostCountsResponse {
+            total: row.total,
+            published: row.published,
+            drafts: row.total.saturating_sub(row.published),
+            unlisted: row.unlisted,
+            protected: row.protected,
+        })
+    }
+
+    pub async fn comment_counts(&self, read: ReadFrom) -> Result<CommentCountsResponse> {
+        let since = Utc::now() - chrono::Duration::days(1);
+        let options = || {
+            CountOptions::builder()
+                .selection_criteria(self.reads.criteria(read))
+                .build()
+        };
+
+        let total = self
+            .comment_collection
+            .count_documents(None, options())
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let last_day = self
+            .comment_collection
+            .count_documents(doc! {"createdAt": {"$gte": since}}, options())
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(CommentCountsResponse {
+            total,
+            lastDay: last_day,
+        })
+    }
+
+    /// Share of metered calls over the current and previous hour that ended
+    /// in a server error.
+    pub async fn error_rate(&self, read: ReadFrom) -> Result<ErrorRateResponse> {
+        let hour = chrono::Duration::hours(1);
+        let from = Utc::now()
+            .duration_trunc(hour)
+            .unwrap_or_else(|_| Utc::now())
+            - hour;
+
+        let pipeline = vec![
+            doc! {"$match": {"hour": {"$gte": from}}},
+            doc! {"$group": {
+                "_id": null,
+                "calls": {"$sum": "$calls"},
+                "serverErrors": {"$sum": {"$cond": [{"$gte": ["$status", 500]}, "$calls", 0]}},
+            }},
+        ];
+
+        let options = mongodb::options::AggregateOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let row = match self
+            .metering_collection
+            .aggregate(pipeline, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .next()
+            .await
+        {
+            Some(doc) => bson::from_document(doc.map_err(MongoQueryError)?)?,
+            None => ErrorRateRow::default(),
+        };
+
+        Ok(ErrorRateResponse {
+            calls: row.calls,
+            serverErrors: row.server_errors,
+            rate: match row.calls {
+                0 => 0.0,
+                calls => row.server_errors as f64 / calls as f64,
+            },
+        })
+    }
+
+    /// Contact messages nobody has opened yet.
+    pub async fn count_unread_contact(&self, read: ReadFrom) -> Result<u64> {
+        let options = CountOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        self.contact_collection
+            .count_documents(doc! {"status": ContactStatus::New.as_str()}, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)
+    }
+
+    /// Fails if `author` has already created `limit` posts this calendar
+    /// month (UTC). A `None` limit is unlimited.
+    pub async fn check_monthly_posts(&self, author: &str, limit: Option<u64>) -> Result<()> {
+        let Some(limit) = limit else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        let month_start = Utc
+            .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .unwrap();
+        let posts = self
+            .blog_collection
+            .count_documents(
+                doc! {"author": author, "createdAt": {"$gte": month_start}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        if posts >= limit {
+            return Err(QuotaExceededError("posts this month", limit));
+        }
+        Ok(())
+    }
+
+    /// Fails if storing `adding` bytes in place of `replacing` would take
+    /// `user_id` over their content quota.
+    async fn check_content_quota(
+        &self,
+        user_id: &str,
+        adding: usize,
+        replacing: usize,
+    ) -> Result<()> {
+        if adding <= replacing {
+            return Ok(());
+        }
+
+        let used = self.content_bytes(user_id).await?;
+        let after = used.saturating_sub(replacing as u64) + adding as u64;
+        if after > self.quotas.max_content_bytes {
+            return Err(QuotaExceededError(
+                "content bytes",
+                self.quotas.max_content_bytes,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Bytes of content in the posts `user_id` owns and in their drafts.
+    async fn content_bytes(&self, user_id: &str) -> Result<u64> {
+        let posts = self
+            .collection
+            .aggregate(
+                vec![
+                    doc! {"$match": {"author": user_id}},
+                    doc! {"$group": {"_id": null, "bytes": {"$sum": {"$strLenBytes": "$content"}}}},
+                ],
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        let drafts = self
+            .draft_collection
+            .aggregate(
+                vec![
+                    doc! {"$match": {"userId": user_id}},
+                    doc! {"$group": {
+                        "_id": null,
+                        "bytes": {"$sum": {"$strLenBytes": {"$ifNull": ["$content", ""]}}},
+                    }},
+                ],
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut bytes = 0;
+        for mut cursor in [posts, drafts] {
+            if let Some(total) = cursor.next().await {
+                let total = total.map_err(MongoQueryError)?;
+                bytes += total
+                    .get_i64("bytes")
+                    .or_else(|_| total.get_i32("bytes").map(i64::from))?;
+            }
+        }
+        Ok(bytes.max(0) as u64)
+    }
+
+    pub async fn discard_draft(&self, id: &str, user_id: &str) -> Result<()> {
+        let oid = ObjectId::from_str(id).map_err(|_| InvalidIDError(id.to_owned()))?;
+
+        let result = self
+            .draft_collection
+            .delete_one(doc! {"postId": oid, "userId": user_id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        match result.deleted_count {
+            0 => Err(DraftNotFoundError(id.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Drops every autosave `user_id` has pending, on any post.
+    pub async fn discard_drafts_by(&self, user_id: &str) -> Result<u64> {
+        let result = self
+            .draft_collection
+            .delete_many(doc! {"userId": user_id}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(result.deleted_count)
+    }
+
+    pub async fn fetch_categories(&self, read: ReadFrom) -> Result<CategoryListResponse> {
+        let find_options = FindOptions::builder()
+            .sort(doc! {"path": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+
+        let mut cursor = self
+            .category_collection
+            .find(None, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<CategoryResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_category(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(CategoryListResponse {
+            status: "success",
+            results: json_result.len(),
+            categories: json_result,
+        })
+    }
+
+    pub async fn create_category(
+        &self,
+        body: &CreateCategorySchema,
+    ) -> Result<SingleCategoryResponse> {
+        let name = body.name.trim();
+        if name.is_empty() {
+            return Err(ValidationError(
+                "category name must not be empty".to_string(),
+            ));
+        }
+
+        let mut path = match &body.parent {
+            Some(parent) => {
+                self.category_collection
+                    .find_one(doc! {"name": parent.as_str()}, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?
+                    .ok_or_else(|| ValidationError(format!("unknown parent category: {}", parent)))?
+                    .path
+            }
+            None => Vec::new(),
+        };
+        path.push(name.to_owned());
+
+        let category = CategoryModel {
+            id: ObjectId::new(),
+            name: name.to_owned(),
+            parent: body.parent.to_owned(),
+            path,
+            createdAt: bson::DateTime::now().to_chrono(),
+        };
+
+        match self.category_collection.insert_one(&category, None).await {
+            Ok(_) => {}
+            Err(e) => {
+                return Err(write_error(e));
+            }
+        };
+
+        Ok(SingleCategoryResponse {
+            status: "success",
+            data: CategoryData {
+                category: self.doc_to_category(&category),
+            },
+        })
+    }
+
+    pub async fn fetch_tag_stats(
+        &self,
+        kind: Option<StatKind>,
+        read: ReadFrom,
+    ) -> Result<TagStatListResponse> {
+        let mut filter = doc! {"count": {"$gt": 0}};
+        if let Some(kind) = kind {
+            filter.insert("kind", kind.as_str());
+        }
+
+        let find_options = FindOptions::builder()
+            .sort(doc! {"count": -1, "name": 1})
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+
+        let mut cursor = self
+            .tag_stats_collection
+            .find(filter, find_options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut json_result: Vec<TagStatResponse> = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            json_result.push(self.doc_to_tag_stat(&doc.map_err(MongoQueryError)?));
+        }
+
+        Ok(TagStatListResponse {
+            status: "success",
+            results: json_result.len(),
+            stats: json_result,
+        })
+    }
+
+    /// Recounts every tag and category from the posts themselves and replaces
+    /// `tag_stats` with the result.
+    ///
+    /// Meant for repairing drift; a post written while the recount runs may be
+    /// missed until the next rebuild.
+    pub async fn rebuild_tag_stats(&self) -> Result<TagStatListResponse> {
+        let stats = self.count_tag_stats().await?;
+
+        let mut session = self.start_transaction().await?;
+        self.tag_stats_collection
+            .delete_many_with_session(doc! {}, None, &mut session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        if !stats.is_empty() {
+            self.tag_stats_collection
+                .insert_many_with_session(&stats, None, &mut session)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+        }
+        session
+            .commit_transaction()
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.fetch_tag_stats(None, ReadFrom::Primary).await
+    }
+
+    /// Scans for data that has drifted out of step: drafts and revisions
+    /// left behind by deleted posts, gaps in revision numbering, category paths
+    /// that no longer match their category, and tag/category counters that
+    /// disagree with the posts. With `repair` each issue found is also fixed.
+    pub async fn audit_consistency(&self, repair: bool) -> Result<ConsistencyReportResponse> {
+        let mut issues: Vec<ConsistencyIssue> = Vec::new();
+        let mut repaired = 0;
+
+        let mut post_ids: HashSet<ObjectId> = HashSet::new();
+        let mut category_paths: Vec<(ObjectId, String, Vec<String>)> = Vec::new();
+        let options = FindOptions::builder()
+            .projection(doc! {"category": 1, "categoryPath": 1})
+            .build();
+        let mut cursor = self
+            .collection
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            let id = doc.get_object_id("_id")?;
+            post_ids.insert(id);
+
+            let category = doc.get_str("category").unwrap_or_default().to_owned();
+            let path = doc
+                .get_array("categoryPath")
+                .map(|path| {
+                    path.iter()
+                        .filter_map(|name| name.as_str().map(str::to_owned))
+                        .collect()
+                })
+                .unwrap_or_default();
+            category_paths.push((id, category, path));
+        }
+
+        // Drafts and revisions whose post is gone.
+        for (kind, collection) in [
+            (
+                "orphanedDraft",
+                self.draft_collection.clone_with_type::<Document>(),
+            ),
+            (
+                "orphanedRevision",
+                self.revision_collection.clone_with_type::<Document>(),
+            ),
+        ] {
+            let orphans: Vec<ObjectId> = collection
+                .distinct("postId", None, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?
+                .into_iter()
+                .filter_map(|id| id.as_object_id())
+                .filter(|id| !post_ids.contains(id))
+                .collect();
+            if orphans.is_empty() {
+                continue;
+            }
+
+            for id in &orphans {
+                issues.push(ConsistencyIssue {
+                    kind,
+                    id: id.to_hex(),
+                    detail: "post no longer exists".to_string(),
+                });
+            }
+            if repair {
+                collection
+                    .delete_many(doc! {"postId": {"$in": orphans.clone()}}, None)
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+                repaired += orphans.len();
+            }
+        }
+
+        // Every post should have revisions numbered 1..=n.
+        let mut numbers: HashMap<ObjectId, Vec<i64>> = HashMap::new();
+        let options = FindOptions::builder()
+            .projection(doc! {"postId": 1, "number": 1})
+            .sort(doc! {"postId": 1, "number": 1})
+            .build();
+        let mut cursor = self
+            .revision_collection
+            .clone_with_type::<Document>()
+            .find(None, options)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            numbers
+                .entry(doc.get_object_id("postId")?)
+                .or_default()
+                .push(doc.get_i64("number")?);
+        }
+        for id in &post_ids {
+            let found = numbers.get(id).map(Vec::as_slice).unwrap_or_default();
+            if found.is_empty() {
+                issues.push(ConsistencyIssue {
+                    kind: "missingRevisions",
+                    id: id.to_hex(),
+                    detail: "post has no revisions".to_string(),
+                });
+                if repair {
+                    if let Some(blog) = self
+                        .blog_collection
+                        .find_one(doc! {"_id": *id}, None)
+                        .guarded(&self.breaker)
+                        .await
+                        .map_err(MongoQueryError)?
+                    {
+                        let mut session = self.start_transaction().await?;
+                        self.record_revision(&mut session, &blog, blog.author.as_deref())
+                            .await?;
+                        session
+                            .commit_transaction()
+                            .guarded(&self.breaker)
+                            .await
+                            .map_err(MongoQueryError)?;
+                        repaired += 1;
+                    }
+                }
+            } else if found
+                .iter()
+                .zip(1..)
+                .any(|(number, expected)| *number != expected)
+            {
+                issues.push(ConsistencyIssue {
+                    kind: "revisionGap",
+                    id: id.to_hex(),
+                    detail: format!("revisions are numbered {:?}", found),
+                });
+                if repair {
+                    // Ascending order only ever moves a revision into a slot
+                    // that is already free, so the unique index holds throughout.
+                    for (number, expected) in found.iter().zip(1..) {
+                        if *number != expected {
+                            self.revision_collection
+                                .update_one(
+                                    doc! {"postId": *id, "number": *number},
+                                    doc! {"$set": {"number": expected}},
+                                    None,
+                                )
+                                .guarded(&self.breaker)
+                                .await
+                                .map_err(MongoQueryError)?;
+                        }
+                    }
+                    repaired += 1;
+                }
+            }
+        }
+
+        // Category paths should match the category as registered today.
+        let mut registered: HashMap<String, Vec<String>> = HashMap::new();
+        let mut cursor = self
+            .category_collection
+            .find(None, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(category) = cursor.next().await {
+            let category = category.map_err(MongoQueryError)?;
+            registered.insert(category.name, category.path);
+        }
+        for (id, category, path) in category_paths {
+            // Mirrors `category_path`.
+            let expected = match registered.get(&category) {
+                _ if category.is_empty() => Vec::new(),
+                Some(registered) => registered.to_owned(),
+                None => vec![category.to_owned()],
+            };
+            if path == expected {
+                continue;
+            }
+
+            issues.push(ConsistencyIssue {
+                kind: "categoryPathDrift",
+                id: id.to_hex(),
+                detail: format!("categoryPath is {:?}, expected {:?}", path, expected),
+            });
+            if repair {
+                self.blog_collection
+                    .update_one(
+                        doc! {"_id": id},
+                        doc! {"$set": {"categoryPath": expected}},
+                        None,
+                    )
+                    .guarded(&self.breaker)
+                    .await
+                    .map_err(MongoQueryError)?;
+                repaired += 1;
+            }
+        }
+
+        // Stored counters against a fresh count.
+        let mut stored: HashMap<(StatKind, String), i64> = HashMap::new();
+        let mut cursor = self
+            .tag_stats_collection
+            .find(None, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        while let Some(stat) = cursor.next().await {
+            let stat = stat.map_err(MongoQueryError)?;
+            if stat.count != 0 {
+                stored.insert((stat.kind, stat.name), stat.count);
+            }
+        }
+        let mut drifted = false;
+        for stat in self.count_tag_stats().await? {
+            let key = (stat.kind, stat.name);
+            let recorded = stored.remove(&key).unwrap_or(0);
+            if recorded != stat.count {
+                drifted = true;
+                issues.push(ConsistencyIssue {
+                    kind: "counterDrift",
+                    id: format!("{}:{}", key.0.as_str(), key.1),
+                    detail: format!("stored {}, actual {}", recorded, stat.count),
+                });
+            }
+        }
+        for ((kind, name), recorded) in stored {
+            drifted = true;
+            issues.push(ConsistencyIssue {
+                kind: "counterDrift",
+                id: format!("{}:{}", kind.as_str(), name),
+                detail: format!("stored {}, actual 0", recorded),
+            });
+        }
+        if drifted && repair {
+            self.rebuild_tag_stats().await?;
+            repaired += 1;
+        }
+
+        Ok(ConsistencyReportResponse {
+            status: "success",
+            dryRun: !repair,
+            checks: vec![
+                "orphanedDraft",
+                "orphanedRevision",
+                "missingRevisions",
+                "revisionGap",
+                "categoryPathDrift",
+                "counterDrift",
+            ],
+            results: issues.len(),
+            repaired,
+            issues,
+        })
+    }
+
+    /// Tag and category counts computed from the published posts.
+    async fn count_tag_stats(&self) -> Result<Vec<TagStatModel>> {
+        let pipelines = [
+            (
+                StatKind::Tag,
+                vec![
+                    doc! {"$match": {"published": true}},
+                    doc! {"$unwind": "$tags"},
+                    doc! {"$group": {"_id": "$tags", "count": {"$sum": 1}}},
+                ],
+            ),
+            (
+                StatKind::Category,
+                vec![
+                    doc! {"$match": {"published": true, "category": {"$nin": [null, ""]}}},
+                    doc! {"$group": {"_id": "$category", "count": {"$sum": 1}}},
+                ],
+            ),
+        ];
+
+        let mut stats: Vec<TagStatModel> = Vec::new();
+        for (kind, pipeline) in pipelines {
+            let mut cursor = self
+                .collection
+                .aggregate(pipeline, None)
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+
+            while let Some(doc) = cursor.next().await {
+                let doc = doc.map_err(MongoQueryError)?;
+                stats.push(TagStatModel {
+                    kind,
+                    name: doc.get_str("_id")?.to_owned(),
+                    count: i64::from(doc.get_i32("count")?),
+                });
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Hands all of `author`'s posts over to [`DELETED_AUTHOR`].
+    pub async fn reattribute_author(&self, author: &str) -> Result<u64> {
+        let result = self
+            .blog_collection
+            .update_many(
+                doc! {"author": author},
+                doc! {"$set": {"author": DELETED_AUTHOR, "updatedAt": Utc::now()}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let options = UpdateOptions::builder()
+            .array_filters(vec![doc! {"contributor.userId": author}])
+            .build();
+        let credited = self
+            .blog_collection
+            .update_many(
+                doc! {"contributors.userId": author},
+                doc! {"$set": {
+                    "contributors.$[contributor].userId": DELETED_AUTHOR,
+                    "updatedAt": Utc::now(),
+                }},
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        self.revision_collection
+            .update_many(
+                doc! {"editedBy": author},
+                doc! {"$set": {"editedBy": DELETED_AUTHOR}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.comment_collection
+            .update_many(
+                doc! {"author": author},
+                doc! {"$set": {"author": DELETED_AUTHOR, "name": DELETED_AUTHOR}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.comment_collection
+            .update_many(
+                doc! {"mentions.userId": author},
+                doc! {"$pull": {"mentions": {"userId": author}}},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        // The counts on each comment stay; they never said who reacted.
+        self.reaction_collection
+            .delete_many(doc! {"userId": author}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.block_collection
+            .delete_many(
+                doc! {"$or": [{"blocker": author}, {"blocked": author}]},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.bookmark_collection
+            .delete_many(doc! {"userId": author}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        self.clear_history(author).await?;
+        self.follow_collection
+            .delete_many(
+                doc! {"$or": [
+                    {"userId": author},
+                    {"kind": FollowKind::Author.as_str(), "target": author},
+                ]},
+                None,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(result.modified_count + credited.modified_count)
+    }
+
+    async fn has_revisions(&self, session: &mut ClientSession, post_id: &ObjectId) -> Result<bool> {
+        let revision = self
+            .revision_collection
+            .find_one_with_session(doc! {"postId": post_id}, None, session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(revision.is_some())
+    }
+
+    /// Appends the post's current state to its history.
+    async fn record_revision(
+        &self,
+        session: &mut ClientSession,
+        blog: &BlogModel,
+        edited_by: Option<&str>,
+    ) -> Result<()> {
+        let options = FindOneOptions::builder().sort(doc! {"number": -1}).build();
+        let latest = self
+            .revision_collection
+            .find_one_with_session(doc! {"postId": blog.id}, options, session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .map_or(0, |revision| revision.number);
+
+        let revision = RevisionModel {
+            id: ObjectId::new(),
+            postId: blog.id,
+            number: latest + 1,
+            title: blog.title.to_owned(),
+            summary: blog.summary.to_owned(),
+            content: blog.content.to_owned(),
+            category: blog.category.to_owned(),
+            tags: blog.tags.to_owned().unwrap_or_default(),
+            published: blog.published.unwrap_or(false),
+            editedBy: edited_by.map(str::to_owned),
+            createdAt: bson::DateTime::now().to_chrono(),
+        };
+
+        self.revision_collection
+            .insert_one_with_session(&revision, None, session)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(())
+    }
+
+    /// Commits the transaction of a request that may be run in the sandbox,
+    /// or rolls it back if it is.
+    async fn finish_transaction(&self, mut session: ClientSession) -> Result<()> {
+        let finished = if sandbox::active() {
+            session.abort_transaction().await
+        } else {
+            session.commit_transaction().await
+        };
+        finished.map_err(MongoQueryError)
+    }
+
+    async fn start_transaction(&self) -> Result<ClientSession> {
+        let mut session = self
+            .client
+            .start_session(None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        session
+            .start_transaction(None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+        Ok(session)
+    }
+
+    /// Moves the counters from what `before` contributed to what `after` does.
+    async fn adjust_tag_stats(
+        &self,
+        session: &mut ClientSession,
+        before: Option<&BlogModel>,
+        after: Option<&BlogModel>,
+    ) -> Result<()> {
+        let removed = before.map(stat_keys).unwrap_or_default();
+        let added = after.map(stat_keys).unwrap_or_default();
+
+        let changes = removed
+            .iter()
+            .filter(|key| !added.contains(key))
+            .map(|key| (key, -1_i64))
+            .chain(
+                added
+                    .iter()
+                    .filter(|key| !removed.contains(key))
+                    .map(|key| (key, 1_i64)),
+            );
+
+        let options = UpdateOptions::builder().upsert(true).build();
+        for ((kind, name), delta) in changes {
+            self.tag_stats_collection
+                .update_one_with_session(
+                    doc! {"kind": kind.as_str(), "name": name.as_str()},
+                    doc! {"$inc": {"count": delta}},
+                    options.clone(),
+                    session,
+                )
+                .guarded(&self.breaker)
+                .await
+                .map_err(MongoQueryError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Breadcrumb for a post filed under `category`. Categories that were never
+    /// registered are treated as top-level.
+    async fn category_path(&self, category: &str) -> Result<Vec<String>> {
+        if category.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let registered = self
+            .category_collection
+            .find_one(doc! {"name": category}, None)
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        Ok(match registered {
+            Some(registered) => registered.path,
+            None => vec![category.to_owned()],
+        })
+    }
+
+    /// Ids of posts whose content fingerprint is within `MAX_DISTANCE` bits of `hash`.
+    async fn find_duplicates(&self, hash: u64) -> Result<Vec<String>> {
+        let options = FindOptions::builder()
+            .projection(doc! {"fingerprint": 1})
+            .build();
+        let mut cursor = self
+            .collection
+            .find(
+                doc! {"fingerprintBands": {"$in": fingerprint::bands(hash)}},
+                options,
+            )
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?;
+
+        let mut duplicates = Vec::new();
+        while let Some(doc) = cursor.next().await {
+            let doc = doc.map_err(MongoQueryError)?;
+            let Ok(other) = doc.get_i64("fingerprint") else {
+                continue;
+            };
+            if fingerprint::distance(hash, other as u64) <= fingerprint::MAX_DISTANCE {
+                duplicates.push(doc.get_object_id("_id")?.to_hex());
+            }
+        }
+        Ok(duplicates)
+    }
+
+    /// Fails unless `viewer` may read the post, for reads that don't load it.
+    async fn check_readable(
+        &self,
+        oid: &ObjectId,
+        viewer: &Viewer<'_>,
+        read: ReadFrom,
+    ) -> Result<()> {
+        let blog = self
+            .blog_collection
+            .find_one(doc! {"_id": oid}, self.find_one_options(read))
+            .guarded(&self.breaker)
+            .await
+            .map_err(MongoQueryError)?
+            .ok_or_else(|| NotFoundError(oid.to_hex()))?;
+        check_can_read(&blog, viewer)
+    }
+
+    fn find_one_options(&self, read: ReadFrom) -> FindOneOptions {
+        FindOneOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build()
+    }
+
+    fn doc_to_blog(&self, blog: &BlogModel, comments: &CommentDefaults) -> Result<BlogResponse> {
+        let blog_response = BlogResponse {
+            id: blog.id.to_hex(),
+            title: blog.title.to_owned(),
+            titleVariants: blog.titleVariants.to_owned().unwrap_or_default(),
+            titleVariant: None,
+            summary: blog.summary.to_owned(),
+            content: blog.content.to_owned(),
+            contentHtml: None,
+            toc: blog.toc.to_owned().unwrap_or_default(),
+            category: blog.category.to_owned().unwrap(),
+            categoryPath: blog.categoryPath.to_owned().unwrap_or_default(),
+            published: blog.published.unwrap(),
+            author: blog.author.to_owned(),
+            contributors: blog
+                .contributors
+                .iter()
+                .flatten()
+                .map(|contributor| ContributorResponse {
+                    userId: contributor.userId.to_owned(),
+                    role: contributor.role,
+                })
+                .collect(),
+            tags: blog.tags.to_owned().unwrap_or_default(),
+            visibility: blog.visibility,
+            commentSettings: blog.commentSettings.to_owned().unwrap_or_default(),
+            comments: comment_status(blog, comments),
+            syndication: blog.syndication.to_owned().unwrap_or_default(),
+            canonicalUrl: blog
+                .syndication
+                .as_ref()
+                .and_then(SyndicationModel::canonical)
+                .map(str::to_owned),
+            embeds: None,
+            bookmarked: None,
+            dueAt: blog.dueAt.map(|at| at.to_chrono()),
+            assignee: blog.assignee.to_owned(),
+            customFields: blog
+                .customFields
+                .iter()
+                .flatten()
+                .map(|(key, value)| (key.to_owned(), custom_fields::to_json(value)))
+                .collect(),
+            createdAt: blog.createdAt,
+            updatedAt: blog.updatedAt,
+        };
 
         Ok(blog_response)
     }
 
+    fn doc_to_tag_stat(&self, stat: &TagStatModel) -> TagStatResponse {
+        TagStatResponse {
+            kind: stat.kind,
+            name: stat.name.to_owned(),
+            count: stat.count,
+        }
+    }
+
+    fn doc_to_revision(&self, revision: &RevisionModel) -> RevisionResponse {
+        RevisionResponse {
+            number: revision.number,
+            title: revision.title.to_owned(),
+            published: revision.published,
+            editedBy: revision.editedBy.to_owned(),
+            createdAt: revision.createdAt,
+        }
+    }
+
+    fn doc_to_draft(&self, draft: &DraftModel) -> DraftResponse {
+        DraftResponse {
+            postId: draft.postId.to_hex(),
+            title: draft.title.to_owned(),
+            summary: draft.summary.to_owned(),
+            content: draft.content.to_owned(),
+            version: draft.version,
+            createdAt: draft.createdAt,
+            updatedAt: draft.updatedAt,
+        }
+    }
+
+    fn doc_to_redirect(&self, redirect: &RedirectModel) -> RedirectResponse {
+        RedirectResponse {
+            id: redirect.id.to_hex(),
+            from: redirect.from.to_owned(),
+            to: redirect.to.to_owned(),
+            status: redirect.status,
+            createdAt: redirect.createdAt,
+        }
+    }
+
+    fn doc_to_contact(&self, contact: &ContactMessageModel) -> ContactMessageResponse {
+        ContactMessageResponse {
+            id: contact.id.to_hex(),
+            name: contact.name.to_owned(),
+            email: contact.email.to_owned(),
+            subject: contact.subject.to_owned(),
+            message: contact.message.to_owned(),
+            status: contact.status,
+            createdAt: contact.createdAt,
+        }
+    }
+
+    fn doc_to_link_check(&self, check: &LinkCheckModel) -> LinkCheckResponse {
+        LinkCheckResponse {
+            postId: check.postId.to_hex(),
+            url: check.url.to_owned(),
+            broken: check.broken,
+            statusCode: check.statusCode,
+            error: check.error.to_owned(),
+            failures: check.failures,
+            brokenSince: check.brokenSince.map(|since| since.to_chrono()),
+            checkedAt: check.checkedAt,
+        }
+    }
+
+    fn doc_to_page(&self, page: &PageModel) -> PageResponse {
+        PageResponse {
+            id: page.id.to_string(),
+            slug: page.slug.to_owned(),
+            title: page.title.to_owned(),
+            content: page.content.to_owned(),
+            status: page.status,
+            navOrder: page.navOrder,
+            navLabel: page.navLabel.to_owned(),
+            author: page.author.to_owned(),
+            createdAt: page.createdAt,
+            updatedAt: page.updatedAt,
+        }
+    }
+
+    fn page_response(&self, page: &PageModel) -> SinglePageResponse {
+        SinglePageResponse {
+            status: "success",
+            data: PageData {
+                page: self.doc_to_page(page),
+            },
+        }
+    }
+
+    fn doc_to_settings(&self, settings: &SettingsModel) -> SettingsResponse {
+        SettingsResponse {
+            siteTitle: settings.siteTitle.to_owned(),
+            description: settings.description.to_owned(),
+            postsPerPage: settings.postsPerPage,
+            defaultCategory: settings.defaultCategory.to_owned(),
+            socialLinks: settings.socialLinks.to_owned(),
+            theme: settings.theme.to_owned(),
+            comments: settings.comments.to_owned(),
+            suggestions: settings.suggestions,
+            updatedAt: Some(settings.updatedAt),
+        }
+    }
+
+    fn doc_to_category(&self, category: &CategoryModel) -> CategoryResponse {
+        CategoryResponse {
+            id: category.id.to_hex(),
+            name: category.name.to_owned(),
+            parent: category.parent.to_owned(),
+            path: category.path.to_owned(),
+            createdAt: category.createdAt,
+        }
+    }
+
     fn create_blog_document(
         &self,
         body: &CreateBlogSchema,
         published: bool,
         category: String,
+        category_path: Vec<String>,
+        author: &str,
     ) -> Result<bson::Document> {
         let serialized_data = bson::to_bson(body).map_err(MongoSerializeBsonError)?;
         let document = serialized_data.as_document().unwrap();
@@ -211,13 +6948,924 @@ impl DB {
         let datetime = Utc::now();
 
         let mut doc_with_dates = doc! {
+            "_id": ObjectId::new(),
             "createdAt": datetime,
             "updatedAt": datetime,
             "published": published,
-            "category": category
+            "category": category,
+            "categoryPath": category_path,
+            "author": author
         };
         doc_with_dates.extend(document.clone());
+        doc_with_dates.insert(
+            "titleVariants",
+            normalize_title_variants(body.titleVariants.as_deref().unwrap_or_default())?,
+        );
+        doc_with_dates.insert(
+            "tags",
+            normalize_tags(body.tags.as_deref().unwrap_or_default()),
+        );
+        doc_with_dates.insert("toc", bson::to_bson(&toc::extract(&body.content))?);
+        doc_with_dates.insert("quality", bson::to_bson(&quality::measure(&body.content))?);
+        doc_with_dates.insert(
+            "contributors",
+            bson::to_bson(&contributors_without(
+                body.contributors.as_deref().unwrap_or_default(),
+                Some(author),
+            ))?,
+        );
+        if let Some(password) = body
+            .password
+            .as_deref()
+            .filter(|password| !password.is_empty())
+        {
+            doc_with_dates.insert("accessHash", access::hash_password(password));
+        }
+        if let Some(due_at) = body.dueAt {
+            doc_with_dates.insert("dueAt", due_at);
+        }
 
         Ok(doc_with_dates)
     }
 }
+
+/// A sync checkpoint: Unix milliseconds as handed out by `fetch_changes`, or
+/// an RFC 3339 timestamp for a first sync from a known date. A page cut short
+/// among posts changed in the same millisecond also carries the `_id` of the
+/// last one sent, as `<millis>_<id>`.
+fn parse_checkpoint(since: &str) -> Result<(DateTime<Utc>, Option<ObjectId>)> {
+    let invalid = || {
+        ValidationError(format!(
+            "since must be an RFC 3339 timestamp or a checkpoint, not {}",
+            since
+        ))
+    };
+    if let Some((millis, id)) = since.split_once('_') {
+        let at = millis
+            .parse::<i64>()
+            .ok()
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single());
+        let id = ObjectId::from_str(id).ok();
+        return match (at, id) {
+            (Some(at), Some(id)) => Ok((at, Some(id))),
+            _ => Err(invalid()),
+        };
+    }
+
+    let parsed = match since.parse::<i64>() {
+        Ok(millis) => Utc.timestamp_millis_opt(millis).single(),
+        Err(_) => DateTime::parse_from_rfc3339(since)
+            .ok()
+            .map(|since| since.with_timezone(&Utc)),
+    };
+    parsed.map(|at| (at, None)).ok_or_else(invalid)
+}
+
+/// English, ignoring case but not accents.
+fn title_collation() -> Collation {
+    Collation::builder()
+        .locale("en")
+        .strength(CollationStrength::Secondary)
+        .build()
+}
+
+/// The stored fingerprint of a post's content; both are cleared when the
+/// content is too short to fingerprint.
+fn fingerprint_fields(fingerprint: Option<u64>) -> Document {
+    match fingerprint {
+        Some(hash) => doc! {
+            "fingerprint": hash as i64,
+            "fingerprintBands": fingerprint::bands(hash),
+        },
+        None => doc! {"fingerprint": Bson::Null, "fingerprintBands": Bson::Null},
+    }
+}
+
+/// Lowercased, trimmed and deduplicated, keeping the order they were given in.
+/// The fields the feed ranks posts by.
+fn feed_projection() -> Document {
+    doc! {"_id": 1, "author": 1, "tags": 1, "category": 1, "createdAt": 1}
+}
+
+fn doc_to_candidate(doc: &Document) -> Result<feed::Candidate> {
+    Ok(feed::Candidate {
+        id: doc.get_object_id("_id")?,
+        author: doc.get_str("author").ok().map(str::to_owned),
+        tags: doc
+            .get_array("tags")
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|tag| tag.as_str().map(str::to_owned))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        category: doc.get_str("category").ok().map(str::to_owned),
+        created_at: doc.get_datetime("createdAt")?.to_chrono(),
+    })
+}
+
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = normalize_tag(tag);
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+/// The counters a post counts towards; drafts count towards none.
+fn stat_keys(blog: &BlogModel) -> Vec<(StatKind, String)> {
+    if !blog.published.unwrap_or(false) {
+        return Vec::new();
+    }
+
+    let mut keys: Vec<(StatKind, String)> = blog
+        .tags
+        .iter()
+        .flatten()
+        .map(|tag| (StatKind::Tag, tag.to_owned()))
+        .collect();
+    if let Some(category) = blog
+        .category
+        .as_ref()
+        .filter(|category| !category.is_empty())
+    {
+        keys.push((StatKind::Category, category.to_owned()));
+    }
+    keys
+}
+
+/// One entry per user, dropping the owner, who needs no contributor role.
+fn contributors_without(
+    contributors: &[ContributorModel],
+    owner: Option<&str>,
+) -> Vec<ContributorModel> {
+    let mut unique: Vec<ContributorModel> = Vec::new();
+    for contributor in contributors {
+        if Some(contributor.userId.as_str()) != owner
+            && !unique.iter().any(|seen| seen.userId == contributor.userId)
+        {
+            unique.push(contributor.clone());
+        }
+    }
+    unique
+}
+
+fn is_owner(blog: &BlogModel, actor: &Actor) -> bool {
+    actor.admin || blog.author.as_deref() == Some(actor.id)
+}
+
+/// Owners can always edit; other contributors only while the post is a draft.
+fn check_can_edit(blog: &BlogModel, actor: &Actor) -> Result<()> {
+    if is_owner(blog, actor) || (!blog.published.unwrap_or(false) && is_contributor(blog, actor.id))
+    {
+        return Ok(());
+    }
+
+    Err(NotPermittedError(
+        "only the owner can edit a published post; contributors can edit drafts".to_string(),
+    ))
+}
+
+fn is_contributor(blog: &BlogModel, user_id: &str) -> bool {
+    blog.contributors
+        .iter()
+        .flatten()
+        .any(|contributor| contributor.userId == user_id)
+}
+
+/// Drafts are only there for whoever could edit them; anyone else gets a 404,
+/// and reads them through a preview link. Protected posts need a password or
+/// access token, unless the reader could edit them anyway. Asking without
+/// either is a 401, with a wrong one a 403.
+fn check_can_read(blog: &BlogModel, viewer: &Viewer) -> Result<()> {
+    let editor = viewer
+        .actor
+        .as_ref()
+        .is_some_and(|actor| is_owner(blog, actor) || is_contributor(blog, actor.id));
+    if editor {
+        return Ok(());
+    }
+    if !blog.published.unwrap_or(false) {
+        return Err(NotFoundError(blog.id.to_hex()));
+    }
+    if blog.visibility != Visibility::Protected {
+        return Ok(());
+    }
+
+    let Some(access) = viewer.access else {
+        return Err(AccessRequiredError(blog.id.to_hex()));
+    };
+    let opened = if access.starts_with(access::TOKEN_PREFIX) {
+        blog.accessTokens
+            .iter()
+            .flatten()
+            .any(|token| credentials::matches(&token.hash, access))
+    } else {
+        blog.accessHash
+            .as_deref()
+            .is_some_and(|stored| access::verify_password(stored, access))
+    };
+
+    if opened {
+        Ok(())
+    } else {
+        Err(AccessDeniedError(blog.id.to_hex()))
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct StatsFacets {
+    daily: Vec<StatsRow>,
+    totals: Vec<StatsRow>,
+    #[serde(default)]
+    countries: Vec<CountryStatsRow>,
+}
+
+#[derive(Deserialize, Default)]
+struct MeteringFacets {
+    totals: Vec<MeteringRow>,
+    keys: Vec<KeyMeteringRow>,
+    routes: Vec<RouteMeteringRow>,
+}
+
+#[derive(Deserialize)]
+struct MeteringRow {
+    calls: i64,
+    bytes: i64,
+}
+
+#[derive(Deserialize)]
+struct KeyMeteringRow {
+    #[serde(rename = "_id")]
+    key_id: Option<String>,
+    calls: i64,
+    bytes: i64,
+}
+
+#[derive(Deserialize)]
+struct RouteMeteringRow {
+    #[serde(rename = "_id")]
+    route: RouteMeteringKey,
+    calls: i64,
+    bytes: i64,
+}
+
+#[derive(Deserialize)]
+struct RouteMeteringKey {
+    method: String,
+    route: String,
+    status: i32,
+}
+
+#[derive(Deserialize)]
+struct ReplyGroup {
+    #[serde(rename = "_id")]
+    parent: ObjectId,
+    count: u64,
+    replies: Vec<CommentModel>,
+}
+
+#[derive(Deserialize)]
+struct ReplyCountRow {
+    #[serde(rename = "_id")]
+    parent: ObjectId,
+    count: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct PostCountRow {
+    total: u64,
+    published: u64,
+    unlisted: u64,
+    protected: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct ErrorRateRow {
+    calls: i64,
+    #[serde(rename = "serverErrors")]
+    server_errors: i64,
+}
+
+#[derive(Deserialize)]
+struct UserMeteringRow {
+    #[serde(rename = "_id")]
+    user_id: String,
+    calls: i64,
+    #[serde(rename = "failedCalls")]
+    failed_calls: i64,
+    bytes: i64,
+}
+
+#[derive(Deserialize)]
+struct VariantRow {
+    #[serde(rename = "_id")]
+    variant: Option<i32>,
+    impressions: i64,
+    clicks: i64,
+}
+
+#[derive(Deserialize)]
+struct StatsRow {
+    #[serde(rename = "_id")]
+    day: Option<String>,
+    views: i64,
+    uniques: i64,
+}
+
+#[derive(Deserialize)]
+struct CountryStatsRow {
+    #[serde(rename = "_id")]
+    country: String,
+    views: i64,
+    uniques: i64,
+}
+
+/// The `[from, to)` window a metering request covers.
+fn metering_window(opts: &MeteringOptions) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let now = Utc::now();
+    let from = opts.from.unwrap_or_else(|| {
+        Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .unwrap()
+    });
+    let to = opts.to.unwrap_or(now);
+    if from >= to {
+        return Err(ValidationError("from must be before to".to_string()));
+    }
+    Ok((from, to))
+}
+
+/// Slugs end up in URLs, so they are kept to lowercase letters, digits and dashes.
+fn check_slug(slug: &str) -> Result<()> {
+    let valid = !slug.is_empty()
+        && slug.len() <= MAX_SLUG_LEN
+        && !slug.starts_with('-')
+        && !slug.ends_with('-')
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError(format!(
+            "slug {:?} must be 1 to {} lowercase letters, digits or inner dashes",
+            slug, MAX_SLUG_LEN
+        )))
+    }
+}
+
+/// `/`-prefixed, without query string or trailing slash, so `/about/` and
+/// `/about?ref=x` find the same redirect.
+fn normalize_path(path: &str) -> Option<String> {
+    let path = path.trim();
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    if !path.starts_with('/') || path.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let trimmed = path.trim_end_matches('/');
+    Some(if trimmed.is_empty() { "/" } else { trimmed }.to_owned())
+}
+
+fn parse_archive_line(line: &[u8]) -> std::result::Result<(String, Document), String> {
+    let mut entry: serde_json::Value =
+        serde_json::from_slice(line).map_err(|e| format!("invalid JSON: {}", e))?;
+
+    let name = match entry.get("collection").and_then(|name| name.as_str()) {
+        Some(name) if !name.is_empty() && !name.starts_with("system.") => name.to_owned(),
+        _ => return Err("missing or invalid collection name".to_string()),
+    };
+    let document = Bson::try_from(entry["document"].take())
+        .map_err(|e| format!("invalid extended JSON: {}", e))?;
+
+    match document {
+        Bson::Document(document) => Ok((name, document)),
+        _ => Err("document is not an object".to_string()),
+    }
+}
+
+/// A cheap sanity check; the address is only ever used as a reply-to.
+fn looks_like_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !email.contains(char::is_whitespace)
+                && email.len() <= 254
+        }
+        None => false,
+    }
+}
+
+fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    duplicate_key(e).is_some()
+}
+
+/// Duplicate-key failures become a conflict naming the clashing field; anything
+/// else is a plain query error.
+fn write_error(e: mongodb::error::Error) -> MyError {
+    match duplicate_key(&e) {
+        Some(conflict) => MongoDuplicateError(conflict),
+        None => MongoQueryError(e),
+    }
+}
+
+/// Trimmed and deduplicated, rejecting more than [`MAX_TITLE_VARIANTS`].
+fn normalize_title_variants(variants: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for variant in variants {
+        let variant = variant.trim().to_owned();
+        if !variant.is_empty() && !normalized.contains(&variant) {
+            normalized.push(variant);
+        }
+    }
+    if normalized.len() > MAX_TITLE_VARIANTS {
+        return Err(ValidationError(format!(
+            "a post can test at most {} alternative titles",
+            MAX_TITLE_VARIANTS
+        )));
+    }
+    Ok(normalized)
+}
+
+/// Swaps in the title this visitor is bucketed into. The bucket only depends
+/// on the visitor and post ids, so a returning reader keeps seeing the same one.
+fn serve_title_variant(blog: &mut BlogResponse, visitor: Option<&str>) {
+    let Some(visitor) = visitor.filter(|visitor| !visitor.is_empty()) else {
+        return;
+    };
+    if blog.titleVariants.is_empty() {
+        return;
+    }
+
+    // FNV-1a, which unlike the std hasher is stable across releases.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in visitor.bytes().chain([b':']).chain(blog.id.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    let variant = (hash % (blog.titleVariants.len() as u64 + 1)) as usize;
+    if variant > 0 {
+        blog.title = blog.titleVariants[variant - 1].to_owned();
+    }
+    blog.titleVariant = Some(variant);
+}
+
+/// Drops what lists leave out of a post.
+fn summarize(blog: BlogResponse) -> BlogSummaryResponse {
+    BlogSummaryResponse {
+        id: blog.id,
+        title: blog.title,
+        titleVariants: blog.titleVariants,
+        titleVariant: blog.titleVariant,
+        summary: blog.summary,
+        toc: blog.toc,
+        category: blog.category,
+        categoryPath: blog.categoryPath,
+        published: blog.published,
+        author: blog.author,
+        contributors: blog.contributors,
+        tags: blog.tags,
+        visibility: blog.visibility,
+        commentSettings: blog.commentSettings,
+        comments: blog.comments,
+        syndication: blog.syndication,
+        canonicalUrl: blog.canonicalUrl,
+        bookmarked: blog.bookmarked,
+        dueAt: blog.dueAt,
+        assignee: blog.assignee,
+        customFields: blog.customFields,
+        createdAt: blog.createdAt,
+        updatedAt: blog.updatedAt,
+    }
+}
+
+fn doc_to_migration_job(job: &MigrationJob) -> MigrationJobResponse {
+    MigrationJobResponse {
+        name: job.name.to_owned(),
+        collection: job.collection.to_owned(),
+        state: job.state,
+        checkpoint: job
+            .checkpoint
+            .clone()
+            .map(|checkpoint| checkpoint.into_relaxed_extjson()),
+        scanned: job.scanned,
+        migrated: job.migrated,
+        error: job.error.to_owned(),
+        startedAt: job.startedAt,
+        updatedAt: job.updatedAt,
+        finishedAt: job.finishedAt.map(|at| at.to_chrono()),
+    }
+}
+
+fn doc_to_template(template: &TemplateModel) -> TemplateResponse {
+    TemplateResponse {
+        id: template.id.to_hex(),
+        name: template.name.to_owned(),
+        titlePattern: template.titlePattern.to_owned(),
+        summary: template.summary.to_owned(),
+        content: template.content.to_owned(),
+        tags: template.tags.to_owned(),
+        category: template.category.to_owned(),
+        createdBy: template.createdBy.to_owned(),
+        createdAt: template.createdAt,
+    }
+}
+
+/// `pattern` with `{date}`, `{year}`, `{month}` and `{week}` (ISO) filled in for `now`.
+fn fill_title_pattern(pattern: &str, now: DateTime<Utc>) -> String {
+    pattern
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{year}", &now.format("%Y").to_string())
+        .replace("{month}", &now.format("%m").to_string())
+        .replace("{week}", &now.format("%V").to_string())
+}
+
+fn doc_to_field_definition(definition: &FieldDefinitionModel) -> FieldDefinitionResponse {
+    FieldDefinitionResponse {
+        key: definition.key.to_owned(),
+        label: definition.label.to_owned(),
+        kind: definition.kind,
+        required: definition.required,
+        options: definition.options.to_owned(),
+        min: definition.min,
+        max: definition.max,
+        maxLength: definition.maxLength,
+        updatedBy: definition.updatedBy.to_owned(),
+        updatedAt: definition.updatedAt,
+    }
+}
+
+/// Trimmed and deduplicated, in the order given.
+fn normalize_field_options(options: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for option in options {
+        let option = option.trim().to_owned();
+        if !option.is_empty() && !normalized.contains(&option) {
+            normalized.push(option);
+        }
+    }
+    normalized
+}
+
+fn doc_to_saved_search(search: &SavedSearchModel) -> SavedSearchResponse {
+    SavedSearchResponse {
+        id: search.id.to_hex(),
+        name: search.name.to_owned(),
+        status: search.status,
+        tag: search.tag.to_owned(),
+        author: search.author.to_owned(),
+        category: search.category.to_owned(),
+        includeDescendants: search.includeDescendants,
+        from: search.from.map(|at| at.to_chrono()),
+        to: search.to.map(|at| at.to_chrono()),
+        createdAt: search.createdAt,
+    }
+}
+
+/// The query for posts matching `criteria`; published public ones only
+/// unless `include_hidden`.
+fn post_filter(criteria: &PostCriteria, include_hidden: bool) -> Result<Document> {
+    if let (Some(from), Some(to)) = (criteria.from, criteria.to) {
+        if from >= to {
+            return Err(ValidationError("from must be before to".to_string()));
+        }
+    }
+
+    let mut filter = Filter::All;
+    if !include_hidden {
+        // Posts from before visibility existed have none and are public.
+        filter = filter.and(Filter::not_in(
+            PostField::Visibility,
+            HIDDEN_VISIBILITIES.iter().copied(),
+        ));
+        filter = filter.and(Filter::eq(PostField::Published, true));
+    }
+    if let Some(status) = criteria.status {
+        filter = filter.and(Filter::eq(
+            PostField::Published,
+            status == PostStatus::Published,
+        ));
+    }
+    if let Some(tag) = criteria.tag {
+        filter = filter.and(Filter::eq(PostField::Tags, normalize_tag(tag)));
+    }
+    if let Some(author) = criteria.author {
+        filter = filter.and(
+            Filter::eq(PostField::Author, author).or(Filter::eq(PostField::ContributorId, author)),
+        );
+    }
+    if let Some(category) = criteria.category {
+        // Every post under a category has it somewhere on its path.
+        let field = match criteria.include_descendants {
+            true => PostField::CategoryPath,
+            false => PostField::Category,
+        };
+        filter = filter.and(Filter::eq(field, category));
+    }
+    filter = filter.and(Filter::range(
+        PostField::CreatedAt,
+        criteria.from,
+        criteria.to,
+    ));
+
+    Ok(filter.to_document())
+}
+
+fn doc_to_ip_rule(rule: &IpRuleModel) -> IpRuleResponse {
+    IpRuleResponse {
+        id: rule.id.to_hex(),
+        kind: rule.kind,
+        cidr: rule.cidr.to_owned(),
+        reason: rule.reason.to_owned(),
+        automatic: rule.automatic,
+        createdBy: rule.createdBy.to_owned(),
+        expiresAt: rule.expiresAt.map(|at| at.to_chrono()),
+        createdAt: rule.createdAt,
+    }
+}
+
+fn doc_to_block(block: &BlockModel) -> BlockResponse {
+    BlockResponse {
+        userId: block.blocked.to_owned(),
+        createdAt: block.createdAt,
+    }
+}
+
+fn doc_to_bookmark(
+    bookmark: &BookmarkModel,
+    blog: Option<BlogSummaryResponse>,
+) -> BookmarkResponse {
+    BookmarkResponse {
+        postId: bookmark.postId.to_hex(),
+        folder: bookmark.folder.to_owned(),
+        labels: bookmark.labels.to_owned(),
+        createdAt: bookmark.createdAt,
+        blog,
+    }
+}
+
+fn doc_to_follow(follow: &FollowModel) -> FollowResponse {
+    FollowResponse {
+        kind: follow.kind,
+        target: follow.target.to_owned(),
+        createdAt: follow.createdAt,
+    }
+}
+
+fn doc_to_comment(comment: &CommentModel) -> CommentResponse {
+    CommentResponse {
+        id: comment.id.to_hex(),
+        postId: comment.postId.to_hex(),
+        parentId: comment.parentId.map(|parent| parent.to_hex()),
+        depth: comment.path.matches('/').count(),
+        author: comment.author.to_owned(),
+        name: comment.name.to_owned(),
+        body: comment.body.to_owned(),
+        createdAt: comment.createdAt,
+        editedAt: comment.editedAt.map(|at| at.to_chrono()),
+        mentions: comment
+            .mentions
+            .iter()
+            .map(|mention| MentionResponse {
+                userId: mention.userId.to_owned(),
+                name: mention.name.to_owned(),
+            })
+            .collect(),
+        reactions: comment
+            .reactions
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(emoji, count)| (emoji.to_owned(), *count))
+            .collect(),
+        replyCount: 0,
+        replies: Vec::new(),
+    }
+}
+
+fn check_comment_body(body: &str) -> Result<&str> {
+    let text = body.trim();
+    if text.is_empty() || text.chars().count() > MAX_COMMENT_LEN {
+        return Err(ValidationError(format!(
+            "comment must be 1 to {} characters",
+            MAX_COMMENT_LEN
+        )));
+    }
+    Ok(text)
+}
+
+/// How long after posting a comment its author may still edit it, from
+/// `COMMENT_EDIT_WINDOW_SECS` (default 15 minutes).
+fn comment_edit_window() -> chrono::Duration {
+    let secs = match std::env::var("COMMENT_EDIT_WINDOW_SECS") {
+        Ok(value) => value.parse::<i64>().unwrap_or_else(|_| {
+            panic!(
+                "COMMENT_EDIT_WINDOW_SECS must be a whole number, got '{}'",
+                value
+            )
+        }),
+        Err(_) => 15 * 60,
+    };
+    chrono::Duration::seconds(secs)
+}
+
+fn inline_replies(replies: Option<i64>) -> Result<i64> {
+    let replies = replies.unwrap_or(DEFAULT_INLINE_REPLIES);
+    if !(0..=MAX_INLINE_REPLIES).contains(&replies) {
+        return Err(ValidationError(format!(
+            "replies must be between 0 and {}",
+            MAX_INLINE_REPLIES
+        )));
+    }
+    Ok(replies)
+}
+
+/// The comment settings in effect on `blog`: its own, then the site's.
+fn comment_status(blog: &BlogModel, defaults: &CommentDefaults) -> CommentStatusResponse {
+    let own = blog.commentSettings.to_owned().unwrap_or_default();
+    let enabled = own.enabled.unwrap_or(defaults.enabled);
+    let locks_at = own
+        .lockAfterDays
+        .or(defaults.lockAfterDays)
+        .filter(|days| *days > 0)
+        .map(|days| blog.createdAt + chrono::Duration::days(days));
+
+    CommentStatusResponse {
+        enabled,
+        membersOnly: own.membersOnly.unwrap_or(defaults.membersOnly),
+        open: enabled
+            && blog.published.unwrap_or(false)
+            && locks_at.is_none_or(|locks_at| Utc::now() < locks_at),
+        locksAt: locks_at,
+    }
+}
+
+/// Narrows a comment filter to leave out comments by `hidden` authors.
+fn hide_authors(filter: &mut Document, hidden: &[String]) {
+    if !hidden.is_empty() {
+        filter.insert("author", doc! {"$nin": hidden.to_vec()});
+    }
+}
+
+fn check_reactions(reactions: &[String]) -> Result<()> {
+    if reactions.len() > MAX_REACTION_KINDS {
+        return Err(ValidationError(format!(
+            "at most {} reactions are allowed",
+            MAX_REACTION_KINDS
+        )));
+    }
+    let mut seen = HashSet::new();
+    for emoji in reactions {
+        // Reactions are field names in each comment's counts, where `.` and
+        // `$` would be read as paths and operators.
+        if emoji.is_empty()
+            || emoji.chars().count() > MAX_REACTION_LEN
+            || emoji
+                .chars()
+                .any(|c| c.is_whitespace() || c == '.' || c == '$')
+        {
+            return Err(ValidationError(format!(
+                "reaction {:?} must be 1 to {} characters without spaces, `.` or `$`",
+                emoji, MAX_REACTION_LEN
+            )));
+        }
+        if !seen.insert(emoji) {
+            return Err(ValidationError(format!(
+                "reaction {:?} is listed twice",
+                emoji
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn check_lock_after_days(days: Option<i64>) -> Result<()> {
+    match days {
+        Some(days) if !(0..=MAX_COMMENT_LOCK_DAYS).contains(&days) => {
+            Err(ValidationError(format!(
+                "lockAfterDays must be between 0 and {}",
+                MAX_COMMENT_LOCK_DAYS
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Syndication URLs end up in `Link` headers and feeds, so they must be plain
+/// absolute http(s) URLs.
+fn check_syndication(syndication: &SyndicationModel) -> Result<()> {
+    for (field, url) in [
+        ("sourceUrl", &syndication.sourceUrl),
+        ("canonicalUrl", &syndication.canonicalUrl),
+    ] {
+        let Some(url) = url else {
+            continue;
+        };
+        let host = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+            .and_then(|rest| rest.split(['/', '?', '#']).next())
+            .unwrap_or_default();
+        if host.is_empty()
+            || url.len() > MAX_SYNDICATION_URL_LEN
+            || url
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | '"'))
+        {
+            return Err(ValidationError(format!(
+                "{} must be an absolute http(s) url of at most {} characters",
+                field, MAX_SYNDICATION_URL_LEN
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn doc_to_access_token(token: &AccessTokenModel) -> AccessTokenResponse {
+    AccessTokenResponse {
+        id: token.id.to_hex(),
+        label: token.label.to_owned(),
+        createdAt: token.createdAt,
+    }
+}
+
+fn stats_response(stats: PostStatsResponse) -> SinglePostStatsResponse {
+    SinglePostStatsResponse {
+        status: "success",
+        data: PostStatsData { stats },
+    }
+}
+
+fn analytics_event(
+    event: &AnalyticsEventSchema,
+    now: DateTime<Utc>,
+    geo: Option<&GeoLocation>,
+) -> std::result::Result<AnalyticsEventModel, String> {
+    let post_id = ObjectId::from_str(&event.postId)
+        .map_err(|_| format!("invalid postId: {}", event.postId))?;
+
+    let visitor_id = event.visitorId.trim();
+    if visitor_id.is_empty() || visitor_id.len() > MAX_VISITOR_ID_LEN {
+        return Err(format!(
+            "visitorId must be 1 to {} characters",
+            MAX_VISITOR_ID_LEN
+        ));
+    }
+
+    if event
+        .referrer
+        .as_ref()
+        .is_some_and(|referrer| referrer.len() > MAX_REFERRER_LEN)
+    {
+        return Err(format!(
+            "referrer must be at most {} characters",
+            MAX_REFERRER_LEN
+        ));
+    }
+
+    let variant = match (event.kind, event.variant) {
+        (AnalyticsKind::Impression | AnalyticsKind::Click, Some(variant)) if variant >= 0 => {
+            Some(variant)
+        }
+        (AnalyticsKind::Impression | AnalyticsKind::Click, _) => {
+            return Err("impression and click events need a variant".to_string())
+        }
+        (_, Some(_)) => return Err("only impression and click events carry a variant".to_string()),
+        (_, None) => None,
+    };
+
+    let depth = match (event.kind, event.depth) {
+        (AnalyticsKind::Read, Some(depth)) if (0.0..=1.0).contains(&depth) => Some(depth),
+        (AnalyticsKind::Read, _) => return Err("read events need a depth from 0 to 1".to_string()),
+        (_, Some(_)) => return Err("only read events carry a depth".to_string()),
+        (_, None) => None,
+    };
+
+    let occurred_at = event.occurredAt.unwrap_or(now);
+    if occurred_at > now + chrono::Duration::minutes(ANALYTICS_CLOCK_SKEW_MINUTES) {
+        return Err("occurredAt is in the future".to_string());
+    }
+
+    Ok(AnalyticsEventModel {
+        id: ObjectId::new(),
+        postId: post_id,
+        kind: event.kind,
+        visitorId: visitor_id.to_owned(),
+        referrer: event.referrer.to_owned(),
+        depth,
+        variant,
+        geo: geo.cloned(),
+        occurredAt: occurred_at,
+    })
+}
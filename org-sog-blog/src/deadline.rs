@@ -0,0 +1,93 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+
+use crate::{error::MyError, notify, AppState};
+
+/// Reminds whoever is to get a draft out that its deadline is near. Every
+/// `DEADLINE_CHECK_MINUTES` (default 15, 0 turns reminders off) drafts due
+/// within `DEADLINE_REMINDER_HOURS` (default 24) get one reminder per
+/// deadline, sent through the notification service. Every instance checks,
+/// but each reminder is claimed first so only one sends it.
+pub struct DeadlineReminders {
+    interval: Option<Duration>,
+    lead: chrono::Duration,
+}
+
+impl DeadlineReminders {
+    pub fn init() -> Self {
+        let env_or = |name: &str, default: i64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<i64>()
+                .ok()
+                .filter(|value| *value >= 0)
+                .unwrap_or_else(|| panic!("{} must be a whole number.", name)),
+            Err(_) => default,
+        };
+        let minutes = env_or("DEADLINE_CHECK_MINUTES", 15);
+
+        Self {
+            interval: (minutes > 0).then(|| Duration::from_secs(minutes as u64 * 60)),
+            lead: chrono::Duration::hours(env_or("DEADLINE_REMINDER_HOURS", 24)),
+        }
+    }
+}
+
+pub fn spawn_scheduler(app_state: Arc<AppState>) {
+    let Some(period) = app_state.deadlines.interval else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match run(&app_state).await {
+                Ok(0) => {}
+                Ok(sent) => println!("⏰ Sent {} deadline reminders", sent),
+                Err(e) => println!("⚠️ Deadline reminders failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Sends the reminders that are due. Returns how many were sent.
+pub async fn run(app_state: &AppState) -> Result<usize, MyError> {
+    let now = Utc::now();
+    let due = app_state
+        .db
+        .due_reminders(now + app_state.deadlines.lead)
+        .await?;
+
+    let mut sent = 0;
+    for post in due {
+        let Some(due_at) = post.dueAt else {
+            continue;
+        };
+        let Some(user_id) = post.assignee.as_ref().or(post.author.as_ref()) else {
+            continue;
+        };
+        if !app_state
+            .db
+            .claim_deadline_reminder(post.id, due_at)
+            .await?
+        {
+            continue;
+        }
+
+        let due_at = due_at.to_chrono();
+        let event = serde_json::json!({
+            "userIds": [user_id],
+            "postId": post.id.to_hex(),
+            "title": post.title,
+            "dueAt": due_at.to_rfc3339(),
+            "overdue": due_at < now,
+        });
+        app_state
+            .notify
+            .publish(notify::DEADLINE_APPROACHING, event)
+            .await;
+        sent += 1;
+    }
+    Ok(sent)
+}
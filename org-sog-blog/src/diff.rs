@@ -0,0 +1,52 @@
+use serde_json::json;
+use similar::{ChangeTag, TextDiff};
+
+use crate::{
+    model::RevisionModel,
+    response::{DiffChunk, DiffOp, FieldChange},
+    schema::Granularity,
+};
+
+/// Diffs two versions of a post body, merging consecutive changes of the same
+/// kind so a rewritten paragraph comes back as one delete and one insert.
+pub fn diff_text(old: &str, new: &str, granularity: Granularity) -> Vec<DiffChunk> {
+    let diff = match granularity {
+        Granularity::Line => TextDiff::from_lines(old, new),
+        Granularity::Word => TextDiff::from_words(old, new),
+    };
+
+    let mut chunks: Vec<DiffChunk> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let op = match change.tag() {
+            ChangeTag::Equal => DiffOp::Equal,
+            ChangeTag::Delete => DiffOp::Delete,
+            ChangeTag::Insert => DiffOp::Insert,
+        };
+
+        match chunks.last_mut() {
+            Some(last) if last.op == op => last.value.push_str(change.value()),
+            _ => chunks.push(DiffChunk {
+                op,
+                value: change.value().to_owned(),
+            }),
+        }
+    }
+    chunks
+}
+
+/// Metadata fields that differ between two revisions; the body is diffed separately.
+pub fn changed_fields(old: &RevisionModel, new: &RevisionModel) -> Vec<FieldChange> {
+    let fields = [
+        ("title", json!(old.title), json!(new.title)),
+        ("summary", json!(old.summary), json!(new.summary)),
+        ("category", json!(old.category), json!(new.category)),
+        ("tags", json!(old.tags), json!(new.tags)),
+        ("published", json!(old.published), json!(new.published)),
+    ];
+
+    fields
+        .into_iter()
+        .filter(|(_, from, to)| from != to)
+        .map(|(field, from, to)| FieldChange { field, from, to })
+        .collect()
+}
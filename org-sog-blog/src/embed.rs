@@ -0,0 +1,260 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use org_sog_core::{
+    circuit_breaker::BreakerMetrics,
+    http::{HttpClient, HttpClientConfig, HttpError, Method},
+};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::response::EmbedResponse;
+
+/// A post expands no more than this many links, so one post can't fan out
+/// into a flood of provider requests.
+const MAX_EMBEDS_PER_POST: usize = 20;
+const MAX_CACHED: usize = 10_000;
+/// Links a provider wouldn't expand are tried again after this long.
+const FAILURE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Frames are only kept when they load one of these players.
+const FRAME_SOURCES: &[&str] = &[
+    "https://www.youtube.com/embed/",
+    "https://www.youtube-nocookie.com/embed/",
+    "https://player.vimeo.com/video/",
+];
+
+/// The oEmbed providers links are expanded for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    YouTube,
+    Vimeo,
+    Twitter,
+}
+
+impl Provider {
+    fn detect(url: &str) -> Option<Self> {
+        let host = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?
+            .split(['/', '?', '#'])
+            .next()?
+            .to_ascii_lowercase();
+        match host.as_str() {
+            "youtube.com" | "www.youtube.com" | "m.youtube.com" | "youtu.be" => {
+                Some(Provider::YouTube)
+            }
+            "vimeo.com" | "www.vimeo.com" => Some(Provider::Vimeo),
+            "twitter.com" | "www.twitter.com" | "mobile.twitter.com" | "x.com" => {
+                Some(Provider::Twitter)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provider::YouTube => "youtube",
+            Provider::Vimeo => "vimeo",
+            Provider::Twitter => "twitter",
+        }
+    }
+
+    fn endpoint(&self) -> &'static str {
+        match self {
+            Provider::YouTube => "https://www.youtube.com/oembed",
+            Provider::Vimeo => "https://vimeo.com/api/oembed.json",
+            Provider::Twitter => "https://publish.twitter.com/oembed",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OEmbed {
+    html: Option<String>,
+    title: Option<String>,
+}
+
+struct Cached {
+    fetched_at: Instant,
+    embed: Option<EmbedResponse>,
+}
+
+/// Expands links to YouTube, Vimeo and Twitter that stand on a line of their
+/// own into the providers' embed HTML.
+///
+/// Provider responses are cached per instance for `EMBED_CACHE_SECS`
+/// (default a day). Their HTML is cleaned against an allowlist before it is
+/// handed out: frames only for the known players, and no scripts, so
+/// clients that want Twitter's widget must load it themselves.
+pub struct EmbedResolver {
+    youtube: HttpClient,
+    vimeo: HttpClient,
+    twitter: HttpClient,
+    ttl: Duration,
+    cache: RwLock<HashMap<String, Cached>>,
+    cleaner: ammonia::Builder<'static>,
+}
+
+impl EmbedResolver {
+    pub fn init() -> Self {
+        let ttl_secs = std::env::var("EMBED_CACHE_SECS")
+            .ok()
+            .map(|value| {
+                value
+                    .parse::<u64>()
+                    .expect("EMBED_CACHE_SECS must be a number.")
+            })
+            .unwrap_or(24 * 60 * 60);
+
+        Self {
+            youtube: HttpClient::new("youtube", HttpClientConfig::default()),
+            vimeo: HttpClient::new("vimeo", HttpClientConfig::default()),
+            twitter: HttpClient::new("twitter", HttpClientConfig::default()),
+            ttl: Duration::from_secs(ttl_secs),
+            cache: RwLock::new(HashMap::new()),
+            cleaner: cleaner(),
+        }
+    }
+
+    /// The embeds for the links in `content`, in the order they appear.
+    /// Links a provider couldn't expand are left out.
+    pub async fn expand(&self, content: &str) -> Vec<EmbedResponse> {
+        let mut embeds = Vec::new();
+        for (url, provider) in embeddable_links(content) {
+            if let Some(embed) = self.resolve(url, provider).await {
+                embeds.push(embed);
+            }
+        }
+        embeds
+    }
+
+    async fn resolve(&self, url: &str, provider: Provider) -> Option<EmbedResponse> {
+        if let Some(cached) = self.cache.read().await.get(url) {
+            let ttl = match cached.embed {
+                Some(_) => self.ttl,
+                None => FAILURE_TTL,
+            };
+            if cached.fetched_at.elapsed() < ttl {
+                return cached.embed.clone();
+            }
+        }
+
+        let embed = match self.fetch(url, provider).await {
+            Ok(embed) => embed,
+            Err(e) => {
+                println!("⚠️ {} oEmbed for {} failed: {}", provider.as_str(), url, e);
+                None
+            }
+        };
+
+        let mut cache = self.cache.write().await;
+        if cache.len() >= MAX_CACHED {
+            let ttl = self.ttl;
+            cache.retain(|_, cached| cached.fetched_at.elapsed() < ttl);
+            if cache.len() >= MAX_CACHED {
+                cache.clear();
+            }
+        }
+        cache.insert(
+            url.to_owned(),
+            Cached {
+                fetched_at: Instant::now(),
+                embed: embed.clone(),
+            },
+        );
+        embed
+    }
+
+    async fn fetch(
+        &self,
+        url: &str,
+        provider: Provider,
+    ) -> Result<Option<EmbedResponse>, HttpError> {
+        let client = match provider {
+            Provider::YouTube => &self.youtube,
+            Provider::Vimeo => &self.vimeo,
+            Provider::Twitter => &self.twitter,
+        };
+        let oembed: OEmbed = client
+            .json(Method::GET, provider.endpoint(), |request| {
+                request.query(&[("url", url), ("format", "json"), ("dnt", "true")])
+            })
+            .await?;
+
+        let html = oembed
+            .html
+            .map(|html| self.cleaner.clean(&html).to_string())
+            .filter(|html| !html.trim().is_empty());
+        Ok(html.map(|html| EmbedResponse {
+            url: url.to_owned(),
+            provider: provider.as_str(),
+            title: oembed.title,
+            html,
+        }))
+    }
+
+    pub fn breaker_metrics(&self) -> Vec<BreakerMetrics> {
+        vec![
+            self.youtube.metrics(),
+            self.vimeo.metrics(),
+            self.twitter.metrics(),
+        ]
+    }
+}
+
+/// Links to a known provider that make up a whole line, each once.
+fn embeddable_links(content: &str) -> Vec<(&str, Provider)> {
+    let mut seen = HashSet::new();
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.contains(char::is_whitespace))
+        .filter_map(|line| Some((line, Provider::detect(line)?)))
+        .filter(|(url, _)| seen.insert(*url))
+        .take(MAX_EMBEDS_PER_POST)
+        .collect()
+}
+
+/// What embed HTML may keep: the players' frames and the markup of a quoted
+/// tweet, with links and frames over https only.
+fn cleaner() -> ammonia::Builder<'static> {
+    let mut builder = ammonia::Builder::empty();
+    builder
+        .tags(HashSet::from(["iframe", "blockquote", "p", "a", "br"]))
+        .clean_content_tags(HashSet::from(["script", "style"]))
+        .tag_attributes(HashMap::from([
+            (
+                "iframe",
+                HashSet::from([
+                    "src",
+                    "width",
+                    "height",
+                    "title",
+                    "allow",
+                    "allowfullscreen",
+                    "frameborder",
+                    "referrerpolicy",
+                ]),
+            ),
+            ("a", HashSet::from(["href"])),
+            ("blockquote", HashSet::from(["lang", "dir"])),
+            ("p", HashSet::from(["lang", "dir"])),
+        ]))
+        .allowed_classes(HashMap::from([(
+            "blockquote",
+            HashSet::from(["twitter-tweet"]),
+        )]))
+        .url_schemes(HashSet::from(["https"]))
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .attribute_filter(|element, attribute, value| match (element, attribute) {
+            ("iframe", "src") => FRAME_SOURCES
+                .iter()
+                .any(|source| value.starts_with(source))
+                .then(|| value.into()),
+            _ => Some(value.into()),
+        });
+    builder
+}
@@ -0,0 +1,53 @@
+use axum::{
+    body::{boxed, Full},
+    http::{
+        header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderValue, Method, Request,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use org_sog_core::encoding::Encoding;
+
+/// Re-encodes JSON from read endpoints in a format `Accept` asks for, when
+/// that format's feature is enabled; otherwise the JSON passes through.
+pub async fn negotiate<B>(request: Request<B>, next: Next<B>) -> Response {
+    let encoding = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::from_accept)
+        .filter(|_| request.method() == Method::GET);
+
+    let response = next.run(request).await;
+    let Some(encoding) = encoding else {
+        return response;
+    };
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(_) => return parts.status.into_response(),
+    };
+    let encoded = serde_json::from_slice(&bytes)
+        .ok()
+        .and_then(|body| encoding.encode(&body).ok());
+    let Some(encoded) = encoded else {
+        return Response::from_parts(parts, boxed(Full::from(bytes)));
+    };
+
+    parts.headers.remove(CONTENT_LENGTH);
+    parts.headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(encoding.content_type()),
+    );
+    Response::from_parts(parts, boxed(Full::from(encoded)))
+}
@@ -0,0 +1,279 @@
+//! Snapshots of a deployment's configuration, for promoting it from one
+//! deployment to another, e.g. staging to production.
+//!
+//! `org-sog-blog env export [FILE]` writes the snapshot of the deployment
+//! the environment points at; `env diff FILE` lists how that deployment
+//! differs from a snapshot and `env apply FILE` brings it in line. Things
+//! only this deployment has are reported but left in place.
+
+use std::{collections::HashSet, fmt};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    db::DB,
+    error::MyError::{self, StorageError, ValidationError},
+    schema::{CreateCategorySchema, CreateTemplateSchema, FieldDefinitionSchema, SettingsSchema},
+};
+
+type Result<T> = std::result::Result<T, MyError>;
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+/// Recorded as who last changed what `env apply` writes.
+const APPLIED_BY: &str = "env-apply";
+
+/// What a deployment is configured with, in the same order wherever it was
+/// taken, so two snapshots can be compared as text too.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Snapshot {
+    pub version: u32,
+    pub exportedAt: DateTime<Utc>,
+    /// Includes the site's feature toggles, such as `suggestions`.
+    pub settings: SettingsSchema,
+    /// Parents before their children.
+    pub categories: Vec<CreateCategorySchema>,
+    pub templates: Vec<CreateTemplateSchema>,
+    pub fields: Vec<FieldSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FieldSnapshot {
+    pub key: String,
+    #[serde(flatten)]
+    pub definition: FieldDefinitionSchema,
+}
+
+/// One way a deployment differs from a snapshot.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Change {
+    /// In the snapshot, missing here.
+    Added { kind: &'static str, name: String },
+    /// Only here.
+    Removed { kind: &'static str, name: String },
+    /// In both, with these fields set differently.
+    Changed {
+        kind: &'static str,
+        name: String,
+        fields: Vec<String>,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { kind, name } => write!(f, "+ {} {}", kind, name),
+            Change::Removed { kind, name } => write!(f, "- {} {}", kind, name),
+            Change::Changed { kind, name, fields } if name.is_empty() => {
+                write!(f, "~ {}: {}", kind, fields.join(", "))
+            }
+            Change::Changed { kind, name, fields } => {
+                write!(f, "~ {} {}: {}", kind, name, fields.join(", "))
+            }
+        }
+    }
+}
+
+/// How `here` would have to change to match `snapshot`.
+pub fn diff(here: &Snapshot, snapshot: &Snapshot) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    let fields = changed_fields(&here.settings, &snapshot.settings);
+    if !fields.is_empty() {
+        changes.push(Change::Changed {
+            kind: "settings",
+            name: String::new(),
+            fields,
+        });
+    }
+    compare(
+        "category",
+        &here.categories,
+        &snapshot.categories,
+        |category| &category.name,
+        &mut changes,
+    );
+    compare(
+        "template",
+        &here.templates,
+        &snapshot.templates,
+        |template| &template.name,
+        &mut changes,
+    );
+    compare(
+        "field",
+        &here.fields,
+        &snapshot.fields,
+        |field| &field.key,
+        &mut changes,
+    );
+
+    changes
+}
+
+/// Brings the deployment `db` points at in line with `snapshot`, through
+/// the same checks the admin API applies, and returns what it changed.
+///
+/// Categories that sit under a different parent here are reported but not
+/// moved, as posts keep their category's path.
+pub async fn apply(db: &DB, snapshot: &Snapshot) -> Result<Vec<Change>> {
+    let changes = diff(&db.snapshot_environment().await?, snapshot);
+    let pending: HashSet<(&str, &str)> = changes
+        .iter()
+        .filter_map(|change| match change {
+            Change::Added { kind, name } => Some((*kind, name.as_str())),
+            Change::Changed { kind, name, .. } => Some((*kind, name.as_str())),
+            Change::Removed { .. } => None,
+        })
+        .collect();
+
+    for category in &snapshot.categories {
+        if changes.contains(&Change::Added {
+            kind: "category",
+            name: category.name.to_owned(),
+        }) {
+            db.create_category(category).await?;
+        } else if pending.contains(&("category", category.name.as_str())) {
+            println!(
+                "⚠️ category {} has another parent here; move it by hand",
+                category.name
+            );
+        }
+    }
+    for template in &snapshot.templates {
+        if pending.contains(&("template", template.name.as_str())) {
+            db.put_template(template, APPLIED_BY).await?;
+        }
+    }
+    for field in &snapshot.fields {
+        if pending.contains(&("field", field.key.as_str())) {
+            db.put_field_definition(&field.key, &field.definition, APPLIED_BY)
+                .await?;
+        }
+    }
+    // Last, as they may name a category created above.
+    if pending.contains(&("settings", "")) {
+        db.update_settings(&snapshot.settings, APPLIED_BY).await?;
+    }
+
+    Ok(changes)
+}
+
+pub async fn run_cli(db: &DB, args: &[String]) -> Result<()> {
+    match args {
+        [command] if command == "export" => {
+            let snapshot = db.snapshot_environment().await?;
+            println!("{}", to_json(&snapshot)?);
+        }
+        [command, path] if command == "export" => {
+            let snapshot = db.snapshot_environment().await?;
+            std::fs::write(path, to_json(&snapshot)?)
+                .map_err(|e| StorageError(format!("could not write {}: {}", path, e)))?;
+            println!("📦 Wrote snapshot to {}", path);
+        }
+        [command, path] if command == "diff" => {
+            let snapshot = read_snapshot(path)?;
+            let changes = diff(&db.snapshot_environment().await?, &snapshot);
+            if changes.is_empty() {
+                println!("✅ Matches {}", path);
+            }
+            for change in changes {
+                println!("{}", change);
+            }
+        }
+        [command, path] if command == "apply" => {
+            let snapshot = read_snapshot(path)?;
+            let changes = apply(db, &snapshot).await?;
+            if changes.is_empty() {
+                println!("✅ Already matches {}", path);
+            }
+            for change in changes {
+                match change {
+                    Change::Removed { .. } => println!("{} (left in place)", change),
+                    change => println!("{}", change),
+                }
+            }
+        }
+        _ => println!("usage: env export [FILE] | diff FILE | apply FILE"),
+    }
+    Ok(())
+}
+
+fn read_snapshot(path: &str) -> Result<Snapshot> {
+    let contents =
+        std::fs::read(path).map_err(|e| StorageError(format!("could not read {}: {}", path, e)))?;
+    let snapshot: Snapshot = serde_json::from_slice(&contents)
+        .map_err(|e| ValidationError(format!("{} is not a snapshot: {}", path, e)))?;
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(ValidationError(format!(
+            "{} is a version {} snapshot; this build reads version {}",
+            path, snapshot.version, SNAPSHOT_VERSION
+        )));
+    }
+    Ok(snapshot)
+}
+
+fn to_json(snapshot: &Snapshot) -> Result<String> {
+    serde_json::to_string_pretty(snapshot)
+        .map_err(|e| StorageError(format!("could not encode snapshot: {}", e)))
+}
+
+/// Adds a change for every item of `there` missing from or different in
+/// `here`, and every item only `here` has, matching them up by `name`.
+fn compare<T: Serialize>(
+    kind: &'static str,
+    here: &[T],
+    there: &[T],
+    name: impl Fn(&T) -> &String,
+    changes: &mut Vec<Change>,
+) {
+    for wanted in there {
+        match here.iter().find(|item| name(item) == name(wanted)) {
+            None => changes.push(Change::Added {
+                kind,
+                name: name(wanted).to_owned(),
+            }),
+            Some(current) => {
+                let fields = changed_fields(current, wanted);
+                if !fields.is_empty() {
+                    changes.push(Change::Changed {
+                        kind,
+                        name: name(wanted).to_owned(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    for current in here {
+        if !there.iter().any(|wanted| name(wanted) == name(current)) {
+            changes.push(Change::Removed {
+                kind,
+                name: name(current).to_owned(),
+            });
+        }
+    }
+}
+
+/// Names of the top-level fields set differently in `a` and `b`.
+fn changed_fields<T: Serialize>(a: &T, b: &T) -> Vec<String> {
+    let (a, b) = (fields_of(a), fields_of(b));
+    let mut changed: Vec<String> = a
+        .keys()
+        .chain(b.keys())
+        .filter(|key| a.get(*key) != b.get(*key))
+        .cloned()
+        .collect();
+    changed.sort();
+    changed.dedup();
+    changed
+}
+
+fn fields_of<T: Serialize>(item: &T) -> Map<String, Value> {
+    match serde_json::to_value(item) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
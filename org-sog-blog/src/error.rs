@@ -1,4 +1,5 @@
 use axum::{http::StatusCode, Json};
+use org_sog_core::{db_breaker, i18n};
 use serde::Serialize;
 
 #[derive(thiserror::Error, Debug)]
@@ -6,84 +7,575 @@ pub enum MyError {
     #[error("MongoDB error")]
     MongoError(#[from] mongodb::error::Error),
     #[error("duplicate key error: {0}")]
-    MongoErrorKind(mongodb::error::ErrorKind),
+    MongoErrorKind(Box<mongodb::error::ErrorKind>),
     #[error("duplicate key error: {0}")]
-    MongoDuplicateError(mongodb::error::Error),
+    MongoDuplicateError(org_sog_core::conflict::DuplicateKey),
     #[error("error during mongodb query: {0}")]
     MongoQueryError(mongodb::error::Error),
     #[error("error serializing BSON")]
     MongoSerializeBsonError(#[from] mongodb::bson::ser::Error),
+    #[error("error deserializing BSON")]
+    MongoDeserializeBsonError(#[from] mongodb::bson::de::Error),
     #[error("validation error")]
     MongoDataError(#[from] mongodb::bson::document::ValueAccessError),
     #[error("invalid ID: {0}")]
     InvalidIDError(String),
     #[error("Blog with ID: {0} not found")]
     NotFoundError(String),
+    #[error("unauthorized: {0}")]
+    UnauthorizedError(String),
+    #[error("missing required scope: {0}")]
+    ForbiddenError(String),
+    #[error("auth service unavailable: {0}")]
+    AuthServiceError(String),
+    #[error("revision {0} not found")]
+    RevisionNotFoundError(i64),
+    #[error("no draft saved for blog {0}")]
+    DraftNotFoundError(String),
+    #[error("page {0} not found")]
+    PageNotFoundError(String),
+    #[error("content duplicates existing posts: {0:?}")]
+    DuplicateContentError(Vec<String>),
+    #[error("a page with slug {0} already exists")]
+    SlugTakenError(String),
+    #[error("a redirect from {0} already exists")]
+    RedirectExistsError(String),
+    #[error("backup {0} not found")]
+    BackupNotFoundError(String),
+    #[error("backup storage error: {0}")]
+    StorageError(String),
+    #[error("name not allowed: {0}")]
+    NameNotAllowedError(#[from] org_sog_core::names::NameRejection),
+    #[error("{0}")]
+    InvalidPageError(#[from] org_sog_core::page::InvalidPage),
+    #[error("validation error: {0}")]
+    ValidationError(String),
+    #[error("not permitted: {0}")]
+    NotPermittedError(String),
+    #[error("post {0} is protected")]
+    AccessRequiredError(String),
+    #[error("wrong password or access token for post {0}")]
+    AccessDeniedError(String),
+    #[error("access token {0} not found")]
+    AccessTokenNotFoundError(String),
+    #[error("post {0} is not taking comments")]
+    CommentsClosedError(String),
+    #[error("comment {0} not found")]
+    CommentNotFoundError(String),
+    #[error("requests from this address are blocked")]
+    IpBlockedError,
+    #[error("IP rule {0} not found")]
+    IpRuleNotFoundError(String),
+    #[error("saved search {0} not found")]
+    SavedSearchNotFoundError(String),
+    #[error("custom field {0} not found")]
+    FieldNotFoundError(String),
+    #[error("template {0} not found")]
+    TemplateNotFoundError(String),
+    #[error("migration {0} not found")]
+    MigrationNotFoundError(String),
+    #[error("migration {0} is {1:?}")]
+    MigrationStateError(String, org_sog_core::migration_jobs::JobState),
+    #[error("{0} can't run in the sandbox")]
+    SandboxUnsupportedError(String),
+    #[error("too many requests, retry in {0}s")]
+    TooManyRequestsError(u64),
+    #[error("quota exceeded: at most {1} {0}")]
+    QuotaExceededError(&'static str, u64),
+    #[error("challenge verification failed")]
+    ChallengeFailedError,
+    #[error("error sending mail: {0}")]
+    MailError(#[from] org_sog_core::mail::MailError),
+    #[error("request timed out")]
+    RequestTimeoutError,
+    #[error("request body is too large")]
+    PayloadTooLargeError,
+    #[error("completion failed: {0}")]
+    CompletionError(String),
 }
 
 #[derive(Serialize)]
 struct ErrorResponse {
     status: &'static str,
+    /// Stable across languages, for clients that want their own wording.
+    code: &'static str,
     message: String,
 }
 
 impl Into<(axum::http::StatusCode, Json<serde_json::Value>)> for MyError {
     fn into(self) -> (axum::http::StatusCode, Json<serde_json::Value>) {
+        // Errors that carry more than a message.
+        match self {
+            MyError::MongoDuplicateError(conflict) => {
+                let field = conflict.field.unwrap_or_else(|| "value".to_string());
+                return (
+                    StatusCode::CONFLICT,
+                    Json(serde_json::json!({
+                        "status": "fail",
+                        "code": "duplicate_key",
+                        "message": i18n::message(
+                            "duplicate_key",
+                            "that {0} is already taken",
+                            &[&field],
+                        ),
+                        "conflict_field": field,
+                    })),
+                );
+            }
+            MyError::DuplicateContentError(ids) => {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(serde_json::json!({
+                        "status": "fail",
+                        "code": "duplicate_content",
+                        "message": i18n::message(
+                            "duplicate_content",
+                            "content is near-identical to existing posts",
+                            &[],
+                        ),
+                        "duplicates": ids,
+                    })),
+                )
+            }
+            MyError::NameNotAllowedError(rejection) => {
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(serde_json::json!({
+                        "status": "fail",
+                        "code": rejection.code(),
+                        "message": i18n::message(
+                            rejection.code(),
+                            &rejection.to_string(),
+                            &[&rejection.name()],
+                        ),
+                    })),
+                )
+            }
+            _ => {}
+        }
+
         let (status, error_response) = match self {
             MyError::MongoErrorKind(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error kind: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
+                },
+            ),
+            MyError::InvalidIDError(id) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "invalid_id",
+                    message: i18n::message("invalid_id", "invalid ID: {0}", &[&id]),
+                },
+            ),
+            MyError::NotFoundError(id) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "post_not_found",
+                    message: i18n::message("post_not_found", "Blog with ID: {0} not found", &[&id]),
+                },
+            ),
+            MyError::UnauthorizedError(reason) => (
+                StatusCode::UNAUTHORIZED,
+                ErrorResponse {
+                    status: "fail",
+                    code: "unauthorized",
+                    message: i18n::message("unauthorized", "{0}", &[&reason]),
+                },
+            ),
+            MyError::ForbiddenError(scope) => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "forbidden",
+                    message: i18n::message("forbidden", "missing required scope: {0}", &[&scope]),
+                },
+            ),
+            MyError::AuthServiceError(e) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ErrorResponse {
+                    status: "error",
+                    code: "auth_unavailable",
+                    message: i18n::message(
+                        "auth_unavailable",
+                        "auth service unavailable: {0}",
+                        &[&e],
+                    ),
+                },
+            ),
+            MyError::RevisionNotFoundError(number) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "revision_not_found",
+                    message: i18n::message(
+                        "revision_not_found",
+                        "revision {0} not found",
+                        &[&number],
+                    ),
+                },
+            ),
+            MyError::DraftNotFoundError(id) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "draft_not_found",
+                    message: i18n::message(
+                        "draft_not_found",
+                        "no draft saved for blog {0}",
+                        &[&id],
+                    ),
+                },
+            ),
+            MyError::PageNotFoundError(slug) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "page_not_found",
+                    message: i18n::message("page_not_found", "page {0} not found", &[&slug]),
                 },
             ),
-            MyError::MongoDuplicateError(_) => (
+            // Answered above.
+            MyError::MongoDuplicateError(_)
+            | MyError::DuplicateContentError(_)
+            | MyError::NameNotAllowedError(_) => unreachable!(),
+            MyError::SlugTakenError(slug) => (
                 StatusCode::CONFLICT,
                 ErrorResponse {
                     status: "fail",
-                    message: "Blog with that title already exists".to_string(),
+                    code: "slug_taken",
+                    message: i18n::message(
+                        "slug_taken",
+                        "a page with slug {0} already exists",
+                        &[&slug],
+                    ),
                 },
             ),
-            MyError::InvalidIDError(id) => (
+            MyError::RedirectExistsError(from) => (
+                StatusCode::CONFLICT,
+                ErrorResponse {
+                    status: "fail",
+                    code: "redirect_exists",
+                    message: i18n::message(
+                        "redirect_exists",
+                        "a redirect from {0} already exists",
+                        &[&from],
+                    ),
+                },
+            ),
+            MyError::BackupNotFoundError(key) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "backup_not_found",
+                    message: i18n::message("backup_not_found", "backup {0} not found", &[&key]),
+                },
+            ),
+            MyError::StorageError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorResponse {
+                    status: "error",
+                    code: "storage_error",
+                    message: i18n::message("storage_error", "backup storage error: {0}", &[&e]),
+                },
+            ),
+            MyError::InvalidPageError(e) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorResponse {
+                    status: "fail",
+                    code: "invalid_page",
+                    message: i18n::message("invalid_page", "{0}", &[&e]),
+                },
+            ),
+            MyError::ValidationError(reason) => (
                 StatusCode::BAD_REQUEST,
                 ErrorResponse {
                     status: "fail",
-                    message: format!("invalid ID: {}", id),
+                    code: "validation_failed",
+                    message: i18n::message("validation_failed", "{0}", &[&reason]),
                 },
             ),
-            MyError::NotFoundError(id) => (
+            MyError::NotPermittedError(reason) => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "not_permitted",
+                    message: i18n::message("not_permitted", "{0}", &[&reason]),
+                },
+            ),
+            MyError::AccessRequiredError(id) => (
+                StatusCode::UNAUTHORIZED,
+                ErrorResponse {
+                    status: "fail",
+                    code: "access_required",
+                    message: i18n::message(
+                        "access_required",
+                        "post {0} is protected; send its password or an access token",
+                        &[&id],
+                    ),
+                },
+            ),
+            MyError::AccessDeniedError(id) => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "access_denied",
+                    message: i18n::message(
+                        "access_denied",
+                        "wrong password or access token for post {0}",
+                        &[&id],
+                    ),
+                },
+            ),
+            MyError::AccessTokenNotFoundError(id) => (
                 StatusCode::NOT_FOUND,
                 ErrorResponse {
                     status: "fail",
-                    message: format!("Blog with ID: {} not found", id),
+                    code: "access_token_not_found",
+                    message: i18n::message(
+                        "access_token_not_found",
+                        "access token {0} not found",
+                        &[&id],
+                    ),
                 },
             ),
+            MyError::CommentsClosedError(id) => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "comments_closed",
+                    message: i18n::message(
+                        "comments_closed",
+                        "post {0} is not taking comments",
+                        &[&id],
+                    ),
+                },
+            ),
+            MyError::CommentNotFoundError(id) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "comment_not_found",
+                    message: i18n::message("comment_not_found", "comment {0} not found", &[&id]),
+                },
+            ),
+            MyError::IpBlockedError => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "ip_blocked",
+                    message: i18n::message(
+                        "ip_blocked",
+                        "requests from this address are blocked",
+                        &[],
+                    ),
+                },
+            ),
+            MyError::IpRuleNotFoundError(id) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "ip_rule_not_found",
+                    message: i18n::message("ip_rule_not_found", "IP rule {0} not found", &[&id]),
+                },
+            ),
+            MyError::FieldNotFoundError(key) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "field_not_found",
+                    message: i18n::message(
+                        "field_not_found",
+                        "custom field {0} not found",
+                        &[&key],
+                    ),
+                },
+            ),
+            MyError::TemplateNotFoundError(id) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "template_not_found",
+                    message: i18n::message("template_not_found", "template {0} not found", &[&id]),
+                },
+            ),
+            MyError::MigrationNotFoundError(name) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "migration_not_found",
+                    message: i18n::message(
+                        "migration_not_found",
+                        "migration {0} not found",
+                        &[&name],
+                    ),
+                },
+            ),
+            MyError::MigrationStateError(name, state) => {
+                let state = format!("{:?}", state).to_lowercase();
+                (
+                    StatusCode::CONFLICT,
+                    ErrorResponse {
+                        status: "fail",
+                        code: "migration_state",
+                        message: i18n::message(
+                            "migration_state",
+                            "migration {0} is {1}",
+                            &[&name, &state],
+                        ),
+                    },
+                )
+            }
+            MyError::SandboxUnsupportedError(route) => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "sandbox_unsupported",
+                    message: i18n::message(
+                        "sandbox_unsupported",
+                        "{0} can't run in the sandbox",
+                        &[&route],
+                    ),
+                },
+            ),
+            MyError::SavedSearchNotFoundError(id) => (
+                StatusCode::NOT_FOUND,
+                ErrorResponse {
+                    status: "fail",
+                    code: "saved_search_not_found",
+                    message: i18n::message(
+                        "saved_search_not_found",
+                        "saved search {0} not found",
+                        &[&id],
+                    ),
+                },
+            ),
+            MyError::TooManyRequestsError(retry_after) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorResponse {
+                    status: "fail",
+                    code: "too_many_requests",
+                    message: i18n::message(
+                        "too_many_requests",
+                        "too many requests, retry in {0}s",
+                        &[&retry_after],
+                    ),
+                },
+            ),
+            MyError::QuotaExceededError(quota, limit) => (
+                StatusCode::FORBIDDEN,
+                ErrorResponse {
+                    status: "fail",
+                    code: "quota_exceeded",
+                    message: i18n::message(
+                        "quota_exceeded",
+                        "quota exceeded: at most {1} {0}",
+                        &[&quota, &limit],
+                    ),
+                },
+            ),
+            MyError::ChallengeFailedError => (
+                StatusCode::BAD_REQUEST,
+                ErrorResponse {
+                    status: "fail",
+                    code: "challenge_failed",
+                    message: i18n::message(
+                        "challenge_failed",
+                        "challenge verification failed",
+                        &[],
+                    ),
+                },
+            ),
+            MyError::MailError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorResponse {
+                    status: "error",
+                    code: "mail_error",
+                    message: i18n::message("mail_error", "mail error: {0}", &[&e]),
+                },
+            ),
+            MyError::RequestTimeoutError => (
+                StatusCode::REQUEST_TIMEOUT,
+                ErrorResponse {
+                    status: "fail",
+                    code: "request_timeout",
+                    message: i18n::message("request_timeout", "request timed out", &[]),
+                },
+            ),
+            MyError::PayloadTooLargeError => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ErrorResponse {
+                    status: "fail",
+                    code: "payload_too_large",
+                    message: i18n::message("payload_too_large", "request body is too large", &[]),
+                },
+            ),
+            MyError::CompletionError(e) => (
+                StatusCode::BAD_GATEWAY,
+                ErrorResponse {
+                    status: "error",
+                    code: "upstream_error",
+                    message: i18n::message("upstream_error", "upstream service error: {0}", &[&e]),
+                },
+            ),
+            MyError::MongoError(e) | MyError::MongoQueryError(e)
+                if db_breaker::unavailable(&e).is_some() =>
+            {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    ErrorResponse {
+                        status: "error",
+                        code: "database_unavailable",
+                        message: i18n::message(
+                            "database_unavailable",
+                            "database temporarily unavailable",
+                            &[],
+                        ),
+                    },
+                )
+            }
             MyError::MongoError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
             MyError::MongoQueryError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
             MyError::MongoSerializeBsonError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
+                },
+            ),
+            MyError::MongoDeserializeBsonError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorResponse {
+                    status: "error",
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
             MyError::MongoDataError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ErrorResponse {
                     status: "error",
-                    message: format!("MongoDB error: {}", e),
+                    code: "database_error",
+                    message: i18n::message("database_error", "MongoDB error: {0}", &[&e]),
                 },
             ),
         };
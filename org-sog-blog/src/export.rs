@@ -0,0 +1,217 @@
+use std::{collections::HashSet, io};
+
+use axum::body::{Bytes, StreamBody};
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream, StreamExt};
+use mongodb::Cursor;
+use org_sog_core::{
+    read::ReadFrom,
+    store::{ObjectStore, StoreError},
+};
+use serde_json::json;
+use tokio::{io::AsyncReadExt, sync::mpsc};
+
+use crate::{error::MyError, model::BlogModel, AppState};
+
+type Result<T> = std::result::Result<T, MyError>;
+type Sender = mpsc::Sender<io::Result<Bytes>>;
+
+/// Tar works in blocks of this size: one per header, contents padded to a whole number.
+const BLOCK: usize = 512;
+/// The longest name a ustar header holds without its prefix field.
+const NAME_LEN: usize = 100;
+/// The largest file a ustar header can give the size of, just under 8 GiB.
+const MAX_SIZE: u64 = (1 << 33) - 1;
+/// How much of a media file is read at a time.
+const CHUNK: usize = 64 * 1024;
+/// Pieces written ahead of the client. Together with `CHUNK` and the size of
+/// a post, this bounds what an export holds however large the site is.
+const BUFFERED: usize = 8;
+/// How content links to files in the media store, with or without a host.
+const MEDIA_PATH: &str = "/media/";
+/// Where media files go in the archive.
+const MEDIA_DIR: &str = "media/";
+
+/// Where the files posts link to are kept, from `MEDIA_DIR`.
+pub struct ExportConfig {
+    media: ObjectStore,
+}
+
+impl ExportConfig {
+    pub fn init() -> Self {
+        let dir = std::env::var("MEDIA_DIR").unwrap_or_else(|_| "./media".to_string());
+        Self {
+            media: ObjectStore::new(dir),
+        }
+    }
+}
+
+/// A tar archive of every post as Markdown under `posts/`, and the media
+/// files they link to under `media/`. It is built as the client reads it, a
+/// post or a chunk of a file at a time, so the site's size doesn't matter.
+pub async fn full(
+    app_state: &AppState,
+) -> Result<StreamBody<impl Stream<Item = io::Result<Bytes>>>> {
+    let posts = app_state.db.export_posts(ReadFrom::Replica).await?;
+    let media = app_state.exports.media.clone();
+
+    let (tx, rx) = mpsc::channel(BUFFERED);
+    tokio::spawn(async move {
+        if let Err(e) = write(posts, &media, &tx).await {
+            println!("⚠️ Export stopped: {}", e);
+            // Ending the body with an error tells the client the archive is
+            // cut short, where simply stopping would look like the end.
+            let _ = tx.send(Err(e)).await;
+        }
+    });
+
+    Ok(StreamBody::new(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|piece| (piece, rx))
+    })))
+}
+
+async fn write(mut posts: Cursor<BlogModel>, media: &ObjectStore, tx: &Sender) -> io::Result<()> {
+    let exported_at = Utc::now();
+    // Files linked from several posts go in once.
+    let mut written: HashSet<String> = HashSet::new();
+
+    while let Some(post) = posts.next().await {
+        let post = post.map_err(io::Error::other)?;
+        let markdown = to_markdown(&post);
+        let path = format!("posts/{}.md", post.id.to_hex());
+
+        let mut entry = header(&path, markdown.len() as u64, post.updatedAt).to_vec();
+        entry.extend_from_slice(markdown.as_bytes());
+        entry.extend_from_slice(&padding(markdown.len() as u64));
+        send(tx, entry).await?;
+
+        for key in media_keys(&post.content) {
+            if !written.insert(key.to_owned()) {
+                continue;
+            }
+            let (mut file, size) = match media.open(key).await {
+                Ok(opened) => opened,
+                Err(StoreError::NotFound(_) | StoreError::InvalidKey(_)) => {
+                    println!(
+                        "⚠️ Export left out {}, linked from {} but not stored",
+                        key, post.id
+                    );
+                    continue;
+                }
+                Err(e) => return Err(io::Error::other(e)),
+            };
+            if size > MAX_SIZE {
+                println!("⚠️ Export left out {}, too large for a tar entry", key);
+                continue;
+            }
+
+            send(
+                tx,
+                header(&format!("{}{}", MEDIA_DIR, key), size, exported_at).to_vec(),
+            )
+            .await?;
+            let mut left = size;
+            while left > 0 {
+                let mut chunk = vec![0; left.min(CHUNK as u64) as usize];
+                file.read_exact(&mut chunk).await?;
+                left -= chunk.len() as u64;
+                send(tx, chunk).await?;
+            }
+            send(tx, padding(size)).await?;
+        }
+    }
+
+    // Two empty blocks mark the end of the archive.
+    send(tx, vec![0; 2 * BLOCK]).await
+}
+
+async fn send(tx: &Sender, piece: Vec<u8>) -> io::Result<()> {
+    if piece.is_empty() {
+        return Ok(());
+    }
+    tx.send(Ok(Bytes::from(piece)))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the client went away"))
+}
+
+/// The post under YAML front matter. Values are written as JSON, which YAML
+/// reads as it is, so titles need no escaping of their own.
+fn to_markdown(post: &BlogModel) -> String {
+    let front = [
+        ("id", json!(post.id.to_hex())),
+        ("title", json!(post.title)),
+        ("summary", json!(post.summary)),
+        ("category", json!(post.category)),
+        ("tags", json!(post.tags.to_owned().unwrap_or_default())),
+        ("published", json!(post.published.unwrap_or(false))),
+        ("visibility", json!(post.visibility)),
+        ("author", json!(post.author)),
+        ("createdAt", json!(post.createdAt)),
+        ("updatedAt", json!(post.updatedAt)),
+    ];
+
+    let mut markdown = String::from("---\n");
+    for (key, value) in front {
+        markdown.push_str(&format!("{}: {}\n", key, value));
+    }
+    markdown.push_str("---\n\n");
+    markdown.push_str(&post.content);
+    if !markdown.ends_with('\n') {
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// Keys of the media store files `content` links to, leaving out any too
+/// long for a tar name.
+fn media_keys(content: &str) -> Vec<&str> {
+    content
+        .match_indices(MEDIA_PATH)
+        .filter_map(|(at, _)| {
+            let rest = &content[at + MEDIA_PATH.len()..];
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+                .unwrap_or(rest.len());
+            // A link ending a sentence takes the full stop with it.
+            let key = rest[..end].trim_end_matches('.');
+            let valid =
+                !key.is_empty() && !key.starts_with('.') && MEDIA_DIR.len() + key.len() <= NAME_LEN;
+            valid.then_some(key)
+        })
+        .collect()
+}
+
+/// A ustar header for a regular file. Paths are ours and kept within
+/// `NAME_LEN`, so the prefix field goes unused.
+fn header(path: &str, size: u64, modified: DateTime<Utc>) -> [u8; BLOCK] {
+    let mut block = [0u8; BLOCK];
+    block[..path.len()].copy_from_slice(path.as_bytes());
+    octal(&mut block[100..108], 0o644);
+    octal(&mut block[108..116], 0);
+    octal(&mut block[116..124], 0);
+    octal(&mut block[124..136], size);
+    octal(&mut block[136..148], modified.timestamp().max(0) as u64);
+    block[156] = b'0';
+    block[257..263].copy_from_slice(b"ustar\0");
+    block[263..265].copy_from_slice(b"00");
+
+    // The checksum is taken with its own field as spaces, then written as
+    // six digits, a NUL and one of those spaces.
+    block[148..156].fill(b' ');
+    let checksum: u64 = block.iter().map(|&byte| byte as u64).sum();
+    octal(&mut block[148..155], checksum);
+    block
+}
+
+/// Writes `value` in octal, zero-padded, leaving the field's last byte NUL.
+fn octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let text = format!("{:0width$o}", value, width = digits);
+    field[..digits].copy_from_slice(text.as_bytes());
+    field[digits] = 0;
+}
+
+/// Zeros to fill out the last block of a file of `size` bytes.
+fn padding(size: u64) -> Vec<u8> {
+    vec![0; (BLOCK - (size % BLOCK as u64) as usize) % BLOCK]
+}
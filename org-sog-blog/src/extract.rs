@@ -0,0 +1,270 @@
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Query, State},
+    http::{
+        header::{AsHeaderName, AUTHORIZATION, FORWARDED},
+        request::Parts,
+        HeaderMap, Request, StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use org_sog_core::{
+    client_ip::{ClientIp, ForwardingHeaders},
+    geo::GeoLocation,
+    http::TRACEPARENT_HEADER,
+    plan::Plan,
+    trace,
+};
+
+use crate::{
+    db::{Actor, Viewer},
+    error::MyError::{self, ForbiddenError, UnauthorizedError},
+    metering::Metered,
+    schema::AccessOptions,
+    scope, AppState,
+};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Carries the password or access token for a protected post.
+pub const POST_ACCESS_HEADER: &str = "x-post-access";
+const X_FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The credential a request came with, for passing on to the auth service
+/// when it needs the caller's own permissions.
+#[derive(Debug, Clone)]
+pub enum Credential {
+    ApiKey(String),
+    Bearer(String),
+}
+
+impl Credential {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+        if let Some(key) = header(API_KEY_HEADER) {
+            return Some(Self::ApiKey(key.to_owned()));
+        }
+        header(AUTHORIZATION.as_str())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| Self::Bearer(token.to_owned()))
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub geo: Option<GeoLocation>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Set by `resolve_client_ip`; requests that skipped it fall back to
+        // the peer address.
+        let ip = match parts.extensions.get::<ClientIp>() {
+            Some(ClientIp(ip)) => Some(ip.to_string()),
+            None => parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string()),
+        };
+
+        Ok(Self {
+            ip,
+            geo: parts.extensions.get::<GeoLocation>().cloned(),
+        })
+    }
+}
+
+/// Runs the request as part of the trace named in its `traceparent` header,
+/// or a new one, so the database calls and outbound requests it makes can be
+/// tied back to it.
+pub async fn propagate_trace<B>(request: Request<B>, next: Next<B>) -> Response {
+    let trace_id = request
+        .headers()
+        .get(TRACEPARENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(trace::parse_traceparent)
+        .map(str::to_owned)
+        .unwrap_or_else(trace::new_trace_id);
+
+    trace::scope(trace_id, next.run(request)).await
+}
+
+/// Works out the client address behind any trusted proxies, and where it is
+/// when geo-IP is enabled, and keeps both in the request's extensions, where
+/// [`ClientInfo`] picks them up.
+pub async fn resolve_client_ip<B>(
+    State(app_state): State<Arc<AppState>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if let Some(peer) = peer {
+        let headers = request.headers();
+        let forwarding = ForwardingHeaders {
+            client_ip: app_state
+                .proxies
+                .client_header()
+                .and_then(|name| headers.get(name))
+                .and_then(|value| value.to_str().ok()),
+            forwarded: header_values(headers, FORWARDED),
+            x_forwarded_for: header_values(headers, X_FORWARDED_FOR_HEADER),
+        };
+        let ip = app_state.proxies.resolve(peer, &forwarding);
+        request.extensions_mut().insert(ClientIp(ip));
+        if let Some(location) = app_state.geo.locate(ip) {
+            request.extensions_mut().insert(location);
+        }
+    }
+
+    next.run(request).await
+}
+
+fn header_values(headers: &HeaderMap, name: impl AsHeaderName) -> Vec<&str> {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect()
+}
+
+/// The caller behind a bearer token or `X-API-Key` header.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub sub: String,
+    pub scopes: Vec<String>,
+    /// The plan the caller's limits come from; `None` for service tokens.
+    pub plan: Option<Plan>,
+}
+
+impl AuthUser {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+
+    pub fn require_scope(&self, scope: &str) -> Result<(), MyError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(ForbiddenError(scope.to_owned()))
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for AuthUser {
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        authenticate(parts, state).await.map_err(|e| e.into())
+    }
+}
+
+/// Someone reading posts: the caller, if they signed in, and whatever
+/// password or access token they offer for protected posts.
+///
+/// Reads are open to everyone, so a missing or rejected sign-in just leaves
+/// the reader anonymous.
+#[derive(Debug, Default)]
+pub struct Reader {
+    pub user: Option<AuthUser>,
+    pub access: Option<String>,
+}
+
+impl Reader {
+    pub fn viewer(&self) -> Viewer<'_> {
+        Viewer {
+            actor: self.user.as_ref().map(|user| Actor {
+                id: &user.sub,
+                admin: user.has_scope(scope::BLOG_ADMIN),
+            }),
+            access: self.access.as_deref(),
+        }
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Reader {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let signed_in = header_str(parts, API_KEY_HEADER).is_some()
+            || header_str(parts, AUTHORIZATION.as_str()).is_some();
+        let user = match signed_in {
+            true => authenticate(parts, state).await.ok(),
+            false => None,
+        };
+
+        let access = match header_str(parts, POST_ACCESS_HEADER) {
+            Some(access) => Some(access.to_owned()),
+            None => Query::<AccessOptions>::try_from_uri(&parts.uri)
+                .ok()
+                .and_then(|Query(opts)| opts.access),
+        };
+
+        Ok(Self { user, access })
+    }
+}
+
+async fn authenticate(parts: &Parts, state: &AppState) -> Result<AuthUser, MyError> {
+    let (user, key_id) = identify(parts, state).await?;
+    if let Some(metered) = parts.extensions.get::<Metered>() {
+        metered.caller(&user.sub, key_id.as_deref());
+    }
+    if let Some(plan) = user.plan {
+        state.rate.check(&user.sub, plan).await?;
+    }
+    Ok(user)
+}
+
+/// The caller, and the id of the API key they used, if any.
+async fn identify(parts: &Parts, state: &AppState) -> Result<(AuthUser, Option<String>), MyError> {
+    if let Some(key) = header_str(parts, API_KEY_HEADER) {
+        let grant = state.auth.introspect_api_key(key).await?;
+        let user = AuthUser {
+            sub: grant.sub,
+            scopes: grant.scopes,
+            plan: grant.plan,
+        };
+        return Ok((user, grant.key_id));
+    }
+
+    let token = header_str(parts, AUTHORIZATION.as_str())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| UnauthorizedError("missing bearer token".to_string()))?;
+
+    let claims = state.auth.verify_token(token).await?;
+
+    let user = AuthUser {
+        scopes: scope::parse(claims.scope.as_deref()),
+        sub: claims.sub,
+        plan: claims.plan,
+    };
+    Ok((user, None))
+}
+
+fn header_str<'a>(parts: &'a Parts, name: &str) -> Option<&'a str> {
+    parts
+        .headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+}
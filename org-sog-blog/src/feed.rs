@@ -0,0 +1,211 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::oid::ObjectId;
+use org_sog_core::read::ReadFrom;
+use tokio::sync::RwLock;
+
+use crate::{error::MyError, AppState};
+
+const MAX_CACHED: usize = 10_000;
+/// Only posts from this far back are ranked.
+const CANDIDATE_WINDOW_DAYS: i64 = 60;
+const MAX_CANDIDATES: i64 = 500;
+/// The reads a reader's interests are judged from.
+const MAX_HISTORY: i64 = 200;
+/// A post's recency counts half after this many days.
+const RECENCY_HALF_LIFE_DAYS: f64 = 7.0;
+
+const RECENCY_WEIGHT: f64 = 0.4;
+const TAG_WEIGHT: f64 = 0.3;
+const AUTHOR_WEIGHT: f64 = 0.2;
+const CATEGORY_WEIGHT: f64 = 0.1;
+/// Following outweighs what reading suggests.
+const FOLLOWED_AUTHOR_BONUS: f64 = 0.5;
+const FOLLOWED_TAG_BONUS: f64 = 0.3;
+
+/// What the feed knows about a post when ranking it.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub id: ObjectId,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What the feed knows about its reader.
+#[derive(Debug, Default)]
+pub struct Signals {
+    /// Posts already read, most recent first.
+    pub history: Vec<Candidate>,
+    pub followed_authors: HashSet<String>,
+    pub followed_tags: HashSet<String>,
+}
+
+/// Ranks `candidates` for a reader: recent posts first, lifted by how much
+/// the reader has read of their tags, author and category, and by whom and
+/// what they follow. Posts already read and the reader's own are left out.
+pub fn rank(candidates: Vec<Candidate>, signals: &Signals, reader: &str) -> Vec<ObjectId> {
+    let read: HashSet<ObjectId> = signals.history.iter().map(|post| post.id).collect();
+    let tags = affinity(signals.history.iter().flat_map(|post| post.tags.iter()));
+    let authors = affinity(
+        signals
+            .history
+            .iter()
+            .filter_map(|post| post.author.as_ref()),
+    );
+    let categories = affinity(
+        signals
+            .history
+            .iter()
+            .filter_map(|post| post.category.as_ref()),
+    );
+
+    let now = Utc::now();
+    let mut scored: Vec<(f64, DateTime<Utc>, ObjectId)> = candidates
+        .into_iter()
+        .filter(|post| !read.contains(&post.id) && post.author.as_deref() != Some(reader))
+        .map(|post| {
+            let age_days = (now - post.created_at).num_seconds().max(0) as f64 / 86_400.0;
+            let recency = 0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+            let tag = post
+                .tags
+                .iter()
+                .filter_map(|tag| tags.get(tag))
+                .cloned()
+                .fold(0.0, f64::max);
+            let author = post
+                .author
+                .as_ref()
+                .and_then(|author| authors.get(author))
+                .cloned()
+                .unwrap_or_default();
+            let category = post
+                .category
+                .as_ref()
+                .and_then(|category| categories.get(category))
+                .cloned()
+                .unwrap_or_default();
+
+            let mut score = RECENCY_WEIGHT * recency
+                + TAG_WEIGHT * tag
+                + AUTHOR_WEIGHT * author
+                + CATEGORY_WEIGHT * category;
+            if post
+                .author
+                .as_ref()
+                .is_some_and(|author| signals.followed_authors.contains(author))
+            {
+                score += FOLLOWED_AUTHOR_BONUS;
+            }
+            if post
+                .tags
+                .iter()
+                .any(|tag| signals.followed_tags.contains(tag))
+            {
+                score += FOLLOWED_TAG_BONUS;
+            }
+            (score, post.created_at, post.id)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.cmp(&a.1)));
+    scored.into_iter().map(|(_, _, id)| id).collect()
+}
+
+/// How often each value comes up, scaled so the most frequent is 1.
+fn affinity<'a>(values: impl Iterator<Item = &'a String>) -> HashMap<&'a String, f64> {
+    let mut counts: HashMap<&String, f64> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1.0;
+    }
+    let top = counts.values().cloned().fold(0.0, f64::max);
+    if top > 0.0 {
+        counts.values_mut().for_each(|count| *count /= top);
+    }
+    counts
+}
+
+/// Each reader's ranked feed, kept for `FEED_CACHE_SECS` (default 300) so
+/// paging through it is cheap and stable. Kept in memory per instance.
+pub struct FeedCache {
+    ttl: Duration,
+    feeds: RwLock<HashMap<String, (Instant, Vec<ObjectId>)>>,
+}
+
+impl FeedCache {
+    pub fn init() -> Self {
+        let secs = match std::env::var("FEED_CACHE_SECS") {
+            Ok(value) => value
+                .parse::<u64>()
+                .expect("FEED_CACHE_SECS must be a number."),
+            Err(_) => 300,
+        };
+        Self {
+            ttl: Duration::from_secs(secs),
+            feeds: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Vec<ObjectId>> {
+        let feeds = self.feeds.read().await;
+        let (ranked_at, ranked) = feeds.get(key)?;
+        (ranked_at.elapsed() < self.ttl).then(|| ranked.clone())
+    }
+
+    pub async fn put(&self, key: String, ranked: Vec<ObjectId>) {
+        let mut feeds = self.feeds.write().await;
+        if feeds.len() >= MAX_CACHED {
+            let ttl = self.ttl;
+            feeds.retain(|_, (ranked_at, _)| ranked_at.elapsed() < ttl);
+            if feeds.len() >= MAX_CACHED {
+                feeds.clear();
+            }
+        }
+        feeds.insert(key, (Instant::now(), ranked));
+    }
+}
+
+/// `reader`'s feed, ranked afresh unless a recent ranking is cached.
+/// `visitor` is the pseudonymous id their client sends analytics with,
+/// which ties their reading to them.
+pub async fn ranked(
+    app_state: &AppState,
+    reader: &str,
+    visitor: Option<&str>,
+) -> Result<Vec<ObjectId>, MyError> {
+    let key = format!("{}:{}", reader, visitor.unwrap_or_default());
+    if let Some(ranked) = app_state.feeds.get(&key).await {
+        return Ok(ranked);
+    }
+
+    let history = match visitor {
+        Some(visitor) => {
+            app_state
+                .db
+                .read_history(visitor, MAX_HISTORY, ReadFrom::Replica)
+                .await?
+        }
+        None => Vec::new(),
+    };
+    let (followed_authors, followed_tags) =
+        app_state.db.follows_of(reader, ReadFrom::Replica).await?;
+    let signals = Signals {
+        history,
+        followed_authors,
+        followed_tags,
+    };
+    let since = Utc::now() - chrono::Duration::days(CANDIDATE_WINDOW_DAYS);
+    let candidates = app_state
+        .db
+        .feed_candidates(since, MAX_CANDIDATES, ReadFrom::Replica)
+        .await?;
+
+    let ranked = rank(candidates, &signals, reader);
+    app_state.feeds.put(key, ranked.clone()).await;
+    Ok(ranked)
+}
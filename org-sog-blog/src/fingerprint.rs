@@ -0,0 +1,91 @@
+/// Posts shorter than this aren't fingerprinted; a few words in common say
+/// little about whether two posts are the same.
+const MIN_WORDS: usize = 20;
+const SHINGLE_WORDS: usize = 3;
+const BANDS: u32 = 4;
+const BAND_BITS: u32 = 64 / BANDS;
+
+/// Fingerprints at most this many bits apart count as the same content. Kept
+/// below `BANDS` so two such fingerprints always share at least one band.
+pub const MAX_DISTANCE: u32 = BANDS - 1;
+
+/// What `create_blog` does with a post that looks like one already stored,
+/// from `DUPLICATE_CONTENT` (`off`, `warn` or `reject`; defaults to `warn`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    Off,
+    Warn,
+    Reject,
+}
+
+impl DuplicatePolicy {
+    pub fn init() -> Self {
+        match std::env::var("DUPLICATE_CONTENT").as_deref() {
+            Err(_) | Ok("warn") => DuplicatePolicy::Warn,
+            Ok("off") => DuplicatePolicy::Off,
+            Ok("reject") => DuplicatePolicy::Reject,
+            Ok(other) => panic!(
+                "DUPLICATE_CONTENT must be off, warn or reject, not {}",
+                other
+            ),
+        }
+    }
+}
+
+/// 64-bit simhash over overlapping word shingles, or `None` when the text is
+/// too short to say anything useful.
+pub fn simhash(text: &str) -> Option<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_WORDS) {
+        let hash = fnv1a(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight > 0)
+            .fold(0, |hash, (bit, _)| hash | (1 << bit)),
+    )
+}
+
+/// The fingerprint split into tagged 16-bit bands, for an indexed `$in` lookup
+/// of candidates that then get the exact `distance` check.
+pub fn bands(hash: u64) -> Vec<i64> {
+    (0..BANDS)
+        .map(|band| {
+            let value = (hash >> (band * BAND_BITS)) & ((1 << BAND_BITS) - 1);
+            i64::from(band) << BAND_BITS | value as i64
+        })
+        .collect()
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+// FNV-1a, which unlike the std hasher is stable across releases.
+fn fnv1a(words: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in words.join(" ").bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
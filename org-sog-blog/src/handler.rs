@@ -1,79 +1,2326 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{
+        header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE, LINK, LOCATION},
+        HeaderMap, HeaderName, HeaderValue, StatusCode, Uri,
+    },
+    response::{IntoResponse, Response},
     Json,
 };
+use org_sog_core::{
+    bot::{BotRejection, Submission},
+    db_metrics,
+    plan::Plan,
+    read::ReadFrom,
+};
 
 use crate::{
+    backup, custom_fields,
+    db::{Actor, Viewer},
     error::MyError,
-    schema::{CreateBlogSchema, FilterOptions, UpdateBlogSchema},
-    AppState,
+    export,
+    extract::{AuthUser, ClientInfo, Credential, Reader},
+    feed, mention,
+    model::{ActivityKind, FollowKind, MentionModel, Visibility},
+    notify, og,
+    response::{
+        AdminOverviewResponse, AuthorData, AuthorResponse, BackupData, BlogListResponse,
+        BlogResponse, GenericResponse, PreviewLinkResponse, QueueBacklogResponse,
+        SingleAuthorResponse, SingleBackupResponse, SuggestionResponse, UnavailableSourceResponse,
+        UserCountsResponse,
+    },
+    sandbox,
+    schema::{
+        ActivityOptions, AnalyticsBatchSchema, AuditOptions, BlockSchema, BookmarkListOptions,
+        BookmarkSchema, BrokenLinkOptions, BuildIndexSchema, ChangesOptions, CheckTitleOptions,
+        CommentListOptions, ContactListOptions, ContactSchema, CreateAccessTokenSchema,
+        CreateBlogSchema, CreateCategorySchema, CreateCommentSchema, CreateIpRuleSchema,
+        CreatePageSchema, CreateRedirectSchema, CreateTemplateSchema, DiffOptions, DraftSchema,
+        EditCommentSchema, EventSchema, FeedOptions, FieldDefinitionSchema, FilterOptions,
+        FollowSchema, HistoryOptions, IndexAdviceOptions, MeteringOptions, MigrationAction,
+        NewPostOptions, OverdueOptions, PageListOptions, ProgressSchema, QualityOptions,
+        ReactionSchema, RestoreSchema, SavedSearchResultsOptions, SavedSearchSchema,
+        SemanticSearchOptions, SettingsSchema, SlowQueryOptions, StatsOptions, StatsWindowOptions,
+        UpdateBlogSchema, UpdateContactSchema, UpdatePageSchema, VariantOptions,
+    },
+    scope, AppState, FORM_COMMENT, FORM_CONTACT,
 };
 
+/// Posts shown on an author page.
+const AUTHOR_RECENT_POSTS: i64 = 5;
+/// How long the admin overview waits on any one source before leaving it out.
+const OVERVIEW_SOURCE_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub async fn dependencies_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    let dependencies: Vec<_> = [
+        app_state.db.breaker.metrics(),
+        app_state.auth.breaker_metrics(),
+    ]
+    .into_iter()
+    .chain(app_state.embeds.breaker_metrics())
+    .chain(app_state.suggester.breaker_metrics())
+    .chain(app_state.semantic.breaker_metrics())
+    .collect();
+
+    Json(serde_json::json!({
+        "status": "success",
+        "dependencies": dependencies,
+    }))
+}
+
+pub async fn metrics_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, db_metrics::CONTENT_TYPE)],
+        app_state.db.metrics.render(),
+    )
+}
+
 pub async fn blog_list_handler(
+    reader: Reader,
     opts: Option<Query<FilterOptions>>,
+    Query(params): Query<Vec<(String, String)>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(mut opts) = opts.unwrap_or_default();
+    opts.custom_fields = params
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let key = name.strip_prefix(custom_fields::FILTER_PREFIX)?;
+            Some((key.to_owned(), value))
+        })
+        .collect();
+    // Drafts and hidden posts are listed to admins, and to authors listing their own.
+    let include_hidden = reader.user.as_ref().is_some_and(|user| {
+        user.has_scope(scope::BLOG_ADMIN) || opts.author.as_deref() == Some(user.sub.as_str())
+    });
+
+    let default_limit = match opts.limit {
+        Some(_) => app_state.paging.default_limit,
+        None => match app_state.db.settings(ReadFrom::Replica).await {
+            Ok(settings) => settings.postsPerPage,
+            Err(e) => return Err(e.into()),
+        },
+    };
+    let paging = match app_state
+        .paging
+        .resolve_or(opts.page, opts.limit, default_limit)
+    {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    let mut res = match app_state
+        .db
+        .fetch_blogs(paging, &opts, include_hidden, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(user) = &reader.user {
+        if let Err(e) = app_state
+            .db
+            .mark_bookmarked(&user.sub, &mut res.blogs)
+            .await
+        {
+            return Err(e.into());
+        }
+    }
+    Ok(Json(res))
+}
+
+pub async fn create_blog_handler(
+    auth: AuthUser,
+    opts: Option<Query<NewPostOptions>>,
+    State(app_state): State<Arc<AppState>>,
+    Json(mut body): Json<CreateBlogSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+    let Query(opts) = opts.unwrap_or_default();
+    if let Some(template) = &opts.template {
+        if let Err(e) = app_state.db.apply_template(template, &mut body).await {
+            return Err(e.into());
+        }
+    }
+    if let Some(plan) = auth.plan {
+        let limit = app_state.plans.limits(plan).posts_per_month;
+        if let Err(e) = app_state.db.check_monthly_posts(&auth.sub, limit).await {
+            return Err(e.into());
+        }
+    }
+
+    match app_state.db.create_blog(&body, &auth.sub).await {
+        Ok(res) => {
+            if !sandbox::active() {
+                announce_post(&app_state, &res.data.blog).await;
+            }
+            Ok((StatusCode::CREATED, Json(res)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Tells the followers of a newly published post's author and tags about
+/// it, once per post. Like mentions, a failure here doesn't fail the post.
+async fn announce_post(app_state: &AppState, blog: &BlogResponse) {
+    if !blog.published || blog.visibility != Visibility::Public {
+        return;
+    }
+    let Some(author) = &blog.author else {
+        return;
+    };
+
+    match app_state.db.claim_publish_notice(&blog.id).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            println!("⚠️ Announcing post {} failed: {}", blog.id, e);
+            return;
+        }
+    }
+    record_activity(
+        app_state,
+        ActivityKind::PostPublished,
+        Some(author.as_str()),
+        &blog.id,
+        format!("{} published \"{}\"", author, blog.title),
+    )
+    .await;
+    let user_ids = match app_state.db.followers_of(author, &blog.tags).await {
+        Ok(user_ids) => user_ids,
+        Err(e) => {
+            println!("⚠️ Announcing post {} failed: {}", blog.id, e);
+            return;
+        }
+    };
+    if user_ids.is_empty() {
+        return;
+    }
+
+    let event = serde_json::json!({
+        "userIds": user_ids,
+        "postId": blog.id,
+        "author": author,
+        "title": blog.title,
+    });
+    app_state
+        .notify
+        .publish(notify::POST_PUBLISHED, event)
+        .await;
+}
+
+pub async fn overdue_handler(
+    auth: AuthUser,
+    opts: Option<Query<OverdueOptions>>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
     let Query(opts) = opts.unwrap_or_default();
 
-    let limit = opts.limit.unwrap_or(10) as i64;
-    let page = opts.page.unwrap_or(1) as i64;
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
 
     match app_state
         .db
-        .fetch_blogs(limit, page)
+        .fetch_overdue(paging, &opts, &actor, ReadFrom::Primary)
         .await
-        .map_err(MyError::from)
     {
         Ok(res) => Ok(Json(res)),
         Err(e) => Err(e.into()),
     }
 }
 
-pub async fn create_blog_handler(
+pub async fn template_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    match app_state.db.fetch_templates(ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_template_handler(
+    auth: AuthUser,
     State(app_state): State<Arc<AppState>>,
-    Json(body): Json<CreateBlogSchema>,
+    Json(body): Json<CreateTemplateSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state.db.create_blog(&body).await.map_err(MyError::from) {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_template(&body, &auth.sub).await {
         Ok(res) => Ok((StatusCode::CREATED, Json(res))),
         Err(e) => Err(e.into()),
     }
 }
 
-pub async fn get_blog_handler(
+pub async fn delete_template_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_template(&id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn field_definition_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    match app_state
+        .db
+        .fetch_field_definitions(ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn put_field_definition_handler(
+    auth: AuthUser,
+    Path(key): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<FieldDefinitionSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state
+        .db
+        .put_field_definition(&key, &body, &auth.sub)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_field_definition_handler(
+    auth: AuthUser,
+    Path(key): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_field_definition(&key).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn saved_search_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    match app_state
+        .db
+        .fetch_saved_searches(&auth.sub, ReadFrom::Primary)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_saved_search_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<SavedSearchSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_saved_search(&auth.sub, &body).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_saved_search_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_saved_search(&id, &auth.sub).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn saved_search_results_handler(
+    auth: AuthUser,
     Path(id): Path<String>,
+    opts: Option<Query<SavedSearchResultsOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+
+    let mut res = match app_state
+        .db
+        .saved_search_results(
+            &id,
+            &actor,
+            paging,
+            opts.visitor.as_deref(),
+            ReadFrom::Replica,
+        )
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => return Err(e.into()),
+    };
+    if let Err(e) = app_state
+        .db
+        .mark_bookmarked(&auth.sub, &mut res.blogs)
+        .await
+    {
+        return Err(e.into());
+    }
+    Ok(Json(res))
+}
+
+pub async fn check_title_handler(
+    Query(opts): Query<CheckTitleOptions>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .check_title(&opts.title, opts.exclude.as_deref(), ReadFrom::Primary)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn changes_handler(
+    reader: Reader,
+    Query(opts): Query<ChangesOptions>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state.db.get_blog(&id).await.map_err(MyError::from) {
+    let include_hidden = reader
+        .user
+        .as_ref()
+        .is_some_and(|user| user.has_scope(scope::BLOG_ADMIN));
+    let limit = match app_state.paging.resolve(None, opts.limit) {
+        Ok(paging) => paging.limit,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state
+        .db
+        .fetch_changes(&opts.since, limit, include_hidden)
+        .await
+    {
         Ok(res) => Ok(Json(res)),
         Err(e) => Err(e.into()),
     }
 }
 
+pub async fn get_blog_handler(
+    reader: Reader,
+    Path(id): Path<String>,
+    opts: Option<Query<VariantOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .db
+        .get_blog(
+            &id,
+            opts.visitor.as_deref(),
+            &reader.viewer(),
+            ReadFrom::Replica,
+        )
+        .await
+    {
+        Ok(mut res) => {
+            if let Some(user) = &reader.user {
+                let blogs = std::slice::from_mut(&mut res.data.blog);
+                if let Err(e) = app_state.db.mark_bookmarked(&user.sub, blogs).await {
+                    return Err(e.into());
+                }
+            }
+            if opts.embeds == Some(true) {
+                let embeds = app_state.embeds.expand(&res.data.blog.content).await;
+                res.data.blog.embeds = Some(embeds);
+            }
+            if opts.render == Some(true) {
+                // Highlighting is CPU-bound, keep it off the async workers.
+                let content = res.data.blog.content.to_owned();
+                let state = app_state.clone();
+                match tokio::task::spawn_blocking(move || state.renderer.render(&content)).await {
+                    Ok(html) => res.data.blog.contentHtml = Some(html),
+                    Err(e) => println!("⚠️ Rendering post {} failed: {}", id, e),
+                }
+            }
+            // Opened with a password or token: no shared cache may keep it.
+            let protected = res.data.blog.visibility == Visibility::Protected;
+            // Syndicated copies credit the original, so they don't compete with it.
+            let canonical = res.data.blog.canonicalUrl.as_ref().and_then(|url| {
+                HeaderValue::from_str(&format!("<{}>; rel=\"canonical\"", url)).ok()
+            });
+            let noindex = res.data.blog.syndication.noindex == Some(true);
+            let mut response = Json(res).into_response();
+            let headers = response.headers_mut();
+            if protected {
+                headers.insert(CACHE_CONTROL, HeaderValue::from_static("private, no-store"));
+            }
+            if let Some(canonical) = canonical {
+                headers.insert(LINK, canonical);
+            }
+            if noindex {
+                headers.insert(
+                    HeaderName::from_static("x-robots-tag"),
+                    HeaderValue::from_static("noindex"),
+                );
+            }
+            Ok(response)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub async fn edit_blog_handler(
+    auth: AuthUser,
     Path(id): Path<String>,
     State(app_state): State<Arc<AppState>>,
     Json(body): Json<UpdateBlogSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+
+    match app_state.db.edit_blog(&id, &body, &actor).await {
+        Ok(res) => {
+            if body.published == Some(true) && !sandbox::active() {
+                announce_post(&app_state, &res.data.blog).await;
+            }
+            Ok(Json(res))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_blog_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+
+    match app_state.db.delete_blog(&id, &actor).await {
+        Ok(_) => {
+            if !sandbox::active() {
+                record_activity(
+                    &app_state,
+                    ActivityKind::PostDeleted,
+                    Some(&auth.sub),
+                    &id,
+                    format!("{} deleted post {}", auth.sub, id),
+                )
+                .await;
+            }
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn revision_list_handler(
+    reader: Reader,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     match app_state
         .db
-        .edit_blog(&id, &body)
+        .fetch_revisions(&id, &reader.viewer(), ReadFrom::Replica)
         .await
-        .map_err(MyError::from)
     {
         Ok(res) => Ok(Json(res)),
         Err(e) => Err(e.into()),
     }
 }
 
-pub async fn delete_blog_handler(
+pub async fn revision_diff_handler(
+    reader: Reader,
+    Path((id, from, to)): Path<(String, i64, i64)>,
+    opts: Option<Query<DiffOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .db
+        .diff_revisions(
+            &id,
+            from,
+            to,
+            opts.granularity.unwrap_or_default(),
+            &reader.viewer(),
+            ReadFrom::Replica,
+        )
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn save_draft_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<DraftSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+
+    match app_state.db.save_draft(&id, &body, &actor).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_draft_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.get_draft(&id, &auth.sub).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn discard_draft_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.discard_draft(&id, &auth.sub).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Signs a link that shows the caller's draft of a post to anyone holding it.
+pub async fn create_preview_link_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+    if let Err(e) = app_state.db.check_can_preview(&id, &actor).await {
+        return Err(e.into());
+    }
+
+    let (token, expires_at) = app_state.previews.sign(&id, &auth.sub);
+    Ok((
+        StatusCode::CREATED,
+        Json(PreviewLinkResponse {
+            status: "success",
+            url: app_state.previews.url(&token),
+            token,
+            expiresAt: expires_at,
+        }),
+    ))
+}
+
+pub async fn preview_handler(
+    Path(token): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let grant = match app_state.previews.verify(&token) {
+        Ok(grant) => grant,
+        Err(e) => return Err(e.into()),
+    };
+
+    // Unpublished work: keep it out of shared caches and search engines.
+    match app_state.db.preview_blog(&grant).await {
+        Ok(res) => Ok((
+            [
+                (CACHE_CONTROL, HeaderValue::from_static("private, no-store")),
+                (
+                    HeaderName::from_static("x-robots-tag"),
+                    HeaderValue::from_static("noindex"),
+                ),
+            ],
+            Json(res),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A post's social card, for link previews.
+pub async fn og_image_handler(
+    reader: Reader,
     Path(id): Path<String>,
     State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    match app_state.db.delete_blog(&id).await.map_err(MyError::from) {
+    match og::card(&app_state, &id, &reader.viewer()).await {
+        Ok((png, protected)) => {
+            let cache = if protected {
+                "private, no-store"
+            } else {
+                "public, max-age=3600"
+            };
+            Ok((
+                [
+                    (CONTENT_TYPE, HeaderValue::from_static("image/png")),
+                    (CACHE_CONTROL, HeaderValue::from_static(cache)),
+                ],
+                png,
+            ))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The stylesheet for highlighted code in rendered posts.
+pub async fn code_stylesheet_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [
+            (
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/css; charset=utf-8"),
+            ),
+            (
+                CACHE_CONTROL,
+                HeaderValue::from_static("public, max-age=86400"),
+            ),
+        ],
+        app_state.renderer.stylesheet(),
+    )
+}
+
+pub async fn comment_list_handler(
+    reader: Reader,
+    Path(id): Path<String>,
+    opts: Option<Query<CommentListOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state
+        .db
+        .fetch_comments(
+            &id,
+            paging,
+            opts.replies,
+            &reader.viewer(),
+            ReadFrom::Replica,
+        )
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn comment_replies_handler(
+    reader: Reader,
+    Path(id): Path<String>,
+    opts: Option<Query<CommentListOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state
+        .db
+        .fetch_replies(
+            &id,
+            paging,
+            opts.replies,
+            &reader.viewer(),
+            ReadFrom::Replica,
+        )
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_comment_handler(
+    reader: Reader,
+    client: ClientInfo,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateCommentSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    // Signed-in readers were rate limited when they authenticated; anonymous
+    // ones are held to the free plan by address and may be challenged.
+    if reader.user.is_none() {
+        let ip = client.ip.as_deref();
+        let caller = format!("ip:{}", ip.unwrap_or("unknown"));
+        if let Err(e) = app_state.rate.check(&caller, Plan::Free).await {
+            return Err(e.into());
+        }
+        let submission = Submission {
+            honeypot: Some(&body.website),
+            stamp: body.formStamp.as_deref(),
+            captcha_token: body.turnstileToken.as_deref(),
+            ip,
+        };
+        if app_state
+            .bots
+            .check(FORM_COMMENT, &submission)
+            .await
+            .is_err()
+        {
+            return Err(MyError::ChallengeFailedError.into());
+        }
+    }
+
+    let mentions = resolve_mentions(&app_state, &body.body).await;
+    match app_state
+        .db
+        .create_comment(&id, &body, mentions, &reader.viewer())
+        .await
+    {
+        Ok(res) => {
+            let comment = &res.data.comment;
+            // Nobody needs telling about mentioning themselves.
+            let user_ids: Vec<&str> = comment
+                .mentions
+                .iter()
+                .map(|mention| mention.userId.as_str())
+                .filter(|user_id| Some(*user_id) != comment.author.as_deref())
+                .collect();
+            if !user_ids.is_empty() {
+                let event = serde_json::json!({
+                    "userIds": user_ids,
+                    "commentId": comment.id,
+                    "postId": comment.postId,
+                    "by": comment.name,
+                });
+                app_state
+                    .notify
+                    .publish(notify::COMMENT_MENTIONED, event)
+                    .await;
+            }
+            record_activity(
+                &app_state,
+                ActivityKind::CommentPosted,
+                comment.author.as_deref(),
+                &comment.id,
+                format!("{} commented on post {}", comment.name, comment.postId),
+            )
+            .await;
+            Ok((StatusCode::CREATED, Json(res)))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Adds a line to the activity stream. It's only there to glance at, so a
+/// failed write is logged and otherwise ignored.
+async fn record_activity(
+    app_state: &AppState,
+    kind: ActivityKind,
+    actor: Option<&str>,
+    subject: &str,
+    summary: String,
+) {
+    if let Err(e) = app_state
+        .db
+        .record_activity(kind, actor, subject, summary)
+        .await
+    {
+        println!("⚠️ Recording activity on {} failed: {}", subject, e);
+    }
+}
+
+/// Looks up the users mentioned in `text`. Mentions are a courtesy, so if the
+/// auth service can't be reached the comment goes up without them.
+async fn resolve_mentions(app_state: &AppState, text: &str) -> Vec<MentionModel> {
+    let names = mention::parse(text);
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    match app_state.auth.resolve_users(&names).await {
+        Ok(users) => users
+            .into_iter()
+            .map(|user| MentionModel {
+                userId: user.id,
+                name: user.name,
+            })
+            .collect(),
+        Err(e) => {
+            println!("⚠️ could not resolve comment mentions: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+pub async fn edit_comment_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<EditCommentSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.edit_comment(&id, &body, &auth.sub).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn add_reaction_handler(
+    reader: Reader,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<ReactionSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .add_reaction(&id, &body, &reader.viewer())
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn remove_reaction_handler(
+    reader: Reader,
+    Path((id, emoji)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .remove_reaction(&id, &emoji, &reader.viewer())
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn block_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.fetch_blocks(&auth.sub).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn block_user_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<BlockSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.block_user(&body, &auth.sub).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn unblock_user_handler(
+    auth: AuthUser,
+    Path(user_id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.unblock_user(&user_id, &auth.sub).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn bookmark_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    body: Option<Json<BookmarkSchema>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Json(body) = body.unwrap_or_default();
+
+    let viewer = Viewer {
+        actor: Some(Actor {
+            id: &auth.sub,
+            admin: auth.has_scope(scope::BLOG_ADMIN),
+        }),
+        access: None,
+    };
+
+    match app_state.db.bookmark(&id, &body, &viewer).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn remove_bookmark_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.remove_bookmark(&id, &auth.sub).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn bookmark_list_handler(
+    auth: AuthUser,
+    opts: Option<Query<BookmarkListOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state
+        .db
+        .fetch_bookmarks(&auth.sub, &opts, paging, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn progress_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<ProgressSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.record_progress(&id, &body, &auth.sub).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn history_handler(
+    auth: AuthUser,
+    opts: Option<Query<HistoryOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state
+        .db
+        .fetch_history(&auth.sub, &opts, paging, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn clear_history_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.clear_history(&auth.sub).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A public author page: what the author shows of their profile in the auth
+/// service, with their latest posts and numbers unless they hide those.
+pub async fn get_author_handler(
+    Path(slug): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let profile = match app_state.auth.author_profile(&slug).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return Err(MyError::NotFoundError(slug).into()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let recent_posts = if profile.recentPosts {
+        match app_state
+            .db
+            .recent_posts_by(&profile.id, AUTHOR_RECENT_POSTS, ReadFrom::Replica)
+            .await
+        {
+            Ok(blogs) => Some(blogs),
+            Err(e) => return Err(e.into()),
+        }
+    } else {
+        None
+    };
+    let stats = if profile.stats {
+        match app_state
+            .db
+            .author_stats(&profile.id, ReadFrom::Replica)
+            .await
+        {
+            Ok(stats) => Some(stats),
+            Err(e) => return Err(e.into()),
+        }
+    } else {
+        None
+    };
+
+    Ok(Json(SingleAuthorResponse {
+        status: "success",
+        data: AuthorData {
+            author: AuthorResponse {
+                id: profile.id,
+                name: profile.name,
+                bio: profile.bio,
+                website: profile.website,
+                location: profile.location,
+                avatarUrl: profile.avatarUrl,
+                joinedAt: profile.joinedAt,
+                recentPosts: recent_posts,
+                stats,
+            },
+        },
+    }))
+}
+
+pub async fn follow_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .fetch_follows(&auth.sub, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn follow_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<FollowSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.follow(&body, &auth.sub).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn unfollow_handler(
+    auth: AuthUser,
+    Path((kind, target)): Path<(FollowKind, String)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.unfollow(kind, &target, &auth.sub).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn follower_count_handler(
+    Path((kind, target)): Path<(FollowKind, String)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .count_followers(kind, &target, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Lets moderators see what an edited comment said before.
+pub async fn comment_history_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.comment_history(&id).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Issues a token that opens a protected post, shown only in this response.
+pub async fn create_access_token_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    body: Option<Json<CreateAccessTokenSchema>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+    let Json(body) = body.unwrap_or_default();
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+
+    match app_state
+        .db
+        .create_access_token(&id, body.label.as_deref(), &actor)
+        .await
+    {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn access_token_list_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+
+    match app_state.db.fetch_access_tokens(&id, &actor).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn revoke_access_token_handler(
+    auth: AuthUser,
+    Path((id, token_id)): Path<(String, String)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+
+    match app_state
+        .db
+        .revoke_access_token(&id, &token_id, &actor)
+        .await
+    {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn category_list_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.fetch_categories(ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_category_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateCategorySchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_category(&body).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn tag_stats_handler(
+    opts: Option<Query<StatsOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .db
+        .fetch_tag_stats(opts.kind, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn rebuild_tag_stats_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.rebuild_tag_stats().await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn analytics_handler(
+    client: ClientInfo,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<AnalyticsBatchSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state
+        .db
+        .ingest_analytics(&body, client.geo.as_ref())
+        .await
+    {
+        Ok(res) => Ok((StatusCode::ACCEPTED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn post_stats_handler(
+    Path(id): Path<String>,
+    opts: Option<Query<StatsWindowOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+    let days = opts.days.unwrap_or(30);
+
+    match app_state.db.post_stats(&id, days, ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn link_health_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    match app_state.db.link_health(&id, ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn quality_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    opts: Option<Query<QualityOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .db
+        .quality(&id, opts.lint == Some(true), ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// A suggested summary and tags for the caller's draft of post `id`, when
+/// the site has suggestions turned on.
+pub async fn suggest_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    match app_state.db.settings(ReadFrom::Replica).await {
+        Ok(settings) if settings.suggestions && app_state.suggester.is_configured() => {}
+        Ok(_) => {
+            return Err(MyError::NotPermittedError(
+                "suggestions are turned off for this site".to_string(),
+            )
+            .into())
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let actor = Actor {
+        id: &auth.sub,
+        admin: auth.has_scope(scope::BLOG_ADMIN),
+    };
+    let (title, content) = match app_state.db.draft_text(&id, &actor).await {
+        Ok(text) => text,
+        Err(e) => return Err(e.into()),
+    };
+    let known_tags = match app_state.db.popular_tags(50, ReadFrom::Replica).await {
+        Ok(tags) => tags,
+        Err(e) => return Err(e.into()),
+    };
+
+    match app_state
+        .suggester
+        .suggest(&auth.sub, &title, &content, &known_tags)
+        .await
+    {
+        Ok(suggestion) => Ok(Json(SuggestionResponse {
+            status: "success",
+            postId: id,
+            summary: suggestion.summary,
+            tags: suggestion.tags,
+        })),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The caller's personal feed: recent public posts ranked by what they read.
+pub async fn feed_handler(
+    auth: AuthUser,
+    opts: Option<Query<FeedOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    let ranked = match feed::ranked(&app_state, &auth.sub, opts.visitor.as_deref()).await {
+        Ok(ranked) => ranked,
+        Err(e) => return Err(e.into()),
+    };
+    let page: Vec<_> = ranked
+        .into_iter()
+        .skip(paging.skip() as usize)
+        .take(paging.limit as usize)
+        .collect();
+
+    let mut blogs = match app_state.db.blogs_in_order(&page, ReadFrom::Replica).await {
+        Ok(blogs) => blogs,
+        Err(e) => return Err(e.into()),
+    };
+    if let Err(e) = app_state.db.mark_bookmarked(&auth.sub, &mut blogs).await {
+        return Err(e.into());
+    }
+
+    Ok(Json(BlogListResponse {
+        status: "success",
+        page: paging.page,
+        limit: paging.limit,
+        results: blogs.len(),
+        blogs,
+    }))
+}
+
+pub async fn semantic_search_handler(
+    opts: Option<Query<SemanticSearchOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+    let query = opts.q.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    let embedded = app_state.semantic.embed_query(&query).await;
+    match app_state
+        .db
+        .semantic_search(
+            &query,
+            embedded
+                .as_ref()
+                .map(|(model, vector)| (*model, vector.as_slice())),
+            app_state.semantic.weight,
+            paging,
+            ReadFrom::Replica,
+        )
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn broken_links_handler(
+    auth: AuthUser,
+    opts: Option<Query<BrokenLinkOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_WRITE) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve_or(opts.page, opts.limit, 20) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state.db.broken_links(paging, ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn title_test_handler(
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.title_test(&id, ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_settings_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.get_settings(ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn update_settings_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<SettingsSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.update_settings(&body, &auth.sub).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn page_list_handler(
+    auth: Option<AuthUser>,
+    opts: Option<Query<PageListOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+    let is_admin = auth.is_some_and(|auth| auth.has_scope(scope::BLOG_ADMIN));
+
+    match app_state
+        .db
+        .fetch_pages(
+            is_admin && opts.include_drafts.unwrap_or(false),
+            ReadFrom::Replica,
+        )
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_page_handler(
+    auth: Option<AuthUser>,
+    Path(slug): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let is_admin = auth.is_some_and(|auth| auth.has_scope(scope::BLOG_ADMIN));
+
+    match app_state
+        .db
+        .get_page(&slug, is_admin, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_page_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreatePageSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_page(&body, &auth.sub).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn edit_page_handler(
+    auth: AuthUser,
+    Path(slug): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdatePageSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.edit_page(&slug, &body).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_page_handler(
+    auth: AuthUser,
+    Path(slug): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_page(&slug).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn navigation_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match app_state.db.navigation(ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn contact_handler(
+    client: ClientInfo,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<ContactSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let accepted = (
+        StatusCode::ACCEPTED,
+        Json(GenericResponse {
+            status: "success".to_string(),
+            message: "Thanks, your message has been sent".to_string(),
+        }),
+    );
+
+    let ip = client.ip.as_deref();
+    let submission = Submission {
+        honeypot: Some(&body.website),
+        stamp: body.formStamp.as_deref(),
+        captcha_token: body.turnstileToken.as_deref(),
+        ip,
+    };
+    match app_state.bots.check(FORM_CONTACT, &submission).await {
+        Ok(()) => {}
+        // Bots that fill in the honeypot get the same answer, just nothing is kept.
+        Err(BotRejection::Honeypot) => return Ok(accepted),
+        Err(_) => return Err(MyError::ChallengeFailedError.into()),
+    }
+
+    if let Err(e) = app_state.contact.check_rate(ip.unwrap_or("unknown")).await {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_contact_message(&body, ip).await {
+        Ok(contact) => {
+            app_state.contact.notify_owner(&contact).await;
+            record_activity(
+                &app_state,
+                ActivityKind::ContactReceived,
+                None,
+                &contact.id.to_hex(),
+                format!("{} sent a message through the contact form", contact.name),
+            )
+            .await;
+            Ok(accepted)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn form_stamp_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(CACHE_CONTROL, HeaderValue::from_static("no-store"))],
+        Json(serde_json::json!({
+            "status": "success",
+            "stamp": app_state.bots.stamp(),
+        })),
+    )
+}
+
+pub async fn bot_metrics_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": "success",
+        "blocked": app_state.bots.metrics(),
+    })))
+}
+
+pub async fn contact_list_handler(
+    auth: AuthUser,
+    opts: Option<Query<ContactListOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve_or(opts.page, opts.limit, 20) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state
+        .db
+        .fetch_contact_messages(paging, &opts, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn update_contact_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<UpdateContactSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.set_contact_status(&id, body.status).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn redirect_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.fetch_redirects(ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_redirect_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateRedirectSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_redirect(&body).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn delete_redirect_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_redirect(&id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn ip_rule_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.fetch_ip_rules(ReadFrom::Replica).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_ip_rule_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<CreateIpRuleSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.create_ip_rule(&body, &auth.sub).await {
+        Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Also lifts automatic bans early.
+pub async fn delete_ip_rule_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.delete_ip_rule(&id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Catch-all for paths no route matched: follows the redirect table, or 404s.
+pub async fn redirect_fallback_handler(
+    uri: Uri,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Response, (StatusCode, Json<serde_json::Value>)> {
+    let redirect = match app_state.db.resolve_redirect(uri.path()).await {
+        Ok(redirect) => redirect,
+        Err(e) => return Err(e.into()),
+    };
+
+    match redirect {
+        Some((410, _)) => Err((
+            StatusCode::GONE,
+            Json(serde_json::json!({
+                "status": "fail",
+                "message": format!("{} has been removed", uri.path()),
+            })),
+        )),
+        Some((status, Some(to))) => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::MOVED_PERMANENTLY);
+            Ok((status, [(LOCATION, to)]).into_response())
+        }
+        _ => Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "status": "fail",
+                "message": format!("no route for {}", uri.path()),
+            })),
+        )),
+    }
+}
+
+pub async fn create_backup_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match backup::run(&app_state).await {
+        Ok(backup) => Ok((
+            StatusCode::CREATED,
+            Json(SingleBackupResponse {
+                status: "success",
+                data: BackupData { backup },
+            }),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Every post as Markdown, with the media files they link to, as one tar
+/// archive streamed while it's built.
+pub async fn full_export_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match export::full(&app_state).await {
+        Ok(body) => {
+            let disposition = format!(
+                "attachment; filename=\"blog-export-{}.tar\"",
+                chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+            );
+            Ok((
+                [
+                    (CONTENT_TYPE, HeaderValue::from_static("application/x-tar")),
+                    (
+                        CONTENT_DISPOSITION,
+                        HeaderValue::from_str(&disposition).unwrap(),
+                    ),
+                    (CACHE_CONTROL, HeaderValue::from_static("no-store")),
+                ],
+                body,
+            ))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn backup_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match backup::list(&app_state).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn restore_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<RestoreSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match backup::restore(&app_state, &body.archive, &body.database, body.overwrite).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn usage_handler(
+    auth: AuthUser,
+    Path(id): Path<String>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if auth.sub != id {
+        if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+            return Err(e.into());
+        }
+    }
+
+    match app_state.db.usage(&id).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn metering_handler(
+    auth: AuthUser,
+    opts: Option<Query<MeteringOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .db
+        .metering(&auth.sub, &opts, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn metering_rollup_handler(
+    auth: AuthUser,
+    opts: Option<Query<MeteringOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve_or(opts.page, opts.limit, 100) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state
+        .db
+        .metering_rollup(paging, &opts, ReadFrom::Replica)
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Key numbers across both services for the ops dashboard. The sources are
+/// asked at once, each with its own timeout, so one slow source leaves a gap
+/// rather than holding up the rest.
+pub async fn admin_overview_handler(
+    auth: AuthUser,
+    headers: HeaderMap,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    let credential = Credential::from_headers(&headers);
+    let auth_stats = async {
+        match &credential {
+            Some(credential) => app_state.auth.admin_stats(credential).await,
+            None => Err(MyError::UnauthorizedError(
+                "no credential to pass on".to_string(),
+            )),
+        }
+    };
+
+    let (auth_stats, posts, comments, errors, unread_contact, metering) = tokio::join!(
+        overview_source("auth", auth_stats),
+        overview_source("posts", app_state.db.post_counts(ReadFrom::Replica)),
+        overview_source("comments", app_state.db.comment_counts(ReadFrom::Replica)),
+        overview_source("errors", app_state.db.error_rate(ReadFrom::Replica)),
+        overview_source(
+            "contact",
+            app_state.db.count_unread_contact(ReadFrom::Replica)
+        ),
+        app_state.meter.backlog(),
+    );
+
+    let mut unavailable = Vec::new();
+    let auth_stats = auth_stats.map_err(|e| unavailable.push(e)).ok();
+    let posts = posts.map_err(|e| unavailable.push(e)).ok();
+    let comments = comments.map_err(|e| unavailable.push(e)).ok();
+    let errors = errors.map_err(|e| unavailable.push(e)).ok();
+    let unread_contact = unread_contact.map_err(|e| unavailable.push(e)).ok();
+
+    Ok(Json(AdminOverviewResponse {
+        status: "success",
+        generatedAt: chrono::Utc::now(),
+        users: auth_stats.as_ref().map(|stats| UserCountsResponse {
+            total: stats.users.total,
+            new: stats.users.new,
+        }),
+        posts,
+        comments,
+        errors,
+        queues: QueueBacklogResponse {
+            outbox: auth_stats.as_ref().map(|stats| stats.queues.outbox),
+            deadLetters: auth_stats.as_ref().map(|stats| stats.queues.deadLetters),
+            unreadContact: unread_contact,
+            metering,
+        },
+        unavailable,
+    }))
+}
+
+/// Runs one overview source, turning a failure or timeout into the entry
+/// reported under `unavailable`.
+async fn overview_source<T>(
+    source: &'static str,
+    work: impl Future<Output = Result<T, MyError>>,
+) -> Result<T, UnavailableSourceResponse> {
+    match tokio::time::timeout(OVERVIEW_SOURCE_TIMEOUT, work).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(UnavailableSourceResponse {
+            source,
+            error: e.to_string(),
+        }),
+        Err(_) => Err(UnavailableSourceResponse {
+            source,
+            error: format!("no answer within {}s", OVERVIEW_SOURCE_TIMEOUT.as_secs()),
+        }),
+    }
+}
+
+pub async fn slow_query_list_handler(
+    auth: AuthUser,
+    opts: Option<Query<SlowQueryOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    let paging = match app_state.paging.resolve(opts.page, opts.limit) {
+        Ok(paging) => paging,
+        Err(e) => return Err(MyError::from(e).into()),
+    };
+
+    match app_state.db.fetch_slow_queries(&opts, paging).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn activity_handler(
+    auth: AuthUser,
+    opts: Option<Query<ActivityOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state.db.fetch_activity(&opts).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn index_advice_handler(
+    auth: AuthUser,
+    opts: Option<Query<IndexAdviceOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state.db.index_advice(&opts).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn index_build_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.index_builds().await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn build_index_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<BuildIndexSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.start_index_build(body).await {
+        Ok(res) => Ok((StatusCode::ACCEPTED, Json(res))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn migration_list_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.migration_jobs().await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn change_migration_handler(
+    auth: AuthUser,
+    Path((name, action)): Path<(String, MigrationAction)>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    match app_state.db.change_migration(&name, action).await {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn consistency_audit_handler(
+    auth: AuthUser,
+    opts: Option<Query<AuditOptions>>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    let Query(opts) = opts.unwrap_or_default();
+
+    match app_state
+        .db
+        .audit_consistency(opts.repair.unwrap_or(false))
+        .await
+    {
+        Ok(res) => Ok(Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn event_handler(
+    auth: AuthUser,
+    State(app_state): State<Arc<AppState>>,
+    Json(body): Json<EventSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if let Err(e) = auth.require_scope(scope::BLOG_ADMIN) {
+        return Err(e.into());
+    }
+
+    // Anonymized and deleted users are handled alike: their posts stay, credited to no one.
+    let departed_user = match body {
+        EventSchema::UserAnonymized { userId: user_id } => Some(user_id),
+        EventSchema::ResourceDeleted {
+            resourceType: resource_type,
+            resourceId: resource_id,
+        } if resource_type == "user" => Some(resource_id),
+        // Nothing here refers to other deleted resources.
+        EventSchema::ResourceDeleted { .. } => None,
+    };
+
+    let handled = match departed_user {
+        Some(user_id) => match app_state.db.discard_drafts_by(&user_id).await {
+            Ok(_) => app_state.db.reattribute_author(&user_id).await.map(|_| ()),
+            Err(e) => Err(e),
+        },
+        None => Ok(()),
+    };
+
+    match handled {
         Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => Err(e.into()),
     }
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use axum::{
+    http::{header::CONTENT_TYPE, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::error::MyError;
+
+/// Request timeout and body size limits, read from the environment.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestLimits {
+    pub timeout: Duration,
+    /// Ceiling for ordinary JSON request bodies.
+    pub json_body: usize,
+    /// Ceiling for routes that accept large content.
+    pub upload_body: usize,
+}
+
+impl RequestLimits {
+    pub fn init() -> Self {
+        Self {
+            timeout: Duration::from_secs(env_or("REQUEST_TIMEOUT_SECS", 30)),
+            json_body: env_or("JSON_BODY_LIMIT_BYTES", 64 * 1024) as usize,
+            upload_body: env_or("UPLOAD_BODY_LIMIT_BYTES", 10 * 1024 * 1024) as usize,
+        }
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a whole number, got '{}'", name, value)),
+        Err(_) => default,
+    }
+}
+
+/// Rewrites the bare 408/413 responses produced by the timeout and body-limit
+/// layers into the usual error envelope.
+pub async fn envelope_errors<B>(request: Request<B>, next: Next<B>) -> Response {
+    let response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if is_json {
+        return response;
+    }
+
+    let error = match response.status() {
+        StatusCode::REQUEST_TIMEOUT => MyError::RequestTimeoutError,
+        StatusCode::PAYLOAD_TOO_LARGE => MyError::PayloadTooLargeError,
+        _ => return response,
+    };
+    let error: (StatusCode, Json<serde_json::Value>) = error.into();
+    error.into_response()
+}
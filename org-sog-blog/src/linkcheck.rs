@@ -0,0 +1,167 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use org_sog_core::http::{HttpClient, HttpClientConfig, Method, StatusCode};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+use crate::{error::MyError, AppState};
+
+/// Links past this many in one post aren't checked.
+const MAX_LINKS_PER_POST: usize = 200;
+
+/// The outcome of checking one URL.
+#[derive(Debug, Clone)]
+pub struct LinkStatus {
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl LinkStatus {
+    /// Rate limiting says nothing about the link, so it doesn't count.
+    pub fn is_broken(&self) -> bool {
+        match self.status {
+            Some(status) => status >= 400 && status != StatusCode::TOO_MANY_REQUESTS.as_u16(),
+            None => true,
+        }
+    }
+}
+
+/// Checks the links in published posts every `LINK_CHECK_INTERVAL_HOURS`
+/// (default 24, 0 turns it off), `LINK_CHECK_CONCURRENCY` (default 8) at a
+/// time. Each instance runs its own checks.
+pub struct LinkChecker {
+    http: Arc<HttpClient>,
+    interval: Option<Duration>,
+    concurrency: usize,
+}
+
+impl LinkChecker {
+    pub fn init() -> Self {
+        let env_or = |name: &str, default: u64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("{} must be a number.", name)),
+            Err(_) => default,
+        };
+        let hours = env_or("LINK_CHECK_INTERVAL_HOURS", 24);
+
+        Self {
+            http: Arc::new(HttpClient::new(
+                "link-check",
+                HttpClientConfig {
+                    max_retries: 0,
+                    ..HttpClientConfig::default()
+                },
+            )),
+            interval: (hours > 0).then(|| Duration::from_secs(hours * 60 * 60)),
+            concurrency: env_or("LINK_CHECK_CONCURRENCY", 8).max(1) as usize,
+        }
+    }
+}
+
+pub fn spawn_scheduler(app_state: Arc<AppState>) {
+    let Some(period) = app_state.link_checker.interval else {
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick fires at once; skip it so restarts don't each run a check.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            match run(&app_state).await {
+                Ok((checked, broken)) => {
+                    println!("🔗 Checked {} links, {} broken", checked, broken)
+                }
+                Err(e) => println!("⚠️ Link check failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Checks every link in every published post once, and records the outcome
+/// per post. Returns how many links were checked and how many are broken.
+pub async fn run(app_state: &AppState) -> Result<(usize, usize), MyError> {
+    let checker = &app_state.link_checker;
+    let posts = app_state
+        .db
+        .published_post_links(MAX_LINKS_PER_POST)
+        .await?;
+
+    // A link in several posts is only fetched once.
+    let mut urls: Vec<String> = posts
+        .iter()
+        .flat_map(|(_, links)| links.iter().cloned())
+        .collect();
+    urls.sort();
+    urls.dedup();
+
+    let permits = Arc::new(Semaphore::new(checker.concurrency));
+    let mut tasks = JoinSet::new();
+    for url in urls {
+        let http = checker.http.clone();
+        let permits = permits.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let status = check(&http, &url).await;
+            (url, status)
+        });
+    }
+
+    let mut results: HashMap<String, LinkStatus> = HashMap::new();
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok((url, status)) = joined {
+            results.insert(url, status);
+        }
+    }
+
+    let broken = results.values().filter(|status| status.is_broken()).count();
+    app_state.db.record_link_checks(&posts, &results).await?;
+    Ok((results.len(), broken))
+}
+
+/// `HEAD` first, then `GET` for servers that don't do `HEAD`.
+async fn check(http: &HttpClient, url: &str) -> LinkStatus {
+    let mut result = http.probe(Method::HEAD, url).await;
+    if matches!(result, Ok(status) if status == StatusCode::METHOD_NOT_ALLOWED
+        || status == StatusCode::NOT_IMPLEMENTED
+        || status == StatusCode::FORBIDDEN)
+    {
+        result = http.probe(Method::GET, url).await;
+    }
+    match result {
+        Ok(status) => LinkStatus {
+            status: Some(status.as_u16()),
+            error: None,
+        },
+        Err(e) => LinkStatus {
+            status: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// The http(s) links in `content`, each once, in the order they appear.
+pub fn extract_links(content: &str) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("http") {
+        let candidate = &rest[start..];
+        if !(candidate.starts_with("https://") || candidate.starts_with("http://")) {
+            rest = &candidate[4..];
+            continue;
+        }
+        // Stop where Markdown or HTML around the link begins.
+        let end = candidate
+            .find(|c: char| {
+                c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\'' | ')' | ']' | '`')
+            })
+            .unwrap_or(candidate.len());
+        let link = candidate[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if !link.ends_with("://") && !links.iter().any(|known| known == link) {
+            links.push(link.to_owned());
+        }
+        rest = &candidate[end..];
+    }
+    links
+}
@@ -1,44 +1,222 @@
+mod abuse;
+mod access;
+mod auth;
+mod backup;
+mod contact;
+mod custom_fields;
+mod dates;
 mod db;
+mod deadline;
+mod diff;
+mod embed;
+mod encoding;
+mod environment;
 mod error;
+mod export;
+mod extract;
+mod feed;
+mod fingerprint;
 mod handler;
+mod hypermedia;
+mod limits;
+mod linkcheck;
+mod locale;
+mod mention;
+mod metering;
+mod migrations;
 mod model;
+mod notify;
+mod og;
+mod preview;
+mod quality;
+mod quota;
+mod rate;
+mod render;
 mod response;
 mod route;
+mod sandbox;
 mod schema;
+mod scope;
+mod semantic;
+mod suggest;
+mod toc;
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::http::{
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-    HeaderValue, Method,
+use abuse::AbuseGuard;
+use auth::AuthVerifier;
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+        HeaderName, HeaderValue, Method,
+    },
+    middleware,
 };
+use backup::BackupConfig;
+use contact::ContactService;
 use db::DB;
+use deadline::DeadlineReminders;
 use dotenv::dotenv;
+use embed::EmbedResolver;
 use error::MyError;
+use export::ExportConfig;
+use feed::FeedCache;
+use limits::RequestLimits;
+use linkcheck::LinkChecker;
+use metering::Meter;
+use notify::Notifier;
+use og::OgImages;
+use org_sog_core::bot::{BotCheck, BotDefense};
+use org_sog_core::client_ip::TrustedProxies;
+use org_sog_core::credentials;
+use org_sog_core::geo::Geo;
+use org_sog_core::i18n;
+use org_sog_core::links::LinkBuilder;
+use org_sog_core::mail::Mailer;
+use org_sog_core::page::PageLimits;
+use org_sog_core::plan::Plans;
+use preview::PreviewSigner;
+use rate::ApiRateLimiter;
+use render::Renderer;
 use route::create_router;
-use tower_http::cors::CorsLayer;
+use semantic::SemanticSearch;
+use suggest::Suggester;
+use tower_http::{cors::CorsLayer, timeout::TimeoutLayer};
 
 pub struct AppState {
     db: DB,
+    auth: AuthVerifier,
+    contact: ContactService,
+    backups: BackupConfig,
+    exports: ExportConfig,
+    og_images: OgImages,
+    embeds: EmbedResolver,
+    link_checker: LinkChecker,
+    deadlines: DeadlineReminders,
+    renderer: Renderer,
+    suggester: Suggester,
+    semantic: SemanticSearch,
+    feeds: FeedCache,
+    paging: PageLimits,
+    links: LinkBuilder,
+    plans: Plans,
+    rate: ApiRateLimiter,
+    meter: Meter,
+    previews: PreviewSigner,
+    notify: Notifier,
+    abuse: AbuseGuard,
+    proxies: TrustedProxies,
+    geo: Geo,
+    bots: BotDefense,
 }
 
+/// Forms run through bot checks, see [`BotDefense`].
+pub const FORM_COMMENT: &str = "comment";
+pub const FORM_CONTACT: &str = "contact";
+
 #[tokio::main]
 async fn main() -> Result<(), MyError> {
     dotenv().ok();
+    i18n::init();
+    credentials::init();
 
     let db = DB::init().await?;
 
+    // `org-sog-blog env ...` exports, compares or applies configuration instead of serving.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("env") {
+        return environment::run_cli(&db, &args[1..]).await;
+    }
+
+    let limits = RequestLimits::init();
+    let mailer = Mailer::init()?;
+
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:8001".parse::<HeaderValue>().unwrap())
-        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
         .allow_credentials(true)
-        .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
+        .allow_headers([
+            AUTHORIZATION,
+            ACCEPT,
+            CONTENT_TYPE,
+            HeaderName::from_static(extract::API_KEY_HEADER),
+            HeaderName::from_static(extract::POST_ACCESS_HEADER),
+            HeaderName::from_static(sandbox::SANDBOX_HEADER),
+        ]);
+
+    let plans = Plans::init();
+    let app_state = Arc::new(AppState {
+        db: db.clone(),
+        auth: AuthVerifier::init(),
+        contact: ContactService::init(mailer),
+        backups: BackupConfig::init(),
+        exports: ExportConfig::init(),
+        og_images: OgImages::init(),
+        embeds: EmbedResolver::init(),
+        link_checker: LinkChecker::init(),
+        deadlines: DeadlineReminders::init(),
+        renderer: Renderer::init(),
+        suggester: Suggester::init(),
+        semantic: SemanticSearch::init(),
+        feeds: FeedCache::init(),
+        paging: PageLimits::init(),
+        links: hypermedia::links(),
+        rate: ApiRateLimiter::init(plans.clone()),
+        plans,
+        meter: Meter::init(),
+        previews: PreviewSigner::init(),
+        notify: Notifier::init(),
+        abuse: AbuseGuard::init(),
+        proxies: TrustedProxies::init(),
+        geo: Geo::init(),
+        bots: BotDefense::init(&[
+            (FORM_COMMENT, &[BotCheck::Honeypot, BotCheck::Captcha]),
+            (FORM_CONTACT, &[BotCheck::Honeypot, BotCheck::Captcha]),
+        ]),
+    });
+    backup::spawn_scheduler(app_state.clone());
+    metering::spawn_flusher(app_state.clone());
+    linkcheck::spawn_scheduler(app_state.clone());
+    deadline::spawn_scheduler(app_state.clone());
+    semantic::spawn_indexer(app_state.clone());
+    app_state.db.migrations.spawn();
 
-    let app = create_router(Arc::new(AppState { db: db.clone() })).layer(cors);
+    let app = create_router(app_state.clone(), &limits)
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            hypermedia::wrap,
+        ))
+        .layer(middleware::from_fn(encoding::negotiate))
+        .layer(middleware::from_fn(dates::negotiate))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            metering::track,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            abuse::guard,
+        ))
+        .layer(middleware::from_fn_with_state(
+            app_state,
+            extract::resolve_client_ip,
+        ))
+        .layer(DefaultBodyLimit::max(limits.json_body))
+        .layer(TimeoutLayer::new(limits.timeout))
+        .layer(middleware::from_fn(limits::envelope_errors))
+        .layer(middleware::from_fn(locale::localize))
+        .layer(middleware::from_fn(extract::propagate_trace))
+        .layer(cors);
 
     println!("🚀 Blog API started successfully");
     axum::Server::bind(&"0.0.0.0:8001".parse().unwrap())
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 
@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+/// Most users a single comment can mention; anything past this is left as text.
+pub const MAX_MENTIONS: usize = 10;
+
+/// The distinct `@name` mentions in `text`, in order of first appearance.
+///
+/// A mention starts at an `@` that doesn't follow a word character, so email
+/// addresses aren't picked up, and runs over letters, digits, `_`, `-` and `.`;
+/// a trailing `.` is taken to end the sentence instead.
+pub fn parse(text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut names = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((at, c)) = chars.next() {
+        let starts_mention = c == '@' && !previous.is_some_and(is_name_char);
+        previous = Some(c);
+        if !starts_mention {
+            continue;
+        }
+
+        let start = at + 1;
+        let mut end = start;
+        while let Some(&(i, next)) = chars.peek() {
+            if !is_name_char(next) {
+                break;
+            }
+            end = i + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        let name = text[start..end].trim_end_matches('.');
+        if !name.is_empty() && seen.insert(name.to_owned()) {
+            names.push(name.to_owned());
+            if names.len() == MAX_MENTIONS {
+                break;
+            }
+        }
+    }
+
+    names
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
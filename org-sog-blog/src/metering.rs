@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex as SyncMutex},
+    time::Duration,
+};
+
+use axum::{
+    body::HttpBody,
+    extract::{MatchedPath, State},
+    http::{header::CONTENT_LENGTH, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, DurationRound, Utc};
+use tokio::sync::Mutex;
+
+use crate::AppState;
+
+/// What one request is counted under.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MeterKey {
+    pub user_id: String,
+    /// The API key used, if the caller didn't sign in with a token.
+    pub key_id: Option<String>,
+    pub method: String,
+    pub route: String,
+    pub status: u16,
+    /// Start of the hour the requests were made in.
+    pub hour: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MeterCounts {
+    pub calls: u64,
+    pub bytes: u64,
+}
+
+/// Filled in while a request is routed and handled: the route by
+/// [`tag_route`], the caller by the `AuthUser` extractor.
+#[derive(Debug, Clone, Default)]
+pub struct Metered(Arc<SyncMutex<MeteredSlot>>);
+
+#[derive(Debug, Default)]
+struct MeteredSlot {
+    route: Option<String>,
+    caller: Option<(String, Option<String>)>,
+}
+
+impl Metered {
+    pub fn caller(&self, user_id: &str, key_id: Option<&str>) {
+        if let Ok(mut slot) = self.0.lock() {
+            slot.caller = Some((user_id.to_owned(), key_id.map(str::to_owned)));
+        }
+    }
+}
+
+/// Counts API calls and bytes served per caller, route and status.
+///
+/// Counts are held in memory and written to the `metering` collection as one
+/// batch every `METERING_FLUSH_SECS` (default 10), or sooner once
+/// `METERING_BATCH_SIZE` (default 500) distinct counters are pending. A crash
+/// loses at most the pending batch.
+pub struct Meter {
+    pub flush_interval: Duration,
+    batch_size: usize,
+    pending: Mutex<HashMap<MeterKey, MeterCounts>>,
+}
+
+impl Meter {
+    pub fn init() -> Self {
+        let env_or = |name: &str, default: u64| match std::env::var(name) {
+            Ok(value) => value
+                .parse::<u64>()
+                .unwrap_or_else(|_| panic!("{} must be a whole number, got '{}'", name, value)),
+            Err(_) => default,
+        };
+
+        Self {
+            flush_interval: Duration::from_secs(env_or("METERING_FLUSH_SECS", 10)),
+            batch_size: env_or("METERING_BATCH_SIZE", 500) as usize,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Adds one call; returns whether the batch is full and should be flushed.
+    async fn record(&self, key: MeterKey, bytes: u64) -> bool {
+        let mut pending = self.pending.lock().await;
+        let counts = pending.entry(key).or_default();
+        counts.calls += 1;
+        counts.bytes += bytes;
+        pending.len() >= self.batch_size
+    }
+
+    /// How many counters are waiting to be flushed.
+    pub async fn backlog(&self) -> usize {
+        self.pending.lock().await.len()
+    }
+
+    async fn take(&self) -> HashMap<MeterKey, MeterCounts> {
+        std::mem::take(&mut *self.pending.lock().await)
+    }
+}
+
+/// Notes which route served the request. Applied with `route_layer`, where
+/// the matched path is known.
+pub async fn tag_route<B>(request: Request<B>, next: Next<B>) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned());
+    if let (Some(route), Some(metered)) = (route, request.extensions().get::<Metered>()) {
+        if let Ok(mut slot) = metered.0.lock() {
+            slot.route = Some(route);
+        }
+    }
+    next.run(request).await
+}
+
+/// Meters every authenticated request that reached a route. Sits outside
+/// the response encoders, so bytes are counted as they were served.
+pub async fn track<B>(
+    State(app_state): State<Arc<AppState>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let metered = Metered::default();
+    request.extensions_mut().insert(metered.clone());
+    let method = request.method().to_string();
+
+    let response = next.run(request).await;
+
+    let (route, caller) = match metered.0.lock() {
+        Ok(mut slot) => (slot.route.take(), slot.caller.take()),
+        Err(_) => return response,
+    };
+    let (Some(route), Some((user_id, key_id))) = (route, caller) else {
+        return response;
+    };
+
+    let bytes = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse().ok())
+        .or_else(|| response.body().size_hint().exact())
+        .unwrap_or(0);
+    let key = MeterKey {
+        user_id,
+        key_id,
+        method,
+        route,
+        status: response.status().as_u16(),
+        hour: Utc::now()
+            .duration_trunc(chrono::Duration::hours(1))
+            .unwrap_or_else(|_| Utc::now()),
+    };
+
+    if app_state.meter.record(key, bytes).await {
+        let app_state = app_state.clone();
+        tokio::spawn(async move { flush(&app_state).await });
+    }
+    response
+}
+
+pub fn spawn_flusher(app_state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(app_state.meter.flush_interval);
+        loop {
+            interval.tick().await;
+            flush(&app_state).await;
+        }
+    });
+}
+
+async fn flush(app_state: &AppState) {
+    let batch = app_state.meter.take().await;
+    if batch.is_empty() {
+        return;
+    }
+    if let Err(e) = app_state.db.record_metering(batch).await {
+        println!("⚠️ Could not write metering batch: {}", e);
+    }
+}
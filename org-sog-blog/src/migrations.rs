@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use mongodb::bson::{self, doc, Document};
+use mongodb::error::Result;
+use org_sog_core::migration_jobs::Migration;
+
+use crate::{quality, toc};
+
+/// The migrations this service runs, see
+/// [`MigrationRunner`](org_sog_core::migration_jobs::MigrationRunner).
+/// Names must never be reused, as a finished job is never run again.
+pub fn all(posts: &str) -> Vec<Arc<dyn Migration>> {
+    vec![Arc::new(PostOutline {
+        collection: posts.to_owned(),
+    })]
+}
+
+/// Gives posts saved before tables of contents and readability scores
+/// existed both, so they don't wait for their next edit.
+struct PostOutline {
+    collection: String,
+}
+
+impl Migration for PostOutline {
+    fn name(&self) -> &'static str {
+        "posts-toc-quality"
+    }
+
+    fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    fn filter(&self) -> Document {
+        doc! {"$or": [{"toc": null}, {"quality": null}]}
+    }
+
+    fn migrate(&self, document: &Document) -> Result<Option<Document>> {
+        let Ok(content) = document.get_str("content") else {
+            return Ok(None);
+        };
+        Ok(Some(doc! {"$set": {
+            "toc": bson::to_bson(&toc::extract(content))?,
+            "quality": bson::to_bson(&quality::measure(content))?,
+        }}))
+    }
+}
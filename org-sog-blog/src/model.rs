@@ -1,5 +1,11 @@
+use std::collections::BTreeMap;
+
 use chrono::prelude::*;
-use mongodb::bson::{self, oid::ObjectId};
+use mongodb::bson::{self, doc, oid::ObjectId, Document};
+use org_sog_core::filter::Field;
+use org_sog_core::geo::GeoLocation;
+use org_sog_core::ids::Id;
+use org_sog_core::repo::{Keyed, UniqueKey};
 use serde::{Deserialize, Serialize};
 
 #[allow(non_snake_case)]
@@ -8,12 +14,829 @@ pub struct BlogModel {
     #[serde(rename = "_id")]
     pub id: ObjectId,
     pub title: String,
+    /// Alternative titles under test; `title` itself is variant 0.
+    pub titleVariants: Option<Vec<String>>,
     pub summary: String,
+    /// Left out of list queries, which only show summaries.
+    #[serde(default)]
     pub content: String,
+    /// The content's headings, kept up to date with it.
+    pub toc: Option<Vec<TocEntryModel>>,
+    /// Readability of the content, measured when it's saved.
+    pub quality: Option<QualityModel>,
     pub category: Option<String>,
+    /// Root-first chain of categories ending in `category`.
+    pub categoryPath: Option<Vec<String>>,
     pub published: Option<bool>,
+    /// The owner; only they may delete the post or change who contributes to it.
+    pub author: Option<String>,
+    pub contributors: Option<Vec<ContributorModel>>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// Hash of the password that opens a protected post, from `access::hash_password`.
+    pub accessHash: Option<String>,
+    pub accessTokens: Option<Vec<AccessTokenModel>>,
+    pub commentSettings: Option<CommentSettingsModel>,
+    pub syndication: Option<SyndicationModel>,
+    /// When a draft is due to be published.
+    pub dueAt: Option<bson::DateTime>,
+    /// Who is to get the draft out; reminders go to them, or else to the author.
+    pub assignee: Option<String>,
+    /// The `dueAt` a reminder was last sent for; moving the deadline sends another.
+    pub remindedFor: Option<bson::DateTime>,
+    /// Values of the site's custom fields, by key; see [`FieldDefinitionModel`].
+    pub customFields: Option<Document>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+/// Fields post lists can be filtered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostField {
+    Author,
+    Category,
+    /// The category and each of its parents.
+    CategoryPath,
+    ContributorId,
+    CreatedAt,
+    Published,
+    Tags,
+    Visibility,
+}
+
+impl Field for PostField {
+    fn path(&self) -> &'static str {
+        match self {
+            PostField::Author => "author",
+            PostField::Category => "category",
+            PostField::CategoryPath => "categoryPath",
+            PostField::ContributorId => "contributors.userId",
+            PostField::CreatedAt => "createdAt",
+            PostField::Published => "published",
+            PostField::Tags => "tags",
+            PostField::Visibility => "visibility",
+        }
+    }
+}
+
+/// Whether a post is out, for filtering lists by it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PostStatus {
+    Published,
+    Draft,
+}
+
+/// Who may read a post. Unlisted posts are left out of listings but open to
+/// anyone with the link; protected ones also need a password or access token.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Public,
+    Unlisted,
+    Protected,
+}
+
+/// A token handed out to read one protected post; only its hash is kept.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccessTokenModel {
+    pub id: ObjectId,
+    pub hash: String,
+    pub label: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// A post's own comment settings; whatever is unset follows the site's
+/// [`CommentDefaults`].
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CommentSettingsModel {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Days after the post was created that it stops taking comments; 0 never locks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockAfterDays: Option<i64>,
+    /// Only signed-in readers may comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub membersOnly: Option<bool>,
+}
+
+/// A heading in a post, for a table of contents.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TocEntryModel {
+    pub text: String,
+    /// 1 for `#`, 2 for `##`, and so on.
+    pub level: u8,
+    /// The heading's fragment id, without the `#`.
+    pub anchor: String,
+}
+
+/// Readability metrics for a post's prose, see [`crate::quality`].
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct QualityModel {
+    pub words: i64,
+    pub sentences: i64,
+    pub averageSentenceLength: f64,
+    /// 0-100, higher reads more easily; 60-70 is plain English.
+    pub fleschReadingEase: f64,
+    /// Sentences that look to be in the passive voice.
+    pub passiveVoice: i64,
+    pub readingMinutes: i64,
+}
+
+/// Where a post was first published, for posts that are copies of someone
+/// else's, and how search engines should treat this copy.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyndicationModel {
+    /// The original the post was syndicated from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sourceUrl: Option<String>,
+    /// Overrides the canonical URL, which is otherwise `sourceUrl`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonicalUrl: Option<String>,
+    /// Asks search engines not to index the post at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub noindex: Option<bool>,
+}
+
+impl SyndicationModel {
+    /// The URL search engines should credit with the post, if it isn't this one.
+    pub fn canonical(&self) -> Option<&str> {
+        self.canonicalUrl.as_deref().or(self.sourceUrl.as_deref())
+    }
+}
+
+/// A reader's comment on a post.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub postId: ObjectId,
+    /// The comment this one replies to; unset for top-level comments.
+    pub parentId: Option<ObjectId>,
+    /// Ids of every comment above this one, root first, each followed by a
+    /// `/`; a reply's path is its parent's path plus the parent's id.
+    #[serde(default)]
+    pub path: String,
+    /// The commenter's user id; unset for anonymous comments.
+    pub author: Option<String>,
+    pub name: String,
+    pub body: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+    pub editedAt: Option<bson::DateTime>,
+    /// Earlier versions of `body`, oldest first, so the first is what was
+    /// originally posted.
+    #[serde(default)]
+    pub history: Vec<CommentEditModel>,
+    /// Users mentioned as `@name` when the comment was posted.
+    #[serde(default)]
+    pub mentions: Vec<MentionModel>,
+    /// How many users reacted with each emoji, kept in step with the
+    /// `comment_reactions` collection.
+    #[serde(default)]
+    pub reactions: BTreeMap<String, i64>,
+}
+
+/// `blocker` no longer wants to see `blocked`'s comments, nor have them
+/// comment on their posts.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub blocker: String,
+    pub blocked: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// A post a user saved to read later, filed under an optional folder.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookmarkModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: String,
+    pub postId: ObjectId,
+    pub folder: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// How far a user got reading a post, so they can pick it up again.
+/// Positions run from 0 at the top of the post to 1 at its end.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReadingProgressModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: String,
+    pub postId: ObjectId,
+    /// Where they were last.
+    pub position: f64,
+    /// The furthest they ever got.
+    pub furthest: f64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+/// What a user can follow.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FollowKind {
+    Author,
+    Tag,
+}
+
+impl FollowKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FollowKind::Author => "author",
+            FollowKind::Tag => "tag",
+        }
+    }
+}
+
+/// A user following an author or a tag, to hear of new posts.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FollowModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: String,
+    pub kind: FollowKind,
+    /// The author's user id, or the tag as posts store it.
+    pub target: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// One user's reaction to a comment; there is at most one per user and emoji.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReactionModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub commentId: ObjectId,
+    pub postId: ObjectId,
+    pub userId: String,
+    pub emoji: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MentionModel {
+    pub userId: String,
+    pub name: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentEditModel {
+    pub body: String,
+    /// When this version was edited away.
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub replacedAt: DateTime<Utc>,
+}
+
+/// Snapshot of a post as it stood after one create or edit.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevisionModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub postId: ObjectId,
+    /// Counts up from 1 per post.
+    pub number: i64,
+    pub title: String,
+    pub summary: String,
+    pub content: String,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub published: bool,
+    pub editedBy: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// Autosaved edits one user has in progress on a post, kept apart from the
+/// post itself until they are saved through the regular edit endpoint.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DraftModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub postId: ObjectId,
+    pub userId: String,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    /// Bumped only when a save follows a pause in editing, not on every keystroke batch.
+    pub version: i64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContributorRole {
+    Author,
+    Editor,
+    Reviewer,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContributorModel {
+    pub userId: String,
+    pub role: ContributorRole,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CategoryModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+    pub parent: Option<String>,
+    /// Names from the root category down to this one.
+    pub path: Vec<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum StatKind {
+    Tag,
+    Category,
+}
+
+impl StatKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatKind::Tag => "tag",
+            StatKind::Category => "category",
+        }
+    }
+}
+
+/// Number of published posts carrying one tag or category.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TagStatModel {
+    pub kind: StatKind,
+    pub name: String,
+    pub count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnalyticsKind {
+    View,
+    Read,
+    Impression,
+    Click,
+}
+
+impl AnalyticsKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnalyticsKind::View => "view",
+            AnalyticsKind::Read => "read",
+            AnalyticsKind::Impression => "impression",
+            AnalyticsKind::Click => "click",
+        }
+    }
+}
+
+/// One reader interaction, stored in the `analytics` time-series collection.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnalyticsEventModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub postId: ObjectId,
+    #[serde(rename = "type")]
+    pub kind: AnalyticsKind,
+    /// Pseudonymous id chosen by the client, used only to count unique readers.
+    pub visitorId: String,
+    pub referrer: Option<String>,
+    /// Fraction of the post scrolled through, for `read` events.
+    pub depth: Option<f64>,
+    /// Title variant shown, for `impression` and `click` events.
+    pub variant: Option<i32>,
+    /// Where the reader was, when geo-IP is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo: Option<GeoLocation>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub occurredAt: DateTime<Utc>,
+}
+
+/// Calls one caller made to one route in one hour, as written by a single
+/// metering flush. Flushes within the same hour each add a document, so
+/// reports sum over them.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MeteringModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub userId: String,
+    pub keyId: Option<String>,
+    pub method: String,
+    pub route: String,
+    pub status: i32,
+    pub calls: i64,
+    pub bytes: i64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub hour: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SocialLink {
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    Light,
+    Dark,
+    #[default]
+    Auto,
+}
+
+/// Presentation hints the front-end is free to ignore.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ThemeHints {
+    pub colorScheme: ColorScheme,
+    pub primaryColor: Option<String>,
+    pub accentColor: Option<String>,
+    pub fontFamily: Option<String>,
+}
+
+/// Site-wide configuration, stored as the only document in `settings`.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SettingsModel {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub siteTitle: String,
+    pub description: String,
+    pub postsPerPage: i64,
+    pub defaultCategory: Option<String>,
+    pub socialLinks: Vec<SocialLink>,
+    pub theme: ThemeHints,
+    #[serde(default)]
+    pub comments: CommentDefaults,
+    /// Authors may ask for suggested summaries and tags, see [`crate::suggest`].
+    #[serde(default)]
+    pub suggestions: bool,
+    pub updatedBy: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+/// How comments work on posts that don't say otherwise.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CommentDefaults {
+    pub enabled: bool,
+    /// Days after a post was created that it stops taking comments; unset or
+    /// 0 never locks.
+    pub lockAfterDays: Option<i64>,
+    pub membersOnly: bool,
+    /// Emoji readers may react to comments with.
+    #[serde(default = "default_reactions")]
+    pub reactions: Vec<String>,
+}
+
+impl Default for CommentDefaults {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lockAfterDays: None,
+            membersOnly: false,
+            reactions: default_reactions(),
+        }
+    }
+}
+
+fn default_reactions() -> Vec<String> {
+    ["👍", "❤️", "😂", "🎉", "😮", "😢"]
+        .into_iter()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PageStatus {
+    #[default]
+    Draft,
+    Published,
+}
+
+impl PageStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PageStatus::Draft => "draft",
+            PageStatus::Published => "published",
+        }
+    }
+}
+
+/// A standalone page such as "about" or "contact", addressed by its slug.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PageModel {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub slug: String,
+    pub title: String,
+    pub content: String,
+    pub status: PageStatus,
+    /// Position in the navigation menu; pages without one are left out of it.
+    pub navOrder: Option<i64>,
+    /// Menu text, when it should differ from `title`.
+    pub navLabel: Option<String>,
+    pub author: Option<String>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub createdAt: DateTime<Utc>,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     pub updatedAt: DateTime<Utc>,
 }
+
+impl Keyed for PageModel {
+    const UNIQUE_KEYS: &'static [&'static [&'static str]] = &[PageSlug::FIELDS];
+}
+
+/// Looks a page up by its slug, as pages are addressed.
+#[derive(Debug)]
+pub enum PageSlug {}
+
+impl UniqueKey for PageSlug {
+    type Model = PageModel;
+    type Value<'a> = &'a str;
+
+    const FIELDS: &'static [&'static str] = &["slug"];
+
+    fn filter(slug: &str) -> Document {
+        doc! {"slug": slug}
+    }
+}
+
+/// Fields page lists can be filtered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageField {
+    NavOrder,
+    Status,
+}
+
+impl Field for PageField {
+    fn path(&self) -> &'static str {
+        match self {
+            PageField::NavOrder => "navOrder",
+            PageField::Status => "status",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContactStatus {
+    #[default]
+    New,
+    Read,
+    Archived,
+}
+
+impl ContactStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContactStatus::New => "new",
+            ContactStatus::Read => "read",
+            ContactStatus::Archived => "archived",
+        }
+    }
+}
+
+/// The last check of one link in a published post.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkCheckModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub postId: ObjectId,
+    pub url: String,
+    pub broken: bool,
+    /// The status the link answered with; none when it couldn't be reached.
+    pub statusCode: Option<i32>,
+    pub error: Option<String>,
+    /// Checks in a row that found the link broken.
+    pub failures: i64,
+    pub brokenSince: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub checkedAt: DateTime<Utc>,
+}
+
+/// A published post's vector from an embedding model, see [`crate::semantic`].
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmbeddingModel {
+    /// The post's id; a post has one vector at a time.
+    #[serde(rename = "_id")]
+    pub postId: ObjectId,
+    pub model: String,
+    pub vector: Vec<f32>,
+    /// The post's `updatedAt` when it was embedded, to tell when it's stale.
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub sourceUpdatedAt: DateTime<Utc>,
+}
+
+/// A message left through the contact form.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContactMessageModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+    pub email: String,
+    pub subject: Option<String>,
+    pub message: String,
+    pub status: ContactStatus,
+    pub ip: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IpRuleKind {
+    Allow,
+    Deny,
+}
+
+/// An address range the abuse guard always lets through or turns away.
+/// Automatic bans are deny rules that expire.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IpRuleModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub kind: IpRuleKind,
+    /// Normalized CIDR, e.g. `203.0.113.0/24`.
+    pub cidr: String,
+    pub reason: Option<String>,
+    /// Set on bans the rate limiter triggered.
+    #[serde(default)]
+    pub automatic: bool,
+    /// The admin who added the rule; unset on automatic bans.
+    pub createdBy: Option<String>,
+    /// Removed by MongoDB's TTL monitor some time after this passes.
+    pub expiresAt: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// Sends requests for a path that moved somewhere else, or marks it as gone.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RedirectModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub from: String,
+    /// Unset for 410 Gone.
+    pub to: Option<String>,
+    /// 301, 302 or 410.
+    pub status: u16,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityKind {
+    #[serde(rename = "post.published")]
+    PostPublished,
+    #[serde(rename = "post.deleted")]
+    PostDeleted,
+    #[serde(rename = "comment.posted")]
+    CommentPosted,
+    #[serde(rename = "contact.received")]
+    ContactReceived,
+}
+
+/// One line of the activity stream, kept in the capped `activity` collection
+/// until newer ones push it out.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ActivityModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub kind: ActivityKind,
+    /// The user who did it; unset for anonymous readers.
+    pub actor: Option<String>,
+    /// Id of the post, comment or message it concerns.
+    pub subject: String,
+    /// One line for people, e.g. `ada published "Hello"`.
+    pub summary: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// A named post filter an editor keeps to run again; unset fields don't narrow it.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedSearchModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub owner: String,
+    pub name: String,
+    pub status: Option<PostStatus>,
+    pub tag: Option<String>,
+    pub author: Option<String>,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub includeDescendants: bool,
+    /// Posts created at or after this.
+    pub from: Option<bson::DateTime>,
+    /// Posts created before this.
+    pub to: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Text,
+    Number,
+    Boolean,
+    /// Stored as a date; accepts RFC 3339 or `YYYY-MM-DD`.
+    Date,
+    /// One of `options`.
+    Select,
+}
+
+/// A custom field posts on this site can carry, checked whenever a post's
+/// custom fields are written.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FieldDefinitionModel {
+    /// Where the value lives under `customFields`, e.g. `location`.
+    #[serde(rename = "_id")]
+    pub key: String,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    #[serde(default)]
+    pub required: bool,
+    /// The choices of a `select` field.
+    pub options: Option<Vec<String>>,
+    /// Bounds of a `number` field.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Longest a `text` field may be, in characters.
+    pub maxLength: Option<i64>,
+    pub updatedBy: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+/// A starting point for new posts of a recurring kind.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TemplateModel {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub name: String,
+    /// The new post's title; `{date}`, `{year}`, `{month}` and `{week}` are
+    /// filled in when it's created.
+    pub titlePattern: String,
+    pub summary: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub createdBy: Option<String>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub createdAt: DateTime<Utc>,
+}
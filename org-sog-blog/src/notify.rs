@@ -0,0 +1,49 @@
+use mongodb::bson::oid::ObjectId;
+use org_sog_core::http::{HttpClient, HttpClientConfig, Method};
+use serde_json::Value;
+
+pub const COMMENT_MENTIONED: &str = "comment.mentioned";
+pub const POST_PUBLISHED: &str = "post.published";
+pub const DEADLINE_APPROACHING: &str = "post.deadline";
+
+/// Hands events meant for users to the notification service at `NOTIFY_URL`,
+/// which decides how and whether to alert them.
+///
+/// Delivery is best effort: whatever triggered an event has already happened,
+/// so a failed delivery is only logged.
+pub struct Notifier {
+    http: HttpClient,
+    url: Option<String>,
+}
+
+impl Notifier {
+    pub fn init() -> Self {
+        let url = std::env::var("NOTIFY_URL").ok();
+        if url.is_none() {
+            println!("⚠️ NOTIFY_URL not set, notification events will be dropped");
+        }
+
+        Self {
+            http: HttpClient::new("notify", HttpClientConfig::default()),
+            url,
+        }
+    }
+
+    /// Sends one event of type `kind`; `payload` must be a JSON object.
+    pub async fn publish(&self, kind: &str, mut payload: Value) {
+        let Some(url) = &self.url else {
+            return;
+        };
+
+        let id = ObjectId::new().to_hex();
+        payload["id"] = id.clone().into();
+        payload["type"] = kind.into();
+        if let Err(e) = self
+            .http
+            .send(Method::POST, url, |request| request.json(&payload))
+            .await
+        {
+            println!("⚠️ failed to deliver {} event {}: {}", kind, id, e);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::convert::Infallible;
+
+use embedded_graphics::{
+    mono_font::{iso_8859_1::FONT_10X20, MonoTextStyle},
+    pixelcolor::Rgb888,
+    prelude::*,
+    text::{Baseline, Text},
+};
+use org_sog_core::{
+    read::ReadFrom,
+    store::{ObjectStore, StoreError},
+};
+use ring::digest;
+
+use crate::{
+    db::Viewer,
+    error::MyError::{self, StorageError},
+    model::Visibility,
+    AppState,
+};
+
+type Result<T> = std::result::Result<T, MyError>;
+
+const KEY_PREFIX: &str = "og-";
+/// Bump when the layout changes, so cached cards are drawn again.
+const LAYOUT_VERSION: &str = "1";
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 630;
+const MARGIN: i32 = 80;
+/// The font's cell, before scaling.
+const GLYPH_WIDTH: i32 = 10;
+const GLYPH_HEIGHT: i32 = 20;
+const TITLE_SCALE: i32 = 3;
+const TITLE_LINES: usize = 4;
+const TEXT_SCALE: i32 = 2;
+
+const BACKGROUND: Rgb888 = Rgb888::new(0x1f, 0x29, 0x37);
+const ACCENT: Rgb888 = Rgb888::new(0x3b, 0x82, 0xf6);
+const TITLE: Rgb888 = Rgb888::new(0xf9, 0xfa, 0xfb);
+const MUTED: Rgb888 = Rgb888::new(0x9c, 0xa3, 0xaf);
+
+/// Where rendered social cards are kept, from `MEDIA_DIR`.
+pub struct OgImages {
+    store: ObjectStore,
+}
+
+impl OgImages {
+    pub fn init() -> Self {
+        let dir = std::env::var("MEDIA_DIR").unwrap_or_else(|_| "./media".to_string());
+        Self {
+            store: ObjectStore::new(dir),
+        }
+    }
+}
+
+/// A post's social card as PNG, and whether the post is protected.
+///
+/// Cards are drawn once per title, author and site title and then served from
+/// the media store; a renamed author shows up once the post is next edited.
+pub async fn card(app_state: &AppState, id: &str, viewer: &Viewer<'_>) -> Result<(Vec<u8>, bool)> {
+    let blog = app_state
+        .db
+        .get_blog(id, None, viewer, ReadFrom::Replica)
+        .await?
+        .data
+        .blog;
+    let site_title = app_state.db.settings(ReadFrom::Replica).await?.siteTitle;
+    let protected = blog.visibility == Visibility::Protected;
+
+    let fingerprint = digest::digest(
+        &digest::SHA256,
+        [
+            LAYOUT_VERSION,
+            &blog.title,
+            blog.author.as_deref().unwrap_or_default(),
+            &site_title,
+        ]
+        .join("\n")
+        .as_bytes(),
+    );
+    let key = format!(
+        "{}{}-{}.png",
+        KEY_PREFIX,
+        blog.id,
+        hex::encode(&fingerprint.as_ref()[..8])
+    );
+
+    let store = &app_state.og_images.store;
+    match store.get(&key).await {
+        Ok(png) => return Ok((png, protected)),
+        Err(StoreError::NotFound(_)) => {}
+        Err(e) => return Err(StorageError(e.to_string())),
+    }
+
+    // The card is still worth having without a byline if auth is unreachable.
+    let author = match &blog.author {
+        Some(author) => app_state
+            .auth
+            .resolve_ids(&[author.to_owned()])
+            .await
+            .ok()
+            .and_then(|users| users.into_iter().next())
+            .map(|user| user.name),
+        None => None,
+    };
+
+    let title = blog.title;
+    let png = tokio::task::spawn_blocking(move || render(&title, author.as_deref(), &site_title))
+        .await
+        .map_err(|e| StorageError(e.to_string()))??;
+
+    store
+        .put(&key, &png)
+        .await
+        .map_err(|e| StorageError(e.to_string()))?;
+    // Cards for the post's earlier titles won't be asked for again.
+    let stale = store
+        .list(&format!("{}{}-", KEY_PREFIX, blog.id))
+        .await
+        .map_err(|e| StorageError(e.to_string()))?;
+    for object in stale.iter().filter(|object| object.key != key) {
+        if let Err(e) = store.delete(&object.key).await {
+            println!("⚠️ Could not remove stale card {}: {}", object.key, e);
+        }
+    }
+
+    Ok((png, protected))
+}
+
+/// An RGB image that text is drawn onto at a whole-number scale.
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        let [r, g, b] = [BACKGROUND.r(), BACKGROUND.g(), BACKGROUND.b()];
+        Self {
+            pixels: [r, g, b].repeat((WIDTH * HEIGHT) as usize),
+        }
+    }
+
+    fn fill(&mut self, x: i32, y: i32, width: i32, height: i32, color: Rgb888) {
+        for py in y.max(0)..(y + height).min(HEIGHT as i32) {
+            for px in x.max(0)..(x + width).min(WIDTH as i32) {
+                let i = (py as usize * WIDTH as usize + px as usize) * 3;
+                self.pixels[i..i + 3].copy_from_slice(&[color.r(), color.g(), color.b()]);
+            }
+        }
+    }
+
+    /// Draws one line of text with its top left corner at `(x, y)`.
+    fn text(&mut self, text: &str, x: i32, y: i32, scale: i32, color: Rgb888) {
+        let style = MonoTextStyle::new(&FONT_10X20, color);
+        let mut target = Scaled {
+            canvas: self,
+            scale,
+            origin: Point::new(x, y),
+        };
+        // Drawing onto memory can't fail.
+        let _ = Text::with_baseline(text, Point::zero(), style, Baseline::Top).draw(&mut target);
+    }
+}
+
+/// The canvas as the font sees it: every pixel drawn becomes a square.
+struct Scaled<'a> {
+    canvas: &'a mut Canvas,
+    scale: i32,
+    origin: Point,
+}
+
+impl OriginDimensions for Scaled<'_> {
+    fn size(&self) -> Size {
+        Size::new(WIDTH / self.scale as u32, HEIGHT / self.scale as u32)
+    }
+}
+
+impl DrawTarget for Scaled<'_> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> std::result::Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            self.canvas.fill(
+                self.origin.x + point.x * self.scale,
+                self.origin.y + point.y * self.scale,
+                self.scale,
+                self.scale,
+                color,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Draws a 1200×630 card: site title, the post's title and its author.
+///
+/// The font is compiled in and covers Latin-1; anything else is drawn as `?`.
+fn render(title: &str, author: Option<&str>, site_title: &str) -> Result<Vec<u8>> {
+    let mut canvas = Canvas::new();
+    canvas.fill(0, 0, WIDTH as i32, 12, ACCENT);
+
+    let text_columns = columns(TEXT_SCALE);
+    canvas.text(
+        &truncate(site_title, text_columns),
+        MARGIN,
+        MARGIN,
+        TEXT_SCALE,
+        MUTED,
+    );
+
+    let line_height = GLYPH_HEIGHT * TITLE_SCALE + 12;
+    for (i, line) in wrap(title, columns(TITLE_SCALE), TITLE_LINES)
+        .iter()
+        .enumerate()
+    {
+        canvas.text(
+            line,
+            MARGIN,
+            MARGIN + 90 + i as i32 * line_height,
+            TITLE_SCALE,
+            TITLE,
+        );
+    }
+
+    if let Some(author) = author {
+        canvas.text(
+            &truncate(&format!("by {}", author), text_columns),
+            MARGIN,
+            HEIGHT as i32 - MARGIN - GLYPH_HEIGHT * TEXT_SCALE,
+            TEXT_SCALE,
+            MUTED,
+        );
+    }
+
+    encode(&canvas.pixels).map_err(|e| StorageError(e.to_string()))
+}
+
+fn encode(pixels: &[u8]) -> std::result::Result<Vec<u8>, png::EncodingError> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, WIDTH, HEIGHT);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+    Ok(png)
+}
+
+/// Characters that fit across the card at `scale`.
+fn columns(scale: i32) -> usize {
+    ((WIDTH as i32 - 2 * MARGIN) / (GLYPH_WIDTH * scale)) as usize
+}
+
+fn truncate(text: &str, columns: usize) -> String {
+    if text.chars().count() <= columns {
+        return text.to_owned();
+    }
+    let kept: String = text.chars().take(columns.saturating_sub(3)).collect();
+    format!("{}...", kept.trim_end())
+}
+
+/// Breaks `text` into at most `max_lines` lines of `columns` characters,
+/// between words where it can, marking a cut-off end with `...`.
+fn wrap(text: &str, columns: usize, max_lines: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let mut word = word.to_owned();
+        loop {
+            let needed = current.chars().count() + usize::from(!current.is_empty());
+            if needed + word.chars().count() <= columns {
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(&word);
+                break;
+            }
+            if current.is_empty() {
+                // A word longer than a line is split wherever it runs out.
+                let head: String = word.chars().take(columns).collect();
+                word = word.chars().skip(columns).collect();
+                lines.push(head);
+            } else {
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        let last = lines.pop().unwrap_or_default();
+        lines.push(truncate(&format!("{} ...", last), columns));
+    }
+    lines
+}
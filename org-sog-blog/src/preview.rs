@@ -0,0 +1,93 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use ring::{hmac, rand::SystemRandom};
+
+use crate::error::MyError::{self, UnauthorizedError};
+
+/// What a valid preview token grants: a look at one post, as the user who
+/// shared it sees it in their draft.
+#[derive(Debug, Clone)]
+pub struct PreviewGrant {
+    pub post_id: String,
+    pub user_id: String,
+}
+
+/// Signs and checks preview links.
+///
+/// Tokens are `<post>.<expiry>.<user>.<hmac>`, signed with `PREVIEW_SECRET`
+/// and valid for `PREVIEW_TTL_SECS` (default 3 days). Nothing is stored, so a
+/// link can't be revoked early short of rotating the secret. Without a secret
+/// a random key is used and links stop working on restart.
+pub struct PreviewSigner {
+    key: hmac::Key,
+    pub ttl: Duration,
+    public_url: String,
+}
+
+impl PreviewSigner {
+    pub fn init() -> Self {
+        let key = match std::env::var("PREVIEW_SECRET") {
+            Ok(secret) => hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+            Err(_) => {
+                println!("⚠️ PREVIEW_SECRET not set, preview links won't survive a restart");
+                hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+                    .expect("Could not generate a preview signing key.")
+            }
+        };
+        let ttl_secs = std::env::var("PREVIEW_TTL_SECS")
+            .ok()
+            .map(|value| {
+                value
+                    .parse::<i64>()
+                    .expect("PREVIEW_TTL_SECS must be a number.")
+            })
+            .unwrap_or(3 * 24 * 60 * 60);
+        let public_url =
+            std::env::var("PUBLIC_URL").unwrap_or_else(|_| "http://localhost:8001".to_string());
+
+        Self {
+            key,
+            ttl: Duration::seconds(ttl_secs),
+            public_url: public_url.trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// A token for `user_id`'s view of `post_id`, and when it expires.
+    pub fn sign(&self, post_id: &str, user_id: &str) -> (String, DateTime<Utc>) {
+        let expires_at = Utc::now() + self.ttl;
+        let payload = format!("{}.{}.{}", post_id, expires_at.timestamp(), user_id);
+        let tag = hmac::sign(&self.key, payload.as_bytes());
+        (format!("{}.{}", payload, hex::encode(tag)), expires_at)
+    }
+
+    pub fn url(&self, token: &str) -> String {
+        format!("{}/api/blog/preview/{}", self.public_url, token)
+    }
+
+    pub fn verify(&self, token: &str) -> Result<PreviewGrant, MyError> {
+        let invalid = || UnauthorizedError("invalid preview link".to_string());
+
+        let (payload, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+        hmac::verify(&self.key, payload.as_bytes(), &signature).map_err(|_| invalid())?;
+
+        let mut parts = payload.splitn(3, '.');
+        let (Some(post_id), Some(expires_at), Some(user_id)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let expires_at = expires_at
+            .parse::<i64>()
+            .ok()
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+            .ok_or_else(invalid)?;
+        if expires_at <= Utc::now() {
+            return Err(UnauthorizedError("preview link has expired".to_string()));
+        }
+
+        Ok(PreviewGrant {
+            post_id: post_id.to_owned(),
+            user_id: user_id.to_owned(),
+        })
+    }
+}
@@ -0,0 +1,212 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::Serialize;
+
+use crate::{model::QualityModel, render};
+
+/// Search engines cut titles and descriptions off past about these lengths.
+const MAX_TITLE_LEN: usize = 60;
+const MAX_SUMMARY_LEN: usize = 160;
+const MIN_SUMMARY_LEN: usize = 50;
+const WORDS_PER_MINUTE: f64 = 230.0;
+
+const BE_VERBS: &[&str] = &["am", "is", "are", "was", "were", "be", "been", "being"];
+/// Irregular participles that don't end in -ed, for spotting the passive.
+const IRREGULAR_PARTICIPLES: &[&str] = &[
+    "built",
+    "bought",
+    "caught",
+    "chosen",
+    "done",
+    "drawn",
+    "driven",
+    "eaten",
+    "found",
+    "given",
+    "held",
+    "hidden",
+    "kept",
+    "known",
+    "laid",
+    "led",
+    "left",
+    "lost",
+    "made",
+    "meant",
+    "met",
+    "paid",
+    "put",
+    "read",
+    "run",
+    "said",
+    "seen",
+    "sent",
+    "set",
+    "shown",
+    "sold",
+    "spent",
+    "taken",
+    "taught",
+    "told",
+    "thought",
+    "understood",
+    "won",
+    "written",
+];
+
+/// Readability of a post's prose; code and markup are left out.
+///
+/// The Flesch reading ease and the passive voice count assume English.
+pub fn measure(content: &str) -> QualityModel {
+    let text = prose(content);
+    let words: Vec<&str> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '-'))
+        .filter(|word| word.chars().any(char::is_alphabetic))
+        .collect();
+    let sentences = text
+        .split(['.', '!', '?'])
+        .filter(|sentence| sentence.chars().any(char::is_alphabetic))
+        .count()
+        .max(1);
+    let syllables: usize = words.iter().map(|word| syllables(word)).sum();
+
+    let word_count = words.len();
+    let (average_sentence, flesch) = if word_count == 0 {
+        (0.0, 0.0)
+    } else {
+        let per_sentence = word_count as f64 / sentences as f64;
+        let per_word = syllables as f64 / word_count as f64;
+        (
+            per_sentence,
+            206.835 - 1.015 * per_sentence - 84.6 * per_word,
+        )
+    };
+
+    QualityModel {
+        words: word_count as i64,
+        sentences: if word_count == 0 { 0 } else { sentences as i64 },
+        averageSentenceLength: round(average_sentence),
+        fleschReadingEase: round(flesch),
+        passiveVoice: passive_voice(&words) as i64,
+        readingMinutes: (word_count as f64 / WORDS_PER_MINUTE).ceil() as i64,
+    }
+}
+
+/// Something a post's metadata could do better.
+#[derive(Serialize, Debug, Clone)]
+pub struct QualityWarning {
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Warnings about a post's title, summary and tags as search results and
+/// link previews would show them.
+pub fn lint(title: &str, summary: &str, tags: &[String]) -> Vec<QualityWarning> {
+    let mut warnings = Vec::new();
+    let title_len = title.chars().count();
+    if title_len > MAX_TITLE_LEN {
+        warnings.push(QualityWarning {
+            field: "title",
+            code: "too_long",
+            message: format!(
+                "title is {} characters, search results show about {}",
+                title_len, MAX_TITLE_LEN
+            ),
+        });
+    }
+
+    let summary_len = summary.trim().chars().count();
+    if summary_len == 0 {
+        warnings.push(QualityWarning {
+            field: "summary",
+            code: "missing",
+            message: "summary is empty, so previews fall back to the opening text".to_string(),
+        });
+    } else if summary_len < MIN_SUMMARY_LEN {
+        warnings.push(QualityWarning {
+            field: "summary",
+            code: "too_short",
+            message: format!(
+                "summary is {} characters, at least {} describe a post well",
+                summary_len, MIN_SUMMARY_LEN
+            ),
+        });
+    } else if summary_len > MAX_SUMMARY_LEN {
+        warnings.push(QualityWarning {
+            field: "summary",
+            code: "too_long",
+            message: format!(
+                "summary is {} characters, previews show about {}",
+                summary_len, MAX_SUMMARY_LEN
+            ),
+        });
+    }
+
+    if tags.is_empty() {
+        warnings.push(QualityWarning {
+            field: "tags",
+            code: "missing",
+            message: "post has no tags".to_string(),
+        });
+    }
+    warnings
+}
+
+/// The readable text of Markdown content, one block per line.
+fn prose(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut in_code = false;
+    for event in Parser::new_ext(content, render::options()) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code = true,
+            Event::End(Tag::CodeBlock(_)) => in_code = false,
+            Event::Text(part) if !in_code => text.push_str(&part),
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            // Headings and list items end without a full stop, but still end.
+            Event::End(Tag::Heading(..) | Tag::Item | Tag::TableCell) => text.push_str(".\n"),
+            Event::End(Tag::Paragraph) => text.push('\n'),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Vowel groups, less a silent final `e`; close enough for English.
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if word.ends_with('e') && !word.ends_with("le") && count > 1 {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+/// A form of "to be" followed, possibly after an adverb, by a participle.
+fn passive_voice(words: &[&str]) -> usize {
+    let is_participle = |word: &str| {
+        let word = word.to_lowercase();
+        (word.len() > 3 && word.ends_with("ed")) || IRREGULAR_PARTICIPLES.contains(&word.as_str())
+    };
+    words
+        .iter()
+        .enumerate()
+        .filter(|(_, word)| BE_VERBS.contains(&word.to_lowercase().as_str()))
+        .filter(|(i, _)| {
+            let next = words.get(i + 1).copied().unwrap_or_default();
+            let after = words.get(i + 2).copied().unwrap_or_default();
+            is_participle(next) || (next.to_lowercase().ends_with("ly") && is_participle(after))
+        })
+        .count()
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
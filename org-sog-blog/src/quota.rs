@@ -0,0 +1,29 @@
+/// How much each user may keep in the blog, from `QUOTA_MAX_DRAFTS` (default
+/// 50) and `QUOTA_MAX_CONTENT_BYTES` (default 10 MiB).
+///
+/// Content counts the posts a user owns and all of their drafts, measured in
+/// UTF-8 bytes. Edits that shrink content are always allowed, so a user over
+/// a lowered limit can still trim back under it.
+#[derive(Debug, Clone, Copy)]
+pub struct Quotas {
+    pub max_drafts: u64,
+    pub max_content_bytes: u64,
+}
+
+impl Quotas {
+    pub fn init() -> Self {
+        Self {
+            max_drafts: env_or("QUOTA_MAX_DRAFTS", 50),
+            max_content_bytes: env_or("QUOTA_MAX_CONTENT_BYTES", 10 * 1024 * 1024),
+        }
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a whole number, got '{}'", name, value)),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use org_sog_core::plan::{Plan, Plans};
+use tokio::sync::Mutex;
+
+use crate::error::MyError::{self, TooManyRequestsError};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Holds each caller to their plan's API requests per minute.
+///
+/// Like the contact form limit, counts are kept in memory, so each instance
+/// allows the full rate on its own.
+pub struct ApiRateLimiter {
+    plans: Plans,
+    requests: Mutex<HashMap<String, Vec<Instant>>>,
+}
+
+impl ApiRateLimiter {
+    pub fn init(plans: Plans) -> Self {
+        Self {
+            plans,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a request from `sub`, failing once it has made too many this minute.
+    pub async fn check(&self, sub: &str, plan: Plan) -> Result<(), MyError> {
+        let Some(max_per_window) = self.plans.limits(plan).api_requests_per_minute else {
+            return Ok(());
+        };
+
+        let mut requests = self.requests.lock().await;
+        requests.retain(|_, made| {
+            made.retain(|at| at.elapsed() < RATE_WINDOW);
+            !made.is_empty()
+        });
+
+        let made = requests.entry(sub.to_owned()).or_default();
+        if made.len() as u64 >= max_per_window {
+            let retry_after = RATE_WINDOW.saturating_sub(made[0].elapsed());
+            return Err(TooManyRequestsError(retry_after.as_secs().max(1)));
+        }
+
+        made.push(Instant::now());
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
+use syntect::{
+    highlighting::{Theme, ThemeSet},
+    html::{
+        css_for_theme_with_class_style, highlighted_html_for_string, ClassStyle,
+        ClassedHTMLGenerator,
+    },
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+use crate::toc::{self, Anchors};
+
+/// Highlighted code gets classes under this prefix, so they can't clash
+/// with a site's own styles.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// How fenced code is highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    /// `<span class="hl-…">`, styled by [`Renderer::stylesheet`].
+    Classes,
+    /// `<span style="…">`, needing no stylesheet.
+    Inline,
+    Off,
+}
+
+/// Renders post content from Markdown to HTML.
+///
+/// Fenced code is highlighted as `CODE_HIGHLIGHT` says (`classes`, the
+/// default, `inline` or `off`), in the `CODE_THEME` colours (default
+/// `InspiredGitHub`). Raw HTML in the Markdown is escaped rather than passed
+/// through, and links and images only keep http(s), mailto and relative
+/// targets, so the output is safe to insert as it is.
+pub struct Renderer {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+    highlight: Highlight,
+}
+
+impl Renderer {
+    pub fn init() -> Self {
+        let highlight = match std::env::var("CODE_HIGHLIGHT").ok().as_deref() {
+            None | Some("classes") => Highlight::Classes,
+            Some("inline") => Highlight::Inline,
+            Some("off") => Highlight::Off,
+            Some(other) => panic!("CODE_HIGHLIGHT {} is not supported.", other),
+        };
+        let name = std::env::var("CODE_THEME").unwrap_or_else(|_| "InspiredGitHub".to_string());
+        let mut themes = ThemeSet::load_defaults().themes;
+        let theme = themes.remove(&name).unwrap_or_else(|| {
+            panic!(
+                "CODE_THEME {} is not one of: {}",
+                name,
+                themes.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        });
+
+        Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme,
+            highlight,
+        }
+    }
+
+    /// Headings get the anchors their table of contents entries link to.
+    pub fn render(&self, markdown: &str) -> String {
+        let mut events = Vec::new();
+        let mut code: Option<(String, String)> = None;
+        let mut anchors = Anchors::default();
+        // Where the open heading's start tag goes once its text is known.
+        let mut heading: Option<(usize, String)> = None;
+        for event in Parser::new_ext(markdown, options()) {
+            match event {
+                Event::Start(Tag::Heading(..)) => {
+                    heading = Some((events.len(), String::new()));
+                    events.push(Event::Html(CowStr::Borrowed("")));
+                }
+                Event::End(Tag::Heading(level, _, _)) => {
+                    if let Some((start, text)) = heading.take() {
+                        let level = toc::depth(level);
+                        events[start] = Event::Html(
+                            format!("<h{} id=\"{}\">", level, anchors.next(&text)).into(),
+                        );
+                        events.push(Event::Html(format!("</h{}>\n", level).into()));
+                    }
+                }
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+                    if self.highlight != Highlight::Off =>
+                {
+                    let language = info.split_whitespace().next().unwrap_or_default();
+                    code = Some((language.to_owned(), String::new()));
+                }
+                Event::Text(text) if code.is_some() => {
+                    if let Some((_, body)) = code.as_mut() {
+                        body.push_str(&text);
+                    }
+                }
+                Event::End(Tag::CodeBlock(CodeBlockKind::Fenced(_))) if code.is_some() => {
+                    if let Some((language, body)) = code.take() {
+                        events.push(Event::Html(self.highlight_code(&language, &body).into()));
+                    }
+                }
+                Event::Html(raw) => events.push(Event::Text(raw)),
+                Event::Start(Tag::Link(kind, target, title)) => {
+                    events.push(Event::Start(Tag::Link(kind, safe_target(target), title)))
+                }
+                Event::Start(Tag::Image(kind, target, title)) => {
+                    events.push(Event::Start(Tag::Image(kind, safe_target(target), title)))
+                }
+                event => {
+                    if let (Some((_, text)), Event::Text(part) | Event::Code(part)) =
+                        (heading.as_mut(), &event)
+                    {
+                        text.push_str(part);
+                    }
+                    events.push(event)
+                }
+            }
+        }
+
+        let mut rendered = String::with_capacity(markdown.len() * 3 / 2);
+        html::push_html(&mut rendered, events.into_iter());
+        rendered
+    }
+
+    /// The stylesheet for [`Highlight::Classes`]; empty in the other modes.
+    pub fn stylesheet(&self) -> String {
+        if self.highlight != Highlight::Classes {
+            return String::new();
+        }
+        css_for_theme_with_class_style(&self.theme, CLASS_STYLE).unwrap_or_else(|e| {
+            println!("⚠️ Could not build the code highlighting stylesheet: {}", e);
+            String::new()
+        })
+    }
+
+    fn highlight_code(&self, language: &str, code: &str) -> String {
+        let syntax = self
+            .syntaxes
+            .find_syntax_by_token(language)
+            .unwrap_or_else(|| self.syntaxes.find_syntax_plain_text());
+
+        let highlighted = match self.highlight {
+            Highlight::Inline => {
+                highlighted_html_for_string(code, &self.syntaxes, syntax, &self.theme)
+            }
+            _ => {
+                let mut generator =
+                    ClassedHTMLGenerator::new_with_class_style(syntax, &self.syntaxes, CLASS_STYLE);
+                LinesWithEndings::from(code)
+                    .try_for_each(|line| generator.parse_html_for_line_which_includes_newline(line))
+                    .map(|_| {
+                        format!(
+                            "<pre class=\"hl-code\"><code>{}</code></pre>\n",
+                            generator.finalize()
+                        )
+                    })
+            }
+        };
+        // A grammar that chokes still leaves the code readable, just plain.
+        highlighted.unwrap_or_else(|_| {
+            let mut plain = String::new();
+            html::push_html(
+                &mut plain,
+                [
+                    Event::Start(Tag::CodeBlock(CodeBlockKind::Indented)),
+                    Event::Text(code.into()),
+                    Event::End(Tag::CodeBlock(CodeBlockKind::Indented)),
+                ]
+                .into_iter(),
+            );
+            plain
+        })
+    }
+}
+
+/// The Markdown extensions posts are written with.
+pub fn options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
+}
+
+/// Keeps link targets that can't run script: http(s), mailto and relative ones.
+fn safe_target(target: CowStr<'_>) -> CowStr<'_> {
+    let scheme = target
+        .split(['/', '?', '#'])
+        .next()
+        .and_then(|head| head.split_once(':'))
+        .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    match scheme.as_deref() {
+        None | Some("http") | Some("https") | Some("mailto") => target,
+        Some(_) => CowStr::Borrowed("#"),
+    }
+}
@@ -1,6 +1,24 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
+use org_sog_core::{
+    dates,
+    index_advisor::{IndexBuild, IndexSuggestion},
+    migration_jobs::JobState,
+    slow_query::PlanSummary,
+};
 use serde::Serialize;
 
+use crate::{
+    model::{
+        ActivityKind, CommentDefaults, CommentSettingsModel, ContactStatus, ContributorRole,
+        FieldType, FollowKind, IpRuleKind, PageStatus, PostStatus, QualityModel, SocialLink,
+        StatKind, SyndicationModel, ThemeHints, TocEntryModel, Visibility,
+    },
+    quality::QualityWarning,
+    schema::Granularity,
+};
+
 #[derive(Serialize)]
 pub struct GenericResponse {
     pub status: String,
@@ -12,28 +30,1315 @@ pub struct GenericResponse {
 pub struct BlogResponse {
     pub id: String,
     pub title: String,
+    pub titleVariants: Vec<String>,
+    /// Which title was served: 0 for `title`, otherwise `titleVariants[n - 1]`.
+    pub titleVariant: Option<usize>,
     pub summary: String,
     pub content: String,
+    /// The content as HTML, when asked for with `?render=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contentHtml: Option<String>,
+    pub toc: Vec<TocEntryModel>,
+    pub category: String,
+    pub categoryPath: Vec<String>,
+    pub published: bool,
+    pub author: Option<String>,
+    pub contributors: Vec<ContributorResponse>,
+    pub tags: Vec<String>,
+    pub visibility: Visibility,
+    /// What the post sets itself, for editing.
+    pub commentSettings: CommentSettingsModel,
+    /// The settings in effect, site defaults applied.
+    pub comments: CommentStatusResponse,
+    pub syndication: SyndicationModel,
+    /// Where search engines should look for the post, when not here.
+    pub canonicalUrl: Option<String>,
+    /// Expanded embeds, when asked for with `?embeds=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeds: Option<Vec<EmbedResponse>>,
+    /// Whether the signed-in reader bookmarked the post.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bookmarked: Option<bool>,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub dueAt: Option<DateTime<Utc>>,
+    pub assignee: Option<String>,
+    pub customFields: BTreeMap<String, serde_json::Value>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+/// A post as lists show it: everything but the content, which is most of
+/// what a post weighs and is left out of the query too.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct BlogSummaryResponse {
+    pub id: String,
+    pub title: String,
+    pub titleVariants: Vec<String>,
+    /// Which title was served: 0 for `title`, otherwise `titleVariants[n - 1]`.
+    pub titleVariant: Option<usize>,
+    pub summary: String,
+    pub toc: Vec<TocEntryModel>,
     pub category: String,
+    pub categoryPath: Vec<String>,
     pub published: bool,
+    pub author: Option<String>,
+    pub contributors: Vec<ContributorResponse>,
+    pub tags: Vec<String>,
+    pub visibility: Visibility,
+    pub commentSettings: CommentSettingsModel,
+    pub comments: CommentStatusResponse,
+    pub syndication: SyndicationModel,
+    pub canonicalUrl: Option<String>,
+    /// Whether the signed-in reader bookmarked the post.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bookmarked: Option<bool>,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub dueAt: Option<DateTime<Utc>>,
+    pub assignee: Option<String>,
+    pub customFields: BTreeMap<String, serde_json::Value>,
+    #[serde(serialize_with = "dates::serialize")]
     pub createdAt: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize")]
     pub updatedAt: DateTime<Utc>,
 }
 
+/// A post in either shape, for what applies to both.
+pub trait PostView {
+    fn post_id(&self) -> &str;
+    fn set_bookmarked(&mut self, bookmarked: bool);
+}
+
+impl PostView for BlogResponse {
+    fn post_id(&self) -> &str {
+        &self.id
+    }
+
+    fn set_bookmarked(&mut self, bookmarked: bool) {
+        self.bookmarked = Some(bookmarked);
+    }
+}
+
+impl PostView for BlogSummaryResponse {
+    fn post_id(&self) -> &str {
+        &self.id
+    }
+
+    fn set_bookmarked(&mut self, bookmarked: bool) {
+        self.bookmarked = Some(bookmarked);
+    }
+}
+
+/// A link in a post's content as the provider would embed it.
+#[derive(Serialize, Debug, Clone)]
+pub struct EmbedResponse {
+    pub url: String,
+    pub provider: &'static str,
+    pub title: Option<String>,
+    /// The provider's embed HTML, cleaned against an allowlist.
+    pub html: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct CommentStatusResponse {
+    pub enabled: bool,
+    pub membersOnly: bool,
+    /// Whether a comment posted now would be taken: enabled, published and not yet locked.
+    pub open: bool,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub locksAt: Option<DateTime<Utc>>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct PreviewLinkResponse {
+    pub status: &'static str,
+    pub url: String,
+    pub token: String,
+    #[serde(serialize_with = "dates::serialize")]
+    pub expiresAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct AccessTokenResponse {
+    pub id: String,
+    pub label: Option<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+}
+
+/// A newly created access token, the only time the token itself is shown.
+#[derive(Serialize, Debug)]
+pub struct NewAccessTokenResponse {
+    pub status: &'static str,
+    pub token: String,
+    pub data: AccessTokenResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AccessTokenListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub tokens: Vec<AccessTokenResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ContributorResponse {
+    pub userId: String,
+    pub role: ContributorRole,
+}
+
 #[derive(Serialize, Debug)]
 pub struct BlogData {
     pub blog: BlogResponse,
 }
 
+#[allow(non_snake_case)]
 #[derive(Serialize, Debug)]
 pub struct SingleBlogResponse {
     pub status: &'static str,
     pub data: BlogData,
+    /// Posts the new one looks near-identical to, when only warning about them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub possibleDuplicates: Vec<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct TitleCheckResponse {
+    pub status: &'static str,
+    pub title: String,
+    pub available: bool,
+    /// The post already using the title, ignoring case.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub takenBy: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct BlogListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub blogs: Vec<BlogSummaryResponse>,
+}
+
+/// A post found by semantic search. Scores run from 0 to 1; `score` blends
+/// the other two.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct SearchHitResponse {
+    pub score: f64,
+    pub semanticScore: f64,
+    pub keywordScore: f64,
+    pub blog: BlogSummaryResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SemanticSearchResponse {
+    pub status: &'static str,
+    pub query: String,
+    /// False when the query couldn't be embedded and only keywords counted.
+    pub semantic: bool,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub hits: Vec<SearchHitResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ChangesResponse {
+    pub status: &'static str,
+    /// Pass back as `since` to continue from here.
+    pub checkpoint: String,
+    /// More changes are waiting past the checkpoint.
+    pub hasMore: bool,
+    pub created: Vec<BlogResponse>,
+    pub updated: Vec<BlogResponse>,
+    pub deleted: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct QuotaUsage {
+    pub used: u64,
+    pub limit: u64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct UsageResponse {
+    pub status: &'static str,
+    pub userId: String,
+    pub drafts: QuotaUsage,
+    pub contentBytes: QuotaUsage,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct KeyMeteringResponse {
+    /// `null` for calls made with an access token rather than an API key.
+    pub keyId: Option<String>,
+    pub calls: i64,
+    pub bytes: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RouteMeteringResponse {
+    pub method: String,
+    pub route: String,
+    pub status: i32,
+    pub calls: i64,
+    pub bytes: i64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct MeteringResponse {
+    pub status: &'static str,
+    pub userId: String,
+    #[serde(serialize_with = "dates::serialize")]
+    pub from: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub to: DateTime<Utc>,
+    pub calls: i64,
+    pub bytes: i64,
+    pub keys: Vec<KeyMeteringResponse>,
+    pub routes: Vec<RouteMeteringResponse>,
+}
+
+/// One user's line in the billing rollup.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct UserMeteringResponse {
+    pub userId: String,
+    pub calls: i64,
+    /// Calls answered with a 4xx or 5xx status.
+    pub failedCalls: i64,
+    pub bytes: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MeteringRollupResponse {
+    pub status: &'static str,
+    #[serde(serialize_with = "dates::serialize")]
+    pub from: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub to: DateTime<Utc>,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub users: Vec<UserMeteringResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TagStatResponse {
+    pub kind: StatKind,
+    pub name: String,
+    pub count: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TagStatListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub stats: Vec<TagStatResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct CategoryResponse {
+    pub id: String,
+    pub name: String,
+    pub parent: Option<String>,
+    pub path: Vec<String>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryData {
+    pub category: CategoryResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleCategoryResponse {
+    pub status: &'static str,
+    pub data: CategoryData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub categories: Vec<CategoryResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct DraftResponse {
+    pub postId: String,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    pub version: i64,
+    pub createdAt: DateTime<Utc>,
+    pub updatedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DraftData {
+    pub draft: DraftResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleDraftResponse {
+    pub status: &'static str,
+    pub data: DraftData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct RevisionResponse {
+    pub number: i64,
+    pub title: String,
+    pub published: bool,
+    pub editedBy: Option<String>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RevisionListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub revisions: Vec<RevisionResponse>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiffChunk {
+    pub op: DiffOp,
+    pub value: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: serde_json::Value,
+    pub to: serde_json::Value,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RevisionDiff {
+    pub from: i64,
+    pub to: i64,
+    pub granularity: Granularity,
+    pub fields: Vec<FieldChange>,
+    pub content: Vec<DiffChunk>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RevisionDiffData {
+    pub diff: RevisionDiff,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RevisionDiffResponse {
+    pub status: &'static str,
+    pub data: RevisionDiffData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AnalyticsAcceptedResponse {
+    pub status: &'static str,
+    pub accepted: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DailyStatsResponse {
+    pub date: String,
+    pub views: i64,
+    pub uniques: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CountryStatsResponse {
+    pub country: String,
+    pub views: i64,
+    pub uniques: i64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct PostStatsResponse {
+    pub postId: String,
+    pub days: i64,
+    pub views: i64,
+    pub uniques: i64,
+    pub daily: Vec<DailyStatsResponse>,
+    /// Views by the readers' country, most first; empty without geo-IP.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub countries: Vec<CountryStatsResponse>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PostStatsData {
+    pub stats: PostStatsResponse,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SinglePostStatsResponse {
+    pub status: &'static str,
+    pub data: PostStatsData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TitleVariantStats {
+    pub variant: usize,
+    pub title: String,
+    pub impressions: i64,
+    pub clicks: i64,
+    /// Clicks per impression, 0 until the variant has been shown.
+    pub ctr: f64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct TitleTestResponse {
+    pub postId: String,
+    pub variants: Vec<TitleVariantStats>,
+    /// Best click-through rate, once every variant has enough impressions to compare.
+    pub leader: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TitleTestData {
+    pub test: TitleTestResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleTitleTestResponse {
+    pub status: &'static str,
+    pub data: TitleTestData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct SettingsResponse {
+    pub siteTitle: String,
+    pub description: String,
+    pub postsPerPage: i64,
+    pub defaultCategory: Option<String>,
+    pub socialLinks: Vec<SocialLink>,
+    pub theme: ThemeHints,
+    pub comments: CommentDefaults,
+    pub suggestions: bool,
+    /// Unset until the settings are saved for the first time.
+    pub updatedAt: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SettingsData {
+    pub settings: SettingsResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleSettingsResponse {
+    pub status: &'static str,
+    pub data: SettingsData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct PageResponse {
+    pub id: String,
+    pub slug: String,
+    pub title: String,
+    pub content: String,
+    pub status: PageStatus,
+    pub navOrder: Option<i64>,
+    pub navLabel: Option<String>,
+    pub author: Option<String>,
+    pub createdAt: DateTime<Utc>,
+    pub updatedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PageData {
+    pub page: PageResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SinglePageResponse {
+    pub status: &'static str,
+    pub data: PageData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PageListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub pages: Vec<PageResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct NavItemResponse {
+    pub slug: String,
+    pub label: String,
+    pub order: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct NavigationResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub items: Vec<NavItemResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct CommentResponse {
+    pub id: String,
+    pub postId: String,
+    pub parentId: Option<String>,
+    /// 0 for top-level comments.
+    pub depth: usize,
+    pub author: Option<String>,
+    pub name: String,
+    pub body: String,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+    /// Set once the comment has been edited.
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub editedAt: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mentions: Vec<MentionResponse>,
+    /// Reaction counts by emoji, leaving out emoji nobody reacted with.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub reactions: BTreeMap<String, i64>,
+    /// Direct replies, of which the first few are inlined in `replies`.
+    pub replyCount: u64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub replies: Vec<CommentResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct MentionResponse {
+    pub userId: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentData {
+    pub comment: CommentResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleCommentResponse {
+    pub status: &'static str,
+    pub data: CommentData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct BlockResponse {
+    pub userId: String,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BlockData {
+    pub block: BlockResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleBlockResponse {
+    pub status: &'static str,
+    pub data: BlockData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BlockListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub blocks: Vec<BlockResponse>,
+}
+
+/// A public author page. Fields the author hides are left out, and so are
+/// `recentPosts` and `stats` when they hide those.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct AuthorResponse {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub website: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatarUrl: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub joinedAt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recentPosts: Option<Vec<BlogSummaryResponse>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<AuthorStatsResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct AuthorStatsResponse {
+    /// Public posts only.
+    pub posts: u64,
+    pub followers: u64,
+    /// What they write about most, most used first.
+    pub topTags: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuthorData {
+    pub author: AuthorResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleAuthorResponse {
+    pub status: &'static str,
+    pub data: AuthorData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct BookmarkResponse {
+    pub postId: String,
+    pub folder: Option<String>,
+    pub labels: Vec<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+    /// The post, unless it has since been unpublished or hidden.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blog: Option<BlogSummaryResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BookmarkData {
+    pub bookmark: BookmarkResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleBookmarkResponse {
+    pub status: &'static str,
+    pub data: BookmarkData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BookmarkListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub bookmarks: Vec<BookmarkResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct HistoryEntryResponse {
+    pub postId: String,
+    pub position: f64,
+    pub furthest: f64,
+    /// Whether the reader ever got to the end.
+    pub finished: bool,
+    #[serde(serialize_with = "dates::serialize")]
+    pub updatedAt: DateTime<Utc>,
+    /// The post, unless it has since been unpublished or hidden.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blog: Option<BlogSummaryResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HistoryListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub history: Vec<HistoryEntryResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct FollowResponse {
+    pub kind: FollowKind,
+    pub target: String,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FollowData {
+    pub follow: FollowResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleFollowResponse {
+    pub status: &'static str,
+    pub data: FollowData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FollowListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub follows: Vec<FollowResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FollowerCountResponse {
+    pub status: &'static str,
+    pub kind: FollowKind,
+    pub target: String,
+    pub followers: u64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct CommentEditResponse {
+    pub body: String,
+    #[serde(serialize_with = "dates::serialize")]
+    pub replacedAt: DateTime<Utc>,
+}
+
+/// A comment as it is now, with what it said before each edit.
+#[derive(Serialize, Debug)]
+pub struct CommentHistoryData {
+    pub comment: CommentResponse,
+    /// The text as first posted.
+    pub original: String,
+    pub edits: Vec<CommentEditResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentHistoryResponse {
+    pub status: &'static str,
+    pub data: CommentHistoryData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub comments: Vec<CommentResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ContactMessageResponse {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub subject: Option<String>,
+    pub message: String,
+    pub status: ContactStatus,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ContactMessageData {
+    pub message: ContactMessageResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleContactMessageResponse {
+    pub status: &'static str,
+    pub data: ContactMessageData,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct LinkCheckResponse {
+    pub postId: String,
+    pub url: String,
+    pub broken: bool,
+    pub statusCode: Option<i32>,
+    pub error: Option<String>,
+    pub failures: i64,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub brokenSince: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub checkedAt: DateTime<Utc>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct LinkHealthResponse {
+    pub status: &'static str,
+    pub postId: String,
+    pub checked: usize,
+    pub broken: usize,
+    pub links: Vec<LinkCheckResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct QualityResponse {
+    pub status: &'static str,
+    pub postId: String,
+    pub quality: QualityModel,
+    /// Only with `?lint=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Vec<QualityWarning>>,
+}
+
+/// Suggested metadata for a post; nothing is saved until the author does.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct SuggestionResponse {
+    pub status: &'static str,
+    pub postId: String,
+    pub summary: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BrokenLinkListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub links: Vec<LinkCheckResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ContactMessageListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub messages: Vec<ContactMessageResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct IpRuleResponse {
+    pub id: String,
+    pub kind: IpRuleKind,
+    pub cidr: String,
+    pub reason: Option<String>,
+    pub automatic: bool,
+    pub createdBy: Option<String>,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub expiresAt: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IpRuleData {
+    pub rule: IpRuleResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleIpRuleResponse {
+    pub status: &'static str,
+    pub data: IpRuleData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IpRuleListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub rules: Vec<IpRuleResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct RedirectResponse {
+    pub id: String,
+    pub from: String,
+    pub to: Option<String>,
+    pub status: u16,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RedirectData {
+    pub redirect: RedirectResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleRedirectResponse {
+    pub status: &'static str,
+    pub data: RedirectData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RedirectListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub redirects: Vec<RedirectResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct BackupResponse {
+    pub key: String,
+    pub size: u64,
+    /// Only known for a backup that was just taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documents: Option<usize>,
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BackupData {
+    pub backup: BackupResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleBackupResponse {
+    pub status: &'static str,
+    pub data: BackupData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BackupListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub backups: Vec<BackupResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RestoredCollection {
+    pub name: String,
+    pub documents: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RestoreResponse {
+    pub status: &'static str,
+    pub database: String,
+    pub collections: Vec<RestoredCollection>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ConsistencyIssue {
+    pub kind: &'static str,
+    /// The post, or for counters the `kind:name` key, the issue was found on.
+    pub id: String,
+    pub detail: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ConsistencyReportResponse {
+    pub status: &'static str,
+    pub dryRun: bool,
+    pub checks: Vec<&'static str>,
+    pub results: usize,
+    pub repaired: usize,
+    pub issues: Vec<ConsistencyIssue>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UserCountsResponse {
+    pub total: u64,
+    /// Signed up within the last week.
+    pub new: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PostCountsResponse {
+    pub total: u64,
+    pub published: u64,
+    pub drafts: u64,
+    pub unlisted: u64,
+    pub protected: u64,
+}
+
+/// Comments go live as they are posted, so there is no moderation queue to
+/// count; these show how busy the threads are instead.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct CommentCountsResponse {
+    pub total: u64,
+    pub lastDay: u64,
+}
+
+/// Metered API calls over the current and previous hour.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ErrorRateResponse {
+    pub calls: i64,
+    pub serverErrors: i64,
+    /// `serverErrors / calls`; 0 when there were no calls.
+    pub rate: f64,
+}
+
+/// Work waiting to be done. A source that didn't answer is null.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct QueueBacklogResponse {
+    /// Auth events not yet delivered.
+    pub outbox: Option<u64>,
+    pub deadLetters: Option<u64>,
+    pub unreadContact: Option<u64>,
+    /// Usage counters not yet written to the database.
+    pub metering: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UnavailableSourceResponse {
+    pub source: &'static str,
+    pub error: String,
+}
+
+/// Key numbers across the blog and auth services. Each source is asked
+/// separately; one that fails or is too slow is null and listed under
+/// `unavailable`.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct AdminOverviewResponse {
+    pub status: &'static str,
+    #[serde(serialize_with = "dates::serialize")]
+    pub generatedAt: DateTime<Utc>,
+    pub users: Option<UserCountsResponse>,
+    pub posts: Option<PostCountsResponse>,
+    pub comments: Option<CommentCountsResponse>,
+    pub errors: Option<ErrorRateResponse>,
+    pub queues: QueueBacklogResponse,
+    pub unavailable: Vec<UnavailableSourceResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct SlowQueryResponse {
+    pub id: String,
+    pub operation: String,
+    pub collection: String,
+    pub durationMs: i64,
+    pub shape: serde_json::Value,
+    pub sort: Option<serde_json::Value>,
+    pub plan: Option<PlanSummary>,
+    pub traceId: Option<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SlowQueryListResponse {
+    pub status: &'static str,
+    pub page: i64,
+    pub limit: i64,
+    pub results: usize,
+    pub queries: Vec<SlowQueryResponse>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexAdviceResponse {
+    pub status: &'static str,
+    /// Slow queries logged since then were looked at.
+    #[serde(serialize_with = "dates::serialize")]
+    pub since: DateTime<Utc>,
+    pub results: usize,
+    pub suggestions: Vec<IndexSuggestion>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexBuildData {
+    pub build: IndexBuild,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleIndexBuildResponse {
+    pub status: &'static str,
+    pub data: IndexBuildData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IndexBuildListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub builds: Vec<IndexBuild>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct ActivityResponse {
+    pub id: String,
+    pub kind: ActivityKind,
+    pub actor: Option<String>,
+    pub subject: String,
+    pub summary: String,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ActivityListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    /// Pass as `after` to get what happens next.
+    pub cursor: Option<String>,
+    /// Oldest first.
+    pub activity: Vec<ActivityResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct SavedSearchResponse {
+    pub id: String,
+    pub name: String,
+    pub status: Option<PostStatus>,
+    pub tag: Option<String>,
+    pub author: Option<String>,
+    pub category: Option<String>,
+    pub includeDescendants: bool,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SavedSearchData {
+    pub search: SavedSearchResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleSavedSearchResponse {
+    pub status: &'static str,
+    pub data: SavedSearchData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SavedSearchListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub searches: Vec<SavedSearchResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct FieldDefinitionResponse {
+    pub key: String,
+    pub label: String,
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    pub required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maxLength: Option<i64>,
+    pub updatedBy: Option<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub updatedAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FieldDefinitionData {
+    pub field: FieldDefinitionResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleFieldDefinitionResponse {
+    pub status: &'static str,
+    pub data: FieldDefinitionData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FieldDefinitionListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub fields: Vec<FieldDefinitionResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct TemplateResponse {
+    pub id: String,
+    pub name: String,
+    pub titlePattern: String,
+    pub summary: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub createdBy: Option<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub createdAt: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TemplateData {
+    pub template: TemplateResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleTemplateResponse {
+    pub status: &'static str,
+    pub data: TemplateData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TemplateListResponse {
+    pub status: &'static str,
+    pub results: usize,
+    pub templates: Vec<TemplateResponse>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug)]
+pub struct MigrationJobResponse {
+    pub name: String,
+    pub collection: String,
+    pub state: JobState,
+    /// `_id` of the last document migrated.
+    pub checkpoint: Option<serde_json::Value>,
+    pub scanned: i64,
+    pub migrated: i64,
+    pub error: Option<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub startedAt: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub updatedAt: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub finishedAt: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MigrationJobData {
+    pub job: MigrationJobResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleMigrationJobResponse {
+    pub status: &'static str,
+    pub data: MigrationJobData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MigrationJobListResponse {
     pub status: &'static str,
     pub results: usize,
-    pub blogs: Vec<BlogResponse>,
+    pub jobs: Vec<MigrationJobResponse>,
 }
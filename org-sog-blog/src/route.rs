@@ -1,27 +1,232 @@
 use std::sync::Arc;
 
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
 use crate::{
     handler::{
-        blog_list_handler, create_blog_handler, delete_blog_handler, edit_blog_handler,
-        get_blog_handler,
+        access_token_list_handler, activity_handler, add_reaction_handler, admin_overview_handler,
+        analytics_handler, backup_list_handler, block_list_handler, block_user_handler,
+        blog_list_handler, bookmark_handler, bookmark_list_handler, bot_metrics_handler,
+        broken_links_handler, build_index_handler, category_list_handler, change_migration_handler,
+        changes_handler, check_title_handler, clear_history_handler, code_stylesheet_handler,
+        comment_history_handler, comment_list_handler, comment_replies_handler,
+        consistency_audit_handler, contact_handler, contact_list_handler,
+        create_access_token_handler, create_backup_handler, create_blog_handler,
+        create_category_handler, create_comment_handler, create_ip_rule_handler,
+        create_page_handler, create_preview_link_handler, create_redirect_handler,
+        create_saved_search_handler, create_template_handler, delete_blog_handler,
+        delete_field_definition_handler, delete_ip_rule_handler, delete_page_handler,
+        delete_redirect_handler, delete_saved_search_handler, delete_template_handler,
+        dependencies_handler, discard_draft_handler, edit_blog_handler, edit_comment_handler,
+        edit_page_handler, event_handler, feed_handler, field_definition_list_handler,
+        follow_handler, follow_list_handler, follower_count_handler, form_stamp_handler,
+        full_export_handler, get_author_handler, get_blog_handler, get_draft_handler,
+        get_page_handler, get_settings_handler, history_handler, index_advice_handler,
+        index_build_list_handler, ip_rule_list_handler, link_health_handler, metering_handler,
+        metering_rollup_handler, metrics_handler, migration_list_handler, navigation_handler,
+        og_image_handler, overdue_handler, page_list_handler, post_stats_handler, preview_handler,
+        progress_handler, put_field_definition_handler, quality_handler, rebuild_tag_stats_handler,
+        redirect_fallback_handler, redirect_list_handler, remove_bookmark_handler,
+        remove_reaction_handler, restore_handler, revision_diff_handler, revision_list_handler,
+        revoke_access_token_handler, save_draft_handler, saved_search_list_handler,
+        saved_search_results_handler, semantic_search_handler, slow_query_list_handler,
+        suggest_handler, tag_stats_handler, template_list_handler, title_test_handler,
+        unblock_user_handler, unfollow_handler, update_contact_handler, update_settings_handler,
+        usage_handler,
     },
-    AppState,
+    limits::RequestLimits,
+    metering, sandbox, AppState,
 };
 
-pub fn create_router(app_state: Arc<AppState>) -> Router {
+pub fn create_router(app_state: Arc<AppState>, limits: &RequestLimits) -> Router {
+    // Post bodies carry the full article, so they get the larger upload limit.
+    let content_limit = DefaultBodyLimit::max(limits.upload_body);
+
     Router::new()
-        .route("/api/blog/new", post(create_blog_handler))
+        .route("/api/healthcheck/dependencies", get(dependencies_handler))
+        .route("/api/metrics", get(metrics_handler))
+        .route(
+            "/api/blog/new",
+            post(create_blog_handler).layer(content_limit.clone()),
+        )
         .route("/api/blog", get(blog_list_handler))
+        .route("/api/blog/check-title", get(check_title_handler))
+        .route("/api/blog/changes", get(changes_handler))
+        .route("/api/blog/overdue", get(overdue_handler))
+        .route(
+            "/api/templates",
+            get(template_list_handler).post(create_template_handler),
+        )
+        .route("/api/templates/:id", delete(delete_template_handler))
+        .route(
+            "/api/searches",
+            get(saved_search_list_handler).post(create_saved_search_handler),
+        )
+        .route("/api/searches/:id", delete(delete_saved_search_handler))
+        .route(
+            "/api/searches/:id/results",
+            get(saved_search_results_handler),
+        )
+        .route("/api/blog/semantic-search", get(semantic_search_handler))
+        .route("/api/blog/preview/:token", get(preview_handler))
         .route(
             "/api/blog/:id",
             get(get_blog_handler)
                 .patch(edit_blog_handler)
+                .layer(content_limit.clone())
                 .delete(delete_blog_handler),
         )
+        .route("/api/blog/:id/og-image.png", get(og_image_handler))
+        .route(
+            "/api/blog/:id/preview-link",
+            post(create_preview_link_handler),
+        )
+        .route(
+            "/api/blog/:id/comments",
+            get(comment_list_handler).post(create_comment_handler),
+        )
+        .route("/api/comments/:id", patch(edit_comment_handler))
+        .route("/api/comments/:id/replies", get(comment_replies_handler))
+        .route("/api/comments/:id/reactions", post(add_reaction_handler))
+        .route(
+            "/api/comments/:id/reactions/:emoji",
+            delete(remove_reaction_handler),
+        )
+        .route(
+            "/api/blocks",
+            get(block_list_handler).post(block_user_handler),
+        )
+        .route("/api/blocks/:user_id", delete(unblock_user_handler))
+        .route(
+            "/api/blog/:id/bookmark",
+            post(bookmark_handler).delete(remove_bookmark_handler),
+        )
+        .route("/api/me/bookmarks", get(bookmark_list_handler))
+        .route("/api/blog/:id/progress", post(progress_handler))
+        .route(
+            "/api/me/history",
+            get(history_handler).delete(clear_history_handler),
+        )
+        .route("/api/authors/:slug", get(get_author_handler))
+        .route(
+            "/api/follows",
+            get(follow_list_handler).post(follow_handler),
+        )
+        .route("/api/follows/:kind/:target", delete(unfollow_handler))
+        .route(
+            "/api/follows/:kind/:target/followers",
+            get(follower_count_handler),
+        )
+        .route(
+            "/api/blog/:id/access-tokens",
+            get(access_token_list_handler).post(create_access_token_handler),
+        )
+        .route(
+            "/api/blog/:id/access-tokens/:token_id",
+            delete(revoke_access_token_handler),
+        )
+        .route("/api/blog/:id/stats", get(post_stats_handler))
+        .route("/api/blog/:id/title-test", get(title_test_handler))
+        .route("/api/blog/:id/link-health", get(link_health_handler))
+        .route("/api/blog/:id/quality", get(quality_handler))
+        .route("/api/blog/:id/suggest", post(suggest_handler))
+        .route("/api/broken-links", get(broken_links_handler))
+        .route("/api/blog/:id/revisions", get(revision_list_handler))
+        .route(
+            "/api/blog/:id/revisions/:from/diff/:to",
+            get(revision_diff_handler),
+        )
+        .route(
+            "/api/blog/:id/draft",
+            put(save_draft_handler)
+                .layer(content_limit.clone())
+                .get(get_draft_handler)
+                .delete(discard_draft_handler),
+        )
+        .route(
+            "/api/categories",
+            get(category_list_handler).post(create_category_handler),
+        )
+        .route(
+            "/api/pages",
+            get(page_list_handler)
+                .post(create_page_handler)
+                .layer(content_limit.clone()),
+        )
+        .route(
+            "/api/pages/:slug",
+            get(get_page_handler)
+                .patch(edit_page_handler)
+                .layer(content_limit)
+                .delete(delete_page_handler),
+        )
+        .route("/api/users/:id/usage", get(usage_handler))
+        .route("/api/usage", get(metering_handler))
+        .route("/api/navigation", get(navigation_handler))
+        .route("/api/feed", get(feed_handler))
+        .route("/api/code-highlight.css", get(code_stylesheet_handler))
+        .route(
+            "/api/redirects",
+            get(redirect_list_handler).post(create_redirect_handler),
+        )
+        .route("/api/redirects/:id", delete(delete_redirect_handler))
+        .route(
+            "/api/admin/ip-rules",
+            get(ip_rule_list_handler).post(create_ip_rule_handler),
+        )
+        .route("/api/admin/ip-rules/:id", delete(delete_ip_rule_handler))
+        .route("/api/forms/stamp", get(form_stamp_handler))
+        .route("/api/admin/bot-metrics", get(bot_metrics_handler))
+        .route(
+            "/api/contact",
+            get(contact_list_handler).post(contact_handler),
+        )
+        .route("/api/contact/:id", patch(update_contact_handler))
+        .route(
+            "/api/settings",
+            get(get_settings_handler).put(update_settings_handler),
+        )
+        .route("/api/tags", get(tag_stats_handler))
+        .route("/api/tags/rebuild", post(rebuild_tag_stats_handler))
+        .route("/api/analytics/events", post(analytics_handler))
+        .route("/api/export/full", get(full_export_handler))
+        .route(
+            "/api/admin/backups",
+            get(backup_list_handler).post(create_backup_handler),
+        )
+        .route("/api/admin/restore", post(restore_handler))
+        .route("/api/admin/consistency", post(consistency_audit_handler))
+        .route("/api/admin/usage", get(metering_rollup_handler))
+        .route("/api/admin/overview", get(admin_overview_handler))
+        .route("/api/admin/activity", get(activity_handler))
+        .route("/api/admin/fields", get(field_definition_list_handler))
+        .route(
+            "/api/admin/fields/:key",
+            put(put_field_definition_handler).delete(delete_field_definition_handler),
+        )
+        .route("/api/admin/slow-queries", get(slow_query_list_handler))
+        .route("/api/admin/index-advice", get(index_advice_handler))
+        .route(
+            "/api/admin/index-builds",
+            get(index_build_list_handler).post(build_index_handler),
+        )
+        .route("/api/admin/migrations", get(migration_list_handler))
+        .route(
+            "/api/admin/migrations/:name/:action",
+            post(change_migration_handler),
+        )
+        .route(
+            "/api/admin/comments/:id/history",
+            get(comment_history_handler),
+        )
+        .route("/api/events", post(event_handler))
+        .route_layer(middleware::from_fn(metering::tag_route))
+        .route_layer(middleware::from_fn(sandbox::isolate))
+        .fallback(redirect_fallback_handler)
         .with_state(app_state)
 }
@@ -0,0 +1,66 @@
+use axum::{
+    extract::MatchedPath,
+    http::{HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::error::MyError;
+
+/// Set to `true` to run a write in the sandbox: it is checked and carried
+/// out as usual, then rolled back instead of committed, and the response
+/// shows what it would have done. Sandboxed responses carry the header back.
+pub const SANDBOX_HEADER: &str = "x-sandbox";
+
+/// Writes that run in a single transaction, and so can be rolled back.
+/// Others are refused in the sandbox rather than half applied.
+const SANDBOXED_ROUTES: [(Method, &str); 3] = [
+    (Method::POST, "/api/blog/new"),
+    (Method::PATCH, "/api/blog/:id"),
+    (Method::DELETE, "/api/blog/:id"),
+];
+
+tokio::task_local! {
+    static SANDBOXED: ();
+}
+
+/// Whether the current request runs in the sandbox; side effects outside
+/// the database, such as notifications, are skipped when it does.
+pub fn active() -> bool {
+    SANDBOXED.try_with(|_| ()).is_ok()
+}
+
+/// Runs requests that ask for it in the sandbox. Applied with `route_layer`,
+/// where the matched path is known.
+pub async fn isolate<B>(request: Request<B>, next: Next<B>) -> Response {
+    let wanted = request
+        .headers()
+        .get(SANDBOX_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+    // Reads change nothing, so they run as they are.
+    if !wanted || request.method().is_safe() {
+        return next.run(request).await;
+    }
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_default();
+    let supported = SANDBOXED_ROUTES
+        .iter()
+        .any(|(method, path)| method == request.method() && *path == route);
+    if !supported {
+        let error: (StatusCode, Json<serde_json::Value>) =
+            MyError::SandboxUnsupportedError(format!("{} {}", request.method(), route)).into();
+        return error.into_response();
+    }
+
+    let mut response = SANDBOXED.scope((), next.run(request)).await;
+    response
+        .headers_mut()
+        .insert(SANDBOX_HEADER, HeaderValue::from_static("true"));
+    response
+}
@@ -1,9 +1,98 @@
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use org_sog_core::index_advisor::IndexKey;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::model::{
+    AnalyticsKind, CommentDefaults, CommentSettingsModel, ContactStatus, ContributorModel,
+    FieldType, FollowKind, IpRuleKind, PageStatus, PostStatus, SocialLink, StatKind,
+    SyndicationModel, ThemeHints, Visibility,
+};
 
 #[derive(Deserialize, Debug, Default)]
 pub struct FilterOptions {
-    pub page: Option<usize>,
-    pub limit: Option<usize>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    pub author: Option<String>,
+    pub category: Option<String>,
+    /// With `category`, also match posts filed under any of its subcategories.
+    pub include_descendants: Option<bool>,
+    pub status: Option<PostStatus>,
+    pub tag: Option<String>,
+    /// Posts created at or after this.
+    pub from: Option<DateTime<Utc>>,
+    /// Posts created before this.
+    pub to: Option<DateTime<Utc>>,
+    /// Serves each post's title variant for this visitor.
+    pub visitor: Option<String>,
+    /// `cf.` parameters by field key, which the query string extractor can't
+    /// collect itself.
+    #[serde(skip)]
+    pub custom_fields: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct FeedOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    /// The id the client sends analytics with, so what was read counts.
+    pub visitor: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SemanticSearchOptions {
+    pub q: Option<String>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct VariantOptions {
+    pub visitor: Option<String>,
+    /// Expands embeddable links in the content, see [`crate::embed`].
+    pub embeds: Option<bool>,
+    /// Adds the content rendered to HTML, see [`crate::render`].
+    pub render: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CheckTitleOptions {
+    pub title: String,
+    /// The post being edited, which may keep its own title.
+    pub exclude: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct StatsOptions {
+    pub kind: Option<StatKind>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateCategorySchema {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Line,
+    Word,
+}
+
+/// A password or access token for a protected post, for readers who can't
+/// send the `X-Post-Access` header.
+#[derive(Deserialize, Debug, Default)]
+pub struct AccessOptions {
+    pub access: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DiffOptions {
+    pub granularity: Option<Granularity>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -11,22 +100,53 @@ pub struct ParamOptions {
     pub id: String,
 }
 
+#[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CreateBlogSchema {
+    /// Title, summary and content may be left out when a template fills them.
+    #[serde(default)]
     pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub titleVariants: Option<Vec<String>>,
+    #[serde(default)]
     pub summary: String,
+    #[serde(default)]
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub category: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub published: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contributors: Option<Vec<ContributorModel>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commentSettings: Option<CommentSettingsModel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syndication: Option<SyndicationModel>,
+    /// Opens the post when it is protected. Stored hashed, never as given.
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    /// When the draft is due to be published.
+    #[serde(skip_serializing)]
+    pub dueAt: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assignee: Option<String>,
+    /// Values for the site's custom fields, checked against their definitions.
+    #[serde(skip_serializing)]
+    pub customFields: Option<BTreeMap<String, serde_json::Value>>,
 }
 
+#[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UpdateBlogSchema {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub titleVariants: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub summary: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
@@ -34,4 +154,433 @@ pub struct UpdateBlogSchema {
     pub category: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub published: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contributors: Option<Vec<ContributorModel>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commentSettings: Option<CommentSettingsModel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syndication: Option<SyndicationModel>,
+    /// Replaces the post's password; an empty one removes it.
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    /// `null` removes the deadline.
+    #[serde(default, deserialize_with = "nullable", skip_serializing)]
+    pub dueAt: Option<Option<DateTime<Utc>>>,
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub assignee: Option<Option<String>>,
+    /// Replaces all of the post's custom field values.
+    #[serde(skip_serializing)]
+    pub customFields: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct CreateAccessTokenSchema {
+    /// Who the token was given to, to tell tokens apart when revoking one.
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DraftSchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct AnalyticsEventSchema {
+    #[serde(rename = "type")]
+    pub kind: AnalyticsKind,
+    pub postId: String,
+    pub visitorId: String,
+    pub referrer: Option<String>,
+    pub depth: Option<f64>,
+    pub variant: Option<i32>,
+    pub occurredAt: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AnalyticsBatchSchema {
+    pub events: Vec<AnalyticsEventSchema>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct StatsWindowOptions {
+    pub days: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PageListOptions {
+    /// Admins only; everyone else sees published pages.
+    pub include_drafts: Option<bool>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct CreatePageSchema {
+    pub slug: String,
+    pub title: String,
+    pub content: String,
+    pub status: Option<PageStatus>,
+    pub navOrder: Option<i64>,
+    pub navLabel: Option<String>,
+}
+
+/// `navOrder` and `navLabel` can be sent as `null` to clear them.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdatePageSchema {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<PageStatus>,
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub navOrder: Option<Option<i64>>,
+    #[serde(
+        default,
+        deserialize_with = "nullable",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub navLabel: Option<Option<String>>,
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`).
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct ContactSchema {
+    pub name: String,
+    pub email: String,
+    pub subject: Option<String>,
+    pub message: String,
+    /// Honeypot; hidden from people, so anything in it came from a bot.
+    #[serde(default)]
+    pub website: String,
+    /// Handed out by `/api/forms/stamp` when the form was opened.
+    pub formStamp: Option<String>,
+    #[serde(alias = "captchaToken")]
+    pub turnstileToken: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct CreateCommentSchema {
+    /// The comment being replied to.
+    pub parentId: Option<String>,
+    /// Shown with the comment; signed-in readers default to their user id.
+    pub name: Option<String>,
+    pub body: String,
+    /// Bot checks only apply to readers who aren't signed in.
+    #[serde(default)]
+    pub website: String,
+    pub formStamp: Option<String>,
+    #[serde(alias = "captchaToken")]
+    pub turnstileToken: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EditCommentSchema {
+    pub body: String,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct BlockSchema {
+    pub userId: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct BookmarkSchema {
+    pub folder: Option<String>,
+    pub labels: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct BookmarkListOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    pub folder: Option<String>,
+    pub label: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ProgressSchema {
+    pub position: f64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HistoryOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    /// Only posts not yet read to the end, for "continue reading".
+    pub unfinished: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FollowSchema {
+    pub kind: FollowKind,
+    pub target: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReactionSchema {
+    pub emoji: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct CommentListOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    /// Replies to inline under each comment, 3 unless given.
+    pub replies: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ContactListOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    pub status: Option<ContactStatus>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct BrokenLinkOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct QualityOptions {
+    /// Adds warnings about the title, summary and tags, see [`crate::quality::lint`].
+    pub lint: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateContactSchema {
+    pub status: ContactStatus,
+}
+
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct CreateIpRuleSchema {
+    pub kind: IpRuleKind,
+    /// An address or CIDR range.
+    pub cidr: String,
+    pub reason: Option<String>,
+    /// Makes the rule temporary; unset rules last until deleted.
+    pub expiresInMinutes: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateRedirectSchema {
+    pub from: String,
+    pub to: Option<String>,
+    /// Defaults to 301.
+    pub status: Option<u16>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RestoreSchema {
+    pub archive: String,
+    /// Target database; restoring into a fresh one and switching over is the safe path.
+    pub database: String,
+    /// Required to restore over the database this service is running on.
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ChangesOptions {
+    /// An RFC 3339 timestamp, or the `checkpoint` from the previous sync.
+    pub since: String,
+    pub limit: Option<i64>,
+}
+
+/// A metering window. Usage is kept per hour, so bounds fall to the hour
+/// they are in; the window defaults to the current calendar month (UTC).
+#[derive(Deserialize, Debug, Default)]
+pub struct MeteringOptions {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct AuditOptions {
+    /// Fix what can be fixed; without it the audit only reports.
+    pub repair: Option<bool>,
+}
+
+/// Replaces the site settings wholesale.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SettingsSchema {
+    pub siteTitle: String,
+    #[serde(default)]
+    pub description: String,
+    pub postsPerPage: i64,
+    pub defaultCategory: Option<String>,
+    #[serde(default)]
+    pub socialLinks: Vec<SocialLink>,
+    #[serde(default)]
+    pub theme: ThemeHints,
+    #[serde(default)]
+    pub comments: CommentDefaults,
+    #[serde(default)]
+    pub suggestions: bool,
+}
+
+/// Events published by the auth service.
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type")]
+pub enum EventSchema {
+    #[serde(rename = "user.anonymized")]
+    UserAnonymized { userId: String },
+    /// A tombstone was left for a deleted user, org, ...
+    #[serde(rename = "resource.deleted")]
+    ResourceDeleted {
+        resourceType: String,
+        resourceId: String,
+    },
+}
+
+/// Response date format, applied to every route.
+#[derive(Deserialize, Debug, Default)]
+pub struct DateOptions {
+    /// `rfc3339` (the default) or `epoch` for Unix milliseconds.
+    pub dates: Option<String>,
+    /// IANA zone for rfc3339 dates, e.g. `Europe/Berlin`.
+    pub tz: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct OverdueOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    /// Only drafts assigned to this user.
+    pub assignee: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SlowQueryOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    /// Only queries on this collection.
+    pub collection: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct IndexAdviceOptions {
+    /// Days of the slow query log to look at.
+    pub days: Option<i64>,
+    pub collection: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BuildIndexSchema {
+    pub collection: String,
+    pub keys: Vec<IndexKey>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ActivityOptions {
+    /// `cursor` from the previous response; only later entries are returned.
+    pub after: Option<String>,
+    pub limit: Option<i64>,
+    /// Seconds to hold the request open when nothing newer than `after` has
+    /// happened yet.
+    pub wait: Option<u64>,
+}
+
+/// The same filters as listing posts takes.
+#[allow(non_snake_case)]
+#[derive(Deserialize, Debug)]
+pub struct SavedSearchSchema {
+    pub name: String,
+    pub status: Option<PostStatus>,
+    pub tag: Option<String>,
+    pub author: Option<String>,
+    pub category: Option<String>,
+    pub includeDescendants: Option<bool>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SavedSearchResultsOptions {
+    pub page: Option<i64>,
+    pub limit: Option<i64>,
+    /// Serves each post's title variant for this visitor.
+    pub visitor: Option<String>,
+}
+
+/// Defines or redefines a custom field. Posts keep values already written.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FieldDefinitionSchema {
+    pub label: String,
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    #[serde(default)]
+    pub required: bool,
+    pub options: Option<Vec<String>>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub maxLength: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct NewPostOptions {
+    /// Fills in whatever the new post leaves out from this template.
+    pub template: Option<String>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateTemplateSchema {
+    pub name: String,
+    pub titlePattern: String,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+}
+
+/// What an admin can do to a migration job.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationAction {
+    Pause,
+    Resume,
+    Abort,
 }
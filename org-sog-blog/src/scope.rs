@@ -0,0 +1,10 @@
+pub const BLOG_WRITE: &str = "blog:write";
+pub const BLOG_ADMIN: &str = "blog:admin";
+
+pub fn parse(scope: Option<&str>) -> Vec<String> {
+    scope
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect()
+}
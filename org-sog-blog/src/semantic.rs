@@ -0,0 +1,287 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use org_sog_core::{
+    circuit_breaker::BreakerMetrics,
+    http::{HttpClient, HttpClientConfig, HttpError, Method},
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{error::MyError, AppState};
+
+/// Posts embedded per indexing run, so a backlog is worked off gradually.
+const MAX_EMBEDDED_PER_RUN: usize = 100;
+/// Embedding models take a bounded input; the start of a post stands for it.
+const MAX_EMBEDDED_CHARS: usize = 8_000;
+
+pub type EmbedFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<f32>, HttpError>> + Send + 'a>>;
+
+/// A model that turns text into a vector, near for texts that mean alike.
+pub trait Embedder: Send + Sync {
+    /// Identifies the model; vectors from different models don't compare.
+    fn model(&self) -> &str;
+
+    fn embed<'a>(&'a self, text: &'a str) -> EmbedFuture<'a>;
+
+    fn metrics(&self) -> BreakerMetrics;
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbeddings {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiEmbedding {
+    embedding: Vec<f32>,
+}
+
+/// Any service speaking OpenAI's embeddings API.
+pub struct OpenAiEmbedder {
+    http: HttpClient,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAiEmbedder {
+    pub fn new(base_url: &str, api_key: Option<String>, model: String) -> Self {
+        Self {
+            http: HttpClient::new("embeddings", HttpClientConfig::default()),
+            url: format!("{}/embeddings", base_url.trim_end_matches('/')),
+            api_key,
+            model,
+        }
+    }
+
+    async fn fetch(&self, text: &str) -> Result<Vec<f32>, HttpError> {
+        let body = json!({"model": self.model, "input": text});
+        let embeddings: OpenAiEmbeddings = self
+            .http
+            .json(Method::POST, &self.url, |request| {
+                let request = request.json(&body);
+                match &self.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            })
+            .await?;
+        embeddings
+            .data
+            .into_iter()
+            .next()
+            .map(|data| data.embedding)
+            .ok_or_else(|| HttpError::Decode("no embedding in response".to_string()))
+    }
+}
+
+impl Embedder for OpenAiEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, text: &'a str) -> EmbedFuture<'a> {
+        Box::pin(self.fetch(text))
+    }
+
+    fn metrics(&self) -> BreakerMetrics {
+        self.http.metrics()
+    }
+}
+
+#[derive(Deserialize)]
+struct LocalEmbedding {
+    embedding: Vec<f32>,
+}
+
+/// A model served on the same network by Ollama.
+pub struct LocalEmbedder {
+    http: HttpClient,
+    url: String,
+    model: String,
+}
+
+impl LocalEmbedder {
+    pub fn new(base_url: &str, model: String) -> Self {
+        Self {
+            http: HttpClient::new("embeddings", HttpClientConfig::default()),
+            url: format!("{}/api/embeddings", base_url.trim_end_matches('/')),
+            model,
+        }
+    }
+
+    async fn fetch(&self, text: &str) -> Result<Vec<f32>, HttpError> {
+        let body = json!({"model": self.model, "prompt": text});
+        let embedding: LocalEmbedding = self
+            .http
+            .json(Method::POST, &self.url, |request| request.json(&body))
+            .await?;
+        Ok(embedding.embedding)
+    }
+}
+
+impl Embedder for LocalEmbedder {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed<'a>(&'a self, text: &'a str) -> EmbedFuture<'a> {
+        Box::pin(self.fetch(text))
+    }
+
+    fn metrics(&self) -> BreakerMetrics {
+        self.http.metrics()
+    }
+}
+
+/// The model named by `EMBEDDING_PROVIDER`: `openai` at `EMBEDDING_URL`
+/// (default `https://api.openai.com/v1`) with `EMBEDDING_API_KEY`, or `local`
+/// at `EMBEDDING_URL` (default `http://localhost:11434`). `EMBEDDING_MODEL`
+/// picks the model.
+pub fn embedder_from_env() -> Option<Box<dyn Embedder>> {
+    let url = std::env::var("EMBEDDING_URL").ok();
+    let model = std::env::var("EMBEDDING_MODEL").ok();
+    match std::env::var("EMBEDDING_PROVIDER").ok().as_deref() {
+        Some("openai") => Some(Box::new(OpenAiEmbedder::new(
+            url.as_deref().unwrap_or("https://api.openai.com/v1"),
+            std::env::var("EMBEDDING_API_KEY").ok(),
+            model.unwrap_or_else(|| "text-embedding-3-small".to_string()),
+        ))),
+        Some("local") => Some(Box::new(LocalEmbedder::new(
+            url.as_deref().unwrap_or("http://localhost:11434"),
+            model.unwrap_or_else(|| "nomic-embed-text".to_string()),
+        ))),
+        Some("none") | None => None,
+        Some(other) => panic!("EMBEDDING_PROVIDER {} is not supported.", other),
+    }
+}
+
+/// Semantic search over published posts.
+///
+/// Every `EMBEDDING_INTERVAL_SECS` (default 300, 0 turns it off) posts
+/// published or edited since their last embedding are embedded again, and
+/// the vectors stored in Mongo. Searches blend how near a post's vector is
+/// to the query's with its keyword score, `SEMANTIC_WEIGHT` (default 0.7)
+/// giving the share of the former. Without an embedder, search is keyword
+/// search alone.
+pub struct SemanticSearch {
+    embedder: Option<Box<dyn Embedder>>,
+    interval: Option<Duration>,
+    pub weight: f64,
+}
+
+impl SemanticSearch {
+    pub fn init() -> Self {
+        let secs = match std::env::var("EMBEDDING_INTERVAL_SECS") {
+            Ok(value) => value
+                .parse::<u64>()
+                .expect("EMBEDDING_INTERVAL_SECS must be a number."),
+            Err(_) => 300,
+        };
+        let weight = match std::env::var("SEMANTIC_WEIGHT") {
+            Ok(value) => value
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| (0.0..=1.0).contains(weight))
+                .expect("SEMANTIC_WEIGHT must be a number from 0 to 1."),
+            Err(_) => 0.7,
+        };
+
+        Self {
+            embedder: embedder_from_env(),
+            interval: (secs > 0).then(|| Duration::from_secs(secs)),
+            weight,
+        }
+    }
+
+    /// The query's vector, with the model it came from. `None` when there is
+    /// no embedder or it failed, so search falls back to keywords.
+    pub async fn embed_query(&self, query: &str) -> Option<(&str, Vec<f32>)> {
+        let embedder = self.embedder.as_ref()?;
+        if query.trim().is_empty() {
+            return None;
+        }
+        match embedder.embed(query).await {
+            Ok(vector) => Some((embedder.model(), vector)),
+            Err(e) => {
+                println!("⚠️ Embedding a search query failed: {}", e);
+                None
+            }
+        }
+    }
+
+    pub fn breaker_metrics(&self) -> Option<BreakerMetrics> {
+        self.embedder.as_ref().map(|embedder| embedder.metrics())
+    }
+}
+
+pub fn spawn_indexer(app_state: Arc<AppState>) {
+    let Some(period) = app_state.semantic.interval else {
+        return;
+    };
+    if app_state.semantic.embedder.is_none() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match run(&app_state).await {
+                Ok(0) => {}
+                Ok(embedded) => println!("🧭 Embedded {} posts", embedded),
+                Err(e) => println!("⚠️ Embedding posts failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Embeds the published posts whose vector is missing or out of date, and
+/// drops the vectors of posts no longer published. Returns how many posts
+/// were embedded.
+pub async fn run(app_state: &AppState) -> Result<usize, MyError> {
+    let Some(embedder) = &app_state.semantic.embedder else {
+        return Ok(0);
+    };
+
+    let stale = app_state
+        .db
+        .posts_to_embed(embedder.model(), MAX_EMBEDDED_PER_RUN)
+        .await?;
+    let mut embedded = 0;
+    for (post_id, updated_at, text) in stale {
+        let text: String = text.chars().take(MAX_EMBEDDED_CHARS).collect();
+        match embedder.embed(&text).await {
+            Ok(vector) => {
+                app_state
+                    .db
+                    .store_embedding(post_id, embedder.model(), vector, updated_at)
+                    .await?;
+                embedded += 1;
+            }
+            // A provider that is down fails every post alike; try again next run.
+            Err(HttpError::CircuitOpen) => break,
+            Err(e) => println!("⚠️ Embedding post {} failed: {}", post_id, e),
+        }
+    }
+    Ok(embedded)
+}
+
+/// Cosine similarity; 0 for vectors of different lengths.
+pub fn cosine(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f64, 0.0f64, 0.0f64);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (*x as f64, *y as f64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
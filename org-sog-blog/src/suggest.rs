@@ -0,0 +1,327 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    time::{Duration, Instant},
+};
+
+use org_sog_core::{
+    circuit_breaker::BreakerMetrics,
+    http::{HttpClient, HttpClientConfig, HttpError, Method},
+};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use crate::error::MyError::{self, CompletionError, TooManyRequestsError};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Only the start of a long post is sent; it says enough about the rest.
+const MAX_PROMPT_CHARS: usize = 12_000;
+const MAX_SUGGESTED_TAGS: usize = 8;
+const MAX_SUMMARY_CHARS: usize = 300;
+
+const INSTRUCTIONS: &str = "You help the author of a blog post describe it. Reply with only a \
+JSON object of the form {\"summary\": string, \"tags\": [string]}: a summary of one or two \
+sentences, under 160 characters, in the post's language, and up to 5 short lowercase tags. \
+Prefer tags from the list of existing tags when they fit.";
+
+pub type CompletionFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<String, HttpError>> + Send + 'a>>;
+
+/// A language model that can continue a conversation.
+pub trait Completion: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// The model's reply to `prompt`, following `instructions`.
+    fn complete<'a>(&'a self, instructions: &'a str, prompt: &'a str) -> CompletionFuture<'a>;
+
+    fn metrics(&self) -> BreakerMetrics;
+}
+
+#[derive(Deserialize)]
+struct ChatCompletion {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: String,
+}
+
+/// Any service speaking OpenAI's chat completions API: OpenAI itself, Azure,
+/// vLLM, LiteLLM and the like.
+pub struct OpenAiCompatible {
+    http: HttpClient,
+    url: String,
+    api_key: Option<String>,
+    model: String,
+}
+
+impl OpenAiCompatible {
+    pub fn new(base_url: &str, api_key: Option<String>, model: String) -> Self {
+        Self {
+            http: HttpClient::new("completion", completion_config()),
+            url: format!("{}/chat/completions", base_url.trim_end_matches('/')),
+            api_key,
+            model,
+        }
+    }
+
+    async fn chat(&self, instructions: &str, prompt: &str) -> Result<String, HttpError> {
+        let body = json!({
+            "model": self.model,
+            "temperature": 0.2,
+            "messages": [
+                {"role": "system", "content": instructions},
+                {"role": "user", "content": prompt},
+            ],
+        });
+        let completion: ChatCompletion = self
+            .http
+            .json(Method::POST, &self.url, |request| {
+                let request = request.json(&body);
+                match &self.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            })
+            .await?;
+        completion
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| HttpError::Decode("no choices in completion".to_string()))
+    }
+}
+
+impl Completion for OpenAiCompatible {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn complete<'a>(&'a self, instructions: &'a str, prompt: &'a str) -> CompletionFuture<'a> {
+        Box::pin(self.chat(instructions, prompt))
+    }
+
+    fn metrics(&self) -> BreakerMetrics {
+        self.http.metrics()
+    }
+}
+
+#[derive(Deserialize)]
+struct LocalChat {
+    message: ChatMessage,
+}
+
+/// A model served on the same network by Ollama, so drafts never leave it.
+pub struct Local {
+    http: HttpClient,
+    url: String,
+    model: String,
+}
+
+impl Local {
+    pub fn new(base_url: &str, model: String) -> Self {
+        Self {
+            http: HttpClient::new("completion", completion_config()),
+            url: format!("{}/api/chat", base_url.trim_end_matches('/')),
+            model,
+        }
+    }
+
+    async fn chat(&self, instructions: &str, prompt: &str) -> Result<String, HttpError> {
+        let body = json!({
+            "model": self.model,
+            "stream": false,
+            "format": "json",
+            "options": {"temperature": 0.2},
+            "messages": [
+                {"role": "system", "content": instructions},
+                {"role": "user", "content": prompt},
+            ],
+        });
+        let chat: LocalChat = self
+            .http
+            .json(Method::POST, &self.url, |request| request.json(&body))
+            .await?;
+        Ok(chat.message.content)
+    }
+}
+
+impl Completion for Local {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn complete<'a>(&'a self, instructions: &'a str, prompt: &'a str) -> CompletionFuture<'a> {
+        Box::pin(self.chat(instructions, prompt))
+    }
+
+    fn metrics(&self) -> BreakerMetrics {
+        self.http.metrics()
+    }
+}
+
+/// Models take a while to answer; waiting on them again is left to the author.
+fn completion_config() -> HttpClientConfig {
+    HttpClientConfig {
+        timeout: Duration::from_secs(60),
+        max_retries: 0,
+        ..HttpClientConfig::default()
+    }
+}
+
+/// The model named by `COMPLETION_PROVIDER`: `openai` at `COMPLETION_URL`
+/// (default `https://api.openai.com/v1`) with `COMPLETION_API_KEY`, or
+/// `local` at `COMPLETION_URL` (default `http://localhost:11434`).
+/// `COMPLETION_MODEL` picks the model.
+pub fn completion_from_env() -> Option<Box<dyn Completion>> {
+    let url = std::env::var("COMPLETION_URL").ok();
+    let model = std::env::var("COMPLETION_MODEL").ok();
+    match std::env::var("COMPLETION_PROVIDER").ok().as_deref() {
+        Some("openai") => Some(Box::new(OpenAiCompatible::new(
+            url.as_deref().unwrap_or("https://api.openai.com/v1"),
+            std::env::var("COMPLETION_API_KEY").ok(),
+            model.unwrap_or_else(|| "gpt-4o-mini".to_string()),
+        ))),
+        Some("local") => Some(Box::new(Local::new(
+            url.as_deref().unwrap_or("http://localhost:11434"),
+            model.unwrap_or_else(|| "llama3".to_string()),
+        ))),
+        Some("none") | None => None,
+        Some(other) => panic!("COMPLETION_PROVIDER {} is not supported.", other),
+    }
+}
+
+/// A summary and tags the author may take or leave.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub summary: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Suggested {
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Suggests summaries and tags for drafts, `SUGGEST_PER_HOUR` (default 20)
+/// per user.
+///
+/// Suggestions are only handed back, never saved to the post. Like the API
+/// rate limit, counts are kept in memory per instance.
+pub struct Suggester {
+    completion: Option<Box<dyn Completion>>,
+    per_hour: u64,
+    requests: Mutex<HashMap<String, Vec<Instant>>>,
+}
+
+impl Suggester {
+    pub fn init() -> Self {
+        let per_hour = match std::env::var("SUGGEST_PER_HOUR") {
+            Ok(value) => value
+                .parse()
+                .expect("SUGGEST_PER_HOUR must be a whole number."),
+            Err(_) => 20,
+        };
+
+        Self {
+            completion: completion_from_env(),
+            per_hour,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.completion.is_some()
+    }
+
+    /// A suggested summary and tags for a draft, preferring `known_tags`.
+    pub async fn suggest(
+        &self,
+        sub: &str,
+        title: &str,
+        content: &str,
+        known_tags: &[String],
+    ) -> Result<Suggestion, MyError> {
+        let Some(completion) = &self.completion else {
+            return Err(CompletionError(
+                "no completion provider configured".to_string(),
+            ));
+        };
+        self.count(sub).await?;
+
+        let content: String = content.chars().take(MAX_PROMPT_CHARS).collect();
+        let prompt = format!(
+            "Existing tags: {}\n\nTitle: {}\n\n{}",
+            known_tags.join(", "),
+            title,
+            content
+        );
+        let reply = completion
+            .complete(INSTRUCTIONS, &prompt)
+            .await
+            .map_err(|e| CompletionError(format!("{}: {}", completion.name(), e)))?;
+        parse(&reply).ok_or_else(|| {
+            CompletionError(format!("{} replied with no suggestion", completion.name()))
+        })
+    }
+
+    pub fn breaker_metrics(&self) -> Option<BreakerMetrics> {
+        self.completion
+            .as_ref()
+            .map(|completion| completion.metrics())
+    }
+
+    async fn count(&self, sub: &str) -> Result<(), MyError> {
+        let mut requests = self.requests.lock().await;
+        requests.retain(|_, made| {
+            made.retain(|at| at.elapsed() < RATE_WINDOW);
+            !made.is_empty()
+        });
+
+        let made = requests.entry(sub.to_owned()).or_default();
+        if made.len() as u64 >= self.per_hour {
+            let retry_after = RATE_WINDOW.saturating_sub(made[0].elapsed());
+            return Err(TooManyRequestsError(retry_after.as_secs().max(1)));
+        }
+
+        made.push(Instant::now());
+        Ok(())
+    }
+}
+
+/// The JSON object in a model's reply; models like to wrap it in prose or
+/// code fences.
+fn parse(reply: &str) -> Option<Suggestion> {
+    let start = reply.find('{')?;
+    let end = reply.rfind('}')?;
+    let suggested: Suggested = serde_json::from_str(reply.get(start..=end)?).ok()?;
+
+    let summary: String = suggested
+        .summary
+        .trim()
+        .chars()
+        .take(MAX_SUMMARY_CHARS)
+        .collect();
+    let mut tags: Vec<String> = Vec::new();
+    for tag in suggested.tags {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags.truncate(MAX_SUGGESTED_TAGS);
+
+    (!summary.is_empty() || !tags.is_empty()).then_some(Suggestion { summary, tags })
+}
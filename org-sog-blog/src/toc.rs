@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{Event, HeadingLevel, Parser, Tag};
+
+use crate::{model::TocEntryModel, render};
+
+/// Headings deeper than this are left out of the table of contents.
+const MAX_TOC_LEVEL: u8 = 4;
+const MAX_TOC_ENTRIES: usize = 100;
+
+/// The Markdown headings in `content`, with the anchors
+/// [`crate::render::Renderer`] gives them.
+pub fn extract(content: &str) -> Vec<TocEntryModel> {
+    let mut entries = Vec::new();
+    let mut anchors = Anchors::default();
+    let mut heading: Option<(u8, String)> = None;
+
+    for event in Parser::new_ext(content, render::options()) {
+        match event {
+            Event::Start(Tag::Heading(level, _, _)) => {
+                heading = Some((depth(level), String::new()))
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(&text);
+                }
+            }
+            Event::End(Tag::Heading(..)) => {
+                let Some((level, text)) = heading.take() else {
+                    continue;
+                };
+                // Every heading takes its anchor, so later ones match the rendered ids.
+                let anchor = anchors.next(&text);
+                if level > MAX_TOC_LEVEL || text.trim().is_empty() {
+                    continue;
+                }
+                entries.push(TocEntryModel {
+                    text: text.trim().to_owned(),
+                    level,
+                    anchor,
+                });
+                if entries.len() == MAX_TOC_ENTRIES {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+pub fn depth(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// Hands out GitHub-style anchors for headings in the order they appear:
+/// lowercase, punctuation dropped, spaces to dashes, and `-1`, `-2`, ... on
+/// repeats.
+#[derive(Default)]
+pub struct Anchors {
+    seen: HashMap<String, usize>,
+}
+
+impl Anchors {
+    pub fn next(&mut self, text: &str) -> String {
+        let base: String = text
+            .trim()
+            .chars()
+            .flat_map(char::to_lowercase)
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+        let seen = self.seen.entry(base.to_owned()).or_insert(0);
+        let anchor = match *seen {
+            0 => base,
+            n => format!("{}-{}", base, n),
+        };
+        *seen += 1;
+        anchor
+    }
+}
@@ -0,0 +1,198 @@
+//! How the repository layer's list and create paths compare with the
+//! cheaper ones proposed for them: projecting away post bodies in lists,
+//! not reading a document back after inserting it, and batching inserts.
+//!
+//! Needs a MongoDB, which the benchmarks fill and drop a database in:
+//!
+//! ```text
+//! BENCH_MONGODB_URI=mongodb://localhost:27017 cargo bench --features bench-mongo
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::FindOptions;
+use mongodb::{Client, Database};
+use org_sog_core::ids::IdStrategy;
+use org_sog_core::read::{ReadFrom, ReadRouting};
+use org_sog_core::repo::Repository;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Runtime;
+
+const DATABASE: &str = "org_sog_bench";
+/// Posts seeded for the list benchmarks.
+const SEEDED: usize = 2_000;
+/// A list page, as the default `PAGE_MAX_LIMIT` allows.
+const PAGE: i64 = 100;
+
+/// Shaped like a blog post, with a body the size of a long one.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Post {
+    #[serde(rename = "_id")]
+    id: ObjectId,
+    title: String,
+    #[serde(default)]
+    content: String,
+    author: String,
+    tags: Vec<String>,
+    published: bool,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    createdAt: DateTime<Utc>,
+}
+
+fn post(n: usize) -> Post {
+    Post {
+        id: ObjectId::new(),
+        title: format!("Post number {}", n),
+        content: "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(80),
+        author: format!("author-{}", n % 20),
+        tags: vec!["rust".to_string(), format!("tag-{}", n % 7)],
+        published: true,
+        createdAt: Utc::now() - Duration::minutes(n as i64),
+    }
+}
+
+async fn connect() -> Database {
+    let uri = std::env::var("BENCH_MONGODB_URI")
+        .unwrap_or_else(|_| "mongodb://localhost:27017".to_string());
+    let client = Client::with_uri_str(&uri)
+        .await
+        .unwrap_or_else(|e| panic!("could not connect to {}: {}", uri, e));
+    let database = client.database(DATABASE);
+    database.drop(None).await.expect("drop benchmark database");
+    database
+}
+
+fn list(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let posts = runtime.block_on(async {
+        let database = connect().await;
+        let posts: Repository<Post> = Repository::new(
+            database.collection("posts"),
+            ReadRouting::init(),
+            IdStrategy::ObjectId,
+        );
+        let seed: Vec<Post> = (0..SEEDED).map(post).collect();
+        posts.collection().insert_many(seed, None).await.unwrap();
+        posts
+    });
+
+    let posts = &posts;
+    let mut group = c.benchmark_group("list");
+    // What list endpoints do now: whole documents, bodies included.
+    group.bench_function("full_documents", |b| {
+        b.to_async(&runtime).iter(|| async move {
+            let options = FindOptions::builder()
+                .sort(doc! {"createdAt": -1})
+                .limit(PAGE)
+                .build();
+            let mut cursor = posts.collection().find(None, options).await.unwrap();
+            let mut found: Vec<Post> = Vec::new();
+            while cursor.advance().await.unwrap() {
+                found.push(cursor.deserialize_current().unwrap());
+            }
+            found
+        })
+    });
+    // Summaries only need the fields a list shows.
+    group.bench_function("projection", |b| {
+        b.to_async(&runtime).iter(|| async move {
+            let options = FindOptions::builder()
+                .sort(doc! {"createdAt": -1})
+                .limit(PAGE)
+                .projection(doc! {"content": 0})
+                .build();
+            let mut cursor = posts.collection().find(None, options).await.unwrap();
+            let mut found: Vec<Post> = Vec::new();
+            while cursor.advance().await.unwrap() {
+                found.push(cursor.deserialize_current().unwrap());
+            }
+            found
+        })
+    });
+    group.finish();
+}
+
+fn create(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let posts: Repository<Post> = runtime.block_on(async {
+        let database = connect().await;
+        Repository::new(
+            database.collection("posts"),
+            ReadRouting::init(),
+            IdStrategy::ObjectId,
+        )
+    });
+
+    let posts = &posts;
+    let mut group = c.benchmark_group("create");
+    group.bench_function("insert_then_refetch", |b| {
+        b.to_async(&runtime).iter_batched(
+            || post(0),
+            |post| async move {
+                posts.insert(&post).await.unwrap();
+                posts
+                    .find_one(doc! {"_id": post.id}, ReadFrom::Primary)
+                    .await
+                    .unwrap()
+                    .unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    // The document carries its own `_id`, so it already is what was stored.
+    group.bench_function("insert_only", |b| {
+        b.to_async(&runtime).iter_batched(
+            || post(0),
+            |post| async move {
+                posts.insert(&post).await.unwrap();
+                post
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bulk(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let posts: Repository<Post> = runtime.block_on(async {
+        let database = connect().await;
+        Repository::new(
+            database.collection("imports"),
+            ReadRouting::init(),
+            IdStrategy::ObjectId,
+        )
+    });
+
+    let posts = &posts;
+    let mut group = c.benchmark_group("bulk");
+    for size in [10, 100] {
+        let batch = move || (0..size).map(post).collect::<Vec<_>>();
+        group.bench_with_input(BenchmarkId::new("one_by_one", size), &size, |b, _| {
+            b.to_async(&runtime).iter_batched(
+                batch,
+                |batch| async move {
+                    for post in &batch {
+                        posts.insert(post).await.unwrap();
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("insert_many", size), &size, |b, _| {
+            b.to_async(&runtime).iter_batched(
+                batch,
+                |batch| async move {
+                    posts.collection().insert_many(batch, None).await.unwrap();
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, list, create, bulk);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+//! Ids taken from paths, e.g. `/api/blog/:id`, are parsed as object ids and
+//! turned down with `invalid_id` when they aren't one.
+
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use mongodb::bson::oid::ObjectId;
+
+fuzz_target!(|id: &str| {
+    if let Ok(oid) = ObjectId::from_str(id) {
+        // Whatever parses is written back the way clients are given it.
+        assert_eq!(oid.to_hex(), id.to_lowercase());
+        assert_eq!(ObjectId::from_str(&oid.to_hex()).ok(), Some(oid));
+    }
+});
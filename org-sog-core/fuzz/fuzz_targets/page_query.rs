@@ -0,0 +1,47 @@
+//! List endpoints take `page` and `limit` from the query string and resolve
+//! them against the configured [`PageLimits`] before skipping that far.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use org_sog_core::page::PageLimits;
+use serde::Deserialize;
+
+/// The paging fields every list option struct starts with.
+#[derive(Deserialize)]
+struct PageOptions {
+    page: Option<i64>,
+    limit: Option<i64>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input<'a> {
+    query: &'a str,
+    default_limit: i64,
+    max_limit: i64,
+    list_default: i64,
+}
+
+fuzz_target!(|input: Input| {
+    // What axum's `Query` extractor does with the query string.
+    let Ok(options) = serde_urlencoded::from_str::<PageOptions>(input.query) else {
+        return;
+    };
+    let limits = PageLimits {
+        default_limit: input.default_limit,
+        max_limit: input.max_limit,
+    };
+
+    for resolved in [
+        limits.resolve(options.page, options.limit),
+        limits.resolve_or(options.page, options.limit, input.list_default),
+    ] {
+        if let Ok(paging) = resolved {
+            assert!(paging.page >= 1);
+            assert!(paging.limit >= 1);
+            // Passed to MongoDB as an i64 in aggregation stages.
+            assert!(i64::try_from(paging.skip()).is_ok());
+        }
+    }
+});
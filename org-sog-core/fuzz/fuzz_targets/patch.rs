@@ -0,0 +1,43 @@
+//! PATCH bodies through [`Patch`], as the services' preference and post
+//! updates build them: fields given a value are set, fields given `null`
+//! are unset.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mongodb::bson::{self, Document};
+use org_sog_core::patch::Patch;
+use serde_json::{Map, Value};
+
+fuzz_target!(|body: &[u8]| {
+    let Ok(fields) = serde_json::from_slice::<Map<String, Value>>(body) else {
+        return;
+    };
+
+    let mut patch = Patch::new();
+    for (field, value) in fields {
+        match value {
+            Value::Null => patch.unset(field),
+            // Numbers out of range for BSON are turned down, not wrapped.
+            value => match bson::to_bson(&value) {
+                Ok(value) => patch.set(field, value),
+                Err(_) => return,
+            },
+        }
+    }
+    let update = patch.into_update();
+
+    // The server rejects an update that sets and unsets the same field.
+    let set = update.get_document("$set").expect("update always sets");
+    if let Ok(unset) = update.get_document("$unset") {
+        assert!(!unset.is_empty());
+        assert!(unset.keys().all(|field| !set.contains_key(field)));
+    }
+
+    // Keys the wire format can't hold, e.g. with a NUL in them, fail here.
+    let Ok(encoded) = bson::to_vec(&update) else {
+        return;
+    };
+    let decoded: Document = bson::from_slice(&encoded).expect("encoded update decodes");
+    assert_eq!(decoded, update);
+});
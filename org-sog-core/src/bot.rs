@@ -0,0 +1,370 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ring::{hmac, rand::SystemRandom};
+use serde::{Deserialize, Serialize};
+
+use crate::http::{HttpClient, HttpClientConfig, HttpError, Method};
+
+const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+
+/// Stamps older than this are refused, so they can't be stockpiled.
+const STAMP_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub type CaptchaFuture<'a> = Pin<Box<dyn Future<Output = Result<bool, HttpError>> + Send + 'a>>;
+
+/// A CAPTCHA service that can vouch for a token solved in the browser.
+pub trait CaptchaProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether `token` was solved, by `ip` when known.
+    fn verify<'a>(&'a self, token: &'a str, ip: Option<&'a str>) -> CaptchaFuture<'a>;
+}
+
+#[derive(Deserialize)]
+struct SiteverifyOutcome {
+    success: bool,
+}
+
+/// Both providers speak the same siteverify dialect, only the endpoint differs.
+async fn siteverify(
+    http: &HttpClient,
+    url: &str,
+    secret: &str,
+    token: &str,
+    ip: Option<&str>,
+) -> Result<bool, HttpError> {
+    let mut form = vec![("secret", secret), ("response", token)];
+    if let Some(ip) = ip {
+        form.push(("remoteip", ip));
+    }
+    let outcome: SiteverifyOutcome = http
+        .json(Method::POST, url, |request| request.form(&form))
+        .await?;
+    Ok(outcome.success)
+}
+
+/// Cloudflare Turnstile.
+pub struct Turnstile {
+    http: HttpClient,
+    secret: String,
+}
+
+impl Turnstile {
+    pub fn new(secret: String) -> Self {
+        Self {
+            http: HttpClient::new("turnstile", HttpClientConfig::default()),
+            secret,
+        }
+    }
+}
+
+impl CaptchaProvider for Turnstile {
+    fn name(&self) -> &'static str {
+        "turnstile"
+    }
+
+    fn verify<'a>(&'a self, token: &'a str, ip: Option<&'a str>) -> CaptchaFuture<'a> {
+        Box::pin(siteverify(
+            &self.http,
+            TURNSTILE_VERIFY_URL,
+            &self.secret,
+            token,
+            ip,
+        ))
+    }
+}
+
+/// hCaptcha.
+pub struct HCaptcha {
+    http: HttpClient,
+    secret: String,
+}
+
+impl HCaptcha {
+    pub fn new(secret: String) -> Self {
+        Self {
+            http: HttpClient::new("hcaptcha", HttpClientConfig::default()),
+            secret,
+        }
+    }
+}
+
+impl CaptchaProvider for HCaptcha {
+    fn name(&self) -> &'static str {
+        "hcaptcha"
+    }
+
+    fn verify<'a>(&'a self, token: &'a str, ip: Option<&'a str>) -> CaptchaFuture<'a> {
+        Box::pin(siteverify(
+            &self.http,
+            HCAPTCHA_VERIFY_URL,
+            &self.secret,
+            token,
+            ip,
+        ))
+    }
+}
+
+/// The provider named by `CAPTCHA_PROVIDER` (`turnstile` or `hcaptcha`) with
+/// its `CAPTCHA_SECRET`. A bare `TURNSTILE_SECRET` still selects Turnstile.
+pub fn captcha_from_env() -> Option<Box<dyn CaptchaProvider>> {
+    let secret = std::env::var("CAPTCHA_SECRET").ok();
+    match std::env::var("CAPTCHA_PROVIDER").ok().as_deref() {
+        Some("turnstile") => Some(Box::new(Turnstile::new(
+            secret.expect("CAPTCHA_SECRET must be set for turnstile."),
+        ))),
+        Some("hcaptcha") => Some(Box::new(HCaptcha::new(
+            secret.expect("CAPTCHA_SECRET must be set for hcaptcha."),
+        ))),
+        Some("none") => None,
+        Some(other) => panic!("CAPTCHA_PROVIDER {} is not supported.", other),
+        None => std::env::var("TURNSTILE_SECRET")
+            .ok()
+            .map(|secret| Box::new(Turnstile::new(secret)) as Box<dyn CaptchaProvider>),
+    }
+}
+
+/// One of the checks a form can be put through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BotCheck {
+    /// A field hidden from people must come back empty.
+    Honeypot,
+    /// The form must have been open for at least the minimum fill time.
+    Timing,
+    /// A CAPTCHA must be solved, when a provider is configured.
+    Captcha,
+}
+
+impl BotCheck {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "honeypot" => Some(BotCheck::Honeypot),
+            "timing" => Some(BotCheck::Timing),
+            "captcha" => Some(BotCheck::Captcha),
+            _ => None,
+        }
+    }
+}
+
+/// Why a submission was taken for a bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BotRejection {
+    Honeypot,
+    /// Sent sooner after the form was opened than a person could fill it in.
+    TooFast,
+    /// The form stamp was missing, forged or expired.
+    BadStamp,
+    CaptchaMissing,
+    CaptchaFailed,
+}
+
+impl BotRejection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BotRejection::Honeypot => "honeypot",
+            BotRejection::TooFast => "too_fast",
+            BotRejection::BadStamp => "bad_stamp",
+            BotRejection::CaptchaMissing => "captcha_missing",
+            BotRejection::CaptchaFailed => "captcha_failed",
+        }
+    }
+}
+
+impl fmt::Display for BotRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a form submission offers as proof of being human.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Submission<'a> {
+    pub honeypot: Option<&'a str>,
+    /// The stamp handed out with the form, see [`BotDefense::stamp`].
+    pub stamp: Option<&'a str>,
+    pub captcha_token: Option<&'a str>,
+    pub ip: Option<&'a str>,
+}
+
+/// How many submissions of one form were turned away, and why.
+#[derive(Debug, Serialize)]
+pub struct BotMetrics {
+    pub form: String,
+    pub reason: &'static str,
+    pub blocked: u64,
+}
+
+/// Honeypot, timing and CAPTCHA checks for public write endpoints.
+///
+/// Each form runs the checks it was registered with, which
+/// `BOT_CHECKS_<FORM>` (comma-separated, or `none`) overrides. The timing
+/// check needs the form to have been fetched with a stamp, signed with
+/// `FORM_STAMP_SECRET` and at least `BOT_MIN_FILL_SECS` (default 3) old when
+/// it comes back. Blocked attempts are counted per instance.
+pub struct BotDefense {
+    forms: HashMap<&'static str, Vec<BotCheck>>,
+    captcha: Option<Box<dyn CaptchaProvider>>,
+    key: hmac::Key,
+    min_fill: Duration,
+    blocked: Mutex<BTreeMap<(&'static str, BotRejection), u64>>,
+}
+
+impl BotDefense {
+    /// Sets up the given forms with their default checks.
+    pub fn init(forms: &[(&'static str, &[BotCheck])]) -> Self {
+        let forms = forms
+            .iter()
+            .map(|(form, defaults)| {
+                let name = format!("BOT_CHECKS_{}", form.to_uppercase());
+                let checks = match std::env::var(&name) {
+                    Ok(value) if value.trim() == "none" => Vec::new(),
+                    Ok(value) => value
+                        .split(',')
+                        .filter(|check| !check.trim().is_empty())
+                        .map(|check| {
+                            BotCheck::parse(check).unwrap_or_else(|| {
+                                panic!("{} has an unknown check: {}", name, check)
+                            })
+                        })
+                        .collect(),
+                    Err(_) => defaults.to_vec(),
+                };
+                (*form, checks)
+            })
+            .collect();
+
+        let min_fill = std::env::var("BOT_MIN_FILL_SECS")
+            .ok()
+            .map(|value| {
+                value
+                    .parse::<u64>()
+                    .expect("BOT_MIN_FILL_SECS must be a number.")
+            })
+            .unwrap_or(3);
+
+        let key = match std::env::var("FORM_STAMP_SECRET") {
+            Ok(secret) => hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()),
+            Err(_) => {
+                println!("⚠️ FORM_STAMP_SECRET not set, form stamps won't survive a restart");
+                hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+                    .expect("failed to generate a form stamp key")
+            }
+        };
+
+        Self {
+            forms,
+            captcha: captcha_from_env(),
+            key,
+            min_fill: Duration::from_secs(min_fill),
+            blocked: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// A stamp recording when a form was handed out, to be sent back with it.
+    pub fn stamp(&self) -> String {
+        let issued = now_secs().to_string();
+        let tag = hmac::sign(&self.key, issued.as_bytes());
+        format!("{}.{}", issued, hex::encode(tag.as_ref()))
+    }
+
+    /// How long ago a genuine stamp was issued.
+    fn stamp_age(&self, stamp: &str) -> Option<Duration> {
+        let (issued, tag) = stamp.split_once('.')?;
+        let tag = hex::decode(tag).ok()?;
+        hmac::verify(&self.key, issued.as_bytes(), &tag).ok()?;
+        let age = Duration::from_secs(now_secs().checked_sub(issued.parse().ok()?)?);
+        (age <= STAMP_MAX_AGE).then_some(age)
+    }
+
+    /// Runs `form`'s checks against a submission, counting any rejection.
+    ///
+    /// A CAPTCHA provider that can't be reached fails the check, since letting
+    /// submissions through would switch the protection off.
+    pub async fn check(
+        &self,
+        form: &'static str,
+        submission: &Submission<'_>,
+    ) -> Result<(), BotRejection> {
+        let result = self.run_checks(form, submission).await;
+        if let Err(rejection) = result {
+            *self
+                .blocked
+                .lock()
+                .unwrap()
+                .entry((form, rejection))
+                .or_default() += 1;
+        }
+        result
+    }
+
+    async fn run_checks(
+        &self,
+        form: &'static str,
+        submission: &Submission<'_>,
+    ) -> Result<(), BotRejection> {
+        let checks = self.forms.get(form).map(Vec::as_slice).unwrap_or_default();
+
+        if checks.contains(&BotCheck::Honeypot)
+            && submission.honeypot.is_some_and(|value| !value.is_empty())
+        {
+            return Err(BotRejection::Honeypot);
+        }
+
+        if checks.contains(&BotCheck::Timing) {
+            let age = submission
+                .stamp
+                .and_then(|stamp| self.stamp_age(stamp))
+                .ok_or(BotRejection::BadStamp)?;
+            if age < self.min_fill {
+                return Err(BotRejection::TooFast);
+            }
+        }
+
+        if checks.contains(&BotCheck::Captcha) {
+            if let Some(provider) = &self.captcha {
+                let token = submission
+                    .captcha_token
+                    .filter(|token| !token.is_empty())
+                    .ok_or(BotRejection::CaptchaMissing)?;
+                match provider.verify(token, submission.ip).await {
+                    Ok(true) => {}
+                    Ok(false) => return Err(BotRejection::CaptchaFailed),
+                    Err(e) => {
+                        println!("⚠️ {} verification failed: {}", provider.name(), e);
+                        return Err(BotRejection::CaptchaFailed);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> Vec<BotMetrics> {
+        self.blocked
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|((form, reason), blocked)| BotMetrics {
+                form: form.to_string(),
+                reason: reason.as_str(),
+                blocked: *blocked,
+            })
+            .collect()
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
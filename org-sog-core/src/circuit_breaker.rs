@@ -0,0 +1,368 @@
+use std::{
+    fmt,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BreakerConfig {
+    /// Consecutive failures that trip the breaker open.
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before letting a probe through.
+    pub open_for: Duration,
+    /// Upper bound on a single call; slower calls count as failures.
+    pub call_timeout: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+            call_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+pub enum BreakerError<E> {
+    /// The breaker is open and the call was not attempted.
+    Open,
+    /// The call did not finish within the configured timeout.
+    Timeout,
+    /// The call ran and failed.
+    Inner(E),
+}
+
+impl<E: fmt::Display> fmt::Display for BreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakerError::Open => write!(f, "circuit open"),
+            BreakerError::Timeout => write!(f, "call timed out"),
+            BreakerError::Inner(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for BreakerError<E> {}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BreakerMetrics {
+    pub name: &'static str,
+    pub state: BreakerState,
+    pub calls: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    pub rejected: u64,
+    pub trips: u64,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A single probe is in flight; everything else is rejected until it settles.
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    calls: AtomicU64,
+    successes: AtomicU64,
+    failures: AtomicU64,
+    timeouts: AtomicU64,
+    rejected: AtomicU64,
+    trips: AtomicU64,
+}
+
+/// Stops calling a dependency that keeps failing, so callers fail fast instead
+/// of piling up behind it.
+///
+/// After `failure_threshold` consecutive failures the breaker opens and rejects
+/// calls for `open_for`. It then lets one probe through: success closes it
+/// again, failure reopens it.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: &'static str,
+    config: BreakerConfig,
+    state: Mutex<State>,
+    counters: Counters,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, config: BreakerConfig) -> Self {
+        Self {
+            name,
+            config,
+            state: Mutex::new(State::Closed { failures: 0 }),
+            counters: Counters::default(),
+        }
+    }
+
+    /// Runs `call` through the breaker, counting every error it returns as a
+    /// failure of the dependency.
+    pub async fn call<T, E, F>(&self, call: F) -> Result<T, BreakerError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        self.call_with(call, |_| true).await
+    }
+
+    /// Like [`call`](Self::call), but only errors `is_failure` picks out count
+    /// against the dependency. Any other error still shows it answered, so it
+    /// counts as a success.
+    pub async fn call_with<T, E, F, P>(&self, call: F, is_failure: P) -> Result<T, BreakerError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+        P: Fn(&E) -> bool,
+    {
+        if !self.try_acquire() {
+            self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(BreakerError::Open);
+        }
+        self.counters.calls.fetch_add(1, Ordering::Relaxed);
+
+        match tokio::time::timeout(self.config.call_timeout, call).await {
+            Ok(Ok(value)) => {
+                self.on_success();
+                Ok(value)
+            }
+            Ok(Err(e)) => {
+                if is_failure(&e) {
+                    self.on_failure();
+                } else {
+                    self.on_success();
+                }
+                Err(BreakerError::Inner(e))
+            }
+            Err(_) => {
+                self.counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                self.on_failure();
+                Err(BreakerError::Timeout)
+            }
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => BreakerState::Closed,
+            State::Open { until } if Instant::now() >= until => BreakerState::HalfOpen,
+            State::Open { .. } => BreakerState::Open,
+            State::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+
+    pub fn metrics(&self) -> BreakerMetrics {
+        BreakerMetrics {
+            name: self.name,
+            state: self.state(),
+            calls: self.counters.calls.load(Ordering::Relaxed),
+            successes: self.counters.successes.load(Ordering::Relaxed),
+            failures: self.counters.failures.load(Ordering::Relaxed),
+            timeouts: self.counters.timeouts.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+            trips: self.counters.trips.load(Ordering::Relaxed),
+        }
+    }
+
+    fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } if Instant::now() >= until => {
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                true
+            }
+            // A probe can't outlive the call timeout, so one this old was
+            // dropped by its caller and will never report back.
+            State::HalfOpen { since } if since.elapsed() >= self.config.call_timeout => {
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    fn on_success(&self) {
+        self.counters.successes.fetch_add(1, Ordering::Relaxed);
+        *self.state.lock().unwrap() = State::Closed { failures: 0 };
+    }
+
+    fn on_failure(&self) {
+        self.counters.failures.fetch_add(1, Ordering::Relaxed);
+
+        let mut state = self.state.lock().unwrap();
+        let trip = match *state {
+            State::Closed { failures } => failures + 1 >= self.config.failure_threshold,
+            State::HalfOpen { .. } => true,
+            // A call admitted before the breaker opened; it's already open.
+            State::Open { .. } => false,
+        };
+
+        if trip {
+            self.counters.trips.fetch_add(1, Ordering::Relaxed);
+            *state = State::Open {
+                until: Instant::now() + self.config.open_for,
+            };
+        } else if let State::Closed { failures } = &mut *state {
+            *failures += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPEN_FOR: Duration = Duration::from_millis(50);
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(
+            "test",
+            BreakerConfig {
+                failure_threshold: 3,
+                open_for: OPEN_FOR,
+                call_timeout: Duration::from_millis(200),
+            },
+        )
+    }
+
+    async fn fail(breaker: &CircuitBreaker) {
+        let _ = breaker.call(async { Err::<(), _>("down") }).await;
+    }
+
+    async fn succeed(breaker: &CircuitBreaker) {
+        breaker.call(async { Ok::<_, ()>(()) }).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn opens_after_consecutive_failures() {
+        let breaker = breaker();
+        fail(&breaker).await;
+        fail(&breaker).await;
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        fail(&breaker).await;
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(breaker.metrics().trips, 1);
+    }
+
+    #[tokio::test]
+    async fn success_resets_the_failure_count() {
+        let breaker = breaker();
+        fail(&breaker).await;
+        fail(&breaker).await;
+        succeed(&breaker).await;
+        fail(&breaker).await;
+        fail(&breaker).await;
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn open_breaker_rejects_without_calling() {
+        let breaker = breaker();
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+
+        let mut called = false;
+        let result = breaker
+            .call(async {
+                called = true;
+                Ok::<_, ()>(())
+            })
+            .await;
+        assert!(matches!(result, Err(BreakerError::Open)));
+        assert!(!called);
+        assert_eq!(breaker.metrics().rejected, 1);
+    }
+
+    #[tokio::test]
+    async fn half_open_probe_success_closes() {
+        let breaker = breaker();
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+        tokio::time::sleep(OPEN_FOR).await;
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+
+        succeed(&breaker).await;
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn half_open_probe_failure_reopens() {
+        let breaker = breaker();
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+        tokio::time::sleep(OPEN_FOR).await;
+
+        fail(&breaker).await;
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert_eq!(breaker.metrics().trips, 2);
+    }
+
+    #[tokio::test]
+    async fn half_open_lets_one_probe_through() {
+        let breaker = breaker();
+        for _ in 0..3 {
+            fail(&breaker).await;
+        }
+        tokio::time::sleep(OPEN_FOR).await;
+
+        assert!(breaker.try_acquire());
+        assert!(!breaker.try_acquire());
+    }
+
+    #[tokio::test]
+    async fn timeouts_count_as_failures() {
+        let breaker = breaker();
+        let result = breaker
+            .call(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                Ok::<_, ()>(())
+            })
+            .await;
+        assert!(matches!(result, Err(BreakerError::Timeout)));
+        assert_eq!(breaker.metrics().failures, 1);
+    }
+
+    #[tokio::test]
+    async fn errors_that_are_not_failures_keep_it_closed() {
+        let breaker = breaker();
+        for _ in 0..5 {
+            let result = breaker
+                .call_with(async { Err::<(), _>("not found") }, |_| false)
+                .await;
+            assert!(matches!(result, Err(BreakerError::Inner("not found"))));
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert_eq!(breaker.metrics().failures, 0);
+    }
+}
@@ -0,0 +1,350 @@
+use std::{fmt, net::IpAddr};
+
+/// An address range in CIDR notation; a bare address is a range of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (address, prefix) = match text.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (text, None),
+        };
+        let address = canonical(address.parse().ok()?);
+        let max = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().ok().filter(|prefix| *prefix <= max)?,
+            None => max,
+        };
+
+        Some(Self {
+            network: mask(address, prefix),
+            prefix,
+        })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = canonical(*ip);
+        match (self.network, ip) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+                mask(ip, self.prefix) == self.network
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+fn mask(ip: IpAddr, prefix: u8) -> IpAddr {
+    match canonical(ip) {
+        IpAddr::V4(ip) => {
+            let bits = u32::from(ip);
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((bits & mask).into())
+        }
+        IpAddr::V6(ip) => {
+            let bits = u128::from(ip);
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((bits & mask).into())
+        }
+    }
+}
+
+/// IPv4 clients reaching a dual-stack listener show up as `::ffff:a.b.c.d`.
+pub fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        ip => ip,
+    }
+}
+
+/// The address a request came from once trusted proxies are looked past,
+/// kept in the request's extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// The forwarding headers of one request, each as every value it was sent
+/// with.
+#[derive(Debug, Default)]
+pub struct ForwardingHeaders<'a> {
+    /// The header named by [`TrustedProxies::client_header`], if any.
+    pub client_ip: Option<&'a str>,
+    pub forwarded: Vec<&'a str>,
+    pub x_forwarded_for: Vec<&'a str>,
+}
+
+/// Which peers are believed about who they forward for, from
+/// `TRUSTED_PROXIES` (comma-separated CIDRs). With none configured every
+/// request is taken to come straight from its peer.
+///
+/// `TRUSTED_PROXY_HEADER` names a single-address header the proxies set
+/// themselves, such as Cloudflare's `CF-Connecting-IP`. It is only read when
+/// named, since a proxy that merely passes it through lets clients pick
+/// their own address.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    proxies: Vec<Cidr>,
+    client_header: Option<String>,
+}
+
+impl TrustedProxies {
+    pub fn init() -> Self {
+        let proxies = std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                Cidr::parse(entry)
+                    .unwrap_or_else(|| panic!("TRUSTED_PROXIES has an invalid entry: {}", entry))
+            })
+            .collect();
+        let client_header = std::env::var("TRUSTED_PROXY_HEADER")
+            .ok()
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty());
+        Self {
+            proxies,
+            client_header,
+        }
+    }
+
+    /// The header to fill [`ForwardingHeaders::client_ip`] from, lowercased.
+    pub fn client_header(&self) -> Option<&str> {
+        self.client_header.as_deref()
+    }
+
+    pub fn is_trusted(&self, ip: &IpAddr) -> bool {
+        self.proxies.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// The client behind `peer`.
+    ///
+    /// Headers only count when `peer` is trusted. The configured client
+    /// header wins if present; otherwise the `Forwarded` chain, or failing
+    /// that `X-Forwarded-For`, is read right to left past trusted hops,
+    /// stopping at the first hop not vouched for by a trusted one.
+    pub fn resolve(&self, peer: IpAddr, headers: &ForwardingHeaders) -> IpAddr {
+        let peer = canonical(peer);
+        if !self.is_trusted(&peer) {
+            return peer;
+        }
+
+        if let Some(ip) = headers
+            .client_ip
+            .filter(|_| self.client_header.is_some())
+            .and_then(|value| value.trim().parse::<IpAddr>().ok())
+        {
+            return canonical(ip);
+        }
+
+        let hops: Vec<&str> = if !headers.forwarded.is_empty() {
+            headers
+                .forwarded
+                .iter()
+                .flat_map(|value| value.split(','))
+                .map(forwarded_for)
+                .collect()
+        } else {
+            headers
+                .x_forwarded_for
+                .iter()
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .collect()
+        };
+
+        let mut client = peer;
+        for hop in hops.into_iter().rev() {
+            let Some(ip) = parse_hop(hop) else {
+                break;
+            };
+            client = ip;
+            if !self.is_trusted(&client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// The `for=` parameter of one `Forwarded` element, or `""` without one.
+fn forwarded_for(element: &str) -> &str {
+    element
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+        .map_or("", |(_, value)| value.trim().trim_matches('"'))
+}
+
+/// One hop as an address, dropping any port: `1.2.3.4:80` or `[::1]:80`.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    if let Ok(ip) = hop.parse::<IpAddr>() {
+        return Some(canonical(ip));
+    }
+    let address = match hop.strip_prefix('[') {
+        Some(rest) => rest.split(']').next()?,
+        None => hop.rsplit_once(':')?.0,
+    };
+    address.parse::<IpAddr>().ok().map(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(text: &str) -> IpAddr {
+        text.parse().unwrap()
+    }
+
+    fn proxies(cidrs: &[&str], client_header: Option<&str>) -> TrustedProxies {
+        TrustedProxies {
+            proxies: cidrs
+                .iter()
+                .map(|cidr| Cidr::parse(cidr).unwrap())
+                .collect(),
+            client_header: client_header.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn cidr_matches_addresses_in_its_range() {
+        let cidr = Cidr::parse("10.1.0.0/16").unwrap();
+        assert!(cidr.contains(&ip("10.1.255.7")));
+        assert!(!cidr.contains(&ip("10.2.0.1")));
+        assert_eq!(cidr.to_string(), "10.1.0.0/16");
+
+        let cidr = Cidr::parse("2001:db8::/32").unwrap();
+        assert!(cidr.contains(&ip("2001:db8:ffff::1")));
+        assert!(!cidr.contains(&ip("2001:db9::1")));
+    }
+
+    #[test]
+    fn cidr_masks_host_bits_and_reads_bare_addresses_as_one() {
+        assert_eq!(
+            Cidr::parse("192.168.1.77/24").unwrap().to_string(),
+            "192.168.1.0/24"
+        );
+
+        let single = Cidr::parse(" 192.168.1.77 ").unwrap();
+        assert!(single.contains(&ip("192.168.1.77")));
+        assert!(!single.contains(&ip("192.168.1.78")));
+
+        let everything = Cidr::parse("0.0.0.0/0").unwrap();
+        assert!(everything.contains(&ip("203.0.113.9")));
+    }
+
+    #[test]
+    fn cidr_rejects_bad_prefixes_and_addresses() {
+        assert_eq!(Cidr::parse("10.0.0.0/33"), None);
+        assert_eq!(Cidr::parse("::/129"), None);
+        assert_eq!(Cidr::parse("10.0.0.0/x"), None);
+        assert_eq!(Cidr::parse("not-an-ip"), None);
+    }
+
+    #[test]
+    fn cidr_matches_ipv4_mapped_addresses_but_not_across_families() {
+        let cidr = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(cidr.contains(&ip("::ffff:10.2.3.4")));
+        assert!(!Cidr::parse("::/0").unwrap().contains(&ip("10.2.3.4")));
+    }
+
+    #[test]
+    fn parse_hop_drops_ports_and_brackets() {
+        assert_eq!(parse_hop("203.0.113.9"), Some(ip("203.0.113.9")));
+        assert_eq!(parse_hop("203.0.113.9:8080"), Some(ip("203.0.113.9")));
+        assert_eq!(parse_hop("[2001:db8::1]:443"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_hop("[2001:db8::1]"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_hop("2001:db8::1"), Some(ip("2001:db8::1")));
+        assert_eq!(parse_hop("::ffff:203.0.113.9"), Some(ip("203.0.113.9")));
+    }
+
+    #[test]
+    fn parse_hop_rejects_obfuscated_and_unknown_hops() {
+        assert_eq!(parse_hop("unknown"), None);
+        assert_eq!(parse_hop("_hidden"), None);
+        assert_eq!(parse_hop(""), None);
+    }
+
+    #[test]
+    fn resolve_ignores_headers_from_untrusted_peers() {
+        let proxies = proxies(&["10.0.0.0/8"], Some("cf-connecting-ip"));
+        let headers = ForwardingHeaders {
+            client_ip: Some("198.51.100.1"),
+            x_forwarded_for: vec!["198.51.100.2"],
+            ..Default::default()
+        };
+        assert_eq!(
+            proxies.resolve(ip("203.0.113.9"), &headers),
+            ip("203.0.113.9")
+        );
+    }
+
+    #[test]
+    fn resolve_reads_the_client_header_only_when_configured() {
+        let headers = ForwardingHeaders {
+            client_ip: Some("198.51.100.1"),
+            x_forwarded_for: vec!["198.51.100.2"],
+            ..Default::default()
+        };
+
+        let configured = proxies(&["10.0.0.0/8"], Some("cf-connecting-ip"));
+        assert_eq!(
+            configured.resolve(ip("10.0.0.1"), &headers),
+            ip("198.51.100.1")
+        );
+
+        let unconfigured = proxies(&["10.0.0.0/8"], None);
+        assert_eq!(
+            unconfigured.resolve(ip("10.0.0.1"), &headers),
+            ip("198.51.100.2")
+        );
+    }
+
+    #[test]
+    fn resolve_walks_forwarded_for_past_trusted_hops_only() {
+        let proxies = proxies(&["10.0.0.0/8"], None);
+        // The client made up the first hop; the second proxy vouches for the
+        // untrusted 198.51.100.7, so that's as far back as it goes.
+        let headers = ForwardingHeaders {
+            x_forwarded_for: vec!["192.0.2.66, 198.51.100.7", "10.0.0.2"],
+            ..Default::default()
+        };
+        assert_eq!(
+            proxies.resolve(ip("10.0.0.1"), &headers),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn resolve_prefers_forwarded_over_x_forwarded_for() {
+        let proxies = proxies(&["10.0.0.0/8"], None);
+        let headers = ForwardingHeaders {
+            forwarded: vec!["for=\"[2001:db8::7]:4711\";proto=https, for=10.0.0.2"],
+            x_forwarded_for: vec!["198.51.100.2"],
+            ..Default::default()
+        };
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &headers), ip("2001:db8::7"));
+    }
+
+    #[test]
+    fn resolve_stops_at_a_hop_it_cannot_read() {
+        let proxies = proxies(&["10.0.0.0/8"], None);
+        let headers = ForwardingHeaders {
+            forwarded: vec!["for=198.51.100.7, for=unknown"],
+            ..Default::default()
+        };
+        assert_eq!(proxies.resolve(ip("10.0.0.1"), &headers), ip("10.0.0.1"));
+    }
+}
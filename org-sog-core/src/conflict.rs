@@ -0,0 +1,153 @@
+use std::fmt;
+
+use mongodb::{
+    bson::{Bson, Document},
+    error::{Error, ErrorKind, WriteFailure},
+};
+
+const DUPLICATE_KEY: i32 = 11000;
+
+/// A write rejected by a unique index, as far as the server described it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateKey {
+    /// The unique index that was violated, e.g. `title_ci`.
+    pub index: Option<String>,
+    /// The first field of that index, e.g. `title`.
+    pub field: Option<String>,
+    /// The clashing value as the server printed it.
+    pub value: Option<String>,
+}
+
+/// Recognises duplicate-key failures from their error code, wherever the
+/// driver reports them: single writes, bulk writes and `findAndModify`.
+pub fn duplicate_key(e: &Error) -> Option<DuplicateKey> {
+    let (message, details) = match &*e.kind {
+        ErrorKind::Write(WriteFailure::WriteError(failure)) if failure.code == DUPLICATE_KEY => {
+            (&failure.message, failure.details.as_ref())
+        }
+        ErrorKind::BulkWrite(failure) => {
+            let failure = failure
+                .write_errors
+                .iter()
+                .flatten()
+                .find(|failure| failure.code == DUPLICATE_KEY)?;
+            (&failure.message, failure.details.as_ref())
+        }
+        ErrorKind::Command(failure) if failure.code == DUPLICATE_KEY => (&failure.message, None),
+        _ => return None,
+    };
+
+    let mut conflict = parse_message(message);
+    if let Some((field, value)) = details.and_then(from_details) {
+        conflict.field = Some(field);
+        conflict.value = value;
+    }
+    Some(conflict)
+}
+
+/// The clashing field and value from the `keyPattern` and `keyValue` the
+/// server attaches to the error, which name them exactly where the message
+/// only prints them.
+fn from_details(details: &Document) -> Option<(String, Option<String>)> {
+    let (field, _) = details.get_document("keyPattern").ok()?.iter().next()?;
+    let value = details
+        .get_document("keyValue")
+        .ok()
+        .and_then(|key| key.get(field))
+        .map(|value| match value {
+            Bson::String(value) => format!("{:?}", value),
+            value => value.to_string(),
+        });
+    Some((field.to_owned(), value))
+}
+
+/// Pulls the index and key out of the server's message, which reads
+/// `E11000 duplicate key error collection: db.posts index: title_ci dup key: { title: "Hello" }`.
+/// The index is only ever named here; the key is only taken from here when
+/// the error has no details. Anything that can't be found is left empty
+/// rather than failing the lookup.
+fn parse_message(message: &str) -> DuplicateKey {
+    let index = message
+        .split_once(" index: ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .map(str::to_owned);
+
+    let key = message
+        .split_once("dup key: {")
+        .map(|(_, rest)| rest.trim_end().trim_end_matches('}').trim());
+    let (field, value) = match key.and_then(|key| key.split_once(':')) {
+        Some((field, value)) => (Some(field.trim().to_owned()), Some(value.trim().to_owned())),
+        None => (None, None),
+    };
+
+    DuplicateKey {
+        index,
+        field: field.filter(|field| !field.is_empty()),
+        value,
+    }
+}
+
+impl fmt::Display for DuplicateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} already taken (index {})",
+            self.field.as_deref().unwrap_or("key"),
+            self.index.as_deref().unwrap_or("unknown")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::doc;
+
+    use super::*;
+
+    const MESSAGE: &str = r#"E11000 duplicate key error collection: db.posts index: title_ci dup key: { title: "Hello" }"#;
+
+    #[test]
+    fn message_gives_index_field_and_value() {
+        assert_eq!(
+            parse_message(MESSAGE),
+            DuplicateKey {
+                index: Some("title_ci".to_string()),
+                field: Some("title".to_string()),
+                value: Some(r#""Hello""#.to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn unrecognised_message_leaves_everything_empty() {
+        assert_eq!(
+            parse_message("E11000 duplicate key error"),
+            DuplicateKey {
+                index: None,
+                field: None,
+                value: None,
+            }
+        );
+    }
+
+    #[test]
+    fn details_give_the_first_field_of_the_key() {
+        let details = doc! {
+            "keyPattern": {"slug": 1, "locale": 1},
+            "keyValue": {"slug": "about: us", "locale": "en"},
+        };
+        assert_eq!(
+            from_details(&details),
+            Some(("slug".to_string(), Some(r#""about: us""#.to_string())))
+        );
+    }
+
+    #[test]
+    fn details_without_a_key_pattern_are_ignored() {
+        assert_eq!(from_details(&doc! {"keyValue": {"slug": "about"}}), None);
+        assert_eq!(
+            from_details(&doc! {"keyPattern": {"count": 1}}),
+            Some(("count".to_string(), None))
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, TimeZone, Utc};
+use ring::{constant_time, digest, hmac};
+
+static PEPPER: OnceLock<Option<hmac::Key>> = OnceLock::new();
+
+/// Reads `CREDENTIAL_PEPPER`, the server-side secret mixed into every stored
+/// credential hash so a copy of the database alone can't be checked against
+/// guesses.
+///
+/// Call at startup; otherwise the pepper is read on first use.
+pub fn init() {
+    pepper();
+}
+
+fn pepper() -> Option<&'static hmac::Key> {
+    PEPPER
+        .get_or_init(|| match std::env::var("CREDENTIAL_PEPPER") {
+            Ok(secret) if !secret.is_empty() => {
+                Some(hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()))
+            }
+            _ => {
+                println!("⚠️ CREDENTIAL_PEPPER not set, credentials are stored unpeppered");
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// When unpeppered hashes stop being accepted. Until then they're upgraded
+/// as they're used; whatever hasn't been used by then has to be issued anew.
+const LEGACY_HASHES_ACCEPTED_UNTIL: (i32, u32, u32) = (2027, 4, 1);
+
+/// The hash a secret is stored under: HMAC-SHA256 with the pepper, or plain
+/// SHA-256 without one. Secrets are long and random, so neither needs a salt.
+pub fn hash(secret: &str) -> String {
+    hash_with(pepper(), secret)
+}
+
+/// Every hash `secret` may be stored under, current first. Hashes stored
+/// before the pepper was set stay plain SHA-256 until they're next used, or
+/// until `LEGACY_HASHES_ACCEPTED_UNTIL`.
+pub fn lookup(secret: &str) -> Vec<String> {
+    lookup_with(pepper(), secret, Utc::now())
+}
+
+/// Whether `stored` is a hash of `secret`, compared in constant time.
+pub fn matches(stored: &str, secret: &str) -> bool {
+    matches_with(pepper(), stored, secret, Utc::now())
+}
+
+/// Compares two values without giving away where they first differ.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    constant_time::verify_slices_are_equal(a, b).is_ok()
+}
+
+fn hash_with(pepper: Option<&hmac::Key>, secret: &str) -> String {
+    match pepper {
+        Some(key) => hex::encode(hmac::sign(key, secret.as_bytes())),
+        None => legacy_hash(secret),
+    }
+}
+
+fn lookup_with(pepper: Option<&hmac::Key>, secret: &str, now: DateTime<Utc>) -> Vec<String> {
+    let current = hash_with(pepper, secret);
+    let legacy = legacy_hash(secret);
+    if current == legacy || !legacy_accepted(now) {
+        vec![current]
+    } else {
+        vec![current, legacy]
+    }
+}
+
+fn matches_with(
+    pepper: Option<&hmac::Key>,
+    stored: &str,
+    secret: &str,
+    now: DateTime<Utc>,
+) -> bool {
+    lookup_with(pepper, secret, now)
+        .iter()
+        .any(|candidate| constant_time_eq(stored.as_bytes(), candidate.as_bytes()))
+}
+
+fn legacy_accepted(now: DateTime<Utc>) -> bool {
+    let (year, month, day) = LEGACY_HASHES_ACCEPTED_UNTIL;
+    Utc.with_ymd_and_hms(year, month, day, 0, 0, 0)
+        .single()
+        .is_some_and(|until| now < until)
+}
+
+fn legacy_hash(secret: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "ak_4f1c2b9e8d7a6f5e";
+
+    fn key() -> hmac::Key {
+        hmac::Key::new(hmac::HMAC_SHA256, b"pepper")
+    }
+
+    fn before_cutoff() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap()
+    }
+
+    fn after_cutoff() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2027, 4, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn unpeppered_hash_is_hex_sha256() {
+        assert_eq!(
+            hash_with(None, "abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn peppered_hash_is_hex_hmac_sha256() {
+        let hash = hash_with(Some(&key()), SECRET);
+        assert_eq!(hash.len(), 64);
+        assert_ne!(hash, legacy_hash(SECRET));
+        assert_eq!(hash, hash_with(Some(&key()), SECRET));
+        let other = hmac::Key::new(hmac::HMAC_SHA256, b"another pepper");
+        assert_ne!(hash, hash_with(Some(&other), SECRET));
+    }
+
+    #[test]
+    fn lookup_without_pepper_has_one_hash() {
+        assert_eq!(
+            lookup_with(None, SECRET, before_cutoff()),
+            vec![legacy_hash(SECRET)]
+        );
+        assert_eq!(
+            lookup_with(None, SECRET, after_cutoff()),
+            vec![legacy_hash(SECRET)]
+        );
+    }
+
+    #[test]
+    fn lookup_tries_the_legacy_hash_until_the_cutoff() {
+        let key = key();
+        assert_eq!(
+            lookup_with(Some(&key), SECRET, before_cutoff()),
+            vec![hash_with(Some(&key), SECRET), legacy_hash(SECRET)]
+        );
+        assert_eq!(
+            lookup_with(Some(&key), SECRET, after_cutoff()),
+            vec![hash_with(Some(&key), SECRET)]
+        );
+    }
+
+    #[test]
+    fn matches_current_hashes_only_for_their_secret() {
+        let key = key();
+        let stored = hash_with(Some(&key), SECRET);
+        assert!(matches_with(Some(&key), &stored, SECRET, after_cutoff()));
+        assert!(!matches_with(
+            Some(&key),
+            &stored,
+            "ak_wrong",
+            after_cutoff()
+        ));
+        assert!(!matches_with(None, &stored, SECRET, after_cutoff()));
+    }
+
+    #[test]
+    fn matches_legacy_hashes_until_the_cutoff() {
+        let key = key();
+        let stored = legacy_hash(SECRET);
+        assert!(matches_with(Some(&key), &stored, SECRET, before_cutoff()));
+        assert!(!matches_with(Some(&key), &stored, SECRET, after_cutoff()));
+        assert!(!matches_with(
+            Some(&key),
+            &stored,
+            "ak_wrong",
+            before_cutoff()
+        ));
+    }
+}
@@ -0,0 +1,83 @@
+use std::{fmt, future::Future};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Serialize, Serializer};
+
+/// How response timestamps are written for the current request.
+///
+/// Chosen per request with `?dates=epoch` for Unix milliseconds, or
+/// `?tz=Europe/Berlin` for RFC 3339 in that zone. Without either, and outside
+/// a request, timestamps are RFC 3339 in UTC.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DateFormat {
+    #[default]
+    Rfc3339,
+    EpochMillis,
+    Zoned(Tz),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDateFormat(pub String);
+
+impl fmt::Display for InvalidDateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDateFormat {}
+
+tokio::task_local! {
+    static DATE_FORMAT: DateFormat;
+}
+
+impl DateFormat {
+    /// From the `dates` and `tz` query parameters.
+    pub fn parse(dates: Option<&str>, tz: Option<&str>) -> Result<Self, InvalidDateFormat> {
+        match (dates, tz) {
+            (None | Some("rfc3339"), None) => Ok(DateFormat::Rfc3339),
+            (Some("epoch"), None) => Ok(DateFormat::EpochMillis),
+            (None | Some("rfc3339"), Some(tz)) => tz
+                .parse::<Tz>()
+                .map(DateFormat::Zoned)
+                .map_err(|_| InvalidDateFormat(format!("unknown time zone: {}", tz))),
+            (Some("epoch"), Some(_)) => Err(InvalidDateFormat(
+                "tz only applies to rfc3339 dates".to_string(),
+            )),
+            (Some(other), _) => Err(InvalidDateFormat(format!(
+                "dates must be rfc3339 or epoch, not {}",
+                other
+            ))),
+        }
+    }
+
+    /// Runs `f` with timestamps written in this format.
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        DATE_FORMAT.scope(self, f).await
+    }
+
+    fn current() -> Self {
+        DATE_FORMAT.try_with(|format| *format).unwrap_or_default()
+    }
+}
+
+/// For `#[serde(serialize_with)]` on response timestamps.
+pub fn serialize<S: Serializer>(at: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    match DateFormat::current() {
+        DateFormat::Rfc3339 => at.serialize(serializer),
+        DateFormat::EpochMillis => serializer.serialize_i64(at.timestamp_millis()),
+        DateFormat::Zoned(tz) => serializer.serialize_str(&at.with_timezone(&tz).to_rfc3339()),
+    }
+}
+
+/// As [`serialize`], for optional timestamps.
+pub fn serialize_option<S: Serializer>(
+    at: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match at {
+        Some(at) => serialize(at, serializer),
+        None => serializer.serialize_none(),
+    }
+}
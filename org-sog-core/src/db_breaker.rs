@@ -0,0 +1,150 @@
+use std::{fmt, future::Future, time::Duration};
+
+use mongodb::error::{Error, ErrorKind, Result};
+
+use crate::circuit_breaker::{BreakerConfig, BreakerError, BreakerMetrics, CircuitBreaker};
+
+/// Long enough for index builds and bulk restores; the breaker's timeout is
+/// only there to catch operations that hang.
+const CALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Why the breaker stopped an operation. It comes back inside a custom
+/// MongoDB error, see [`unavailable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unavailable {
+    /// The breaker is open and the operation was not sent.
+    CircuitOpen,
+    /// The operation ran past the breaker's timeout.
+    Timeout,
+}
+
+impl fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unavailable::CircuitOpen => write!(f, "database circuit open"),
+            Unavailable::Timeout => write!(f, "database operation timed out"),
+        }
+    }
+}
+
+/// A circuit breaker around a service's MongoDB operations.
+///
+/// While the database is unreachable every operation would otherwise wait
+/// out the driver's server selection timeout; once the breaker opens they
+/// fail at once. Only errors that say the database couldn't be reached count
+/// against it: a duplicate key or failed validation is still an answer.
+#[derive(Debug)]
+pub struct DbBreaker {
+    breaker: CircuitBreaker,
+}
+
+impl Default for DbBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DbBreaker {
+    pub fn new() -> Self {
+        Self {
+            breaker: CircuitBreaker::new(
+                "mongodb",
+                BreakerConfig {
+                    call_timeout: CALL_TIMEOUT,
+                    ..BreakerConfig::default()
+                },
+            ),
+        }
+    }
+
+    pub async fn run<T, F>(&self, operation: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        self.breaker
+            .call_with(operation, is_failure)
+            .await
+            .map_err(|e| match e {
+                BreakerError::Inner(e) => e,
+                BreakerError::Open => Error::custom(Unavailable::CircuitOpen),
+                BreakerError::Timeout => Error::custom(Unavailable::Timeout),
+            })
+    }
+
+    pub fn metrics(&self) -> BreakerMetrics {
+        self.breaker.metrics()
+    }
+}
+
+/// Postfix [`DbBreaker::run`], so an operation still reads in call order:
+/// `collection.find_one(filter, None).guarded(&breaker).await`.
+pub trait Guarded<T>: Future<Output = Result<T>> + Send + Sized {
+    fn guarded(self, breaker: &DbBreaker) -> impl Future<Output = Result<T>> + Send;
+}
+
+impl<T, F> Guarded<T> for F
+where
+    T: Send,
+    F: Future<Output = Result<T>> + Send,
+{
+    fn guarded(self, breaker: &DbBreaker) -> impl Future<Output = Result<T>> + Send {
+        breaker.run(self)
+    }
+}
+
+/// Whether the breaker, not the database, is behind `e`.
+pub fn unavailable(e: &Error) -> Option<Unavailable> {
+    e.get_custom::<Unavailable>().copied()
+}
+
+fn is_failure(e: &Error) -> bool {
+    matches!(
+        *e.kind,
+        ErrorKind::Io(_)
+            | ErrorKind::ConnectionPoolCleared { .. }
+            | ErrorKind::ServerSelection { .. }
+            | ErrorKind::DnsResolve { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unreachable() -> Error {
+        Error::from(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+    }
+
+    /// Stands in for an error the database answered with.
+    fn refused() -> Error {
+        Error::custom("duplicate key")
+    }
+
+    #[test]
+    fn only_connection_errors_are_failures() {
+        assert!(is_failure(&unreachable()));
+        assert!(!is_failure(&refused()));
+    }
+
+    #[tokio::test]
+    async fn open_breaker_fails_with_unavailable() {
+        let breaker = DbBreaker::new();
+        for _ in 0..BreakerConfig::default().failure_threshold {
+            let _ = breaker.run(async { Err::<(), _>(unreachable()) }).await;
+        }
+
+        let e = async { Ok(()) }.guarded(&breaker).await.unwrap_err();
+        assert_eq!(unavailable(&e), Some(Unavailable::CircuitOpen));
+        assert_eq!(unavailable(&unreachable()), None);
+    }
+
+    #[tokio::test]
+    async fn refused_operations_keep_it_closed() {
+        let breaker = DbBreaker::new();
+        for _ in 0..2 * BreakerConfig::default().failure_threshold {
+            let _ = breaker.run(async { Err::<(), _>(refused()) }).await;
+        }
+
+        assert!(async { Ok(()) }.guarded(&breaker).await.is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use mongodb::{
+    bson::{Bson, Document},
+    event::command::{
+        CommandEventHandler, CommandFailedEvent, CommandStartedEvent, CommandSucceededEvent,
+    },
+    options::ClientOptions,
+};
+
+use crate::{
+    slow_query::{Candidate, SlowQueryLog},
+    trace,
+};
+
+/// What [`DbMetrics::render`] returns.
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+const METRIC: &str = "mongodb_command_duration_seconds";
+/// Upper bounds of the histogram buckets, in seconds.
+const BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+/// Commands still waiting on a reply beyond this many are forgotten, so
+/// ones whose reply never came can't pile up.
+const MAX_IN_FLIGHT: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    operation: String,
+    collection: String,
+    outcome: &'static str,
+}
+
+#[derive(Debug, Clone)]
+struct Exemplar {
+    trace_id: String,
+    seconds: f64,
+    /// Unix time it was recorded at.
+    at: f64,
+}
+
+#[derive(Debug)]
+struct Histogram {
+    /// Per bucket, not cumulative; the last is `+Inf`.
+    counts: Vec<u64>,
+    sum: f64,
+    /// The latest traced observation per bucket.
+    exemplars: Vec<Option<Exemplar>>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; BUCKETS.len() + 1],
+            sum: 0.0,
+            exemplars: vec![None; BUCKETS.len() + 1],
+        }
+    }
+
+    fn observe(&mut self, seconds: f64, trace_id: Option<String>) {
+        let bucket = BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += seconds;
+        if let Some(trace_id) = trace_id {
+            self.exemplars[bucket] = Some(Exemplar {
+                trace_id,
+                seconds,
+                at: unix_now(),
+            });
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Started {
+    operation: String,
+    collection: String,
+    trace_id: Option<String>,
+    /// The command itself, kept when the slow query log may want it.
+    command: Option<Document>,
+}
+
+/// Latency histograms for every command sent to MongoDB, by operation,
+/// collection and outcome.
+///
+/// Fed by the driver's command monitoring, so queries are measured however
+/// they are issued; install it with [`DbMetrics::install`] before creating
+/// the client. Observations made within a [`trace::scope`] carry its trace
+/// id as an exemplar.
+#[derive(Debug, Default)]
+pub struct DbMetrics {
+    in_flight: Mutex<HashMap<i32, Started>>,
+    series: Mutex<BTreeMap<SeriesKey, Histogram>>,
+    slow_queries: Option<Arc<SlowQueryLog>>,
+}
+
+impl DbMetrics {
+    /// Also hands successful commands to `slow_queries`.
+    pub fn with_slow_query_log(slow_queries: Arc<SlowQueryLog>) -> Self {
+        Self {
+            slow_queries: Some(slow_queries),
+            ..Self::default()
+        }
+    }
+
+    pub fn install(self: &Arc<Self>, options: &mut ClientOptions) {
+        options.command_event_handler = Some(self.clone());
+    }
+
+    /// Every series in the OpenMetrics text format, served as [`CONTENT_TYPE`].
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# TYPE {} histogram", METRIC);
+        let _ = writeln!(out, "# UNIT {} seconds", METRIC);
+        let _ = writeln!(out, "# HELP {} Time taken by MongoDB commands.", METRIC);
+
+        let series = match self.series.lock() {
+            Ok(series) => series,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for (key, histogram) in series.iter() {
+            let labels = format!(
+                "operation=\"{}\",collection=\"{}\",outcome=\"{}\"",
+                escape(&key.operation),
+                escape(&key.collection),
+                key.outcome
+            );
+
+            let mut cumulative = 0;
+            for (bucket, count) in histogram.counts.iter().enumerate() {
+                cumulative += count;
+                let le = match BUCKETS.get(bucket) {
+                    Some(bound) => format!("{:?}", bound),
+                    None => "+Inf".to_string(),
+                };
+                let _ = write!(
+                    out,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    METRIC, labels, le, cumulative
+                );
+                if let Some(exemplar) = &histogram.exemplars[bucket] {
+                    let _ = write!(
+                        out,
+                        " # {{trace_id=\"{}\"}} {} {:.3}",
+                        exemplar.trace_id, exemplar.seconds, exemplar.at
+                    );
+                }
+                out.push('\n');
+            }
+            let _ = writeln!(out, "{}_count{{{}}} {}", METRIC, labels, cumulative);
+            let _ = writeln!(out, "{}_sum{{{}}} {}", METRIC, labels, histogram.sum);
+        }
+
+        out.push_str("# EOF\n");
+        out
+    }
+
+    fn finish(&self, request_id: i32, duration: Duration, outcome: &'static str) {
+        let started = match self.in_flight.lock() {
+            Ok(mut in_flight) => in_flight.remove(&request_id),
+            Err(_) => None,
+        };
+        let Some(started) = started else {
+            return;
+        };
+
+        if let Ok(mut series) = self.series.lock() {
+            let key = SeriesKey {
+                operation: started.operation.clone(),
+                collection: started.collection.clone(),
+                outcome,
+            };
+            series
+                .entry(key)
+                .or_insert_with(Histogram::new)
+                .observe(duration.as_secs_f64(), started.trace_id.clone());
+        }
+
+        if let (Some(slow_queries), Some(command)) = (&self.slow_queries, started.command) {
+            if outcome == "success" {
+                let candidate = Candidate {
+                    operation: started.operation,
+                    collection: started.collection,
+                    command,
+                    trace_id: started.trace_id,
+                };
+                slow_queries.observe(candidate, duration);
+            }
+        }
+    }
+}
+
+impl CommandEventHandler for DbMetrics {
+    fn handle_command_started_event(&self, event: CommandStartedEvent) {
+        let collection = collection_of(&event.command_name, &event.command);
+        let wanted = self
+            .slow_queries
+            .as_ref()
+            .is_some_and(|slow_queries| slow_queries.wants(&event.command_name, &collection));
+        let started = Started {
+            collection,
+            operation: event.command_name,
+            trace_id: trace::current(),
+            command: wanted.then_some(event.command),
+        };
+
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            if in_flight.len() >= MAX_IN_FLIGHT {
+                in_flight.clear();
+            }
+            in_flight.insert(event.request_id, started);
+        }
+    }
+
+    fn handle_command_succeeded_event(&self, event: CommandSucceededEvent) {
+        self.finish(event.request_id, event.duration, "success");
+    }
+
+    fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+        self.finish(event.request_id, event.duration, "failure");
+    }
+}
+
+/// The collection a command works on: the value of its name field, or for
+/// `getMore` its `collection` field. Empty for database-wide commands.
+fn collection_of(command_name: &str, command: &Document) -> String {
+    let collection = match command.get(command_name) {
+        Some(Bson::String(collection)) => Some(collection.as_str()),
+        _ => command.get_str("collection").ok(),
+    };
+    collection.unwrap_or_default().to_owned()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs_f64())
+        .unwrap_or_default()
+}
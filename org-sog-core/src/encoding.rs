@@ -0,0 +1,83 @@
+use std::fmt;
+
+use serde_json::Value;
+
+/// Response bodies other than JSON that a client can ask for through `Accept`.
+///
+/// Each encoding is only compiled in with its cargo feature (`xml`, `msgpack`,
+/// `cbor`), so JSON-only builds carry none of the encoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    #[cfg(feature = "xml")]
+    Xml,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeError(pub String);
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+impl Encoding {
+    /// The first enabled encoding an `Accept` header lists, if any.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        accept
+            .split(',')
+            .find_map(|range| match range.split(';').next()?.trim() {
+                #[cfg(feature = "xml")]
+                "application/xml" | "text/xml" => Some(Encoding::Xml),
+                #[cfg(feature = "msgpack")]
+                "application/msgpack" | "application/x-msgpack" => Some(Encoding::MessagePack),
+                #[cfg(feature = "cbor")]
+                "application/cbor" => Some(Encoding::Cbor),
+                _ => None,
+            })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "xml")]
+            Encoding::Xml => "application/xml",
+            #[cfg(feature = "msgpack")]
+            Encoding::MessagePack => "application/msgpack",
+            #[cfg(feature = "cbor")]
+            Encoding::Cbor => "application/cbor",
+        }
+    }
+
+    /// Re-encodes a JSON response body. XML documents are rooted at
+    /// `<response>`, with one element per field and per array item; the
+    /// binary formats keep field names, so they decode to the same shape.
+    #[cfg_attr(
+        not(any(feature = "xml", feature = "msgpack", feature = "cbor")),
+        allow(unused_variables)
+    )]
+    pub fn encode(&self, body: &Value) -> Result<Vec<u8>, EncodeError> {
+        match *self {
+            #[cfg(feature = "xml")]
+            Encoding::Xml => quick_xml::se::to_string_with_root("response", body)
+                .map(|xml| format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, xml).into_bytes())
+                .map_err(|e| EncodeError(e.to_string())),
+            #[cfg(feature = "msgpack")]
+            Encoding::MessagePack => {
+                rmp_serde::to_vec_named(body).map_err(|e| EncodeError(e.to_string()))
+            }
+            #[cfg(feature = "cbor")]
+            Encoding::Cbor => {
+                let mut encoded = Vec::new();
+                ciborium::ser::into_writer(body, &mut encoded)
+                    .map_err(|e| EncodeError(e.to_string()))?;
+                Ok(encoded)
+            }
+        }
+    }
+}
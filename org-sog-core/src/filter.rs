@@ -0,0 +1,232 @@
+use mongodb::bson::{doc, Bson, Document};
+
+/// A field of a model that filters may name. Models list theirs as an enum,
+/// so a filter can't name a field the model doesn't have.
+pub trait Field: Copy {
+    /// The field as stored, dotted for fields of embedded documents.
+    fn path(&self) -> &'static str;
+}
+
+/// A condition on documents with fields `F`, built up with the constructors
+/// below and turned into a MongoDB query with [`Filter::to_document`].
+/// Building one needs no database, so filters can be checked on their own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter<F: Field> {
+    /// Every document.
+    All,
+    Eq(F, Bson),
+    In(F, Vec<Bson>),
+    NotIn(F, Vec<Bson>),
+    /// From `from` inclusive up to `to` exclusive; either end may be open.
+    Range {
+        field: F,
+        from: Option<Bson>,
+        to: Option<Bson>,
+    },
+    /// The field is set to something other than null.
+    Present(F),
+    /// Matches the collection's text index.
+    Text(String),
+    And(Vec<Filter<F>>),
+    Or(Vec<Filter<F>>),
+}
+
+impl<F: Field> Filter<F> {
+    pub fn eq(field: F, value: impl Into<Bson>) -> Self {
+        Filter::Eq(field, value.into())
+    }
+
+    pub fn is_in<V: Into<Bson>>(field: F, values: impl IntoIterator<Item = V>) -> Self {
+        Filter::In(field, values.into_iter().map(Into::into).collect())
+    }
+
+    pub fn not_in<V: Into<Bson>>(field: F, values: impl IntoIterator<Item = V>) -> Self {
+        Filter::NotIn(field, values.into_iter().map(Into::into).collect())
+    }
+
+    pub fn range<V: Into<Bson>>(field: F, from: Option<V>, to: Option<V>) -> Self {
+        Filter::Range {
+            field,
+            from: from.map(Into::into),
+            to: to.map(Into::into),
+        }
+    }
+
+    pub fn present(field: F) -> Self {
+        Filter::Present(field)
+    }
+
+    pub fn text(query: impl Into<String>) -> Self {
+        Filter::Text(query.into())
+    }
+
+    /// Documents matching this and `other`.
+    pub fn and(self, other: Self) -> Self {
+        match (self, other) {
+            (Filter::All, other) | (other, Filter::All) => other,
+            (Filter::And(mut filters), other) => {
+                filters.push(other);
+                Filter::And(filters)
+            }
+            (filter, other) => Filter::And(vec![filter, other]),
+        }
+    }
+
+    /// Documents matching this or `other`.
+    pub fn or(self, other: Self) -> Self {
+        match (self, other) {
+            (Filter::Or(mut filters), other) => {
+                filters.push(other);
+                Filter::Or(filters)
+            }
+            (filter, other) => Filter::Or(vec![filter, other]),
+        }
+    }
+
+    /// The query for this filter. Conditions on different fields are kept
+    /// in one document, as they would be written by hand; `$and` is only
+    /// used when two of them would clash.
+    pub fn to_document(&self) -> Document {
+        match self {
+            Filter::All => doc! {},
+            Filter::Eq(field, value) => doc! {field.path(): value.clone()},
+            Filter::In(field, values) => doc! {field.path(): {"$in": values.clone()}},
+            Filter::NotIn(field, values) => doc! {field.path(): {"$nin": values.clone()}},
+            Filter::Range { field, from, to } => {
+                let mut bounds = Document::new();
+                if let Some(from) = from {
+                    bounds.insert("$gte", from.clone());
+                }
+                if let Some(to) = to {
+                    bounds.insert("$lt", to.clone());
+                }
+                if bounds.is_empty() {
+                    return doc! {};
+                }
+                doc! {field.path(): bounds}
+            }
+            Filter::Present(field) => doc! {field.path(): {"$ne": null}},
+            Filter::Text(query) => doc! {"$text": {"$search": query}},
+            Filter::And(filters) => {
+                let parts: Vec<Document> = filters
+                    .iter()
+                    .map(Filter::to_document)
+                    .filter(|part| !part.is_empty())
+                    .collect();
+                let mut merged = Document::new();
+                for part in &parts {
+                    if part.keys().any(|key| merged.contains_key(key)) {
+                        return doc! {"$and": parts};
+                    }
+                    merged.extend(part.clone());
+                }
+                merged
+            }
+            Filter::Or(filters) => {
+                let parts: Vec<Document> = filters.iter().map(Filter::to_document).collect();
+                match parts.as_slice() {
+                    // No alternatives match nothing; every document has an `_id`.
+                    [] => doc! {"_id": {"$exists": false}},
+                    [part] => part.clone(),
+                    _ if parts.iter().any(Document::is_empty) => doc! {},
+                    _ => doc! {"$or": parts},
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum PostField {
+        Author,
+        CreatedAt,
+        Tags,
+    }
+
+    impl Field for PostField {
+        fn path(&self) -> &'static str {
+            match self {
+                PostField::Author => "author",
+                PostField::CreatedAt => "createdAt",
+                PostField::Tags => "tags",
+            }
+        }
+    }
+
+    #[test]
+    fn conditions_on_different_fields_share_one_document() {
+        let filter = Filter::eq(PostField::Author, "ada")
+            .and(Filter::is_in(PostField::Tags, ["rust", "mongo"]))
+            .and(Filter::All);
+
+        assert_eq!(
+            filter.to_document(),
+            doc! {"author": "ada", "tags": {"$in": ["rust", "mongo"]}}
+        );
+    }
+
+    #[test]
+    fn clashing_conditions_fall_back_to_and() {
+        let filter = Filter::is_in(PostField::Tags, ["rust"])
+            .and(Filter::eq(PostField::Author, "ada"))
+            .and(Filter::not_in(PostField::Tags, ["draft"]));
+
+        assert_eq!(
+            filter.to_document(),
+            doc! {"$and": [
+                {"tags": {"$in": ["rust"]}},
+                {"author": "ada"},
+                {"tags": {"$nin": ["draft"]}},
+            ]}
+        );
+    }
+
+    #[test]
+    fn or_without_alternatives_matches_nothing() {
+        let filter: Filter<PostField> = Filter::Or(Vec::new());
+
+        assert_eq!(filter.to_document(), doc! {"_id": {"$exists": false}});
+    }
+
+    #[test]
+    fn or_with_an_empty_alternative_matches_everything() {
+        let filter = Filter::eq(PostField::Author, "ada").or(Filter::All);
+        assert_eq!(filter.to_document(), doc! {});
+
+        let open_range = Filter::range::<i32>(PostField::CreatedAt, None, None);
+        let filter = Filter::present(PostField::Author).or(open_range);
+        assert_eq!(filter.to_document(), doc! {});
+    }
+
+    #[test]
+    fn or_with_one_alternative_is_that_alternative() {
+        let filter = Filter::Or(vec![Filter::eq(PostField::Author, "ada")]);
+
+        assert_eq!(filter.to_document(), doc! {"author": "ada"});
+    }
+
+    #[test]
+    fn range_with_both_ends_open_is_no_condition() {
+        let filter = Filter::range::<i32>(PostField::CreatedAt, None, None);
+        assert_eq!(filter.to_document(), doc! {});
+
+        let filter = Filter::eq(PostField::Author, "ada").and(filter);
+        assert_eq!(filter.to_document(), doc! {"author": "ada"});
+    }
+
+    #[test]
+    fn range_keeps_the_ends_it_has() {
+        let filter = Filter::range(PostField::CreatedAt, Some(1), None);
+        assert_eq!(filter.to_document(), doc! {"createdAt": {"$gte": 1}});
+
+        let filter = Filter::range(PostField::CreatedAt, Some(1), Some(5));
+        assert_eq!(
+            filter.to_document(),
+            doc! {"createdAt": {"$gte": 1, "$lt": 5}}
+        );
+    }
+}
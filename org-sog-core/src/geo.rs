@@ -0,0 +1,93 @@
+use std::net::IpAddr;
+
+use maxminddb::{geoip2, Reader};
+use serde::{Deserialize, Serialize};
+
+/// Where an address is, as coarse as is useful: ISO country and subdivision
+/// codes, never a city or coordinates.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GeoLocation {
+    pub country: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+}
+
+/// A source of locations for addresses.
+pub trait GeoLookup: Send + Sync {
+    fn locate(&self, ip: IpAddr) -> Option<GeoLocation>;
+}
+
+/// A MaxMind-format database (GeoLite2/GeoIP2 City or Country) read from disk.
+pub struct MaxMindDb {
+    reader: Reader<Vec<u8>>,
+}
+
+impl MaxMindDb {
+    pub fn open(path: &str) -> Result<Self, maxminddb::MaxMindDBError> {
+        Ok(Self {
+            reader: Reader::open_readfile(path)?,
+        })
+    }
+}
+
+impl GeoLookup for MaxMindDb {
+    fn locate(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let city: geoip2::City = self.reader.lookup(ip).ok()?;
+        let country = city.country?.iso_code?.to_owned();
+        let region = city
+            .subdivisions
+            .and_then(|subdivisions| subdivisions.into_iter().next())
+            .and_then(|subdivision| subdivision.iso_code)
+            .map(|code| format!("{}-{}", country, code));
+        Some(GeoLocation { country, region })
+    }
+}
+
+/// Geo-IP enrichment, from the database at `GEOIP_DB`.
+///
+/// `GEOIP_ENABLED=false` turns it off without removing the database, for
+/// deployments that mustn't record where people are; nothing is looked up
+/// or stored then.
+pub struct Geo {
+    lookup: Option<Box<dyn GeoLookup>>,
+}
+
+impl Geo {
+    pub fn init() -> Self {
+        let enabled = std::env::var("GEOIP_ENABLED")
+            .map(|value| value != "false" && value != "0")
+            .unwrap_or(true);
+        let lookup = match std::env::var("GEOIP_DB") {
+            Ok(path) if enabled => {
+                let db = MaxMindDb::open(&path)
+                    .unwrap_or_else(|e| panic!("GEOIP_DB {} could not be opened: {}", path, e));
+                Some(Box::new(db) as Box<dyn GeoLookup>)
+            }
+            _ => None,
+        };
+        Self::with_lookup(lookup)
+    }
+
+    pub fn with_lookup(lookup: Option<Box<dyn GeoLookup>>) -> Self {
+        Self { lookup }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.lookup.is_some()
+    }
+
+    /// The location of a public address; private and loopback ones have none.
+    pub fn locate(&self, ip: IpAddr) -> Option<GeoLocation> {
+        let lookup = self.lookup.as_ref()?;
+        let public = match ip {
+            IpAddr::V4(v4) => {
+                !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified())
+            }
+            IpAddr::V6(v6) => !(v6.is_loopback() || v6.is_unspecified()),
+        };
+        if !public {
+            return None;
+        }
+        lookup.locate(ip)
+    }
+}
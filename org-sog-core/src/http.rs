@@ -0,0 +1,237 @@
+use std::{fmt, time::Duration};
+
+use rand::Rng;
+pub use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::circuit_breaker::{BreakerConfig, BreakerError, BreakerMetrics, CircuitBreaker};
+use crate::trace;
+
+/// W3C trace-context header attached to every outbound request.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+#[derive(Debug, Clone, Copy)]
+pub struct HttpClientConfig {
+    /// Limit for a single attempt, including reading the response.
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// Extra attempts made for idempotent requests that fail transiently.
+    pub max_retries: u32,
+    /// Base delay for exponential backoff; each wait is jittered between zero and the cap.
+    pub retry_backoff: Duration,
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            connect_timeout: Duration::from_secs(2),
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(100),
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 16,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    /// The dependency's circuit breaker is open; nothing was sent.
+    CircuitOpen,
+    Timeout,
+    Connect(String),
+    /// The dependency answered with a non-success status.
+    Status(StatusCode),
+    /// The response body didn't match the expected shape.
+    Decode(String),
+    Request(String),
+}
+
+impl HttpError {
+    /// Whether trying again stands a chance of succeeding.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            HttpError::Timeout | HttpError::Connect(_) => true,
+            HttpError::Status(status) => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            HttpError::CircuitOpen | HttpError::Decode(_) | HttpError::Request(_) => false,
+        }
+    }
+
+    /// Whether this says the dependency is unwell, and so counts against its
+    /// circuit breaker. Client errors are answers, however unwelcome.
+    pub fn is_failure(&self) -> bool {
+        match self {
+            HttpError::Timeout | HttpError::Connect(_) | HttpError::Request(_) => true,
+            HttpError::Status(status) => status.is_server_error(),
+            HttpError::CircuitOpen | HttpError::Decode(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::CircuitOpen => write!(f, "circuit open"),
+            HttpError::Timeout => write!(f, "request timed out"),
+            HttpError::Connect(e) => write!(f, "connection failed: {}", e),
+            HttpError::Status(status) => write!(f, "unexpected status {}", status),
+            HttpError::Decode(e) => write!(f, "invalid response body: {}", e),
+            HttpError::Request(e) => write!(f, "request failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl From<reqwest::Error> for HttpError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            HttpError::Timeout
+        } else if e.is_connect() {
+            HttpError::Connect(e.to_string())
+        } else if let Some(status) = e.status() {
+            HttpError::Status(status)
+        } else if e.is_decode() {
+            HttpError::Decode(e.to_string())
+        } else {
+            HttpError::Request(e.to_string())
+        }
+    }
+}
+
+/// Outbound HTTP client for calls to other services.
+///
+/// Connections are pooled, every attempt is bounded by a timeout and guarded by
+/// a circuit breaker, and idempotent requests are retried with jittered backoff.
+pub struct HttpClient {
+    inner: reqwest::Client,
+    config: HttpClientConfig,
+    breaker: CircuitBreaker,
+}
+
+impl HttpClient {
+    /// `name` identifies the dependency in breaker metrics.
+    pub fn new(name: &'static str, config: HttpClientConfig) -> Self {
+        let inner = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout)
+            .pool_idle_timeout(config.pool_idle_timeout)
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .build()
+            .expect("HTTP client configuration is valid");
+
+        let breaker = CircuitBreaker::new(
+            name,
+            BreakerConfig {
+                call_timeout: config.timeout,
+                ..BreakerConfig::default()
+            },
+        );
+
+        Self {
+            inner,
+            config,
+            breaker,
+        }
+    }
+
+    /// Sends a request and fails on non-success statuses.
+    ///
+    /// `build` fills in the request and is called again for each retry.
+    pub async fn send<F>(&self, method: Method, url: &str, build: F) -> Result<Response, HttpError>
+    where
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        let max_retries = if is_idempotent(&method) {
+            self.config.max_retries
+        } else {
+            0
+        };
+        // Part of the caller's trace when there is one, else a new one.
+        let trace_id = trace::current().unwrap_or_else(trace::new_trace_id);
+
+        let mut attempt = 0;
+        loop {
+            let request = build(self.inner.request(method.clone(), url)).header(
+                TRACEPARENT_HEADER,
+                format!("00-{}-{}-01", trace_id, trace::random_hex(8)),
+            );
+
+            let error = match self
+                .breaker
+                .call_with(
+                    async { Ok::<_, HttpError>(request.send().await?.error_for_status()?) },
+                    HttpError::is_failure,
+                )
+                .await
+            {
+                Ok(response) => return Ok(response),
+                Err(BreakerError::Open) => return Err(HttpError::CircuitOpen),
+                Err(BreakerError::Timeout) => HttpError::Timeout,
+                Err(BreakerError::Inner(e)) => e,
+            };
+
+            if attempt >= max_retries || !error.is_transient() {
+                return Err(error);
+            }
+            attempt += 1;
+            tokio::time::sleep(self.backoff(attempt)).await;
+        }
+    }
+
+    /// Like [`send`](Self::send), decoding the response body as JSON.
+    pub async fn json<T, F>(&self, method: Method, url: &str, build: F) -> Result<T, HttpError>
+    where
+        T: DeserializeOwned,
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        self.send(method, url, build)
+            .await?
+            .json()
+            .await
+            .map_err(|e| HttpError::Decode(e.to_string()))
+    }
+
+    /// Sends one request outside the breaker and reports its status, whatever
+    /// it is. For checking URLs on many unrelated hosts, where one failing
+    /// says nothing about the next.
+    pub async fn probe(&self, method: Method, url: &str) -> Result<StatusCode, HttpError> {
+        Ok(self.inner.request(method, url).send().await?.status())
+    }
+
+    pub fn metrics(&self) -> BreakerMetrics {
+        self.breaker.metrics()
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let cap = self.config.retry_backoff * 2u32.saturating_pow(attempt - 1);
+        rand::thread_rng().gen_range(Duration::ZERO..=cap)
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transport_errors_and_server_errors_are_failures() {
+        assert!(HttpError::Timeout.is_failure());
+        assert!(HttpError::Connect("refused".to_string()).is_failure());
+        assert!(HttpError::Status(StatusCode::BAD_GATEWAY).is_failure());
+
+        assert!(!HttpError::Status(StatusCode::NOT_FOUND).is_failure());
+        assert!(!HttpError::Status(StatusCode::UNAUTHORIZED).is_failure());
+        assert!(!HttpError::Status(StatusCode::TOO_MANY_REQUESTS).is_failure());
+    }
+}
@@ -0,0 +1,97 @@
+use std::{collections::HashMap, fmt::Display, future::Future, path::Path, sync::OnceLock};
+
+use crate::locale;
+
+type Catalog = HashMap<String, String>;
+
+/// Catalogs that ship with the services. English needs none: it is the
+/// wording written at each call site.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("de", include_str!("../locales/de.json")),
+    ("es", include_str!("../locales/es.json")),
+];
+
+static CATALOGS: OnceLock<HashMap<String, Catalog>> = OnceLock::new();
+
+tokio::task_local! {
+    static LANGUAGES: Vec<String>;
+}
+
+/// Loads the message catalogs: the built-in ones, then any `<lang>.json` in
+/// `LOCALES_DIR`, which add languages or override built-in messages.
+///
+/// Call at startup so a broken catalog fails there; otherwise the catalogs
+/// are loaded on first use.
+pub fn init() {
+    catalogs();
+}
+
+/// Runs `f` with messages in the languages an `Accept-Language` header asks
+/// for, regional ones falling back to their language (`es-MX` → `es`).
+pub async fn scope<F: Future>(accept_language: Option<&str>, f: F) -> F::Output {
+    let ranges = accept_language.map(locale::parse).unwrap_or_default();
+    let languages = locale::fallback_chain(&ranges);
+    LANGUAGES.scope(languages, f).await
+}
+
+/// The message for `code` in the caller's preferred language, falling back to
+/// `english`. `{0}`, `{1}`, ... in the chosen template are filled from `args`.
+pub fn message(code: &str, english: &str, args: &[&dyn Display]) -> String {
+    let translated = LANGUAGES
+        .try_with(|languages| {
+            languages
+                .iter()
+                .take_while(|language| *language != "en")
+                .find_map(|language| catalogs().get(language)?.get(code).cloned())
+        })
+        .ok()
+        .flatten();
+
+    let mut rendered = translated.unwrap_or_else(|| english.to_owned());
+    for (i, arg) in args.iter().enumerate() {
+        rendered = rendered.replace(&format!("{{{}}}", i), &arg.to_string());
+    }
+    rendered
+}
+
+fn catalogs() -> &'static HashMap<String, Catalog> {
+    CATALOGS.get_or_init(|| {
+        let mut catalogs: HashMap<String, Catalog> = BUILT_IN
+            .iter()
+            .map(|(language, json)| {
+                let catalog = serde_json::from_str(json)
+                    .unwrap_or_else(|e| panic!("built-in {} catalog is invalid: {}", language, e));
+                (language.to_string(), catalog)
+            })
+            .collect();
+
+        if let Ok(dir) = std::env::var("LOCALES_DIR") {
+            load_dir(Path::new(&dir), &mut catalogs);
+        }
+        catalogs
+    })
+}
+
+fn load_dir(dir: &Path, catalogs: &mut HashMap<String, Catalog>) {
+    let entries = std::fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("LOCALES_DIR {} can't be read: {}", dir.display(), e));
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(language) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+
+        let catalog: Catalog = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| panic!("catalog {} is invalid: {}", path.display(), e));
+        catalogs
+            .entry(language.to_ascii_lowercase())
+            .or_default()
+            .extend(catalog);
+    }
+}
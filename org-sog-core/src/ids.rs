@@ -0,0 +1,141 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use mongodb::bson::{oid::ObjectId, Bson};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Characters NanoIDs are made of, all safe in a URL as they are.
+const NANOID_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789_-";
+const NANOID_LEN: usize = 21;
+
+/// How new documents are keyed, from `ID_STRATEGY`:
+///
+/// - `objectid` (default): MongoDB's own 12-byte ids, as 24 hex characters.
+/// - `uuidv7`: time-ordered UUIDs, stored as strings that sort by creation
+///   under the default `_id` index.
+/// - `nanoid`: 21 URL-safe characters, shorter in links but in no order.
+///
+/// Ids already stored keep working after a switch, so a deployment can move
+/// from ObjectIds without rewriting its documents.
+///
+/// Only resources nothing else refers to by id follow it, which so far is
+/// the blog's pages. Posts and users stay on ObjectIds whatever is set:
+/// comments and bookmarks store post ids, sessions, memberships and posts'
+/// authors store user ids, all as ObjectIds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdStrategy {
+    ObjectId,
+    UuidV7,
+    NanoId,
+}
+
+impl IdStrategy {
+    pub fn init() -> Self {
+        match std::env::var("ID_STRATEGY").as_deref() {
+            Ok("objectid") | Err(_) => IdStrategy::ObjectId,
+            Ok("uuidv7") => IdStrategy::UuidV7,
+            Ok("nanoid") => IdStrategy::NanoId,
+            Ok(other) => panic!(
+                "ID_STRATEGY must be 'objectid', 'uuidv7' or 'nanoid', got '{}'",
+                other
+            ),
+        }
+    }
+
+    pub fn generate(&self) -> Id {
+        match self {
+            IdStrategy::ObjectId => Id::ObjectId(ObjectId::new()),
+            IdStrategy::UuidV7 => Id::String(uuid_v7()),
+            IdStrategy::NanoId => Id::String(nanoid()),
+        }
+    }
+
+    /// The id `raw` names, if it is one this strategy makes or an ObjectId
+    /// from before the deployment switched. UUIDs are matched in lower case,
+    /// as they are stored.
+    pub fn parse(&self, raw: &str) -> Option<Id> {
+        if let Ok(oid) = raw.parse::<ObjectId>() {
+            return Some(Id::ObjectId(oid));
+        }
+        match self {
+            IdStrategy::ObjectId => None,
+            IdStrategy::UuidV7 => is_uuid_v7(raw).then(|| Id::String(raw.to_lowercase())),
+            IdStrategy::NanoId => is_nanoid(raw).then(|| Id::String(raw.to_owned())),
+        }
+    }
+}
+
+/// A document's `_id` under any [`IdStrategy`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum Id {
+    ObjectId(ObjectId),
+    String(String),
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Id::ObjectId(oid) => f.write_str(&oid.to_hex()),
+            Id::String(id) => f.write_str(id),
+        }
+    }
+}
+
+impl From<Id> for Bson {
+    fn from(id: Id) -> Self {
+        match id {
+            Id::ObjectId(oid) => Bson::ObjectId(oid),
+            Id::String(id) => Bson::String(id),
+        }
+    }
+}
+
+/// 48 bits of Unix milliseconds, then random bits around the version and
+/// variant, as RFC 9562 lays out.
+fn uuid_v7() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default();
+
+    let mut bytes = [0u8; 16];
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    rand::thread_rng().fill_bytes(&mut bytes[6..]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x70;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn is_uuid_v7(raw: &str) -> bool {
+    let bytes = raw.as_bytes();
+    bytes.len() == 36
+        && bytes.iter().enumerate().all(|(i, byte)| match i {
+            8 | 13 | 18 | 23 => *byte == b'-',
+            _ => byte.is_ascii_hexdigit(),
+        })
+        && bytes[14] == b'7'
+        && matches!(bytes[19].to_ascii_lowercase(), b'8' | b'9' | b'a' | b'b')
+}
+
+fn nanoid() -> String {
+    let mut rng = rand::thread_rng();
+    (0..NANOID_LEN)
+        .map(|_| NANOID_ALPHABET[rng.gen_range(0..NANOID_ALPHABET.len())] as char)
+        .collect()
+}
+
+fn is_nanoid(raw: &str) -> bool {
+    raw.len() == NANOID_LEN && raw.bytes().all(|byte| NANOID_ALPHABET.contains(&byte))
+}
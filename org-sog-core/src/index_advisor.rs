@@ -0,0 +1,424 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::error::Result;
+use mongodb::options::{AggregateOptions, IndexOptions};
+use mongodb::{Client, Database, IndexModel};
+use serde::{Deserialize, Serialize};
+
+use crate::dates;
+use crate::slow_query::{self, number};
+
+/// Distinct query shapes looked at per request.
+const MAX_SHAPES: i64 = 500;
+/// Finished builds kept for [`IndexAdvisor::builds`], newest first.
+const MAX_BUILDS: usize = 50;
+/// Compound indexes can't have more keys than this.
+pub const MAX_INDEX_KEYS: usize = 32;
+const RANGE_OPERATORS: [&str; 4] = ["$gt", "$gte", "$lt", "$lte"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IndexKey {
+    pub field: String,
+    /// 1 for ascending, -1 for descending.
+    pub direction: i32,
+}
+
+/// An index that would serve queries found in the slow query log.
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct IndexSuggestion {
+    pub collection: String,
+    pub name: String,
+    pub keys: Vec<IndexKey>,
+    /// Slow queries it would serve.
+    pub queries: i64,
+    pub avgDurationMs: f64,
+    pub maxDurationMs: i64,
+    /// Of the explained ones, those that scanned the whole collection.
+    pub collectionScans: i64,
+    /// The shape of the most frequent query it would serve.
+    pub example: serde_json::Value,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildState {
+    Building,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct BuildProgress {
+    pub done: i64,
+    pub total: i64,
+}
+
+#[allow(non_snake_case)]
+#[derive(Serialize, Debug, Clone)]
+pub struct IndexBuild {
+    pub id: String,
+    pub collection: String,
+    pub name: String,
+    pub keys: Vec<IndexKey>,
+    pub state: BuildState,
+    /// How far the server is, while it is building.
+    pub progress: Option<BuildProgress>,
+    pub error: Option<String>,
+    #[serde(serialize_with = "dates::serialize")]
+    pub startedAt: DateTime<Utc>,
+    #[serde(serialize_with = "dates::serialize_option")]
+    pub finishedAt: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct ShapeRow {
+    #[serde(rename = "_id")]
+    key: ShapeKey,
+    queries: i64,
+    #[serde(rename = "avgMs")]
+    avg_ms: f64,
+    #[serde(rename = "maxMs")]
+    max_ms: i64,
+    scans: i64,
+}
+
+#[derive(Deserialize)]
+struct ShapeKey {
+    collection: String,
+    shape: Document,
+    sort: Option<Document>,
+}
+
+/// Suggests indexes for the queries in the slow query log that no existing
+/// index serves, and builds the ones an admin picks in the background.
+///
+/// Builds are tracked in memory, so each instance only reports the builds it
+/// started.
+#[derive(Clone, Debug)]
+pub struct IndexAdvisor {
+    /// For `currentOp`, which only runs against the admin database.
+    client: Client,
+    database: Database,
+    builds: Arc<Mutex<Vec<IndexBuild>>>,
+}
+
+impl IndexAdvisor {
+    pub fn new(client: &Client, database: &Database) -> Self {
+        Self {
+            client: client.clone(),
+            database: database.clone(),
+            builds: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Indexes missing for slow queries logged since `since`, optionally on
+    /// `collection` only, those serving the most slow queries first.
+    ///
+    /// Keys follow the equality, sort, range rule. A query counts as served
+    /// when an existing index starts with the suggested fields, in any order
+    /// for the equality fields; sort direction is ignored, since an index can
+    /// be walked either way.
+    pub async fn suggest(
+        &self,
+        since: DateTime<Utc>,
+        collection: Option<&str>,
+    ) -> Result<Vec<IndexSuggestion>> {
+        let mut filter = doc! {"at": {"$gte": since}};
+        if let Some(collection) = collection {
+            filter.insert("collection", collection);
+        }
+        let pipeline = vec![
+            doc! {"$match": filter},
+            doc! {"$group": {
+                "_id": {"collection": "$collection", "shape": "$shape", "sort": "$sort"},
+                "queries": {"$sum": 1},
+                "avgMs": {"$avg": "$durationMs"},
+                "maxMs": {"$max": "$durationMs"},
+                "scans": {"$sum": {"$cond": [
+                    {"$in": ["COLLSCAN", {"$ifNull": ["$plan.stages", []]}]}, 1, 0,
+                ]}},
+            }},
+            doc! {"$sort": {"queries": -1}},
+            doc! {"$limit": MAX_SHAPES},
+        ];
+
+        let mut cursor = self
+            .database
+            .collection::<Document>(slow_query::COLLECTION)
+            .aggregate(pipeline, AggregateOptions::default())
+            .await?;
+        let mut rows = Vec::new();
+        while cursor.advance().await? {
+            let row: ShapeRow = mongodb::bson::from_document(cursor.deserialize_current()?)?;
+            rows.push(row);
+        }
+
+        let mut existing: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+        let mut suggestions: Vec<IndexSuggestion> = Vec::new();
+        for row in rows {
+            let (equality, keys) = candidate_keys(&row.key.shape, row.key.sort.as_ref());
+            if keys.is_empty() {
+                continue;
+            }
+
+            if !existing.contains_key(&row.key.collection) {
+                let indexes = self.index_fields(&row.key.collection).await?;
+                existing.insert(row.key.collection.clone(), indexes);
+            }
+            if existing[&row.key.collection]
+                .iter()
+                .any(|index| serves(index, &keys, equality))
+            {
+                continue;
+            }
+
+            let name = index_name(&keys);
+            match suggestions
+                .iter_mut()
+                .find(|s| s.collection == row.key.collection && s.name == name)
+            {
+                Some(suggestion) => {
+                    let total = suggestion.queries + row.queries;
+                    suggestion.avgDurationMs = (suggestion.avgDurationMs
+                        * suggestion.queries as f64
+                        + row.avg_ms * row.queries as f64)
+                        / total as f64;
+                    suggestion.queries = total;
+                    suggestion.maxDurationMs = suggestion.maxDurationMs.max(row.max_ms);
+                    suggestion.collectionScans += row.scans;
+                }
+                None => suggestions.push(IndexSuggestion {
+                    collection: row.key.collection,
+                    name,
+                    keys,
+                    queries: row.queries,
+                    avgDurationMs: row.avg_ms,
+                    maxDurationMs: row.max_ms,
+                    collectionScans: row.scans,
+                    example: Bson::Document(row.key.shape).into_relaxed_extjson(),
+                }),
+            }
+        }
+
+        suggestions.sort_by_key(|s| Reverse(s.queries));
+        Ok(suggestions)
+    }
+
+    pub async fn collection_exists(&self, collection: &str) -> Result<bool> {
+        let names = self
+            .database
+            .list_collection_names(doc! {"name": collection})
+            .await?;
+        Ok(!names.is_empty())
+    }
+
+    /// Starts building an index on `collection` and returns at once; follow
+    /// it with [`IndexAdvisor::builds`]. An index that already exists
+    /// finishes straight away.
+    pub fn build(&self, collection: &str, keys: Vec<IndexKey>) -> IndexBuild {
+        let name = index_name(&keys);
+        let build = IndexBuild {
+            id: ObjectId::new().to_hex(),
+            collection: collection.to_owned(),
+            name: name.clone(),
+            keys: keys.clone(),
+            state: BuildState::Building,
+            progress: None,
+            error: None,
+            startedAt: Utc::now(),
+            finishedAt: None,
+        };
+        if let Ok(mut builds) = self.builds.lock() {
+            builds.insert(0, build.clone());
+            builds.truncate(MAX_BUILDS);
+        }
+
+        let target = self.database.collection::<Document>(collection);
+        let builds = self.builds.clone();
+        let id = build.id.clone();
+        tokio::spawn(async move {
+            let model = IndexModel::builder()
+                .keys(
+                    keys.into_iter()
+                        .map(|key| (key.field, Bson::Int32(key.direction)))
+                        .collect::<Document>(),
+                )
+                .options(IndexOptions::builder().name(name).build())
+                .build();
+            let result = target.create_index(model, None).await;
+
+            if let Ok(mut builds) = builds.lock() {
+                if let Some(build) = builds.iter_mut().find(|build| build.id == id) {
+                    build.finishedAt = Some(Utc::now());
+                    match result {
+                        Ok(_) => build.state = BuildState::Done,
+                        Err(e) => {
+                            build.state = BuildState::Failed;
+                            build.error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+        });
+
+        build
+    }
+
+    /// Builds started here, newest first, with the server's progress on
+    /// those still running.
+    pub async fn builds(&self) -> Result<Vec<IndexBuild>> {
+        let mut builds = match self.builds.lock() {
+            Ok(builds) => builds.clone(),
+            Err(_) => Vec::new(),
+        };
+
+        for build in builds
+            .iter_mut()
+            .filter(|build| build.state == BuildState::Building)
+        {
+            build.progress = self.progress(&build.collection, &build.name).await?;
+        }
+        Ok(builds)
+    }
+
+    async fn progress(&self, collection: &str, name: &str) -> Result<Option<BuildProgress>> {
+        let ops = self
+            .client
+            .database("admin")
+            .run_command(
+                doc! {
+                    "currentOp": 1,
+                    "command.createIndexes": collection,
+                    "command.indexes.name": name,
+                },
+                None,
+            )
+            .await?;
+
+        let progress = ops
+            .get_array("inprog")
+            .ok()
+            .and_then(|ops| ops.iter().filter_map(Bson::as_document).next())
+            .and_then(|op| op.get_document("progress").ok())
+            .and_then(|progress| {
+                Some(BuildProgress {
+                    done: number(progress, "done")?,
+                    total: number(progress, "total")?,
+                })
+            });
+        Ok(progress)
+    }
+
+    /// The fields of each index on `collection`, in key order.
+    async fn index_fields(&self, collection: &str) -> Result<Vec<Vec<String>>> {
+        let mut cursor = self
+            .database
+            .collection::<Document>(collection)
+            .list_indexes(None)
+            .await?;
+
+        let mut indexes = Vec::new();
+        while cursor.advance().await? {
+            let index = cursor.deserialize_current()?;
+            indexes.push(index.keys.keys().cloned().collect());
+        }
+        Ok(indexes)
+    }
+}
+
+/// Index keys for a query of `shape` and `sort`: equality fields, then sort
+/// fields, then range fields. Also returns how many are equality fields.
+///
+/// Clauses under `$or`, `$expr` and the like, and operators like `$ne` or
+/// `$regex` that can't narrow an index scan much, are left out.
+fn candidate_keys(shape: &Document, sort: Option<&Document>) -> (usize, Vec<IndexKey>) {
+    let mut equality = Vec::new();
+    let mut range = Vec::new();
+    for (field, value) in shape {
+        if field.starts_with('$') {
+            continue;
+        }
+        match value {
+            Bson::Document(operators) if operators.keys().any(|op| op.starts_with('$')) => {
+                if operators.keys().all(|op| op == "$eq" || op == "$in") {
+                    equality.push(field.clone());
+                } else if operators
+                    .keys()
+                    .any(|op| RANGE_OPERATORS.contains(&op.as_str()))
+                {
+                    range.push(field.clone());
+                }
+            }
+            _ => equality.push(field.clone()),
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut keys = Vec::new();
+    for field in &equality {
+        if seen.insert(field.clone()) {
+            keys.push(IndexKey {
+                field: field.clone(),
+                direction: 1,
+            });
+        }
+    }
+    let equality_len = keys.len();
+    for (field, direction) in sort.into_iter().flatten() {
+        let direction = match direction {
+            Bson::Int32(n) => *n as i64,
+            Bson::Int64(n) => *n,
+            Bson::Double(n) => *n as i64,
+            // `{"$meta": "textScore"}` and the like can't be indexed.
+            _ => continue,
+        };
+        if seen.insert(field.clone()) {
+            keys.push(IndexKey {
+                field: field.clone(),
+                direction: if direction < 0 { -1 } else { 1 },
+            });
+        }
+    }
+    for field in range {
+        if seen.insert(field.clone()) {
+            keys.push(IndexKey {
+                field,
+                direction: 1,
+            });
+        }
+    }
+
+    keys.truncate(MAX_INDEX_KEYS);
+    (equality_len.min(keys.len()), keys)
+}
+
+/// Whether an index with `fields` serves `keys`, the first `equality` of
+/// which may come in any order.
+fn serves(fields: &[String], keys: &[IndexKey], equality: usize) -> bool {
+    if fields.len() < keys.len() {
+        return false;
+    }
+    let (head, tail) = keys.split_at(equality);
+    let prefix: HashSet<&str> = fields[..equality].iter().map(String::as_str).collect();
+
+    head.iter().all(|key| prefix.contains(key.field.as_str()))
+        && tail
+            .iter()
+            .zip(&fields[equality..])
+            .all(|(key, field)| key.field == *field)
+}
+
+/// The name MongoDB itself would give an index with `keys`.
+pub fn index_name(keys: &[IndexKey]) -> String {
+    keys.iter()
+        .map(|key| format!("{}_{}", key.field, key.direction))
+        .collect::<Vec<_>>()
+        .join("_")
+}
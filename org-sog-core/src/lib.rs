@@ -0,0 +1,32 @@
+//! Building blocks shared by the org-sog services.
+
+pub mod bot;
+pub mod circuit_breaker;
+pub mod client_ip;
+pub mod conflict;
+pub mod credentials;
+pub mod dates;
+pub mod db_breaker;
+pub mod db_metrics;
+pub mod encoding;
+pub mod filter;
+pub mod geo;
+pub mod http;
+pub mod i18n;
+pub mod ids;
+pub mod index_advisor;
+pub mod links;
+pub mod locale;
+pub mod mail;
+pub mod migrate;
+pub mod migration_jobs;
+pub mod names;
+pub mod page;
+pub mod patch;
+pub mod plan;
+pub mod read;
+pub mod repo;
+pub mod slow_query;
+pub mod store;
+pub mod tombstone;
+pub mod trace;
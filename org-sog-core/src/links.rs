@@ -0,0 +1,280 @@
+use serde_json::{json, Map, Value};
+
+/// Hypermedia formats a client can opt into through `Accept`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hypermedia {
+    Hal,
+    JsonApi,
+}
+
+impl Hypermedia {
+    /// The first hypermedia type an `Accept` header lists, if any.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        accept
+            .split(',')
+            .find_map(|range| match range.split(';').next()?.trim() {
+                "application/hal+json" => Some(Hypermedia::Hal),
+                "application/vnd.api+json" => Some(Hypermedia::JsonApi),
+                _ => None,
+            })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Hypermedia::Hal => "application/hal+json",
+            Hypermedia::JsonApi => "application/vnd.api+json",
+        }
+    }
+}
+
+/// One kind of resource as it appears in the plain JSON envelopes:
+/// `{"data": {<key>: {...}}}` for one, `{<collection_key>: [...]}` for a page.
+///
+/// Link templates fill `{field}` from the resource's own fields, and a link
+/// whose fields are missing or null is left out.
+#[derive(Debug, Clone)]
+pub struct Resource {
+    kind: String,
+    key: String,
+    collection_key: String,
+    id_field: String,
+    href: String,
+    relations: Vec<(String, String)>,
+}
+
+impl Resource {
+    /// `kind` is the JSON:API type; `href` the template for the resource's own URL.
+    pub fn new(kind: &str, key: &str, collection_key: &str, href: &str) -> Self {
+        Self {
+            kind: kind.to_owned(),
+            key: key.to_owned(),
+            collection_key: collection_key.to_owned(),
+            id_field: "id".to_owned(),
+            href: href.to_owned(),
+            relations: Vec::new(),
+        }
+    }
+
+    /// Identifies resources by another field than `id`, e.g. a slug.
+    pub fn id_field(mut self, field: &str) -> Self {
+        self.id_field = field.to_owned();
+        self
+    }
+
+    pub fn relation(mut self, name: &str, href: &str) -> Self {
+        self.relations.push((name.to_owned(), href.to_owned()));
+        self
+    }
+}
+
+/// Builds `self`, `next` and related links, and rewraps plain JSON responses
+/// as HAL or JSON:API.
+///
+/// Relative templates are resolved against `base_url`; absolute ones (links
+/// into another service) are used as they are.
+#[derive(Debug, Clone)]
+pub struct LinkBuilder {
+    base_url: String,
+    resources: Vec<Resource>,
+}
+
+impl LinkBuilder {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            resources: Vec::new(),
+        }
+    }
+
+    pub fn resource(mut self, resource: Resource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    pub fn href(&self, path: &str) -> String {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_owned()
+        } else {
+            format!("{}{}", self.base_url, path)
+        }
+    }
+
+    /// Rewraps `body`, the response to `path_and_query`, in `mode`. Returns
+    /// `None` for anything that isn't one of the registered resources.
+    pub fn wrap(&self, mode: Hypermedia, path_and_query: &str, body: Value) -> Option<Value> {
+        let Value::Object(mut body) = body else {
+            return None;
+        };
+
+        for resource in &self.resources {
+            let single = body
+                .get_mut("data")
+                .and_then(|data| data.get_mut(&resource.key))
+                .filter(|item| item.is_object())
+                .map(Value::take);
+            if let Some(Value::Object(item)) = single {
+                return Some(self.wrap_one(mode, resource, item));
+            }
+
+            if !body
+                .get(&resource.collection_key)
+                .is_some_and(Value::is_array)
+            {
+                continue;
+            }
+            if let Some(Value::Array(items)) = body.remove(&resource.collection_key) {
+                let items = items
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        Value::Object(item) => Some(item),
+                        _ => None,
+                    })
+                    .collect();
+                return Some(self.wrap_page(mode, resource, path_and_query, &body, items));
+            }
+        }
+        None
+    }
+
+    fn wrap_one(&self, mode: Hypermedia, resource: &Resource, item: Map<String, Value>) -> Value {
+        let self_href = self.item_href(resource, &item);
+        let mut wrapped = self.wrap_item(mode, resource, item);
+        if mode == Hypermedia::JsonApi {
+            wrapped = json!({"data": wrapped, "links": {"self": self_href}});
+        }
+        wrapped
+    }
+
+    fn wrap_page(
+        &self,
+        mode: Hypermedia,
+        resource: &Resource,
+        path_and_query: &str,
+        envelope: &Map<String, Value>,
+        items: Vec<Map<String, Value>>,
+    ) -> Value {
+        let mut links = Map::new();
+        links.insert("self".into(), json!(self.href(path_and_query)));
+
+        // A full page suggests there is another; the lists don't count totals.
+        let page = envelope.get("page").and_then(Value::as_i64);
+        let limit = envelope.get("limit").and_then(Value::as_i64);
+        if let (Some(page), Some(limit)) = (page, limit) {
+            if items.len() as i64 >= limit {
+                let next = with_page(path_and_query, page + 1);
+                links.insert("next".into(), json!(self.href(&next)));
+            }
+        }
+
+        let mut meta = envelope.clone();
+        meta.remove("status");
+        let items: Vec<Value> = items
+            .into_iter()
+            .map(|item| self.wrap_item(mode, resource, item))
+            .collect();
+
+        match mode {
+            Hypermedia::Hal => {
+                let links: Map<String, Value> = links
+                    .into_iter()
+                    .map(|(name, href)| (name, json!({ "href": href })))
+                    .collect();
+                meta.insert("_links".into(), Value::Object(links));
+                meta.insert(
+                    "_embedded".into(),
+                    json!({ resource.collection_key.as_str(): items }),
+                );
+                Value::Object(meta)
+            }
+            Hypermedia::JsonApi => json!({"data": items, "links": links, "meta": meta}),
+        }
+    }
+
+    fn wrap_item(
+        &self,
+        mode: Hypermedia,
+        resource: &Resource,
+        mut item: Map<String, Value>,
+    ) -> Value {
+        let self_href = self.item_href(resource, &item);
+        let related: Vec<(&str, String)> = resource
+            .relations
+            .iter()
+            .filter_map(|(name, template)| {
+                Some((name.as_str(), self.href(&fill(template, &item)?)))
+            })
+            .collect();
+
+        match mode {
+            Hypermedia::Hal => {
+                let mut links = Map::new();
+                if let Some(href) = self_href {
+                    links.insert("self".into(), json!({ "href": href }));
+                }
+                for (name, href) in related {
+                    links.insert(name.into(), json!({ "href": href }));
+                }
+                item.insert("_links".into(), Value::Object(links));
+                Value::Object(item)
+            }
+            Hypermedia::JsonApi => {
+                let id = item.get(&resource.id_field).map(plain).unwrap_or_default();
+                item.remove("id");
+                let relationships: Map<String, Value> = related
+                    .into_iter()
+                    .map(|(name, href)| (name.to_owned(), json!({"links": {"related": href}})))
+                    .collect();
+                json!({
+                    "type": resource.kind,
+                    "id": id,
+                    "attributes": item,
+                    "relationships": relationships,
+                    "links": {"self": self_href},
+                })
+            }
+        }
+    }
+
+    fn item_href(&self, resource: &Resource, item: &Map<String, Value>) -> Option<String> {
+        Some(self.href(&fill(&resource.href, item)?))
+    }
+}
+
+/// Replaces each `{field}` in `template` with that field of `item`.
+fn fill(template: &str, item: &Map<String, Value>) -> Option<String> {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        let value = item
+            .get(&rest[start + 1..end])
+            .filter(|value| !value.is_null())?;
+        filled.push_str(&rest[..start]);
+        filled.push_str(&plain(value));
+        rest = &rest[end + 1..];
+    }
+    filled.push_str(rest);
+    Some(filled)
+}
+
+/// Strings without their JSON quotes, anything else as JSON.
+fn plain(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.to_owned(),
+        value => value.to_string(),
+    }
+}
+
+/// `path_and_query` with its `page` parameter set to `page`.
+fn with_page(path_and_query: &str, page: i64) -> String {
+    let (path, query) = path_and_query
+        .split_once('?')
+        .unwrap_or((path_and_query, ""));
+    let mut params: Vec<String> = query
+        .split('&')
+        .filter(|param| !param.is_empty() && !param.starts_with("page="))
+        .map(str::to_owned)
+        .collect();
+    params.push(format!("page={}", page));
+    format!("{}?{}", path, params.join("&"))
+}
@@ -0,0 +1,130 @@
+//! `Accept-Language` negotiation: q-values, and regional tags falling back
+//! to their language (`en-GB` → `en`), as in RFC 4647 lookup.
+
+/// The language ranges of an `Accept-Language` header, lowercased, most
+/// preferred first. Ranges with `q=0` are dropped.
+pub fn parse(header: &str) -> Vec<String> {
+    let mut weighted: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let tag = params.next()?.trim().to_ascii_lowercase();
+            let quality = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+        })
+        .collect();
+    // Stable, so equally weighted ranges keep the order they were listed in.
+    weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut ranges: Vec<String> = Vec::new();
+    for (tag, _) in weighted {
+        if !ranges.contains(&tag) {
+            ranges.push(tag);
+        }
+    }
+    ranges
+}
+
+/// The tags to try, in order: each range followed by its shorter forms, so
+/// `en-gb, fr` becomes `en-gb, en, fr`. The wildcard is left out.
+pub fn fallback_chain(ranges: &[String]) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    for range in ranges.iter().filter(|range| *range != "*") {
+        let mut tag = range.as_str();
+        loop {
+            if !chain.iter().any(|tried| tried == tag) {
+                chain.push(tag.to_owned());
+            }
+            let Some((shorter, _)) = tag.rsplit_once('-') else {
+                break;
+            };
+            // A singleton (the `x` of `de-x-foo`) never stands alone.
+            tag = match shorter.rsplit_once('-') {
+                Some((rest, last)) if last.len() == 1 => rest,
+                _ => shorter,
+            };
+        }
+    }
+    chain
+}
+
+/// The best of `available` for `ranges`, or `None` if nothing matches. A
+/// wildcard accepts the first available tag. Matching ignores case.
+pub fn negotiate<'a>(ranges: &[String], available: &[&'a str]) -> Option<&'a str> {
+    let found = fallback_chain(ranges).into_iter().find_map(|tag| {
+        available
+            .iter()
+            .find(|candidate| candidate.eq_ignore_ascii_case(&tag))
+            .copied()
+    });
+    found.or_else(|| {
+        ranges
+            .iter()
+            .any(|range| range == "*")
+            .then(|| available.first().copied())
+            .flatten()
+    })
+}
+
+/// [`negotiate`] straight from a header.
+pub fn negotiate_header<'a>(header: Option<&str>, available: &[&'a str]) -> Option<&'a str> {
+    negotiate(&header.map(parse).unwrap_or_default(), available)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_are_ordered_by_q_value() {
+        assert_eq!(
+            parse("fr;q=0.5, de, en-GB;q=0.8"),
+            vec!["de", "en-gb", "fr"]
+        );
+    }
+
+    #[test]
+    fn equal_q_values_keep_the_order_given() {
+        assert_eq!(parse("nl;q=0.7, es;q=0.7, it"), vec!["it", "nl", "es"]);
+    }
+
+    #[test]
+    fn q_zero_and_duplicates_are_dropped() {
+        assert_eq!(parse("en, fr;q=0, EN;q=0.5"), vec!["en"]);
+    }
+
+    #[test]
+    fn malformed_ranges_are_skipped() {
+        assert_eq!(parse("de;q=high, , ;q=0.5, fr ; q= 0.4"), vec!["fr"]);
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn regional_tags_fall_back_to_their_language() {
+        assert_eq!(
+            fallback_chain(&parse("de-CH, fr")),
+            vec!["de-ch", "de", "fr"]
+        );
+        assert_eq!(fallback_chain(&parse("de-x-foo")), vec!["de-x-foo", "de"]);
+        assert_eq!(negotiate(&parse("en-GB"), &["de", "en"]), Some("en"));
+    }
+
+    #[test]
+    fn a_preferred_match_wins_over_the_wildcard() {
+        assert_eq!(negotiate(&parse("*, fr;q=0.5"), &["de", "fr"]), Some("fr"));
+    }
+
+    #[test]
+    fn the_wildcard_falls_back_to_the_first_available() {
+        assert_eq!(negotiate(&parse("ja, *;q=0.1"), &["de", "fr"]), Some("de"));
+        assert_eq!(negotiate(&parse("ja"), &["de", "fr"]), None);
+    }
+
+    #[test]
+    fn a_missing_header_negotiates_nothing() {
+        assert_eq!(negotiate_header(None, &["de", "fr"]), None);
+        assert_eq!(negotiate_header(Some("FR"), &["de", "fr"]), Some("fr"));
+    }
+}
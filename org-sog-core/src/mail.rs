@@ -0,0 +1,104 @@
+use std::fmt;
+
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+#[derive(Debug)]
+pub struct MailError(pub String);
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for MailError {}
+
+type Result<T> = std::result::Result<T, MailError>;
+
+/// Outgoing mail over SMTP, configured from `SMTP_HOST`, `SMTP_USERNAME`,
+/// `SMTP_PASSWORD` and `MAIL_FROM`.
+#[derive(Clone)]
+pub struct Mailer {
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn init() -> Result<Self> {
+        let from = std::env::var("MAIL_FROM")
+            .unwrap_or_else(|_| "no-reply@localhost".to_string())
+            .parse::<Mailbox>()
+            .expect("MAIL_FROM must be a valid mailbox.");
+
+        let transport = match std::env::var("SMTP_HOST") {
+            Ok(host) => {
+                let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+                    .map_err(|e| MailError(e.to_string()))?;
+                if let (Ok(username), Ok(password)) = (
+                    std::env::var("SMTP_USERNAME"),
+                    std::env::var("SMTP_PASSWORD"),
+                ) {
+                    builder = builder.credentials(Credentials::new(username, password));
+                }
+                Some(builder.build())
+            }
+            Err(_) => {
+                println!("⚠️ SMTP_HOST not set, outgoing mail will be logged without its body");
+                None
+            }
+        };
+
+        Ok(Self { transport, from })
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<()> {
+        self.send_with_reply_to(to, None, subject, body).await
+    }
+
+    /// Like [`Mailer::send`], with replies going to `reply_to` instead of the sender.
+    pub async fn send_with_reply_to(
+        &self,
+        to: &str,
+        reply_to: Option<&str>,
+        subject: &str,
+        body: String,
+    ) -> Result<()> {
+        let to = to
+            .parse::<Mailbox>()
+            .map_err(|e| MailError(e.to_string()))?;
+        let reply_to = reply_to
+            .map(|address| address.parse::<Mailbox>())
+            .transpose()
+            .map_err(|e| MailError(e.to_string()))?;
+
+        // Bodies carry sign-in and invite links, which don't belong in logs.
+        let Some(transport) = &self.transport else {
+            println!(
+                "📧 To: {}\nSubject: {}\n({} byte body not shown)",
+                to,
+                subject,
+                body.len()
+            );
+            return Ok(());
+        };
+
+        let mut builder = Message::builder().from(self.from.clone()).to(to);
+        if let Some(reply_to) = reply_to {
+            builder = builder.reply_to(reply_to);
+        }
+        let message = builder
+            .subject(subject)
+            .body(body)
+            .map_err(|e| MailError(e.to_string()))?;
+
+        transport
+            .send(message)
+            .await
+            .map_err(|e| MailError(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use mongodb::error::{ErrorKind, Result};
+use mongodb::{Collection, IndexModel};
+
+/// Server error code for a collection that doesn't exist yet.
+const NAMESPACE_NOT_FOUND: i32 = 26;
+
+/// Brings an index up to date without a window where it is missing.
+///
+/// `index` must be named; an index of that name is taken to be current, so a
+/// changed definition needs a new name. The new index is built before any in
+/// `replaces` are dropped, and if the build fails (say, a new unique
+/// constraint that existing documents violate) the old ones are left alone.
+pub async fn ensure_index<T>(
+    collection: &Collection<T>,
+    index: IndexModel,
+    replaces: &[&str],
+) -> Result<()> {
+    let name = index
+        .options
+        .as_ref()
+        .and_then(|options| options.name.clone())
+        .expect("migrated indexes must be named");
+
+    let existing = match collection.list_index_names().await {
+        Ok(names) => names,
+        Err(e) if is_namespace_not_found(&e) => Vec::new(),
+        Err(e) => return Err(e),
+    };
+
+    if !existing.contains(&name) {
+        collection.create_index(index, None).await?;
+    }
+    for old in replaces {
+        if existing.iter().any(|name| name == old) {
+            collection.drop_index(*old, None).await?;
+        }
+    }
+    Ok(())
+}
+
+fn is_namespace_not_found(e: &mongodb::error::Error) -> bool {
+    matches!(&*e.kind, ErrorKind::Command(command) if command.code == NAMESPACE_NOT_FOUND)
+}
@@ -0,0 +1,405 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::{self, doc, oid::ObjectId, Bson, Document};
+use mongodb::error::Result;
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions};
+use mongodb::{Collection, Database};
+use serde::{Deserialize, Serialize};
+
+pub const COLLECTION: &str = "migration_jobs";
+/// How long a claim on a job holds without a checkpoint; another instance
+/// takes the job over after that, so one that went away mid-deploy doesn't
+/// strand it.
+const LEASE_SECS: i64 = 60;
+/// How often each instance looks for jobs to pick up.
+const POLL_SECS: u64 = 30;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Paused,
+    Aborted,
+    Done,
+    Failed,
+}
+
+/// Progress of one [`Migration`], checkpointed after every chunk.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MigrationJob {
+    /// The migration's name; each runs at most once.
+    #[serde(rename = "_id")]
+    pub name: String,
+    pub collection: String,
+    pub state: JobState,
+    /// `_id` of the last document done; the next chunk starts after it.
+    pub checkpoint: Option<Bson>,
+    /// Documents looked at so far.
+    pub scanned: i64,
+    /// Of those, the ones that were changed.
+    pub migrated: i64,
+    pub error: Option<String>,
+    /// The instance working on it, while it holds the lease.
+    pub owner: Option<String>,
+    pub leaseUntil: Option<bson::DateTime>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub startedAt: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub updatedAt: DateTime<Utc>,
+    pub finishedAt: Option<bson::DateTime>,
+}
+
+/// A change to every document of a collection that is too big to make in
+/// one go, such as a backfill or re-encrypting a field.
+///
+/// Documents are visited in `_id` order. A chunk may be visited again after
+/// a pause or a crash, so `migrate` must leave a migrated document alone.
+pub trait Migration: Send + Sync {
+    /// Stable across releases; it names the job.
+    fn name(&self) -> &'static str;
+    fn collection(&self) -> &str;
+    /// Narrows the documents visited, e.g. to those missing a field.
+    fn filter(&self) -> Document {
+        Document::new()
+    }
+    /// The update to apply to `document`, or `None` to leave it as it is.
+    fn migrate(&self, document: &Document) -> Result<Option<Document>>;
+}
+
+/// What came of asking a job to pause, resume or abort.
+#[derive(Debug)]
+pub enum Transition {
+    Changed(Box<MigrationJob>),
+    NotFound,
+    /// The job is in a state the change doesn't apply to.
+    Refused(JobState),
+}
+
+/// Runs registered [`Migration`]s in the background as resumable jobs,
+/// tracked in the `migration_jobs` collection.
+///
+/// A job is started the first time an instance that knows its migration
+/// comes up. Instances share the work through a lease, so old and new
+/// deployments can run side by side. Chunks are `MIGRATION_CHUNK_SIZE`
+/// documents (default 200), paced to at most `MIGRATION_DOCS_PER_SEC`
+/// (default 500) per job so a migration doesn't crowd out live traffic.
+pub struct MigrationRunner {
+    database: Database,
+    jobs: Collection<MigrationJob>,
+    migrations: Vec<Arc<dyn Migration>>,
+    instance: String,
+    chunk_size: i64,
+    docs_per_sec: u64,
+}
+
+impl fmt::Debug for MigrationRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let migrations: Vec<&str> = self.migrations.iter().map(|m| m.name()).collect();
+        f.debug_struct("MigrationRunner")
+            .field("migrations", &migrations)
+            .field("instance", &self.instance)
+            .field("chunk_size", &self.chunk_size)
+            .field("docs_per_sec", &self.docs_per_sec)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MigrationRunner {
+    pub fn init(database: &Database, migrations: Vec<Arc<dyn Migration>>) -> Self {
+        let chunk_size = env_or("MIGRATION_CHUNK_SIZE", 200).max(1) as i64;
+        let docs_per_sec = env_or("MIGRATION_DOCS_PER_SEC", 500).max(1);
+
+        Self {
+            database: database.clone(),
+            jobs: database.collection(COLLECTION),
+            migrations,
+            instance: ObjectId::new().to_hex(),
+            chunk_size,
+            docs_per_sec,
+        }
+    }
+
+    /// Registers new migrations, then keeps picking up running jobs whose
+    /// lease has lapsed.
+    pub fn spawn(self: &Arc<Self>) {
+        let runner = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = runner.register().await {
+                println!("⚠️ failed to register migrations: {}", e);
+            }
+            let mut interval = tokio::time::interval(Duration::from_secs(POLL_SECS));
+            loop {
+                interval.tick().await;
+                for migration in &runner.migrations {
+                    if let Err(e) = runner.work_on(migration.as_ref()).await {
+                        println!("⚠️ migration {} failed: {}", migration.name(), e);
+                        runner.fail(migration.name(), &e.to_string()).await;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Every job, newest first.
+    pub async fn jobs(&self) -> Result<Vec<MigrationJob>> {
+        let options = FindOptions::builder().sort(doc! {"startedAt": -1}).build();
+        let mut cursor = self.jobs.find(None, options).await?;
+
+        let mut jobs = Vec::new();
+        while cursor.advance().await? {
+            jobs.push(cursor.deserialize_current()?);
+        }
+        Ok(jobs)
+    }
+
+    /// Stops a running job after the chunk in progress.
+    pub async fn pause(&self, name: &str) -> Result<Transition> {
+        self.transition(name, &[JobState::Running], JobState::Paused)
+            .await
+    }
+
+    /// Picks a paused or failed job up again from its checkpoint.
+    pub async fn resume(&self, name: &str) -> Result<Transition> {
+        self.transition(
+            name,
+            &[JobState::Paused, JobState::Failed],
+            JobState::Running,
+        )
+        .await
+    }
+
+    /// Gives up on a job for good; what it already changed stays changed.
+    pub async fn abort(&self, name: &str) -> Result<Transition> {
+        self.transition(
+            name,
+            &[JobState::Running, JobState::Paused, JobState::Failed],
+            JobState::Aborted,
+        )
+        .await
+    }
+
+    async fn transition(&self, name: &str, from: &[JobState], to: JobState) -> Result<Transition> {
+        let Some(job) = self.jobs.find_one(doc! {"_id": name}, None).await? else {
+            return Ok(Transition::NotFound);
+        };
+        if !from.contains(&job.state) {
+            return Ok(Transition::Refused(job.state));
+        }
+
+        let mut set = doc! {"state": bson::to_bson(&to)?, "updatedAt": Utc::now()};
+        if to == JobState::Aborted {
+            set.insert("finishedAt", Utc::now());
+        }
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        // Giving up the lease lets whichever instance sees it first resume it.
+        let changed = self
+            .jobs
+            .find_one_and_update(
+                doc! {"_id": name, "state": bson::to_bson(&job.state)?},
+                doc! {
+                    "$set": set,
+                    "$unset": {"owner": "", "leaseUntil": "", "error": ""},
+                },
+                options,
+            )
+            .await?;
+
+        Ok(match changed {
+            Some(job) => Transition::Changed(Box::new(job)),
+            None => Transition::Refused(job.state),
+        })
+    }
+
+    async fn register(&self) -> Result<()> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        for migration in &self.migrations {
+            let now = Utc::now();
+            self.jobs
+                .update_one(
+                    doc! {"_id": migration.name()},
+                    doc! {"$setOnInsert": {
+                        "collection": migration.collection(),
+                        "state": bson::to_bson(&JobState::Running)?,
+                        "checkpoint": Bson::Null,
+                        "scanned": 0_i64,
+                        "migrated": 0_i64,
+                        "startedAt": now,
+                        "updatedAt": now,
+                    }},
+                    options.clone(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Runs `migration`'s job to the end if it's running and nobody else
+    /// holds it, checkpointing after each chunk.
+    async fn work_on(&self, migration: &dyn Migration) -> Result<()> {
+        let Some(mut job) = self.claim(migration.name()).await? else {
+            return Ok(());
+        };
+        let collection = self.database.collection::<Document>(migration.collection());
+
+        loop {
+            let started = tokio::time::Instant::now();
+
+            let mut filter = migration.filter();
+            if let Some(checkpoint) = &job.checkpoint {
+                filter = doc! {"$and": [filter, {"_id": {"$gt": checkpoint.clone()}}]};
+            }
+            let options = FindOptions::builder()
+                .sort(doc! {"_id": 1})
+                .limit(self.chunk_size)
+                .build();
+            let mut cursor = collection.find(filter, options).await?;
+            let mut chunk = Vec::new();
+            while cursor.advance().await? {
+                chunk.push(cursor.deserialize_current()?);
+            }
+
+            let Some(last) = chunk
+                .last()
+                .and_then(|document| document.get("_id"))
+                .cloned()
+            else {
+                self.finish(&job.name).await?;
+                return Ok(());
+            };
+            let mut migrated = 0_i64;
+            for document in &chunk {
+                if let Some(update) = migration.migrate(document)? {
+                    let id = document.get("_id").cloned().unwrap_or(Bson::Null);
+                    collection
+                        .update_one(doc! {"_id": id}, update, None)
+                        .await?;
+                    migrated += 1;
+                }
+            }
+
+            match self
+                .checkpoint(&job.name, &last, chunk.len() as i64, migrated)
+                .await?
+            {
+                Some(updated) => job = updated,
+                // Paused, aborted or taken over while this chunk ran.
+                None => return Ok(()),
+            }
+
+            let budget = Duration::from_secs_f64(chunk.len() as f64 / self.docs_per_sec as f64);
+            tokio::time::sleep_until(started + budget).await;
+        }
+    }
+
+    async fn claim(&self, name: &str) -> Result<Option<MigrationJob>> {
+        let now = Utc::now();
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.jobs
+            .find_one_and_update(
+                doc! {
+                    "_id": name,
+                    "state": bson::to_bson(&JobState::Running)?,
+                    "$or": [
+                        {"leaseUntil": null},
+                        {"leaseUntil": {"$lt": now}},
+                        {"owner": self.instance.as_str()},
+                    ],
+                },
+                doc! {"$set": {
+                    "owner": self.instance.as_str(),
+                    "leaseUntil": now + chrono::Duration::seconds(LEASE_SECS),
+                }},
+                options,
+            )
+            .await
+    }
+
+    /// Records a finished chunk and renews the lease; `None` when the job is
+    /// no longer this instance's to run.
+    async fn checkpoint(
+        &self,
+        name: &str,
+        last: &Bson,
+        scanned: i64,
+        migrated: i64,
+    ) -> Result<Option<MigrationJob>> {
+        let now = Utc::now();
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.jobs
+            .find_one_and_update(
+                doc! {
+                    "_id": name,
+                    "owner": self.instance.as_str(),
+                    "state": bson::to_bson(&JobState::Running)?,
+                },
+                doc! {
+                    "$set": {
+                        "checkpoint": last.clone(),
+                        "leaseUntil": now + chrono::Duration::seconds(LEASE_SECS),
+                        "updatedAt": now,
+                    },
+                    "$inc": {"scanned": scanned, "migrated": migrated},
+                },
+                options,
+            )
+            .await
+    }
+
+    async fn finish(&self, name: &str) -> Result<()> {
+        let now = Utc::now();
+        self.jobs
+            .update_one(
+                doc! {"_id": name, "owner": self.instance.as_str()},
+                doc! {
+                    "$set": {
+                        "state": bson::to_bson(&JobState::Done)?,
+                        "updatedAt": now,
+                        "finishedAt": now,
+                    },
+                    "$unset": {"owner": "", "leaseUntil": ""},
+                },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Parks a job that hit an error until an admin resumes it.
+    async fn fail(&self, name: &str, error: &str) {
+        let state = match bson::to_bson(&JobState::Failed) {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        let result = self
+            .jobs
+            .update_one(
+                doc! {"_id": name, "owner": self.instance.as_str()},
+                doc! {
+                    "$set": {"state": state, "error": error, "updatedAt": Utc::now()},
+                    "$unset": {"owner": "", "leaseUntil": ""},
+                },
+                None,
+            )
+            .await;
+        if let Err(e) = result {
+            println!("⚠️ failed to record that migration {} failed: {}", name, e);
+        }
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a whole number, got '{}'", name, value)),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashSet, fmt};
+
+/// Taken by routes, subdomains or roles, so never handed out as a name.
+const DEFAULT_RESERVED: &[&str] = &[
+    "about",
+    "admin",
+    "administrator",
+    "api",
+    "app",
+    "assets",
+    "auth",
+    "blog",
+    "contact",
+    "dashboard",
+    "help",
+    "login",
+    "logout",
+    "me",
+    "mod",
+    "moderator",
+    "new",
+    "null",
+    "owner",
+    "register",
+    "root",
+    "settings",
+    "signup",
+    "static",
+    "support",
+    "system",
+    "undefined",
+    "www",
+];
+
+/// Why a name was turned down. `code` is stable so clients can translate it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameRejection {
+    /// The whole name is on the reserved list.
+    Reserved(String),
+    /// The name contains a denied pattern.
+    Denied(String),
+}
+
+impl NameRejection {
+    pub fn code(&self) -> &'static str {
+        match self {
+            NameRejection::Reserved(_) => "name_reserved",
+            NameRejection::Denied(_) => "name_denied",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            NameRejection::Reserved(name) | NameRejection::Denied(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for NameRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameRejection::Reserved(name) => write!(f, "{} is reserved", name),
+            NameRejection::Denied(name) => write!(f, "{} is not allowed", name),
+        }
+    }
+}
+
+impl std::error::Error for NameRejection {}
+
+/// Reserved names and denied patterns for user-chosen names, slugs and titles.
+///
+/// `RESERVED_NAMES` adds to the built-in reserved list and `DENIED_NAME_PATTERNS`
+/// sets the denied patterns (profanity and the like); both are comma-separated.
+/// Patterns match anywhere in a name once case, separators and common letter
+/// substitutions ("b4d") are folded away.
+#[derive(Debug, Clone)]
+pub struct NamePolicy {
+    reserved: HashSet<String>,
+    patterns: Vec<String>,
+}
+
+impl NamePolicy {
+    pub fn init() -> Self {
+        let mut reserved: HashSet<String> = DEFAULT_RESERVED
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        reserved.extend(env_list("RESERVED_NAMES").map(|name| name.to_lowercase()));
+
+        let patterns = env_list("DENIED_NAME_PATTERNS")
+            .map(|pattern| fold(&pattern))
+            .filter(|pattern| !pattern.is_empty())
+            .collect();
+
+        Self { reserved, patterns }
+    }
+
+    /// For names and slugs: neither reserved nor containing a denied pattern.
+    pub fn check_name(&self, name: &str) -> Result<(), NameRejection> {
+        let trimmed = name.trim();
+        if self.reserved.contains(&trimmed.to_lowercase()) {
+            return Err(NameRejection::Reserved(trimmed.to_owned()));
+        }
+        self.check_text(trimmed)
+    }
+
+    /// For free text such as titles, where reserved words are fine.
+    pub fn check_text(&self, text: &str) -> Result<(), NameRejection> {
+        let folded = fold(text);
+        if self.patterns.iter().any(|pattern| folded.contains(pattern)) {
+            return Err(NameRejection::Denied(text.trim().to_owned()));
+        }
+        Ok(())
+    }
+}
+
+fn env_list(name: &str) -> impl Iterator<Item = String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(|item| item.trim().to_owned())
+        .filter(|item| !item.is_empty())
+        .collect::<Vec<_>>()
+        .into_iter()
+}
+
+/// Lowercase letters only, with look-alike digits and symbols read as letters,
+/// so "B.a-d", "bad" and "b4d" all fold the same.
+fn fold(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|c| match c {
+            '0' => Some('o'),
+            '1' | '!' => Some('i'),
+            '3' => Some('e'),
+            '4' | '@' => Some('a'),
+            '5' | '$' => Some('s'),
+            '7' => Some('t'),
+            c if c.is_alphabetic() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
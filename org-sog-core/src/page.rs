@@ -0,0 +1,90 @@
+use std::fmt;
+
+/// A page before the first, or too far out to skip to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidPage;
+
+impl fmt::Display for InvalidPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "page is out of range, pages count from 1")
+    }
+}
+
+impl std::error::Error for InvalidPage {}
+
+/// The page and page size a list was actually served with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: i64,
+    pub limit: i64,
+}
+
+impl Pagination {
+    /// Documents before this page. Pages built by hand rather than through
+    /// [`PageLimits::resolve`] can't overflow it either.
+    pub fn skip(&self) -> u64 {
+        (self.page - 1).saturating_mul(self.limit).max(0) as u64
+    }
+}
+
+/// Page size bounds for list endpoints, from `PAGE_DEFAULT_LIMIT` (default 10)
+/// and `PAGE_MAX_LIMIT` (default 100).
+#[derive(Debug, Clone, Copy)]
+pub struct PageLimits {
+    pub default_limit: i64,
+    pub max_limit: i64,
+}
+
+impl PageLimits {
+    pub fn init() -> Self {
+        let max_limit = env_or("PAGE_MAX_LIMIT", 100).max(1);
+        let default_limit = env_or("PAGE_DEFAULT_LIMIT", 10).clamp(1, max_limit);
+        Self {
+            default_limit,
+            max_limit,
+        }
+    }
+
+    /// Validates `page` and clamps `limit` into `1..=max_limit`, falling back
+    /// to the configured default size.
+    pub fn resolve(
+        &self,
+        page: Option<i64>,
+        limit: Option<i64>,
+    ) -> Result<Pagination, InvalidPage> {
+        self.resolve_or(page, limit, self.default_limit)
+    }
+
+    /// As [`PageLimits::resolve`], for lists with their own default size.
+    pub fn resolve_or(
+        &self,
+        page: Option<i64>,
+        limit: Option<i64>,
+        default_limit: i64,
+    ) -> Result<Pagination, InvalidPage> {
+        let page = page.unwrap_or(1);
+        if page < 1 {
+            return Err(InvalidPage);
+        }
+        // A zero or negative maximum would make `clamp` panic.
+        let limit = limit
+            .unwrap_or(default_limit)
+            .clamp(1, self.max_limit.max(1));
+
+        // Keep `skip` within range however far out the page is.
+        if (page - 1).checked_mul(limit).is_none() {
+            return Err(InvalidPage);
+        }
+
+        Ok(Pagination { page, limit })
+    }
+}
+
+fn env_or(name: &str, default: i64) -> i64 {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("{} must be a whole number, got '{}'", name, value)),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,62 @@
+use mongodb::bson::{doc, Bson, Document};
+
+/// A PATCH body as one update document: fields given a value go into
+/// `$set`, cleared fields into `$unset`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch {
+    set: Document,
+    unset: Document,
+}
+
+impl Patch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from fields that are set as they are, e.g. a serialized body.
+    pub fn from_set(set: Document) -> Self {
+        Self {
+            set,
+            unset: Document::new(),
+        }
+    }
+
+    pub fn set(&mut self, field: impl Into<String>, value: impl Into<Bson>) {
+        let field = field.into();
+        self.unset.remove(&field);
+        self.set.insert(field, value);
+    }
+
+    /// Sets and unsets of one field in one update are a conflict to the
+    /// server, so the last one given wins.
+    pub fn unset(&mut self, field: impl Into<String>) {
+        let field = field.into();
+        self.set.remove(&field);
+        self.unset.insert(field, "");
+    }
+
+    /// A nullable PATCH field: `Some(None)` clears it, `None` leaves it be.
+    pub fn nullable<T: Into<Bson>>(&mut self, field: impl Into<String>, value: Option<Option<T>>) {
+        match value {
+            Some(Some(value)) => self.set(field, value),
+            Some(None) => self.unset(field),
+            None => {}
+        }
+    }
+
+    pub fn extend(&mut self, fields: Document) {
+        for (field, value) in fields {
+            self.set(field, value);
+        }
+    }
+
+    /// `$set` is always there, as the services stamp `updatedAt`; `$unset`
+    /// only when something was cleared.
+    pub fn into_update(self) -> Document {
+        let mut update = doc! {"$set": self.set};
+        if !self.unset.is_empty() {
+            update.insert("$unset", self.unset);
+        }
+        update
+    }
+}
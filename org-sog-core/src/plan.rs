@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+/// A billing tier. Accounts that have never subscribed are on `Free`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Plan {
+    #[default]
+    Free,
+    Pro,
+    Team,
+}
+
+impl Plan {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Plan::Free => "free",
+            Plan::Pro => "pro",
+            Plan::Team => "team",
+        }
+    }
+}
+
+/// What one plan allows; `None` is unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlanLimits {
+    pub posts_per_month: Option<u64>,
+    pub api_requests_per_minute: Option<u64>,
+    pub webhooks: Option<u64>,
+}
+
+/// The limits of every plan.
+///
+/// Each limit can be overridden with `PLAN_<PLAN>_<LIMIT>`, e.g.
+/// `PLAN_PRO_POSTS_PER_MONTH=500` or `PLAN_TEAM_WEBHOOKS=unlimited`.
+#[derive(Debug, Clone)]
+pub struct Plans {
+    free: PlanLimits,
+    pro: PlanLimits,
+    team: PlanLimits,
+}
+
+impl Plans {
+    pub fn init() -> Self {
+        Self {
+            free: limits_from_env(
+                Plan::Free,
+                PlanLimits {
+                    posts_per_month: Some(20),
+                    api_requests_per_minute: Some(60),
+                    webhooks: Some(0),
+                },
+            ),
+            pro: limits_from_env(
+                Plan::Pro,
+                PlanLimits {
+                    posts_per_month: Some(200),
+                    api_requests_per_minute: Some(600),
+                    webhooks: Some(5),
+                },
+            ),
+            team: limits_from_env(
+                Plan::Team,
+                PlanLimits {
+                    posts_per_month: None,
+                    api_requests_per_minute: Some(3000),
+                    webhooks: Some(25),
+                },
+            ),
+        }
+    }
+
+    pub fn limits(&self, plan: Plan) -> PlanLimits {
+        match plan {
+            Plan::Free => self.free,
+            Plan::Pro => self.pro,
+            Plan::Team => self.team,
+        }
+    }
+}
+
+fn limits_from_env(plan: Plan, defaults: PlanLimits) -> PlanLimits {
+    let limit = |name: &str, default: Option<u64>| {
+        let var = format!("PLAN_{}_{}", plan.as_str().to_uppercase(), name);
+        match std::env::var(&var) {
+            Ok(value) if value == "unlimited" => None,
+            Ok(value) => Some(value.parse().unwrap_or_else(|_| {
+                panic!(
+                    "{} must be a whole number or 'unlimited', got '{}'",
+                    var, value
+                )
+            })),
+            Err(_) => default,
+        }
+    };
+
+    PlanLimits {
+        posts_per_month: limit("POSTS_PER_MONTH", defaults.posts_per_month),
+        api_requests_per_minute: limit("API_REQUESTS_PER_MINUTE", defaults.api_requests_per_minute),
+        webhooks: limit("WEBHOOKS", defaults.webhooks),
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use mongodb::options::{ReadPreference, ReadPreferenceOptions, SelectionCriteria};
+
+/// Where a single read should be served from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadFrom {
+    /// Whatever `MONGO_READ_PREFERENCE` allows, secondaries included.
+    Replica,
+    /// Always the primary, for reads that must observe a write made just before.
+    Primary,
+}
+
+/// Read preference applied to list and get operations.
+///
+/// Writes always go to the primary; reads default to `MONGO_READ_PREFERENCE`
+/// (`primary`, `primaryPreferred`, `secondary`, `secondaryPreferred` or `nearest`)
+/// and can be pinned back to the primary per call with [`ReadFrom::Primary`].
+/// `MONGO_MAX_STALENESS_SECS` bounds how far behind a secondary may lag; MongoDB
+/// rejects values below 90.
+#[derive(Clone, Debug)]
+pub struct ReadRouting {
+    replica: ReadPreference,
+}
+
+impl ReadRouting {
+    pub fn init() -> Self {
+        let mode = std::env::var("MONGO_READ_PREFERENCE").unwrap_or_else(|_| "primary".to_string());
+        let max_staleness = std::env::var("MONGO_MAX_STALENESS_SECS").ok().map(|value| {
+            let secs = value
+                .parse::<u64>()
+                .expect("MONGO_MAX_STALENESS_SECS must be a number of seconds.");
+            Duration::from_secs(secs)
+        });
+        let options = ReadPreferenceOptions::builder()
+            .max_staleness(max_staleness)
+            .build();
+
+        let replica = match mode.as_str() {
+            "primary" => ReadPreference::Primary,
+            "primaryPreferred" => ReadPreference::PrimaryPreferred { options },
+            "secondary" => ReadPreference::Secondary { options },
+            "secondaryPreferred" => ReadPreference::SecondaryPreferred { options },
+            "nearest" => ReadPreference::Nearest { options },
+            other => panic!("MONGO_READ_PREFERENCE has an unknown mode: {}", other),
+        };
+
+        Self { replica }
+    }
+
+    pub fn criteria(&self, read: ReadFrom) -> SelectionCriteria {
+        match read {
+            ReadFrom::Replica => SelectionCriteria::ReadPreference(self.replica.clone()),
+            ReadFrom::Primary => SelectionCriteria::ReadPreference(ReadPreference::Primary),
+        }
+    }
+}
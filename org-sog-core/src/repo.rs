@@ -0,0 +1,169 @@
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::error::Result;
+use mongodb::options::{
+    FindOneAndUpdateOptions, FindOneOptions, FindOptions, IndexOptions, ReturnDocument,
+};
+use mongodb::{Collection, IndexModel};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::ids::{Id, IdStrategy};
+use crate::read::{ReadFrom, ReadRouting};
+
+/// A unique index of a model, so a value for it names at most one
+/// document. Each key is its own type with the value it is looked up by, so
+/// lookups only take keys the model has, and values of the right shape.
+pub trait UniqueKey {
+    type Model: Keyed;
+
+    /// What names a document by this key; a tuple for composite keys.
+    type Value<'a>;
+
+    /// The fields the index covers, in order.
+    const FIELDS: &'static [&'static str];
+
+    /// The filter matching the document `value` names.
+    fn filter(value: Self::Value<'_>) -> Document;
+}
+
+/// A model that can be looked up by natural keys as well as by id.
+pub trait Keyed {
+    /// The [`UniqueKey::FIELDS`] of each of the model's keys, for creating
+    /// their indexes.
+    const UNIQUE_KEYS: &'static [&'static [&'static str]];
+}
+
+/// Typed CRUD over one collection.
+///
+/// Covers the plain single-document operations every resource needs, with reads
+/// routed through [`ReadRouting`] and ids made and read by the service's
+/// [`IdStrategy`]. Anything more involved (aggregations, transactions) goes
+/// through [`Repository::collection`] directly.
+#[derive(Clone, Debug)]
+pub struct Repository<T> {
+    collection: Collection<T>,
+    reads: ReadRouting,
+    ids: IdStrategy,
+}
+
+impl<T> Repository<T>
+where
+    T: Serialize + DeserializeOwned + Unpin + Send + Sync,
+{
+    pub fn new(collection: Collection<T>, reads: ReadRouting, ids: IdStrategy) -> Self {
+        Self {
+            collection,
+            reads,
+            ids,
+        }
+    }
+
+    pub fn collection(&self) -> &Collection<T> {
+        &self.collection
+    }
+
+    /// The `_id` for a document about to be inserted.
+    pub fn new_id(&self) -> Id {
+        self.ids.generate()
+    }
+
+    /// The document `id` names. Strings that can't be an id find nothing.
+    pub async fn find_by_id(&self, id: &str, read: ReadFrom) -> Result<Option<T>> {
+        match self.ids.parse(id) {
+            Some(id) => self.find_one(doc! {"_id": id}, read).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn find_one(&self, filter: Document, read: ReadFrom) -> Result<Option<T>> {
+        let options = FindOneOptions::builder()
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        self.collection.find_one(filter, options).await
+    }
+
+    /// Every match, in `sort` order when given.
+    pub async fn find(
+        &self,
+        filter: Document,
+        sort: Option<Document>,
+        read: ReadFrom,
+    ) -> Result<Vec<T>> {
+        let options = FindOptions::builder()
+            .sort(sort)
+            .selection_criteria(self.reads.criteria(read))
+            .build();
+        let mut cursor = self.collection.find(filter, options).await?;
+
+        let mut found = Vec::new();
+        while cursor.advance().await? {
+            found.push(cursor.deserialize_current()?);
+        }
+        Ok(found)
+    }
+
+    pub async fn insert(&self, document: &T) -> Result<()> {
+        self.collection.insert_one(document, None).await?;
+        Ok(())
+    }
+
+    /// Applies `update` to the first match and returns it as it stands afterwards.
+    pub async fn update_one(&self, filter: Document, update: Document) -> Result<Option<T>> {
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        self.collection
+            .find_one_and_update(filter, update, options)
+            .await
+    }
+
+    /// Whether a document matched and was removed.
+    pub async fn delete_one(&self, filter: Document) -> Result<bool> {
+        let result = self.collection.delete_one(filter, None).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
+
+impl<T> Repository<T>
+where
+    T: Keyed + Serialize + DeserializeOwned + Unpin + Send + Sync,
+{
+    /// Creates the unique index behind each of the model's keys.
+    pub async fn ensure_unique_keys(&self) -> Result<()> {
+        for fields in T::UNIQUE_KEYS {
+            let keys: Document = fields
+                .iter()
+                .map(|field| (field.to_string(), Bson::Int32(1)))
+                .collect();
+            let index = IndexModel::builder()
+                .keys(keys)
+                .options(IndexOptions::builder().unique(true).build())
+                .build();
+            self.collection.create_index(index, None).await?;
+        }
+        Ok(())
+    }
+
+    /// The document `value` names under the key `K`, e.g.
+    /// `pages.find_by::<PageSlug>("about", read)`.
+    pub async fn find_by<K: UniqueKey<Model = T>>(
+        &self,
+        value: K::Value<'_>,
+        read: ReadFrom,
+    ) -> Result<Option<T>> {
+        self.find_one(K::filter(value), read).await
+    }
+
+    /// As [`Repository::update_one`], for the document `value` names.
+    pub async fn update_by<K: UniqueKey<Model = T>>(
+        &self,
+        value: K::Value<'_>,
+        update: Document,
+    ) -> Result<Option<T>> {
+        self.update_one(K::filter(value), update).await
+    }
+
+    /// As [`Repository::delete_one`], for the document `value` names.
+    pub async fn delete_by<K: UniqueKey<Model = T>>(&self, value: K::Value<'_>) -> Result<bool> {
+        self.delete_one(K::filter(value)).await
+    }
+}
@@ -0,0 +1,358 @@
+use std::{sync::OnceLock, time::Duration};
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use mongodb::error::Result;
+use mongodb::options::{FindOptions, IndexOptions};
+use mongodb::{Collection, Database, IndexModel};
+use serde::{Deserialize, Serialize};
+
+use crate::page::Pagination;
+
+pub const COLLECTION: &str = "slow_queries";
+/// Slow queries are kept this long.
+const RETENTION_DAYS: u64 = 14;
+/// Commands `explain` accepts, and so the only ones logged.
+const EXPLAINABLE: [&str; 7] = [
+    "find",
+    "aggregate",
+    "count",
+    "distinct",
+    "findAndModify",
+    "update",
+    "delete",
+];
+/// Fields the driver adds to a command that `explain` rejects.
+const SESSION_FIELDS: [&str; 6] = [
+    "lsid",
+    "txnNumber",
+    "autocommit",
+    "startTransaction",
+    "readConcern",
+    "writeConcern",
+];
+
+/// A query that took longer than the slow query threshold.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SlowQuery {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    pub operation: String,
+    pub collection: String,
+    pub durationMs: i64,
+    /// The filter with every value replaced by `1`, so queries that differ
+    /// only in their values share a shape.
+    pub shape: Document,
+    /// The sort as sent; its directions say nothing about the values queried.
+    pub sort: Option<Document>,
+    /// How the server ran it, when the query was sampled for `explain`.
+    pub plan: Option<PlanSummary>,
+    pub traceId: Option<String>,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub at: DateTime<Utc>,
+}
+
+/// The gist of an `explain` with execution stats.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PlanSummary {
+    /// Stages of the winning plan, outermost first, e.g. `FETCH`, `IXSCAN`.
+    pub stages: Vec<String>,
+    /// Indexes the winning plan scans; empty for a collection scan.
+    pub indexes: Vec<String>,
+    pub returned: Option<i64>,
+    pub keysExamined: Option<i64>,
+    pub docsExamined: Option<i64>,
+}
+
+/// A command seen starting that may turn out to be slow.
+#[derive(Debug)]
+pub(crate) struct Candidate {
+    pub operation: String,
+    pub collection: String,
+    pub command: Document,
+    pub trace_id: Option<String>,
+}
+
+/// Logs queries slower than `SLOW_QUERY_MS` (default 100, 0 turns the log
+/// off) to the `slow_queries` collection, where they are kept for two weeks.
+///
+/// A share of them, `SLOW_QUERY_EXPLAIN_RATE` (default 0.1), is run again
+/// through `explain` to record how the server planned it. Fed by
+/// [`DbMetrics`](crate::db_metrics::DbMetrics); nothing is written until
+/// [`SlowQueryLog::attach`] is given the database.
+#[derive(Debug)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+    explain_rate: f64,
+    target: OnceLock<(Database, Collection<SlowQuery>)>,
+}
+
+impl SlowQueryLog {
+    pub fn init() -> Self {
+        let threshold_ms = match std::env::var("SLOW_QUERY_MS") {
+            Ok(value) => value.parse::<u64>().unwrap_or_else(|_| {
+                panic!("SLOW_QUERY_MS must be a whole number, got '{}'", value)
+            }),
+            Err(_) => 100,
+        };
+        let explain_rate = match std::env::var("SLOW_QUERY_EXPLAIN_RATE") {
+            Ok(value) => value
+                .parse::<f64>()
+                .ok()
+                .filter(|rate| (0.0..=1.0).contains(rate))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "SLOW_QUERY_EXPLAIN_RATE must be between 0 and 1, got '{}'",
+                        value
+                    )
+                }),
+            Err(_) => 0.1,
+        };
+
+        Self {
+            threshold: Duration::from_millis(threshold_ms),
+            explain_rate,
+            target: OnceLock::new(),
+        }
+    }
+
+    /// Starts logging to `database`.
+    pub async fn attach(&self, database: &Database) -> Result<()> {
+        let collection = database.collection::<SlowQuery>(COLLECTION);
+
+        let index = IndexModel::builder()
+            .keys(doc! {"collection": 1, "at": -1})
+            .build();
+        collection.create_index(index, None).await?;
+
+        let options = IndexOptions::builder()
+            .expire_after(Duration::from_secs(RETENTION_DAYS * 24 * 60 * 60))
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"at": 1})
+            .options(options)
+            .build();
+        collection.create_index(index, None).await?;
+
+        let _ = self.target.set((database.clone(), collection));
+        Ok(())
+    }
+
+    /// Whether a command is worth keeping until it's known how long it took.
+    pub(crate) fn wants(&self, operation: &str, collection: &str) -> bool {
+        !self.threshold.is_zero() && EXPLAINABLE.contains(&operation) && collection != COLLECTION
+    }
+
+    /// Logs `candidate` if it took `duration` or longer than the threshold.
+    pub(crate) fn observe(&self, candidate: Candidate, duration: Duration) {
+        if duration < self.threshold {
+            return;
+        }
+        let Some((database, collection)) = self.target.get().cloned() else {
+            return;
+        };
+        // Called from the driver, which always runs on a runtime; checked
+        // anyway, as a missing log entry beats a panic.
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let explain = rand::random::<f64>() < self.explain_rate;
+
+        runtime.spawn(async move {
+            let plan = match explain {
+                true => match explain_command(&database, &candidate.command).await {
+                    Ok(plan) => Some(plan),
+                    Err(e) => {
+                        println!(
+                            "⚠️ failed to explain slow {} on {}: {}",
+                            candidate.operation, candidate.collection, e
+                        );
+                        None
+                    }
+                },
+                false => None,
+            };
+
+            let (filter, sort) = filter_and_sort(&candidate.operation, &candidate.command);
+            let entry = SlowQuery {
+                id: ObjectId::new(),
+                operation: candidate.operation,
+                collection: candidate.collection,
+                durationMs: duration.as_millis() as i64,
+                shape: filter.map(shape_of).unwrap_or_default(),
+                sort: sort.cloned(),
+                plan,
+                traceId: candidate.trace_id,
+                at: Utc::now(),
+            };
+            if let Err(e) = collection.insert_one(&entry, None).await {
+                println!("⚠️ failed to log slow query: {}", e);
+            }
+        });
+    }
+
+    /// The latest slow queries, optionally only those on `collection`.
+    pub async fn recent(
+        &self,
+        collection: Option<&str>,
+        paging: Pagination,
+    ) -> Result<Vec<SlowQuery>> {
+        let Some((_, slow_queries)) = self.target.get() else {
+            return Ok(Vec::new());
+        };
+
+        let filter = match collection {
+            Some(collection) => doc! {"collection": collection},
+            None => doc! {},
+        };
+        let options = FindOptions::builder()
+            .sort(doc! {"at": -1})
+            .skip(paging.skip())
+            .limit(paging.limit)
+            .build();
+        let mut cursor = slow_queries.find(filter, options).await?;
+
+        let mut found = Vec::new();
+        while cursor.advance().await? {
+            found.push(cursor.deserialize_current()?);
+        }
+        Ok(found)
+    }
+}
+
+async fn explain_command(database: &Database, command: &Document) -> Result<PlanSummary> {
+    let mut command = command.clone();
+    let driver_fields: Vec<String> = command
+        .keys()
+        .filter(|key| key.starts_with('$') || SESSION_FIELDS.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    for field in driver_fields {
+        command.remove(&field);
+    }
+
+    let explained = database
+        .run_command(
+            doc! {"explain": command, "verbosity": "executionStats"},
+            None,
+        )
+        .await?;
+    Ok(summarize(&explained))
+}
+
+/// The filter and sort a command runs with, where it has them.
+fn filter_and_sort<'a>(
+    operation: &str,
+    command: &'a Document,
+) -> (Option<&'a Document>, Option<&'a Document>) {
+    let first_statement = |field: &str, key: &str| {
+        command
+            .get_array(field)
+            .ok()
+            .and_then(|statements| statements.first())
+            .and_then(Bson::as_document)
+            .and_then(|statement| statement.get_document(key).ok())
+    };
+    let first_stage = |name: &str| {
+        command
+            .get_array("pipeline")
+            .ok()?
+            .iter()
+            .filter_map(Bson::as_document)
+            .find_map(|stage| stage.get_document(name).ok())
+    };
+
+    match operation {
+        "find" => (
+            command.get_document("filter").ok(),
+            command.get_document("sort").ok(),
+        ),
+        "aggregate" => (first_stage("$match"), first_stage("$sort")),
+        "findAndModify" => (
+            command.get_document("query").ok(),
+            command.get_document("sort").ok(),
+        ),
+        "count" | "distinct" => (command.get_document("query").ok(), None),
+        "update" => (first_statement("updates", "q"), None),
+        "delete" => (first_statement("deletes", "q"), None),
+        _ => (None, None),
+    }
+}
+
+/// `document` with every value replaced by `1`, keeping field names,
+/// operators and the nesting of `$and`/`$or` clauses.
+fn shape_of(document: &Document) -> Document {
+    document
+        .iter()
+        .map(|(key, value)| (key.clone(), shape_value(value)))
+        .collect()
+}
+
+fn shape_value(value: &Bson) -> Bson {
+    match value {
+        Bson::Document(document) => Bson::Document(shape_of(document)),
+        Bson::Array(items) if items.iter().any(|item| item.as_document().is_some()) => {
+            Bson::Array(items.iter().map(shape_value).collect())
+        }
+        _ => Bson::Int32(1),
+    }
+}
+
+fn summarize(explained: &Document) -> PlanSummary {
+    // Aggregations that start with a query wrap its plan in their first stage.
+    let cursor_stage = explained
+        .get_array("stages")
+        .ok()
+        .and_then(|stages| stages.first())
+        .and_then(Bson::as_document)
+        .and_then(|stage| stage.get_document("$cursor").ok());
+    let section = |name: &str| {
+        explained
+            .get_document(name)
+            .ok()
+            .or_else(|| cursor_stage.and_then(|stage| stage.get_document(name).ok()))
+    };
+
+    let mut summary = PlanSummary::default();
+    let winning =
+        section("queryPlanner").and_then(|planner| planner.get_document("winningPlan").ok());
+    if let Some(winning) = winning {
+        // Newer servers nest the classic plan under `queryPlan`.
+        let plan = winning.get_document("queryPlan").unwrap_or(winning);
+        walk_plan(plan, &mut summary);
+    }
+    if let Some(stats) = section("executionStats") {
+        summary.returned = number(stats, "nReturned");
+        summary.keysExamined = number(stats, "totalKeysExamined");
+        summary.docsExamined = number(stats, "totalDocsExamined");
+    }
+    summary
+}
+
+fn walk_plan(stage: &Document, summary: &mut PlanSummary) {
+    if let Ok(name) = stage.get_str("stage") {
+        summary.stages.push(name.to_owned());
+    }
+    if let Ok(index) = stage.get_str("indexName") {
+        summary.indexes.push(index.to_owned());
+    }
+    if let Ok(input) = stage.get_document("inputStage") {
+        walk_plan(input, summary);
+    }
+    if let Ok(inputs) = stage.get_array("inputStages") {
+        for input in inputs.iter().filter_map(Bson::as_document) {
+            walk_plan(input, summary);
+        }
+    }
+}
+
+pub(crate) fn number(document: &Document, key: &str) -> Option<i64> {
+    match document.get(key)? {
+        Bson::Int32(n) => Some(*n as i64),
+        Bson::Int64(n) => Some(*n),
+        Bson::Double(n) => Some(*n as i64),
+        _ => None,
+    }
+}
@@ -0,0 +1,135 @@
+use std::{fmt, io, path::PathBuf, time::SystemTime};
+
+#[derive(Debug)]
+pub enum StoreError {
+    NotFound(String),
+    InvalidKey(String),
+    Io(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NotFound(key) => write!(f, "object {} not found", key),
+            StoreError::InvalidKey(key) => write!(f, "invalid object key: {}", key),
+            StoreError::Io(e) => write!(f, "storage error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e.to_string())
+    }
+}
+
+type Result<T> = std::result::Result<T, StoreError>;
+
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Flat key/value blob storage rooted at a directory.
+///
+/// Keys are single path segments, so callers can't reach outside the root.
+/// Point `root` at a mounted bucket (s3fs, gcsfuse, an NFS share) to keep
+/// objects off the host.
+#[derive(Debug, Clone)]
+pub struct ObjectStore {
+    root: PathBuf,
+}
+
+impl ObjectStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub async fn put(&self, key: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.path(key)?;
+        tokio::fs::create_dir_all(&self.root).await?;
+
+        // Write under a temporary name so readers never see half an object.
+        let partial = self.root.join(format!(".{}.partial", key));
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(StoreError::NotFound(key.into())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The object opened for reading, with its size, for callers that pass
+    /// it on a piece at a time rather than holding all of it.
+    pub async fn open(&self, key: &str) -> Result<(tokio::fs::File, u64)> {
+        match tokio::fs::File::open(self.path(key)?).await {
+            Ok(file) => {
+                let size = file.metadata().await?.len();
+                Ok((file, size))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(StoreError::NotFound(key.into())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(StoreError::NotFound(key.into())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Objects whose key starts with `prefix`, oldest first.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let mut entries = match tokio::fs::read_dir(&self.root).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut objects = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let Ok(key) = entry.file_name().into_string() else {
+                continue;
+            };
+            if !key.starts_with(prefix) || key.starts_with('.') {
+                continue;
+            }
+
+            let metadata = entry.metadata().await?;
+            if metadata.is_file() {
+                objects.push(ObjectInfo {
+                    key,
+                    size: metadata.len(),
+                    modified: metadata.modified()?,
+                });
+            }
+        }
+
+        objects.sort_by(|a, b| a.modified.cmp(&b.modified).then(a.key.cmp(&b.key)));
+        Ok(objects)
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let valid = !key.is_empty()
+            && !key.starts_with('.')
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if valid {
+            Ok(self.root.join(key))
+        } else {
+            Err(StoreError::InvalidKey(key.to_owned()))
+        }
+    }
+}
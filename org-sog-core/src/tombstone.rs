@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::error::{ErrorKind, Result};
+use mongodb::options::{FindOptions, IndexOptions};
+use mongodb::{ClientSession, Collection, Database, IndexModel};
+use serde::{Deserialize, Serialize};
+
+const COLLECTION: &str = "tombstones";
+const TTL_INDEX: &str = "deletedAt_ttl";
+/// Server error code for an index that exists with other options.
+const INDEX_OPTIONS_CONFLICT: i32 = 85;
+
+/// What is left of a deleted resource: enough for delta sync and event
+/// consumers to learn it is gone.
+#[allow(non_snake_case)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Tombstone {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+    /// What was deleted, e.g. `post` or `user`.
+    pub resourceType: String,
+    pub resourceId: String,
+    #[serde(with = "mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub deletedAt: DateTime<Utc>,
+}
+
+/// The `tombstones` collection of one service's database.
+///
+/// Tombstones expire after `TOMBSTONE_RETENTION_DAYS` (default 90), so a
+/// client that hasn't synced for longer may miss deletions and should sync
+/// from scratch.
+#[derive(Clone, Debug)]
+pub struct Tombstones {
+    collection: Collection<Tombstone>,
+}
+
+impl Tombstones {
+    pub async fn init(database: &Database) -> Result<Self> {
+        let collection = database.collection(COLLECTION);
+
+        let index = IndexModel::builder()
+            .keys(doc! {"resourceType": 1, "deletedAt": 1})
+            .build();
+        collection.create_index(index, None).await?;
+
+        let retention = Duration::from_secs(retention_days() * 24 * 60 * 60);
+        let options = IndexOptions::builder()
+            .name(TTL_INDEX.to_string())
+            .expire_after(retention)
+            .build();
+        let index = IndexModel::builder()
+            .keys(doc! {"deletedAt": 1})
+            .options(options)
+            .build();
+        match collection.create_index(index, None).await {
+            Ok(_) => {}
+            // The retention changed since the index was built; adjust it in place.
+            Err(e) if is_options_conflict(&e) => {
+                database
+                    .run_command(
+                        doc! {
+                            "collMod": COLLECTION,
+                            "index": {
+                                "name": TTL_INDEX,
+                                "expireAfterSeconds": retention.as_secs() as i64,
+                            },
+                        },
+                        None,
+                    )
+                    .await?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(Self { collection })
+    }
+
+    pub async fn record(&self, resource_type: &str, resource_id: &str) -> Result<()> {
+        self.collection
+            .insert_one(tombstone(resource_type, resource_id), None)
+            .await?;
+        Ok(())
+    }
+
+    /// As [`Tombstones::record`], inside the transaction that deletes the resource.
+    pub async fn record_with_session(
+        &self,
+        resource_type: &str,
+        resource_id: &str,
+        session: &mut ClientSession,
+    ) -> Result<()> {
+        self.collection
+            .insert_one_with_session(tombstone(resource_type, resource_id), None, session)
+            .await?;
+        Ok(())
+    }
+
+    /// Up to `limit` deletions of `resource_type` at or after `since`, oldest
+    /// first. Always read from the primary, so a checkpoint taken afterwards
+    /// can't skip a deletion a secondary hasn't seen yet.
+    pub async fn since(
+        &self,
+        resource_type: &str,
+        since: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Tombstone>> {
+        let options = FindOptions::builder()
+            .sort(doc! {"deletedAt": 1})
+            .limit(limit)
+            .build();
+        let mut cursor = self
+            .collection
+            .find(
+                doc! {"resourceType": resource_type, "deletedAt": {"$gte": since}},
+                options,
+            )
+            .await?;
+
+        let mut found = Vec::new();
+        while cursor.advance().await? {
+            found.push(cursor.deserialize_current()?);
+        }
+        Ok(found)
+    }
+}
+
+fn tombstone(resource_type: &str, resource_id: &str) -> Tombstone {
+    Tombstone {
+        id: ObjectId::new(),
+        resourceType: resource_type.to_owned(),
+        resourceId: resource_id.to_owned(),
+        deletedAt: Utc::now(),
+    }
+}
+
+fn retention_days() -> u64 {
+    match std::env::var("TOMBSTONE_RETENTION_DAYS") {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            panic!(
+                "TOMBSTONE_RETENTION_DAYS must be a whole number, got '{}'",
+                value
+            )
+        }),
+        Err(_) => 90,
+    }
+}
+
+fn is_options_conflict(e: &mongodb::error::Error) -> bool {
+    matches!(&*e.kind, ErrorKind::Command(command) if command.code == INDEX_OPTIONS_CONFLICT)
+}
@@ -0,0 +1,51 @@
+use std::future::Future;
+
+use rand::Rng;
+
+tokio::task_local! {
+    static TRACE_ID: String;
+}
+
+/// Runs `work` as part of trace `trace_id`, which outbound requests and
+/// metric exemplars made along the way will carry.
+pub async fn scope<F: Future>(trace_id: String, work: F) -> F::Output {
+    TRACE_ID.scope(trace_id, work).await
+}
+
+/// The trace the current task is part of, if it was started with [`scope`].
+pub fn current() -> Option<String> {
+    TRACE_ID.try_with(|trace_id| trace_id.clone()).ok()
+}
+
+pub fn new_trace_id() -> String {
+    random_hex(16)
+}
+
+/// The trace id in a W3C `traceparent` header, if the header is well formed.
+pub fn parse_traceparent(value: &str) -> Option<&str> {
+    let is_hex = |part: &str, len: usize| {
+        part.len() == len
+            && part
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    };
+
+    let mut parts = value.trim().split('-');
+    let (version, trace_id, parent_id, flags) =
+        (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let valid = is_hex(version, 2)
+        && version != "ff"
+        && is_hex(trace_id, 32)
+        && is_hex(parent_id, 16)
+        && is_hex(flags, 2)
+        && trace_id.bytes().any(|b| b != b'0');
+
+    valid.then_some(trace_id)
+}
+
+pub(crate) fn random_hex(bytes: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..bytes)
+        .map(|_| format!("{:02x}", rng.gen::<u8>()))
+        .collect()
+}
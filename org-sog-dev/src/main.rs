@@ -0,0 +1,165 @@
+//! `sog-dev` runs the whole stack locally: MongoDB in Docker, org-sog-auth
+//! and org-sog-blog built from this checkout, and a few people and posts to
+//! try them with. Ctrl-C stops the services; MongoDB and its data stay up
+//! for the next run, unless it is started with `--reset`.
+//!
+//! `DEV_MONGO_PORT` picks the host port MongoDB is published on (default
+//! 27018, next to the docker-compose one).
+
+mod mongo;
+mod seed;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use tokio::process::{Child, Command};
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// The first build of a service can take a while.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Where everything runs, as the services are configured with it.
+pub struct Services {
+    pub database_url: String,
+    pub auth_url: String,
+    pub blog_url: String,
+    pub auth_database: String,
+    pub users_collection: String,
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run().await {
+        eprintln!("🔥 {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
+    let reset = std::env::args().skip(1).any(|arg| arg == "--reset");
+    let port = match std::env::var("DEV_MONGO_PORT") {
+        Ok(value) => value
+            .parse::<u16>()
+            .map_err(|_| format!("DEV_MONGO_PORT must be a port, got '{}'", value))?,
+        Err(_) => 27018,
+    };
+
+    let services = Services {
+        database_url: mongo::start(port, reset).await?,
+        auth_url: "http://localhost:8000".to_string(),
+        blog_url: "http://localhost:8001".to_string(),
+        auth_database: "sog_auth".to_string(),
+        users_collection: "users".to_string(),
+    };
+    // Tokens are hashed here the way org-sog-auth hashes them.
+    let pepper = std::env::var("CREDENTIAL_PEPPER").unwrap_or_default();
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("org-sog-dev sits in the repository root")
+        .to_path_buf();
+    let mut auth = spawn(
+        &root,
+        "org-sog-auth",
+        &[
+            ("DATABASE_URL", services.database_url.as_str()),
+            ("MONGO_INITDB_DATABASE", services.auth_database.as_str()),
+            (
+                "MONGODB_NOTE_COLLECTION",
+                services.users_collection.as_str(),
+            ),
+            ("CREDENTIAL_PEPPER", pepper.as_str()),
+            ("PUBLIC_URL", services.auth_url.as_str()),
+            ("BLOG_URL", services.blog_url.as_str()),
+            ("MAGIC_LINK_URL", "http://localhost:8000/api/auth/magic"),
+            ("REGISTRATION_MODE", "open"),
+            ("BOT_CHECKS_SIGNUP", "none"),
+        ],
+    )?;
+    let (client_id, client_secret) = seed::blog_client(&services).await?;
+    let mut blog = spawn(
+        &root,
+        "org-sog-blog",
+        &[
+            ("DATABASE_URL", services.database_url.as_str()),
+            ("MONGO_INITDB_DATABASE", "sog_blog"),
+            ("MONGODB_NOTE_COLLECTION", "posts"),
+            ("CREDENTIAL_PEPPER", pepper.as_str()),
+            ("PUBLIC_URL", services.blog_url.as_str()),
+            ("AUTH_URL", services.auth_url.as_str()),
+            ("AUTH_CLIENT_ID", client_id.as_str()),
+            ("AUTH_CLIENT_SECRET", client_secret.as_str()),
+        ],
+    )?;
+
+    wait_ready("org-sog-auth", &services.auth_url, &mut auth).await?;
+    wait_ready("org-sog-blog", &services.blog_url, &mut blog).await?;
+
+    println!("🌱 Seeding");
+    let signed = seed::run(&services).await?;
+
+    println!();
+    println!("✅ Ready");
+    println!("   auth     {}", services.auth_url);
+    println!("   blog     {}", services.blog_url);
+    println!("   posts    {}/api/blog", services.blog_url);
+    println!("   mongodb  {}", services.database_url);
+    println!();
+    for person in &signed {
+        println!("🔑 {} ({})", person.name, person.scopes.join(" "));
+        println!("   Authorization: Bearer {}", person.token);
+    }
+    println!();
+    println!("Ctrl-C to stop.");
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        status = auth.wait() => return Err(exited("org-sog-auth", status?)),
+        status = blog.wait() => return Err(exited("org-sog-blog", status?)),
+    }
+    println!("👋 Stopped the services; `sog-dev --reset` starts from an empty database");
+    Ok(())
+}
+
+/// Builds and runs a service from the checkout, in its own directory so it
+/// finds its `.env`. Variables set here win over that file.
+fn spawn(root: &Path, service: &str, env: &[(&str, &str)]) -> Result<Child> {
+    let dir: PathBuf = root.join(service);
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let child = Command::new(cargo)
+        .args(["run", "--quiet", "--manifest-path"])
+        .arg(dir.join("Cargo.toml"))
+        .current_dir(&dir)
+        .envs(env.iter().copied())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("could not start {}: {}", service, e))?;
+    println!("🚀 Starting {}", service);
+    Ok(child)
+}
+
+/// Polls the service's health check until it answers, giving up early if
+/// the service exits.
+async fn wait_ready(service: &str, url: &str, child: &mut Child) -> Result<()> {
+    let http = reqwest::Client::new();
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+
+    while tokio::time::Instant::now() < deadline {
+        if let Some(status) = child.try_wait()? {
+            return Err(exited(service, status));
+        }
+        let health = http.get(format!("{}/api/healthcheck", url)).send().await;
+        if matches!(health, Ok(response) if response.status().is_success()) {
+            println!("✅ {} is up at {}", service, url);
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+    Err(format!("{} did not come up within {:?}", service, STARTUP_TIMEOUT).into())
+}
+
+fn exited(service: &str, status: ExitStatus) -> Box<dyn std::error::Error + Send + Sync> {
+    format!("{} exited with {}", service, status).into()
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bollard::container::{
+    Config, CreateContainerOptions, InspectContainerOptions, RemoveContainerOptions,
+    StartContainerOptions,
+};
+use bollard::errors::Error as DockerError;
+use bollard::image::CreateImageOptions;
+use bollard::models::{HostConfig, PortBinding};
+use bollard::Docker;
+use futures::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::error::{Error as MongoError, ErrorKind};
+use mongodb::Client;
+
+use crate::Result;
+
+const CONTAINER: &str = "sog-dev-mongo";
+const IMAGE: &str = "mongo:7";
+/// Posts are written in transactions, which need a replica set, even of one.
+const REPLICA_SET: &str = "rs0";
+const ALREADY_INITIALIZED: i32 = 23;
+
+/// Starts the development MongoDB, creating its container the first time,
+/// and returns the URI the services reach it at. Data is kept between runs
+/// unless `reset` is set.
+pub async fn start(port: u16, reset: bool) -> Result<String> {
+    let docker = Docker::connect_with_local_defaults()?;
+
+    if reset {
+        let options = RemoveContainerOptions {
+            force: true,
+            v: true,
+            ..Default::default()
+        };
+        match docker.remove_container(CONTAINER, Some(options)).await {
+            Ok(()) => println!("🧹 Removed {}", CONTAINER),
+            Err(DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            }) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    match docker
+        .inspect_container(CONTAINER, None::<InspectContainerOptions>)
+        .await
+    {
+        Ok(container) => {
+            let running = container.state.and_then(|state| state.running);
+            if running != Some(true) {
+                docker
+                    .start_container(CONTAINER, None::<StartContainerOptions<String>>)
+                    .await?;
+            }
+        }
+        Err(DockerError::DockerResponseServerError {
+            status_code: 404, ..
+        }) => create(&docker, port).await?,
+        Err(e) => return Err(e.into()),
+    }
+
+    let uri = format!("mongodb://localhost:{}/?directConnection=true", port);
+    initiate(&uri).await?;
+    println!("🍃 MongoDB is up at {}", uri);
+    Ok(uri)
+}
+
+async fn create(docker: &Docker, port: u16) -> Result<()> {
+    println!("📥 Pulling {}", IMAGE);
+    docker
+        .create_image(
+            Some(CreateImageOptions {
+                from_image: IMAGE,
+                ..Default::default()
+            }),
+            None,
+            None,
+        )
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let host_config = HostConfig {
+        port_bindings: Some(HashMap::from([(
+            "27017/tcp".to_string(),
+            Some(vec![PortBinding {
+                host_ip: Some("127.0.0.1".to_string()),
+                host_port: Some(port.to_string()),
+            }]),
+        )])),
+        ..Default::default()
+    };
+    let config = Config {
+        image: Some(IMAGE),
+        cmd: Some(vec!["--replSet", REPLICA_SET, "--bind_ip_all"]),
+        exposed_ports: Some(HashMap::from([("27017/tcp", HashMap::new())])),
+        host_config: Some(host_config),
+        ..Default::default()
+    };
+    let options = CreateContainerOptions {
+        name: CONTAINER,
+        platform: None,
+    };
+    docker.create_container(Some(options), config).await?;
+    docker
+        .start_container(CONTAINER, None::<StartContainerOptions<String>>)
+        .await?;
+    println!("🐳 Started {}", CONTAINER);
+    Ok(())
+}
+
+/// Waits for the server, makes it a replica set if it isn't one yet, and
+/// waits again until it accepts writes.
+async fn initiate(uri: &str) -> Result<()> {
+    let client = Client::with_uri_str(uri).await?;
+    let admin = client.database("admin");
+
+    let mut attempts = 0;
+    loop {
+        let initiated = admin
+            .run_command(
+                doc! {"replSetInitiate": {
+                    "_id": REPLICA_SET,
+                    "members": [{"_id": 0, "host": "localhost:27017"}],
+                }},
+                None,
+            )
+            .await;
+        match initiated {
+            Ok(_) => break,
+            Err(e) if already_initialized(&e) => break,
+            Err(e) if attempts >= 30 => return Err(e.into()),
+            Err(_) => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    for _ in 0..30 {
+        let hello = admin.run_command(doc! {"hello": 1}, None).await?;
+        if hello.get_bool("isWritablePrimary").unwrap_or(false) {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    Err("MongoDB did not become primary".into())
+}
+
+fn already_initialized(e: &MongoError) -> bool {
+    matches!(&*e.kind, ErrorKind::Command(failure) if failure.code == ALREADY_INITIALIZED)
+}
@@ -0,0 +1,195 @@
+use chrono::{Duration, Utc};
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::{options::UpdateOptions, Client};
+use org_sog_core::credentials;
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use crate::{Result, Services};
+
+/// Someone to sign in as, with the scopes they are given.
+struct Person {
+    name: &'static str,
+    email: &'static str,
+    /// Scope names as org-sog-auth defines them.
+    scopes: &'static [&'static str],
+}
+
+const PEOPLE: &[Person] = &[
+    Person {
+        name: "ada",
+        email: "ada@example.com",
+        scopes: &["blog:read", "blog:write", "blog:admin", "users:admin"],
+    },
+    Person {
+        name: "grace",
+        email: "grace@example.com",
+        scopes: &["blog:read", "blog:write"],
+    },
+    Person {
+        name: "linus",
+        email: "linus@example.com",
+        scopes: &["blog:read"],
+    },
+];
+
+const POSTS: &[(&str, &str, &[&str])] = &[
+    (
+        "Hello, world",
+        "## Welcome\n\nThe first post on this development blog.\n",
+        &["news"],
+    ),
+    (
+        "Working with Rust and MongoDB",
+        "## Setup\n\nConnect with the driver.\n\n## Queries\n\nFilter, sort and page.\n",
+        &["rust", "mongodb"],
+    ),
+    ("A draft worth finishing", "Notes for later.\n", &["drafts"]),
+];
+
+/// A person's access token, ready to send as a bearer token.
+pub struct Signed {
+    pub name: &'static str,
+    pub scopes: &'static [&'static str],
+    pub token: String,
+}
+
+/// The service account org-sog-blog introspects API keys as.
+const BLOG_CLIENT_ID: &str = "sa_dev_blog";
+
+/// Client credentials for org-sog-blog, written straight to the auth
+/// database so they exist before the blog starts. The secret is new on every
+/// run.
+pub async fn blog_client(services: &Services) -> Result<(String, String)> {
+    let mongo = Client::with_uri_str(&services.database_url).await?;
+    let accounts = mongo
+        .database(&services.auth_database)
+        .collection::<Document>("service_accounts");
+
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect();
+    let now = Utc::now();
+    accounts
+        .update_one(
+            doc! {"clientId": BLOG_CLIENT_ID},
+            doc! {
+                "$set": {"secretHash": credentials::hash(&secret), "secretRotatedAt": now},
+                "$setOnInsert": {
+                    "name": "org-sog-blog",
+                    "scopes": ["auth:introspect"],
+                    "createdAt": now,
+                },
+            },
+            UpdateOptions::builder().upsert(true).build(),
+        )
+        .await?;
+
+    Ok((BLOG_CLIENT_ID.to_string(), secret))
+}
+
+/// Signs everyone up and in, and has the first of them write the posts.
+/// Safe to run again: what already exists is kept.
+pub async fn run(services: &Services) -> Result<Vec<Signed>> {
+    let http = reqwest::Client::new();
+    let mongo = Client::with_uri_str(&services.database_url).await?;
+    let auth_db = mongo.database(&services.auth_database);
+    let users = auth_db.collection::<Document>(&services.users_collection);
+    let links = auth_db.collection::<Document>("magic_links");
+
+    let mut signed = Vec::new();
+    for person in PEOPLE {
+        let response = http
+            .post(format!("{}/api/users/new", services.auth_url))
+            .json(&json!({"name": person.name, "uid": person.name, "email": person.email}))
+            .send()
+            .await?;
+        expect_created(response, person.name).await?;
+
+        let Some(user) = users.find_one(doc! {"name": person.name}, None).await? else {
+            return Err(format!("{} was not saved", person.name).into());
+        };
+        let user_id = user.get_object_id("_id")?;
+        users
+            .update_one(
+                doc! {"_id": user_id},
+                doc! {"$set": {"scopes": person.scopes.to_vec()}},
+                None,
+            )
+            .await?;
+
+        // What a sign-in link would carry, without waiting for the mail.
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let now = Utc::now();
+        links
+            .insert_one(
+                doc! {
+                    "_id": ObjectId::new(),
+                    "userId": user_id,
+                    "tokenHash": credentials::hash(&secret),
+                    "expiresAt": now + Duration::minutes(5),
+                    "usedAt": null,
+                    "createdAt": now,
+                },
+                None,
+            )
+            .await?;
+        let response: Value = http
+            .get(format!("{}/api/auth/magic/{}", services.auth_url, secret))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let Some(token) = response["access_token"].as_str() else {
+            return Err(format!("no token for {}: {}", person.name, response).into());
+        };
+
+        signed.push(Signed {
+            name: person.name,
+            scopes: person.scopes,
+            token: token.to_owned(),
+        });
+    }
+
+    let author = &signed[0];
+    for (i, (title, content, tags)) in POSTS.iter().enumerate() {
+        let response = http
+            .post(format!("{}/api/blog/new", services.blog_url))
+            .bearer_auth(&author.token)
+            .json(&json!({
+                "title": title,
+                "summary": content.lines().last().unwrap_or_default(),
+                "content": content,
+                "tags": tags,
+                "published": i + 1 < POSTS.len(),
+            }))
+            .send()
+            .await?;
+        expect_created(response, title).await?;
+    }
+
+    Ok(signed)
+}
+
+/// Accepts a conflict too, as it means an earlier run created `what`.
+async fn expect_created(response: reqwest::Response, what: &str) -> Result<()> {
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        StatusCode::CONFLICT => Ok(()),
+        status => Err(format!(
+            "could not create {}: {} {}",
+            what,
+            status,
+            response.text().await.unwrap_or_default()
+        )
+        .into()),
+    }
+}